@@ -1,7 +1,4 @@
 use std::fmt;
-use std::fs;
-use std::io;
-use std::path::Path;
 
 use ropey::Rope;
 
@@ -14,6 +11,8 @@ pub struct Buffer {
     #[allow(dead_code)]
     pub id: BufferId,
     pub content: Rope,
+    /// Bumped on every edit, like Vim's `b:changedtick`.
+    pub changedtick: u64,
 }
 
 impl Buffer {
@@ -21,6 +20,7 @@ impl Buffer {
         Self {
             id,
             content: Rope::new(),
+            changedtick: 0,
         }
     }
 
@@ -29,32 +29,21 @@ impl Buffer {
         Self {
             id,
             content: Rope::from_str(text),
+            changedtick: 0,
         }
     }
 
-    /// Load buffer contents from a file. Returns an io::Error if reading fails.
-    pub fn from_file(id: BufferId, path: &Path) -> Result<Self, io::Error> {
-        let text = fs::read_to_string(path)?;
-        Ok(Self {
-            id,
-            content: Rope::from_str(&text),
-        })
-    }
-
-    /// Write buffer contents to a file.
-    pub fn save_to_file(&self, path: &Path) -> Result<(), io::Error> {
-        fs::write(path, self.to_string())
-    }
-
     pub fn insert(&mut self, char_idx: usize, text: &str) {
         if char_idx <= self.content.len_chars() {
             self.content.insert(char_idx, text);
+            self.changedtick += 1;
         }
     }
 
     pub fn delete_range(&mut self, start_idx: usize, end_idx: usize) {
         if start_idx < end_idx && end_idx <= self.content.len_chars() {
             self.content.remove(start_idx..end_idx);
+            self.changedtick += 1;
         }
     }
 
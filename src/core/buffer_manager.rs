@@ -1,11 +1,21 @@
 use std::collections::HashMap;
-use std::io;
+use std::io::{self, BufRead, Read};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::time::SystemTime;
 
+use serde::{Deserialize, Serialize};
+
 use super::buffer::{Buffer, BufferId};
 use super::cursor::Cursor;
+use super::editorconfig::{EditorConfig, EndOfLine, IndentStyle};
+use super::encoding::{self, FileEncoding};
+use super::filetype;
+use super::rule_syntax::RuleHighlighter;
+use super::safe_write::{self, BackupMode};
+use super::settings::Settings;
 use super::syntax::Syntax;
+use super::undofile::content_checksum;
 
 /// Line ending format for a buffer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,7 +25,8 @@ pub enum LineEnding {
 }
 
 impl LineEnding {
-    /// Detect line ending from file content bytes. Scans up to 8KB.
+    /// Detect the dominant line ending from file content. Scans up to 8KB;
+    /// ties (including files without line breaks) count as LF.
     pub fn detect(text: &str) -> Self {
         let mut end = text.len().min(8192);
         // Back up to a valid char boundary (multi-byte chars may straddle 8KB)
@@ -23,7 +34,9 @@ impl LineEnding {
             end -= 1;
         }
         let scan = &text[..end];
-        if scan.contains("\r\n") {
+        let crlf = scan.matches("\r\n").count();
+        let lf = scan.matches('\n').count() - crlf;
+        if crlf > lf {
             LineEnding::Crlf
         } else {
             LineEnding::LF
@@ -36,6 +49,73 @@ impl LineEnding {
             LineEnding::Crlf => "CRLF",
         }
     }
+
+    /// Vim `fileformat` name: `unix` or `dos`.
+    pub fn fileformat_name(self) -> &'static str {
+        match self {
+            LineEnding::LF => "unix",
+            LineEnding::Crlf => "dos",
+        }
+    }
+
+    /// Parse a Vim `fileformat` name.
+    pub fn from_fileformat(name: &str) -> Option<Self> {
+        match name {
+            "unix" => Some(LineEnding::LF),
+            "dos" => Some(LineEnding::Crlf),
+            _ => None,
+        }
+    }
+
+    /// `text` with every line break (`\r\n`, `\n` or a lone `\r`) converted
+    /// to this format.
+    pub fn normalize(self, text: &str) -> String {
+        let unix = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self {
+            LineEnding::LF => unix,
+            LineEnding::Crlf => unix.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Buffer-local overrides of the global settings, from a modeline or
+/// `.editorconfig`.  `None` means "use the global setting".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferOptions {
+    pub tabstop: Option<u8>,
+    /// `Some(0)` means "same as tabstop".
+    pub shift_width: Option<u8>,
+    pub textwidth: Option<usize>,
+    pub expand_tab: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+    /// `Some(true)` adds a missing final newline on save, `Some(false)`
+    /// drops the last line break.
+    pub insert_final_newline: Option<bool>,
+    /// Line breaks written on save.
+    pub end_of_line: Option<EndOfLine>,
+    /// Lowercase charset the file is read and written in.
+    pub charset: Option<String>,
+}
+
+impl BufferOptions {
+    /// The options an `.editorconfig` section sets.
+    pub fn from_editorconfig(ec: &EditorConfig) -> Self {
+        Self {
+            tabstop: ec.tab_width,
+            shift_width: ec.indent_size,
+            textwidth: None,
+            expand_tab: ec.indent_style.map(|s| s == IndentStyle::Space),
+            trim_trailing_whitespace: ec.trim_trailing_whitespace,
+            insert_final_newline: ec.insert_final_newline,
+            end_of_line: ec.end_of_line,
+            charset: ec.charset.clone(),
+        }
+    }
+
+    /// The encoding named by `charset`, if it is one we support.
+    pub fn charset_encoding(&self) -> Option<FileEncoding> {
+        self.charset.as_deref().and_then(FileEncoding::from_name)
+    }
 }
 
 // =============================================================================
@@ -43,7 +123,7 @@ impl LineEnding {
 // =============================================================================
 
 /// A single text edit operation (insert or delete).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum EditOp {
     /// Text was inserted at position `pos`.
     Insert { pos: usize, text: String },
@@ -53,12 +133,14 @@ pub enum EditOp {
 
 /// A group of edits that form one undoable action.
 /// In Vim, this corresponds to a single Normal mode command or an entire Insert mode session.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UndoEntry {
     /// The operations in this undo group (in order of execution).
     pub ops: Vec<EditOp>,
     /// Cursor position before the operations (restored on undo).
     pub cursor_before: Cursor,
+    /// When the group was finished (used by `:earlier {N}s` / `:later {N}s`).
+    pub time: SystemTime,
 }
 
 impl UndoEntry {
@@ -66,6 +148,7 @@ impl UndoEntry {
         Self {
             ops: Vec::new(),
             cursor_before: cursor,
+            time: SystemTime::now(),
         }
     }
 
@@ -98,6 +181,8 @@ pub struct BufferState {
     pub source_file: Option<PathBuf>,
     /// Syntax highlighter for this buffer (`None` for plain text / unrecognised extensions).
     pub syntax: Option<Syntax>,
+    /// Rule-based highlighter for file types without a tree-sitter grammar.
+    pub rule_syntax: Option<RuleHighlighter>,
     /// Cached syntax highlights (byte ranges + scope names).
     pub highlights: Vec<(usize, usize, String)>,
     /// Whether highlights are stale (tree was re-parsed but highlights not yet re-extracted).
@@ -127,12 +212,20 @@ pub struct BufferState {
     /// Cached maximum line length (in chars) across the whole buffer.
     /// Recomputed in `update_syntax` so renders don't need to scan every line.
     pub max_col: usize,
-    /// Whether this buffer is read-only (e.g. markdown preview).
+    /// Whether this buffer is read-only (e.g. markdown preview, or a file
+    /// without write permission); `:set noreadonly` or `:w!` clears it.
     pub read_only: bool,
+    /// False when no changes may be made at all (`:set nomodifiable`).
+    pub modifiable: bool,
     /// Pre-rendered markdown content (set for markdown preview buffers).
     pub md_rendered: Option<crate::core::markdown::MdRendered>,
     /// LSP semantic tokens (decoded, absolute positions). Overlays tree-sitter highlights.
     pub semantic_tokens: Vec<crate::core::lsp::SemanticToken>,
+    /// LSP document symbol tree, kept for the winbar (`:set winbar`) and
+    /// sticky scroll (`:set stickyscroll`).
+    pub document_symbols: Vec<crate::core::lsp::SymbolInfo>,
+    /// LSP inlay hints for the most recently requested line range.
+    pub inlay_hints: Vec<crate::core::lsp::InlayHint>,
     /// For netrw buffers: the directory currently being listed.
     pub netrw_dir: Option<PathBuf>,
     /// Whether this buffer is a keymaps editor scratch buffer.
@@ -150,6 +243,10 @@ pub struct BufferState {
     /// Last-known modification time of the file on disk.
     /// Set on file open and save; used by `check_file_changes()` to detect external edits.
     pub file_mtime: Option<SystemTime>,
+    /// Checksum of the text last read from or written to the file — the
+    /// base `saved_undo_depth` refers to.  `:e!` keeps the undo history
+    /// when the file still matches it.  `None` in large-file mode.
+    pub disk_checksum: Option<u64>,
     /// Whether a "file changed on disk" warning has already been shown for the
     /// current external modification.  Reset when the mtime is updated (reload / save).
     pub file_change_warned: bool,
@@ -159,6 +256,103 @@ pub struct BufferState {
     pub detected_indent: Option<u8>,
     /// Line ending format (LF or CRLF). Detected on file open, default LF.
     pub line_ending: LineEnding,
+    /// Whether the file ended with a line break when it was loaded (Vim's
+    /// `endofline`).  Saving keeps it unless a final-newline rule says
+    /// otherwise; new files default to true.
+    pub final_newline: bool,
+    /// `:set commentstring` for this buffer; `None` uses the global setting,
+    /// then the built-in per-language table.
+    pub commentstring: Option<String>,
+    /// Encoding of the file on disk. Detected on file open; the text is
+    /// converted back to it on save.
+    pub encoding: FileEncoding,
+    /// First line (0-based) where bytes invalid in `encoding` were replaced
+    /// with U+FFFD on the last read; writing the buffer loses those bytes.
+    pub illegal_byte_line: Option<usize>,
+    /// Opened in large-file mode (`large_file_mb`): read-only, and no
+    /// highlighting, indent/modeline detection, LSP or git diff.
+    pub large_file: bool,
+    /// Pending background read of a large file; [`poll_load`](Self::poll_load)
+    /// appends each piece as it arrives.  Writes are refused meanwhile.
+    pub loading: Option<Receiver<io::Result<LargeChunk>>>,
+    /// Buffer-local option overrides set by modelines (highest precedence).
+    pub local_options: BufferOptions,
+    /// Options from the `.editorconfig` files that apply to this file; below
+    /// modelines and the project `.vimcode.json`, above the global settings.
+    pub editorconfig: BufferOptions,
+    /// The editor's working directory when the file was opened; a relative
+    /// `file_path` is looked up against it for `.editorconfig` files.
+    pub cwd: PathBuf,
+}
+
+/// Bytes read per piece of a large file; every piece is extended to the
+/// end of its last line.
+const LARGE_FILE_CHUNK: usize = 4 * 1024 * 1024;
+
+/// One piece of a large file read off the UI thread.  Pieces arrive in
+/// order and are appended as they come, so the start of the file can be
+/// viewed while the rest is still being read.
+pub struct LargeChunk {
+    text: String,
+    encoding: FileEncoding,
+    /// Longest line in `text`, in characters.
+    max_col: usize,
+    /// Line within `text` of the first byte the decoder had to replace.
+    illegal_byte_line: Option<usize>,
+}
+
+impl LargeChunk {
+    /// Read `path` in pieces of about `chunk_len` bytes, sending each one to
+    /// `tx`.  The encoding is `forced`, or detected from the first piece.
+    /// Stops early once the receiving buffer has gone away.
+    fn read_all(
+        path: &Path,
+        forced: Option<FileEncoding>,
+        chunk_len: usize,
+        tx: &mpsc::Sender<io::Result<Self>>,
+    ) -> io::Result<()> {
+        let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+        let mut encoding = None;
+        loop {
+            let mut bytes = Vec::with_capacity(chunk_len);
+            io::Read::by_ref(&mut reader)
+                .take(chunk_len as u64)
+                .read_to_end(&mut bytes)?;
+            if bytes.is_empty() {
+                return Ok(());
+            }
+            let encoding = *encoding.get_or_insert_with(|| FileEncoding::for_bytes(&bytes, forced));
+            if matches!(
+                encoding,
+                FileEncoding::Utf16Le { .. } | FileEncoding::Utf16Be { .. }
+            ) {
+                // A UTF-16 line break isn't a single byte: take the rest at once.
+                reader.read_to_end(&mut bytes)?;
+            } else {
+                reader.read_until(b'\n', &mut bytes)?;
+            }
+            let (text, had_errors) = encoding.decode(&bytes);
+            let illegal_byte_line = if had_errors {
+                encoding::first_replaced_line(&text)
+            } else {
+                None
+            };
+            let max_col = text
+                .lines()
+                .map(|l| l.trim_end_matches('\r').chars().count())
+                .max()
+                .unwrap_or(0);
+            let chunk = Self {
+                text,
+                encoding,
+                max_col,
+                illegal_byte_line,
+            };
+            if tx.send(Ok(chunk)).is_err() {
+                return Ok(());
+            }
+        }
+    }
 }
 
 impl std::fmt::Debug for BufferState {
@@ -185,6 +379,7 @@ impl BufferState {
             preview: false,
             source_file: None,
             syntax: None,
+            rule_syntax: None,
             highlights: Vec::new(),
             syntax_stale: false,
             syntax_stale_since: None,
@@ -199,8 +394,11 @@ impl BufferState {
             lsp_language_id: None,
             max_col: 0,
             read_only: false,
+            modifiable: true,
             md_rendered: None,
             semantic_tokens: Vec::new(),
+            document_symbols: Vec::new(),
+            inlay_hints: Vec::new(),
             netrw_dir: None,
             is_keymaps_buf: false,
             is_registries_buf: false,
@@ -209,20 +407,39 @@ impl BufferState {
             scratch_name: None,
             diff_label: None,
             file_mtime: None,
+            disk_checksum: None,
             file_change_warned: false,
             detected_indent: None,
             line_ending: LineEnding::LF,
+            final_newline: true,
+            commentstring: None,
+            encoding: FileEncoding::Utf8,
+            illegal_byte_line: None,
+            large_file: false,
+            loading: None,
+            local_options: BufferOptions::default(),
+            editorconfig: BufferOptions::default(),
+            cwd: PathBuf::new(),
         };
         state.update_syntax();
         state
     }
 
-    pub fn with_file(buffer: Buffer, path: PathBuf) -> Self {
+    /// A buffer for `path`; a relative `path` is resolved against `cwd` to
+    /// find its `.editorconfig` files.
+    pub fn with_file(buffer: Buffer, path: PathBuf, cwd: &Path) -> Self {
         let syntax = Syntax::new_from_path(path.to_str());
+        let rule_syntax = if syntax.is_none() {
+            path.to_str().and_then(RuleHighlighter::for_path)
+        } else {
+            None
+        };
         let lsp_language_id = crate::core::lsp::language_id_from_path(&path);
         let canonical_path = path.canonicalize().ok();
         let file_mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
         let line_ending = LineEnding::detect(&buffer.to_string());
+        let editorconfig = BufferOptions::from_editorconfig(&EditorConfig::for_path(&path, cwd));
+        let encoding = editorconfig.charset_encoding().unwrap_or_default();
 
         let mut state = Self {
             buffer,
@@ -233,6 +450,7 @@ impl BufferState {
             preview: false,
             source_file: None,
             syntax,
+            rule_syntax,
             highlights: Vec::new(),
             syntax_stale: false,
             syntax_stale_since: None,
@@ -247,8 +465,11 @@ impl BufferState {
             lsp_language_id,
             max_col: 0,
             read_only: false,
+            modifiable: true,
             md_rendered: None,
             semantic_tokens: Vec::new(),
+            document_symbols: Vec::new(),
+            inlay_hints: Vec::new(),
             netrw_dir: None,
             is_keymaps_buf: false,
             is_registries_buf: false,
@@ -257,17 +478,31 @@ impl BufferState {
             scratch_name: None,
             diff_label: None,
             file_mtime,
+            disk_checksum: None,
             file_change_warned: false,
             detected_indent: None,
             line_ending,
+            final_newline: true,
+            commentstring: None,
+            encoding,
+            illegal_byte_line: None,
+            large_file: false,
+            loading: None,
+            local_options: BufferOptions::default(),
+            editorconfig,
+            cwd: cwd.to_path_buf(),
         };
         state.detect_indent();
+        state.detect_filetype();
         state.update_syntax();
         state
     }
 
     /// Re-parse the buffer and update syntax highlights and max_col cache.
     pub fn update_syntax(&mut self) {
+        if self.large_file {
+            return;
+        }
         let text = self.buffer.to_string();
         self.highlights = if let Some(ref mut syn) = self.syntax {
             let mut hl = syn.parse(&text);
@@ -275,6 +510,8 @@ impl BufferState {
             // search (partition_point) to narrow highlights to the viewport.
             hl.sort_by_key(|h| h.0);
             hl
+        } else if let Some(ref mut rules) = self.rule_syntax {
+            rules.highlight(&text)
         } else {
             Vec::new()
         };
@@ -324,6 +561,169 @@ impl BufferState {
         self.detected_indent = best;
     }
 
+    /// Look for a filetype and buffer-local options in the file's contents:
+    /// a `#!` line names the language of files the path didn't identify,
+    /// and Vim modelines in the first/last lines override both the language
+    /// and the indent options (see [`filetype`]).  Call `update_syntax`
+    /// afterwards.
+    pub fn detect_filetype(&mut self) {
+        let content = &self.buffer.content;
+        let total = content.len_lines();
+        let line_text = |i: usize| -> String {
+            let line: String = content.line(i).chars().collect();
+            line.trim_end_matches(['\n', '\r']).to_string()
+        };
+        let head = 0..total.min(filetype::MODELINE_LINES);
+        let tail = total.saturating_sub(filetype::MODELINE_LINES).max(head.end)..total;
+        let lines: Vec<String> = head.chain(tail).map(line_text).collect();
+
+        let modeline = filetype::parse_modelines(lines.iter().map(|l| l.as_str()));
+        let detected = modeline.filetype.clone().or_else(|| {
+            if self.lsp_language_id.is_some() || self.syntax.is_some() || self.rule_syntax.is_some()
+            {
+                return None;
+            }
+            lines
+                .first()
+                .and_then(|l| filetype::language_id_from_shebang(l))
+        });
+        if let Some(lang) = detected {
+            self.set_language(&lang);
+        }
+        self.local_options = BufferOptions {
+            tabstop: modeline.tabstop,
+            shift_width: modeline.shift_width,
+            textwidth: modeline.textwidth,
+            expand_tab: modeline.expand_tab,
+            ..BufferOptions::default()
+        };
+    }
+
+    /// Switch the buffer's language: LSP id plus a matching highlighter
+    /// (tree-sitter when available, otherwise a rule set).
+    pub fn set_language(&mut self, lang: &str) {
+        self.lsp_language_id = Some(lang.to_string());
+        self.syntax = Syntax::new_from_language_id(lang);
+        self.rule_syntax = if self.syntax.is_none() {
+            RuleHighlighter::for_language_id(lang)
+        } else {
+            None
+        };
+    }
+
+    /// A buffer-local value: the modeline's, else the `.editorconfig` one
+    /// unless the project `.vimcode.json` sets the settings key `key`.
+    fn local_value<T>(
+        &self,
+        settings: &Settings,
+        key: &str,
+        get: impl Fn(&BufferOptions) -> Option<T>,
+    ) -> Option<T> {
+        get(&self.local_options).or_else(|| {
+            if settings.project_keys.iter().any(|k| k == key) {
+                None
+            } else {
+                get(&self.editorconfig)
+            }
+        })
+    }
+
+    /// Tab width for this buffer: modeline, `.editorconfig`, global `tabstop`.
+    pub fn tabstop(&self, settings: &Settings) -> usize {
+        self.local_value(settings, "tabstop", |o| o.tabstop)
+            .unwrap_or(settings.tabstop)
+            .max(1) as usize
+    }
+
+    /// Indent width for this buffer: a modeline or `.editorconfig` width
+    /// (0 = `tabstop`), then the auto-detected indent, then the global
+    /// `shiftwidth`.
+    pub fn shift_width(&self, settings: &Settings) -> usize {
+        match self.local_value(settings, "shift_width", |o| o.shift_width) {
+            Some(0) => self.tabstop(settings),
+            Some(n) => n as usize,
+            None => self
+                .detected_indent
+                .map(|n| n as usize)
+                .unwrap_or(settings.shift_width as usize),
+        }
+    }
+
+    /// `textwidth` for this buffer (0 = no limit).
+    pub fn textwidth(&self, settings: &Settings) -> usize {
+        self.local_value(settings, "textwidth", |o| o.textwidth)
+            .unwrap_or(settings.textwidth)
+    }
+
+    /// Whether Tab/indent inserts spaces in this buffer.
+    pub fn expand_tab(&self, settings: &Settings) -> bool {
+        self.local_value(settings, "expand_tab", |o| o.expand_tab)
+            .unwrap_or(settings.expand_tab)
+    }
+
+    /// Whether trailing whitespace is stripped when this buffer is saved.
+    pub fn trim_trailing_whitespace(&self, settings: &Settings) -> bool {
+        self.local_value(settings, "strip_whitespace_on_save", |o| {
+            o.trim_trailing_whitespace
+        })
+        .unwrap_or(settings.strip_whitespace_on_save)
+    }
+
+    /// Whether the saved file ends with a line break: the `.editorconfig`
+    /// `insert_final_newline` rule, else on when the global setting is,
+    /// else whatever the file had when it was loaded.
+    pub fn writes_final_newline(&self, settings: &Settings) -> bool {
+        self.local_value(settings, "insert_final_newline", |o| o.insert_final_newline)
+            .unwrap_or(settings.insert_final_newline || self.final_newline)
+    }
+
+    /// The text written to disk: the buffer with the final-newline and
+    /// `.editorconfig` line-ending rules applied.  Without an `end_of_line`
+    /// rule a dos buffer gets CRLF on lines added since it was loaded, so a
+    /// CRLF file stays CRLF.
+    ///
+    /// A trailing line break in the buffer only terminates the last line,
+    /// so `"a"` and `"a\n"` are the same single line and the final-newline
+    /// rule decides which one is written.  Blank lines before it are kept,
+    /// except with `fixendofline`, which leaves exactly one line break.
+    fn text_for_disk(&self, settings: &Settings) -> String {
+        let mut text = self.buffer.to_string();
+        if text.is_empty() {
+            return text;
+        }
+        let break_len = if text.ends_with("\r\n") {
+            2
+        } else if text.ends_with(['\n', '\r']) {
+            1
+        } else {
+            0
+        };
+        let last_break = text.split_off(text.len() - break_len);
+        let final_newline = self.writes_final_newline(settings);
+        if final_newline && settings.insert_final_newline {
+            text.truncate(text.trim_end_matches(['\n', '\r']).len());
+        }
+        if final_newline {
+            text.push_str(if last_break.is_empty() {
+                "\n"
+            } else {
+                &last_break
+            });
+        }
+        let opts = &self.editorconfig;
+        if let Some(eol) = opts.end_of_line {
+            let normalized = text.replace("\r\n", "\n");
+            text = match eol {
+                EndOfLine::Lf => normalized,
+                EndOfLine::Crlf => normalized.replace('\n', "\r\n"),
+                EndOfLine::Cr => normalized.replace('\n', "\r"),
+            };
+        } else if self.line_ending == LineEnding::Crlf {
+            text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+        }
+        text
+    }
+
     /// Mark syntax as needing a re-parse. Does NO work — just records the
     /// timestamp so the idle handler can debounce and re-parse after the user
     /// pauses typing. Call this on every keystroke in insert mode.
@@ -366,33 +766,32 @@ impl BufferState {
                 self.buffer.content.len_bytes()
             };
             self.highlights = syn.extract_highlights_range(&text, start_byte, end_byte);
+        } else if let Some(ref mut rules) = self.rule_syntax {
+            // Unchanged lines come from the per-line cache, so a full pass is cheap.
+            self.highlights = rules.highlight(&text);
         }
         self.max_col = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
     }
 
-    /// Switch line ending format. Converts all line endings in the buffer content.
-    pub fn set_line_ending(&mut self, new: LineEnding) {
-        if self.line_ending == new {
-            return;
-        }
-        let text = self.buffer.to_string();
-        let converted = match new {
-            LineEnding::Crlf => text.replace('\n', "\r\n"),
-            LineEnding::LF => text.replace("\r\n", "\n"),
-        };
-        let char_len = self.buffer.len_chars();
-        self.buffer.delete_range(0, char_len);
-        if !converted.is_empty() {
-            self.buffer.insert(0, &converted);
-        }
-        self.line_ending = new;
-        self.dirty = true;
-    }
-
-    /// Save the buffer to its associated file path.
-    pub fn save(&mut self) -> Result<usize, io::Error> {
+    /// Save the buffer to its associated file path.  The file is replaced
+    /// atomically, with a `file~` backup when `backup`/`writebackup` is set.
+    /// A read-only file is refused; `:w!` makes it writable first.
+    pub fn save(&mut self, settings: &Settings) -> Result<usize, io::Error> {
         if let Some(ref path) = self.file_path {
-            self.buffer.save_to_file(path)?;
+            if safe_write::is_read_only(path) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "file is read-only (add ! to override)",
+                ));
+            }
+            let backup = BackupMode {
+                keep: settings.backup,
+                during_write: settings.writebackup,
+            };
+            let text = self.text_for_disk(settings);
+            let bytes = self.encoding.encode(&text)?;
+            safe_write::write_file(path, &bytes, backup)?;
+            self.disk_checksum = Some(content_checksum(&text));
             self.dirty = false;
             self.saved_undo_depth = Some(self.undo_stack.len());
             self.file_mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
@@ -403,33 +802,217 @@ impl BufferState {
         }
     }
 
+    /// True when the file's modification time on disk differs from the one
+    /// recorded when it was last loaded or saved.
+    pub fn changed_on_disk(&self) -> bool {
+        match (&self.file_path, self.file_mtime) {
+            (Some(path), Some(prev)) => std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .is_ok_and(|t| t != prev),
+            _ => false,
+        }
+    }
+
+    /// Read an existing file into a new buffer, decoding it from the
+    /// `.editorconfig` charset or the detected encoding.  Files of at least
+    /// `large_file_mb` open in large-file mode.
+    pub fn open(
+        id: BufferId,
+        path: PathBuf,
+        cwd: &Path,
+        settings: &Settings,
+    ) -> Result<Self, io::Error> {
+        let threshold = u64::from(settings.large_file_mb) * 1024 * 1024;
+        let large = threshold > 0 && std::fs::metadata(&path)?.len() >= threshold;
+        let writable = safe_write::is_writable(&path);
+        let mut state = Self::with_file(Buffer::new(id), path, cwd);
+        state.read_only = !writable;
+        if large {
+            state.large_file = true;
+            state.read_only = true;
+            state.syntax = None;
+            state.rule_syntax = None;
+            state.lsp_language_id = None;
+        }
+        state.reload_from_disk()?;
+        Ok(state)
+    }
+
     /// Re-read the file from disk, replacing all buffer content.
     /// Resets dirty flag, undo/redo stacks, and updates mtime.
     pub fn reload_from_disk(&mut self) -> Result<(), io::Error> {
-        if let Some(ref path) = self.file_path {
-            let text = std::fs::read_to_string(path)?;
-            self.line_ending = LineEnding::detect(&text);
-            let char_len = self.buffer.len_chars();
-            self.buffer.delete_range(0, char_len);
-            if !text.is_empty() {
-                self.buffer.insert(0, &text);
-            }
-            self.dirty = false;
-            self.saved_undo_depth = Some(0);
-            self.undo_stack.clear();
+        self.reload_with_encoding(None)
+    }
+
+    /// Like [`reload_from_disk`](Self::reload_from_disk), decoding the file
+    /// as `forced` (`:e ++enc=…`) instead of detecting its encoding.
+    pub fn reload_with_encoding(&mut self, forced: Option<FileEncoding>) -> Result<(), io::Error> {
+        if self.large_file && self.file_path.is_some() {
+            return self.reload_large(forced);
+        }
+        let text = self.read_file_text(forced)?;
+        self.load_text(&text);
+        Ok(())
+    }
+
+    /// `:e!`: re-read the file, discarding unsaved changes.  When the file
+    /// still holds the text last read or written — the base the undo
+    /// history leads back to — the discarded changes become one more undo
+    /// step instead of the history being dropped; otherwise this is a plain
+    /// reload.  `cursor` is restored when that step is undone.  Returns
+    /// whether the history was kept.
+    pub fn revert_to_disk(&mut self, cursor: Cursor) -> Result<bool, io::Error> {
+        if self.large_file {
+            return self.reload_from_disk().map(|()| false);
+        }
+        let text = self.read_file_text(None)?;
+        if self.disk_checksum != Some(content_checksum(&text)) {
+            self.load_text(&text);
+            return Ok(false);
+        }
+        self.finish_undo_group();
+        let old = self.buffer.to_string();
+        if old != text {
+            let mut entry = UndoEntry::new(cursor);
+            entry.ops.push(EditOp::Delete { pos: 0, text: old });
+            entry.ops.push(EditOp::Insert {
+                pos: 0,
+                text: text.clone(),
+            });
+            self.undo_stack.push(entry);
             self.redo_stack.clear();
-            self.current_undo_group = None;
-            self.undo_timeline.clear();
-            self.undo_timeline_pos = None;
-            self.file_mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
-            self.file_change_warned = false;
-            self.update_syntax();
-            Ok(())
+            self.replace_text(&text);
+        }
+        self.dirty = false;
+        self.saved_undo_depth = Some(self.undo_stack.len());
+        self.line_undo_state = None;
+        self.file_mtime = self
+            .file_path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+        self.file_change_warned = false;
+        self.update_syntax();
+        Ok(true)
+    }
+
+    /// Read and decode the file, refreshing the `.editorconfig` options,
+    /// encoding and line ending from it.
+    fn read_file_text(&mut self, forced: Option<FileEncoding>) -> Result<String, io::Error> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No file name"));
+        };
+        self.editorconfig =
+            BufferOptions::from_editorconfig(&EditorConfig::for_path(&path, &self.cwd));
+        let forced = forced.or_else(|| self.editorconfig.charset_encoding());
+        let (text, encoding, had_errors) = encoding::read_file(&path, forced)?;
+        self.encoding = encoding;
+        self.illegal_byte_line = if had_errors {
+            encoding::first_replaced_line(&text)
         } else {
-            Err(io::Error::new(io::ErrorKind::NotFound, "No file name"))
+            None
+        };
+        self.line_ending = LineEnding::detect(&text);
+        Ok(text)
+    }
+
+    /// Fill the buffer with freshly read `text`, resetting history and
+    /// re-running the per-file detection.
+    fn load_text(&mut self, text: &str) {
+        self.replace_text(text);
+        self.reset_after_load();
+        self.detect_indent();
+        self.detect_filetype();
+        self.update_syntax();
+    }
+
+    fn replace_text(&mut self, text: &str) {
+        let char_len = self.buffer.len_chars();
+        self.buffer.delete_range(0, char_len);
+        if !text.is_empty() {
+            self.buffer.insert(0, text);
+        }
+    }
+
+    /// Large-file reload: the buffer is emptied and the file is read on a
+    /// background thread in line-aligned pieces, which
+    /// [`poll_load`](Self::poll_load) appends as they arrive.  Opening never
+    /// blocks the UI and the first screenful shows up right away.
+    fn reload_large(&mut self, forced: Option<FileEncoding>) -> Result<(), io::Error> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No file name"));
+        };
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            if let Err(e) = LargeChunk::read_all(&path, forced, LARGE_FILE_CHUNK, &tx) {
+                let _ = tx.send(Err(e));
+            }
+        });
+        self.replace_text("");
+        self.max_col = 0;
+        self.illegal_byte_line = None;
+        self.reset_after_load();
+        self.loading = Some(rx);
+        Ok(())
+    }
+
+    /// Append the pieces of a background load that have arrived.  Returns
+    /// `None` while nothing new came in, otherwise the outcome; `loading` is
+    /// cleared once the whole file has been read or the read failed.
+    pub fn poll_load(&mut self) -> Option<io::Result<()>> {
+        let mut appended = false;
+        loop {
+            let chunk = match self.loading.as_ref()?.try_recv() {
+                Ok(Ok(chunk)) => chunk,
+                Ok(Err(e)) => {
+                    self.loading = None;
+                    return Some(Err(e));
+                }
+                Err(TryRecvError::Empty) => return appended.then_some(Ok(())),
+                Err(TryRecvError::Disconnected) => {
+                    // The reader finished: the file is fully loaded.
+                    self.loading = None;
+                    self.reset_after_load();
+                    return Some(Ok(()));
+                }
+            };
+            let end = self.buffer.len_chars();
+            if self.illegal_byte_line.is_none() {
+                self.illegal_byte_line = chunk
+                    .illegal_byte_line
+                    .map(|line| self.buffer.len_lines() - 1 + line);
+            }
+            if end == 0 {
+                let head: String = chunk.text.chars().take(8192).collect();
+                self.line_ending = LineEnding::detect(&head);
+            }
+            self.buffer.content.insert(end, &chunk.text);
+            self.buffer.changedtick += 1;
+            self.encoding = chunk.encoding;
+            self.max_col = self.max_col.max(chunk.max_col);
+            appended = true;
         }
     }
 
+    /// Clear dirty state, undo history and the external-change marker after
+    /// the buffer has been (re)filled from disk.
+    fn reset_after_load(&mut self) {
+        self.dirty = false;
+        self.saved_undo_depth = Some(0);
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.current_undo_group = None;
+        self.undo_timeline.clear();
+        self.undo_timeline_pos = None;
+        self.file_mtime = self
+            .file_path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+        self.file_change_warned = false;
+        self.disk_checksum = (!self.large_file).then(|| content_checksum(&self.buffer.to_string()));
+        let len = self.buffer.len_chars();
+        self.final_newline = len == 0 || matches!(self.buffer.content.char(len - 1), '\n' | '\r');
+    }
+
     /// Get the display name for this buffer (filename or "[No Name]").
     pub fn display_name(&self) -> String {
         if self.is_keymaps_buf {
@@ -510,8 +1093,9 @@ impl BufferState {
     /// Finish the current undo group and push it to the undo stack.
     /// Call this after a Normal mode command completes, or when leaving Insert mode.
     pub fn finish_undo_group(&mut self) {
-        if let Some(group) = self.current_undo_group.take() {
+        if let Some(mut group) = self.current_undo_group.take() {
             if !group.is_empty() {
+                group.time = SystemTime::now();
                 self.undo_stack.push(group);
             }
         }
@@ -561,6 +1145,7 @@ impl BufferState {
         self.redo_stack.push(UndoEntry {
             ops: entry.ops,
             cursor_before: cursor_to_restore,
+            time: entry.time,
         });
 
         self.update_syntax();
@@ -579,15 +1164,20 @@ impl BufferState {
             match op {
                 EditOp::Insert { pos, text } => {
                     self.buffer.insert(*pos, text);
-                    // Position cursor at end of inserted text
-                    let line = self
-                        .buffer
-                        .content
-                        .char_to_line(*pos + text.chars().count());
+                    // Position cursor at end of inserted text — or at its
+                    // start when it ends the buffer with a newline (e.g. an
+                    // `:e!` reload), rather than on the empty line past it.
+                    let end = *pos + text.chars().count();
+                    let at = if end == self.buffer.len_chars() && text.ends_with('\n') {
+                        *pos
+                    } else {
+                        end
+                    };
+                    let line = self.buffer.content.char_to_line(at);
                     let line_start = self.buffer.line_to_char(line);
                     cursor_after = Cursor {
                         line,
-                        col: (*pos + text.chars().count()) - line_start,
+                        col: at - line_start,
                     };
                 }
                 EditOp::Delete { pos, text } => {
@@ -749,7 +1339,7 @@ impl BufferManager {
         if language_map.is_empty() {
             return;
         }
-        if let Some(state) = self.buffers.get_mut(&id) {
+        if let Some(state) = self.buffers.get_mut(&id).filter(|s| !s.large_file) {
             if let Some(ext) = state
                 .file_path
                 .as_ref()
@@ -764,7 +1354,14 @@ impl BufferManager {
     }
 
     /// Create a buffer from a file. Reuses existing buffer if file is already open.
-    pub fn open_file(&mut self, path: &Path) -> Result<BufferId, io::Error> {
+    /// `settings` decides whether the file opens in large-file mode; `cwd`
+    /// is the editor's working directory.
+    pub fn open_file(
+        &mut self,
+        path: &Path,
+        cwd: &Path,
+        settings: &Settings,
+    ) -> Result<BufferId, io::Error> {
         // Check if file is already open
         let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
         for (id, state) in &self.buffers {
@@ -783,12 +1380,11 @@ impl BufferManager {
         self.next_id += 1;
 
         let buffer_state = if path.exists() {
-            let buffer = Buffer::from_file(id, path)?;
-            BufferState::with_file(buffer, path.to_path_buf())
+            BufferState::open(id, path.to_path_buf(), cwd, settings)?
         } else {
             // New file (doesn't exist yet)
             let buffer = Buffer::new(id);
-            BufferState::with_file(buffer, path.to_path_buf())
+            BufferState::with_file(buffer, path.to_path_buf(), cwd)
         };
 
         self.buffers.insert(id, buffer_state);
@@ -1015,4 +1611,18 @@ mod tests {
         // file1 should be at front now
         assert_eq!(manager.recent_files.len(), 2);
     }
+
+    #[test]
+    fn test_large_chunks_end_on_line_breaks() {
+        let path = std::env::temp_dir().join("vimcode_test_large_chunks.txt");
+        std::fs::write(&path, "short\na much longer line\nend").unwrap();
+        let (tx, rx) = mpsc::channel();
+        LargeChunk::read_all(&path, None, 8, &tx).unwrap();
+        drop(tx);
+        let chunks: Vec<LargeChunk> = rx.iter().map(|c| c.unwrap()).collect();
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["short\na much longer line\n", "end"]);
+        assert_eq!(chunks[0].max_col, "a much longer line".len());
+        let _ = std::fs::remove_file(&path);
+    }
 }
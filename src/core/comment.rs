@@ -136,6 +136,29 @@ pub fn resolve_comment_style(
     }
 }
 
+/// Parse a Vim-style `commentstring` (`"// %s"`, `"/* %s */"`) into a comment
+/// style.  Returns `None` when it is empty or has no marker before `%s`.
+pub fn parse_commentstring(cms: &str) -> Option<CommentStyleOwned> {
+    let (before, after) = cms.split_once("%s")?;
+    let (open, close) = (before.trim(), after.trim());
+    if open.is_empty() {
+        return None;
+    }
+    Some(if close.is_empty() {
+        CommentStyleOwned {
+            line: open.to_string(),
+            block_open: String::new(),
+            block_close: String::new(),
+        }
+    } else {
+        CommentStyleOwned {
+            line: String::new(),
+            block_open: open.to_string(),
+            block_close: close.to_string(),
+        }
+    })
+}
+
 // ─── Edit descriptor ──────────────────────────────────────────────────────────
 
 /// Describes a single line edit produced by `compute_toggle_edits`.
@@ -310,6 +333,19 @@ mod tests {
         assert_eq!(s.line, "##");
     }
 
+    #[test]
+    fn parse_commentstring_line_and_block() {
+        let s = parse_commentstring("// %s").unwrap();
+        assert_eq!(s.line, "//");
+        assert!(s.block_open.is_empty());
+        let s = parse_commentstring("<!-- %s -->").unwrap();
+        assert_eq!(s.line, "");
+        assert_eq!(s.block_open, "<!--");
+        assert_eq!(s.block_close, "-->");
+        assert!(parse_commentstring("").is_none());
+        assert!(parse_commentstring("%s").is_none());
+    }
+
     #[test]
     fn resolve_falls_back_to_hash() {
         let s = resolve_comment_style("unknown_lang", &HashMap::new());
@@ -0,0 +1,418 @@
+//! `.editorconfig` support.
+//!
+//! For a file being opened, every `.editorconfig` from its directory up to
+//! the first one marked `root = true` is read; sections whose glob matches
+//! the file contribute their properties, nearer files and later sections
+//! winning.  Section globs support `*`, `**`, `?`, `[abc]`, `[!abc]`,
+//! `{a,b}` and `{1..10}`.  A glob without `/` matches the file name at any
+//! depth; one with `/` is anchored at the `.editorconfig`'s directory.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File name looked up in the file's directory and its parents.
+pub const EDITORCONFIG_FILE: &str = ".editorconfig";
+
+/// Largest `{n..m}` range expanded into alternatives.
+const MAX_RANGE: i64 = 1000;
+
+/// Most plain globs one section header expands into; several ranges in a
+/// row multiply, so the rest are dropped.
+const MAX_EXPANSIONS: usize = 10_000;
+
+/// `indent_style` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// `end_of_line` value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfLine {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+/// The supported properties that apply to one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    /// `indent_size`; `Some(0)` stands for `indent_size = tab`.
+    pub indent_size: Option<u8>,
+    pub tab_width: Option<u8>,
+    pub end_of_line: Option<EndOfLine>,
+    /// Lowercase `charset` (`utf-8`, `utf-8-bom`, `latin1`, …).
+    pub charset: Option<String>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl EditorConfig {
+    /// Look up the properties for `path` (which need not exist yet); a
+    /// relative `path` is taken relative to `cwd`.
+    pub fn for_path(path: &Path, cwd: &Path) -> Self {
+        let path = cwd.join(path);
+        let Some(dir) = path.parent() else {
+            return Self::default();
+        };
+        // Nearest first; stop after a `root = true` file.
+        let mut files: Vec<(PathBuf, String)> = Vec::new();
+        for ancestor in dir.ancestors() {
+            let candidate = ancestor.join(EDITORCONFIG_FILE);
+            let Ok(text) = std::fs::read_to_string(&candidate) else {
+                continue;
+            };
+            let root = is_root(&text);
+            files.push((ancestor.to_path_buf(), text));
+            if root {
+                break;
+            }
+        }
+        let mut props = HashMap::new();
+        for (config_dir, text) in files.iter().rev() {
+            let Ok(rel) = path.strip_prefix(config_dir) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            collect_properties(text, &rel, &mut props);
+        }
+        Self::from_properties(&props)
+    }
+
+    /// Interpret raw (lowercased) properties; unknown or invalid values are
+    /// ignored, as the spec asks.
+    pub fn from_properties(props: &HashMap<String, String>) -> Self {
+        let get = |key: &str| props.get(key).map(|v| v.as_str());
+        let indent_style = match get("indent_style") {
+            Some("space") => Some(IndentStyle::Space),
+            Some("tab") => Some(IndentStyle::Tab),
+            _ => None,
+        };
+        let tab_width = get("tab_width").and_then(parse_width);
+        let indent_size = match get("indent_size") {
+            Some("tab") => Some(0),
+            Some(v) => parse_width(v),
+            // With tabs and no indent_size, indent by one tab.
+            None if indent_style == Some(IndentStyle::Tab) => Some(0),
+            None => None,
+        };
+        // tab_width defaults to a numeric indent_size.
+        let tab_width = tab_width.or(indent_size.filter(|&n| n > 0));
+        let end_of_line = match get("end_of_line") {
+            Some("lf") => Some(EndOfLine::Lf),
+            Some("crlf") => Some(EndOfLine::Crlf),
+            Some("cr") => Some(EndOfLine::Cr),
+            _ => None,
+        };
+        let charset = get("charset").map(str::to_string);
+        let bool_prop = |key: &str| match get(key) {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        };
+        Self {
+            indent_style,
+            indent_size,
+            tab_width,
+            end_of_line,
+            charset,
+            trim_trailing_whitespace: bool_prop("trim_trailing_whitespace"),
+            insert_final_newline: bool_prop("insert_final_newline"),
+        }
+    }
+}
+
+fn parse_width(value: &str) -> Option<u8> {
+    value.parse().ok().filter(|n| (1..=32).contains(n))
+}
+
+/// Split a line into a lowercase key and its value.
+fn key_value(line: &str) -> Option<(String, &str)> {
+    let (key, value) = line.split_once(['=', ':'])?;
+    Some((key.trim().to_ascii_lowercase(), value.trim()))
+}
+
+/// Whether the preamble (before the first section) says `root = true`.
+fn is_root(text: &str) -> bool {
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some((key, value)) = key_value(line) {
+            if key == "root" && value.eq_ignore_ascii_case("true") {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Add the properties of every section of `text` that matches `rel_path`
+/// (relative to the file's directory, `/`-separated) to `props`.
+fn collect_properties(text: &str, rel_path: &str, props: &mut HashMap<String, String>) {
+    let mut matching = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let section = line
+                .strip_prefix('[')
+                .and_then(|l| l.rfind(']').map(|end| &l[..end]))
+                .unwrap_or("");
+            matching = section_matches(section, rel_path);
+            continue;
+        }
+        if !matching {
+            continue;
+        }
+        if let Some((key, value)) = key_value(line) {
+            let value = value.to_ascii_lowercase();
+            if value == "unset" {
+                props.remove(&key);
+            } else {
+                props.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Whether the section glob `section` matches `rel_path`.
+pub fn section_matches(section: &str, rel_path: &str) -> bool {
+    if section.is_empty() {
+        return false;
+    }
+    let (pattern, target) = if section.contains('/') {
+        (section.trim_start_matches('/'), rel_path)
+    } else {
+        (section, rel_path.rsplit('/').next().unwrap_or(rel_path))
+    };
+    let target: Vec<char> = target.chars().collect();
+    expand_braces(pattern).iter().any(|alt| {
+        let alt: Vec<char> = alt.chars().collect();
+        glob_match(&alt, &target)
+    })
+}
+
+/// Expand `{a,b}` alternatives and `{n..m}` ranges into plain globs.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let Some(open) = chars.iter().position(|&c| c == '{') else {
+        return vec![pattern.to_string()];
+    };
+    // Find the matching close brace, allowing nesting.
+    let mut depth = 0;
+    let mut close = None;
+    for (i, &c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let Some(close) = close else {
+        return vec![pattern.to_string()];
+    };
+    let prefix: String = chars[..open].iter().collect();
+    let inner: String = chars[open + 1..close].iter().collect();
+    let suffix: String = chars[close + 1..].iter().collect();
+
+    let Some(alternatives) = brace_alternatives(&inner) else {
+        // Neither a list nor a range: the braces are literal.
+        let head = format!("{prefix}{{{inner}}}");
+        return expand_braces(&suffix)
+            .into_iter()
+            .map(|rest| format!("{head}{rest}"))
+            .collect();
+    };
+    let mut out = Vec::new();
+    for alt in alternatives {
+        out.extend(expand_braces(&format!("{prefix}{alt}{suffix}")));
+        if out.len() >= MAX_EXPANSIONS {
+            out.truncate(MAX_EXPANSIONS);
+            break;
+        }
+    }
+    out
+}
+
+/// The alternatives inside one pair of braces: a numeric range or a
+/// top-level comma list.  `None` when it is neither.
+fn brace_alternatives(inner: &str) -> Option<Vec<String>> {
+    if let Some((lo, hi)) = inner.split_once("..") {
+        if let (Ok(lo), Ok(hi)) = (lo.parse::<i64>(), hi.parse::<i64>()) {
+            if lo <= hi && hi - lo <= MAX_RANGE {
+                return Some((lo..=hi).map(|n| n.to_string()).collect());
+            }
+        }
+    }
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in inner.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+    (parts.len() > 1).then_some(parts)
+}
+
+/// Match a brace-free glob against a path.  `*` and `?` stop at `/`;
+/// `**` crosses directories.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && text[0] != '/' && glob_match(&pattern[1..], &text[1..]),
+        Some('[') => match pattern.iter().skip(1).position(|&c| c == ']') {
+            Some(len) if len > 0 => {
+                let class = &pattern[1..=len];
+                let rest = &pattern[len + 2..];
+                let Some(&c) = text.first() else {
+                    return false;
+                };
+                let (negated, class) = match class.first() {
+                    Some('!') => (true, &class[1..]),
+                    _ => (false, class),
+                };
+                class_contains(class, c) != negated && c != '/' && glob_match(rest, &text[1..])
+            }
+            // No closing bracket: a literal '['.
+            _ => text.first() == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(&c) => text.first() == Some(&c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Whether `c` is in a `[...]` class body (`abc`, `a-z`).
+fn class_contains(class: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            if (class[i]..=class[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn globs_match_like_editorconfig() {
+        assert!(section_matches("*", "src/main.rs"));
+        assert!(section_matches("*.{js,py}", "lib/app.py"));
+        assert!(!section_matches("*.{js,py}", "lib/app.rs"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+        assert!(section_matches("lib/**.js", "lib/a/b/c.js"));
+        assert!(!section_matches("lib/*.js", "lib/a/c.js"));
+        assert!(section_matches("/src/*.rs", "src/main.rs"));
+        assert!(!section_matches("/src/*.rs", "other/src/main.rs"));
+        assert!(section_matches("file[0-9].txt", "file7.txt"));
+        assert!(section_matches("file[!0-9].txt", "filex.txt"));
+        assert!(section_matches("v{1..3}.md", "v2.md"));
+        assert!(!section_matches("v{1..3}.md", "v4.md"));
+        assert!(section_matches("{a,{b,c}}.txt", "c.txt"));
+        assert!(section_matches("?.c", "x.c"));
+        assert!(section_matches("{single}.txt", "{single}.txt"));
+    }
+
+    #[test]
+    fn brace_expansion_is_capped() {
+        let pattern = "{1..1000}{1..1000}{1..1000}";
+        assert_eq!(expand_braces(pattern).len(), MAX_EXPANSIONS);
+        assert!(section_matches(pattern, "111"));
+    }
+
+    #[test]
+    fn interprets_properties() {
+        let props: HashMap<String, String> = [
+            ("indent_style", "tab"),
+            ("tab_width", "8"),
+            ("end_of_line", "crlf"),
+            ("insert_final_newline", "true"),
+            ("trim_trailing_whitespace", "maybe"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let ec = EditorConfig::from_properties(&props);
+        assert_eq!(ec.indent_style, Some(IndentStyle::Tab));
+        assert_eq!(ec.indent_size, Some(0));
+        assert_eq!(ec.tab_width, Some(8));
+        assert_eq!(ec.end_of_line, Some(EndOfLine::Crlf));
+        assert_eq!(ec.insert_final_newline, Some(true));
+        assert_eq!(ec.trim_trailing_whitespace, None);
+    }
+
+    #[test]
+    fn nearer_files_win_and_root_stops_the_walk() {
+        let base = std::env::temp_dir().join("vimcode_test_editorconfig");
+        let _ = fs::remove_dir_all(&base);
+        let project = base.join("project");
+        let nested = project.join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(base.join(EDITORCONFIG_FILE), "[*]\ncharset = latin1\n").unwrap();
+        fs::write(
+            project.join(EDITORCONFIG_FILE),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\n\n[*.md]\nindent_size = 2\n",
+        )
+        .unwrap();
+        fs::write(nested.join(EDITORCONFIG_FILE), "[*.rs]\nindent_size = 3\n").unwrap();
+
+        let ec = EditorConfig::for_path(Path::new("main.rs"), &nested);
+        assert_eq!(ec.indent_style, Some(IndentStyle::Space));
+        assert_eq!(ec.indent_size, Some(3));
+        assert_eq!(ec.tab_width, Some(3));
+        assert_eq!(ec.charset, None, "walk stops at root = true");
+
+        let ec = EditorConfig::for_path(&project.join("README.md"), &nested);
+        assert_eq!(ec.indent_size, Some(2));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,283 @@
+//! File encodings: detection on load, decoding to the UTF-8 text buffers
+//! hold, and re-encoding on save.
+//!
+//! Detection sniffs a byte-order mark first, then guesses UTF-16 from the
+//! pattern of NUL bytes, then accepts valid UTF-8; anything else is read
+//! as latin-1, which never fails.  Bytes invalid in a forced encoding
+//! decode to U+FFFD so the damage is visible in the buffer.
+
+use std::io;
+use std::path::Path;
+
+use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Number of leading bytes examined by the UTF-16 heuristic.
+const SNIFF_LEN: usize = 4096;
+
+/// Encoding of a file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileEncoding {
+    #[default]
+    Utf8,
+    /// UTF-8 with a leading byte-order mark.
+    Utf8Bom,
+    /// UTF-16; `bom` records whether the file starts with a byte-order
+    /// mark, so a file without one is written back without one.
+    Utf16Le {
+        bom: bool,
+    },
+    Utf16Be {
+        bom: bool,
+    },
+    Latin1,
+}
+
+impl FileEncoding {
+    /// Every supported encoding, in the order offered to the user.
+    pub const ALL: [FileEncoding; 5] = [
+        FileEncoding::Utf8,
+        FileEncoding::Utf8Bom,
+        FileEncoding::Utf16Le { bom: true },
+        FileEncoding::Utf16Be { bom: true },
+        FileEncoding::Latin1,
+    ];
+
+    /// Name shown in the status line and accepted by `:set fileencoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileEncoding::Utf8 => "utf-8",
+            FileEncoding::Utf8Bom => "utf-8-bom",
+            FileEncoding::Utf16Le { .. } => "utf-16le",
+            FileEncoding::Utf16Be { .. } => "utf-16be",
+            FileEncoding::Latin1 => "latin1",
+        }
+    }
+
+    /// Parse an encoding name, accepting Vim and EditorConfig spellings.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(FileEncoding::Utf8),
+            "utf-8-bom" | "utf8-bom" => Some(FileEncoding::Utf8Bom),
+            "utf-16le" | "utf16le" | "ucs-2le" => Some(FileEncoding::Utf16Le { bom: true }),
+            "utf-16be" | "utf16be" | "utf-16" | "utf16" | "ucs-2" => {
+                Some(FileEncoding::Utf16Be { bom: true })
+            }
+            "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" | "l1" => Some(FileEncoding::Latin1),
+            _ => None,
+        }
+    }
+
+    /// Guess the encoding of `bytes`.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            return FileEncoding::Utf8Bom;
+        }
+        if bytes.starts_with(UTF16LE_BOM) {
+            return FileEncoding::Utf16Le { bom: true };
+        }
+        if bytes.starts_with(UTF16BE_BOM) {
+            return FileEncoding::Utf16Be { bom: true };
+        }
+        if let Some(enc) = guess_utf16(bytes) {
+            return enc;
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            FileEncoding::Utf8
+        } else {
+            FileEncoding::Latin1
+        }
+    }
+
+    /// The encoding to read `bytes` with: `forced`, keeping whether the
+    /// file has a UTF-16 BOM, or else the detected one.
+    pub fn for_bytes(bytes: &[u8], forced: Option<Self>) -> Self {
+        match forced {
+            Some(FileEncoding::Utf16Le { .. }) => FileEncoding::Utf16Le {
+                bom: bytes.starts_with(UTF16LE_BOM),
+            },
+            Some(FileEncoding::Utf16Be { .. }) => FileEncoding::Utf16Be {
+                bom: bytes.starts_with(UTF16BE_BOM),
+            },
+            Some(encoding) => encoding,
+            None => FileEncoding::detect(bytes),
+        }
+    }
+
+    /// Decode `bytes` (BOM included) to text.  Returns the text and whether
+    /// any invalid sequence was replaced with U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> (String, bool) {
+        let (encoding, bom) = match self {
+            FileEncoding::Utf8 | FileEncoding::Utf8Bom => (UTF_8, UTF8_BOM),
+            FileEncoding::Utf16Le { .. } => (UTF_16LE, UTF16LE_BOM),
+            FileEncoding::Utf16Be { .. } => (UTF_16BE, UTF16BE_BOM),
+            // WINDOWS_1252 is the Encoding Standard's "latin1"; its extra
+            // characters occupy the C1 control range real files rarely use.
+            FileEncoding::Latin1 => (WINDOWS_1252, &b""[..]),
+        };
+        let body = if bom.is_empty() {
+            bytes
+        } else {
+            bytes.strip_prefix(bom).unwrap_or(bytes)
+        };
+        let (text, had_errors) = encoding.decode_without_bom_handling(body);
+        (text.into_owned(), had_errors)
+    }
+
+    /// Encode `text` for writing, adding the BOM where the encoding has one
+    /// (for UTF-16, where the file had one).
+    /// Fails on the first character the encoding cannot represent.
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>> {
+        let utf16 = |big_endian: bool, bom: bool| {
+            let mut out = Vec::with_capacity(text.len() * 2 + 2);
+            if bom {
+                out.extend_from_slice(if big_endian { UTF16BE_BOM } else { UTF16LE_BOM });
+            }
+            for unit in text.encode_utf16() {
+                let bytes = if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                };
+                out.extend_from_slice(&bytes);
+            }
+            out
+        };
+        Ok(match self {
+            FileEncoding::Utf8 => text.as_bytes().to_vec(),
+            FileEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            FileEncoding::Utf16Le { bom } => utf16(false, bom),
+            FileEncoding::Utf16Be { bom } => utf16(true, bom),
+            FileEncoding::Latin1 => {
+                let (bytes, _, had_errors) = WINDOWS_1252.encode(text);
+                if had_errors {
+                    let bad = text
+                        .chars()
+                        .find(|c| WINDOWS_1252.encode(&c.to_string()).2)
+                        .unwrap_or('?');
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{bad:?} cannot be written as latin1"),
+                    ));
+                }
+                bytes.into_owned()
+            }
+        })
+    }
+}
+
+/// UTF-16 without a BOM: ASCII-heavy text has a NUL in every other byte.
+fn guess_utf16(bytes: &[u8]) -> Option<FileEncoding> {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_nuls = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_nuls = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+    if odd_nuls * 10 >= pairs * 4 && even_nuls * 10 < pairs {
+        Some(FileEncoding::Utf16Le { bom: false })
+    } else if even_nuls * 10 >= pairs * 4 && odd_nuls * 10 < pairs {
+        Some(FileEncoding::Utf16Be { bom: false })
+    } else {
+        None
+    }
+}
+
+/// Line (0-based) of the first U+FFFD in `text` — where decoding replaced
+/// bytes that weren't valid in the encoding.
+pub fn first_replaced_line(text: &str) -> Option<usize> {
+    let pos = text.find('\u{FFFD}')?;
+    Some(text[..pos].matches('\n').count())
+}
+
+/// Read `path` and decode it with `forced`, or the detected encoding.
+/// Returns the text, the encoding used and whether bytes were replaced.
+pub fn read_file(
+    path: &Path,
+    forced: Option<FileEncoding>,
+) -> io::Result<(String, FileEncoding, bool)> {
+    let bytes = std::fs::read(path)?;
+    let encoding = FileEncoding::for_bytes(&bytes, forced);
+    let (text, had_errors) = encoding.decode(&bytes);
+    Ok((text, encoding, had_errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_boms_and_heuristics() {
+        let cases: [(&[u8], FileEncoding); 8] = [
+            (b"plain", FileEncoding::Utf8),
+            ("caf\u{e9}".as_bytes(), FileEncoding::Utf8),
+            (b"\xEF\xBB\xBFhi", FileEncoding::Utf8Bom),
+            (b"\xFF\xFEh\0i\0", FileEncoding::Utf16Le { bom: true }),
+            (b"\xFE\xFF\0h\0i", FileEncoding::Utf16Be { bom: true }),
+            (b"h\0e\0l\0l\0o\0", FileEncoding::Utf16Le { bom: false }),
+            (b"\0h\0e\0l\0l\0o", FileEncoding::Utf16Be { bom: false }),
+            (b"caf\xE9\n", FileEncoding::Latin1),
+        ];
+        for (bytes, want) in cases {
+            assert_eq!(FileEncoding::detect(bytes), want, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn round_trips_every_encoding() {
+        let text = "caf\u{e9} \u{fc}ber\n";
+        for enc in FileEncoding::ALL {
+            let bytes = enc.encode(text).unwrap();
+            assert_eq!(FileEncoding::detect(&bytes), enc, "{enc:?}");
+            assert_eq!(enc.decode(&bytes), (text.to_string(), false), "{enc:?}");
+        }
+    }
+
+    #[test]
+    fn utf16_without_bom_is_written_without_one() {
+        let bytes = b"h\0i\0\n\0";
+        let enc = FileEncoding::detect(bytes);
+        let (text, _) = enc.decode(bytes);
+        assert_eq!(enc.encode(&text).unwrap(), bytes);
+        // Rereading with a forced encoding keeps the missing BOM too.
+        let forced = FileEncoding::for_bytes(bytes, Some(FileEncoding::Utf16Le { bom: true }));
+        assert_eq!(forced, FileEncoding::Utf16Le { bom: false });
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced_visibly() {
+        let (text, had_errors) = FileEncoding::Utf8.decode(b"ok\xFF\n");
+        assert_eq!(text, "ok\u{FFFD}\n");
+        assert!(had_errors);
+        assert_eq!(first_replaced_line("a\nb\nc\u{FFFD}\n"), Some(2));
+        assert_eq!(first_replaced_line("clean\n"), None);
+    }
+
+    #[test]
+    fn latin1_rejects_unmappable_characters() {
+        let err = FileEncoding::Latin1.encode("snow \u{2603}").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(
+            FileEncoding::from_name("ISO-8859-1"),
+            Some(FileEncoding::Latin1)
+        );
+        assert_eq!(FileEncoding::from_name("utf8"), Some(FileEncoding::Utf8));
+        assert_eq!(FileEncoding::from_name("ebcdic"), None);
+        for enc in FileEncoding::ALL {
+            assert_eq!(FileEncoding::from_name(enc.name()), Some(enc));
+        }
+    }
+}
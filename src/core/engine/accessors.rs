@@ -183,6 +183,18 @@ impl Engine {
         self.active_buffer_state().dirty
     }
 
+    /// Show `message` as an error or warning that backends draw in the
+    /// error color (`E486: Pattern not found`, search wrap notices, …).
+    pub(crate) fn set_error_message(&mut self, message: String) {
+        self.error_message = Some(message.clone());
+        self.message = message;
+    }
+
+    /// True when `message` was set by [`Self::set_error_message`].
+    pub fn message_is_error(&self) -> bool {
+        !self.message.is_empty() && self.error_message.as_ref() == Some(&self.message)
+    }
+
     /// True if ANY open buffer has unsaved changes.
     pub fn has_any_unsaved(&self) -> bool {
         self.buffer_manager
@@ -308,9 +320,10 @@ impl Engine {
         (git_statuses, diag_counts)
     }
 
-    /// Save every dirty buffer that has a known file path.
-    /// Returns the number of buffers successfully saved.
-    pub fn save_all_dirty(&mut self) -> usize {
+    /// Save every dirty buffer that has a known file path.  Returns the
+    /// number of buffers saved, or the first error when any buffer could not
+    /// be written (read-only, changed on disk, or a failed write).
+    pub fn save_all_dirty(&mut self) -> Result<usize, String> {
         let dirty_ids: Vec<_> = self
             .buffer_manager
             .list()
@@ -322,14 +335,21 @@ impl Engine {
             })
             .collect();
         let mut saved = 0;
+        let mut error = None;
         for id in dirty_ids {
-            if let Some(state) = self.buffer_manager.get_mut(id) {
-                if state.save().is_ok() {
+            match self.write_buffer(id) {
+                Ok(_) => {
                     saved += 1;
+                    self.swap_delete_for_buffer(id);
+                    self.swap_write_needed.remove(&id);
+                    self.undo_file_write_for_buffer(id);
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
                 }
             }
         }
-        saved
+        error.map_or(Ok(saved), Err)
     }
 
     /// Set the dirty flag for the active buffer.
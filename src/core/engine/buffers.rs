@@ -1,5 +1,23 @@
 use super::*;
 
+/// How long format-on-save waits for the server before saving unformatted.
+const FORMAT_ON_SAVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long the status line shows the auto-save indicator.
+const AUTOSAVE_INDICATOR: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long edits must pause before `:GitBlame` is re-run.
+const BLAME_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Vim's ` [ILLEGAL BYTE in line N]` when reading the buffer replaced bytes
+/// invalid in its encoding, or an empty string.
+fn illegal_byte_note(state: &BufferState) -> String {
+    state
+        .illegal_byte_line
+        .map(|line| format!(" [ILLEGAL BYTE in line {}]", line + 1))
+        .unwrap_or_default()
+}
+
 impl Engine {
     // =======================================================================
     // Buffer operations
@@ -97,17 +115,129 @@ impl Engine {
         }
         self.pending_key = None;
         self.pending_find_operator = None;
+        self.pending_surround = None;
         self.count = None;
     }
 
+    /// Refuse changes to a `nomodifiable` or read-only active buffer,
+    /// explaining why in the message.  Every edit primitive checks this.
+    pub(crate) fn check_modifiable(&mut self) -> bool {
+        self.check_buffer_modifiable(self.active_buffer_id())
+    }
+
+    /// [`Self::check_modifiable`] for any buffer, e.g. the other side of a
+    /// diff or the target of an LSP edit.
+    pub(crate) fn check_buffer_modifiable(&mut self, id: BufferId) -> bool {
+        let Some(state) = self.buffer_manager.get(id) else {
+            return false;
+        };
+        let error = if !state.modifiable {
+            "E21: Cannot make changes, 'modifiable' is off"
+        } else if state.read_only {
+            "E45: 'readonly' option is set (add ! to override)"
+        } else {
+            return true;
+        };
+        self.set_error_message(error.to_string());
+        false
+    }
+
     /// Insert text with undo recording.
     pub fn insert_with_undo(&mut self, pos: usize, text: &str) {
+        if !self.check_modifiable() {
+            return;
+        }
         self.active_buffer_state_mut().record_insert(pos, text);
         self.buffer_mut().insert(pos, text);
     }
 
+    /// Convert every line break in the active buffer to `format` as one
+    /// undo step (a mixed-ending buffer is normalized even when its
+    /// detected format already matches).  Returns false when no text changed.
+    pub fn set_file_format(&mut self, format: LineEnding) -> bool {
+        self.active_buffer_state_mut().line_ending = format;
+        let text = self.buffer().to_string();
+        let converted = format.normalize(&text);
+        if converted == text {
+            return false;
+        }
+        let cursor = self.view().cursor;
+        let len = self.buffer().len_chars();
+        self.start_undo_group();
+        self.delete_with_undo(0, len);
+        self.insert_with_undo(0, &converted);
+        self.finish_undo_group();
+        self.set_dirty(true);
+        self.view_mut().cursor = cursor;
+        self.clamp_cursor_col();
+        self.update_syntax();
+        true
+    }
+
+    /// The message shown after opening `path` into `buffer_id`.
+    pub(crate) fn opened_message(&self, buffer_id: BufferId, path: &Path) -> String {
+        let note = self
+            .buffer_manager
+            .get(buffer_id)
+            .map(illegal_byte_note)
+            .unwrap_or_default();
+        format!("\"{}\"{note}", path.display())
+    }
+
+    /// `:e[dit][!] ++enc={enc} [file]`: open `file`, or reread the current
+    /// buffer, decoding it as `{enc}`.  `rest` is the text after `++enc`.
+    pub(crate) fn edit_with_encoding(&mut self, force: bool, rest: &str) -> EngineAction {
+        let rest = rest.strip_prefix("oding").unwrap_or(rest);
+        let Some(rest) = rest.strip_prefix('=') else {
+            self.set_error_message("E474: Invalid argument".to_string());
+            return EngineAction::Error;
+        };
+        let (name, file) = rest.split_once(' ').unwrap_or((rest, ""));
+        let Some(encoding) = FileEncoding::from_name(name) else {
+            self.set_error_message(format!("E474: Invalid argument: ++enc={name}"));
+            return EngineAction::Error;
+        };
+        let file = file.trim();
+        if !file.is_empty() {
+            self.open_file_in_tab(&paths::expand_tilde(file));
+        }
+        if self.dirty() && !force {
+            self.set_error_message(
+                "E37: No write since last change (add ! to override)".to_string(),
+            );
+            return EngineAction::Error;
+        }
+        let state = self.active_buffer_state_mut();
+        if !state.file_path.as_ref().is_some_and(|p| p.exists()) {
+            // Nothing to reread; the encoding applies when the file is written.
+            state.encoding = encoding;
+            self.message = format!("fileencoding={}", encoding.name());
+            return EngineAction::None;
+        }
+        match state.reload_with_encoding(Some(encoding)) {
+            Ok(()) => {
+                let illegal = illegal_byte_note(state);
+                self.message = format!(
+                    "\"{}\" [{}]{illegal}",
+                    state.display_name(),
+                    encoding.name()
+                );
+                let id = self.active_buffer_id();
+                self.clamp_views_to_buffer(id);
+                EngineAction::None
+            }
+            Err(e) => {
+                self.message = format!("Error: {}", e);
+                EngineAction::Error
+            }
+        }
+    }
+
     /// Delete a range with undo recording.
     pub fn delete_with_undo(&mut self, start: usize, end: usize) {
+        if !self.check_modifiable() {
+            return;
+        }
         // Capture the text being deleted before deleting
         let deleted_text: String = self.buffer().content.slice(start..end).chars().collect();
         self.active_buffer_state_mut()
@@ -117,6 +247,9 @@ impl Engine {
 
     /// Perform undo on the active buffer. Returns true if undo was performed.
     pub fn undo(&mut self) -> bool {
+        if !self.check_modifiable() {
+            return false;
+        }
         if let Some(cursor) = self.active_buffer_state_mut().undo() {
             self.view_mut().cursor = cursor;
             self.clamp_cursor_col();
@@ -137,6 +270,9 @@ impl Engine {
 
     /// Perform redo on the active buffer. Returns true if redo was performed.
     pub fn redo(&mut self) -> bool {
+        if !self.check_modifiable() {
+            return false;
+        }
         if let Some(cursor) = self.active_buffer_state_mut().redo() {
             self.view_mut().cursor = cursor;
             self.clamp_cursor_col();
@@ -155,6 +291,69 @@ impl Engine {
         }
     }
 
+    /// Step backward (`:earlier`) or forward (`:later`) through the undo
+    /// stack by change count or by time. Requests beyond the available
+    /// history clamp to the oldest/newest state.
+    pub(crate) fn undo_time_travel(&mut self, step: UndoStep, forward: bool) {
+        self.active_buffer_state_mut().finish_undo_group();
+        let bs = self.active_buffer_state();
+        let state_time = bs
+            .undo_stack
+            .last()
+            .or(bs.redo_stack.last())
+            .map(|e| e.time);
+        // `None` means no time bound (the offset overflowed), so clamp to the end.
+        let target = state_time.and_then(|t| match step {
+            UndoStep::Seconds(secs) if forward => {
+                t.checked_add(std::time::Duration::from_secs(secs))
+            }
+            UndoStep::Seconds(secs) => t.checked_sub(std::time::Duration::from_secs(secs)),
+            UndoStep::Count(_) => None,
+        });
+        let mut changes = 0usize;
+        loop {
+            let bs = self.active_buffer_state();
+            let next = if forward {
+                bs.redo_stack.last()
+            } else {
+                bs.undo_stack.last()
+            };
+            let Some(entry) = next else { break };
+            let more = match (step, target) {
+                (UndoStep::Count(n), _) => changes < n,
+                (UndoStep::Seconds(_), None) => true,
+                (UndoStep::Seconds(_), Some(t)) if forward => entry.time <= t,
+                (UndoStep::Seconds(_), Some(t)) => entry.time > t,
+            };
+            if !more {
+                break;
+            }
+            if forward {
+                self.redo();
+            } else {
+                self.undo();
+            }
+            changes += 1;
+        }
+        let seq = self.active_buffer_state().undo_stack.len();
+        self.message = match (changes, forward) {
+            (0, false) => "Already at oldest change".to_string(),
+            (0, true) => "Already at newest change".to_string(),
+            (n, false) => format!(
+                "{} change{}; before #{}",
+                n,
+                if n == 1 { "" } else { "s" },
+                seq + 1
+            ),
+            (n, true) => format!(
+                "{} change{}; after #{}",
+                n,
+                if n == 1 { "" } else { "s" },
+                seq
+            ),
+        };
+    }
+
     /// Navigate to an earlier buffer state chronologically (`g-`).
     pub fn g_earlier(&mut self) -> bool {
         let bs = self.active_buffer_state_mut();
@@ -284,7 +483,8 @@ impl Engine {
         }
         let state = self.active_buffer_state_mut();
         if let Some(ref path) = state.file_path.clone() {
-            match state.save() {
+            let id = self.active_buffer_id();
+            match self.write_buffer(id) {
                 Ok(line_count) => {
                     let rel = self.copy_relative_path(path);
                     self.message = format!("\"{}\" {}L written", rel, line_count);
@@ -296,22 +496,56 @@ impl Engine {
                     // Delete swap file — content is safely on disk now.
                     self.swap_delete_for_buffer(id);
                     self.swap_write_needed.remove(&id);
+                    self.undo_file_write_for_buffer(id);
                     let path_str = path.to_string_lossy().into_owned();
                     self.plugin_event("save", &path_str);
                     self.plugin_event("BufWrite", &path_str);
                     Ok(())
                 }
                 Err(e) => {
-                    self.message = format!("Error writing {}: {}", path.display(), e);
+                    self.set_error_message(e);
                     Err(self.message.clone())
                 }
             }
         } else {
-            self.message = "No file name".to_string();
+            self.set_error_message("No file name".to_string());
             Err(self.message.clone())
         }
     }
 
+    /// Write a buffer to its file unless it is still loading, read-only or
+    /// the file changed on disk since it was read (`:w!` clears the last two
+    /// first).  Every save path goes through here.  Returns the number of lines written, or the
+    /// message to show.
+    pub(crate) fn write_buffer(&mut self, buffer_id: BufferId) -> Result<usize, String> {
+        let Some(state) = self.buffer_manager.get(buffer_id) else {
+            return Err("Buffer closed before save".to_string());
+        };
+        let Some(path) = state.file_path.clone() else {
+            return Err("No file name".to_string());
+        };
+        if state.loading.is_some() {
+            return Err(format!(
+                "\"{}\" is still loading — wait before writing",
+                self.copy_relative_path(&path)
+            ));
+        }
+        if state.read_only {
+            return Err("E45: 'readonly' option is set (add ! to override)".to_string());
+        }
+        if state.changed_on_disk() {
+            return Err(format!(
+                "WARNING: \"{}\" has changed on disk since reading it — :w! to overwrite, :e! to reload",
+                self.copy_relative_path(&path)
+            ));
+        }
+        self.buffer_manager
+            .get_mut(buffer_id)
+            .unwrap()
+            .save(&self.settings)
+            .map_err(|e| format!("Error writing {}: {}", path.display(), e))
+    }
+
     /// Check all open buffers for external file modifications.
     ///
     /// For each buffer with a file path, compare the on-disk mtime against the
@@ -325,85 +559,227 @@ impl Engine {
         if !self.settings.autoread {
             return false;
         }
+        self.reload_changed_files(false)
+    }
+
+    /// `:checktime` — check every buffer for external modifications now,
+    /// whether or not `autoread` is set, repeating earlier warnings.
+    pub fn checktime(&mut self) -> bool {
+        let changed = self.reload_changed_files(true);
+        if !changed {
+            self.message = "No files changed on disk".to_string();
+        }
+        changed
+    }
+
+    /// Reload clean buffers whose file changed on disk and warn about dirty
+    /// ones (once per change unless `rewarn`).  Returns true if anything was
+    /// reloaded or a warning was shown.
+    fn reload_changed_files(&mut self, rewarn: bool) -> bool {
         let mut any_changed = false;
 
         // Collect buffer IDs and paths first to avoid borrow conflicts.
-        let to_check: Vec<(BufferId, PathBuf, bool, bool, Option<std::time::SystemTime>)> = self
+        let to_check: Vec<(BufferId, PathBuf, bool, bool)> = self
             .buffer_manager
             .iter()
+            .filter(|(_, state)| state.changed_on_disk())
             .filter_map(|(id, state)| {
                 let path = state.file_path.as_ref()?.clone();
-                Some((
-                    *id,
-                    path,
-                    state.dirty,
-                    state.file_change_warned,
-                    state.file_mtime,
-                ))
+                Some((*id, path, state.dirty, state.file_change_warned))
             })
             .collect();
 
-        for (buf_id, path, is_dirty, already_warned, stored_mtime) in to_check {
-            let disk_mtime = match std::fs::metadata(&path).and_then(|m| m.modified()) {
-                Ok(t) => t,
-                Err(_) => continue, // file deleted or inaccessible — ignore
-            };
-
-            let changed = match stored_mtime {
-                Some(prev) => disk_mtime != prev,
-                None => false, // no stored mtime (e.g. new buffer) — skip
-            };
-
-            if !changed {
-                continue;
-            }
-
+        for (buf_id, path, is_dirty, already_warned) in to_check {
             if is_dirty {
                 // Buffer has unsaved changes — warn (once per external modification).
-                if !already_warned {
+                if !already_warned || rewarn {
                     if let Some(state) = self.buffer_manager.get_mut(buf_id) {
                         state.file_change_warned = true;
                     }
                     let name = path.display();
                     self.message = format!(
-                        "W12: Warning: File \"{}\" has changed since editing started. Use :e! to reload.",
+                        "W12: Warning: File \"{}\" has changed since editing started. Use :e! to reload, or :w! to keep your version.",
                         name
                     );
                     any_changed = true;
                 }
             } else {
-                // Buffer is clean — silently reload.
+                // Buffer is clean — silently reload, keeping each window's place.
                 let name = path
                     .file_name()
                     .map(|n| n.to_string_lossy().into_owned())
                     .unwrap_or_default();
-                if let Some(state) = self.buffer_manager.get_mut(buf_id) {
-                    if state.reload_from_disk().is_ok() {
-                        self.message = format!("\"{}\" reloaded", name);
-                        any_changed = true;
+                let reloaded = self
+                    .buffer_manager
+                    .get_mut(buf_id)
+                    .is_some_and(|state| state.reload_from_disk().is_ok());
+                if reloaded {
+                    if self
+                        .buffer_manager
+                        .get(buf_id)
+                        .is_some_and(|s| s.loading.is_some())
+                    {
+                        // A large file refills in the background; restore
+                        // the windows once it's all there.
+                        let views = self
+                            .windows
+                            .iter()
+                            .filter(|(_, w)| w.buffer_id == buf_id)
+                            .map(|(id, w)| (*id, w.view.clone()))
+                            .collect();
+                        self.reload_views.insert(buf_id, views);
                     }
+                    self.clamp_views_to_buffer(buf_id);
+                    self.message = format!("\"{}\" reloaded", name);
+                    any_changed = true;
                 }
             }
         }
         any_changed
     }
 
+    /// Keep every window showing `buffer_id` valid after its text was
+    /// replaced from disk: cursors, scroll offsets and folds are clamped to
+    /// the new length instead of reset, so each window stays where it was.
+    pub(crate) fn clamp_views_to_buffer(&mut self, buffer_id: BufferId) {
+        let Some(state) = self.buffer_manager.get(buffer_id) else {
+            return;
+        };
+        let buffer = &state.buffer;
+        let last = buffer.len_lines().saturating_sub(1);
+        for window in self.windows.values_mut() {
+            if window.buffer_id != buffer_id {
+                continue;
+            }
+            let view = &mut window.view;
+            view.cursor.line = view.cursor.line.min(last);
+            let line_len = buffer
+                .content
+                .line(view.cursor.line)
+                .chars()
+                .filter(|c| !matches!(c, '\n' | '\r'))
+                .count();
+            view.cursor.col = view.cursor.col.min(line_len.saturating_sub(1));
+            view.extra_cursors.clear();
+            view.scroll_top = view.scroll_top.min(last);
+            view.folds.retain(|f| f.end <= last);
+        }
+    }
+
+    /// Put back the cursor, scroll position and folds each window had before
+    /// its large buffer was reloaded; the caller clamps them afterwards.
+    fn restore_reload_views(&mut self, buffer_id: BufferId) {
+        for (window_id, saved) in self.reload_views.remove(&buffer_id).unwrap_or_default() {
+            let Some(window) = self.windows.get_mut(&window_id) else {
+                continue;
+            };
+            if window.buffer_id == buffer_id {
+                window.view.cursor = saved.cursor;
+                window.view.scroll_top = saved.scroll_top;
+                window.view.folds = saved.folds;
+            }
+        }
+    }
+
+    /// Append the pieces of large files read in the background so far.
+    /// Returns `true` if any buffer changed (caller should redraw).
+    pub fn poll_file_loads(&mut self) -> bool {
+        let loading: Vec<BufferId> = self
+            .buffer_manager
+            .iter()
+            .filter(|(_, s)| s.loading.is_some())
+            .map(|(id, _)| *id)
+            .collect();
+        let mut changed = false;
+        for id in loading {
+            let Some(state) = self.buffer_manager.get_mut(id) else {
+                continue;
+            };
+            match state.poll_load() {
+                None => continue,
+                Some(Ok(())) => {
+                    let done = state.loading.is_none();
+                    if done && state.illegal_byte_line.is_some() {
+                        self.message =
+                            format!("\"{}\"{}", state.display_name(), illegal_byte_note(state));
+                    }
+                    if done {
+                        self.restore_reload_views(id);
+                    }
+                    self.clamp_views_to_buffer(id);
+                }
+                Some(Err(e)) => {
+                    self.message = format!("Cannot read \"{}\": {}", state.display_name(), e);
+                    self.reload_views.remove(&id);
+                }
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    /// Remove trailing spaces/tabs from every line of the active buffer as one
+    /// undo step, keeping the cursor in place.  Returns the number of lines
+    /// changed.
+    pub fn strip_trailing_whitespace(&mut self) -> usize {
+        let mut ranges = Vec::new();
+        for line_idx in 0..self.buffer().len_lines() {
+            let text: String = self.buffer().content.line(line_idx).chars().collect();
+            let content = text.trim_end_matches(['\n', '\r']);
+            let trimmed = content.trim_end_matches([' ', '\t']);
+            if trimmed.len() < content.len() {
+                let start = self.buffer().line_to_char(line_idx) + trimmed.chars().count();
+                ranges.push((start, start + content[trimmed.len()..].chars().count()));
+            }
+        }
+        if ranges.is_empty() {
+            return 0;
+        }
+        let cursor = self.view().cursor;
+        self.start_undo_group();
+        for &(start, end) in ranges.iter().rev() {
+            self.delete_with_undo(start, end);
+        }
+        self.finish_undo_group();
+        self.view_mut().cursor = cursor;
+        self.clamp_cursor_col();
+        self.update_syntax();
+        ranges.len()
+    }
+
     /// Save the current buffer, optionally requesting LSP formatting first.
     ///
     /// When `format_on_save` is enabled and an LSP server supports formatting,
     /// this sends a formatting request and defers the actual disk save until the
-    /// formatting response arrives (handled in `poll_lsp`).
+    /// formatting response arrives (handled in `poll_lsp`). If no response
+    /// arrives within `FORMAT_ON_SAVE_TIMEOUT` the buffer is saved unformatted.
     pub fn save_with_format(&mut self, quit_after: bool) -> Result<(), String> {
-        // Check if we should format first.
-        if self.settings.format_on_save && self.settings.lsp_enabled {
+        let trim = self
+            .buffer_manager
+            .get(self.active_buffer_id())
+            .map_or(self.settings.strip_whitespace_on_save, |s| {
+                s.trim_trailing_whitespace(&self.settings)
+            });
+        if trim {
+            self.strip_trailing_whitespace();
+        }
+        // Only LSP-backed file buffers are formatted.
+        let has_lsp = self
+            .buffer_manager
+            .get(self.active_buffer_id())
+            .is_some_and(|s| s.lsp_language_id.is_some());
+        if self.settings.format_on_save && self.settings.lsp_enabled && has_lsp {
             self.ensure_lsp_manager();
             if let Some((path, _, _)) = self.lsp_cursor_position() {
-                let tab_size = self.settings.tabstop as u32;
-                let insert_spaces = self.settings.expand_tab;
+                let tab_size = self.effective_tabstop() as u32;
+                let insert_spaces = self.effective_expand_tab();
                 if let Some(mgr) = &mut self.lsp_manager {
                     if let Some(id) = mgr.request_formatting(&path, tab_size, insert_spaces) {
                         self.lsp_pending_formatting = Some(id);
+                        self.lsp_formatting_buffer = Some(self.active_buffer_id());
                         self.format_on_save_pending = Some(self.active_buffer_id());
+                        self.format_on_save_deadline =
+                            Some(std::time::Instant::now() + FORMAT_ON_SAVE_TIMEOUT);
                         self.quit_after_format_save = quit_after;
                         self.message = "Formatting...".to_string();
                         return Ok(());
@@ -412,11 +788,147 @@ impl Engine {
             }
         }
         // No format-on-save — save immediately.
-        let result = self.save();
-        if quit_after && result.is_ok() {
-            // Caller checks EngineAction — handled at call site.
+        self.save()
+    }
+
+    /// Complete a deferred format-on-save by writing the buffer to disk.
+    /// `warning` is prefixed to the save message when formatting was skipped.
+    pub(crate) fn finish_format_on_save(&mut self, warning: Option<&str>) {
+        let Some(buffer_id) = self.format_on_save_pending.take() else {
+            return;
+        };
+        self.format_on_save_deadline = None;
+        let result = if buffer_id == self.active_buffer_id() {
+            self.save()
+        } else {
+            // The user switched buffers while waiting; write the original one.
+            self.save_buffer(buffer_id)
+        };
+        if let Some(w) = warning {
+            self.message = format!("{w} — {}", self.message);
+        }
+        if self.quit_after_format_save {
+            self.quit_after_format_save = false;
+            self.format_save_quit_ready = result.is_ok();
+        }
+    }
+
+    /// Write a buffer other than the active one to its file.
+    fn save_buffer(&mut self, buffer_id: BufferId) -> Result<(), String> {
+        let path = self
+            .buffer_manager
+            .get(buffer_id)
+            .and_then(|s| s.file_path.clone())
+            .unwrap_or_default();
+        match self.write_buffer(buffer_id) {
+            Ok(line_count) => {
+                let rel = self.copy_relative_path(&path);
+                self.message = format!("\"{}\" {}L written", rel, line_count);
+                self.lsp_did_save(buffer_id);
+                self.swap_delete_for_buffer(buffer_id);
+                self.swap_write_needed.remove(&buffer_id);
+                self.undo_file_write_for_buffer(buffer_id);
+                Ok(())
+            }
+            Err(e) => {
+                self.set_error_message(e);
+                Err(self.message.clone())
+            }
+        }
+    }
+
+    /// Save modified buffers when the editor window loses focus
+    /// (`autosave = "focus"`).  Skipped mid-edit, as for `tick_autosave`.
+    /// Called by the GTK and TUI backends; returns true when a redraw is
+    /// needed.
+    pub fn on_focus_lost(&mut self) -> bool {
+        self.settings.autosave == "focus" && self.autosave_idle() && self.autosave()
+    }
+
+    /// Whether no edit is under way that a save would cut short: saving
+    /// trims or formats the buffer and closes the current undo group.
+    fn autosave_idle(&self) -> bool {
+        self.mode == Mode::Normal && self.pending_key.is_none() && self.pending_operator.is_none()
+    }
+
+    /// Save modified buffers once typing has paused for `autosave_interval`
+    /// milliseconds in Normal mode (`autosave = "interval"`), and expire the
+    /// status-line indicator.  Called every poll tick; returns true when a
+    /// redraw is needed.
+    pub fn tick_autosave(&mut self) -> bool {
+        let expired = self
+            .autosaved_at
+            .is_some_and(|t| t.elapsed() >= AUTOSAVE_INDICATOR);
+        if expired {
+            self.autosaved_at = None;
+        }
+        let idle = std::time::Duration::from_millis(u64::from(self.settings.autosave_interval));
+        let due = self.settings.autosave == "interval"
+            && self.autosave_key_at.is_some_and(|t| t.elapsed() >= idle)
+            && self.autosave_idle();
+        if !due {
+            return expired;
+        }
+        // One attempt per pause, so a failing write isn't retried every tick.
+        self.autosave_key_at = None;
+        self.autosave() || expired
+    }
+
+    /// Write every modified buffer that has a file name, isn't read-only and
+    /// hasn't changed on disk.  The active buffer takes the normal save path,
+    /// including format-on-save.  Success only shows the status-line
+    /// indicator; errors replace the message.  Returns true if anything was
+    /// written.
+    fn autosave(&mut self) -> bool {
+        if self.format_on_save_pending.is_some() {
+            return false;
+        }
+        let ids: Vec<BufferId> = self
+            .buffer_manager
+            .iter()
+            .filter(|(_, s)| {
+                s.dirty && s.file_path.is_some() && !s.read_only && !s.changed_on_disk()
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if ids.is_empty() {
+            return false;
+        }
+        let message = std::mem::take(&mut self.message);
+        let active = self.active_buffer_id();
+        let mut errors = Vec::new();
+        for id in ids {
+            let result = if id == active {
+                self.save_with_format(false)
+            } else {
+                self.save_buffer(id)
+            };
+            if let Err(e) = result {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() {
+            self.message = message;
+            self.autosaved_at = Some(std::time::Instant::now());
+        } else {
+            self.message = errors.join("; ");
+        }
+        true
+    }
+
+    /// Save unformatted if a format-on-save request has outlived its deadline.
+    /// Called every poll tick; returns true when a save happened (redraw needed).
+    pub(crate) fn check_format_on_save_timeout(&mut self) -> bool {
+        match self.format_on_save_deadline {
+            Some(deadline) if std::time::Instant::now() >= deadline => {
+                // Ignore the response if it still turns up.
+                self.lsp_pending_formatting = None;
+                self.lsp_formatting_buffer = None;
+                self.finish_format_on_save(Some("Formatting timed out, saved unformatted"));
+                true
+            }
+            _ => false,
         }
-        result
     }
 
     // =======================================================================
@@ -428,6 +940,7 @@ impl Engine {
         if let Some(path) = self
             .buffer_manager
             .get(buffer_id)
+            .filter(|s| !s.large_file)
             .and_then(|s| s.file_path.clone())
         {
             let diff = git::compute_file_diff(&path);
@@ -688,7 +1201,10 @@ impl Engine {
         } else {
             // File — open in current window (replacing netrw buffer)
             let netrw_buf_id = self.active_buffer_id();
-            let buf_id = match self.buffer_manager.open_file(&path) {
+            let buf_id = match self
+                .buffer_manager
+                .open_file(&path, &self.cwd, &self.settings)
+            {
                 Ok(id) => id,
                 Err(e) => {
                     self.message = format!("Error: {}", e);
@@ -704,7 +1220,7 @@ impl Engine {
             if !still_used {
                 self.buffer_manager.remove(netrw_buf_id);
             }
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buf_id, &path);
             self.lsp_did_open(buf_id);
             EngineAction::None
         }
@@ -803,9 +1319,7 @@ impl Engine {
                 self.windows.remove(&left_win);
                 // Remove from layout.
                 let tab = self.active_tab_mut();
-                if let Some(new_layout) = tab.layout.remove(left_win) {
-                    tab.layout = new_layout;
-                }
+                tab.remove_window(left_win);
                 // Delete the scratch buffer if nothing else references it.
                 let still_used = self.windows.values().any(|w| w.buffer_id == left_buf);
                 if !still_used {
@@ -1030,9 +1544,7 @@ impl Engine {
                 let left_buf = self.windows[&left_win].buffer_id;
                 self.windows.remove(&left_win);
                 let tab = self.active_tab_mut();
-                if let Some(new_layout) = tab.layout.remove(left_win) {
-                    tab.layout = new_layout;
-                }
+                tab.remove_window(left_win);
                 let still_used = self.windows.values().any(|w| w.buffer_id == left_buf);
                 if !still_used {
                     let _ = self.buffer_manager.delete(left_buf, true);
@@ -1308,25 +1820,34 @@ impl Engine {
             self.blame_annotations_active = false;
             self.editor_hover_content.clear();
             self.blame_rx = None;
+            self.blame_debounce = None;
             self.message = "Inline blame off".to_string();
             return;
         }
-        let file = match self.file_path() {
-            Some(p) => p.to_path_buf(),
-            None => {
-                self.message = "No file".to_string();
-                return;
-            }
-        };
-        let repo_root = match crate::core::git::find_repo_root(&file) {
-            Some(r) => r,
-            None => {
-                self.message = "Not a git repository".to_string();
-                return;
-            }
-        };
-        // Get buffer contents for unsaved changes.
         let bid = self.active_window().buffer_id;
+        if let Some(entries) = self.blame_cache.get(&bid).cloned() {
+            self.apply_blame_annotations(&entries);
+            self.message = format!("Inline blame on ({} lines)", entries.len());
+            return;
+        }
+        if let Err(msg) = self.request_blame(bid) {
+            self.message = msg.to_string();
+            return;
+        }
+        self.message = "Loading blame…".to_string();
+    }
+
+    /// Spawn `git blame --porcelain` for buffer `bid` on a background thread;
+    /// the result arrives through [`Engine::poll_blame`].  Unsaved changes are
+    /// piped in so line numbers match the buffer.
+    fn request_blame(&mut self, bid: BufferId) -> Result<(), &'static str> {
+        let file = self
+            .buffer_manager
+            .get(bid)
+            .and_then(|s| s.file_path.clone())
+            .ok_or("No file")?;
+        let repo_root = crate::core::git::find_repo_root(&file).ok_or("Not a git repository")?;
+        // Get buffer contents for unsaved changes.
         let buf_content = if self
             .buffer_manager
             .get(bid)
@@ -1353,15 +1874,28 @@ impl Engine {
                 crate::core::git::blame_file_structured(&repo, &f, buf_content.as_deref());
             let _ = tx.send(entries);
         });
-        self.blame_rx = Some(rx);
-        self.message = "Loading blame…".to_string();
+        self.blame_rx = Some((bid, rx));
+        Ok(())
     }
 
     /// Poll for async blame results. Call from backend event loops.
+    /// Also starts a debounced re-blame once edits have paused.
     /// Returns true if blame data was applied (triggers redraw).
     pub fn poll_blame(&mut self) -> bool {
-        let entries = match self.blame_rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
-            Some(e) => e,
+        if let Some((bid, t)) = self.blame_debounce {
+            if t.elapsed() >= BLAME_DEBOUNCE {
+                self.blame_debounce = None;
+                if self.blame_annotations_active {
+                    let _ = self.request_blame(bid);
+                }
+            }
+        }
+        let (bid, entries) = match self
+            .blame_rx
+            .as_ref()
+            .and_then(|(bid, rx)| rx.try_recv().ok().map(|e| (*bid, e)))
+        {
+            Some(r) => r,
             None => return false,
         };
         self.blame_rx = None;
@@ -1369,6 +1903,27 @@ impl Engine {
             self.message = "git blame returned no data".to_string();
             return true;
         }
+        let refresh = self.blame_annotations_active;
+        self.blame_cache.insert(bid, entries.clone());
+        // The user switched buffers while blame was running: keep it cached.
+        if bid != self.active_buffer_id() {
+            return false;
+        }
+        self.apply_blame_annotations(&entries);
+        if !refresh {
+            self.message = format!("Inline blame on ({} lines)", entries.len());
+        }
+        true
+    }
+
+    /// Show blame `entries` for the active buffer as inline annotations.
+    /// Lines git reports as uncommitted read "(modified)" while the buffer
+    /// has unsaved changes.
+    pub(crate) fn apply_blame_annotations(&mut self, entries: &[crate::core::git::BlameInfo]) {
+        let dirty = self
+            .buffer_manager
+            .get(self.active_buffer_id())
+            .is_some_and(|s| s.dirty);
         // Find repo root for commit URL generation.
         let repo_root = self
             .file_path()
@@ -1377,7 +1932,8 @@ impl Engine {
         self.editor_hover_content.clear();
         for (i, info) in entries.iter().enumerate() {
             if info.not_committed {
-                self.line_annotations.insert(i, "Not committed".to_string());
+                let label = if dirty { "(modified)" } else { "Not committed" };
+                self.line_annotations.insert(i, label.to_string());
             } else {
                 self.line_annotations.insert(
                     i,
@@ -1403,8 +1959,29 @@ impl Engine {
             }
         }
         self.blame_annotations_active = true;
-        self.message = format!("Inline blame on ({} lines)", entries.len());
-        true
+    }
+
+    /// The active buffer was edited: drop its cached blame and, when blame is
+    /// showing, re-run it against the new text once edits pause for
+    /// `BLAME_DEBOUNCE` (deferred while in Insert mode, where annotations are
+    /// hidden anyway).
+    pub(crate) fn blame_buffer_changed(&mut self) {
+        let bid = self.active_buffer_id();
+        self.blame_cache.remove(&bid);
+        if self.blame_annotations_active && self.mode != Mode::Insert {
+            self.blame_debounce = Some((bid, std::time::Instant::now()));
+        }
+    }
+
+    /// Leaving Insert mode: refresh blame that went stale while typing.
+    pub(crate) fn blame_refresh_if_stale(&mut self) {
+        let bid = self.active_buffer_id();
+        if self.blame_annotations_active
+            && self.blame_rx.is_none()
+            && !self.blame_cache.contains_key(&bid)
+        {
+            let _ = self.request_blame(bid);
+        }
     }
 
     /// Open the diff peek popup for the hunk under the cursor on the current buffer.
@@ -1609,6 +2186,113 @@ impl Engine {
         }
     }
 
+    /// `:GitStageHunk` — stage the hunk under the cursor in a regular file
+    /// buffer (falls back to `:Ghs` behaviour in a `:Gdiff` buffer).  The
+    /// patch comes from the saved file, so unsaved edits are never staged;
+    /// the cursor line is mapped onto the saved text first.
+    pub(crate) fn cmd_git_stage_file_hunk(&mut self) -> EngineAction {
+        if self.active_buffer_state().source_file.is_some() {
+            return self.cmd_git_stage_hunk();
+        }
+        let bid = self.active_buffer_id();
+        let Some(path) = self.file_path().map(|p| p.to_path_buf()) else {
+            self.message = "No file".to_string();
+            return EngineAction::Error;
+        };
+        let Some(repo_dir) = git::find_repo_root(&path) else {
+            self.message = "Not a git repository".to_string();
+            return EngineAction::Error;
+        };
+        let hunks = git::compute_unstaged_hunks(&path);
+        if hunks.is_empty() {
+            self.message = "No unstaged changes".to_string();
+            return EngineAction::None;
+        }
+        let mut line = self.view().cursor.line;
+        if self.active_buffer_state().dirty {
+            if let Ok(saved) = std::fs::read_to_string(&path) {
+                let buf_text = self.buffer().to_string();
+                let saved_lines: Vec<&str> = saved.lines().collect();
+                let buf_lines: Vec<&str> = buf_text.lines().collect();
+                let (da, db) = lcs_diff(&saved_lines, &buf_lines);
+                line = saved_line_for(&diff_hunk_ranges(&da, &db), line);
+            }
+        }
+        let Some(idx) = git::hunk_for_line(&hunks, line) else {
+            self.message = "No unstaged hunk at cursor".to_string();
+            return EngineAction::None;
+        };
+        let h = &hunks[idx];
+        match git::stage_hunk(&repo_dir, &h.file_header, &h.hunk) {
+            Ok(()) => {
+                self.refresh_git_diff(bid);
+                self.compute_diff();
+                self.message = format!("Hunk {} staged", idx + 1);
+                EngineAction::None
+            }
+            Err(e) => {
+                self.message = format!("Stage hunk failed: {e}");
+                EngineAction::Error
+            }
+        }
+    }
+
+    /// `:GitUndoHunk` — replace the hunk under the cursor with its `HEAD`
+    /// content.  Works on the buffer text (including unsaved edits) as a
+    /// single undoable change; nothing is written to disk.
+    pub(crate) fn cmd_git_undo_hunk(&mut self) -> EngineAction {
+        let Some(path) = self.file_path().map(|p| p.to_path_buf()) else {
+            self.message = "No file".to_string();
+            return EngineAction::Error;
+        };
+        let Some(repo_dir) = git::find_repo_root(&path) else {
+            self.message = "Not a git repository".to_string();
+            return EngineAction::Error;
+        };
+        let rel_path = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.clone())
+            .strip_prefix(&repo_dir)
+            .map(|r| r.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let Some(head) = git::show_file_at_ref(&repo_dir, "HEAD", &rel_path) else {
+            self.message = "File is not in HEAD".to_string();
+            return EngineAction::Error;
+        };
+        let buf_text = self.buffer().to_string();
+        let head_lines: Vec<&str> = head.lines().collect();
+        let buf_lines: Vec<&str> = buf_text.lines().collect();
+        let (da, db) = lcs_diff(&head_lines, &buf_lines);
+        let hunks = diff_hunk_ranges(&da, &db);
+        let Some(idx) = hunk_at_line(&hunks, self.view().cursor.line) else {
+            self.message = "No hunk at cursor".to_string();
+            return EngineAction::None;
+        };
+        let (a, b) = hunks[idx].clone();
+        let total = self.buffer().content.len_lines();
+        let start = self.buffer().line_to_char(b.start.min(total - 1));
+        let end = if b.end < total {
+            self.buffer().line_to_char(b.end)
+        } else {
+            self.buffer().len_chars()
+        };
+        let mut text: String = head_lines[a].iter().map(|l| format!("{l}\n")).collect();
+        // Replacing the final line of a file without a trailing newline.
+        if b.end >= buf_lines.len() && !buf_text.ends_with('\n') && !b.is_empty() {
+            text.pop();
+        }
+        self.start_undo_group();
+        self.delete_with_undo(start, end);
+        self.insert_with_undo(start, &text);
+        self.finish_undo_group();
+        self.view_mut().cursor.line = b.start.min(self.buffer().len_lines().saturating_sub(1));
+        self.view_mut().cursor.col = 0;
+        self.set_dirty(true);
+        self.update_syntax();
+        self.message = "Hunk reverted to HEAD".to_string();
+        EngineAction::None
+    }
+
     /// Helper: resolve the git repo dir from either the current file's directory or cwd.
     pub(crate) fn git_dir(&self) -> PathBuf {
         self.file_path()
@@ -1671,6 +2355,10 @@ impl Engine {
                 "  r                 Rename — type new name, Enter to confirm\n",
                 "  M                 Move — type destination dir, Enter to confirm\n",
                 "  D                 Delete — y to confirm, n to cancel\n",
+                "  .                 Show / hide dotfiles\n",
+                "\n",
+                "Hide more entries with :set explorerignore=target/,*.o and\n",
+                ":set respectgitignore.\n",
                 "\n",
                 "The activity bar (left edge) also provides clickable icons\n",
                 "for the explorer, search panel, and settings.\n",
@@ -1808,7 +2496,7 @@ impl Engine {
                 EngineAction::None
             }
             Err(e) => {
-                self.message = e;
+                self.set_error_message(e);
                 EngineAction::Error
             }
         }
@@ -1830,7 +2518,7 @@ impl Engine {
                 EngineAction::None
             }
             Err(e) => {
-                self.message = e;
+                self.set_error_message(e);
                 EngineAction::Error
             }
         }
@@ -1849,7 +2537,7 @@ impl Engine {
                 EngineAction::None
             }
             Err(e) => {
-                self.message = e;
+                self.set_error_message(e);
                 EngineAction::Error
             }
         }
@@ -1923,6 +2611,18 @@ impl Engine {
         Ok(())
     }
 
+    /// Flip `show_hidden_files` (explorer `.` key) and persist it.  Backends
+    /// rebuild their tree when the explorer settings change.
+    pub fn toggle_show_hidden_files(&mut self) {
+        self.settings.show_hidden_files = !self.settings.show_hidden_files;
+        let _ = self.settings.save();
+        self.message = if self.settings.show_hidden_files {
+            "Showing hidden files".to_string()
+        } else {
+            "Hiding hidden files".to_string()
+        };
+    }
+
     /// Start inline rename for the given path in the explorer sidebar.
     ///
     /// Pre-fills the input with the current filename and places the cursor
@@ -2009,7 +2709,7 @@ impl Engine {
                             self.message = format!("Renamed to '{}'", new_name);
                         }
                         Err(e) => {
-                            self.message = e;
+                            self.set_error_message(e);
                         }
                     }
                 }
@@ -2217,7 +2917,7 @@ impl Engine {
                                 &path,
                                 crate::core::engine::OpenMode::Permanent,
                             ) {
-                                self.message = e;
+                                self.set_error_message(e);
                             }
                         }
                         Err(e) => {
@@ -2533,9 +3233,9 @@ impl Engine {
             self.clear_diff_labels(a, b);
         }
         self.diff_window_pair = None;
+        self.diff_unified = None;
         self.diff_results.clear();
         self.diff_aligned.clear();
-        self.diff_aligned.clear();
         self.diff_unchanged_hidden = false;
         self.message = "Diff off".to_string();
         EngineAction::None
@@ -2557,9 +3257,87 @@ impl Engine {
         EngineAction::None
     }
 
+    /// Show the current window as a unified diff against `path`: lines added
+    /// relative to `path` are highlighted in place and lines only in `path`
+    /// appear as read-only virtual rows.  Public for use by UI backends.
+    pub fn cmd_diffunified(&mut self, path: &Path) -> EngineAction {
+        let base = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.set_error_message(format!("E484: Can't open file {}: {}", path.display(), e));
+                return EngineAction::Error;
+            }
+        };
+        if self.diff_window_pair.is_some() || self.diff_unified.is_some() {
+            self.cmd_diffoff();
+        }
+        let win = self.active_window_id();
+        self.diff_unified = Some(UnifiedDiff {
+            window: win,
+            base_lines: ropey::Rope::from_str(&base)
+                .lines()
+                .map(|l| l.to_string())
+                .collect(),
+        });
+        self.compute_diff();
+
+        // Put the cursor on the first hunk (the buffer line at or after it).
+        let results = self.diff_results.get(&win).cloned().unwrap_or_default();
+        let aligned = self.diff_aligned.get(&win).cloned().unwrap_or_default();
+        let first_change = aligned
+            .iter()
+            .position(|e| {
+                e.removed_line.is_some()
+                    || e.source_line
+                        .is_some_and(|l| results.get(l) != Some(&DiffLine::Same))
+            })
+            .and_then(|start| aligned[start..].iter().find_map(|e| e.source_line));
+        if let Some(line) = first_change {
+            self.view_mut().cursor.line = line;
+            self.view_mut().cursor.col = 0;
+            self.scroll_cursor_center();
+        }
+        self.message = format!("Unified diff: {}", path.display());
+        EngineAction::None
+    }
+
+    /// Internal: recompute the `:diffunified` view against its base file.
+    fn compute_unified_diff(&mut self) {
+        let Some(unified) = &self.diff_unified else {
+            return;
+        };
+        let win = unified.window;
+        let buf_lines: Vec<String> = self
+            .windows
+            .get(&win)
+            .and_then(|w| self.buffer_manager.get(w.buffer_id))
+            .map(|s| s.buffer.content.lines().map(|l| l.to_string()).collect())
+            .unwrap_or_default();
+        let base_refs: Vec<&str> = unified.base_lines.iter().map(String::as_str).collect();
+        let buf_refs: Vec<&str> = buf_lines.iter().map(String::as_str).collect();
+        let (mut da, mut db) = lcs_diff(&base_refs, &buf_refs);
+        merge_short_same_runs(&mut da, DiffLine::Removed);
+        merge_short_same_runs(&mut db, DiffLine::Added);
+        self.diff_aligned.insert(win, build_unified_diff(&da, &db));
+        self.diff_results.insert(win, db);
+    }
+
+    /// Drop the unified diff if `win` was showing it (window closed).
+    pub(crate) fn diff_unified_forget_window(&mut self, win: WindowId) {
+        if self.diff_unified.as_ref().is_some_and(|u| u.window == win) {
+            self.diff_unified = None;
+            self.diff_results.remove(&win);
+            self.diff_aligned.remove(&win);
+        }
+    }
+
     /// Internal: compute the LCS diff between the two diff windows and store
     /// results in `self.diff_results`.
     pub(crate) fn compute_diff(&mut self) {
+        if self.diff_unified.is_some() {
+            self.compute_unified_diff();
+            return;
+        }
         let (a_win, b_win) = match self.diff_window_pair {
             Some(pair) => pair,
             None => return,
@@ -2997,6 +3775,41 @@ impl Engine {
         }
     }
 
+    /// Open a file named on the command line.  A directory becomes the
+    /// workspace and explorer root, like netrw: no files are restored, the
+    /// window holds an empty `[No Name]` buffer and the explorer takes focus.
+    /// A file loads into the current window with the cursor on the target's
+    /// line and column, centred.
+    pub fn open_file_target(&mut self, target: &paths::FileTarget) {
+        if target.path.is_dir() {
+            self.switch_folder(&target.path, false);
+            self.explorer_focus_on_start = true;
+            return;
+        }
+        match self.open_file_with_mode(&target.path, OpenMode::Permanent) {
+            Ok(()) => self.goto_file_position(target),
+            Err(e) => self.set_error_message(e),
+        }
+    }
+
+    /// Put the cursor on `target`'s 1-based line and column (the first
+    /// non-blank without a column), clamped to the buffer, and centre it.
+    pub(crate) fn goto_file_position(&mut self, target: &paths::FileTarget) {
+        let Some(line) = target.line else {
+            return;
+        };
+        let line = line
+            .saturating_sub(1)
+            .min(self.buffer().len_lines().saturating_sub(1));
+        let col = match target.col {
+            Some(col) => col.saturating_sub(1),
+            None => self.first_non_blank_col(line),
+        };
+        self.view_mut().cursor = Cursor { line, col };
+        self.clamp_cursor_col();
+        self.scroll_cursor_center();
+    }
+
     /// Open a file in the current window with the given mode.
     ///
     /// - `Preview`: Replaces any existing preview buffer. The tab shows italic/dimmed.
@@ -3009,7 +3822,7 @@ impl Engine {
 
         let buffer_id = self
             .buffer_manager
-            .open_file(path)
+            .open_file(path, &self.cwd, &self.settings)
             .map_err(|e| format!("Error: {}", e))?;
         self.buffer_manager
             .apply_language_map(buffer_id, &self.settings.language_map);
@@ -3028,7 +3841,7 @@ impl Engine {
                 self.buffer_manager.alternate_buffer = Some(current);
             }
             self.switch_window_buffer(buffer_id);
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buffer_id, path);
             return Ok(());
         }
 
@@ -3061,8 +3874,9 @@ impl Engine {
         }
         self.switch_window_buffer(buffer_id);
         self.refresh_git_diff(buffer_id);
-        self.message = format!("\"{}\"", path.display());
+        self.message = self.opened_message(buffer_id, path);
         self.lsp_did_open(buffer_id);
+        self.undo_file_restore_for_buffer(buffer_id);
         // Watch the file for external changes
         self.watch_file(path);
         Ok(())
@@ -3271,6 +4085,12 @@ impl Engine {
     /// Open a folder as the new working directory.  Clears all buffers/tabs,
     /// resets the explorer root, and loads any per-project session state.
     pub fn open_folder(&mut self, path: &Path) {
+        self.switch_folder(path, true);
+    }
+
+    /// [`open_folder`](Self::open_folder), reopening the folder's session
+    /// files only when `restore_files` is set.
+    fn switch_folder(&mut self, path: &Path, restore_files: bool) {
         let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
 
         // Save current per-workspace session before switching
@@ -3279,6 +4099,9 @@ impl Engine {
         }
 
         // Restore user settings baseline before applying any new folder overlay
+        if let Some(base) = self.project_settings_base.take() {
+            self.settings = *base;
+        }
         if let Some(base) = self.base_settings.take() {
             self.settings = *base;
         }
@@ -3337,6 +4160,9 @@ impl Engine {
         self.workspace_root = Some(canonical.clone());
         let _ = std::env::set_current_dir(&canonical);
 
+        // Layer the new project's .vimcode.json over the settings
+        self.apply_project_settings();
+
         // Update git branch
         self.git_branch = git::current_branch(&canonical);
 
@@ -3351,17 +4177,156 @@ impl Engine {
         self.session.add_recent_workspace(&canonical);
 
         // Re-open session files
-        for fp in &open_files {
-            self.open_file_in_tab(fp);
-        }
-        // Focus the previously active file
-        if let Some(ref af) = active_file {
-            self.open_file_in_tab(af);
+        if restore_files {
+            for fp in &open_files {
+                self.open_file_in_tab(fp);
+            }
+            // Focus the previously active file
+            if let Some(ref af) = active_file {
+                self.open_file_in_tab(af);
+            }
         }
 
         self.message = format!("Opened folder: {}", canonical.display());
     }
 
+    /// `:cd [dir]` / `:lcd [dir]`.  No argument means the home directory and
+    /// `-` the previous global directory.
+    pub(crate) fn change_directory_command(&mut self, local: bool, arg: &str) {
+        let target = match arg {
+            "" => paths::home_dir(),
+            "-" => match self.prev_cwd.clone() {
+                Some(dir) => dir,
+                None => {
+                    self.set_error_message("E186: No previous directory".to_string());
+                    return;
+                }
+            },
+            _ => paths::expand_tilde(arg),
+        };
+        let result = if local {
+            self.change_window_directory(&target)
+        } else {
+            self.change_directory(&target)
+        };
+        match result {
+            Ok(()) => self.message = self.effective_cwd().display().to_string(),
+            Err(e) => self.set_error_message(e),
+        }
+    }
+
+    /// Make `path` the global working directory (`:cd`).  Unlike
+    /// [`open_folder`](Self::open_folder) the open buffers are kept; the file
+    /// explorer, project search and relative paths follow the new directory.
+    /// Clears the active window's `:lcd` directory, as in Vim.
+    pub fn change_directory(&mut self, path: &Path) -> Result<(), String> {
+        let dir = self.existing_directory(path)?;
+        let _ = std::env::set_current_dir(&dir);
+        if dir != self.cwd {
+            self.prev_cwd = Some(std::mem::replace(&mut self.cwd, dir));
+        }
+        self.active_window_mut().local_cwd = None;
+        self.apply_project_settings();
+        self.git_branch = git::current_branch(&self.cwd);
+        self.cwd_changed = true;
+        Ok(())
+    }
+
+    /// Give the active window its own working directory (`:lcd`).  Splits
+    /// made from it inherit the directory.
+    pub fn change_window_directory(&mut self, path: &Path) -> Result<(), String> {
+        let dir = self.existing_directory(path)?;
+        self.active_window_mut().local_cwd = Some(dir);
+        Ok(())
+    }
+
+    /// The active window's working directory: its `:lcd` directory if it has
+    /// one, otherwise the global cwd.
+    pub fn effective_cwd(&self) -> &Path {
+        self.active_window()
+            .local_cwd
+            .as_deref()
+            .unwrap_or(&self.cwd)
+    }
+
+    /// Resolve a relative path typed in the active window against its `:lcd`
+    /// directory.  Without one the path is returned unchanged, since the
+    /// process directory already follows `:cd`.
+    pub(crate) fn resolve_path(&self, path: PathBuf) -> PathBuf {
+        match &self.active_window().local_cwd {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        }
+    }
+
+    /// Canonicalize a `:cd` target, relative to the active window's directory.
+    fn existing_directory(&self, path: &Path) -> Result<PathBuf, String> {
+        let path = self.effective_cwd().join(path);
+        match path.canonicalize() {
+            Ok(dir) if dir.is_dir() => Ok(dir),
+            _ => Err(format!(
+                "E344: Can't find directory \"{}\" in cdpath",
+                path.display()
+            )),
+        }
+    }
+
+    /// Layer the project's `.vimcode.json` (the nearest one from the cwd up to
+    /// the git root) over the global settings.  A malformed file is reported
+    /// and the global settings stay in effect.  Returns false in that case.
+    pub fn apply_project_settings(&mut self) -> bool {
+        if let Some(base) = self.project_settings_base.take() {
+            self.settings = *base;
+        }
+        self.project_settings_path = Settings::find_project_settings(&self.cwd);
+        let Some(path) = self.project_settings_path.clone() else {
+            self.project_settings_mtime = None;
+            return true;
+        };
+        self.project_settings_mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let merged = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| self.settings.with_project_overrides(&json));
+        match merged {
+            Ok(settings) => {
+                let base = std::mem::replace(&mut self.settings, settings);
+                self.project_settings_base = Some(Box::new(base));
+                true
+            }
+            Err(e) => {
+                self.message =
+                    format!("Ignoring {}: {} (using global settings)", path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Replace the global settings (e.g. after settings.json changed on disk),
+    /// keeping the project `.vimcode.json` on top.
+    pub fn set_global_settings(&mut self, settings: Settings) {
+        self.project_settings_base = None;
+        self.settings = settings;
+        self.apply_project_settings();
+        self.ensure_spell_checker();
+    }
+
+    /// Re-apply the project settings when its `.vimcode.json` is created,
+    /// edited or removed.  Returns true if the settings were reloaded.
+    pub fn check_project_settings_changed(&mut self) -> bool {
+        let path = Settings::find_project_settings(&self.cwd);
+        let mtime = path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+        if path == self.project_settings_path && mtime == self.project_settings_mtime {
+            return false;
+        }
+        if self.apply_project_settings() {
+            self.message = "Project settings reloaded".to_string();
+        }
+        self.ensure_spell_checker();
+        true
+    }
+
     /// Parse and load a `.vimcode-workspace` JSON file.
     pub fn open_workspace(&mut self, ws_path: &Path) {
         let content = match std::fs::read_to_string(ws_path) {
@@ -3508,6 +4473,7 @@ impl Engine {
             }
         }
         ws_session.active_file = self.file_path().cloned();
+        ws_session.cwd = Some(self.cwd.clone());
         ws_session.file_positions = self.session.file_positions.clone();
         // Save active_group as index position in leaf order for backward compat
         ws_session.active_group = group_ids
@@ -3601,7 +4567,7 @@ impl Engine {
                         let wid = self.new_window_id();
                         let buf_id = self
                             .buffer_manager
-                            .open_file(path)
+                            .open_file(path, &self.cwd, &self.settings)
                             .unwrap_or_else(|_| self.buffer_manager.create());
                         let mut w = Window::new(wid, buf_id);
                         let view = self.restore_file_position(buf_id);
@@ -63,8 +63,13 @@ impl Engine {
             }
         }
 
-        // Handle :term / :terminal — open integrated terminal
+        // Handle :term / :terminal — open a shell in a horizontal split
         if cmd == "terminal" {
+            self.open_terminal_window();
+            return EngineAction::None;
+        }
+        // Handle :TerminalPanel — open a new tab in the bottom terminal panel
+        if cmd == "TerminalPanel" {
             return EngineAction::OpenTerminal;
         }
 
@@ -79,14 +84,34 @@ impl Engine {
         if cmd == "SaveWorkspaceAs" || cmd == "save_workspace_as_dialog" {
             return EngineAction::SaveWorkspaceAsDialog;
         }
-        if let Some(path_str) = cmd.strip_prefix("cd ").map(|s| s.trim()) {
-            let path = Path::new(path_str);
-            let target = if path.is_absolute() {
-                path.to_path_buf()
+        // :earlier / :later — step through undo history by count or time.
+        let (word, arg) = cmd.split_once(' ').unwrap_or((cmd, ""));
+        let is_earlier = word.len() >= 2 && "earlier".starts_with(word);
+        let is_later = word.len() >= 3 && "later".starts_with(word);
+        if is_earlier || is_later {
+            match parse_undo_step(arg.trim()) {
+                Some(step) => {
+                    self.undo_time_travel(step, is_later);
+                    self.refresh_md_previews();
+                }
+                None => self.set_error_message(format!("E475: Invalid argument: {}", arg.trim())),
+            }
+            return EngineAction::None;
+        }
+        if word == "cd" || word == "lcd" {
+            self.change_directory_command(word == "lcd", arg.trim());
+            return EngineAction::None;
+        }
+        // :mks[ession][!] {name} / :so[urce] {name} — named sessions
+        if word == "mksession" || word == "mksession!" || word == "source" {
+            let name = arg.trim();
+            if name.is_empty() {
+                self.set_error_message("E471: Argument required".to_string());
+            } else if word == "source" {
+                self.source_session(name);
             } else {
-                self.cwd.join(path)
-            };
-            self.open_folder(&target);
+                self.make_session(name, word.ends_with('!'));
+            }
             return EngineAction::None;
         }
 
@@ -273,6 +298,11 @@ impl Engine {
             return EngineAction::None;
         }
 
+        // Handle :Diagnostics — all open buffers' diagnostics in the quickfix list
+        if cmd == "Diagnostics" {
+            return self.diagnostics_to_quickfix();
+        }
+
         // Handle :LspInfo — show running LSP servers (● marks active for current buffer)
         if cmd == "LspInfo" {
             let buf_lang = self
@@ -363,14 +393,18 @@ impl Engine {
             return EngineAction::None;
         }
 
-        // Handle :Lformat — LSP format current buffer
-        if cmd == "Lformat" {
+        // Handle :Lformat / :LspFormat — LSP format current buffer
+        if cmd == "Lformat" || cmd == "LspFormat" {
             self.lsp_format_current();
             return EngineAction::None;
         }
 
-        // Handle :Rename <newname> — LSP rename symbol at cursor
-        if let Some(new_name) = cmd.strip_prefix("Rename").map(|s| s.trim()) {
+        // Handle :Rename / :LspRename <newname> — LSP rename symbol at cursor
+        if let Some(new_name) = cmd
+            .strip_prefix("LspRename")
+            .or_else(|| cmd.strip_prefix("Rename"))
+            .map(|s| s.trim())
+        {
             if new_name.is_empty() {
                 // Pre-fill with word under cursor for interactive editing
                 let word = self.word_under_cursor().unwrap_or_default();
@@ -427,6 +461,14 @@ impl Engine {
             self.message = "Usage: :diffsplit <file>".to_string();
             return EngineAction::None;
         }
+        if let Some(path_str) = cmd.strip_prefix("diffunified ") {
+            let path = paths::expand_tilde(path_str.trim());
+            return self.cmd_diffunified(&path);
+        }
+        if cmd == "diffunified" {
+            self.message = "Usage: :diffunified <file>".to_string();
+            return EngineAction::None;
+        }
         if cmd == "DiffNext" {
             self.diff_jump_next();
             return EngineAction::None;
@@ -480,13 +522,24 @@ impl Engine {
             return self.cmd_git_stage_hunk();
         }
 
+        if cmd == "GitStageHunk" {
+            return self.cmd_git_stage_file_hunk();
+        }
+        if cmd == "GitUndoHunk" {
+            return self.cmd_git_undo_hunk();
+        }
+
+        if cmd == "StripWhitespace" {
+            return self.cmd_strip_whitespace();
+        }
+
         // Handle :DiffPeek — open inline diff peek popup
         if cmd == "DiffPeek" {
             self.open_diff_peek();
             return EngineAction::None;
         }
 
-        if cmd == "ToggleBlame" || cmd == "Gib" {
+        if cmd == "ToggleBlame" || cmd == "Gib" || cmd == "GitBlame" {
             self.toggle_inline_blame();
             return EngineAction::None;
         }
@@ -674,10 +727,25 @@ impl Engine {
             }
             return EngineAction::None;
         }
+        // :nnoremap / :inoremap / :vnoremap (and the :nmap family — mappings
+        // never recurse) are shorthands for `:map <mode> …`.
+        for (prefix, mode) in [
+            ("nnoremap ", "n"),
+            ("nmap ", "n"),
+            ("inoremap ", "i"),
+            ("imap ", "i"),
+            ("vnoremap ", "v"),
+            ("xnoremap ", "v"),
+            ("vmap ", "v"),
+        ] {
+            if let Some(rest) = cmd.strip_prefix(prefix) {
+                return self.execute_command(&format!("map {mode} {}", rest.trim()));
+            }
+        }
         if let Some(rest) = cmd.strip_prefix("map ") {
             let rest = rest.trim();
             // :map n <C-/> :Commentary → add keymap
-            if parse_keymap_def(rest).is_some() {
+            if parse_keymap_def(rest, self.settings.leader).is_some() {
                 let entry = rest.to_string();
                 if !self.settings.keymaps.contains(&entry) {
                     self.settings.keymaps.push(entry.clone());
@@ -687,7 +755,7 @@ impl Engine {
                 self.message = format!("Mapped: {entry}");
             } else {
                 self.message =
-                    "Usage: :map <mode> <keys> :<command>  (e.g. :map n <C-/> :Commentary)"
+                    "Usage: :map <mode> <keys> {:<command>|<keys>}  (e.g. :map i jk <Esc>)"
                         .to_string();
             }
             return EngineAction::None;
@@ -719,6 +787,56 @@ impl Engine {
             return EngineAction::None;
         }
 
+        // ── :iabbrev / :unabbreviate — insert-mode abbreviations ─────────────────
+        if cmd == "iabbrev" || cmd == "abbreviate" {
+            if self.settings.abbreviations.is_empty() {
+                self.message = "No abbreviation found".to_string();
+            } else {
+                self.message = self
+                    .settings
+                    .abbreviations
+                    .iter()
+                    .map(|(lhs, rhs)| format!("{lhs} {rhs}"))
+                    .collect::<Vec<_>>()
+                    .join("  |  ");
+            }
+            return EngineAction::None;
+        }
+        if let Some(rest) = cmd
+            .strip_prefix("iabbrev ")
+            .or_else(|| cmd.strip_prefix("abbreviate "))
+        {
+            match rest.trim().split_once(char::is_whitespace) {
+                Some((lhs, rhs)) => {
+                    let rhs = rhs.trim().to_string();
+                    self.message = format!("Abbreviation: {lhs} {rhs}");
+                    self.settings.abbreviations.insert(lhs.to_string(), rhs);
+                    let _ = self.settings.save();
+                }
+                None => {
+                    let lhs = rest.trim();
+                    self.message = match self.settings.abbreviations.get(lhs) {
+                        Some(rhs) => format!("{lhs} {rhs}"),
+                        None => "No abbreviation found".to_string(),
+                    };
+                }
+            }
+            return EngineAction::None;
+        }
+        if let Some(lhs) = cmd
+            .strip_prefix("unabbreviate ")
+            .or_else(|| cmd.strip_prefix("iunabbrev "))
+        {
+            let lhs = lhs.trim();
+            if self.settings.abbreviations.remove(lhs).is_some() {
+                let _ = self.settings.save();
+                self.message = format!("Removed abbreviation: {lhs}");
+            } else {
+                self.set_error_message(format!("E24: No such abbreviation: {lhs}"));
+            }
+            return EngineAction::None;
+        }
+
         // ── Extension commands (:ExtInstall / :ExtRemove / :ExtRefresh / :ExtList /
         //                        :ExtEnable / :ExtDisable) ──────────────────────────────
         if let Some(subcmd) = cmd.strip_prefix("Ext").map(|s| s.trim()) {
@@ -851,14 +969,31 @@ impl Engine {
             return EngineAction::None;
         }
 
-        // Handle :e[dit]! — reload current file from disk (discard changes)
+        // Handle :e[dit][!] ++enc={enc} [file] — (re)read a file in a given encoding
+        if let Some(rest) = cmd
+            .strip_prefix("edit ++enc")
+            .or_else(|| cmd.strip_prefix("edit! ++enc"))
+        {
+            return self.edit_with_encoding(cmd.starts_with("edit!"), rest);
+        }
+
+        // Handle :e[dit]! — reload current file from disk (discard changes,
+        // keeping them undoable when the file is still the undo base)
         if cmd == "edit!" {
             let buf_id = self.active_buffer_id();
+            let cursor = self.view().cursor;
             let state = self.buffer_manager.get_mut(buf_id).unwrap();
-            match state.reload_from_disk() {
-                Ok(()) => {
+            match state.revert_to_disk(cursor) {
+                Ok(kept_history) => {
                     let name = state.display_name();
                     self.message = format!("\"{}\" reloaded", name);
+                    self.clamp_views_to_buffer(buf_id);
+                    self.lsp_dirty_buffers.insert(buf_id, true);
+                    if kept_history {
+                        let cursor = self.view().cursor;
+                        self.active_buffer_state_mut()
+                            .record_timeline_snapshot(cursor);
+                    }
                 }
                 Err(e) => {
                     self.message = format!("Error: {}", e);
@@ -873,32 +1008,84 @@ impl Engine {
                 self.message = "No file name".to_string();
                 return EngineAction::Error;
             }
-            return EngineAction::OpenFile(PathBuf::from(filename));
+            return EngineAction::OpenFile(self.resolve_path(paths::expand_tilde(filename)));
         }
 
-        // Handle :e[dit] <filename>
+        // Handle :e# — reopen the most recently used file that is no longer
+        // shown in any window (i.e. the last one closed).
+        if cmd == "edit#" || cmd == "edit #" {
+            let shown: Vec<PathBuf> = self
+                .windows
+                .values()
+                .filter_map(|w| self.buffer_manager.get(w.buffer_id)?.file_path.clone())
+                .map(|p| p.canonicalize().unwrap_or(p))
+                .collect();
+            let last = self
+                .buffer_manager
+                .recent_files
+                .iter()
+                .find(|p| p.is_file() && !shown.contains(p))
+                .cloned();
+            return match last {
+                Some(path) => {
+                    self.open_file_in_tab(&path);
+                    EngineAction::None
+                }
+                None => {
+                    self.set_error_message(
+                        "E194: No alternate file name to substitute for '#'".to_string(),
+                    );
+                    EngineAction::Error
+                }
+            };
+        }
+
+        // Handle :e[dit] <filename>[:line[:col]]
         if let Some(filename) = cmd.strip_prefix("edit ") {
             let filename = filename.trim();
             if filename.is_empty() {
                 self.message = "No file name".to_string();
                 return EngineAction::Error;
             }
-            return EngineAction::OpenFile(PathBuf::from(filename));
+            let mut target = paths::FileTarget::parse(filename);
+            if target.line.is_some() {
+                target.path = self.resolve_path(target.path);
+                if let Err(e) = self.open_file_with_mode(&target.path, OpenMode::Permanent) {
+                    self.set_error_message(e);
+                    return EngineAction::Error;
+                }
+                self.goto_file_position(&target);
+                return EngineAction::None;
+            }
+            return EngineAction::OpenFile(self.resolve_path(paths::expand_tilde(filename)));
         }
 
-        // Handle :b[uffer] <buffer>
+        // Handle :vie[w] [filename] — like :edit, but the buffer is read-only
+        if cmd == "view" || cmd.starts_with("view ") {
+            let filename = cmd["view".len()..].trim();
+            if !filename.is_empty() {
+                let path = self.resolve_path(paths::expand_tilde(filename));
+                if let Err(e) = self.open_file_with_mode(&path, OpenMode::Permanent) {
+                    self.set_error_message(e);
+                    return EngineAction::Error;
+                }
+            }
+            self.active_buffer_state_mut().read_only = true;
+            return EngineAction::None;
+        }
+
+        // Handle :b[uffer] <buffer> — a number, or a (fuzzy) name; bare
+        // `:b` opens the buffer picker.
+        if cmd == "buffer" {
+            self.open_picker(PickerSource::Buffers);
+            return EngineAction::None;
+        }
         if let Some(arg) = cmd.strip_prefix("buffer ") {
             let arg = arg.trim();
             if let Ok(num) = arg.parse::<usize>() {
                 self.goto_buffer(num);
-            } else if let Some(id) = self.buffer_manager.find_by_path(arg) {
-                let current = self.active_buffer_id();
-                if id != current {
-                    self.buffer_manager.alternate_buffer = Some(current);
-                    self.switch_window_buffer(id);
-                }
             } else {
-                self.message = format!("No matching buffer for {}", arg);
+                self.buffer_switch_fuzzy(arg);
             }
             return EngineAction::None;
         }
@@ -934,7 +1121,7 @@ impl Engine {
                     self.message = "Buffer deleted".to_string();
                 }
                 Err(e) => {
-                    self.message = e;
+                    self.set_error_message(e);
                     return EngineAction::Error;
                 }
             }
@@ -946,8 +1133,9 @@ impl Engine {
             let file = cmd
                 .strip_prefix("split")
                 .map(|s| s.trim())
-                .filter(|s| !s.is_empty());
-            self.split_window(SplitDirection::Horizontal, file.map(Path::new));
+                .filter(|s| !s.is_empty())
+                .map(|f| self.resolve_path(paths::expand_tilde(f)));
+            self.split_window(SplitDirection::Horizontal, file.as_deref());
             return EngineAction::None;
         }
 
@@ -956,8 +1144,9 @@ impl Engine {
             let file = cmd
                 .strip_prefix("vsplit")
                 .map(|s| s.trim())
-                .filter(|s| !s.is_empty());
-            self.split_window(SplitDirection::Vertical, file.map(Path::new));
+                .filter(|s| !s.is_empty())
+                .map(|f| self.resolve_path(paths::expand_tilde(f)));
+            self.split_window(SplitDirection::Vertical, file.as_deref());
             return EngineAction::None;
         }
 
@@ -967,9 +1156,9 @@ impl Engine {
             return EngineAction::None;
         }
 
-        // Handle :on[ly]
-        if cmd == "only" {
-            self.close_other_windows();
+        // Handle :on[ly][!]
+        if cmd == "only" || cmd == "only!" {
+            self.close_other_windows(cmd.ends_with('!'));
             return EngineAction::None;
         }
 
@@ -977,7 +1166,7 @@ impl Engine {
         if cmd == "wincmd" || cmd.starts_with("wincmd ") {
             let args = cmd.strip_prefix("wincmd").unwrap().trim();
             if args.is_empty() {
-                self.message = "E471: Argument required".to_string();
+                self.set_error_message("E471: Argument required".to_string());
                 return EngineAction::None;
             }
             let mut chars = args.chars();
@@ -1001,8 +1190,9 @@ impl Engine {
                 .strip_prefix("tabnew")
                 .or_else(|| cmd.strip_prefix("tabe"))
                 .map(|s| s.trim())
-                .filter(|s| !s.is_empty());
-            self.new_tab(file.map(Path::new));
+                .filter(|s| !s.is_empty())
+                .map(|f| self.resolve_path(paths::expand_tilde(f)));
+            self.new_tab(file.as_deref());
             return EngineAction::None;
         }
 
@@ -1088,6 +1278,113 @@ impl Engine {
                 return EngineAction::None;
             }
 
+            // Handle :set [no]readonly / [no]modifiable (ro / ma) — per-buffer edit
+            // guards — and :set [no]endofline (eol), the file's final line break
+            let (bare, query) = match trimmed.strip_suffix('?') {
+                Some(opt) => (opt, true),
+                None => (trimmed, false),
+            };
+            let (name, enable) = match bare.strip_prefix("no") {
+                Some(opt) if !query => (opt, false),
+                _ => (bare, true),
+            };
+            let buffer_flag = match name {
+                "readonly" | "ro" => Some("readonly"),
+                "modifiable" | "ma" => Some("modifiable"),
+                "endofline" | "eol" => Some("endofline"),
+                _ => None,
+            };
+            if let Some(full) = buffer_flag {
+                let state = self.active_buffer_state_mut();
+                let value = match full {
+                    "readonly" => &mut state.read_only,
+                    "modifiable" => &mut state.modifiable,
+                    _ => &mut state.final_newline,
+                };
+                if !query {
+                    *value = enable;
+                }
+                let on = *value;
+                self.message = if on {
+                    full.to_string()
+                } else {
+                    format!("no{full}")
+                };
+                return EngineAction::None;
+            }
+
+            // Handle :set fileformat=unix|dos / :set ff? — per-buffer line endings
+            let ff_val = trimmed
+                .strip_prefix("fileformat=")
+                .or_else(|| trimmed.strip_prefix("ff="));
+            if let Some(name) = ff_val {
+                let Some(format) = LineEnding::from_fileformat(name.trim()) else {
+                    self.set_error_message(format!("E474: Invalid argument: {trimmed}"));
+                    return EngineAction::Error;
+                };
+                self.set_file_format(format);
+                self.message = format!("fileformat={}", format.fileformat_name());
+                return EngineAction::None;
+            }
+            if matches!(trimmed, "fileformat?" | "ff?" | "fileformat" | "ff") {
+                let format = self
+                    .buffer_manager
+                    .get(self.active_buffer_id())
+                    .map_or(LineEnding::LF, |s| s.line_ending);
+                self.message = format!("fileformat={}", format.fileformat_name());
+                return EngineAction::None;
+            }
+
+            // Handle :set commentstring=<tmpl> / :set cms? — `gc` template for
+            // this buffer only; other buffers keep their language's style
+            let cms_val = trimmed
+                .strip_prefix("commentstring=")
+                .or_else(|| trimmed.strip_prefix("cms="));
+            if let Some(value) = cms_val {
+                // Accept Vim's escaped spaces: `:set cms=//\ %s`
+                let value = value.replace("\\ ", " ");
+                if !value.is_empty() && !value.contains("%s") {
+                    self.message = "commentstring must contain %s".to_string();
+                    return EngineAction::Error;
+                }
+                self.message = format!("commentstring={value}");
+                self.active_buffer_state_mut().commentstring = Some(value);
+                return EngineAction::None;
+            }
+            if matches!(trimmed, "commentstring?" | "cms?" | "commentstring" | "cms") {
+                let value = self
+                    .active_buffer_state()
+                    .commentstring
+                    .clone()
+                    .unwrap_or_else(|| self.settings.commentstring.clone());
+                self.message = format!("commentstring={value}");
+                return EngineAction::None;
+            }
+
+            // Handle :set fileencoding=<enc> / :set fenc? — encoding used on save
+            let fenc_val = trimmed
+                .strip_prefix("fileencoding=")
+                .or_else(|| trimmed.strip_prefix("fenc="));
+            if let Some(name) = fenc_val {
+                let Some(encoding) = FileEncoding::from_name(name.trim()) else {
+                    self.set_error_message(format!("E474: Invalid argument: {trimmed}"));
+                    return EngineAction::Error;
+                };
+                let state = self.active_buffer_state_mut();
+                // Same name: keep the file's BOM (or lack of one) as loaded.
+                if state.encoding.name() != encoding.name() {
+                    state.encoding = encoding;
+                    self.set_dirty(true);
+                }
+                self.message = format!("fileencoding={}", encoding.name());
+                return EngineAction::None;
+            }
+            if matches!(trimmed, "fileencoding?" | "fenc?" | "fileencoding" | "fenc") {
+                let name = self.active_buffer_state().encoding.name();
+                self.message = format!("fileencoding={name}");
+                return EngineAction::None;
+            }
+
             match self.settings.parse_set_option(trimmed) {
                 Ok(msg) => {
                     if let Err(e) = self.settings.save() {
@@ -1097,7 +1394,7 @@ impl Engine {
                     }
                 }
                 Err(e) => {
-                    self.message = e;
+                    self.set_error_message(e);
                     return EngineAction::Error;
                 }
             }
@@ -1105,6 +1402,13 @@ impl Engine {
             if self.settings.spell {
                 self.ensure_spell_checker();
             }
+            // Fetch symbols for the winbar / sticky scroll as soon as either
+            // is switched on.
+            if self.settings.winbar || self.settings.sticky_scroll {
+                if let Some(path) = self.active_buffer_path() {
+                    self.lsp_request_winbar_symbols(&path);
+                }
+            }
             return EngineAction::None;
         }
 
@@ -1117,6 +1421,7 @@ impl Engine {
                 "solarized-dark",
                 "vscode-dark",
                 "vscode-light",
+                "high-contrast",
             ];
             let custom = list_custom_theme_names();
             let custom_strs: Vec<&str> = custom.iter().map(|s| s.as_str()).collect();
@@ -1132,7 +1437,8 @@ impl Engine {
                 "tokyonight" => "tokyo-night",
                 "solarized" => "solarized-dark",
                 "vscode" | "dark+" => "vscode-dark",
-                "light+" => "vscode-light",
+                "light+" | "light" => "vscode-light",
+                "hc" => "high-contrast",
                 other => other,
             };
             // Verify the theme exists (built-in or custom VSCode JSON)
@@ -1143,6 +1449,7 @@ impl Engine {
                 "solarized-dark",
                 "vscode-dark",
                 "vscode-light",
+                "high-contrast",
             ];
             let custom = list_custom_theme_names();
             let is_valid = builtin.contains(&canonical) || custom.iter().any(|n| n == canonical);
@@ -1201,7 +1508,7 @@ impl Engine {
         if cmd == "config reload" {
             match Settings::load_with_validation() {
                 Ok(new_settings) => {
-                    self.settings = new_settings;
+                    self.set_global_settings(new_settings);
                     self.message = "Settings reloaded successfully".to_string();
                 }
                 Err(e) => {
@@ -1240,6 +1547,9 @@ impl Engine {
         if cmd == "copen" {
             return self.open_quickfix();
         }
+        if cmd == "SearchToQuickfix" {
+            return self.send_project_search_to_quickfix();
+        }
         if cmd == "cclose" {
             return self.close_quickfix();
         }
@@ -1269,11 +1579,25 @@ impl Engine {
             self.open_picker(PickerSource::Buffers);
             return EngineAction::None;
         }
+        if cmd == "History" {
+            self.open_picker(PickerSource::RecentFiles);
+            return EngineAction::None;
+        }
+        if cmd == "Harpoon" {
+            self.open_picker(PickerSource::Harpoon);
+            return EngineAction::None;
+        }
         if cmd == "search_keybindings" {
             self.open_picker(PickerSource::Keybindings);
             return EngineAction::None;
         }
-        if cmd == "document_outline" {
+        if cmd == "WorkspaceSymbols" {
+            self.open_picker(PickerSource::CommandCenter);
+            self.picker_query = "#".to_string();
+            self.picker_filter();
+            return EngineAction::None;
+        }
+        if cmd == "document_outline" || cmd == "DocumentSymbols" {
             self.open_picker(PickerSource::CommandCenter);
             self.picker_query = "@".to_string();
             self.picker_filter();
@@ -1352,41 +1676,34 @@ impl Engine {
             return filter_result;
         }
 
-        // Handle :! {command} — run a shell command and show output
+        // Handle :! {command} — run a shell command in the background and
+        // show its output
         if let Some(shell_cmd_raw) = cmd.strip_prefix('!') {
             let shell_cmd = shell_cmd_raw.trim();
             if shell_cmd.is_empty() {
                 self.message = "Usage: :!command".to_string();
                 return EngineAction::None;
             }
-            match std::process::Command::new("sh")
-                .arg("-c")
-                .arg(shell_cmd)
-                .output()
-            {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    let combined = if stderr.is_empty() {
-                        stdout.to_string()
-                    } else if stdout.is_empty() {
-                        stderr.to_string()
-                    } else {
-                        format!("{}{}", stdout, stderr)
-                    };
-                    // Show first line in message, rest truncated
-                    let first_line = combined.lines().next().unwrap_or("(no output)");
-                    let total_lines = combined.lines().count();
-                    if total_lines > 1 {
-                        self.message = format!("{} ({} lines)", first_line, total_lines);
-                    } else {
-                        self.message = first_line.to_string();
-                    }
-                }
-                Err(e) => {
-                    self.message = format!("Shell error: {}", e);
-                }
+            self.spawn_shell_command(shell_cmd, None, ShellTarget::Show);
+            return EngineAction::None;
+        }
+
+        // Handle :r[ead] !{command} — insert command output after cursor line
+        if let Some(shell_cmd) = cmd
+            .strip_prefix("read")
+            .and_then(|rest| rest.trim_start().strip_prefix('!'))
+        {
+            let shell_cmd = shell_cmd.trim();
+            if shell_cmd.is_empty() {
+                self.message = "Usage: :r !command".to_string();
+                return EngineAction::Error;
             }
+            let target = ShellTarget::Read {
+                buffer: self.active_buffer_id(),
+                line: self.view().cursor.line,
+                tick: self.buffer().changedtick,
+            };
+            self.spawn_shell_command(shell_cmd, None, target);
             return EngineAction::None;
         }
 
@@ -1395,7 +1712,7 @@ impl Engine {
             let path = if Path::new(file_arg).is_absolute() {
                 PathBuf::from(file_arg)
             } else {
-                self.cwd.join(file_arg)
+                self.effective_cwd().join(file_arg)
             };
             match std::fs::read_to_string(&path) {
                 Ok(content) => {
@@ -1486,7 +1803,7 @@ impl Engine {
             let path = if Path::new(path_str).is_absolute() {
                 PathBuf::from(path_str)
             } else {
-                self.cwd.join(path_str)
+                self.effective_cwd().join(path_str)
             };
             self.buffer_manager
                 .get_mut(self.active_buffer_id())
@@ -1556,24 +1873,12 @@ impl Engine {
 
         // Handle :windo {cmd}
         if let Some(subcmd) = cmd.strip_prefix("windo ") {
-            let subcmd = subcmd.trim().to_string();
-            let win_ids: Vec<WindowId> = self.windows.keys().copied().collect();
-            for wid in win_ids {
-                self.active_tab_mut().active_window = wid;
-                self.execute_command(&subcmd);
-            }
-            return EngineAction::None;
+            return self.execute_windo(subcmd.trim());
         }
 
         // Handle :bufdo {cmd}
         if let Some(subcmd) = cmd.strip_prefix("bufdo ") {
-            let subcmd = subcmd.trim().to_string();
-            let buf_ids: Vec<_> = self.buffer_manager.list();
-            for bid in buf_ids {
-                self.switch_window_buffer(bid);
-                self.execute_command(&subcmd);
-            }
-            return EngineAction::None;
+            return self.execute_bufdo(subcmd.trim());
         }
 
         // Handle :tabdo {cmd}
@@ -1587,15 +1892,11 @@ impl Engine {
             return EngineAction::None;
         }
 
-        // Handle :make [args] — run build command
+        // Handle :make [args] — run makeprg in the background, then load its
+        // errors into the quickfix list
         if cmd == "make" || cmd.starts_with("make ") {
             let args = cmd.strip_prefix("make").unwrap_or("").trim();
-            let shell_cmd = if args.is_empty() {
-                "make".to_string()
-            } else {
-                format!("make {}", args)
-            };
-            return self.execute_command(&format!("!{}", shell_cmd));
+            return self.start_make(args);
         }
 
         // Handle :$ (jump to last line), :+N, :-N, :. (current line)
@@ -1626,6 +1927,36 @@ impl Engine {
                 let _ = self.save_with_format(false);
                 EngineAction::None
             }
+            "write!" => {
+                // Overwrite even if the file changed on disk since it was read,
+                // clearing 'readonly' and making a read-only file writable
+                // where permissions allow.  A file still loading keeps both
+                // and the write below is refused.
+                let id = self.active_buffer_id();
+                let path = self
+                    .buffer_manager
+                    .get(id)
+                    .filter(|s| s.loading.is_none())
+                    .and_then(|s| s.file_path.clone());
+                if let Some(path) = path {
+                    if safe_write::is_read_only(&path) {
+                        if let Err(e) = safe_write::make_writable(&path) {
+                            self.message = format!("Cannot make {} writable: {e}", path.display());
+                            return EngineAction::None;
+                        }
+                    }
+                    if let Some(state) = self.buffer_manager.get_mut(id) {
+                        state.read_only = false;
+                        state.file_mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                    }
+                }
+                let _ = self.save_with_format(false);
+                EngineAction::None
+            }
+            "checktime" => {
+                self.checktime();
+                EngineAction::None
+            }
             "quit" => {
                 // Block if the current buffer has unsaved changes AND this is
                 // the last window showing it.  If another window still displays
@@ -1692,21 +2023,28 @@ impl Engine {
             }
             "qall!" => EngineAction::Quit,
             // Write all dirty buffers
-            "wall" => {
-                let saved = self.save_all_dirty();
-                self.message = format!("{} file(s) written", saved);
-                EngineAction::None
-            }
-            // Write all + quit
-            "wqall" | "xall" => {
-                let _ = self.save_all_dirty();
-                EngineAction::Quit
-            }
+            "wall" => match self.save_all_dirty() {
+                Ok(saved) => {
+                    self.message = format!("{} file(s) written", saved);
+                    EngineAction::None
+                }
+                Err(e) => {
+                    self.set_error_message(e);
+                    EngineAction::Error
+                }
+            },
+            // Write all + quit; stay open if anything could not be written.
+            "wqall" | "xall" => match self.save_all_dirty() {
+                Ok(_) => EngineAction::Quit,
+                Err(e) => {
+                    self.set_error_message(e);
+                    EngineAction::Error
+                }
+            },
             "wqall!" => EngineAction::Quit,
             // Clear search highlight
             "nohlsearch" => {
-                self.search_matches.clear();
-                self.search_index = None;
+                self.clear_search_highlight();
                 EngineAction::None
             }
             // Display registers
@@ -1994,7 +2332,7 @@ impl Engine {
                 self.lsp_request_definition();
                 EngineAction::None
             }
-            "refs" => {
+            "refs" | "LspReferences" => {
                 self.lsp_request_references();
                 EngineAction::None
             }
@@ -2142,7 +2480,7 @@ impl Engine {
                 EngineAction::None
             }
             "pwd" => {
-                self.message = self.cwd.to_string_lossy().to_string();
+                self.message = self.effective_cwd().to_string_lossy().to_string();
                 EngineAction::None
             }
             "file" => {
@@ -2202,8 +2540,8 @@ impl Engine {
                 EngineAction::None
             }
             "retab" => {
-                let tab_size = self.settings.tabstop as usize;
-                let expand = self.settings.expand_tab;
+                let tab_size = self.effective_tabstop();
+                let expand = self.effective_expand_tab();
                 self.start_undo_group();
                 let num_lines = self.buffer().len_lines();
                 for line_idx in 0..num_lines {
@@ -2246,10 +2584,7 @@ impl Engine {
                     let reg = arg.chars().next().unwrap_or('"');
                     let line = self.view().cursor.line;
                     let text = self.buffer().content.line(line).chars().collect::<String>();
-                    self.registers.insert(reg, (text.clone(), true));
-                    if reg != '"' {
-                        self.registers.insert('"', (text, true));
-                    }
+                    self.set_yank_register(reg, text, true);
                     return EngineAction::None;
                 }
                 if let Some(arg) = cmd.strip_prefix("put ") {
@@ -2310,6 +2645,76 @@ impl Engine {
         }
     }
 
+    /// `:windo {cmd}` — run `cmd` in every window of the current tab, then
+    /// return focus to the window that was active before.  The window list is
+    /// snapshotted first so a command that closes windows can't derail the
+    /// iteration; windows that vanish mid-loop are simply skipped.
+    pub(crate) fn execute_windo(&mut self, subcmd: &str) -> EngineAction {
+        let original = self.active_window_id();
+        let win_ids = self.active_tab().layout.window_ids();
+        let mut errors: Vec<String> = Vec::new();
+        for wid in win_ids {
+            if !self.windows.contains_key(&wid)
+                || !self.active_tab().layout.window_ids().contains(&wid)
+            {
+                continue;
+            }
+            self.active_tab_mut().active_window = wid;
+            if self.execute_command(subcmd) == EngineAction::Error {
+                errors.push(format!("window {}: {}", wid.0, self.message));
+            }
+        }
+        if self.windows.contains_key(&original)
+            && self.active_tab().layout.window_ids().contains(&original)
+        {
+            self.active_tab_mut().active_window = original;
+        }
+        self.report_do_errors("windo", errors)
+    }
+
+    /// `:bufdo {cmd}` — make each listed buffer current in the active window,
+    /// run `cmd`, and finally switch back to the buffer we started from.
+    /// Buffers deleted by the command itself are skipped.
+    pub(crate) fn execute_bufdo(&mut self, subcmd: &str) -> EngineAction {
+        let original = self.active_buffer_id();
+        let buf_ids = self.buffer_manager.list();
+        let mut errors: Vec<String> = Vec::new();
+        for bid in buf_ids {
+            if self.buffer_manager.get(bid).is_none() {
+                continue;
+            }
+            self.switch_window_buffer(bid);
+            if self.execute_command(subcmd) == EngineAction::Error {
+                let name = self
+                    .buffer_manager
+                    .get(bid)
+                    .map(|s| s.display_name())
+                    .unwrap_or_else(|| format!("buffer {}", bid.0));
+                errors.push(format!("{}: {}", name, self.message));
+            }
+        }
+        if self.buffer_manager.get(original).is_some() && self.active_buffer_id() != original {
+            self.switch_window_buffer(original);
+        }
+        self.report_do_errors("bufdo", errors)
+    }
+
+    /// Summarise per-target failures from `:windo`/`:bufdo` in the message
+    /// bar.  On full success the last sub-command's message is left intact.
+    fn report_do_errors(&mut self, what: &str, errors: Vec<String>) -> EngineAction {
+        match errors.len() {
+            0 => EngineAction::None,
+            1 => {
+                self.message = format!("{what}: {}", errors[0]);
+                EngineAction::Error
+            }
+            n => {
+                self.message = format!("{what}: {n} errors (first: {})", errors[0]);
+                EngineAction::Error
+            }
+        }
+    }
+
     pub(crate) fn execute_norm_command(&mut self, range_str: &str, keys: &str) -> EngineAction {
         if keys.is_empty() {
             self.message = "Usage: :norm[al][!] {keys}".to_string();
@@ -2322,8 +2727,17 @@ impl Engine {
         let (start_line, end_line) = if range_str == "%" {
             (0usize, total_lines.saturating_sub(1))
         } else if range_str == "'<,'>" {
-            match self.get_visual_selection_range() {
-                Some((start, end)) => (start.line, end.line),
+            // The live selection, else the '< '> marks of the last one.
+            let marks = self
+                .visual_mark_start
+                .zip(self.visual_mark_end)
+                .map(|((sl, _), (el, _))| (sl, el.min(total_lines.saturating_sub(1))));
+            match self
+                .get_visual_selection_range()
+                .map(|(start, end)| (start.line, end.line))
+                .or(marks)
+            {
+                Some(range) => range,
                 None => {
                     self.message = "No visual selection".to_string();
                     return EngineAction::Error;
@@ -2347,10 +2761,29 @@ impl Engine {
         // Save undo stack depth so we can merge all new entries into one step
         let saved_undo_len = self.active_buffer_state_mut().undo_stack.len();
 
-        for line_num in start_line..=end_line {
-            if line_num >= self.buffer().len_lines() {
-                break;
+        // Mark each line by its starting char offset; the markers follow the
+        // edits made on earlier lines, and lines the keys delete are skipped.
+        let mut markers: Vec<Option<usize>> = (start_line..=end_line)
+            .filter(|&l| l < self.buffer().len_lines())
+            .map(|l| Some(self.buffer().line_to_char(l)))
+            .collect();
+        // Macros started by the keys (`@a`) play here, not on the caller's queue.
+        let outer_queue = std::mem::take(&mut self.macro_playback_queue);
+        let mut processed = 0usize;
+
+        for i in 0..markers.len() {
+            let Some(offset) = markers[i] else {
+                continue;
+            };
+            if offset > self.buffer().len_chars() {
+                continue;
+            }
+            let line_num = self.buffer().content.char_to_line(offset);
+            if self.buffer().line_to_char(line_num) != offset {
+                // Joined into the previous line.
+                continue;
             }
+            let undo_base = self.active_buffer_state().undo_stack.len();
             // Position cursor at start of line in Normal mode
             self.view_mut().cursor.line = line_num;
             self.view_mut().cursor.col = 0;
@@ -2394,11 +2827,24 @@ impl Engine {
                 let _ = self.handle_key(&key_name, unicode, ctrl);
                 self.macro_recursion_depth -= 1;
             }
+            while self.advance_macro_playback().0 {}
 
             // Ensure Normal mode after each line's key sequence
             self.mode = Mode::Normal;
             self.pending_key = None;
+            processed += 1;
+
+            self.active_buffer_state_mut().finish_undo_group();
+            let stack = &self.active_buffer_state().undo_stack;
+            if stack.len() > undo_base {
+                for op in stack[undo_base..].iter().flat_map(|e| &e.ops) {
+                    for marker in markers[i + 1..].iter_mut() {
+                        *marker = marker.and_then(|m| shift_line_marker(m, op));
+                    }
+                }
+            }
         }
+        self.macro_playback_queue = outer_queue;
 
         // Finalize the last open undo group (e.g. from trailing insert mode)
         self.active_buffer_state_mut().finish_undo_group();
@@ -2408,16 +2854,18 @@ impl Engine {
         if state.undo_stack.len() > saved_undo_len + 1 {
             let new_entries: Vec<UndoEntry> = state.undo_stack.drain(saved_undo_len..).collect();
             let cursor_before = new_entries[0].cursor_before;
+            let time = new_entries[new_entries.len() - 1].time;
             let merged_ops: Vec<_> = new_entries.into_iter().flat_map(|e| e.ops).collect();
             if !merged_ops.is_empty() {
                 state.undo_stack.push(UndoEntry {
                     ops: merged_ops,
                     cursor_before,
+                    time,
                 });
             }
         }
 
-        let n = end_line.saturating_sub(start_line) + 1;
+        let n = processed;
         self.message = format!("{} line{} affected", n, if n == 1 { "" } else { "s" });
         EngineAction::None
     }
@@ -2451,7 +2899,7 @@ impl Engine {
         }
 
         if matching.is_empty() {
-            self.message = format!("Pattern not found: {}", pattern);
+            self.set_error_message(format!("E486: Pattern not found: {}", pattern));
             return EngineAction::None;
         }
 
@@ -2855,6 +3303,17 @@ impl Engine {
         current
     }
 
+    /// `:StripWhitespace` — remove trailing whitespace across the buffer.
+    pub(crate) fn cmd_strip_whitespace(&mut self) -> EngineAction {
+        let lines = self.strip_trailing_whitespace();
+        self.message = if lines == 0 {
+            "No trailing whitespace".to_string()
+        } else {
+            format!("Stripped trailing whitespace from {lines} line(s)")
+        };
+        EngineAction::None
+    }
+
     pub(crate) fn execute_substitute_command(&mut self, cmd: &str) -> EngineAction {
         // Parse: [range]s/pattern/replacement/[flags]
         // Supported ranges: none (current line), % (all lines), '<,'> (visual selection)
@@ -2910,6 +3369,10 @@ impl Engine {
             None
         };
 
+        if flags.contains('c') {
+            return self.start_substitute_confirm(range, pattern, replacement, flags);
+        }
+
         // Execute replacement
         match self.replace_in_range(range, pattern, replacement, flags) {
             Ok(count) => {
@@ -2921,7 +3384,7 @@ impl Engine {
                 EngineAction::None
             }
             Err(e) => {
-                self.message = e;
+                self.set_error_message(e);
                 EngineAction::Error
             }
         }
@@ -2929,20 +3392,63 @@ impl Engine {
 
     // --- Search ---
 
+    /// Hide search highlights in every window without forgetting the pattern,
+    /// so `n` / `N` still work (`:noh`).
+    pub(crate) fn clear_search_highlight(&mut self) {
+        self.search_matches.clear();
+        self.search_index = None;
+        self.search_highlight_cleared = true;
+    }
+
+    /// Strip `\c` / `\C` from a search pattern and decide whether it matches
+    /// case-insensitively.  `\c` forces ignoring case and `\C` forces
+    /// matching it; otherwise `ignorecase` applies, overridden by `smartcase`
+    /// (when `use_smartcase`) if the pattern contains an uppercase letter.
+    pub(crate) fn search_pattern_case(&self, pattern: &str, use_smartcase: bool) -> (String, bool) {
+        let mut stripped = String::with_capacity(pattern.len());
+        let mut force = None;
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some('c')) => {
+                    chars.next();
+                    force = Some(true);
+                }
+                ('\\', Some('C')) => {
+                    chars.next();
+                    force.get_or_insert(false);
+                }
+                ('\\', Some(&next)) => {
+                    chars.next();
+                    stripped.push(c);
+                    stripped.push(next);
+                }
+                _ => stripped.push(c),
+            }
+        }
+        let insensitive = force.unwrap_or_else(|| {
+            self.settings.ignorecase
+                && !(use_smartcase
+                    && self.settings.smartcase
+                    && stripped.chars().any(|c| c.is_uppercase()))
+        });
+        (stripped, insensitive)
+    }
+
     pub fn run_search(&mut self) {
         self.search_matches.clear();
         self.search_index = None;
+        self.search_highlight_cleared = false;
 
         if self.search_query.is_empty() {
             return;
         }
 
         let text = self.buffer().to_string();
-        let query_orig = self.search_query.clone();
-
-        // Apply ignorecase / smartcase
-        let case_insensitive = self.settings.ignorecase
-            && !(self.settings.smartcase && query_orig.chars().any(|c| c.is_uppercase()));
+        let (query_orig, case_insensitive) = self.search_pattern_case(&self.search_query, true);
+        if query_orig.is_empty() {
+            return;
+        }
 
         if case_insensitive {
             let text_lower = text.to_lowercase();
@@ -2969,7 +3475,7 @@ impl Engine {
         }
 
         if self.search_matches.is_empty() {
-            self.message = format!("Pattern not found: {}", self.search_query);
+            self.set_error_message(format!("E486: Pattern not found: {}", self.search_query));
         }
     }
 
@@ -2979,7 +3485,10 @@ impl Engine {
                 // Re-run search (matches may have been cleared by Escape/:noh)
                 self.run_search();
                 if self.search_matches.is_empty() {
-                    self.message = format!("Pattern not found: {}", self.search_query);
+                    self.set_error_message(format!(
+                        "E486: Pattern not found: {}",
+                        self.search_query
+                    ));
                     return;
                 }
             } else {
@@ -2987,18 +3496,29 @@ impl Engine {
             }
         }
 
-        let line = self.view().cursor.line;
-        let col = self.view().cursor.col;
-        let cursor_char = self.buffer().line_to_char(line) + col;
+        let cursor_char = self.search_anchor_char();
 
         let next = self
             .search_matches
             .iter()
             .position(|(start, _)| *start > cursor_char);
-        let idx = next.unwrap_or(0);
+        let idx = match next {
+            Some(idx) => idx,
+            None if self.settings.wrapscan => 0,
+            None => {
+                self.set_error_message(format!(
+                    "E385: search hit BOTTOM without match for: {}",
+                    self.search_query
+                ));
+                return;
+            }
+        };
 
         self.search_index = Some(idx);
         self.jump_to_search_match(idx);
+        if next.is_none() {
+            self.set_error_message("search hit BOTTOM, continuing at TOP".to_string());
+        }
     }
 
     pub fn search_prev(&mut self) {
@@ -3007,7 +3527,10 @@ impl Engine {
                 // Re-run search (matches may have been cleared by Escape/:noh)
                 self.run_search();
                 if self.search_matches.is_empty() {
-                    self.message = format!("Pattern not found: {}", self.search_query);
+                    self.set_error_message(format!(
+                        "E486: Pattern not found: {}",
+                        self.search_query
+                    ));
                     return;
                 }
             } else {
@@ -3015,27 +3538,82 @@ impl Engine {
             }
         }
 
-        let line = self.view().cursor.line;
-        let col = self.view().cursor.col;
-        let cursor_char = self.buffer().line_to_char(line) + col;
+        let cursor_char = self.search_anchor_char();
 
         let prev = self
             .search_matches
             .iter()
             .rposition(|(start, _)| *start < cursor_char);
-        let idx = prev.unwrap_or(self.search_matches.len() - 1);
+        let idx = match prev {
+            Some(idx) => idx,
+            None if self.settings.wrapscan => self.search_matches.len() - 1,
+            None => {
+                self.set_error_message(format!(
+                    "E384: search hit TOP without match for: {}",
+                    self.search_query
+                ));
+                return;
+            }
+        };
 
         self.search_index = Some(idx);
         self.jump_to_search_match(idx);
+        if prev.is_none() {
+            self.set_error_message("search hit TOP, continuing at BOTTOM".to_string());
+        }
+    }
+
+    /// Where `n` / `N` search from: the cursor, or the start of the current
+    /// match when the cursor sits where a search offset placed it.
+    fn search_anchor_char(&self) -> usize {
+        let cursor = self.view().cursor;
+        if self.search_offset != SearchOffset::None {
+            if let Some(&m) = self.search_index.and_then(|i| self.search_matches.get(i)) {
+                if self.search_offset_target(m) == cursor {
+                    return m.0;
+                }
+            }
+        }
+        self.buffer().line_to_char(cursor.line) + cursor.col
+    }
+
+    /// Cursor position for a match `(start_char, end_char)` after applying
+    /// `search_offset`, clamped to the buffer and line bounds.
+    fn search_offset_target(&self, (start_char, end_char): (usize, usize)) -> Cursor {
+        let buf = self.buffer();
+        let last_char = buf.len_chars().saturating_sub(1);
+        let at_char = |ch: isize| {
+            let ch = (ch.max(0) as usize).min(last_char);
+            let line = buf.content.char_to_line(ch);
+            (line, ch - buf.line_to_char(line))
+        };
+        let (line, col) = match self.search_offset {
+            SearchOffset::None => at_char(start_char as isize),
+            SearchOffset::Start(n) => at_char(start_char as isize + n),
+            SearchOffset::End(n) => at_char(end_char as isize - 1 + n),
+            SearchOffset::Lines(n) => {
+                let match_line = buf.content.char_to_line(start_char) as isize;
+                let max_line = buf.len_lines().saturating_sub(1) as isize;
+                ((match_line + n).clamp(0, max_line) as usize, 0)
+            }
+        };
+        // Never land on the line ending.
+        let len = buf.line_len_chars(line);
+        let content_len = if len > 0 && buf.content.char(buf.line_to_char(line) + len - 1) == '\n' {
+            len - 1
+        } else {
+            len
+        };
+        Cursor {
+            line,
+            col: col.min(content_len.saturating_sub(1)),
+        }
     }
 
     pub(crate) fn jump_to_search_match(&mut self, idx: usize) {
-        if let Some(&(start_char, _)) = self.search_matches.get(idx) {
-            let line = self.buffer().content.char_to_line(start_char);
-            let line_start = self.buffer().line_to_char(line);
-            let col = start_char - line_start;
-            self.view_mut().cursor.line = line;
-            self.view_mut().cursor.col = col;
+        if let Some(&m) = self.search_matches.get(idx) {
+            let target = self.search_offset_target(m);
+            self.view_mut().cursor = target;
             self.ensure_cursor_visible();
             // If the match landed in the bottom quarter of the viewport,
             // center it so it's not barely visible at the edge (Vim-like behavior).
@@ -3053,14 +3631,24 @@ impl Engine {
 
     /// Perform incremental search as user types
     pub fn perform_incremental_search(&mut self) {
-        // Update search query from command buffer
-        self.search_query = self.command_buffer.clone();
+        // Update search query from command buffer (any `/offset` is applied on Enter)
+        let delim = match self.search_direction {
+            SearchDirection::Forward => '/',
+            SearchDirection::Backward => '?',
+        };
+        let pattern = parse_search_offset(&self.command_buffer, delim).0;
+        if pattern.is_empty() && !self.command_buffer.is_empty() {
+            // `//e`: only an offset so far — keep the previous pattern for Enter.
+            self.restore_search_start();
+            self.search_matches.clear();
+            self.search_index = None;
+            return;
+        }
+        self.search_query = pattern;
 
         if self.search_query.is_empty() {
             // Restore to start position if search is empty
-            if let Some(start_cursor) = self.search_start_cursor {
-                self.view_mut().cursor = start_cursor;
-            }
+            self.restore_search_start();
             self.search_matches.clear();
             self.search_index = None;
             self.message.clear();
@@ -3095,12 +3683,25 @@ impl Engine {
             };
 
             self.search_index = Some(idx);
+            // The preview lands on the match itself; the offset applies on Enter.
+            let offset = std::mem::take(&mut self.search_offset);
             self.jump_to_search_match(idx);
+            self.search_offset = offset;
         } else {
             // No matches, restore to start position
-            if let Some(start_cursor) = self.search_start_cursor {
-                self.view_mut().cursor = start_cursor;
-            }
+            self.restore_search_start();
+        }
+    }
+
+    /// Move the cursor and scroll back to where search mode was entered.
+    /// The saved position is kept so later keystrokes can preview again.
+    pub(crate) fn restore_search_start(&mut self) {
+        if let Some(start_cursor) = self.search_start_cursor {
+            self.view_mut().cursor = start_cursor;
+        }
+        if let Some((top, left)) = self.search_start_scroll {
+            self.view_mut().scroll_top = top;
+            self.view_mut().scroll_left = left;
         }
     }
 
@@ -3108,10 +3709,11 @@ impl Engine {
 
     /// Replace text in a given range
     /// range: None = current line, Some((start_line, end_line)) = line range
-    /// pattern: string to find (will use simple substring matching for now)
-    /// replacement: string to replace with
-    /// flags: "g" (all), "c" (confirm), "i" (case-insensitive)
-    /// Returns: (num_replacements, modified_text_preview)
+    /// pattern: Vim "magic" pattern (see [`vim_pattern_to_regex`])
+    /// replacement: template expanded per match (see [`expand_substitute_replacement`])
+    /// flags: "g" (all), "i" / "I" (ignore / match case); "c" is handled by
+    /// the `:s` command itself
+    /// Returns: number of lines changed
     pub fn replace_in_range(
         &mut self,
         range: Option<(usize, usize)>,
@@ -3122,116 +3724,266 @@ impl Engine {
         if pattern.is_empty() {
             return Err("Pattern cannot be empty".to_string());
         }
+        let re = self.substitute_regex(pattern, flags)?;
+        Ok(self.replace_regex_in_range(range, &re, replacement, flags.contains('g')))
+    }
 
-        let global = flags.contains('g');
-        let _confirm = flags.contains('c'); // For Phase 2
-        let case_insensitive = flags.contains('i');
+    /// Compile a `:s` pattern, honouring `\c` / `\C`, `ignorecase` /
+    /// `smartcase` and the `i` / `I` flags.
+    pub(crate) fn substitute_regex(
+        &self,
+        pattern: &str,
+        flags: &str,
+    ) -> Result<regex::Regex, String> {
+        let (stripped, mut case_insensitive) = self.search_pattern_case(pattern, true);
+        if flags.contains('i') {
+            case_insensitive = true;
+        } else if flags.contains('I') {
+            case_insensitive = false;
+        }
+        let translated = vim_pattern_to_regex(&stripped)?;
+        regex::RegexBuilder::new(&translated)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|_| format!("E383: Invalid search string: {}", pattern))
+    }
 
-        // Determine line range
-        let (start_line, end_line) = match range {
-            Some((s, e)) => (s, e),
-            None => {
-                let current = self.view().cursor.line;
-                (current, current)
-            }
-        };
+    /// Replace matches of `re` on each line of `range` (`None` = current
+    /// line), expanding `replacement` for every match.  One undo step.
+    /// Returns the number of lines changed.
+    pub(crate) fn replace_regex_in_range(
+        &mut self,
+        range: Option<(usize, usize)>,
+        re: &regex::Regex,
+        replacement: &str,
+        global: bool,
+    ) -> usize {
+        let (start_line, mut end_line) = range.unwrap_or_else(|| {
+            let current = self.view().cursor.line;
+            (current, current)
+        });
 
         let mut replacements = 0;
         self.start_undo_group();
 
-        // Process each line in range
-        for line_num in start_line..=end_line {
-            if line_num >= self.buffer().len_lines() {
-                break;
-            }
-
+        let mut line_num = start_line;
+        while line_num <= end_line && line_num < self.buffer().len_lines() {
             let line_start_char = self.buffer().line_to_char(line_num);
-            let line_len = self.buffer().line_len_chars(line_num);
-            let line_text: String = self
-                .buffer()
-                .content
-                .slice(line_start_char..line_start_char + line_len)
-                .chars()
-                .collect();
-
-            // Find and replace in this line
+            let line_text = self.substitute_line_text(line_num);
+            let expand = |caps: &regex::Captures| expand_substitute_replacement(replacement, caps);
             let new_line = if global {
-                self.replace_all_in_string(&line_text, pattern, replacement, case_insensitive)
+                re.replace_all(&line_text, expand)
             } else {
-                self.replace_first_in_string(&line_text, pattern, replacement, case_insensitive)
+                re.replacen(&line_text, 1, expand)
             };
 
-            if new_line != line_text {
+            if new_line != line_text.as_str() {
                 // Delete old line content and insert new
-                self.delete_with_undo(line_start_char, line_start_char + line_len);
+                let old_len = line_text.chars().count();
+                self.delete_with_undo(line_start_char, line_start_char + old_len);
                 self.insert_with_undo(line_start_char, &new_line);
                 replacements += 1;
+                // `\r` in the replacement splits the line; skip past the pieces.
+                let added = new_line.matches('\n').count();
+                line_num += added;
+                end_line += added;
             }
+            line_num += 1;
         }
 
         self.finish_undo_group();
-        Ok(replacements)
+        replacements
     }
 
-    /// Helper: Replace all occurrences in a string
-    pub(crate) fn replace_all_in_string(
-        &self,
-        text: &str,
+    /// Text of `line` without its line break.
+    fn substitute_line_text(&self, line: usize) -> String {
+        let mut text: String = self.buffer().content.line(line).chars().collect();
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        text
+    }
+
+    /// Begin an interactive `:s///c`: offer each match in the range in turn
+    /// and wait for y/n/a/q/l (see [`Self::handle_substitute_confirm_key`]).
+    fn start_substitute_confirm(
+        &mut self,
+        range: Option<(usize, usize)>,
         pattern: &str,
         replacement: &str,
-        case_insensitive: bool,
-    ) -> String {
-        if case_insensitive {
-            // Case-insensitive: convert to lowercase for comparison
-            let pattern_lower = pattern.to_lowercase();
-            let text_lower = text.to_lowercase();
-
-            let mut result = String::new();
-            let mut last_pos = 0;
-
-            while let Some(pos) = text_lower[last_pos..].find(&pattern_lower) {
-                let absolute_pos = last_pos + pos;
-                result.push_str(&text[last_pos..absolute_pos]);
-                result.push_str(replacement);
-                last_pos = absolute_pos + pattern.len();
+        flags: &str,
+    ) -> EngineAction {
+        if pattern.is_empty() {
+            self.message = "Pattern cannot be empty".to_string();
+            return EngineAction::Error;
+        }
+        let regex = match self.substitute_regex(pattern, flags) {
+            Ok(re) => re,
+            Err(e) => {
+                self.set_error_message(e);
+                return EngineAction::Error;
             }
-            result.push_str(&text[last_pos..]);
-            result
+        };
+        let (line, end_line) = range.unwrap_or_else(|| {
+            let current = self.view().cursor.line;
+            (current, current)
+        });
+        self.start_undo_group();
+        self.substitute_confirm = Some(SubstituteConfirm {
+            regex,
+            replacement: replacement.to_string(),
+            global: flags.contains('g'),
+            line,
+            col: 0,
+            match_end: 0,
+            end_line,
+            count: 0,
+        });
+        self.substitute_confirm_seek();
+        EngineAction::None
+    }
+
+    /// Answer the pending `:s///c` prompt: `y` replace, `n` skip, `a` replace
+    /// all remaining, `l` replace this one and stop, `q` / Escape stop.
+    pub(crate) fn handle_substitute_confirm_key(
+        &mut self,
+        key_name: &str,
+        unicode: Option<char>,
+        ctrl: bool,
+    ) {
+        let answer = if key_name == "Escape" || (ctrl && unicode == Some('c')) {
+            'q'
+        } else if ctrl {
+            return;
         } else {
-            text.replace(pattern, replacement)
+            match unicode {
+                Some(c) => c,
+                None => return,
+            }
+        };
+        match answer {
+            'y' => {
+                self.substitute_confirm_replace();
+                self.substitute_confirm_seek();
+            }
+            'n' => {
+                if let Some(sc) = self.substitute_confirm.as_mut() {
+                    if sc.global {
+                        sc.col = sc.match_end.max(sc.col + 1);
+                    } else {
+                        sc.line += 1;
+                        sc.col = 0;
+                    }
+                }
+                self.substitute_confirm_seek();
+            }
+            'a' => {
+                while self.substitute_confirm.is_some() {
+                    self.substitute_confirm_replace();
+                    self.substitute_confirm_seek();
+                }
+            }
+            'l' => {
+                self.substitute_confirm_replace();
+                self.finish_substitute_confirm();
+            }
+            'q' => self.finish_substitute_confirm(),
+            _ => {}
         }
     }
 
-    /// Helper: Replace first occurrence in a string
-    pub(crate) fn replace_first_in_string(
-        &self,
-        text: &str,
-        pattern: &str,
-        replacement: &str,
-        case_insensitive: bool,
-    ) -> String {
-        if case_insensitive {
-            let pattern_lower = pattern.to_lowercase();
-            let text_lower = text.to_lowercase();
-
-            if let Some(pos) = text_lower.find(&pattern_lower) {
-                let mut result = String::new();
-                result.push_str(&text[..pos]);
-                result.push_str(replacement);
-                result.push_str(&text[pos + pattern.len()..]);
-                result
+    /// Replace the offered match and step past it.
+    fn substitute_confirm_replace(&mut self) {
+        let Some(sc) = self.substitute_confirm.clone() else {
+            return;
+        };
+        let text = self.substitute_line_text(sc.line);
+        let byte_col = text
+            .char_indices()
+            .nth(sc.col)
+            .map_or(text.len(), |(b, _)| b);
+        let Some(caps) = sc.regex.captures_at(&text, byte_col) else {
+            return;
+        };
+        let new_text = expand_substitute_replacement(&sc.replacement, &caps);
+        let start = self.buffer().line_to_char(sc.line) + sc.col;
+        self.delete_with_undo(start, start + (sc.match_end - sc.col));
+        self.insert_with_undo(start, &new_text);
+        if let Some(sc) = self.substitute_confirm.as_mut() {
+            sc.count += 1;
+            let added_lines = new_text.matches('\n').count();
+            if !sc.global {
+                sc.line += added_lines + 1;
+                sc.end_line += added_lines;
+                sc.col = 0;
+            } else if added_lines > 0 {
+                sc.line += added_lines;
+                sc.end_line += added_lines;
+                sc.col = new_text
+                    .rsplit('\n')
+                    .next()
+                    .map_or(0, |tail| tail.chars().count());
             } else {
-                text.to_string()
-            }
-        } else if let Some(pos) = text.find(pattern) {
-            let mut result = String::new();
-            result.push_str(&text[..pos]);
-            result.push_str(replacement);
-            result.push_str(&text[pos + pattern.len()..]);
-            result
-        } else {
-            text.to_string()
+                // An empty match must still move forward.
+                let empty = sc.match_end == sc.col;
+                sc.col += new_text.chars().count() + usize::from(empty);
+            }
+        }
+    }
+
+    /// Move to the next match at or after the current position, highlight it
+    /// and prompt; finish when the range has no more matches.
+    fn substitute_confirm_seek(&mut self) {
+        let Some(sc) = self.substitute_confirm.clone() else {
+            return;
+        };
+        let last_line = sc.end_line.min(self.buffer().len_lines().saturating_sub(1));
+        let mut from = sc.col;
+        for line in sc.line..=last_line {
+            let text = self.substitute_line_text(line);
+            let byte_from = match text.char_indices().nth(from) {
+                Some((b, _)) => b,
+                None if from == text.chars().count() => text.len(),
+                None => {
+                    from = 0;
+                    continue;
+                }
+            };
+            if let Some(m) = sc.regex.find_at(&text, byte_from) {
+                let col = text[..m.start()].chars().count();
+                let match_end = col + m.as_str().chars().count();
+                if let Some(state) = self.substitute_confirm.as_mut() {
+                    state.line = line;
+                    state.col = col;
+                    state.match_end = match_end;
+                }
+                let start = self.buffer().line_to_char(line) + col;
+                self.view_mut().cursor = Cursor { line, col };
+                self.ensure_cursor_visible();
+                self.search_matches = vec![(start, start + (match_end - col))];
+                self.search_index = Some(0);
+                self.search_highlight_cleared = false;
+                self.message = format!("replace with {}? (y/n/a/q/l)", sc.replacement);
+                return;
+            }
+            from = 0;
         }
+        self.finish_substitute_confirm();
+    }
+
+    /// End the interactive substitute and report how many replacements were made.
+    fn finish_substitute_confirm(&mut self) {
+        let Some(sc) = self.substitute_confirm.take() else {
+            return;
+        };
+        self.finish_undo_group();
+        self.search_matches.clear();
+        self.search_index = None;
+        self.message = format!(
+            "{} substitution{}",
+            sc.count,
+            if sc.count == 1 { "" } else { "s" }
+        );
     }
 
     /// Handle a click on an interactive status bar segment.
@@ -3255,7 +4007,12 @@ impl Engine {
                 self.open_picker(PickerSource::LineEndings);
             }
             StatusAction::ChangeEncoding => {
-                self.message = "Only UTF-8 encoding is supported".to_string();
+                let current = self.active_buffer_state().encoding.name();
+                let names: Vec<&str> = FileEncoding::ALL.iter().map(|e| e.name()).collect();
+                self.message = format!(
+                    "fileencoding={current} — :set fenc={{enc}} to convert, :e ++enc={{enc}} to reread ({})",
+                    names.join(", ")
+                );
             }
             StatusAction::SwitchBranch => {
                 self.open_picker(PickerSource::GitBranches);
@@ -3388,6 +4145,288 @@ impl Engine {
     }
 }
 
+/// Translate a Vim "magic" pattern (as used by `:s`) into `regex` syntax.
+/// Supports `\(\)`, `\%(\)`, `\|`, `\+`, `\?`, `\=`, `\{n,m}` (and `\{-}`),
+/// `\<` / `\>`, `[...]` and the Vim character classes; a bare `( ) | + ? { }`
+/// is literal, as in Vim.
+pub(crate) fn vim_pattern_to_regex(pattern: &str) -> Result<String, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    // Whether the previous item can take a quantifier; `*` or `^` after
+    // nothing (or a group start / branch) keeps its Vim meaning.
+    let mut after_atom = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            '\\' => {
+                let Some(&n) = chars.get(i) else {
+                    out.push_str("\\\\");
+                    break;
+                };
+                i += 1;
+                after_atom = true;
+                match n {
+                    '(' => {
+                        out.push('(');
+                        after_atom = false;
+                    }
+                    '%' if chars.get(i) == Some(&'(') => {
+                        i += 1;
+                        out.push_str("(?:");
+                        after_atom = false;
+                    }
+                    ')' => out.push(')'),
+                    '|' => {
+                        out.push('|');
+                        after_atom = false;
+                    }
+                    '+' => out.push('+'),
+                    '=' | '?' => out.push('?'),
+                    '{' => {
+                        let close = chars[i..]
+                            .iter()
+                            .position(|&ch| ch == '}')
+                            .map(|p| i + p)
+                            .ok_or_else(|| "E554: Syntax error in \\{...}".to_string())?;
+                        let mut body: String = chars[i..close].iter().collect();
+                        i = close + 1;
+                        if body.ends_with('\\') {
+                            body.pop();
+                        }
+                        let lazy = body.starts_with('-');
+                        let body = body.trim_start_matches('-');
+                        if !body.chars().all(|ch| ch.is_ascii_digit() || ch == ',') {
+                            return Err("E554: Syntax error in \\{...}".to_string());
+                        }
+                        match body {
+                            "" | "," => out.push('*'),
+                            _ if body.starts_with(',') => {
+                                out.push_str("{0");
+                                out.push_str(body);
+                                out.push('}');
+                            }
+                            _ => {
+                                out.push('{');
+                                out.push_str(body);
+                                out.push('}');
+                            }
+                        }
+                        if lazy {
+                            out.push('?');
+                        }
+                    }
+                    '<' | '>' => {
+                        out.push_str("\\b");
+                        after_atom = false;
+                    }
+                    's' | 'S' | 'd' | 'D' | 'w' | 'W' => {
+                        out.push('\\');
+                        out.push(n);
+                    }
+                    'a' => out.push_str("[A-Za-z]"),
+                    'A' => out.push_str("[^A-Za-z]"),
+                    'l' => out.push_str("[a-z]"),
+                    'L' => out.push_str("[^a-z]"),
+                    'u' => out.push_str("[A-Z]"),
+                    'U' => out.push_str("[^A-Z]"),
+                    'x' => out.push_str("[0-9A-Fa-f]"),
+                    'X' => out.push_str("[^0-9A-Fa-f]"),
+                    'o' => out.push_str("[0-7]"),
+                    'O' => out.push_str("[^0-7]"),
+                    'h' => out.push_str("[A-Za-z_]"),
+                    'H' => out.push_str("[^A-Za-z_]"),
+                    'n' => out.push_str("\\n"),
+                    't' => out.push_str("\\t"),
+                    'r' => out.push_str("\\r"),
+                    'e' => out.push_str("\\x1b"),
+                    '1'..='9' => {
+                        return Err("E65: Back references are not supported in patterns".into())
+                    }
+                    _ => out.push_str(&regex::escape(&n.to_string())),
+                }
+            }
+            '[' => match bracket_end(&chars, i) {
+                Some(end) => {
+                    out.push('[');
+                    let mut j = i;
+                    while j < end {
+                        let ch = chars[j];
+                        j += 1;
+                        match ch {
+                            '\\' if j < end => {
+                                let esc = chars[j];
+                                j += 1;
+                                if esc == 'e' {
+                                    out.push_str("\\x1b");
+                                } else {
+                                    out.push('\\');
+                                    out.push(esc);
+                                }
+                            }
+                            '[' if chars.get(j) == Some(&':') => {
+                                // POSIX class such as `[:alpha:]`.
+                                let close = (j..end)
+                                    .find(|&k| chars[k] == ':' && chars.get(k + 1) == Some(&']'))
+                                    .unwrap_or(end - 1);
+                                out.extend(&chars[j - 1..=close + 1]);
+                                j = close + 2;
+                            }
+                            '[' | '&' | '~' => {
+                                out.push('\\');
+                                out.push(ch);
+                            }
+                            _ => out.push(ch),
+                        }
+                    }
+                    out.push(']');
+                    i = end + 1;
+                    after_atom = true;
+                }
+                None => {
+                    out.push_str("\\[");
+                    after_atom = true;
+                }
+            },
+            '*' if !after_atom => {
+                out.push_str("\\*");
+                after_atom = true;
+            }
+            '^' if after_atom => out.push_str("\\^"),
+            '^' => out.push('^'),
+            '$' => {
+                // Only an anchor at the end of the pattern or a branch.
+                let at_end = i == chars.len()
+                    || (chars[i] == '\\' && matches!(chars.get(i + 1), Some('|' | ')')));
+                out.push_str(if at_end { "$" } else { "\\$" });
+                after_atom = true;
+            }
+            '(' | ')' | '|' | '+' | '?' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+                after_atom = true;
+            }
+            _ => {
+                out.push(c);
+                after_atom = true;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Move a line-start marker (char offset) across one edit.  Returns `None`
+/// when the edit deletes the marked line.
+fn shift_line_marker(marker: usize, op: &EditOp) -> Option<usize> {
+    match op {
+        EditOp::Insert { pos, text } => {
+            let n = text.chars().count();
+            if marker > *pos {
+                Some(marker + n)
+            } else if marker == *pos {
+                // Text inserted at the line start stays on the marked line,
+                // except whole lines inserted above it.
+                Some(marker + text.rfind('\n').map_or(0, |b| text[..=b].chars().count()))
+            } else {
+                Some(marker)
+            }
+        }
+        EditOp::Delete { pos, text } => {
+            let n = text.chars().count();
+            if marker >= pos + n {
+                Some(marker - n)
+            } else if marker > *pos || (marker == *pos && text.contains('\n')) {
+                None
+            } else {
+                Some(marker)
+            }
+        }
+    }
+}
+
+/// Index of the `]` closing a bracket expression whose contents start at
+/// `start` (just after the `[`), or `None` when it is unterminated.
+fn bracket_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut j = start;
+    if chars.get(j) == Some(&'^') {
+        j += 1;
+    }
+    // A leading `]` is literal.
+    if chars.get(j) == Some(&']') {
+        j += 1;
+    }
+    while j < chars.len() {
+        match chars[j] {
+            '\\' => j += 2,
+            '[' if chars.get(j + 1) == Some(&':') => {
+                j = (j + 2..chars.len())
+                    .find(|&k| chars[k] == ':' && chars.get(k + 1) == Some(&']'))
+                    .map_or(j + 1, |k| k + 2);
+            }
+            ']' => return Some(j),
+            _ => j += 1,
+        }
+    }
+    None
+}
+
+/// Expand a `:s` replacement template for one match.  `&` and `\0` insert
+/// the whole match and `\1`..`\9` a capture group; `\u` / `\l` change the
+/// case of the next character and `\U` / `\L` everything up to `\E` / `\e`.
+/// `\r` inserts a line break, `\n` a NUL (as in Vim), `\t` a tab, and `\&` /
+/// `\\` are literal.
+pub(crate) fn expand_substitute_replacement(template: &str, caps: &regex::Captures) -> String {
+    let mut out = String::with_capacity(template.len());
+    // `Some(true)` = uppercase, `Some(false)` = lowercase.
+    let mut next_case: Option<bool> = None;
+    let mut span_case: Option<bool> = None;
+    let group = |n: usize| caps.get(n).map_or("", |m| m.as_str()).to_string();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        let text = match c {
+            '&' => group(0),
+            '\\' => match chars.next() {
+                Some(d @ '0'..='9') => group(d as usize - '0' as usize),
+                Some('u') => {
+                    next_case = Some(true);
+                    continue;
+                }
+                Some('l') => {
+                    next_case = Some(false);
+                    continue;
+                }
+                Some('U') => {
+                    span_case = Some(true);
+                    continue;
+                }
+                Some('L') => {
+                    span_case = Some(false);
+                    continue;
+                }
+                Some('E' | 'e') => {
+                    span_case = None;
+                    continue;
+                }
+                Some('r') => "\n".to_string(),
+                Some('n') => "\0".to_string(),
+                Some('t') => "\t".to_string(),
+                Some(other) => other.to_string(),
+                None => "\\".to_string(),
+            },
+            other => other.to_string(),
+        };
+        for ch in text.chars() {
+            match next_case.take().or(span_case) {
+                Some(true) => out.extend(ch.to_uppercase()),
+                Some(false) => out.extend(ch.to_lowercase()),
+                None => out.push(ch),
+            }
+        }
+    }
+    out
+}
+
 /// Matches `rest` against command name `name` followed by a valid argument
 /// separator (space, digit, `+`, `-`, `.`, `$`). Returns the trimmed argument
 /// if matched, else None.
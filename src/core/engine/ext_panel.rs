@@ -1,5 +1,12 @@
 use super::*;
 
+/// Editor hover popups show at most this many content rows; longer content
+/// scrolls (`j`/`k`, `Ctrl-D`/`Ctrl-U`, `Ctrl-F`/`Ctrl-B`).
+pub(crate) const EDITOR_HOVER_MAX_LINES: usize = 20;
+
+/// Hover content is word-wrapped to this many columns.
+const EDITOR_HOVER_MAX_WIDTH: usize = 80;
+
 impl Engine {
     // ─── Extension Panel helpers ────────────────────────────────────────────
 
@@ -1088,7 +1095,8 @@ impl Engine {
         take_focus: bool,
         add_goto_links: bool,
     ) {
-        let mut rendered = crate::core::markdown::render_markdown(markdown);
+        let mut rendered =
+            crate::core::markdown::render_markdown(markdown).wrap(EDITOR_HOVER_MAX_WIDTH);
         let mut links = Self::extract_hover_links(&rendered);
 
         // Append "Go to" navigation links after actual LSP content (vim mode only).
@@ -1132,7 +1140,7 @@ impl Engine {
             .map(|l| l.chars().count())
             .max()
             .unwrap_or(10)
-            .clamp(10, 80);
+            .clamp(10, EDITOR_HOVER_MAX_WIDTH);
         let (frozen_scroll_top, frozen_scroll_left) = {
             let v = self.view();
             (v.scroll_top, v.scroll_left)
@@ -1234,21 +1242,28 @@ impl Engine {
                 }
             }
             "j" | "Down" => {
-                // Scroll down — stop when last line is visible
-                if let Some(hover) = &mut self.editor_hover {
-                    let max_scroll = hover.rendered.lines.len().saturating_sub(20);
-                    if hover.scroll_top < max_scroll {
-                        hover.scroll_top += 1;
-                    }
-                }
+                self.editor_hover_scroll(1);
             }
             "k" | "Up" => {
-                // Scroll up
-                if let Some(hover) = &mut self.editor_hover {
-                    if hover.scroll_top > 0 {
-                        hover.scroll_top -= 1;
-                    }
-                }
+                self.editor_hover_scroll(-1);
+            }
+            "f" if ctrl => {
+                self.editor_hover_scroll(EDITOR_HOVER_MAX_LINES as i32);
+            }
+            "b" if ctrl => {
+                self.editor_hover_scroll(-(EDITOR_HOVER_MAX_LINES as i32));
+            }
+            "d" if ctrl => {
+                self.editor_hover_scroll(EDITOR_HOVER_MAX_LINES as i32 / 2);
+            }
+            "u" if ctrl => {
+                self.editor_hover_scroll(-(EDITOR_HOVER_MAX_LINES as i32) / 2);
+            }
+            "Page_Down" => {
+                self.editor_hover_scroll(EDITOR_HOVER_MAX_LINES as i32);
+            }
+            "Page_Up" => {
+                self.editor_hover_scroll(-(EDITOR_HOVER_MAX_LINES as i32));
             }
             // Ignore bare modifier keys (GTK sends these as separate key events)
             "Control_L" | "Control_R" | "Shift_L" | "Shift_R" | "Alt_L" | "Alt_R" | "Super_L"
@@ -1366,7 +1381,11 @@ impl Engine {
     /// Returns true if the popup was scrolled.
     pub fn editor_hover_scroll(&mut self, delta: i32) -> bool {
         if let Some(hover) = &mut self.editor_hover {
-            let max_scroll = hover.rendered.lines.len().saturating_sub(20);
+            let max_scroll = hover
+                .rendered
+                .lines
+                .len()
+                .saturating_sub(EDITOR_HOVER_MAX_LINES);
             if delta > 0 {
                 let new = (hover.scroll_top + delta as usize).min(max_scroll);
                 if new != hover.scroll_top {
@@ -2326,9 +2345,10 @@ impl Engine {
         // Build content: header comment + one keymap per line
         let mut content = String::from(
             "# User keymaps — one per line.  :w to save.\n\
-             # Format: mode keys :command\n\
+             # Format: mode keys :command   or   mode keys keys\n\
              # Modes: n (normal), v (visual), i (insert), c (command)\n\
-             # Keys:  single char (x), modifier (<C-x>, <A-x>), sequence (gcc)\n\
+             # Keys:  single char (x), modifier (<C-x>, <A-x>), sequence (gcc), <leader>\n\
+             # Key right-hand sides are replayed without remapping (like noremap).\n\
              #\n\
              # In VSCode mode, \"n\" keymaps apply (use modifiers like <C-x>, <A-x>).\n\
              # Run :Keybindings to see all built-in keybindings and command names.\n\
@@ -2339,6 +2359,8 @@ impl Engine {
              # n gcc   :Commentary\n\
              # n <A-j> :move +1\n\
              # n <A-k> :move -1\n\
+             # n <leader>w :w<CR>\n\
+             # i jk <Esc>\n\
              #\n",
         );
         for km in &self.settings.keymaps {
@@ -2377,9 +2399,9 @@ impl Engine {
                 continue;
             }
             // Validate the keymap definition
-            if parse_keymap_def(trimmed).is_none() {
+            if parse_keymap_def(trimmed, self.settings.leader).is_none() {
                 return Err(format!(
-                    "Invalid keymap on line {}: \"{}\" (expected: mode keys :command)",
+                    "Invalid keymap on line {}: \"{}\" (expected: mode keys :command or mode keys keys)",
                     line_idx + 1,
                     trimmed
                 ));
@@ -3007,6 +3029,7 @@ impl Engine {
             file_path: canonical.clone(),
             pid: std::process::id(),
             modified: crate::core::swap::now_iso8601(),
+            file_mtime: state.file_mtime.map(crate::core::swap::mtime_secs),
         };
         let content = state.buffer.to_string();
         crate::core::swap::write_swap(&swap_path, &header, &content);
@@ -3085,6 +3108,18 @@ impl Engine {
 
         // Content differs → offer recovery via dialog.
         let fname = file_path.file_name().unwrap_or_default().to_string_lossy();
+        let mut body = vec![
+            format!("A swap file was found for \"{}\".", fname),
+            format!("Modified: {}", header.modified),
+            format!("Original PID: {} (no longer running)", header.pid),
+        ];
+        if header.file_is_newer() {
+            body.push(
+                "WARNING: the file was changed on disk after these edits began; \
+                 recovering replaces that version."
+                    .to_string(),
+            );
+        }
         self.pending_swap_recovery = Some(SwapRecovery {
             swap_path,
             recovered_content: content,
@@ -3093,11 +3128,7 @@ impl Engine {
         self.show_dialog(
             "swap_recovery",
             "Swap File Found",
-            vec![
-                format!("A swap file was found for \"{}\".", fname),
-                format!("Modified: {}", header.modified),
-                format!("Original PID: {} (no longer running)", header.pid),
-            ],
+            body,
             vec![
                 DialogButton {
                     label: "Recover".into(),
@@ -12,12 +12,27 @@ impl Engine {
         unicode: Option<char>,
         ctrl: bool,
     ) -> EngineAction {
+        // Interval auto-save waits for typing to pause.
+        self.autosave_key_at = Some(std::time::Instant::now());
+
         // Spell suggestion selection intercepts all keys.
         if self.spell_suggestions.is_some() {
             self.handle_spell_suggestion_key(key_name, unicode);
             return EngineAction::None;
         }
 
+        // Code-action popup intercepts all keys.
+        if self.code_action_menu_idx.is_some() {
+            self.handle_code_action_menu_key(key_name, unicode, ctrl);
+            return EngineAction::None;
+        }
+
+        // An interactive `:s///c` waits for y/n/a/q/l.
+        if self.substitute_confirm.is_some() {
+            self.handle_substitute_confirm_key(key_name, unicode, ctrl);
+            return EngineAction::None;
+        }
+
         // Clear message on any keypress (unless we're in command/search mode
         // or a dialog is open)
         if self.mode != Mode::Command && self.mode != Mode::Search && self.dialog.is_none() {
@@ -62,6 +77,20 @@ impl Engine {
             return EngineAction::None;
         }
 
+        // `:terminal` windows: in terminal insert mode keys go to the shell;
+        // `i`/`a`/`I`/`A` in Normal mode resume typing into it.
+        if self.terminal_insert && self.handle_terminal_window_key(key_name, unicode, ctrl) {
+            return EngineAction::None;
+        }
+        if self.mode == Mode::Normal
+            && !ctrl
+            && self.pending_key.is_none()
+            && self.pending_operator.is_none()
+            && self.terminal_window_enter_insert(unicode)
+        {
+            return EngineAction::None;
+        }
+
         // Context menu intercepts all keys when open.
         if self.context_menu.is_some() {
             let (consumed, _action) = self.handle_context_menu_key(key_name);
@@ -313,7 +342,10 @@ impl Engine {
                 }
             }
             Mode::Insert => {
-                self.handle_insert_key(key_name, unicode, ctrl, &mut changed);
+                if !self.snippet_handle_key(key_name, unicode, ctrl, &mut changed) {
+                    self.handle_insert_key(key_name, unicode, ctrl, &mut changed);
+                    self.snippet_track_edit();
+                }
             }
             Mode::Replace => {
                 self.handle_replace_key(key_name, unicode, ctrl, &mut changed);
@@ -368,7 +400,11 @@ impl Engine {
 
             // A normal-mode buffer modification (paste, delete, replace…) invalidates
             // any extra-cursor positions — clear them so stale cursors don't appear.
-            if was_normal && !self.view().extra_cursors.is_empty() {
+            // Multi-cursor edits (`x` at every cursor) re-position them instead.
+            if was_normal
+                && !std::mem::take(&mut self.mc_keep_extra_cursors)
+                && !self.view().extra_cursors.is_empty()
+            {
                 self.view_mut().extra_cursors.clear();
             }
             self.set_dirty(true);
@@ -402,6 +438,7 @@ impl Engine {
             if !self.search_matches.is_empty() {
                 self.run_search();
             }
+            self.blame_buffer_changed();
             let t6 = std::time::Instant::now();
 
             // Log timing for performance profiling (only when total > 10ms)
@@ -498,6 +535,9 @@ impl Engine {
 
         // Special keys
         match inner {
+            // Recordings store Escape as a raw \x1b; `<Esc>` is accepted so a
+            // macro edited as text can spell it out.
+            "Esc" => Some(("Escape".to_string(), None, false)),
             "CR" => Some(("Return".to_string(), None, false)),
             "BS" => Some(("BackSpace".to_string(), None, false)),
             "Del" => Some(("Delete".to_string(), None, false)),
@@ -547,14 +587,43 @@ impl Engine {
         ctrl: bool,
         changed: &mut bool,
     ) -> EngineAction {
-        // Read-only guard: block keys that would enter Insert/Replace mode.
-        if self.active_buffer_state().read_only {
+        // Read-only guard: block keys that would enter Insert/Replace mode
+        // or change the text before any state changes.
+        let state = self.active_buffer_state();
+        if (state.read_only || !state.modifiable)
+            && !ctrl
+            && self.pending_key.is_none()
+            && self.pending_operator.is_none()
+        {
             let blocked = matches!(
                 unicode,
-                Some('i' | 'a' | 'o' | 'O' | 'I' | 'A' | 's' | 'S' | 'c' | 'C' | 'R')
+                Some(
+                    'i' | 'a'
+                        | 'o'
+                        | 'O'
+                        | 'I'
+                        | 'A'
+                        | 's'
+                        | 'S'
+                        | 'c'
+                        | 'C'
+                        | 'R'
+                        | 'x'
+                        | 'X'
+                        | 'd'
+                        | 'D'
+                        | 'p'
+                        | 'P'
+                        | 'J'
+                        | 'r'
+                        | '~'
+                        | '>'
+                        | '<'
+                        | '='
+                )
             );
-            if blocked && self.pending_key.is_none() && self.pending_operator.is_none() {
-                self.message = "Buffer is read-only".to_string();
+            if blocked {
+                self.check_modifiable();
                 return EngineAction::None;
             }
         }
@@ -592,6 +661,7 @@ impl Engine {
             && self.pending_operator.is_none()
             && self.pending_find_operator.is_none()
             && self.pending_text_object.is_none()
+            && self.pending_surround.is_none()
             && unicode == Some(self.settings.leader)
         {
             self.leader_partial = Some(String::new());
@@ -605,23 +675,14 @@ impl Engine {
                     // Half-page down (fold-aware)
                     let count = self.take_count();
                     let half = self.viewport_lines() / 2;
-                    let scroll_amount = half * count;
-                    let max_line = self.buffer().len_lines().saturating_sub(1);
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().next_visible_line(cur, scroll_amount, max_line);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(half * count, true);
                     return EngineAction::None;
                 }
                 "u" => {
                     // Ctrl-U: Half-page up (fold-aware)
                     let count = self.take_count();
                     let half = self.viewport_lines() / 2;
-                    let scroll_amount = half * count;
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().prev_visible_line(cur, scroll_amount);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(half * count, false);
                     return EngineAction::None;
                 }
                 "r" => {
@@ -639,23 +700,14 @@ impl Engine {
                     // Full page down (fold-aware)
                     let count = self.take_count();
                     let viewport = self.viewport_lines();
-                    let scroll_amount = viewport * count;
-                    let max_line = self.buffer().len_lines().saturating_sub(1);
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().next_visible_line(cur, scroll_amount, max_line);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(viewport * count, true);
                     return EngineAction::None;
                 }
                 "b" => {
                     // Full page up (fold-aware)
                     let count = self.take_count();
                     let viewport = self.viewport_lines();
-                    let scroll_amount = viewport * count;
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().prev_visible_line(cur, scroll_amount);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(viewport * count, false);
                     return EngineAction::None;
                 }
                 "w" => {
@@ -726,12 +778,17 @@ impl Engine {
                     // Ctrl-Y: scroll up one line (fold-aware, cursor stays)
                     let count = self.take_count();
                     self.scroll_up_visible(count);
-                    // Keep cursor visible
+                    // Keep cursor visible: clamp it to the last visible line,
+                    // counting closed folds as one line.
                     let viewport = self.viewport_lines();
-                    if viewport > 0 && self.view().cursor.line >= self.view().scroll_top + viewport
-                    {
-                        self.view_mut().cursor.line = self.view().scroll_top + viewport - 1;
-                        self.clamp_cursor_col();
+                    if viewport > 0 {
+                        let max_line = self.buffer().len_lines().saturating_sub(1);
+                        let st = self.view().scroll_top;
+                        let bottom = self.view().next_visible_line(st, viewport - 1, max_line);
+                        if self.view().cursor.line > bottom {
+                            self.view_mut().cursor.line = bottom;
+                            self.clamp_cursor_col();
+                        }
                     }
                     return EngineAction::None;
                 }
@@ -788,6 +845,12 @@ impl Engine {
             return self.handle_pending_key(pending, key_name, unicode, changed);
         }
 
+        // Surround delimiter keys (ds{c}, cs{c}{c}, ys{motion}{c}).
+        if let Some(pending) = self.pending_surround.take() {
+            self.handle_surround_key(pending, unicode, changed);
+            return EngineAction::None;
+        }
+
         // Handle count accumulation (digits 1-9, and 0 when count already exists)
         if let Some(ch) = unicode {
             match ch {
@@ -1018,7 +1081,7 @@ impl Engine {
                             );
                         }
                         Err(e) => {
-                            self.message = e;
+                            self.set_error_message(e);
                         }
                     }
                     *changed = true;
@@ -1030,6 +1093,18 @@ impl Engine {
                 let line = self.view().cursor.line;
                 self.view_mut().cursor.col = self.get_max_cursor_col(line);
             }
+            Some('x') if !self.view().extra_cursors.is_empty() => {
+                let count = self.take_count();
+                if self.mc_delete_chars(count) {
+                    *changed = true;
+                    self.last_change = Some(Change {
+                        op: ChangeOp::Delete,
+                        text: String::new(),
+                        count,
+                        motion: Some(Motion::Right),
+                    });
+                }
+            }
             Some('x') => {
                 let count = self.take_count();
                 let line = self.view().cursor.line;
@@ -1099,9 +1174,11 @@ impl Engine {
             }
             Some('w') => {
                 let count = self.take_count();
-                for _ in 0..count {
-                    self.move_word_forward();
-                }
+                self.mc_move(|e| {
+                    for _ in 0..count {
+                        e.move_word_forward();
+                    }
+                });
             }
             Some('W') => {
                 let count = self.take_count();
@@ -1111,9 +1188,11 @@ impl Engine {
             }
             Some('b') => {
                 let count = self.take_count();
-                for _ in 0..count {
-                    self.move_word_backward();
-                }
+                self.mc_move(|e| {
+                    for _ in 0..count {
+                        e.move_word_backward();
+                    }
+                });
             }
             Some('B') => {
                 let count = self.take_count();
@@ -1170,6 +1249,7 @@ impl Engine {
                 // R: enter Replace mode
                 self.start_undo_group();
                 self.insert_text_buffer.clear();
+                self.replace_overwritten.clear();
                 self.mode = Mode::Replace;
                 self.count = None;
             }
@@ -1592,6 +1672,7 @@ impl Engine {
                 self.command_cursor = 0;
                 self.search_direction = SearchDirection::Forward;
                 self.search_start_cursor = Some(self.view().cursor);
+                self.search_start_scroll = Some((self.view().scroll_top, self.view().scroll_left));
                 self.search_word_bounded = false; // Clear word-boundary mode
                 self.count = None; // Clear count when entering search mode
             }
@@ -1601,6 +1682,7 @@ impl Engine {
                 self.command_cursor = 0;
                 self.search_direction = SearchDirection::Backward;
                 self.search_start_cursor = Some(self.view().cursor);
+                self.search_start_scroll = Some((self.view().scroll_top, self.view().scroll_left));
                 self.search_word_bounded = false; // Clear word-boundary mode
                 self.count = None; // Clear count when entering search mode
             }
@@ -1612,10 +1694,7 @@ impl Engine {
                     self.pending_key = None;
                     self.view_mut().extra_cursors.clear();
                     // Clear search highlights (like :noh)
-                    if !self.search_matches.is_empty() {
-                        self.search_matches.clear();
-                        self.search_index = None;
-                    }
+                    self.clear_search_highlight();
                 }
                 "Left" => {
                     let count = self.take_count();
@@ -1837,7 +1916,7 @@ impl Engine {
                                     self.scroll_cursor_center();
                                 }
                             }
-                            Err(e) => self.message = e,
+                            Err(e) => self.set_error_message(e),
                         }
                     } else {
                         self.message = "No file path under cursor".to_string();
@@ -2179,6 +2258,7 @@ impl Engine {
                     // gR: enter Virtual Replace mode (tab-aware overwrite)
                     self.start_undo_group();
                     self.insert_text_buffer.clear();
+                    self.replace_overwritten.clear();
                     self.virtual_replace = true;
                     self.mode = Mode::Replace;
                     self.count = None;
@@ -2207,13 +2287,17 @@ impl Engine {
                 }
                 _ => {}
             },
-            // gc pending: gcc toggles comment on current line (count-aware)
+            // gc pending: gcc toggles comment on current line (count-aware),
+            // anything else is a motion/text object for the gc operator ('C').
             '\x03' => {
                 if let Some('c') = unicode {
                     let count = self.take_count().max(1);
                     let line = self.view().cursor.line + 1; // 1-indexed
                     self.toggle_comment(line, line + count - 1);
                     *changed = true;
+                } else if unicode.is_some() {
+                    self.operator_count = self.count.take();
+                    return self.handle_operator_motion('C', key_name, unicode, changed);
                 }
             }
             ']' => match unicode {
@@ -2414,16 +2498,8 @@ impl Engine {
             '"' => {
                 // Register selection: "x sets selected_register for next operation
                 // Uppercase A-Z appends to lowercase register
-                if let Some(ch) = unicode {
-                    if ch.is_ascii_lowercase()
-                        || ch.is_ascii_uppercase()
-                        || ch == '"'
-                        || ch == '+'
-                        || ch == '*'
-                        || ch.is_ascii_digit()
-                    {
-                        self.selected_register = Some(ch);
-                    }
+                if let Some(ch) = unicode.filter(|&c| Self::is_register_name(c)) {
+                    self.selected_register = Some(ch);
                 }
             }
             'q' => {
@@ -2433,7 +2509,7 @@ impl Engine {
                         self.open_cmdline_window(false);
                     } else if ch == '/' || ch == '?' {
                         self.open_cmdline_window(true);
-                    } else if ch.is_ascii_lowercase() {
+                    } else if ch.is_ascii_alphabetic() {
                         self.start_macro_recording(ch);
                     } else {
                         self.message = "Invalid register for macro".to_string();
@@ -2818,12 +2894,21 @@ impl Engine {
                     Some('o') => self.cmd_fold_open(),
                     Some('c') => self.cmd_fold_close(),
                     Some('R') => {
-                        self.view_mut().open_all_folds();
+                        self.cmd_fold_open_all();
                         if self.diff_unchanged_hidden {
                             self.diff_unchanged_hidden = false;
                         }
                     }
                     Some('M') => self.cmd_fold_close_all(),
+                    // Fold: level
+                    Some('m') => {
+                        let count = self.take_count();
+                        self.cmd_fold_more(count);
+                    }
+                    Some('r') => {
+                        let count = self.take_count();
+                        self.cmd_fold_reduce(count);
+                    }
                     // Fold: recursive
                     Some('A') => self.cmd_fold_toggle_recursive(),
                     Some('O') => self.cmd_fold_open_recursive(),
@@ -2938,6 +3023,30 @@ impl Engine {
             return EngineAction::None;
         }
 
+        // vim-surround: ys{motion}{c}, yss{c}, ds{c}, cs{c}{c}.  `s` is not a
+        // motion, so it is free here unless a text object (`dis`) is pending.
+        if unicode == Some('s') && self.pending_text_object.is_none() {
+            match operator {
+                'y' => {
+                    self.pending_operator = Some('s');
+                    return EngineAction::None;
+                }
+                'd' => {
+                    self.pending_surround = Some(SurroundPending::Delete);
+                    return EngineAction::None;
+                }
+                'c' => {
+                    self.pending_surround = Some(SurroundPending::Change(None));
+                    return EngineAction::None;
+                }
+                's' => {
+                    self.surround_begin_current_line();
+                    return EngineAction::None;
+                }
+                _ => {}
+            }
+        }
+
         // Handle 'g' motion for operator + g{x} (cgn/dgn, dgg, dge, etc.)
         if unicode == Some('g') {
             // Re-set pending_key = 'g' and pending_operator = operator so next key
@@ -3894,6 +4003,24 @@ impl Engine {
                 self.command_buffer = format!("{},{}!", start_line + 1, end_line + 1);
                 self.command_cursor = self.command_buffer.chars().count();
             }
+            's' => {
+                // ys{motion}: wait for the surround delimiter
+                self.pending_surround = Some(SurroundPending::Add {
+                    start,
+                    end,
+                    linewise: false,
+                });
+            }
+            'C' => {
+                // gc{motion}: toggle comments on the lines the range touches
+                let start_line = self.buffer().content.char_to_line(start);
+                let end_line = self
+                    .buffer()
+                    .content
+                    .char_to_line(end.saturating_sub(1).max(start));
+                self.toggle_comment(start_line + 1, end_line + 1);
+                *changed = true;
+            }
             '@' => {
                 // g@: call user-defined operatorfunc (charwise)
                 let start_line = self.buffer().content.char_to_line(start);
@@ -4022,12 +4149,29 @@ impl Engine {
                 self.view_mut().cursor = saved;
                 self.clamp_cursor_col();
             }
+            'C' => {
+                // gc{motion}: toggle comments on lines
+                self.toggle_comment(start_line + 1, end_line + 1);
+                *changed = true;
+            }
             '!' => {
                 // Filter: switch to command mode with range + !
                 self.mode = Mode::Command;
                 self.command_buffer = format!("{},{}!", start_line + 1, end_line + 1);
                 self.command_cursor = self.command_buffer.chars().count();
             }
+            's' => {
+                // ys{motion}: wait for the surround delimiter
+                let start = self.buffer().line_to_char(start_line);
+                let end = self
+                    .buffer()
+                    .line_to_char((end_line + 1).min(self.buffer().len_lines()));
+                self.pending_surround = Some(SurroundPending::Add {
+                    start,
+                    end,
+                    linewise: true,
+                });
+            }
             '@' => {
                 // g@: call user-defined operatorfunc (linewise)
                 // Set '[ and '] marks for the range, then call the plugin
@@ -4091,7 +4235,7 @@ impl Engine {
     /// If `op` is Some('c'), delete the match and enter Insert (cgn).
     pub(crate) fn cmd_gn(&mut self, op: Option<char>, backward: bool, changed: &mut bool) {
         if self.search_matches.is_empty() {
-            self.message = format!("Pattern not found: {}", self.search_query);
+            self.set_error_message(format!("E486: Pattern not found: {}", self.search_query));
             return;
         }
         let line = self.view().cursor.line;
@@ -4192,6 +4336,24 @@ impl Engine {
         EngineAction::None
     }
 
+    /// True when `char_idx` in the active buffer lies inside a comment or
+    /// string highlight span, where auto-pairs stay out of the way.
+    pub(crate) fn in_comment_or_string(&self, char_idx: usize) -> bool {
+        let byte = self.buffer().content.char_to_byte(char_idx);
+        self.active_buffer_state()
+            .highlights
+            .iter()
+            .any(|(start, end, scope)| {
+                if scope.starts_with("comment") {
+                    *start < byte && byte <= *end
+                } else if scope.starts_with("string") {
+                    *start < byte && byte < *end
+                } else {
+                    false
+                }
+            })
+    }
+
     pub(crate) fn handle_leader_key(&mut self, unicode: Option<char>) -> EngineAction {
         let ch = match unicode {
             Some(c) => c,
@@ -4204,12 +4366,12 @@ impl Engine {
         let mut partial = self.leader_partial.take().unwrap_or_default();
         partial.push(ch);
 
-        // All known built-in leader sequences
-        const SEQUENCES: &[&str] = &[
-            "b", "rn", "gf", "gF", "gi", "gb", "ca", "sb", "sf", "sg", "sk", "so", "sp", "sw",
-        ];
-
         match partial.as_str() {
+            "a" => self.harpoon_add(),
+            "1" | "2" | "3" | "4" => {
+                let slot = (ch as u8 - b'0') as usize;
+                self.harpoon_jump(slot);
+            }
             "b" => {
                 // Enter breadcrumb focus mode
                 self.rebuild_breadcrumb_segments();
@@ -4266,6 +4428,15 @@ impl Engine {
             "sp" => {
                 self.open_picker(PickerSource::Commands);
             }
+            "fr" => {
+                self.open_picker(PickerSource::RecentFiles);
+            }
+            "sS" => {
+                // Workspace symbols (queried live from the server)
+                self.open_picker(PickerSource::CommandCenter);
+                self.picker_query = "#".to_string();
+                self.picker_filter();
+            }
             "sw" => {
                 // Grep word under cursor
                 if let Some(word) = self.word_under_cursor() {
@@ -4285,7 +4456,11 @@ impl Engine {
                 }
 
                 // Check if partial is a prefix of a built-in sequence
-                if SEQUENCES.iter().any(|seq| seq.starts_with(s)) {
+                let is_builtin_prefix = which_key::WHICH_KEY_BINDINGS.iter().any(|(seq, _)| {
+                    seq.strip_prefix("<leader>")
+                        .is_some_and(|seq| seq.starts_with(s))
+                });
+                if is_builtin_prefix {
                     self.leader_partial = Some(partial);
                     return EngineAction::None;
                 }
@@ -4476,7 +4651,7 @@ impl Engine {
 
         // Record for dot repeat (d/y/>/</gu/gU/g~ with motion).
         // 'c' is recorded after Esc from insert mode, not here.
-        if operator != 'c' && operator != 'y' {
+        if operator != 'c' && operator != 'y' && operator != 's' {
             let motion_enum = match motion {
                 'w' => Some(Motion::WordForward),
                 'b' => Some(Motion::WordBackward),
@@ -4527,7 +4702,7 @@ impl Engine {
 
         // Find the matching bracket position
         if let Some(match_pos) =
-            self.find_matching_bracket(start_pos, open_char, close_char, is_opening)
+            self.find_matching_bracket_bounded(start_pos, open_char, close_char, is_opening)
         {
             // Determine range to delete (inclusive of both brackets)
             let (delete_start, delete_end) = if is_opening {
@@ -4594,6 +4769,58 @@ impl Engine {
         }
     }
 
+    /// Expand the insert-mode abbreviation ending at the cursor, which is
+    /// about to be followed by `trigger` (a non-keyword char; `\n` for Enter).
+    /// Like Vim, an all-keyword lhs must start a word, and any other lhs must
+    /// follow whitespace and be triggered by whitespace when it ends in a
+    /// non-keyword char.  Returns true if the buffer changed.
+    fn expand_abbreviation(&mut self, trigger: char) -> bool {
+        if self.settings.abbreviations.is_empty() {
+            return false;
+        }
+        let line = self.view().cursor.line;
+        let col = self.view().cursor.col;
+        let before: Vec<char> = self.buffer().content.line(line).chars().take(col).collect();
+        let mut best: Option<(String, String)> = None;
+        for (lhs, rhs) in &self.settings.abbreviations {
+            let lhs_chars: Vec<char> = lhs.chars().collect();
+            let n = lhs_chars.len();
+            if n == 0 || n > before.len() || before[before.len() - n..] != lhs_chars[..] {
+                continue;
+            }
+            let prev = before.len().checked_sub(n + 1).map(|i| before[i]);
+            let ok = if lhs_chars.iter().all(|&c| Self::is_word_char(c)) {
+                !matches!(prev, Some(c) if Self::is_word_char(c))
+            } else {
+                let ends_in_keyword = Self::is_word_char(lhs_chars[n - 1]);
+                !matches!(prev, Some(c) if !c.is_whitespace())
+                    && (ends_in_keyword || trigger.is_whitespace())
+            };
+            let longer = match &best {
+                Some((b, _)) => n > b.chars().count(),
+                None => true,
+            };
+            if ok && longer {
+                best = Some((lhs.clone(), rhs.clone()));
+            }
+        }
+        let Some((lhs, rhs)) = best else {
+            return false;
+        };
+        let n = lhs.chars().count();
+        let start = self.buffer().line_to_char(line) + col - n;
+        self.delete_with_undo(start, start + n);
+        self.insert_with_undo(start, &rhs);
+        self.view_mut().cursor.col = col - n + rhs.chars().count();
+        // Keep the `.` repeat text in step with the buffer.
+        if self.insert_text_buffer.ends_with(&lhs) {
+            let keep = self.insert_text_buffer.len() - lhs.len();
+            self.insert_text_buffer.truncate(keep);
+            self.insert_text_buffer.push_str(&rhs);
+        }
+        true
+    }
+
     pub(crate) fn handle_insert_key(
         &mut self,
         key_name: &str,
@@ -4636,11 +4863,16 @@ impl Engine {
             }
             if self.completion_idx.is_none() {
                 let (prefix, start_col) = self.completion_prefix_at_cursor();
-                let candidates = self.word_completions_for_prefix(&prefix);
+                let mut candidates = self.word_completions_for_prefix(&prefix, !next);
                 if candidates.is_empty() {
                     self.message = "No completions".to_string();
                     return;
                 }
+                if !next {
+                    // Ctrl-P lists matches bottom-up and starts on the last,
+                    // the nearest one above the cursor.
+                    candidates.reverse();
+                }
                 self.completion_start_col = start_col;
                 self.completion_candidates = candidates;
                 let idx = if next {
@@ -4709,11 +4941,27 @@ impl Engine {
             }
         }
 
-        // ── Tab: accept display-only popup OR fall through ────────────────────
-        if !ctrl && key_name == "Tab" && self.completion_display_only {
+        // ── Ctrl-Y / Enter: keep the Ctrl-N/P candidate already inserted ─────
+        if self.completion_idx.is_some()
+            && !self.completion_display_only
+            && ((ctrl && key_name == "y") || (!ctrl && key_name == "Return"))
+        {
+            self.dismiss_completion();
+            return;
+        }
+
+        // ── Tab / Ctrl-Y: accept display-only popup OR fall through ───────────
+        let accept_key = (!ctrl && key_name == "Tab") || (ctrl && key_name == "y");
+        if accept_key && self.completion_display_only {
             if let Some(idx) = self.completion_idx {
+                let snippet = self.selected_completion_snippet();
+                let start =
+                    self.buffer().line_to_char(self.view().cursor.line) + self.completion_start_col;
                 self.apply_completion_candidate(idx);
                 self.dismiss_completion();
+                if let Some(snippet) = snippet {
+                    self.start_snippet_session(&snippet, start);
+                }
                 *changed = true;
                 return;
             }
@@ -4865,7 +5113,7 @@ impl Engine {
             let line = self.view().cursor.line;
             let line_start = self.buffer().line_to_char(line);
             let sw = self.effective_shift_width();
-            let indent = if self.settings.expand_tab {
+            let indent = if self.effective_expand_tab() {
                 " ".repeat(sw)
             } else {
                 "\t".to_string()
@@ -5035,8 +5283,26 @@ impl Engine {
             return;
         }
 
+        // ── Abbreviations: expand the text before a non-keyword character ────
+        // (Ctrl-V returned above, so a literal trigger never expands.)
+        if !ctrl && self.view().extra_cursors.is_empty() {
+            let trigger = if key_name == "Return" {
+                Some('\n')
+            } else {
+                unicode.filter(|&c| !Self::is_word_char(c))
+            };
+            if let Some(trigger) = trigger {
+                if self.expand_abbreviation(trigger) {
+                    *changed = true;
+                }
+            }
+        }
+
         match key_name {
             "Escape" => {
+                // Replay a visual-block I/A before closing the group so the
+                // whole multi-line insert is undone by a single `u`.
+                self.apply_visual_block_insert();
                 self.finish_undo_group();
                 // Record the insert operation for repeat and ". register
                 if !self.insert_text_buffer.is_empty() {
@@ -5066,53 +5332,6 @@ impl Engine {
                     }
                 }
                 self.pending_change_motion = None;
-                // Apply visual block insert/append to remaining lines
-                if let Some((start_line, end_line, col, _is_append, virtual_end)) =
-                    self.visual_block_insert_info.take()
-                {
-                    let text = self.insert_text_buffer.clone();
-                    if !text.is_empty() {
-                        // The first line was already typed into; apply to remaining lines
-                        let first_typed_line = start_line;
-                        self.start_undo_group();
-                        for line in start_line..=end_line {
-                            if line == first_typed_line {
-                                continue;
-                            }
-                            if line >= self.buffer().len_lines() {
-                                break;
-                            }
-                            let line_len = self.buffer().line_len_chars(line);
-                            let line_len_no_nl = if line_len > 0
-                                && self
-                                    .buffer()
-                                    .content
-                                    .char(self.buffer().line_to_char(line) + line_len - 1)
-                                    == '\n'
-                            {
-                                line_len - 1
-                            } else {
-                                line_len
-                            };
-                            // In virtual-end mode (`$<C-v>...A`), the insert column is this
-                            // specific line's own end — no padding needed, just append.
-                            // Otherwise use the captured column and pad if the line is shorter.
-                            let target_col = if virtual_end { line_len_no_nl } else { col };
-                            let insert_col = target_col.min(line_len_no_nl);
-                            let pad = target_col.saturating_sub(line_len_no_nl);
-                            let char_idx = self.buffer().line_to_char(line) + insert_col;
-                            if pad > 0 {
-                                let spaces = " ".repeat(pad);
-                                self.insert_with_undo(char_idx, &spaces);
-                            }
-                            self.insert_with_undo(
-                                self.buffer().line_to_char(line) + target_col,
-                                &text,
-                            );
-                        }
-                        self.finish_undo_group();
-                    }
-                }
                 // Repeat o/O insert for count > 1: duplicate typed text on new lines
                 if self.insert_open_count > 1 && !self.insert_text_buffer.is_empty() {
                     let text = self.insert_text_buffer.clone();
@@ -5154,6 +5373,7 @@ impl Engine {
                 // we're back in Normal mode. cursor_move is suppressed during
                 // Insert mode to avoid stale blame on uncommitted lines.
                 self.fire_cursor_move_hook();
+                self.blame_refresh_if_stale();
             }
             "BackSpace" => {
                 if !self.view().extra_cursors.is_empty() {
@@ -5238,8 +5458,8 @@ impl Engine {
             }
             "Tab" => {
                 if !self.view().extra_cursors.is_empty() {
-                    let tab_text = if self.settings.expand_tab {
-                        " ".repeat(self.settings.tabstop as usize)
+                    let tab_text = if self.effective_expand_tab() {
+                        " ".repeat(self.effective_tabstop())
                     } else {
                         "\t".to_string()
                     };
@@ -5250,8 +5470,8 @@ impl Engine {
                     let line = self.view().cursor.line;
                     let col = self.view().cursor.col;
                     let char_idx = self.buffer().line_to_char(line) + col;
-                    if self.settings.expand_tab {
-                        let n = self.settings.tabstop as usize;
+                    if self.effective_expand_tab() {
+                        let n = self.effective_tabstop();
                         let spaces = " ".repeat(n);
                         self.insert_with_undo(char_idx, &spaces);
                         self.insert_text_buffer.push_str(&spaces);
@@ -5315,8 +5535,10 @@ impl Engine {
                         } else if self.settings.auto_pairs && closing_pair.is_some() {
                             let closer = closing_pair.unwrap();
                             // Smart context for quotes: only auto-pair if preceded by
-                            // whitespace, bracket, or BOL
-                            let should_pair = if is_quote_char(ch) {
+                            // whitespace, bracket, or BOL.  Never inside comments/strings.
+                            let should_pair = if self.in_comment_or_string(char_idx) {
+                                false
+                            } else if is_quote_char(ch) {
                                 if char_idx == 0 {
                                     true
                                 } else {
@@ -5444,6 +5666,7 @@ impl Engine {
         if !self.search_matches.is_empty() {
             self.run_search();
         }
+        self.blame_buffer_changed();
         self.ensure_cursor_visible();
         self.sync_scroll_binds();
         self.update_bracket_match();
@@ -5635,22 +5858,18 @@ impl Engine {
                 self.history_search_query.clear();
                 self.history_search_index = None;
 
-                if self.history.command_history.is_empty() {
-                    return EngineAction::None;
-                }
                 if self.command_history_index.is_none() {
                     self.command_typing_buffer = self.command_buffer.clone();
-                    self.command_history_index = Some(self.history.command_history.len() - 1);
-                } else if let Some(idx) = self.command_history_index {
-                    if idx > 0 {
-                        self.command_history_index = Some(idx - 1);
-                    }
                 }
-                if let Some(idx) = self.command_history_index {
-                    if let Some(cmd) = self.history.command_history.get(idx) {
-                        self.command_buffer = cmd.clone();
-                        self.command_cursor = self.command_buffer.chars().count();
-                    }
+                if let Some(idx) = history_step(
+                    &self.history.command_history,
+                    self.command_history_index,
+                    &self.command_typing_buffer,
+                    true,
+                ) {
+                    self.command_history_index = Some(idx);
+                    self.command_buffer = self.history.command_history[idx].clone();
+                    self.command_cursor = self.command_buffer.chars().count();
                 }
                 EngineAction::None
             }
@@ -5663,18 +5882,18 @@ impl Engine {
                 if self.command_history_index.is_none() {
                     return EngineAction::None;
                 }
-                let idx = self.command_history_index.unwrap();
-                if idx + 1 >= self.history.command_history.len() {
-                    self.command_buffer = self.command_typing_buffer.clone();
-                    self.command_cursor = self.command_buffer.chars().count();
-                    self.command_history_index = None;
-                } else {
-                    self.command_history_index = Some(idx + 1);
-                    if let Some(cmd) = self.history.command_history.get(idx + 1) {
-                        self.command_buffer = cmd.clone();
-                        self.command_cursor = self.command_buffer.chars().count();
-                    }
-                }
+                self.command_history_index = history_step(
+                    &self.history.command_history,
+                    self.command_history_index,
+                    &self.command_typing_buffer,
+                    false,
+                );
+                self.command_buffer = match self.command_history_index {
+                    Some(idx) => self.history.command_history[idx].clone(),
+                    // Past the newest entry: back to what was being typed
+                    None => self.command_typing_buffer.clone(),
+                };
+                self.command_cursor = self.command_buffer.chars().count();
                 EngineAction::None
             }
             "Tab" | "ISO_Left_Tab" => {
@@ -5889,9 +6108,11 @@ impl Engine {
                 self.search_history_index = None;
                 self.search_typing_buffer.clear();
 
-                // Restore cursor to original position (incremental search)
-                if let Some(start_cursor) = self.search_start_cursor.take() {
-                    self.view_mut().cursor = start_cursor;
+                // Restore cursor and scroll to the original position (incremental search)
+                if self.search_start_cursor.is_some() {
+                    self.restore_search_start();
+                    self.search_start_cursor = None;
+                    self.search_start_scroll = None;
                     // Clear search matches and query
                     self.search_matches.clear();
                     self.search_index = None;
@@ -5902,6 +6123,11 @@ impl Engine {
                 self.mode = Mode::Normal;
                 let query = self.command_buffer.clone();
                 self.command_buffer.clear();
+                let delim = match self.search_direction {
+                    SearchDirection::Forward => '/',
+                    SearchDirection::Backward => '?',
+                };
+                let (pattern, offset) = parse_search_offset(&query, delim);
 
                 // Add to search history
                 if !query.is_empty() {
@@ -5916,6 +6142,7 @@ impl Engine {
                     }
 
                     self.search_start_cursor = None; // Clear saved cursor position
+                    self.search_start_scroll = None;
 
                     self.history.add_search(&query);
                     self.search_history_index = None;
@@ -5925,11 +6152,30 @@ impl Engine {
                     let _ = self.session.save();
                     let _ = self.history.save();
 
-                    self.search_query = query;
+                    self.search_offset = offset;
+                    // `//e` etc. reuses the previous pattern with a new offset
+                    if !pattern.is_empty() {
+                        self.search_query = pattern.clone();
+                    }
+                    if self.search_query.is_empty() {
+                        return;
+                    }
                     self.run_search();
-                    // If incremental search is enabled, cursor is already at the correct match
+                    // If incremental search is enabled, cursor is already at the matched
+                    // text and only the offset remains to be applied.
                     // Otherwise, jump to first match in the appropriate direction
-                    if !self.settings.incremental_search {
+                    if self.settings.incremental_search && !pattern.is_empty() {
+                        let cur = self.view().cursor;
+                        let cursor_char = self.buffer().line_to_char(cur.line) + cur.col;
+                        if let Some(idx) = self
+                            .search_matches
+                            .iter()
+                            .position(|&(start, _)| start == cursor_char)
+                        {
+                            self.search_index = Some(idx);
+                            self.jump_to_search_match(idx);
+                        }
+                    } else {
                         match self.search_direction {
                             SearchDirection::Forward => self.search_next(),
                             SearchDirection::Backward => self.search_prev(),
@@ -5937,6 +6183,7 @@ impl Engine {
                     }
                 } else {
                     self.search_start_cursor = None;
+                    self.search_start_scroll = None;
                     // Empty query with existing search — repeat in current direction
                     if !self.search_query.is_empty() {
                         self.run_search();
@@ -5948,26 +6195,21 @@ impl Engine {
                 }
             }
             "Up" => {
-                // Cycle to previous search
-                if self.history.search_history.is_empty() {
-                    return;
-                }
-
-                // First Up press: save current typing
+                // Cycle to previous search; first Up press saves current typing
                 if self.search_history_index.is_none() {
                     self.search_typing_buffer = self.command_buffer.clone();
-                    self.search_history_index = Some(self.history.search_history.len() - 1);
-                } else if let Some(idx) = self.search_history_index {
-                    if idx > 0 {
-                        self.search_history_index = Some(idx - 1);
-                    }
                 }
-
-                // Load history entry
-                if let Some(idx) = self.search_history_index {
-                    if let Some(query) = self.history.search_history.get(idx) {
-                        self.command_buffer = query.clone();
-                        self.command_cursor = self.command_buffer.chars().count();
+                if let Some(idx) = history_step(
+                    &self.history.search_history,
+                    self.search_history_index,
+                    &self.search_typing_buffer,
+                    true,
+                ) {
+                    self.search_history_index = Some(idx);
+                    self.command_buffer = self.history.search_history[idx].clone();
+                    self.command_cursor = self.command_buffer.chars().count();
+                    if self.settings.incremental_search {
+                        self.perform_incremental_search();
                     }
                 }
             }
@@ -5977,18 +6219,20 @@ impl Engine {
                     return;
                 }
 
-                let idx = self.search_history_index.unwrap();
-                if idx + 1 >= self.history.search_history.len() {
+                self.search_history_index = history_step(
+                    &self.history.search_history,
+                    self.search_history_index,
+                    &self.search_typing_buffer,
+                    false,
+                );
+                self.command_buffer = match self.search_history_index {
+                    Some(idx) => self.history.search_history[idx].clone(),
                     // Reached end, restore typing buffer
-                    self.command_buffer = self.search_typing_buffer.clone();
-                    self.command_cursor = self.command_buffer.chars().count();
-                    self.search_history_index = None;
-                } else {
-                    self.search_history_index = Some(idx + 1);
-                    if let Some(query) = self.history.search_history.get(idx + 1) {
-                        self.command_buffer = query.clone();
-                        self.command_cursor = self.command_buffer.chars().count();
-                    }
+                    None => self.search_typing_buffer.clone(),
+                };
+                self.command_cursor = self.command_buffer.chars().count();
+                if self.settings.incremental_search {
+                    self.perform_incremental_search();
                 }
             }
             "Left" => {
@@ -6034,9 +6278,11 @@ impl Engine {
                 }
                 if self.command_buffer.is_empty() {
                     self.mode = Mode::Normal;
-                    // Restore cursor to original position
-                    if let Some(start_cursor) = self.search_start_cursor.take() {
-                        self.view_mut().cursor = start_cursor;
+                    // Restore cursor and scroll to the original position
+                    if self.search_start_cursor.is_some() {
+                        self.restore_search_start();
+                        self.search_start_cursor = None;
+                        self.search_start_scroll = None;
                         self.search_matches.clear();
                         self.search_index = None;
                         self.search_query.clear();
@@ -6212,6 +6458,12 @@ impl Engine {
                     self.uppercase_visual_selection(changed);
                     return EngineAction::None;
                 }
+                'S' if self.pending_key.is_none() && self.mode != Mode::VisualBlock => {
+                    // S{char}: surround the selection
+                    self.count = None;
+                    self.surround_begin_visual();
+                    return EngineAction::None;
+                }
                 'J' if self.pending_key.is_none() => {
                     // Visual J: join all selected lines
                     self.count = None;
@@ -6399,6 +6651,13 @@ impl Engine {
                             self.view_mut().cursor.line = start_line;
                             self.view_mut().cursor.col = first_line_col;
                             self.start_undo_group();
+                            // Pad a first line that ends before the append column.
+                            let first_len = self.get_line_len_for_insert(start_line);
+                            if first_line_col > first_len {
+                                let pos = self.buffer().line_to_char(start_line) + first_len;
+                                let spaces = " ".repeat(first_line_col - first_len);
+                                self.insert_with_undo(pos, &spaces);
+                            }
                             self.insert_text_buffer.clear();
                         }
                     } else {
@@ -6419,43 +6678,25 @@ impl Engine {
                 "d" => {
                     let count = self.take_count();
                     let half = self.viewport_lines() / 2;
-                    let scroll_amount = half * count;
-                    let max_line = self.buffer().len_lines().saturating_sub(1);
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().next_visible_line(cur, scroll_amount, max_line);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(half * count, true);
                     return EngineAction::None;
                 }
                 "u" => {
                     let count = self.take_count();
                     let half = self.viewport_lines() / 2;
-                    let scroll_amount = half * count;
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().prev_visible_line(cur, scroll_amount);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(half * count, false);
                     return EngineAction::None;
                 }
                 "f" => {
                     let count = self.take_count();
                     let viewport = self.viewport_lines();
-                    let scroll_amount = viewport * count;
-                    let max_line = self.buffer().len_lines().saturating_sub(1);
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().next_visible_line(cur, scroll_amount, max_line);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(viewport * count, true);
                     return EngineAction::None;
                 }
                 "b" => {
                     let count = self.take_count();
                     let viewport = self.viewport_lines();
-                    let scroll_amount = viewport * count;
-                    let cur = self.view().cursor.line;
-                    let new_line = self.view().prev_visible_line(cur, scroll_amount);
-                    self.view_mut().cursor.line = new_line;
-                    self.clamp_cursor_col();
+                    self.scroll_page(viewport * count, false);
                     return EngineAction::None;
                 }
                 _ => {}
@@ -6466,16 +6707,8 @@ impl Engine {
         if let Some(pending) = self.pending_key.take() {
             if pending == '"' {
                 // Register selection: "x (uppercase A-Z appends to lowercase)
-                if let Some(ch) = unicode {
-                    if ch.is_ascii_lowercase()
-                        || ch.is_ascii_uppercase()
-                        || ch.is_ascii_digit()
-                        || ch == '"'
-                        || ch == '+'
-                        || ch == '*'
-                    {
-                        self.selected_register = Some(ch);
-                    }
+                if let Some(ch) = unicode.filter(|&c| Self::is_register_name(c)) {
+                    self.selected_register = Some(ch);
                 }
                 return EngineAction::None;
             }
@@ -6999,6 +7232,9 @@ impl Engine {
             "e ",
             "e!",
             "enew",
+            "w!",
+            "view ",
+            "checktime",
             // Buffers
             "bn",
             "bp",
@@ -7006,6 +7242,10 @@ impl Engine {
             "b#",
             "ls",
             "buffers",
+            "Harpoon",
+            "History",
+            "DocumentSymbols",
+            "WorkspaceSymbols",
             "files",
             // Splits & tabs
             "split",
@@ -7065,8 +7305,12 @@ impl Engine {
             "Gp",
             "Gblame",
             "Gb",
+            "GitBlame",
             "Ghs",
             "Ghunk",
+            "GitStageHunk",
+            "GitUndoHunk",
+            "StripWhitespace",
             "Gpull",
             "Gfetch",
             "Gswitch",
@@ -7081,14 +7325,18 @@ impl Engine {
             "DiffPrev",
             "DiffToggleContext",
             // LSP
+            "Diagnostics",
             "LspInfo",
             "LspRestart",
             "LspStop",
             "LspInstall",
             "Lformat",
+            "LspFormat",
             "Rename",
+            "LspRename",
             "def",
             "refs",
+            "LspReferences",
             "hover",
             "LspImpl",
             "LspTypedef",
@@ -7145,12 +7393,19 @@ impl Engine {
             "diffthis",
             "diffoff",
             "diffsplit",
+            "diffunified ",
             // Misc ex commands
             "sort",
             "terminal",
+            "TerminalPanel",
             "cd ",
+            "lcd ",
+            "pwd",
+            "mksession ",
+            "source ",
             "make",
             "copen",
+            "SearchToQuickfix",
             "cn",
             "cp",
             "cc",
@@ -7159,6 +7414,8 @@ impl Engine {
             "Plugin",
             "map",
             "unmap",
+            "iabbrev ",
+            "unabbreviate ",
         ]
     }
 
@@ -7184,6 +7441,8 @@ impl Engine {
             "ic",
             "smartcase",
             "scs",
+            "wrapscan",
+            "ws",
             "cursorline",
             "cul",
             "autoread",
@@ -7195,9 +7454,17 @@ impl Engine {
             "ai_completions",
             "formatonsave",
             "fos",
+            "inlayhints",
+            "ih",
             "showhiddenfiles",
             "shf",
             "swapfile",
+            "undofile",
+            "backup",
+            "writebackup",
+            "fixendofline",
+            "fixeol",
+            "udf",
             "breadcrumbs",
             "autohidepanels",
             // Value options
@@ -7213,9 +7480,17 @@ impl Engine {
             "tw",
             "updatetime",
             "ut",
+            "largefile",
+            "autosave",
+            "autosaveinterval",
+            "asi",
             "mode",
             "filetype",
             "ft",
+            "fileformat",
+            "ff",
+            "fileencoding",
+            "fenc",
         ]
     }
 
@@ -7230,6 +7505,20 @@ impl Engine {
             let cmd_prefix = &partial[..space_pos];
             let arg_partial = partial[space_pos + 1..].trim_start();
 
+            if matches!(cmd_prefix, "source" | "so") {
+                return complete_source_arg(arg_partial, self.effective_cwd())
+                    .into_iter()
+                    .map(|arg| format!("{cmd_prefix} {arg}"))
+                    .collect();
+            }
+            if let Some(&(_, dirs_only)) = PATH_ARG_COMMANDS.iter().find(|(c, _)| *c == cmd_prefix)
+            {
+                return complete_path_arg(arg_partial, dirs_only, self.effective_cwd())
+                    .into_iter()
+                    .map(|path| format!("{cmd_prefix} {path}"))
+                    .collect();
+            }
+
             return match cmd_prefix {
                 "set" => {
                     // Complete setting names, including "no" prefixed variants
@@ -7263,9 +7552,11 @@ impl Engine {
                         "solarized-dark".to_string(),
                         "vscode-dark".to_string(),
                         "vscode-light".to_string(),
+                        "high-contrast".to_string(),
                         "gruvbox".to_string(),
                         "tokyonight".to_string(),
                         "solarized".to_string(),
+                        "light".to_string(),
                     ];
                     names.extend(list_custom_theme_names());
                     names.sort();
@@ -7321,17 +7612,36 @@ impl Engine {
     /// Rebuild the parsed user_keymaps cache from settings.keymaps.
     /// Call after loading or changing settings.
     pub fn rebuild_user_keymaps(&mut self) {
+        let leader = self.settings.leader;
         self.user_keymaps = self
             .settings
             .keymaps
             .iter()
-            .filter_map(|s| parse_keymap_def(s))
+            .filter_map(|s| parse_keymap_def(s, leader))
             .collect();
     }
 
+    /// Keymap mode for the current editor mode, or `None` where user keymaps
+    /// don't apply.
+    pub(crate) fn user_keymap_mode(&self) -> Option<&'static str> {
+        if self.is_vscode_mode() {
+            // VSCode mode has no modal distinction; "n" keymaps apply.
+            return Some("n");
+        }
+        match self.mode {
+            Mode::Normal => Some("n"),
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => Some("v"),
+            Mode::Insert => Some("i"),
+            Mode::Command => Some("c"),
+            _ => None,
+        }
+    }
+
     /// Check user keymaps for the current keypress. Returns `Some(action)` if
     /// an exact match was found, `None` to fall through to built-in handling.
-    /// Handles multi-key sequences by buffering keypresses.
+    /// Handles multi-key sequences by buffering keypresses; a buffer that is
+    /// both a mapping and the prefix of a longer one waits for the next key
+    /// or for `tick_keymap_timeout`.
     pub(crate) fn try_user_keymap(
         &mut self,
         key_name: &str,
@@ -7342,24 +7652,12 @@ impl Engine {
         if self.keymap_replaying || self.user_keymaps.is_empty() {
             return None;
         }
-
-        let mode_str = if self.is_vscode_mode() {
-            // VSCode mode has no modal distinction; "n" keymaps apply.
-            "n"
-        } else {
-            match self.mode {
-                Mode::Normal => "n",
-                Mode::Visual | Mode::VisualLine | Mode::VisualBlock => "v",
-                Mode::Insert => "i",
-                Mode::Command => "c",
-                _ => return None,
-            }
-        };
+        let mode_str = self.user_keymap_mode()?;
 
         let encoded = encode_keypress(key_name, unicode, ctrl);
         self.keymap_buf.push(encoded);
 
-        let mut exact_match_action = None;
+        let mut exact_match = None;
         let mut has_prefix = false;
 
         for km in &self.user_keymaps {
@@ -7367,7 +7665,7 @@ impl Engine {
                 continue;
             }
             if km.keys == self.keymap_buf {
-                exact_match_action = Some(km.action.clone());
+                exact_match = Some(km.action.clone());
             } else if km.keys.len() > self.keymap_buf.len()
                 && km.keys[..self.keymap_buf.len()] == self.keymap_buf[..]
             {
@@ -7375,42 +7673,117 @@ impl Engine {
             }
         }
 
-        if let Some(action) = exact_match_action {
-            self.keymap_buf.clear();
-            let count = self.take_count();
-            // Substitute {count} in the action, or append count as argument
-            let cmd = if action.contains("{count}") {
-                action.replace("{count}", &count.to_string())
-            } else if count > 1 {
-                format!("{action} {count}")
-            } else {
-                action
-            };
-            *changed = true;
-            return Some(self.execute_command(&cmd));
-        }
-
         if has_prefix {
-            // More keys needed — consume this keypress
+            // More keys needed (or the timeout) — consume this keypress
+            if let Some(action) = exact_match {
+                self.keymap_buf_match = Some((self.keymap_buf.len(), action));
+            }
+            self.keymap_buf_since = Some(std::time::Instant::now());
             return Some(EngineAction::None);
         }
 
-        // No match and no prefix. Replay buffered keys.
+        self.keymap_buf_since = None;
+        if let Some(action) = exact_match {
+            self.keymap_buf.clear();
+            self.keymap_buf_match = None;
+            return Some(self.run_user_keymap(action, changed));
+        }
+
+        // No match and no prefix: fall back to the longest mapping seen.
         let buf: Vec<String> = self.keymap_buf.drain(..).collect();
-        if buf.len() <= 1 {
+        if buf.len() <= 1 && self.keymap_buf_match.is_none() {
             // Single key, no match — fall through to built-in handling
             return None;
         }
+        Some(self.resolve_keymap_buf(buf, changed))
+    }
+
+    /// Resolve a pending keymap prefix once `timeoutlen` ms pass without
+    /// another key: run the mapping it matches exactly, or replay the keys
+    /// unmapped (so a lone `j` from an `i jk <Esc>` map is still typed).
+    /// Returns the resulting action if anything ran (caller should redraw
+    /// and handle it like a `handle_key` result).  Call from the event loop
+    /// idle path.
+    pub fn tick_keymap_timeout(&mut self) -> Option<EngineAction> {
+        let since = self.keymap_buf_since?;
+        let timeout = std::time::Duration::from_millis(self.settings.timeoutlen as u64);
+        if since.elapsed() < timeout {
+            return None;
+        }
+        self.keymap_buf_since = None;
+        let buf: Vec<String> = self.keymap_buf.drain(..).collect();
+        if buf.is_empty() {
+            self.keymap_buf_match = None;
+            return None;
+        }
+        let mode_str = self.user_keymap_mode();
+        if let Some(action) = self
+            .user_keymaps
+            .iter()
+            .find(|km| Some(km.mode.as_str()) == mode_str && km.keys == buf)
+            .map(|km| km.action.clone())
+        {
+            self.keymap_buf_match = Some((buf.len(), action));
+        }
+        let mut changed = false;
+        let action = self.resolve_keymap_buf(buf, &mut changed);
+        if changed {
+            self.set_dirty(true);
+        }
+        Some(action)
+    }
+
+    /// Run the longest mapping that matched a prefix of `buf`, then feed
+    /// the keys after it back through `handle_key` (where they may start
+    /// another mapping), as Vim does.  Without a match the keys are
+    /// replayed unmapped.  Returns the last action that wasn't `None`.
+    fn resolve_keymap_buf(&mut self, buf: Vec<String>, changed: &mut bool) -> EngineAction {
+        let Some((len, action)) = self.keymap_buf_match.take() else {
+            return self.replay_keymap_keys(buf);
+        };
+        let mut result = self.run_user_keymap(action, changed);
+        for encoded_key in buf.into_iter().skip(len) {
+            let (name, unicode, ctrl) = decode_keypress(&encoded_key);
+            let action = self.handle_key(&name, unicode, ctrl);
+            if action != EngineAction::None {
+                result = action;
+            }
+        }
+        result
+    }
+
+    /// Run a matched user keymap's action.
+    fn run_user_keymap(&mut self, action: KeymapAction, changed: &mut bool) -> EngineAction {
+        match action {
+            KeymapAction::Command(action) => {
+                let count = self.take_count();
+                // Substitute {count} in the action, or append count as argument
+                let cmd = if action.contains("{count}") {
+                    action.replace("{count}", &count.to_string())
+                } else if count > 1 {
+                    format!("{action} {count}")
+                } else {
+                    action
+                };
+                *changed = true;
+                self.execute_command(&cmd)
+            }
+            // Any pending count stays put for the replayed keys to consume.
+            KeymapAction::Keys(keys) => self.replay_keymap_keys(keys),
+        }
+    }
 
-        // Multi-key sequence that didn't match any keymap: replay all keys
+    /// Feed encoded keys through `handle_key` without re-checking user
+    /// keymaps.  Returns the last key's action.
+    fn replay_keymap_keys(&mut self, keys: Vec<String>) -> EngineAction {
         self.keymap_replaying = true;
         let mut last_action = EngineAction::None;
-        for encoded_key in buf {
+        for encoded_key in keys {
             let (rk_name, rk_unicode, rk_ctrl) = decode_keypress(&encoded_key);
             last_action = self.handle_key(&rk_name, rk_unicode, rk_ctrl);
         }
         self.keymap_replaying = false;
-        Some(last_action)
+        last_action
     }
 
     /// Try to run a named plugin command. Returns `true` if the command was found.
@@ -56,12 +56,14 @@ impl Engine {
                 None => return,
             };
             let path = match &state.file_path {
-                Some(p) => p.clone(),
-                None => return,
+                Some(p) if !state.large_file => p.clone(),
+                _ => return,
             };
-            // User language_map takes priority; fall back to built-in extension table
-            let lang_id = lsp::language_id_from_path_with_map(&path, &self.settings.language_map)
-                .or_else(|| state.lsp_language_id.clone());
+            // The buffer's language (extension, language_map, shebang or
+            // modeline); fall back to the extension table with user overrides.
+            let lang_id = state.lsp_language_id.clone().or_else(|| {
+                lsp::language_id_from_path_with_map(&path, &self.settings.language_map)
+            });
             let lang_id = match lang_id {
                 Some(l) => l,
                 None => return,
@@ -70,12 +72,16 @@ impl Engine {
         };
         self.ensure_lsp_manager();
         let no_server = if let Some(mgr) = &mut self.lsp_manager {
+            if lsp::language_id_from_path(&path).as_deref() != Some(lang_id.as_str()) {
+                mgr.set_language_for_path(&path, &lang_id);
+            }
             mgr.notify_did_open(&path, &text).err()
         } else {
             None
         };
         // Request semantic tokens after opening a file.
         self.lsp_request_semantic_tokens(&path);
+        self.lsp_request_winbar_symbols(&path);
         // Show extension hint based on VimCode extension state (independent of LSP binary
         // availability — ext_remove intentionally leaves the binary on disk).
         let manifests = self.ext_available_manifests();
@@ -96,7 +102,7 @@ impl Engine {
             }
         } else if let Some(err) = no_server {
             // Show dependency errors prominently; generic "no server" only as fallback.
-            self.message = err;
+            self.set_error_message(err);
         }
     }
 
@@ -722,6 +728,68 @@ impl Engine {
         false
     }
 
+    /// Names of the document symbols enclosing `window_id`'s cursor, outermost
+    /// first (e.g. `["Foo", "bar"]`), from the cached `documentSymbol` tree.
+    /// Empty for buffers without LSP symbols.
+    pub fn winbar_symbols(&self, window_id: WindowId) -> Vec<String> {
+        let Some(window) = self.windows.get(&window_id) else {
+            return Vec::new();
+        };
+        let Some(state) = self.buffer_manager.get(window.buffer_id) else {
+            return Vec::new();
+        };
+        let line = window.view.cursor.line as u32;
+        let mut chain = Vec::new();
+        let mut level = &state.document_symbols;
+        while let Some(sym) = level.iter().find(|s| s.line <= line && line <= s.end_line) {
+            chain.push(sym.name.clone());
+            level = &sym.children;
+        }
+        chain
+    }
+
+    /// Buffer lines pinned at the top of `window_id` by sticky scroll
+    /// (`:set stickyscroll`), outermost first: headers of the symbols
+    /// enclosing the top of the window that have scrolled out of view.  At
+    /// most [`STICKY_SCROLL_MAX_LINES`], and no more than half of the
+    /// window's `rows`.
+    pub fn sticky_scroll_lines(&self, window_id: WindowId, rows: usize) -> Vec<usize> {
+        if !self.settings.sticky_scroll {
+            return Vec::new();
+        }
+        let Some(window) = self.windows.get(&window_id) else {
+            return Vec::new();
+        };
+        let Some(state) = self.buffer_manager.get(window.buffer_id) else {
+            return Vec::new();
+        };
+        let max = STICKY_SCROLL_MAX_LINES.min(rows / 2);
+        sticky_headers(&state.document_symbols, window.view.scroll_top, max)
+    }
+
+    /// Click on a sticky scroll row: jump to the pinned header `line` and
+    /// scroll so it shows just below the headers that stay pinned above it.
+    pub fn sticky_scroll_jump(&mut self, window_id: WindowId, line: usize) {
+        let Some(window) = self.windows.get(&window_id) else {
+            return;
+        };
+        let Some(state) = self.buffer_manager.get(window.buffer_id) else {
+            return;
+        };
+        let symbols = &state.document_symbols;
+        let top = (0..=STICKY_SCROLL_MAX_LINES)
+            .filter_map(|above| line.checked_sub(above))
+            .find(|&top| sticky_headers(symbols, top, STICKY_SCROLL_MAX_LINES).len() <= line - top)
+            .unwrap_or(line);
+
+        self.focus_group_for_window(window_id);
+        self.active_tab_mut().active_window = window_id;
+        self.push_jump_location();
+        let col = self.first_non_blank_col(line);
+        self.mouse_click(window_id, line, col);
+        self.set_scroll_top_for_window(window_id, top);
+    }
+
     /// Get the LSP status for a specific buffer's language.
     /// Returns `LspStatus::None` if no LSP is configured or the manager isn't started.
     pub fn lsp_status_for_buffer(
@@ -745,3 +813,31 @@ impl Engine {
         }
     }
 }
+
+/// Most scope headers sticky scroll pins at once.
+const STICKY_SCROLL_MAX_LINES: usize = 3;
+
+/// Header lines of the symbols pinned when the window starts at `top`.  A
+/// symbol is pinned when its header is above the row its pin would cover
+/// and its body continues below that row; only the `max` innermost are kept.
+fn sticky_headers(symbols: &[crate::core::lsp::SymbolInfo], top: usize, max: usize) -> Vec<usize> {
+    let mut headers: Vec<usize> = Vec::new();
+    let mut level = symbols;
+    loop {
+        let row = top + headers.len();
+        let Some(sym) = level
+            .iter()
+            .find(|s| (s.line as usize) < row && row < s.end_line as usize)
+        else {
+            break;
+        };
+        // `impl Foo { fn bar() {` on one line pins once.
+        if headers.last() != Some(&(sym.line as usize)) {
+            headers.push(sym.line as usize);
+        }
+        level = &sym.children;
+    }
+    let excess = headers.len().saturating_sub(max);
+    headers.drain(..excess);
+    headers
+}
@@ -3,13 +3,14 @@ use std::path::{Path, PathBuf};
 
 use super::ai::AiMessage;
 use super::buffer::{Buffer, BufferId};
-use super::buffer_manager::{BufferManager, BufferState, UndoEntry};
+use super::buffer_manager::{BufferManager, BufferState, EditOp, LineEnding, UndoEntry};
 use super::comment;
 use super::dap::{BreakpointInfo, DapEvent, DapVariable, StackFrame};
 use super::dap_manager::{
     generate_launch_json, parse_launch_json, parse_tasks_json, task_to_shell_command,
     type_to_adapter, DapManager, LaunchConfig,
 };
+use super::encoding::{self, FileEncoding};
 use super::extensions;
 use super::git;
 use super::lsp::{
@@ -19,13 +20,20 @@ use super::lsp::{
 use super::lsp_manager::LspManager;
 use super::paths;
 use super::plugin;
-use super::project_search::{self, ProjectMatch, ReplaceResult, SearchError, SearchOptions};
+use super::project_search::{
+    self, ProjectMatch, ReplaceResult, SearchControl, SearchError, SearchOptions, SearchOutcome,
+};
 use super::registry;
-use super::session::{ExtensionState, HistoryState, SessionGroupLayout, SessionState};
+use super::safe_write;
+use super::session::{
+    ExtensionState, HistoryState, NamedSession, SessionGroup, SessionGroupLayout, SessionSplit,
+    SessionState, SessionTab, SessionWindow,
+};
 use super::settings::{EditorMode, Settings};
+use super::snippet;
 use super::syntax::Syntax;
 use super::tab::{Tab, TabId};
-use super::terminal::{default_shell, InstallContext, TerminalPane};
+use super::terminal::{default_shell, key_to_pty_bytes, InstallContext, TerminalPane};
 use super::view::{FoldRegion, View};
 use super::window::{
     DropZone, GroupDivider, GroupId, GroupLayout, SplitDirection, Window, WindowId, WindowLayout,
@@ -33,6 +41,8 @@ use super::window::{
 };
 use std::borrow::Cow;
 
+use shell_ops::ShellTarget;
+
 use super::{Cursor, Mode};
 
 /// High bit marker for synthetic "Non-Public Members" group var_refs.
@@ -90,11 +100,13 @@ pub enum SettingsRow {
 // Each entry is (canonical_name, min_prefix_length).
 // `normalize_ex_command("sor foo")` → `"sort foo"`.
 static EX_ABBREVS: &[(&str, usize)] = &[
+    ("abbreviate", 2),
     ("bdelete", 2),
     ("bnext", 2),
     ("bprevious", 2),
     ("buffer", 1),
     ("cclose", 3),
+    ("checktime", 6),
     ("close", 3),
     ("cnext", 2),
     ("colorscheme", 4),
@@ -111,10 +123,14 @@ static EX_ABBREVS: &[(&str, usize)] = &[
     ("grep", 2),
     ("help", 1),
     ("history", 3),
+    ("iabbrev", 3),
+    ("iunabbrev", 4),
     ("join", 1),
     ("jumps", 2),
+    ("lcd", 2),
     ("make", 3),
     ("mark", 2),
+    ("mksession", 3),
     ("move", 1),
     ("nohlsearch", 3),
     ("number", 2),
@@ -131,15 +147,18 @@ static EX_ABBREVS: &[(&str, usize)] = &[
     ("saveas", 3),
     ("set", 2),
     ("sort", 3),
+    ("source", 2),
     ("split", 2),
     ("tabclose", 4),
     ("tabmove", 4),
     ("tabnext", 4),
     ("tabprevious", 4),
     ("terminal", 2),
+    ("unabbreviate", 3),
     ("undo", 1),
     ("update", 2),
     ("version", 2),
+    ("view", 3),
     ("vimgrep", 3),
     ("vnew", 3),
     ("vsplit", 2),
@@ -348,7 +367,7 @@ pub static PALETTE_COMMANDS: &[PaletteCommand] = &[
         label: "View: Toggle Terminal",
         shortcut: "Ctrl+T",
         vscode_shortcut: "",
-        action: "term",
+        action: "TerminalPanel",
     },
     PaletteCommand {
         label: "View: Toggle Menu Bar",
@@ -399,6 +418,12 @@ pub static PALETTE_COMMANDS: &[PaletteCommand] = &[
         vscode_shortcut: "<leader>sw",
         action: "GrepWord",
     },
+    PaletteCommand {
+        label: "Search: Send Results to Quickfix",
+        shortcut: "",
+        vscode_shortcut: "",
+        action: "SearchToQuickfix",
+    },
     PaletteCommand {
         label: "Search: Open Buffers",
         shortcut: "<leader>sb",
@@ -411,6 +436,12 @@ pub static PALETTE_COMMANDS: &[PaletteCommand] = &[
         vscode_shortcut: "<leader>so",
         action: "document_outline",
     },
+    PaletteCommand {
+        label: "Go to Symbol in Workspace",
+        shortcut: "<leader>sS",
+        vscode_shortcut: "<leader>sS",
+        action: "WorkspaceSymbols",
+    },
     PaletteCommand {
         label: "Help: Search Key Bindings",
         shortcut: "<leader>sk",
@@ -513,7 +544,7 @@ pub static PALETTE_COMMANDS: &[PaletteCommand] = &[
         label: "Terminal: New Terminal",
         shortcut: "",
         vscode_shortcut: "",
-        action: "term",
+        action: "TerminalPanel",
     },
     PaletteCommand {
         label: "Terminal: Close Terminal",
@@ -619,6 +650,12 @@ pub static PALETTE_COMMANDS: &[PaletteCommand] = &[
         vscode_shortcut: "Shift+Alt+F",
         action: "Lformat",
     },
+    PaletteCommand {
+        label: "Strip Trailing Whitespace",
+        shortcut: ":StripWhitespace",
+        vscode_shortcut: "",
+        action: "StripWhitespace",
+    },
     PaletteCommand {
         label: "LSP: Rename Symbol",
         shortcut: "",
@@ -810,6 +847,8 @@ pub enum PickerSource {
     Indentation,
     /// Line ending picker (LF / CRLF).
     LineEndings,
+    /// Pinned files for the current project (`:Harpoon`).
+    Harpoon,
     Custom(String),
 }
 
@@ -877,10 +916,15 @@ pub enum PickerAction {
 }
 
 /// Preview context shown in the picker's right pane.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PickerPreview {
     /// Lines to display: (1-based line number, text, is_highlighted).
     pub lines: Vec<(usize, String, bool)>,
+    /// Tree-sitter highlights for each entry of `lines`:
+    /// (start byte, end byte, scope).  Empty when the file type is unknown.
+    pub highlights: Vec<Vec<(usize, usize, String)>>,
+    /// Shown in place of `lines`, e.g. `(binary)` or `Loading…`.
+    pub note: Option<String>,
 }
 
 /// How a file should be opened: as a temporary preview or permanent buffer.
@@ -915,6 +959,18 @@ pub enum DiffLine {
 #[derive(Clone, Copy, Debug)]
 pub struct AlignedDiffEntry {
     pub source_line: Option<usize>,
+    /// Unified diff only: a line deleted from the base file, drawn at this
+    /// row as a read-only virtual line (`source_line` is `None`).
+    pub removed_line: Option<usize>,
+}
+
+/// State for `:diffunified` — one window showing its buffer against a base
+/// file, with lines only present in the base drawn as virtual rows.
+#[derive(Clone, Debug)]
+pub struct UnifiedDiff {
+    pub window: WindowId,
+    /// Lines of the base file, each including its line ending.
+    pub base_lines: Vec<String>,
 }
 
 /// Direction of the last search operation
@@ -924,6 +980,19 @@ pub enum SearchDirection {
     Backward, // Last search was '?'
 }
 
+/// Cursor offset applied after a `/` or `?` search match (`/pat/e`, `/pat/+2`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchOffset {
+    #[default]
+    None,
+    /// `/pat/+N`: N lines below (above when negative) the match, column 0.
+    Lines(isize),
+    /// `/pat/s+N` or `/pat/b+N`: N chars from the start of the match.
+    Start(isize),
+    /// `/pat/e+N`: N chars from the last char of the match.
+    End(isize),
+}
+
 /// Toggle options for the inline find/replace overlay (Ctrl+F).
 #[derive(Debug, Clone, Default)]
 pub struct FindReplaceOptions {
@@ -1310,6 +1379,23 @@ struct Change {
     motion: Option<Motion>,
 }
 
+/// A surround command (`ds`, `cs`, `ys`, visual `S`) waiting for its
+/// delimiter keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SurroundPending {
+    /// `ds` — waiting for the target delimiter.
+    Delete,
+    /// `cs` — waiting for the target, then the replacement.
+    Change(Option<char>),
+    /// `ys{motion}` / `S` — waiting for the delimiter to wrap `start..end`
+    /// with.  Linewise ranges put the delimiters on their own lines.
+    Add {
+        start: usize,
+        end: usize,
+        linewise: bool,
+    },
+}
+
 /// C preprocessor directive kind for `[#` / `]#` navigation.
 pub(crate) enum PreprocKind {
     If,
@@ -1677,39 +1763,78 @@ pub struct UserKeymap {
     pub mode: String,
     /// Parsed key sequence, e.g. `["g", "c", "c"]` or `["<C-/>"]`.
     pub keys: Vec<String>,
-    /// The ex command to run (without leading `:`), e.g. `"Commentary"`.
-    pub action: String,
+    /// What the mapping does.
+    pub action: KeymapAction,
+}
+
+/// Right-hand side of a [`UserKeymap`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeymapAction {
+    /// Ex command to run (without leading `:`), e.g. `"Commentary"`.
+    Command(String),
+    /// Keys fed back through the editor, e.g. `["<Escape>"]` for `jk` → `<Esc>`.
+    /// Replayed without re-checking user keymaps (`noremap` semantics).
+    Keys(Vec<String>),
+}
+
+impl std::fmt::Display for KeymapAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeymapAction::Command(cmd) => write!(f, ":{cmd}"),
+            KeymapAction::Keys(keys) => write!(f, "{}", keys.join("")),
+        }
+    }
+}
+
+/// Canonical spelling of a `<...>` key token, matching `encode_keypress`.
+/// `<leader>` expands to the configured leader key.
+fn canonical_key_token(token: &str, leader: char) -> String {
+    let inner = &token[1..token.len() - 1];
+    match inner.to_ascii_lowercase().as_str() {
+        "leader" => encode_keypress("", Some(leader), false),
+        "esc" | "escape" => "<Escape>".to_string(),
+        "cr" | "enter" | "return" => "<Return>".to_string(),
+        "bs" | "backspace" => "<BS>".to_string(),
+        "space" => "<Space>".to_string(),
+        "tab" => "<Tab>".to_string(),
+        "lt" => "<".to_string(),
+        _ => token.to_string(),
+    }
 }
 
 /// Parse a key notation string into individual key specs.
-/// `"gcc"` → `["g", "c", "c"]`; `"<C-/>x"` → `["<C-/>", "x"]`.
-fn parse_key_sequence(s: &str) -> Vec<String> {
+/// `"gcc"` → `["g", "c", "c"]`; `"<C-/>x"` → `["<C-/>", "x"]`;
+/// `"<leader>w"` → `["<Space>", "w"]` with the default leader.
+fn parse_key_sequence(s: &str, leader: char) -> Vec<String> {
     let mut keys = Vec::new();
     let chars: Vec<char> = s.chars().collect();
     let mut i = 0;
     while i < chars.len() {
         if chars[i] == '<' {
             // Find matching '>'
-            if let Some(end) = chars[i..].iter().position(|&c| c == '>') {
+            if let Some(end) = chars[i..].iter().position(|&c| c == '>').filter(|&e| e > 1) {
                 let token: String = chars[i..=i + end].iter().collect();
-                keys.push(token);
+                keys.push(canonical_key_token(&token, leader));
                 i += end + 1;
             } else {
                 keys.push(chars[i].to_string());
                 i += 1;
             }
         } else {
-            keys.push(chars[i].to_string());
+            keys.push(encode_keypress("", Some(chars[i]), false));
             i += 1;
         }
     }
     keys
 }
 
-/// Parse a keymap definition string: `"n gcc :Commentary"` → `UserKeymap`.
-fn parse_keymap_def(s: &str) -> Option<UserKeymap> {
+/// Parse a keymap definition string into a `UserKeymap`:
+/// `"n gcc :Commentary"` runs an ex command (a trailing `<CR>` is allowed,
+/// as in `"n <leader>w :w<CR>"`); anything else is a key sequence, e.g.
+/// `"i jk <Esc>"`.
+fn parse_keymap_def(s: &str, leader: char) -> Option<UserKeymap> {
     let s = s.trim();
-    // Split: mode (first char or token), keys, :action
+    // Split: mode (first char or token), keys, action
     let mut parts = s.splitn(3, ' ');
     let mode = parts.next()?.to_string();
     if !matches!(mode.as_str(), "n" | "v" | "i" | "c") {
@@ -1717,14 +1842,28 @@ fn parse_keymap_def(s: &str) -> Option<UserKeymap> {
     }
     let keys_str = parts.next()?;
     let action_str = parts.next()?.trim();
-    if !action_str.starts_with(':') {
-        return None;
-    }
-    let action = action_str[1..].to_string();
-    if action.is_empty() {
-        return None;
+    let command = action_str.strip_prefix(':').map(|c| {
+        let lower = c.to_ascii_lowercase();
+        if lower.ends_with("<cr>") || lower.ends_with("<enter>") {
+            &c[..c.rfind('<').unwrap_or(c.len())]
+        } else {
+            c
+        }
+    });
+    let action = match command {
+        // A single `:cmd` is run as an ex command; `:a<CR>:b<CR>` or
+        // `:cmd<CR>dd` fall through to a replayed key sequence.
+        Some(cmd) if !cmd.to_ascii_lowercase().contains("<cr>") => {
+            KeymapAction::Command(cmd.to_string())
+        }
+        _ => KeymapAction::Keys(parse_key_sequence(action_str, leader)),
+    };
+    match &action {
+        KeymapAction::Command(cmd) if cmd.trim().is_empty() => return None,
+        KeymapAction::Keys(keys) if keys.is_empty() => return None,
+        _ => {}
     }
-    let keys = parse_key_sequence(keys_str);
+    let keys = parse_key_sequence(keys_str, leader);
     if keys.is_empty() {
         return None;
     }
@@ -1870,6 +2009,7 @@ Ctrl+W h/j/k/l     Focus left / down / up / right        :wincmd h/j/k/l
 Ctrl+W w  W         Cycle next / previous window          :wincmd w
 Ctrl+W c  q         Close window                          :close
 Ctrl+W o            Close all other windows               :only
+Ctrl+W z            Zoom window / restore layout          :wincmd z
 Ctrl+W s  v         Horizontal / vertical split           :split  :vsplit
 Ctrl+W e  E         Split editor group right / down       :wincmd e/E
 Ctrl+W n            New window                            :new
@@ -2147,6 +2287,8 @@ fn encode_keypress(key_name: &str, unicode: Option<char>, ctrl: bool) -> String
             }
         };
         format!("<C-{base}>")
+    } else if unicode == Some(' ') {
+        "<Space>".to_string()
     } else if let Some(ch) = unicode {
         ch.to_string()
     } else {
@@ -2192,6 +2334,40 @@ fn decode_keypress(encoded: &str) -> (String, Option<char>, bool) {
     }
 }
 
+/// An in-progress `:s///c` substitute waiting for a y/n/a/q/l answer.
+#[derive(Debug, Clone)]
+pub struct SubstituteConfirm {
+    pub regex: regex::Regex,
+    pub replacement: String,
+    /// Replace every match on a line (`g` flag) rather than the first.
+    pub global: bool,
+    /// Line of the match currently offered.
+    pub line: usize,
+    /// Char column of the match currently offered, within `line`.
+    pub col: usize,
+    /// Char column just past the match currently offered.
+    pub match_end: usize,
+    /// Last line of the range (inclusive).
+    pub end_line: usize,
+    /// Replacements made so far.
+    pub count: usize,
+}
+
+/// An expanded completion snippet whose tab stops Tab/Shift-Tab visit.
+#[derive(Debug, Clone)]
+pub struct SnippetSession {
+    pub buffer_id: BufferId,
+    /// Tab stops as (buffer char offset, length), in visiting order.
+    pub stops: Vec<(usize, usize)>,
+    /// Index into `stops` of the active stop.
+    pub current: usize,
+    /// The active placeholder is still untouched: the first typed character
+    /// replaces it.
+    pub selected: bool,
+    /// Buffer length (chars) after the last tracked edit.
+    buffer_len: usize,
+}
+
 /// State for the inline diff peek popup (preview a git diff hunk).
 pub struct DiffPeekState {
     /// Index into the buffer's `diff_hunks` array.
@@ -2247,16 +2423,28 @@ pub struct Engine {
     pub wildmenu_original: String,
     /// Status message shown in the command line area (e.g. "written", errors).
     pub message: String,
+    /// The error last shown with [`Engine::set_error_message`].  It is drawn
+    /// in the error color for as long as `message` still holds it, so any
+    /// plain assignment to `message` drops the styling.
+    error_message: Option<String>,
     /// Current search query (from last `/` or `?` search).
     pub search_query: String,
     /// Char-offset pairs (start, end) for all search matches in active buffer.
     pub search_matches: Vec<(usize, usize)>,
     /// Index into `search_matches` for the current match.
     pub search_index: Option<usize>,
+    /// Set by `:noh` / Escape so other windows stop highlighting `search_query`
+    /// too; cleared by the next search.
+    pub search_highlight_cleared: bool,
     /// Direction of the last search operation.
     pub search_direction: SearchDirection,
+    /// Offset from the last `/pat/{offset}` search, reapplied by `n` / `N`.
+    pub search_offset: SearchOffset,
     /// Cursor position when search mode was entered (for incremental search)
     search_start_cursor: Option<Cursor>,
+    /// `(scroll_top, scroll_left)` when search mode was entered, restored
+    /// when an incremental search is cancelled.
+    search_start_scroll: Option<(usize, usize)>,
 
     // --- Find/Replace state ---
     /// Replacement text for current operation
@@ -2274,6 +2462,22 @@ pub struct Engine {
     pub keymap_buf: Vec<String>,
     /// Guard: true while replaying buffered keys through handle_key.
     pub keymap_replaying: bool,
+    /// When the last key was added to `keymap_buf`; an ambiguous prefix is
+    /// resolved by `tick_keymap_timeout` after `timeoutlen` ms.
+    pub keymap_buf_since: Option<std::time::Instant>,
+    /// The longest mapping matched exactly while `keymap_buf` kept growing:
+    /// its key count and action.  Runs if the buffer stops matching.
+    keymap_buf_match: Option<(usize, KeymapAction)>,
+    /// When the current which-key prefix was first seen pending.
+    which_key_since: Option<std::time::Instant>,
+    /// Whether the which-key popup is showing (set by `tick_which_key`).
+    pub which_key_visible: bool,
+    /// Identifier under the cursor whose occurrences are highlighted, with
+    /// the buffer it was taken from (set by `tick_word_highlight`).
+    pub word_highlight: Option<(BufferId, String)>,
+    /// Cursor position (window, line, col) the word highlight is waiting on,
+    /// and when the cursor arrived there.
+    word_highlight_anchor: Option<((WindowId, usize, usize), std::time::Instant)>,
     /// Set by `focus_window_direction` when navigation overflows the window list.
     /// `Some(false)` = tried to go left past first window, `Some(true)` = right past last.
     /// Consumed by the UI backend to move focus to sidebar/toolbar.
@@ -2324,15 +2528,24 @@ pub struct Engine {
     // --- Text object state ---
     /// Pending text object modifier: 'i' (inner) or 'a' (around)
     pub pending_text_object: Option<char>,
+    /// Surround command waiting for delimiter keys (`ds(`, `cs"'`, `ysiw)`).
+    pub(crate) pending_surround: Option<SurroundPending>,
 
     // --- Repeat state ---
     /// Last change operation for repeat (.)
     last_change: Option<Change>,
+    /// Set by a normal-mode multi-cursor edit so `handle_key` keeps the
+    /// (already re-positioned) extra cursors instead of clearing them.
+    mc_keep_extra_cursors: bool,
     /// Text accumulated during insert mode for repeat
     insert_text_buffer: String,
     /// When true, Replace mode uses virtual column awareness (gR).
     /// Tabs are expanded to spaces before overwriting.
     virtual_replace: bool,
+    /// Characters overwritten in the current Replace session, one entry per
+    /// typed char (`None` when it was appended past end of line).  Popped by
+    /// Backspace to restore the original text.
+    replace_overwritten: Vec<Option<char>>,
     /// When insert mode was entered via a change operator (cw, ce, cb, etc.),
     /// stores (motion_char, count) so `.` can replay the full change.
     pending_change_motion: Option<(char, usize)>,
@@ -2403,6 +2616,14 @@ pub struct Engine {
     /// True when the popup was triggered automatically (typing/Ctrl-Space):
     /// Tab accepts the highlighted item. False for Ctrl-N/P (inserts immediately as before).
     pub completion_display_only: bool,
+    /// LSP items behind the last server completion popup (for documentation).
+    pub completion_items: Vec<lsp::CompletionItem>,
+    /// Where `completion_items` were requested: (buffer, line, word start column).
+    completion_items_origin: Option<(BufferId, usize, usize)>,
+    /// Selected candidate and when it was selected (resolve debounce).
+    completion_resolve_anchor: Option<(usize, std::time::Instant)>,
+    /// Active snippet from an accepted completion (Insert mode only).
+    pub snippet_session: Option<SnippetSession>,
 
     // --- Project search state ---
     /// Current text typed in the project search input box.
@@ -2415,9 +2636,13 @@ pub struct Engine {
     pub project_search_options: SearchOptions,
     /// Receiver for async search results (set while a search thread is running).
     pub project_search_receiver:
-        Option<std::sync::mpsc::Receiver<Result<Vec<ProjectMatch>, SearchError>>>,
+        Option<std::sync::mpsc::Receiver<Result<SearchOutcome, SearchError>>>,
     /// True while a background search thread is running.
     pub project_search_running: bool,
+    /// Progress counters and cancel flag of the running search thread.
+    project_search_control: Option<std::sync::Arc<SearchControl>>,
+    /// `(files, matches)` last shown in the progress message.
+    project_search_progress: (usize, usize),
 
     // --- Project replace state ---
     /// Current text typed in the project replace input box.
@@ -2427,6 +2652,12 @@ pub struct Engine {
         Option<std::sync::mpsc::Receiver<Result<ReplaceResult, SearchError>>>,
     /// True while a background replace thread is running.
     pub project_replace_running: bool,
+    /// Replace preview: results show their replaced text and can be
+    /// unchecked before "Replace Selected".
+    pub project_replace_preview: bool,
+    /// Per-result "included" flag for "Replace Selected", parallel to
+    /// `project_search_results`.
+    pub project_search_included: Vec<bool>,
 
     // --- LSP state ---
     /// Multi-server LSP coordinator. None until first LSP-capable file is opened.
@@ -2440,6 +2671,8 @@ pub struct Engine {
     pub lsp_completion_active: bool,
     /// Request ID of the pending completion request.
     pub lsp_pending_completion: Option<i64>,
+    /// Pending `completionItem/resolve`: (request ID, index into `completion_items`).
+    pub lsp_pending_completion_resolve: Option<(i64, usize)>,
     /// Request ID of the pending hover request.
     pub lsp_pending_hover: Option<i64>,
     /// Request ID of the pending definition request.
@@ -2454,10 +2687,21 @@ pub struct Engine {
     pub lsp_pending_signature: Option<i64>,
     /// Request ID of the pending formatting request.
     pub lsp_pending_formatting: Option<i64>,
+    /// Buffer the pending formatting request was sent for.
+    lsp_formatting_buffer: Option<BufferId>,
     /// Buffer that triggered a format-on-save; after formatting completes we save it.
     format_on_save_pending: Option<BufferId>,
+    /// When the pending format-on-save gives up and saves unformatted.
+    format_on_save_deadline: Option<std::time::Instant>,
     /// If true, quit the editor after the deferred format-on-save completes.
     quit_after_format_save: bool,
+    /// Time of the last key press not yet followed by an interval auto-save.
+    autosave_key_at: Option<std::time::Instant>,
+    /// When buffers were last auto-saved; drives the status-line indicator.
+    pub autosaved_at: Option<std::time::Instant>,
+    /// Window positions in large buffers being reloaded in the background,
+    /// put back once the whole file has been read again.
+    reload_views: HashMap<BufferId, Vec<(WindowId, View)>>,
     /// Set to true when a format-on-save + quit has completed; backends should exit.
     pub format_save_quit_ready: bool,
     /// Request ID of the pending rename request.
@@ -2465,6 +2709,27 @@ pub struct Engine {
     /// Pending semantic tokens requests: maps request_id → file path.
     /// Multiple requests can be in flight simultaneously (e.g. after LSP Initialized).
     pub lsp_pending_semantic_tokens: HashMap<i64, PathBuf>,
+    /// Pending winbar document symbol requests: maps request_id → file path.
+    pub(crate) lsp_pending_winbar_symbols: HashMap<i64, PathBuf>,
+    /// Pending inlay hint requests: maps request_id → buffer.
+    pub(crate) lsp_pending_inlay_hints: HashMap<i64, BufferId>,
+    /// (buffer, first line, end line) of the last inlay hint request; cleared
+    /// when the buffer changes so the next refresh re-requests.
+    pub(crate) inlay_hint_request_key: Option<(BufferId, usize, usize)>,
+    /// When the visible range was first seen to differ from the last request.
+    /// The request is sent once it has been stable for `INLAY_HINT_DEBOUNCE`.
+    pub(crate) inlay_hint_debounce: Option<std::time::Instant>,
+    /// Workspace-symbol query typed in the `#` picker and when it last
+    /// changed; sent once typing pauses.
+    pub(crate) workspace_symbol_debounce: Option<(String, std::time::Instant)>,
+    /// Live-grep query typed in the picker and when it last changed; the
+    /// search starts once typing pauses.
+    pub(crate) picker_grep_debounce: Option<(String, std::time::Instant)>,
+    /// Live-grep search streaming results from a background thread.
+    pub(crate) picker_grep_job: Option<picker::GrepJob>,
+    /// Matches found by the latest live grep (`None` when the picker isn't
+    /// showing grep results).
+    pub picker_grep_total: Option<usize>,
     /// Currently visible signature help data (set in insert mode after `(` or `,`).
     pub lsp_signature_help: Option<SignatureHelpData>,
     /// Tracks whether we need to send didChange on next poll (debounce).
@@ -2483,8 +2748,11 @@ pub struct Engine {
     lsp_code_action_last_line: Option<(PathBuf, usize)>,
     /// When true, the next CodeActionResponse should display a popup (on-demand request).
     lsp_show_code_action_popup_pending: bool,
-    /// Actions shown in the current code-action dialog, indexed by dialog button position.
-    pending_code_action_choices: Vec<lsp::CodeAction>,
+    /// Actions listed in the code-action popup.
+    pub(crate) pending_code_action_choices: Vec<lsp::CodeAction>,
+    /// Highlighted row of the code-action popup, or `None` when it is closed.
+    /// The popup is drawn with the completion-popup renderer.
+    pub code_action_menu_idx: Option<usize>,
 
     /// Set when cursor moves; backends flush the actual hook after a debounce delay (150ms).
     pub cursor_move_pending: Option<std::time::Instant>,
@@ -2516,10 +2784,20 @@ pub struct Engine {
     /// Snapshot of user settings before any workspace/folder overlay was applied.
     /// Set when entering a workspace, restored when leaving. `None` if no overlay active.
     pub base_settings: Option<Box<Settings>>,
+    /// The project `.vimcode.json` currently layered over the settings, if any.
+    pub project_settings_path: Option<PathBuf>,
+    /// Modification time of `project_settings_path` when it was applied.
+    project_settings_mtime: Option<std::time::SystemTime>,
+    /// Settings before the project overlay; restored when it is re-applied.
+    project_settings_base: Option<Box<Settings>>,
 
     // --- Sidebar focus (shared by all backends) ---
     /// Whether the Explorer sidebar panel has keyboard focus.
     pub explorer_has_focus: bool,
+    /// Set when started on a directory (`vimcode dir/`): the frontend shows
+    /// the explorer, rooted at `cwd`, focused instead of the editor, then
+    /// clears it.
+    pub explorer_focus_on_start: bool,
     /// Whether the Search sidebar panel has keyboard focus.
     pub search_has_focus: bool,
 
@@ -2575,6 +2853,11 @@ pub struct Engine {
     // --- Fuzzy file finder ---
     /// Project root directory for the fuzzy finder.
     pub cwd: PathBuf,
+    /// Directory `:cd -` returns to.
+    pub prev_cwd: Option<PathBuf>,
+    /// Set by `:cd` so the frontend re-roots the file explorer at `cwd`;
+    /// the frontend clears it.
+    pub cwd_changed: bool,
     /// Whether the fuzzy finder modal is open.
     // --- Tab switcher (Alt+Tab MRU popup) ---
     /// Whether the tab switcher popup is open.
@@ -2626,12 +2909,23 @@ pub struct Engine {
     pub picker_preview: Option<PickerPreview>,
     /// Scroll offset for the preview pane (lines scrolled from top).
     pub picker_preview_scroll: usize,
+    /// File previews loaded while the picker is open, keyed by absolute path.
+    pub(crate) picker_preview_cache: HashMap<PathBuf, PickerPreview>,
+    /// Large file whose preview is being loaded on a worker thread.
+    pub(crate) picker_preview_job:
+        Option<(PathBuf, std::sync::mpsc::Receiver<Option<PickerPreview>>)>,
     /// Per-source search history (session-scoped, not persisted).
     pub picker_history: std::collections::HashMap<PickerSource, Vec<String>>,
     /// Current position in history when navigating (None = not browsing history).
     pub picker_history_index: Option<usize>,
     /// Saves the user's in-progress query when they start browsing history.
     pub picker_history_typing_buffer: String,
+    /// Query the file picker was last closed with (restored on reopen when
+    /// `fuzzy_remember_query` is set).
+    pub picker_last_file_query: String,
+    /// File last opened from the file picker (relative to `cwd`); it is
+    /// pre-selected when the picker reopens and still lists it.
+    pub picker_last_file: Option<PathBuf>,
 
     // --- Find/Replace overlay (Ctrl+F) ---
     /// Whether the find/replace overlay is open.
@@ -2681,6 +2975,8 @@ pub struct Engine {
     pub diff_aligned: HashMap<WindowId, Vec<AlignedDiffEntry>>,
     /// Whether unchanged sections in the diff view are hidden (folded).
     pub diff_unchanged_hidden: bool,
+    /// Single-window unified diff (`:diffunified`), or None when off.
+    pub diff_unified: Option<UnifiedDiff>,
 
     /// Receiver for background `git show HEAD:file` results used by
     /// the Source Control panel's click-to-diff flow.  The thread sends
@@ -2845,6 +3141,14 @@ pub struct Engine {
     /// Zero means "use the pane's actual PTY column count".
     /// Set by `terminal_split_set_drag_cols`; cleared by `terminal_split_finalize_drag`.
     pub terminal_split_left_cols: u16,
+    /// Shells opened in editor windows by `:terminal`, keyed by the
+    /// read-only buffer that mirrors their scrollback and screen.
+    pub terminal_buffers: HashMap<BufferId, TerminalPane>,
+    /// Terminal insert mode: keys typed in a `:terminal` window go to its
+    /// shell.  `Ctrl-\ Ctrl-N` clears it to browse the output in Normal mode.
+    pub terminal_insert: bool,
+    /// `Ctrl-\` was typed in terminal insert mode; the next key decides.
+    terminal_pending_escape: bool,
 
     // --- Special marks (for '', '., '<, '>) ---
     /// Position before last jump (for '' and `` marks).
@@ -2885,6 +3189,8 @@ pub struct Engine {
     // --- Last substitute (&) ---
     /// Last substitute (pattern, replacement, flags) for & repeat.
     pub last_substitute: Option<(String, String, String)>,
+    /// Interactive substitute (`c` flag) awaiting an answer; intercepts keys.
+    pub substitute_confirm: Option<SubstituteConfirm>,
 
     // --- Yank highlight (transient visual feedback) ---
     /// Region to highlight briefly after a yank operation: (start, end, is_linewise).
@@ -2986,13 +3292,25 @@ pub struct Engine {
     pub line_annotations: HashMap<usize, String>,
     /// Whether current `line_annotations` are blame-sourced (enables rich hover).
     pub blame_annotations_active: bool,
-    /// Receiver for async blame results (background thread).
-    blame_rx: Option<std::sync::mpsc::Receiver<Vec<crate::core::git::BlameInfo>>>,
+    /// Receiver for async blame results (background thread), tagged with
+    /// the buffer the blame was requested for.
+    blame_rx: Option<(
+        BufferId,
+        std::sync::mpsc::Receiver<Vec<crate::core::git::BlameInfo>>,
+    )>,
+    /// Per-buffer blame results; dropped when the buffer is modified.
+    pub blame_cache: HashMap<BufferId, Vec<crate::core::git::BlameInfo>>,
+    /// Buffer whose blame is re-run once edits pause for `BLAME_DEBOUNCE`.
+    pub(crate) blame_debounce: Option<(BufferId, std::time::Instant)>,
 
     // --- Async shell tasks (plugin background commands) ---
     /// Background shell tasks spawned by plugins via `vimcode.async_shell()`.
     /// Keyed by callback_event name (last-writer-wins: new request replaces old).
     async_shell_tasks: HashMap<String, std::sync::mpsc::Receiver<(bool, String)>>,
+    /// `:!cmd`, `:r !cmd` and `:{range}!cmd` commands still running.
+    shell_jobs: Vec<shell_ops::ShellJob>,
+    /// The `:make` build in progress, if any.
+    make_job: Option<shell_ops::MakeJob>,
 
     // --- AI assistant panel ---
     /// Conversation history shown in the AI sidebar.
@@ -3199,17 +3517,27 @@ impl Engine {
             wildmenu_selected: None,
             wildmenu_original: String::new(),
             message: String::new(),
+            error_message: None,
             search_query: String::new(),
             search_matches: Vec::new(),
             search_index: None,
+            search_highlight_cleared: false,
             search_direction: SearchDirection::Forward,
+            search_offset: SearchOffset::None,
             search_start_cursor: None,
+            search_start_scroll: None,
             replace_text: String::new(),
             replace_flags: String::new(),
             pending_key: None,
             user_keymaps: Vec::new(),
             keymap_buf: Vec::new(),
             keymap_replaying: false,
+            keymap_buf_since: None,
+            keymap_buf_match: None,
+            which_key_since: None,
+            which_key_visible: false,
+            word_highlight: None,
+            word_highlight_anchor: None,
             window_nav_overflow: None,
             registers: HashMap::new(),
             selected_register: None,
@@ -3223,9 +3551,12 @@ impl Engine {
             pending_operator: None,
             pending_find_operator: None,
             pending_text_object: None,
+            pending_surround: None,
             last_change: None,
+            mc_keep_extra_cursors: false,
             insert_text_buffer: String::new(),
             virtual_replace: false,
+            replace_overwritten: Vec::new(),
             pending_change_motion: None,
             settings: {
                 // Ensure settings.json exists with defaults
@@ -3256,20 +3587,29 @@ impl Engine {
             completion_idx: None,
             completion_start_col: 0,
             completion_display_only: false,
+            completion_items: Vec::new(),
+            completion_items_origin: None,
+            completion_resolve_anchor: None,
+            snippet_session: None,
             project_search_query: String::new(),
             project_search_results: Vec::new(),
             project_search_selected: 0,
             project_search_options: SearchOptions::default(),
             project_search_receiver: None,
             project_search_running: false,
+            project_search_control: None,
+            project_search_progress: (0, 0),
             project_replace_text: String::new(),
             project_replace_receiver: None,
             project_replace_running: false,
+            project_replace_preview: false,
+            project_search_included: Vec::new(),
             lsp_manager: None,
             lsp_diagnostics: HashMap::new(),
             lsp_hover_text: None,
             lsp_completion_active: false,
             lsp_pending_completion: None,
+            lsp_pending_completion_resolve: None,
             lsp_pending_hover: None,
             lsp_pending_definition: None,
             lsp_pending_references: None,
@@ -3277,11 +3617,24 @@ impl Engine {
             lsp_pending_type_definition: None,
             lsp_pending_signature: None,
             lsp_pending_formatting: None,
+            lsp_formatting_buffer: None,
             format_on_save_pending: None,
+            format_on_save_deadline: None,
             quit_after_format_save: false,
+            autosave_key_at: None,
+            autosaved_at: None,
+            reload_views: HashMap::new(),
             format_save_quit_ready: false,
             lsp_pending_rename: None,
             lsp_pending_semantic_tokens: HashMap::new(),
+            lsp_pending_winbar_symbols: HashMap::new(),
+            lsp_pending_inlay_hints: HashMap::new(),
+            inlay_hint_request_key: None,
+            inlay_hint_debounce: None,
+            workspace_symbol_debounce: None,
+            picker_grep_debounce: None,
+            picker_grep_job: None,
+            picker_grep_total: None,
             lsp_signature_help: None,
             lsp_dirty_buffers: HashMap::new(),
             lsp_pending_code_action: None,
@@ -3292,6 +3645,7 @@ impl Engine {
             lsp_code_action_last_line: None,
             lsp_show_code_action_popup_pending: false,
             pending_code_action_choices: Vec::new(),
+            code_action_menu_idx: None,
             cursor_move_pending: None,
             lsp_installing: std::collections::HashSet::new(),
             lsp_lookup_in_flight: std::collections::HashSet::new(),
@@ -3312,7 +3666,11 @@ impl Engine {
             workspace_file: None,
             workspace_root: Some(cwd.clone()),
             base_settings: None,
+            project_settings_path: None,
+            project_settings_mtime: None,
+            project_settings_base: None,
             explorer_has_focus: false,
+            explorer_focus_on_start: false,
             search_has_focus: false,
             sc_file_statuses: Vec::new(),
             sc_worktrees: Vec::new(),
@@ -3338,6 +3696,8 @@ impl Engine {
             comment_overrides: HashMap::new(),
             highlight_overrides: HashMap::new(),
             cwd,
+            prev_cwd: None,
+            cwd_changed: false,
             tab_switcher_open: false,
             tab_switcher_selected: 0,
             tab_mru: vec![(GroupId(0), 0)],
@@ -3359,9 +3719,13 @@ impl Engine {
             picker_title: String::new(),
             picker_preview: None,
             picker_preview_scroll: 0,
+            picker_preview_cache: HashMap::new(),
+            picker_preview_job: None,
             picker_history: std::collections::HashMap::new(),
             picker_history_index: None,
             picker_history_typing_buffer: String::new(),
+            picker_last_file_query: String::new(),
+            picker_last_file: None,
             breadcrumb_focus: false,
             breadcrumb_selected: 0,
             breadcrumb_segments: Vec::new(),
@@ -3370,6 +3734,7 @@ impl Engine {
             diff_results: HashMap::new(),
             diff_aligned: HashMap::new(),
             diff_unchanged_hidden: false,
+            diff_unified: None,
             sc_diff_rx: None,
             sc_diff_pending_win: None,
             diff_peek: None,
@@ -3391,6 +3756,9 @@ impl Engine {
             terminal_find_matches: Vec::new(),
             terminal_split: false,
             terminal_split_left_cols: 0,
+            terminal_buffers: HashMap::new(),
+            terminal_insert: false,
+            terminal_pending_escape: false,
             menu_bar_visible: false,
             menu_bar_toggleable: false,
             menu_open_idx: None,
@@ -3444,6 +3812,7 @@ impl Engine {
             last_inserted_text: String::new(),
             last_ex_command: None,
             last_substitute: None,
+            substitute_confirm: None,
             yank_highlight: None,
             bracket_match: None,
             insert_ctrl_r_pending: false,
@@ -3482,7 +3851,11 @@ impl Engine {
             line_annotations: HashMap::new(),
             blame_annotations_active: false,
             blame_rx: None,
+            blame_debounce: None,
+            blame_cache: HashMap::new(),
             async_shell_tasks: HashMap::new(),
+            shell_jobs: Vec::new(),
+            make_job: None,
             ai_ghost_text: None,
             ai_ghost_alternatives: Vec::new(),
             ai_ghost_alt_idx: 0,
@@ -3548,6 +3921,16 @@ impl Engine {
         };
         // Initialize file watcher
         engine.init_file_watcher();
+        // Tests stay hermetic: no project settings from the test's cwd and
+        // no registers or recent files from the last session.
+        #[cfg(not(test))]
+        engine.apply_project_settings();
+        #[cfg(not(test))]
+        engine.restore_session_registers();
+        #[cfg(not(test))]
+        {
+            engine.buffer_manager.recent_files = engine.session.recent_files.clone();
+        }
         // If vscode mode is configured, start in Insert mode with menu visible
         if engine.is_vscode_mode() {
             engine.mode = Mode::Insert;
@@ -3567,7 +3950,10 @@ impl Engine {
         let old_buffer_id = engine.active_buffer_id();
         let _ = engine.buffer_manager.delete(old_buffer_id, true);
 
-        match engine.buffer_manager.open_file(path) {
+        match engine
+            .buffer_manager
+            .open_file(path, &engine.cwd, &engine.settings)
+        {
             Ok(buffer_id) => {
                 engine
                     .buffer_manager
@@ -3583,8 +3969,11 @@ impl Engine {
                 }
                 engine.refresh_git_diff(buffer_id);
                 engine.lsp_did_open(buffer_id);
+                engine.undo_file_restore_for_buffer(buffer_id);
                 if !path.exists() {
                     engine.message = format!("\"{}\" [New File]", path.display());
+                } else if engine.active_buffer_state().illegal_byte_line.is_some() {
+                    engine.message = engine.opened_message(buffer_id, path);
                 }
             }
             Err(e) => {
@@ -3785,14 +4174,206 @@ fn cmd_char_to_byte(s: &str, char_idx: usize) -> usize {
         .unwrap_or(s.len())
 }
 
-/// occupies when the viewport is `viewport_cols` columns wide.
-/// Always returns at least 1 (even for empty lines).
-/// Duplicated from render.rs so core/ stays GTK/render-free.
-fn engine_visual_rows_for_line(line_char_len: usize, viewport_cols: usize) -> usize {
-    if viewport_cols == 0 {
-        return 1;
+/// Compute word-aware soft-wrap segment boundaries for a line (without its
+/// line ending). Shared with the renderer so motions and drawing agree.
+/// Returns a list of `(start_char, end_char)` pairs. Breaks prefer word boundaries
+/// (spaces, hyphens, punctuation) so words are not split mid-way.
+pub fn word_wrap_segments(line: &str, viewport_cols: usize) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let total = chars.len();
+    if viewport_cols == 0 || total <= viewport_cols {
+        return vec![(0, total)];
     }
-    line_char_len.div_ceil(viewport_cols).max(1)
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    while pos < total {
+        let remaining = total - pos;
+        if remaining <= viewport_cols {
+            segments.push((pos, total));
+            break;
+        }
+        let end = pos + viewport_cols;
+        // Scan backwards from the break point to find a word boundary (space or after punctuation).
+        let mut break_at = end;
+        for i in (pos + 1..=end).rev() {
+            if chars[i - 1] == ' ' || chars[i - 1] == '-' || chars[i - 1] == '/' {
+                break_at = i;
+                break;
+            }
+        }
+        // If no boundary found within the segment, hard-break at viewport width.
+        if break_at == end && !chars[end - 1].is_whitespace() {
+            // Check if we found a boundary at all (break_at didn't change means
+            // the for loop completed without breaking).
+            let found = (pos + 1..=end)
+                .rev()
+                .any(|i| chars[i - 1] == ' ' || chars[i - 1] == '-' || chars[i - 1] == '/');
+            if !found {
+                break_at = end;
+            }
+        }
+        segments.push((pos, break_at));
+        // Safety: guarantee forward progress to prevent infinite loops.
+        pos = break_at.max(pos + 1);
+    }
+    segments
+}
+
+/// Index of the wrap segment containing char `col`; columns at or past the
+/// end of the line belong to the last segment.
+pub(crate) fn wrap_segment_index(segments: &[(usize, usize)], col: usize) -> usize {
+    segments
+        .iter()
+        .position(|&(start, end)| col >= start && col < end)
+        .unwrap_or(segments.len().saturating_sub(1))
+}
+
+/// How far `:earlier` / `:later` should travel through undo history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UndoStep {
+    /// A number of undo groups.
+    Count(usize),
+    /// Elapsed time in seconds (`10s`, `5m`, `2h`, `1d`).
+    Seconds(u64),
+}
+
+/// Parse the argument of `:earlier` / `:later`. An empty argument means one change.
+fn parse_undo_step(arg: &str) -> Option<UndoStep> {
+    if arg.is_empty() {
+        return Some(UndoStep::Count(1));
+    }
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let n: u64 = arg[..split].parse().ok()?;
+    let scale = match &arg[split..] {
+        "" => return Some(UndoStep::Count(n as usize)),
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(UndoStep::Seconds(n.saturating_mul(scale)))
+}
+
+/// Next history entry for Up (`older`) / Down recall, starting after `index`
+/// (or from the newest entry when `None`). Like Vim, only entries beginning
+/// with `prefix` — the text typed before recall started — are visited.
+fn history_step(
+    history: &[String],
+    index: Option<usize>,
+    prefix: &str,
+    older: bool,
+) -> Option<usize> {
+    let matches = |i: &usize| history[*i].starts_with(prefix);
+    if older {
+        (0..index.unwrap_or(history.len())).rev().find(matches)
+    } else {
+        let start = index.map_or(history.len(), |i| i + 1);
+        (start..history.len()).find(matches)
+    }
+}
+
+/// Ex commands whose argument is completed as a file path (`dirs_only` for `:cd`).
+const PATH_ARG_COMMANDS: &[(&str, bool)] = &[
+    ("e", false),
+    ("edit", false),
+    ("diffsplit", false),
+    ("diffunified", false),
+    ("e!", false),
+    ("edit!", false),
+    ("sp", false),
+    ("split", false),
+    ("vs", false),
+    ("vsp", false),
+    ("vsplit", false),
+    ("tabe", false),
+    ("tabedit", false),
+    ("tabnew", false),
+    ("vie", false),
+    ("view", false),
+    ("w", false),
+    ("write", false),
+    ("r", false),
+    ("read", false),
+    ("saveas", false),
+    ("cd", true),
+    ("lcd", true),
+];
+
+/// Complete the argument of `:source`: saved session names, or files once
+/// the argument looks like a path.
+fn complete_source_arg(arg: &str, cwd: &Path) -> Vec<String> {
+    if arg.contains(std::path::is_separator) || arg.starts_with(['~', '.']) {
+        return complete_path_arg(arg, false, cwd);
+    }
+    NamedSession::names()
+        .into_iter()
+        .filter(|name| name.starts_with(arg))
+        .collect()
+}
+
+/// Complete a partial path typed on the command line. `~` is expanded for the
+/// lookup but kept in the result; relative paths resolve against `cwd`, the
+/// window's working directory. Directories get a trailing `/` so the next Tab
+/// descends into them.
+fn complete_path_arg(arg: &str, dirs_only: bool, cwd: &Path) -> Vec<String> {
+    let arg = if arg == "~" { "~/" } else { arg };
+    let split = arg.rfind(std::path::is_separator).map_or(0, |i| i + 1);
+    let (dir_part, name_prefix) = arg.split_at(split);
+    let dir = cwd.join(paths::expand_tilde(dir_part));
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut results: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Hidden entries only when the user started typing a dot
+            if !name.starts_with(name_prefix)
+                || (name.starts_with('.') && !name_prefix.starts_with('.'))
+            {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            if dirs_only && !is_dir {
+                return None;
+            }
+            Some(format!("{dir_part}{name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    results.sort();
+    results
+}
+
+/// Split typed search input into its pattern and trailing offset, e.g.
+/// `foo/e+1` → (`foo`, `End(1)`). `\{delim}` in the pattern is a literal delimiter.
+fn parse_search_offset(input: &str, delim: char) -> (String, SearchOffset) {
+    let mut pattern = String::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && chars.peek().is_some_and(|&(_, next)| next == delim) {
+            chars.next();
+            pattern.push(delim);
+        } else if c == delim {
+            let spec = &input[i + c.len_utf8()..];
+            let num = |s: &str| match s {
+                "" => Some(0),
+                "+" => Some(1),
+                "-" => Some(-1),
+                _ => s.strip_prefix('+').unwrap_or(s).parse::<isize>().ok(),
+            };
+            let offset = match spec.chars().next() {
+                None => SearchOffset::None,
+                Some('e') => num(&spec[1..]).map_or(SearchOffset::None, SearchOffset::End),
+                Some('s' | 'b') => num(&spec[1..]).map_or(SearchOffset::None, SearchOffset::Start),
+                Some(_) => num(spec).map_or(SearchOffset::None, SearchOffset::Lines),
+            };
+            return (pattern, offset);
+        } else {
+            pattern.push(c);
+        }
+    }
+    (pattern, SearchOffset::None)
 }
 
 /// Try to parse a `:norm[al][!] {keys}` command with an optional range prefix.
@@ -3949,9 +4530,11 @@ pub fn build_aligned_diff(
         if i < da.len() && j < db.len() && da[i] == DiffLine::Same && db[j] == DiffLine::Same {
             aligned_a.push(AlignedDiffEntry {
                 source_line: Some(i),
+                removed_line: None,
             });
             aligned_b.push(AlignedDiffEntry {
                 source_line: Some(j),
+                removed_line: None,
             });
             i += 1;
             j += 1;
@@ -3977,14 +4560,22 @@ pub fn build_aligned_diff(
             if i < da.len() {
                 aligned_a.push(AlignedDiffEntry {
                     source_line: Some(i),
+                    removed_line: None,
+                });
+                aligned_b.push(AlignedDiffEntry {
+                    source_line: None,
+                    removed_line: None,
                 });
-                aligned_b.push(AlignedDiffEntry { source_line: None });
                 i += 1;
             }
             if j < db.len() {
-                aligned_a.push(AlignedDiffEntry { source_line: None });
+                aligned_a.push(AlignedDiffEntry {
+                    source_line: None,
+                    removed_line: None,
+                });
                 aligned_b.push(AlignedDiffEntry {
                     source_line: Some(j),
+                    removed_line: None,
                 });
                 j += 1;
             }
@@ -3997,16 +4588,24 @@ pub fn build_aligned_diff(
             if k < removed.len() {
                 aligned_a.push(AlignedDiffEntry {
                     source_line: Some(removed[k]),
+                    removed_line: None,
                 });
             } else {
-                aligned_a.push(AlignedDiffEntry { source_line: None });
+                aligned_a.push(AlignedDiffEntry {
+                    source_line: None,
+                    removed_line: None,
+                });
             }
             if k < added.len() {
                 aligned_b.push(AlignedDiffEntry {
                     source_line: Some(added[k]),
+                    removed_line: None,
                 });
             } else {
-                aligned_b.push(AlignedDiffEntry { source_line: None });
+                aligned_b.push(AlignedDiffEntry {
+                    source_line: None,
+                    removed_line: None,
+                });
             }
         }
     }
@@ -4014,6 +4613,55 @@ pub fn build_aligned_diff(
     (aligned_a, aligned_b)
 }
 
+/// Build the unified diff sequence for `:diffunified`: every line of side B
+/// in order, with each hunk's removed side-A lines emitted as virtual rows
+/// (`removed_line`) just before the lines that replaced them.
+pub fn build_unified_diff(da: &[DiffLine], db: &[DiffLine]) -> Vec<AlignedDiffEntry> {
+    let buffer_row = |j: usize| AlignedDiffEntry {
+        source_line: Some(j),
+        removed_line: None,
+    };
+    let removed_row = |i: usize| AlignedDiffEntry {
+        source_line: None,
+        removed_line: Some(i),
+    };
+    let mut unified = Vec::with_capacity(da.len().max(db.len()));
+    let mut i = 0; // pointer into da (base)
+    let mut j = 0; // pointer into db (buffer)
+
+    while i < da.len() || j < db.len() {
+        if i < da.len() && j < db.len() && da[i] == DiffLine::Same && db[j] == DiffLine::Same {
+            unified.push(buffer_row(j));
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let hunk_start = (i, j);
+        while i < da.len() && da[i] != DiffLine::Same {
+            unified.push(removed_row(i));
+            i += 1;
+        }
+        while j < db.len() && db[j] != DiffLine::Same {
+            unified.push(buffer_row(j));
+            j += 1;
+        }
+        // One side ran out of lines with the other still on a Same line:
+        // treat the leftovers as unmatched to avoid an infinite loop.
+        if (i, j) == hunk_start {
+            if i < da.len() {
+                unified.push(removed_row(i));
+                i += 1;
+            }
+            if j < db.len() {
+                unified.push(buffer_row(j));
+                j += 1;
+            }
+        }
+    }
+
+    unified
+}
+
 /// Falls back to all-Same if the edit distance exceeds `MAX_EDIT_DIST` (to
 /// avoid pathological runtime on completely unrelated files).
 pub fn lcs_diff(a: &[&str], b: &[&str]) -> (Vec<DiffLine>, Vec<DiffLine>) {
@@ -4133,6 +4781,68 @@ pub fn lcs_diff(a: &[&str], b: &[&str]) -> (Vec<DiffLine>, Vec<DiffLine>) {
     (da, db)
 }
 
+/// Group per-line [`lcs_diff`] results into hunks: each entry pairs the
+/// changed line range of side A with the range of side B that replaced it.
+/// Either range may be empty (pure insertion / deletion).
+pub fn diff_hunk_ranges(
+    da: &[DiffLine],
+    db: &[DiffLine],
+) -> Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < da.len() || j < db.len() {
+        if da.get(i) == Some(&DiffLine::Same) && db.get(j) == Some(&DiffLine::Same) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (a0, b0) = (i, j);
+        while i < da.len() && da[i] != DiffLine::Same {
+            i += 1;
+        }
+        while j < db.len() && db[j] != DiffLine::Same {
+            j += 1;
+        }
+        hunks.push((a0..i, b0..j));
+    }
+    hunks
+}
+
+/// Index of the hunk from [`diff_hunk_ranges`] covering side-B `line`.  A
+/// pure deletion also matches the lines directly around it.
+fn hunk_at_line(
+    hunks: &[(std::ops::Range<usize>, std::ops::Range<usize>)],
+    line: usize,
+) -> Option<usize> {
+    hunks
+        .iter()
+        .position(|(_, b)| b.contains(&line))
+        .or_else(|| {
+            hunks
+                .iter()
+                .position(|(_, b)| b.is_empty() && (line == b.start || line + 1 == b.start))
+        })
+}
+
+/// Map side-B `line` onto side A using the hunks from [`diff_hunk_ranges`];
+/// lines inside a changed region map to the start of its side-A range.
+fn saved_line_for(
+    hunks: &[(std::ops::Range<usize>, std::ops::Range<usize>)],
+    line: usize,
+) -> usize {
+    let mut shift: isize = 0;
+    for (a, b) in hunks {
+        if line < b.start {
+            break;
+        }
+        if b.contains(&line) {
+            return a.start;
+        }
+        shift += a.len() as isize - b.len() as isize;
+    }
+    (line as isize + shift).max(0) as usize
+}
+
 mod accessors;
 mod buffers;
 mod dap_ops;
@@ -4146,11 +4856,13 @@ mod picker;
 mod plugins;
 mod search;
 pub use search::find_word_boundaries;
+mod shell_ops;
 mod source_control;
 mod spell_ops;
 mod terminal_ops;
 mod visual;
 mod vscode;
+mod which_key;
 mod windows;
 
 #[cfg(test)]
@@ -1,5 +1,13 @@
 use super::*;
 
+/// Upper bound on characters scanned by `%` and bracket-match highlighting,
+/// so an unbalanced bracket in a huge file can't stall the UI.
+const BRACKET_SCAN_LIMIT: usize = 10_000;
+
+/// How long a completion candidate must stay selected before its
+/// documentation is resolved with the language server.
+const COMPLETION_RESOLVE_DELAY: std::time::Duration = std::time::Duration::from_millis(150);
+
 impl Engine {
     // --- Word motions ---
 
@@ -675,7 +683,7 @@ impl Engine {
                         let prev_text: String = self.buffer().content.line(prev).chars().collect();
                         let prev_trimmed = prev_text.trim_end_matches(['\n', '\r']);
                         if self.line_triggers_indent(prev_trimmed) {
-                            let extra = if self.settings.expand_tab {
+                            let extra = if self.effective_expand_tab() {
                                 " ".repeat(sw)
                             } else {
                                 "\t".to_string()
@@ -718,7 +726,8 @@ impl Engine {
 
     /// Toggle comments on a range of lines (1-indexed, inclusive).
     ///
-    /// Resolves comment style from overrides → built-in table → fallback `#`.
+    /// Resolves comment style from the buffer's `commentstring`, else the
+    /// global setting → overrides → built-in table → fallback `#`.
     /// Uses line comments when available, block comments otherwise.
     /// All non-blank lines are toggled: if all are already commented, uncomment;
     /// otherwise add comment markers.
@@ -736,7 +745,13 @@ impl Engine {
             })
             .unwrap_or_default();
 
-        let style = comment::resolve_comment_style(&lang_id, &self.comment_overrides);
+        let cms = self
+            .active_buffer_state()
+            .commentstring
+            .as_deref()
+            .unwrap_or(&self.settings.commentstring);
+        let style = comment::parse_commentstring(cms)
+            .unwrap_or_else(|| comment::resolve_comment_style(&lang_id, &self.comment_overrides));
 
         let total = self.buffer().len_lines();
         let start = (start_1.saturating_sub(1)).min(total.saturating_sub(1));
@@ -845,10 +860,9 @@ impl Engine {
         let total = self.buffer().len_lines();
         let start = start_line.min(total.saturating_sub(1));
         let end = end_line.min(total.saturating_sub(1));
-        let tw = if self.settings.textwidth > 0 {
-            self.settings.textwidth
-        } else {
-            79
+        let tw = match self.effective_textwidth() {
+            0 => 79,
+            tw => tw,
         };
 
         // Collect the text of the range
@@ -1196,7 +1210,7 @@ impl Engine {
         self.run_search();
 
         if self.search_matches.is_empty() {
-            self.message = format!("Pattern not found: {}", word);
+            self.set_error_message(format!("E486: Pattern not found: {}", word));
             return;
         }
 
@@ -1325,7 +1339,10 @@ impl Engine {
         let _ = ctrl;
         match key_name {
             "Escape" => {
+                // The whole Replace session (started by R / gR) is one undo group.
+                self.finish_undo_group();
                 self.virtual_replace = false;
+                self.replace_overwritten.clear();
                 self.mode = Mode::Normal;
                 // Vim steps cursor one left when leaving Replace mode (unless at col 0)
                 if self.view().cursor.col > 0 {
@@ -1334,22 +1351,43 @@ impl Engine {
                 self.clamp_cursor_col();
             }
             "BackSpace" => {
-                // In replace mode, backspace just moves cursor back (simplified)
-                self.move_left();
-            }
-            "Left" => self.move_left(),
-            "Right" => self.move_right(),
-            "Up" => {
-                if self.view().cursor.line > 0 {
-                    self.view_mut().cursor.line -= 1;
-                    self.clamp_cursor_col();
+                // Undo the most recent overwrite: restore the original char,
+                // or remove a char that was appended past end of line.
+                // Beyond the start of the session, just move left.
+                if self.view().cursor.col == 0 {
+                    return;
+                }
+                self.view_mut().cursor.col -= 1;
+                if let Some(original) = self.replace_overwritten.pop() {
+                    let line = self.view().cursor.line;
+                    let char_idx = self.buffer().line_to_char(line) + self.view().cursor.col;
+                    self.delete_with_undo(char_idx, char_idx + 1);
+                    if let Some(orig) = original {
+                        let mut buf = [0u8; 4];
+                        self.insert_with_undo(char_idx, orig.encode_utf8(&mut buf));
+                    }
+                    *changed = true;
                 }
             }
-            "Down" => {
-                let max_line = self.buffer().len_lines().saturating_sub(1);
-                if self.view().cursor.line < max_line {
-                    self.view_mut().cursor.line += 1;
-                    self.clamp_cursor_col();
+            "Left" | "Right" | "Up" | "Down" => {
+                // Moving the cursor ends the Backspace-restorable run.
+                self.replace_overwritten.clear();
+                match key_name {
+                    "Left" => self.move_left(),
+                    "Right" => self.move_right(),
+                    "Up" => {
+                        if self.view().cursor.line > 0 {
+                            self.view_mut().cursor.line -= 1;
+                            self.clamp_cursor_col();
+                        }
+                    }
+                    _ => {
+                        let max_line = self.buffer().len_lines().saturating_sub(1);
+                        if self.view().cursor.line < max_line {
+                            self.view_mut().cursor.line += 1;
+                            self.clamp_cursor_col();
+                        }
+                    }
                 }
             }
             _ => {
@@ -1374,11 +1412,14 @@ impl Engine {
                         0
                     };
 
+                    let mut buf = [0u8; 4];
+                    let s = ch.encode_utf8(&mut buf);
+
                     // Virtual Replace: expand tab to spaces before overwriting
                     if self.virtual_replace && col < line_content_len {
                         let cur_char = self.buffer().content.char(char_idx);
                         if cur_char == '\t' {
-                            let tabstop = self.settings.tabstop as usize;
+                            let tabstop = self.effective_tabstop();
                             // Calculate visual column of cursor
                             let line_start = self.buffer().line_to_char(line);
                             let mut vcol = 0usize;
@@ -1392,38 +1433,31 @@ impl Engine {
                             }
                             let tab_width = tabstop - (vcol % tabstop);
                             // Replace tab with spaces, then overwrite first space
-                            self.start_undo_group();
                             self.delete_with_undo(char_idx, char_idx + 1);
                             let spaces = " ".repeat(tab_width);
                             self.insert_with_undo(char_idx, &spaces);
                             // Now overwrite the first space with the typed char
                             self.delete_with_undo(char_idx, char_idx + 1);
-                            let mut buf = [0u8; 4];
-                            let s = ch.encode_utf8(&mut buf);
                             self.insert_with_undo(char_idx, s);
+                            self.replace_overwritten.push(Some(' '));
                             self.view_mut().cursor.col += 1;
-                            self.finish_undo_group();
                             *changed = true;
                             return;
                         }
                     }
 
-                    self.start_undo_group();
                     if col < line_content_len {
                         // Overwrite: delete one char, insert replacement
+                        let original = self.buffer().content.char(char_idx);
                         self.delete_with_undo(char_idx, char_idx + 1);
-                        let mut buf = [0u8; 4];
-                        let s = ch.encode_utf8(&mut buf);
                         self.insert_with_undo(char_idx, s);
-                        self.view_mut().cursor.col += 1;
+                        self.replace_overwritten.push(Some(original));
                     } else {
                         // Past end of line: insert
-                        let mut buf = [0u8; 4];
-                        let s = ch.encode_utf8(&mut buf);
                         self.insert_with_undo(char_idx, s);
-                        self.view_mut().cursor.col += 1;
+                        self.replace_overwritten.push(None);
                     }
-                    self.finish_undo_group();
+                    self.view_mut().cursor.col += 1;
                     *changed = true;
                 }
             }
@@ -1615,7 +1649,7 @@ impl Engine {
 
         // Find matching bracket
         if let Some(match_pos) =
-            self.find_matching_bracket(char_pos, open_char, close_char, is_opening)
+            self.find_matching_bracket_bounded(char_pos, open_char, close_char, is_opening)
         {
             let new_line = self.buffer().content.char_to_line(match_pos);
             let line_start = self.buffer().line_to_char(new_line);
@@ -1701,6 +1735,85 @@ impl Engine {
         None
     }
 
+    /// Like `find_matching_bracket`, but scans at most `BRACKET_SCAN_LIMIT`
+    /// characters and skips brackets inside string/comment syntax spans.
+    /// The skip only applies when highlights are available and the starting
+    /// bracket is itself outside such a span; otherwise matching is naive.
+    pub(crate) fn find_matching_bracket_bounded(
+        &self,
+        start_pos: usize,
+        open_char: char,
+        close_char: char,
+        is_opening: bool,
+    ) -> Option<usize> {
+        let rope = &self.buffer().content;
+        let total_chars = rope.len_chars();
+        let skip = self.string_comment_char_spans(start_pos);
+        let in_skip = |pos: usize| {
+            let i = skip.partition_point(|&(s, _)| s <= pos);
+            i > 0 && pos < skip[i - 1].1
+        };
+        let skip_enabled = !skip.is_empty() && !in_skip(start_pos);
+        let mut depth = 1usize;
+        let mut pos = start_pos;
+        for _ in 0..BRACKET_SCAN_LIMIT {
+            if is_opening {
+                pos += 1;
+                if pos >= total_chars {
+                    return None;
+                }
+            } else {
+                if pos == 0 {
+                    return None;
+                }
+                pos -= 1;
+            }
+            let ch = rope.char(pos);
+            if (ch != open_char && ch != close_char) || (skip_enabled && in_skip(pos)) {
+                continue;
+            }
+            let deeper = (ch == open_char) == is_opening;
+            if deeper {
+                depth += 1;
+            } else {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+        }
+        None
+    }
+
+    /// Char ranges of `string*`/`comment*` highlight spans in the active
+    /// buffer within `BRACKET_SCAN_LIMIT` chars of `around`, sorted by start
+    /// and merged so they never overlap.
+    fn string_comment_char_spans(&self, around: usize) -> Vec<(usize, usize)> {
+        let state = self.active_buffer_state();
+        let rope = &state.buffer.content;
+        let len_bytes = rope.len_bytes();
+        let total_chars = rope.len_chars();
+        let lo = rope.char_to_byte(around.saturating_sub(BRACKET_SCAN_LIMIT).min(total_chars));
+        let hi = rope.char_to_byte((around + BRACKET_SCAN_LIMIT + 1).min(total_chars));
+        let mut spans: Vec<(usize, usize)> = state
+            .highlights
+            .iter()
+            .filter(|(start, end, _)| *start <= hi && *end > lo)
+            .filter(|(_, _, scope)| scope.starts_with("string") || scope.starts_with("comment"))
+            .filter(|(start, end, _)| start < end && *end <= len_bytes)
+            .map(|(start, end, _)| (rope.byte_to_char(*start), rope.byte_to_char(*end)))
+            .collect();
+        spans.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
     /// Update `self.bracket_match` based on the character under the cursor.
     /// Called at the end of `handle_key()` when `match_brackets` is enabled.
     pub fn update_bracket_match(&mut self) {
@@ -1730,7 +1843,7 @@ impl Engine {
             }
         };
         if let Some(match_pos) =
-            self.find_matching_bracket(char_pos, open_char, close_char, is_opening)
+            self.find_matching_bracket_bounded(char_pos, open_char, close_char, is_opening)
         {
             let match_line = self.buffer().content.char_to_line(match_pos);
             let match_line_start = self.buffer().line_to_char(match_line);
@@ -1855,42 +1968,30 @@ impl Engine {
         let line_len = self.buffer().line_len_chars(cursor_line);
         let line_end = line_start + line_len;
 
-        // Find opening quote (search backward from cursor)
-        let mut open_pos = None;
-        let mut pos = cursor_pos;
-        while pos >= line_start {
-            let ch = self.buffer().content.char(pos);
-            if ch == quote_char {
-                // Check if it's escaped
-                if pos == line_start || self.buffer().content.char(pos - 1) != '\\' {
-                    open_pos = Some(pos);
-                    break;
-                }
-            }
-            if pos == line_start {
-                break;
-            }
-            pos -= 1;
-        }
-
-        let open_pos = open_pos?;
+        // Unescaped quote positions on the cursor line.
+        let quotes: Vec<usize> = (line_start..line_end)
+            .filter(|&p| {
+                self.buffer().content.char(p) == quote_char
+                    && (p == line_start || self.buffer().content.char(p - 1) != '\\')
+            })
+            .collect();
 
-        // Find closing quote (search forward from opening)
-        let mut close_pos = None;
-        let mut pos = open_pos + 1;
-        while pos < line_end {
-            let ch = self.buffer().content.char(pos);
-            if ch == quote_char {
-                // Check if it's escaped
-                if self.buffer().content.char(pos - 1) != '\\' {
-                    close_pos = Some(pos);
-                    break;
-                }
+        let (open_pos, close_pos) = if let Some(i) = quotes.iter().position(|&p| p == cursor_pos) {
+            // On a quote: pair quotes up from the start of the line (like Vim)
+            // to tell whether this one opens or closes a string.
+            if i % 2 == 0 {
+                (quotes[i], *quotes.get(i + 1)?)
+            } else {
+                (quotes[i - 1], quotes[i])
             }
-            pos += 1;
-        }
-
-        let close_pos = close_pos?;
+        } else {
+            match quotes.iter().rposition(|&p| p < cursor_pos) {
+                // Nearest quote before the cursor opens, the next one closes.
+                Some(i) => (quotes[i], *quotes.get(i + 1)?),
+                // No quote before the cursor: use the first string after it.
+                None => (*quotes.first()?, *quotes.get(1)?),
+            }
+        };
 
         // Return range based on modifier
         if modifier == 'i' {
@@ -1950,11 +2051,12 @@ impl Engine {
         let mut open_pos = None;
         let mut depth = 0;
 
-        // Search backward for opening bracket
+        // Search backward for opening bracket. A closing bracket under the
+        // cursor belongs to the enclosing pair, so it is not counted.
         let mut pos = cursor_pos;
         loop {
             let ch = self.buffer().content.char(pos);
-            if ch == close_char {
+            if ch == close_char && pos != cursor_pos {
                 depth += 1;
             } else if ch == open_char {
                 if depth == 0 {
@@ -2676,6 +2778,28 @@ impl Engine {
         };
 
         let (start_pos, end_pos) = range;
+        if operator == 'C' {
+            // gc{text-object}: toggle comments on the lines it covers
+            let start_line = self.buffer().content.char_to_line(start_pos);
+            let end_line = self
+                .buffer()
+                .content
+                .char_to_line(end_pos.saturating_sub(1).max(start_pos));
+            self.toggle_comment(start_line + 1, end_line + 1);
+            *changed = true;
+            return;
+        }
+        if operator == 's' {
+            // ys{text-object}: wait for the surround delimiter
+            if start_pos < end_pos {
+                self.pending_surround = Some(SurroundPending::Add {
+                    start: start_pos,
+                    end: end_pos,
+                    linewise: false,
+                });
+            }
+            return;
+        }
         if start_pos >= end_pos {
             // Empty inner range (e.g. ci( on "()"). For 'c' operator,
             // still enter insert mode at the position between the delimiters.
@@ -3032,7 +3156,7 @@ impl Engine {
 
         if self.line_triggers_indent(trimmed) {
             let sw = self.effective_shift_width();
-            let extra = if self.settings.expand_tab {
+            let extra = if self.effective_expand_tab() {
                 " ".repeat(sw)
             } else {
                 "\t".to_string()
@@ -3065,7 +3189,7 @@ impl Engine {
         let cur_indent = self.get_line_indent_str(line_idx);
         if cur_indent.len() >= sw {
             let new_len = cur_indent.len() - sw;
-            if self.settings.expand_tab {
+            if self.effective_expand_tab() {
                 Some(" ".repeat(new_len))
             } else {
                 Some(cur_indent[..cur_indent.len().saturating_sub(1)].to_string())
@@ -3090,16 +3214,39 @@ impl Engine {
     }
 
     /// Return the effective shift width for the active buffer.
-    /// Uses the buffer's auto-detected indent width if available,
-    /// otherwise falls back to `settings.shift_width`.
+    /// Uses a modeline `shiftwidth` or the buffer's auto-detected indent
+    /// width if available, otherwise falls back to `settings.shift_width`.
     pub(crate) fn effective_shift_width(&self) -> usize {
         self.buffer_manager
             .get(self.active_buffer_id())
-            .and_then(|s| s.detected_indent)
-            .map(|n| n as usize)
+            .map(|s| s.shift_width(&self.settings))
             .unwrap_or(self.settings.shift_width as usize)
     }
 
+    /// Tab width for the active buffer (modeline `tabstop` or the global one).
+    pub(crate) fn effective_tabstop(&self) -> usize {
+        self.buffer_manager
+            .get(self.active_buffer_id())
+            .map(|s| s.tabstop(&self.settings))
+            .unwrap_or(self.settings.tabstop as usize)
+    }
+
+    /// `textwidth` for the active buffer (modeline override or the global one).
+    pub(crate) fn effective_textwidth(&self) -> usize {
+        self.buffer_manager
+            .get(self.active_buffer_id())
+            .map(|s| s.textwidth(&self.settings))
+            .unwrap_or(self.settings.textwidth)
+    }
+
+    /// Whether indenting inserts spaces in the active buffer.
+    pub(crate) fn effective_expand_tab(&self) -> bool {
+        self.buffer_manager
+            .get(self.active_buffer_id())
+            .map(|s| s.expand_tab(&self.settings))
+            .unwrap_or(self.settings.expand_tab)
+    }
+
     /// True for word characters: [a-zA-Z0-9_].
     pub(crate) fn is_word_char(c: char) -> bool {
         c.is_alphanumeric() || c == '_'
@@ -3158,13 +3305,53 @@ impl Engine {
     }
 
     /// Collect all words in the current buffer that start with `prefix`,
-    /// deduplicated, sorted, excluding an exact match of `prefix` itself.
+    /// deduplicated, excluding an exact match of `prefix` itself.  Words
+    /// nearest the cursor come first, then the more frequent ones; when
+    /// `backward` (Ctrl-P), distance is measured searching up from the
+    /// cursor and wrapping at the top, so earlier lines win.  With
+    /// `complete_all_buffers`, words found only in other open buffers follow.
     /// Used by Ctrl-N/Ctrl-P (manual completion) which can afford the full scan.
-    pub(crate) fn word_completions_for_prefix(&self, prefix: &str) -> Vec<String> {
-        let mut set: std::collections::HashSet<String> = Default::default();
-        for line_idx in 0..self.buffer().len_lines() {
-            let text: String = self.buffer().content.line(line_idx).chars().collect();
-            let chars: Vec<char> = text.chars().collect();
+    pub(crate) fn word_completions_for_prefix(&self, prefix: &str, backward: bool) -> Vec<String> {
+        let cursor_line = self.view().cursor.line;
+        let total = self.buffer().len_lines();
+        // word → (line distance from the cursor, occurrences)
+        let mut found: HashMap<String, (usize, usize)> = HashMap::new();
+        Self::for_each_prefixed_word(&self.buffer().content, prefix, |word, line| {
+            let distance = if backward {
+                (cursor_line + total - line) % total
+            } else {
+                line.abs_diff(cursor_line)
+            };
+            let entry = found.entry(word).or_insert((usize::MAX, 0));
+            entry.0 = entry.0.min(distance);
+            entry.1 += 1;
+        });
+        if self.settings.complete_all_buffers {
+            let active = self.active_buffer_id();
+            for (id, state) in self.buffer_manager.iter() {
+                if *id != active {
+                    Self::for_each_prefixed_word(&state.buffer.content, prefix, |word, _| {
+                        found.entry(word).or_insert((usize::MAX, 0)).1 += 1;
+                    });
+                }
+            }
+        }
+        let mut v: Vec<(String, (usize, usize))> = found.into_iter().collect();
+        v.sort_by(|(wa, (da, ca)), (wb, (db, cb))| {
+            da.cmp(db).then(cb.cmp(ca)).then_with(|| wa.cmp(wb))
+        });
+        v.into_iter().map(|(word, _)| word).collect()
+    }
+
+    /// Call `f(word, line)` for every word in `content` that starts with
+    /// `prefix` (other than `prefix` itself).
+    fn for_each_prefixed_word(
+        content: &ropey::Rope,
+        prefix: &str,
+        mut f: impl FnMut(String, usize),
+    ) {
+        for (line_idx, line) in content.lines().enumerate() {
+            let chars: Vec<char> = line.chars().collect();
             let len = chars.len();
             let mut i = 0usize;
             while i < len {
@@ -3175,16 +3362,13 @@ impl Engine {
                     }
                     let word: String = chars[start..i].iter().collect();
                     if word.starts_with(prefix) && word != prefix {
-                        set.insert(word);
+                        f(word, line_idx);
                     }
                 } else {
                     i += 1;
                 }
             }
         }
-        let mut v: Vec<String> = set.into_iter().collect();
-        v.sort();
-        v
     }
 
     /// Delete the previously inserted candidate (or prefix), insert the new
@@ -3211,23 +3395,294 @@ impl Engine {
         self.completion_idx = None;
         self.completion_display_only = false;
         self.lsp_pending_completion = None;
+        self.completion_resolve_anchor = None;
+        self.lsp_pending_completion_resolve = None;
+    }
+
+    /// Text a completion item inserts (and shows in the popup): snippets
+    /// with their markup removed.
+    pub(crate) fn completion_item_text(item: &lsp::CompletionItem) -> String {
+        let text = item.insert_text.as_deref().unwrap_or(&item.label);
+        if item.is_snippet {
+            snippet::parse(text).text
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Candidate texts of `items` that fuzzy-match `query`, best score first;
+    /// ties keep the server's `sortText` order.
+    pub(crate) fn rank_completion_items(items: &[lsp::CompletionItem], query: &str) -> Vec<String> {
+        let mut scored: Vec<(i32, &str, String)> = items
+            .iter()
+            .filter_map(|item| {
+                let text = Self::completion_item_text(item);
+                let score = Self::fuzzy_score(&text, query)?;
+                Some((
+                    score,
+                    item.sort_text.as_deref().unwrap_or(&item.label),
+                    text,
+                ))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let mut seen = HashSet::new();
+        scored
+            .into_iter()
+            .filter_map(|(_, _, text)| seen.insert(text.clone()).then_some(text))
+            .collect()
+    }
+
+    /// Replace the completion candidates.  The selection stays on the same
+    /// candidate if the user had moved it and it is still listed; otherwise
+    /// it resets to the best match.
+    pub(crate) fn set_completion_candidates(&mut self, candidates: Vec<String>) {
+        let kept = self
+            .completion_idx
+            .filter(|&idx| idx > 0)
+            .and_then(|idx| self.completion_candidates.get(idx))
+            .and_then(|prev| candidates.iter().position(|c| c == prev));
+        self.completion_candidates = candidates;
+        self.completion_idx = Some(kept.unwrap_or(0));
+    }
+
+    /// Index into `completion_items` of the LSP item behind the selected candidate.
+    fn selected_completion_item(&self) -> Option<usize> {
+        let candidate = self.completion_candidates.get(self.completion_idx?)?;
+        self.completion_items
+            .iter()
+            .position(|item| Self::completion_item_text(item) == *candidate)
+    }
+
+    /// The parsed snippet behind the selected candidate, if it is one.
+    pub(crate) fn selected_completion_snippet(&self) -> Option<snippet::Snippet> {
+        let item = &self.completion_items[self.selected_completion_item()?];
+        item.is_snippet
+            .then(|| snippet::parse(item.insert_text.as_deref().unwrap_or(&item.label)))
+    }
+
+    /// Markdown documentation for the selected completion candidate: the
+    /// item's `detail` as a code block followed by its documentation.
+    pub fn completion_documentation(&self) -> Option<String> {
+        let item = &self.completion_items[self.selected_completion_item()?];
+        let mut doc = String::new();
+        if let Some(detail) = item.detail.as_deref().filter(|d| !d.trim().is_empty()) {
+            doc.push_str(&format!("```\n{}\n```\n", detail.trim()));
+        }
+        if let Some(text) = item
+            .documentation
+            .as_deref()
+            .filter(|d| !d.trim().is_empty())
+        {
+            if !doc.is_empty() {
+                doc.push('\n');
+            }
+            doc.push_str(text.trim());
+        }
+        (!doc.is_empty()).then_some(doc)
+    }
+
+    /// Resolve the selected completion item once the selection has settled
+    /// for `COMPLETION_RESOLVE_DELAY`, if the server left out its documentation.
+    /// Called from the backends' idle loops; the response arrives via `poll_lsp`.
+    pub fn tick_completion_resolve(&mut self) {
+        let Some(idx) = self.selected_completion_item() else {
+            self.completion_resolve_anchor = None;
+            return;
+        };
+        let item = &self.completion_items[idx];
+        if item.documentation.is_some()
+            || self
+                .lsp_pending_completion_resolve
+                .is_some_and(|(_, pending)| pending == idx)
+        {
+            return;
+        }
+        match self.completion_resolve_anchor {
+            Some((anchor, since)) if anchor == idx => {
+                if since.elapsed() < COMPLETION_RESOLVE_DELAY {
+                    return;
+                }
+            }
+            _ => {
+                self.completion_resolve_anchor = Some((idx, std::time::Instant::now()));
+                return;
+            }
+        }
+        let Some((path, _, _)) = self.lsp_cursor_position() else {
+            return;
+        };
+        let raw = self.completion_items[idx].raw.clone();
+        if let Some(id) = self
+            .lsp_manager
+            .as_mut()
+            .and_then(|mgr| mgr.request_completion_resolve(&path, &raw))
+        {
+            self.lsp_pending_completion_resolve = Some((id, idx));
+        } else {
+            // Server can't resolve: don't ask again for this item.
+            self.completion_items[idx].documentation = Some(String::new());
+        }
+    }
+
+    // ── Snippet sessions ────────────────────────────────────────────────────
+
+    /// Start visiting the tab stops of `snippet`, whose text has just been
+    /// inserted at buffer char offset `start`.
+    pub(crate) fn start_snippet_session(&mut self, snippet: &snippet::Snippet, start: usize) {
+        let stops: Vec<(usize, usize)> = snippet
+            .stops
+            .iter()
+            .map(|stop| (start + stop.start, stop.len))
+            .collect();
+        self.snippet_session = Some(SnippetSession {
+            buffer_id: self.active_buffer_id(),
+            stops,
+            current: 0,
+            selected: false,
+            buffer_len: self.buffer().len_chars(),
+        });
+        self.snippet_goto(0);
+    }
+
+    /// Move to tab stop `idx`; reaching the final stop ends the session.
+    fn snippet_goto(&mut self, idx: usize) {
+        let Some(session) = self.snippet_session.as_mut() else {
+            return;
+        };
+        session.current = idx;
+        let (start, len) = session.stops[idx];
+        session.selected = len > 0;
+        if idx + 1 == session.stops.len() {
+            self.snippet_session = None;
+        }
+        self.view_mut().cursor = self.char_idx_to_cursor(start);
+    }
+
+    /// Handle the snippet keys of an Insert-mode keystroke: Tab/Shift-Tab
+    /// jump between stops, and the first edit of an untouched placeholder
+    /// deletes it.  Returns true when the key was consumed.
+    pub(crate) fn snippet_handle_key(
+        &mut self,
+        key_name: &str,
+        unicode: Option<char>,
+        ctrl: bool,
+        changed: &mut bool,
+    ) -> bool {
+        let Some((current, selected, (start, len))) =
+            self.snippet_session.as_ref().map(|session| {
+                (
+                    session.current,
+                    session.selected,
+                    session.stops[session.current],
+                )
+            })
+        else {
+            return false;
+        };
+        let popup_open = self.completion_display_only && self.completion_idx.is_some();
+        if !ctrl && !popup_open && self.ai_ghost_text.is_none() {
+            match key_name {
+                "Tab" => {
+                    self.snippet_goto(current + 1);
+                    return true;
+                }
+                "ISO_Left_Tab" => {
+                    self.snippet_goto(current.saturating_sub(1));
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        if !selected {
+            return false;
+        }
+        let deletes = matches!(key_name, "BackSpace" | "Delete");
+        if ctrl || !(deletes || unicode.is_some()) {
+            // Moving away (or any other key) leaves the placeholder as is.
+            if let Some(session) = self.snippet_session.as_mut() {
+                session.selected = false;
+            }
+            return false;
+        }
+        self.delete_with_undo(start, start + len);
+        self.view_mut().cursor = self.char_idx_to_cursor(start);
+        self.snippet_track_edit();
+        if let Some(session) = self.snippet_session.as_mut() {
+            session.selected = false;
+        }
+        *changed = true;
+        deletes
+    }
+
+    /// Update the stops after an Insert-mode keystroke: the active stop
+    /// grows or shrinks with the edit and later stops shift.  Ends the
+    /// session when the cursor leaves the active stop or Insert mode ends.
+    pub(crate) fn snippet_track_edit(&mut self) {
+        let Some(session) = self.snippet_session.as_ref() else {
+            return;
+        };
+        if self.mode != Mode::Insert || session.buffer_id != self.active_buffer_id() {
+            self.snippet_session = None;
+            return;
+        }
+        let buffer_len = self.buffer().len_chars();
+        let cursor = self.buffer().line_to_char(self.view().cursor.line) + self.view().cursor.col;
+        let Some(session) = self.snippet_session.as_mut() else {
+            return;
+        };
+        let delta = buffer_len as isize - session.buffer_len as isize;
+        session.buffer_len = buffer_len;
+        let current = session.current;
+        let (start, len) = session.stops[current];
+        let end = start + len;
+        let new_end = (end as isize + delta).max(start as isize) as usize;
+        if delta != 0 {
+            for (i, stop) in session.stops.iter_mut().enumerate() {
+                if i == current {
+                    stop.1 = new_end - start;
+                } else if stop.0 >= end {
+                    stop.0 = (stop.0 as isize + delta).max(0) as usize;
+                } else if stop.0 <= start && stop.0 + stop.1 >= end {
+                    // An enclosing placeholder grows with the active one.
+                    stop.1 = (stop.1 as isize + delta).max(0) as usize;
+                } else if stop.0 >= start {
+                    // Nested inside the active placeholder: keep it in bounds.
+                    stop.0 = stop.0.min(new_end);
+                    stop.1 = stop.1.min(new_end - stop.0);
+                }
+            }
+        }
+        if cursor < start || cursor > new_end {
+            self.snippet_session = None;
+        }
     }
 
     /// Trigger auto-popup completion based on current cursor prefix.
     /// Called after each text change in Insert mode.
     pub(crate) fn trigger_auto_completion(&mut self) {
-        let (prefix, _) = self.completion_prefix_at_cursor();
+        let (prefix, start_col) = self.completion_prefix_at_cursor();
         if prefix.is_empty() {
             self.dismiss_completion();
             return;
         }
-        // Use a fast nearby-lines scan instead of scanning the entire buffer.
-        // For a 15K-line file, full scan takes 270ms; nearby scan is ~1ms.
-        let candidates = self.word_completions_nearby(&prefix);
+        // Server items for this word are re-ranked against the longer prefix
+        // while the next request is in flight.
+        let origin = (self.active_buffer_id(), self.view().cursor.line, start_col);
+        if self.completion_items_origin != Some(origin) {
+            self.completion_items.clear();
+            self.completion_items_origin = None;
+        }
+        let candidates = if self.completion_items.is_empty() {
+            // Use a fast nearby-lines scan instead of scanning the entire buffer.
+            // For a 15K-line file, full scan takes 270ms; nearby scan is ~1ms.
+            self.word_completions_nearby(&prefix)
+        } else {
+            Self::rank_completion_items(&self.completion_items, &prefix)
+        };
         if !candidates.is_empty() {
-            self.completion_start_col = self.view().cursor.col - prefix.chars().count();
-            self.completion_candidates = candidates;
-            self.completion_idx = Some(0);
+            self.completion_start_col = start_col;
+            self.set_completion_candidates(candidates);
             self.completion_display_only = true;
         } else {
             // No buffer-word hits yet; clear popup but keep LSP pending
@@ -3426,10 +3881,100 @@ impl Engine {
                 i += 1;
             }
         }
-        // Clamp cursor if it ended up hidden.
+        self.view_mut().fold_level = Some(0);
+        self.move_cursor_out_of_folds();
+    }
+
+    /// zR — open every fold and set the fold level to the deepest nesting.
+    pub(crate) fn cmd_fold_open_all(&mut self) {
+        let max_level = self.max_fold_level();
+        self.view_mut().open_all_folds();
+        self.view_mut().fold_level = Some(max_level);
+    }
+
+    /// zm — close one more level of folds (`count` levels with a count).
+    pub(crate) fn cmd_fold_more(&mut self, count: usize) {
+        let max_level = self.max_fold_level();
+        let current = self.view().fold_level.unwrap_or(max_level).min(max_level);
+        self.apply_fold_level(current.saturating_sub(count));
+    }
+
+    /// zr — open one more level of folds (`count` levels with a count).
+    pub(crate) fn cmd_fold_reduce(&mut self, count: usize) {
+        let max_level = self.max_fold_level();
+        let current = self.view().fold_level.unwrap_or(max_level).min(max_level);
+        self.apply_fold_level((current + count).min(max_level));
+    }
+
+    /// Every indent-based fold region in the buffer as `(start, end, level)`,
+    /// where `level` is the nesting depth (1 = outermost).  Regions follow the
+    /// same rules as `detect_fold_range` but are found in a single pass.
+    pub(crate) fn fold_regions_with_levels(&self) -> Vec<(usize, usize, usize)> {
+        let total = self.buffer().len_lines();
+        let mut regions = Vec::new();
+        // Open candidates: (header line, header indent, depth).
+        let mut stack: Vec<(usize, usize, usize)> = Vec::new();
+        for idx in 0..total {
+            let blank = self
+                .buffer()
+                .content
+                .line(idx)
+                .chars()
+                .all(char::is_whitespace);
+            if blank {
+                continue;
+            }
+            let indent = self.line_indent(idx);
+            while let Some(&(start, start_indent, level)) = stack.last() {
+                if start_indent < indent {
+                    break;
+                }
+                stack.pop();
+                if idx - 1 > start {
+                    regions.push((start, idx - 1, level));
+                }
+            }
+            let level = stack.len() + 1;
+            stack.push((idx, indent, level));
+        }
+        let last = total.saturating_sub(1);
+        while let Some((start, _, level)) = stack.pop() {
+            if last > start {
+                regions.push((start, last, level));
+            }
+        }
+        regions.sort_by_key(|r| r.0);
+        regions
+    }
+
+    /// Deepest fold nesting level in the buffer (0 when nothing can fold).
+    pub(crate) fn max_fold_level(&self) -> usize {
+        self.fold_regions_with_levels()
+            .iter()
+            .map(|r| r.2)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Close exactly the folds deeper than `level` and remember the level.
+    /// Folds nested inside a closed fold are hidden with it, so only regions
+    /// at `level + 1` need to be closed.
+    pub(crate) fn apply_fold_level(&mut self, level: usize) {
+        let regions = self.fold_regions_with_levels();
+        let view = self.view_mut();
+        view.open_all_folds();
+        for (start, end, _) in regions.into_iter().filter(|r| r.2 == level + 1) {
+            view.close_fold(start, end);
+        }
+        view.fold_level = Some(level);
+        self.move_cursor_out_of_folds();
+    }
+
+    /// If the cursor is hidden inside a closed fold, move it to that fold's
+    /// header — the first visible line above it.
+    pub(crate) fn move_cursor_out_of_folds(&mut self) {
         let cursor_line = self.view().cursor.line;
         if self.view().is_line_hidden(cursor_line) {
-            // Move cursor to the nearest fold header above.
             for f in self.view().folds.iter().rev() {
                 if f.start <= cursor_line && cursor_line <= f.end {
                     self.view_mut().cursor.line = f.start;
@@ -3471,7 +4016,7 @@ impl Engine {
     pub(crate) fn cmd_fold_delete(&mut self) {
         let line = self.view().cursor.line;
         if !self.view_mut().delete_fold_at(line) {
-            self.message = "E490: No fold found".to_string();
+            self.set_error_message("E490: No fold found".to_string());
         }
     }
 
@@ -3481,7 +4026,7 @@ impl Engine {
         if let Some(fold) = self.view().fold_at(line).cloned() {
             self.view_mut().delete_folds_in_range(fold.start, fold.end);
         } else if !self.view_mut().delete_fold_at(line) {
-            self.message = "E490: No fold found".to_string();
+            self.set_error_message("E490: No fold found".to_string());
         }
     }
 
@@ -3672,6 +4217,38 @@ impl Engine {
 
     // --- Register operations ---
 
+    /// Whether `ch` may follow `"` to select a register: named `a`-`z`
+    /// (`A`-`Z` to append), numbered `0`-`9`, unnamed `"`, small-delete `-`,
+    /// black hole `_`, clipboard `+`/`*`, and the read-only `%`, `/`, `.`.
+    pub(crate) fn is_register_name(ch: char) -> bool {
+        ch.is_ascii_alphanumeric() || matches!(ch, '"' | '-' | '_' | '+' | '*' | '%' | '/' | '.')
+    }
+
+    /// Registers kept in the session: named, numbered, small-delete and
+    /// unnamed.  Clipboard and read-only registers are not saved.
+    fn is_session_register(ch: char) -> bool {
+        ch.is_ascii_lowercase() || ch.is_ascii_digit() || matches!(ch, '"' | '-')
+    }
+
+    /// Copy the registers into the session state, ready for saving.
+    pub fn collect_session_registers(&mut self) {
+        self.session.registers = self
+            .registers
+            .iter()
+            .filter(|(&ch, _)| Self::is_session_register(ch))
+            .map(|(&ch, value)| (ch, value.clone()))
+            .collect();
+    }
+
+    /// Restore the registers saved in the session state.
+    pub fn restore_session_registers(&mut self) {
+        for (&ch, value) in &self.session.registers {
+            if Self::is_session_register(ch) {
+                self.registers.insert(ch, value.clone());
+            }
+        }
+    }
+
     /// Returns the active register name (selected or default '"').
     pub(crate) fn active_register(&self) -> char {
         self.selected_register.unwrap_or('"')
@@ -3679,7 +4256,11 @@ impl Engine {
 
     /// Sets a register's content. `is_linewise` affects paste behavior.
     /// For `+` and `*` registers, also writes to the system clipboard.
+    /// Writes to the black hole `_` and read-only registers are discarded.
     pub(crate) fn set_register(&mut self, reg: char, content: String, is_linewise: bool) {
+        if matches!(reg, '_' | '%' | '/' | '.') {
+            return;
+        }
         // Uppercase register (A-Z): append to lowercase register
         if reg.is_ascii_uppercase() {
             let lower = reg.to_ascii_lowercase();
@@ -3722,6 +4303,9 @@ impl Engine {
     /// target is the unnamed register. Yanks to a named register (e.g. "ayy)
     /// leave "0 untouched, matching Vim's :help registers semantics.
     pub(crate) fn set_yank_register(&mut self, reg: char, content: String, is_linewise: bool) {
+        if reg == '_' {
+            return;
+        }
         self.set_register(reg, content.clone(), is_linewise);
         if reg == '"' {
             // "0 is the yank-only register — set on every unnamed yank, never on
@@ -3733,7 +4317,12 @@ impl Engine {
     /// Sets a delete register. Like set_register, but:
     /// - Linewise / multi-line: shifts "1"-"8" → "2"-"9", sets "1".
     /// - Character (< 1 line): sets "-" (small-delete register).
+    ///
+    /// Deletes into the black hole register `"_` touch no register at all.
     pub(crate) fn set_delete_register(&mut self, reg: char, content: String, is_linewise: bool) {
+        if reg == '_' {
+            return;
+        }
         self.set_register(reg, content.clone(), is_linewise);
         if is_linewise || content.contains('\n') {
             // Multi-line delete: shift numbered registers down
@@ -3853,17 +4442,36 @@ impl Engine {
     }
 
     /// Start recording a macro into the specified register.
+    /// An uppercase register (`qA`) appends to the lowercase register's macro.
     pub(crate) fn start_macro_recording(&mut self, register: char) {
         self.macro_recording = Some(register);
         self.recording_buffer.clear();
-        self.message = format!("Recording macro into register '{}'", register);
+        self.message = if register.is_ascii_uppercase() {
+            format!(
+                "Appending macro to register '{}'",
+                register.to_ascii_lowercase()
+            )
+        } else {
+            format!("Recording macro into register '{}'", register)
+        };
     }
 
     /// Stop recording and save the macro to the register.
     pub(crate) fn stop_macro_recording(&mut self) {
         if let Some(reg) = self.macro_recording {
             // Convert recording_buffer to string
-            let macro_content: String = self.recording_buffer.iter().collect();
+            let mut macro_content: String = self.recording_buffer.iter().collect();
+
+            // Appending (qA): join the keys directly onto the existing macro —
+            // set_register's newline separator would be replayed as a keystroke.
+            let reg = reg.to_ascii_lowercase();
+            if self.macro_recording.is_some_and(|r| r.is_ascii_uppercase()) {
+                let existing = self
+                    .get_register(reg)
+                    .map(|(content, lw)| Self::macro_keys(content, *lw).to_string())
+                    .unwrap_or_default();
+                macro_content = existing + &macro_content;
+            }
 
             // Store in register (not linewise)
             self.set_register(reg, macro_content, false);
@@ -3874,6 +4482,16 @@ impl Engine {
         }
     }
 
+    /// The keystrokes of a macro register.  A macro edited in the buffer and
+    /// yanked back linewise (`"ayy`) drops the line's trailing newline.
+    fn macro_keys(content: &str, linewise: bool) -> &str {
+        if linewise {
+            content.strip_suffix('\n').unwrap_or(content)
+        } else {
+            content
+        }
+    }
+
     /// Play a macro from the specified register.
     pub(crate) fn play_macro(&mut self, register: char) -> Result<(), String> {
         // Check recursion depth
@@ -3882,8 +4500,8 @@ impl Engine {
         }
 
         // Get macro content from register (clone it to avoid borrow issues)
-        let content = if let Some((content, _)) = self.get_register(register) {
-            content.clone()
+        let content = if let Some((content, lw)) = self.get_register(register) {
+            Self::macro_keys(content, *lw).to_string()
         } else {
             self.message = format!("Register '{}' is empty", register);
             return Ok(());
@@ -4771,25 +5389,61 @@ impl Engine {
     // Scroll cursor to position (zz / zt / zb)
     // =======================================================================
 
-    /// Scroll so that cursor line is centered in viewport.
+    /// Scroll the view and move the cursor by `lines` visible lines (closed
+    /// folds count as one), keeping the cursor's row on screen (`Ctrl-D`,
+    /// `Ctrl-U`, `Ctrl-F`, `Ctrl-B`).  The view stops once the last line
+    /// reaches the bottom, or at the top of the buffer; the cursor keeps
+    /// moving until it hits the first or last line.
+    pub(crate) fn scroll_page(&mut self, lines: usize, down: bool) {
+        let max_line = self.buffer().len_lines().saturating_sub(1);
+        let viewport = self.viewport_lines();
+        let top = self.view().scroll_top;
+        let cur = self.view().cursor.line;
+        let (new_top, new_cur) = if down {
+            let max_top = self
+                .view()
+                .prev_visible_line(max_line, viewport.saturating_sub(1))
+                .max(top);
+            (
+                self.view()
+                    .next_visible_line(top, lines, max_line)
+                    .min(max_top),
+                self.view().next_visible_line(cur, lines, max_line),
+            )
+        } else {
+            (
+                self.view().prev_visible_line(top, lines),
+                self.view().prev_visible_line(cur, lines),
+            )
+        };
+        self.view_mut().scroll_top = new_top;
+        self.view_mut().cursor.line = new_cur;
+        self.clamp_cursor_col();
+    }
+
+    /// Scroll so that cursor line is centered in viewport.  Closed folds
+    /// count as one row, so the centering is by visible lines.
     pub(crate) fn scroll_cursor_center(&mut self) {
         let cursor_line = self.view().cursor.line;
         let half = self.viewport_lines() / 2;
-        let new_top = cursor_line.saturating_sub(half);
+        let new_top = self.view().prev_visible_line(cursor_line, half);
         self.view_mut().scroll_top = new_top;
     }
 
     /// Scroll so that cursor line is at the top of viewport.
     pub(crate) fn scroll_cursor_top(&mut self) {
         let cursor_line = self.view().cursor.line;
-        self.view_mut().scroll_top = cursor_line;
+        self.set_scroll_top(cursor_line);
     }
 
-    /// Scroll so that cursor line is at the bottom of viewport.
+    /// Scroll so that cursor line is at the bottom of viewport (by visible
+    /// lines, skipping fold bodies).
     pub(crate) fn scroll_cursor_bottom(&mut self) {
         let cursor_line = self.view().cursor.line;
         let viewport = self.viewport_lines();
-        let new_top = cursor_line.saturating_sub(viewport.saturating_sub(1));
+        let new_top = self
+            .view()
+            .prev_visible_line(cursor_line, viewport.saturating_sub(1));
         self.view_mut().scroll_top = new_top;
     }
 
@@ -4929,7 +5583,7 @@ impl Engine {
 
     /// Indent `count` lines starting at `start_line` by shift_width.
     pub(crate) fn indent_lines(&mut self, start_line: usize, count: usize, changed: &mut bool) {
-        let indent_str = if self.settings.expand_tab {
+        let indent_str = if self.effective_expand_tab() {
             " ".repeat(self.effective_shift_width())
         } else {
             "\t".to_string()
@@ -5030,4 +5684,227 @@ impl Engine {
             *changed = true;
         }
     }
+
+    // --- Surround (ys / cs / ds / visual S) ---
+
+    /// Delimiters used when wrapping text with `ch` (`ysiw)`, `cs"(`).
+    /// Opening brackets pad the text with a space like vim-surround; closing
+    /// brackets and the `b`/`B`/`r`/`a` aliases do not.
+    fn surround_delimiters(ch: char) -> Option<(&'static str, &'static str)> {
+        Some(match ch {
+            ')' | 'b' => ("(", ")"),
+            '(' => ("( ", " )"),
+            ']' | 'r' => ("[", "]"),
+            '[' => ("[ ", " ]"),
+            '}' | 'B' => ("{", "}"),
+            '{' => ("{ ", " }"),
+            '>' | '<' | 'a' => ("<", ">"),
+            '"' => ("\"", "\""),
+            '\'' => ("'", "'"),
+            '`' => ("`", "`"),
+            _ => return None,
+        })
+    }
+
+    /// Char ranges of the opening and closing delimiters of the `target` pair
+    /// around the cursor.  Opening-bracket targets (`ds(`) also take the
+    /// whitespace just inside the brackets, mirroring the padding above.
+    fn surround_target_ranges(&self, target: char) -> Option<((usize, usize), (usize, usize))> {
+        let cursor = self.view().cursor;
+        let pos = self.buffer().line_to_char(cursor.line) + cursor.col;
+        let (open, close) = match target {
+            '"' | '\'' | '`' => {
+                let (s, e) = self.find_quote_object('i', target, pos)?;
+                ((s - 1, s), (e, e + 1))
+            }
+            't' => {
+                let (outer_s, outer_e) = self.find_tag_text_object('a', pos)?;
+                let (inner_s, inner_e) = self.find_tag_text_object('i', pos)?;
+                ((outer_s, inner_s), (inner_e, outer_e))
+            }
+            _ => {
+                let (open_ch, close_ch) = match target {
+                    '(' | ')' | 'b' => ('(', ')'),
+                    '[' | ']' | 'r' => ('[', ']'),
+                    '{' | '}' | 'B' => ('{', '}'),
+                    '<' | '>' | 'a' => ('<', '>'),
+                    _ => return None,
+                };
+                let (s, e) = self.find_bracket_object('a', open_ch, close_ch, pos)?;
+                ((s, s + 1), (e - 1, e))
+            }
+        };
+        if !matches!(target, '(' | '[' | '{' | '<') {
+            return Some((open, close));
+        }
+        let is_blank = |p: usize| matches!(self.buffer().content.char(p), ' ' | '\t');
+        let (mut open_end, mut close_start) = (open.1, close.0);
+        while open_end < close_start && is_blank(open_end) {
+            open_end += 1;
+        }
+        while close_start > open_end && is_blank(close_start - 1) {
+            close_start -= 1;
+        }
+        Some(((open.0, open_end), (close_start, close.1)))
+    }
+
+    /// Handle the key after `ds`, `cs`, `cs{c}` or `ys{motion}`.
+    pub(crate) fn handle_surround_key(
+        &mut self,
+        pending: SurroundPending,
+        unicode: Option<char>,
+        changed: &mut bool,
+    ) {
+        let Some(ch) = unicode else {
+            self.count = None;
+            self.operator_count = None;
+            return;
+        };
+        match pending {
+            SurroundPending::Delete => self.surround_replace(ch, None, changed),
+            SurroundPending::Change(None) => {
+                self.pending_surround = Some(SurroundPending::Change(Some(ch)));
+            }
+            SurroundPending::Change(Some(target)) => {
+                if let Some(delims) = Self::surround_delimiters(ch) {
+                    self.surround_replace(target, Some(delims), changed);
+                }
+            }
+            SurroundPending::Add {
+                start,
+                end,
+                linewise,
+            } => self.surround_add(start, end, linewise, ch, changed),
+        }
+        self.count = None;
+        self.operator_count = None;
+    }
+
+    /// `yss`: surround the current line, from its first non-blank to its end.
+    pub(crate) fn surround_begin_current_line(&mut self) {
+        let line = self.view().cursor.line;
+        let line_start = self.buffer().line_to_char(line);
+        let text: String = self.buffer().content.line(line).chars().collect();
+        let content = text.trim_end_matches(['\n', '\r']);
+        let indent = content.chars().take_while(|c| c.is_whitespace()).count();
+        let len = content.chars().count();
+        if indent < len {
+            self.pending_surround = Some(SurroundPending::Add {
+                start: line_start + indent,
+                end: line_start + len,
+                linewise: false,
+            });
+        }
+    }
+
+    /// Visual `S`: leave visual mode and wait for the surround delimiter.
+    pub(crate) fn surround_begin_visual(&mut self) {
+        let Some((start, end)) = self.get_visual_selection_range() else {
+            return;
+        };
+        let linewise = self.mode == Mode::VisualLine;
+        let total = self.buffer().len_chars();
+        let (start_pos, end_pos) = if linewise {
+            let last = (end.line + 1).min(self.buffer().len_lines());
+            (
+                self.buffer().line_to_char(start.line),
+                self.buffer().line_to_char(last),
+            )
+        } else {
+            (
+                self.buffer().line_to_char(start.line) + start.col,
+                (self.buffer().line_to_char(end.line) + end.col + 1).min(total),
+            )
+        };
+        self.mode = Mode::Normal;
+        self.visual_anchor = None;
+        self.visual_dollar = false;
+        self.view_mut().cursor = start;
+        if start_pos < end_pos {
+            self.pending_surround = Some(SurroundPending::Add {
+                start: start_pos,
+                end: end_pos,
+                linewise,
+            });
+        }
+    }
+
+    /// `ds{target}` (no replacement) or `cs{target}{replacement}`, as one
+    /// undo step.  The cursor ends on the opening delimiter.
+    fn surround_replace(
+        &mut self,
+        target: char,
+        replacement: Option<(&str, &str)>,
+        changed: &mut bool,
+    ) {
+        let Some((open, close)) = self.surround_target_ranges(target) else {
+            return;
+        };
+        let (open_text, close_text) = replacement.unwrap_or(("", ""));
+        self.start_undo_group();
+        self.delete_with_undo(close.0, close.1);
+        if !close_text.is_empty() {
+            self.insert_with_undo(close.0, close_text);
+        }
+        self.delete_with_undo(open.0, open.1);
+        if !open_text.is_empty() {
+            self.insert_with_undo(open.0, open_text);
+        }
+        self.finish_undo_group();
+        self.view_mut().cursor = self.char_idx_to_cursor(open.0);
+        self.clamp_cursor_col();
+        *changed = true;
+    }
+
+    /// Wrap `start..end` with the delimiters for `ch`, as one undo step.
+    /// Charwise ranges drop trailing whitespace first; linewise ranges get the
+    /// delimiters on their own lines at the first line's indent.
+    fn surround_add(
+        &mut self,
+        start: usize,
+        mut end: usize,
+        linewise: bool,
+        ch: char,
+        changed: &mut bool,
+    ) {
+        let Some((open, close)) = Self::surround_delimiters(ch) else {
+            return;
+        };
+        if !linewise {
+            while end > start && self.buffer().content.char(end - 1).is_whitespace() {
+                end -= 1;
+            }
+            if start == end {
+                return;
+            }
+        }
+        self.start_undo_group();
+        let cursor_pos = if linewise {
+            let line = self.buffer().content.char_to_line(start);
+            let indent: String = self
+                .buffer()
+                .content
+                .line(line)
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect();
+            let ends_with_newline = end > start && self.buffer().content.char(end - 1) == '\n';
+            let close_line = if ends_with_newline {
+                format!("{}{}\n", indent, close.trim())
+            } else {
+                format!("\n{}{}", indent, close.trim())
+            };
+            self.insert_with_undo(end, &close_line);
+            self.insert_with_undo(start, &format!("{}{}\n", indent, open.trim()));
+            start + indent.chars().count()
+        } else {
+            self.insert_with_undo(end, close);
+            self.insert_with_undo(start, open);
+            start
+        };
+        self.finish_undo_group();
+        self.view_mut().cursor = self.char_idx_to_cursor(cursor_pos);
+        self.clamp_cursor_col();
+        *changed = true;
+    }
 }
@@ -1,5 +1,8 @@
 use super::*;
 
+/// How long the visible range must be stable before inlay hints are requested.
+const INLAY_HINT_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
 impl Engine {
     // ─── Dialog system ─────────────────────────────────────────────────
 
@@ -178,7 +181,7 @@ impl Engine {
                                 self.explorer_needs_refresh = true;
                             }
                             Err(e) => {
-                                self.message = e;
+                                self.set_error_message(e);
                             }
                         }
                     }
@@ -255,7 +258,7 @@ impl Engine {
                                     self.explorer_needs_refresh = true;
                                 }
                                 Err(e) => {
-                                    self.message = e;
+                                    self.set_error_message(e);
                                 }
                             }
                         }
@@ -332,24 +335,6 @@ impl Engine {
                 }
                 EngineAction::None
             }
-            "code_actions" => {
-                if let Some(idx_str) = action.strip_prefix("apply_") {
-                    if let Ok(idx) = idx_str.parse::<usize>() {
-                        if let Some(ca) = self.pending_code_action_choices.get(idx).cloned() {
-                            self.pending_code_action_choices.clear();
-                            if let Some(edit) = ca.edit {
-                                self.apply_workspace_edit(edit);
-                                self.message = format!("Applied: {}", ca.title);
-                            } else {
-                                self.message = format!("No edit available for '{}'", ca.title);
-                            }
-                        }
-                    }
-                } else {
-                    self.pending_code_action_choices.clear();
-                }
-                EngineAction::None
-            }
             "close_tab_confirm" => {
                 match action {
                     "save_close" => {
@@ -366,10 +351,13 @@ impl Engine {
                 EngineAction::None
             }
             "quit_unsaved" => match action {
-                "save_quit" => {
-                    self.save_all_dirty();
-                    EngineAction::SaveQuit
-                }
+                "save_quit" => match self.save_all_dirty() {
+                    Ok(_) => EngineAction::SaveQuit,
+                    Err(e) => {
+                        self.set_error_message(e);
+                        EngineAction::Error
+                    }
+                },
                 "discard_quit" => EngineAction::Quit,
                 _ => EngineAction::None, // cancel
             },
@@ -421,6 +409,53 @@ impl Engine {
         crate::core::swap::delete_swap(&swap_path);
     }
 
+    /// Persist a buffer's undo history after a successful save (`:set undofile`).
+    pub(crate) fn undo_file_write_for_buffer(&self, buf_id: BufferId) {
+        if !self.settings.persistent_undo {
+            return;
+        }
+        let Some(state) = self.buffer_manager.get(buf_id) else {
+            return;
+        };
+        let Some(canonical) = &state.canonical_path else {
+            return;
+        };
+        let file = crate::core::undofile::UndoFile::new(
+            &state.buffer.to_string(),
+            &state.undo_stack,
+            &state.redo_stack,
+        );
+        crate::core::undofile::write_undo_file(
+            &crate::core::undofile::undo_path_for(canonical),
+            &file,
+        );
+    }
+
+    /// Restore persisted undo history for a freshly opened buffer.  The
+    /// history is only applied when the text still matches what was saved.
+    pub(crate) fn undo_file_restore_for_buffer(&mut self, buf_id: BufferId) {
+        if !self.settings.persistent_undo {
+            return;
+        }
+        let Some(state) = self.buffer_manager.get_mut(buf_id) else {
+            return;
+        };
+        if state.dirty || !state.undo_stack.is_empty() || !state.redo_stack.is_empty() {
+            return;
+        }
+        let Some(canonical) = &state.canonical_path else {
+            return;
+        };
+        let undo_path = crate::core::undofile::undo_path_for(canonical);
+        if let Some(file) =
+            crate::core::undofile::read_undo_file(&undo_path, &state.buffer.to_string())
+        {
+            state.saved_undo_depth = Some(file.undo_stack.len());
+            state.undo_stack = file.undo_stack;
+            state.redo_stack = file.redo_stack;
+        }
+    }
+
     /// Delete swap files for ALL open buffers.  Called on clean shutdown.
     pub fn cleanup_all_swaps(&self) {
         for buf_id in self.buffer_manager.list() {
@@ -656,6 +691,20 @@ impl Engine {
         }
     }
 
+    /// Request the document symbol tree for the winbar and sticky scroll.
+    /// No-op unless `:set winbar` or `:set stickyscroll` is on.
+    pub(crate) fn lsp_request_winbar_symbols(&mut self, path: &Path) {
+        if !self.settings.winbar && !self.settings.sticky_scroll {
+            return;
+        }
+        if let Some(mgr) = &mut self.lsp_manager {
+            if let Some(req_id) = mgr.request_document_symbols(path) {
+                self.lsp_pending_winbar_symbols
+                    .insert(req_id, path.to_path_buf());
+            }
+        }
+    }
+
     pub fn lsp_flush_changes(&mut self) {
         if self.lsp_manager.is_none() {
             return;
@@ -681,6 +730,13 @@ impl Engine {
             // the previous buffer state would highlight/annotate wrong lines.
             if let Some(state) = self.buffer_manager.get_mut(buffer_id) {
                 state.semantic_tokens.clear();
+                state.inlay_hints.clear();
+            }
+            if self
+                .inlay_hint_request_key
+                .is_some_and(|(b, ..)| b == buffer_id)
+            {
+                self.inlay_hint_request_key = None;
             }
             self.lsp_diagnostics.remove(&path);
             self.lsp_code_actions.remove(&path);
@@ -690,18 +746,61 @@ impl Engine {
             }
             // Re-request semantic tokens after the server processes the change.
             self.lsp_request_semantic_tokens(&path);
+            self.lsp_request_winbar_symbols(&path);
+        }
+        self.lsp_refresh_inlay_hints();
+    }
+
+    /// Request inlay hints for the active window's visible lines once the
+    /// range (or the buffer contents) has been stable for `INLAY_HINT_DEBOUNCE`.
+    /// Called from the UI idle loop via `lsp_flush_changes`.
+    pub fn lsp_refresh_inlay_hints(&mut self) {
+        if !self.settings.inlay_hints || !self.settings.lsp_enabled || self.lsp_manager.is_none() {
+            return;
+        }
+        let buffer_id = self.active_buffer_id();
+        let start = self.view().scroll_top;
+        let end = start + self.viewport_lines().max(1);
+        let key = (buffer_id, start, end);
+        if self.inlay_hint_request_key == Some(key) {
+            self.inlay_hint_debounce = None;
+            return;
+        }
+        match self.inlay_hint_debounce {
+            None => {
+                self.inlay_hint_debounce = Some(std::time::Instant::now());
+                return;
+            }
+            Some(t) if t.elapsed() < INLAY_HINT_DEBOUNCE => return,
+            Some(_) => self.inlay_hint_debounce = None,
+        }
+        let Some(path) = self
+            .buffer_manager
+            .get(buffer_id)
+            .filter(|s| s.lsp_language_id.is_some())
+            .and_then(|s| s.file_path.clone())
+        else {
+            return;
+        };
+        if let Some(mgr) = &mut self.lsp_manager {
+            if let Some(id) = mgr.request_inlay_hints(&path, start as u32, end as u32) {
+                self.lsp_pending_inlay_hints.insert(id, buffer_id);
+            }
         }
+        self.inlay_hint_request_key = Some(key);
     }
 
     /// Poll LSP for events. Called every frame from the UI event loop.
     /// Returns true if a redraw is needed.
     pub fn poll_lsp(&mut self) -> bool {
+        let timed_out = self.check_format_on_save_timeout();
+        self.picker_flush_workspace_symbols();
         let events = match &mut self.lsp_manager {
             Some(mgr) => mgr.poll_events(),
-            None => return false,
+            None => return timed_out,
         };
         if events.is_empty() {
-            return false;
+            return timed_out;
         }
 
         // Pre-compute canonical paths for visible buffers (once, not per-event).
@@ -727,7 +826,7 @@ impl Engine {
             .flat_map(|m| m.lsp.ignore_error_sources.clone())
             .collect();
 
-        let mut redraw = false;
+        let mut redraw = timed_out;
         for event in events {
             match event {
                 LspEvent::Initialized(server_id, ..) => {
@@ -760,6 +859,7 @@ impl Engine {
                     // Request semantic tokens for all reopened buffers.
                     for (path, _) in &buffers {
                         self.lsp_request_semantic_tokens(path);
+                        self.lsp_request_winbar_symbols(path);
                     }
                 }
                 LspEvent::Diagnostics {
@@ -799,26 +899,49 @@ impl Engine {
                         // The user may have pressed Escape between the request and response.
                         let in_insert = self.mode == Mode::Insert || self.is_vscode_mode();
                         if in_insert && !items.is_empty() {
-                            let (cur_prefix, _) = self.completion_prefix_at_cursor();
-                            let lsp_cands: Vec<String> = items
-                                .iter()
-                                .filter_map(|item| {
-                                    let text = item.insert_text.as_deref().unwrap_or(&item.label);
-                                    text.starts_with(&cur_prefix).then(|| text.to_string())
-                                })
-                                .collect();
+                            let (cur_prefix, start_col) = self.completion_prefix_at_cursor();
+                            let lsp_cands = Self::rank_completion_items(&items, &cur_prefix);
                             if !lsp_cands.is_empty() {
-                                self.completion_start_col =
-                                    self.view().cursor.col - cur_prefix.chars().count();
-                                self.completion_candidates = lsp_cands;
-                                self.completion_idx = Some(0);
+                                self.completion_start_col = start_col;
+                                self.set_completion_candidates(lsp_cands);
                                 self.completion_display_only = true;
+                                self.completion_items = items;
+                                self.completion_items_origin = Some((
+                                    self.active_buffer_id(),
+                                    self.view().cursor.line,
+                                    start_col,
+                                ));
+                                self.lsp_pending_completion_resolve = None;
                                 redraw = true;
                             }
                         }
                     }
                     // else: stale response (request already superseded) — ignore
                 }
+                LspEvent::CompletionResolveResponse {
+                    server_id,
+                    request_id,
+                    detail,
+                    documentation,
+                } => {
+                    if let Some(mgr) = self.lsp_manager.as_mut() {
+                        mgr.mark_server_responded(server_id);
+                    }
+                    if let Some((_, idx)) = self
+                        .lsp_pending_completion_resolve
+                        .filter(|(id, _)| *id == request_id)
+                    {
+                        self.lsp_pending_completion_resolve = None;
+                        if let Some(item) = self.completion_items.get_mut(idx) {
+                            if detail.is_some() {
+                                item.detail = detail;
+                            }
+                            // An empty string marks the item as resolved.
+                            item.documentation = Some(documentation.unwrap_or_default());
+                            redraw = true;
+                        }
+                    }
+                }
                 LspEvent::DefinitionResponse {
                     server_id,
                     locations,
@@ -1045,40 +1168,14 @@ impl Engine {
                     self.lsp_pending_references = None;
                     if locations.is_empty() {
                         self.message = "No references found".to_string();
-                    } else if locations.len() == 1 {
-                        // Single result — jump directly like gd
-                        let loc = &locations[0];
-                        let path = loc.path.clone();
-                        let line = loc.range.start.line as usize;
-                        if path
-                            != self
-                                .buffer_manager
-                                .get(self.active_buffer_id())
-                                .and_then(|s| s.file_path.clone())
-                                .unwrap_or_default()
-                        {
-                            let _ = self.open_file_with_mode(&path, OpenMode::Permanent);
-                        }
-                        self.view_mut().cursor.line = line;
-                        let line_text: String = self.buffer().content.line(line).chars().collect();
-                        let col = lsp::utf16_offset_to_char(&line_text, loc.range.start.character);
-                        self.view_mut().cursor.col = col;
-                        self.ensure_cursor_visible();
                     } else {
-                        // Multiple results — populate quickfix window
-                        self.quickfix_items = locations
-                            .into_iter()
-                            .map(|l| ProjectMatch {
-                                file: l.path,
-                                line: l.range.start.line as usize,
-                                col: l.range.start.character as usize,
-                                line_text: String::new(),
-                            })
-                            .collect();
+                        self.quickfix_items = self.references_to_quickfix(locations);
                         self.quickfix_selected = 0;
                         self.quickfix_open = true;
                         self.quickfix_has_focus = false;
-                        self.message = format!("{} references found", self.quickfix_items.len());
+                        let n = self.quickfix_items.len();
+                        self.message =
+                            format!("{n} reference{} found", if n == 1 { "" } else { "s" });
                     }
                     redraw = true;
                 }
@@ -1152,11 +1249,29 @@ impl Engine {
                     }
                 }
                 LspEvent::FormattingResponse {
-                    request_id, edits, ..
+                    request_id,
+                    edits,
+                    error_message,
+                    ..
                 } => {
                     if self.lsp_pending_formatting == Some(request_id) {
                         self.lsp_pending_formatting = None;
-                        let buffer_id = self.active_buffer_id();
+                        let buffer_id = self
+                            .lsp_formatting_buffer
+                            .take()
+                            .unwrap_or_else(|| self.active_buffer_id());
+                        let format_on_save = self.format_on_save_pending == Some(buffer_id);
+                        if let Some(err) = error_message {
+                            if format_on_save {
+                                let warning =
+                                    format!("Formatting failed ({err}), saved unformatted");
+                                self.finish_format_on_save(Some(&warning));
+                            } else {
+                                self.message = format!("Formatting failed: {err}");
+                            }
+                            redraw = true;
+                            continue;
+                        }
                         let had_edits = !edits.is_empty();
                         if had_edits {
                             self.apply_lsp_edits(buffer_id, edits);
@@ -1165,12 +1280,8 @@ impl Engine {
                             self.lsp_dirty_buffers.insert(buffer_id, true);
                         }
                         // If this was a format-on-save, perform the actual save now.
-                        if self.format_on_save_pending.take() == Some(buffer_id) {
-                            let _ = self.save();
-                            if self.quit_after_format_save {
-                                self.quit_after_format_save = false;
-                                self.format_save_quit_ready = true;
-                            }
+                        if format_on_save {
+                            self.finish_format_on_save(None);
                         } else if had_edits {
                             self.message = "Buffer formatted".to_string();
                         } else {
@@ -1187,18 +1298,31 @@ impl Engine {
                 } => {
                     if self.lsp_pending_rename == Some(request_id) {
                         self.lsp_pending_rename = None;
-                        let n = workspace_edit.changes.len();
-                        if n > 0 {
-                            self.apply_workspace_edit(workspace_edit);
-                            self.message = format!("Renamed in {n} file(s)");
-                        } else if let Some(err) = error_message {
-                            self.message = format!("Rename failed: {err}");
-                        } else {
-                            self.message = "Rename: no changes returned by server".to_string();
-                        }
+                        self.handle_rename_response(workspace_edit, error_message);
                         redraw = true;
                     }
                 }
+                LspEvent::InlayHintResponse {
+                    request_id, hints, ..
+                } => {
+                    if let Some(buffer_id) = self.lsp_pending_inlay_hints.remove(&request_id) {
+                        if let Some(state) = self.buffer_manager.get_mut(buffer_id) {
+                            state.inlay_hints = hints;
+                            redraw = true;
+                        }
+                    }
+                }
+                LspEvent::ApplyWorkspaceEdit {
+                    server_id,
+                    request_id,
+                    workspace_edit,
+                } => {
+                    let result = self.apply_server_workspace_edit(workspace_edit);
+                    if let Some(mgr) = self.lsp_manager.as_ref() {
+                        mgr.respond(server_id, request_id, result);
+                    }
+                    redraw = true;
+                }
                 LspEvent::SemanticTokensResponse {
                     server_id,
                     request_id,
@@ -1245,7 +1369,7 @@ impl Engine {
                                 if actions.is_empty() {
                                     self.message = "No code actions available".to_string();
                                 } else {
-                                    self.show_code_actions_hover(line, actions);
+                                    self.open_code_action_menu(actions);
                                 }
                             }
                             redraw = true;
@@ -1257,7 +1381,16 @@ impl Engine {
                     symbols,
                     ..
                 } => {
-                    if self.lsp_pending_document_symbols == Some(request_id) {
+                    if let Some(path) = self.lsp_pending_winbar_symbols.remove(&request_id) {
+                        for &bid in self.buffer_manager.list().iter() {
+                            if let Some(state) = self.buffer_manager.get_mut(bid) {
+                                if state.file_path.as_ref() == Some(&path) {
+                                    state.document_symbols = symbols.clone();
+                                }
+                            }
+                        }
+                        redraw = true;
+                    } else if self.lsp_pending_document_symbols == Some(request_id) {
                         self.lsp_pending_document_symbols = None;
                         if self.picker_open && self.picker_source == PickerSource::CommandCenter {
                             self.picker_populate_document_symbols(symbols);
@@ -1575,25 +1708,94 @@ impl Engine {
         }
     }
 
-    pub(crate) fn show_code_actions_hover(&mut self, _line: usize, actions: Vec<lsp::CodeAction>) {
-        let buttons: Vec<DialogButton> = actions
+    /// Open the code-action popup listing `actions`, first entry highlighted.
+    pub(crate) fn open_code_action_menu(&mut self, actions: Vec<lsp::CodeAction>) {
+        if actions.is_empty() {
+            return;
+        }
+        self.pending_code_action_choices = actions;
+        self.code_action_menu_idx = Some(0);
+    }
+
+    /// Popup labels for the pending code actions: `1. title (kind)`.
+    pub fn code_action_menu_items(&self) -> Vec<String> {
+        self.pending_code_action_choices
             .iter()
             .enumerate()
-            .map(|(i, a)| {
-                let kind_str = a
-                    .kind
-                    .as_deref()
-                    .map(|k| format!(" ({})", k))
-                    .unwrap_or_default();
-                DialogButton {
-                    label: format!("{}{}", a.title, kind_str),
-                    hotkey: '\0', // no single-key hotkey
-                    action: format!("apply_{}", i),
-                }
+            .map(|(i, a)| match &a.kind {
+                Some(kind) => format!("{}. {} ({kind})", i + 1, a.title),
+                None => format!("{}. {}", i + 1, a.title),
             })
-            .collect();
-        self.pending_code_action_choices = actions;
-        self.show_dialog("code_actions", "Code Actions", vec![], buttons);
+            .collect()
+    }
+
+    /// Close the code-action popup without applying anything.
+    pub(crate) fn close_code_action_menu(&mut self) {
+        self.pending_code_action_choices.clear();
+        self.code_action_menu_idx = None;
+    }
+
+    /// Key handler while the code-action popup is open. Every key is consumed:
+    /// j/k/arrows/Tab/Ctrl-N/P move, Return or 1-9 applies, anything else closes.
+    pub(crate) fn handle_code_action_menu_key(
+        &mut self,
+        key_name: &str,
+        unicode: Option<char>,
+        ctrl: bool,
+    ) {
+        let Some(idx) = self.code_action_menu_idx else {
+            return;
+        };
+        let len = self.pending_code_action_choices.len();
+        let next = matches!((ctrl, unicode), (true, Some('n')) | (false, Some('j')));
+        let prev = matches!((ctrl, unicode), (true, Some('p')) | (false, Some('k')));
+        if next || key_name == "Down" || key_name == "Tab" {
+            self.code_action_menu_idx = Some((idx + 1) % len);
+        } else if prev || key_name == "Up" || key_name == "ISO_Left_Tab" {
+            self.code_action_menu_idx = Some((idx + len - 1) % len);
+        } else if key_name == "Return" {
+            self.apply_code_action_choice(idx);
+        } else {
+            match unicode.and_then(|c| c.to_digit(10)) {
+                Some(d) if !ctrl && d >= 1 && d as usize <= len => {
+                    self.apply_code_action_choice(d as usize - 1);
+                }
+                _ => self.close_code_action_menu(),
+            }
+        }
+    }
+
+    /// Apply the popup entry at `idx`: its edit goes through the undo system,
+    /// then its command (if any) is sent to the server.
+    pub(crate) fn apply_code_action_choice(&mut self, idx: usize) {
+        let choice = self.pending_code_action_choices.get(idx).cloned();
+        self.close_code_action_menu();
+        let Some(ca) = choice else {
+            return;
+        };
+        let has_edit = ca
+            .edit
+            .as_ref()
+            .is_some_and(|we| we.changes.iter().any(|f| !f.edits.is_empty()));
+        if !has_edit && ca.command.is_none() {
+            self.message = format!("No edit available for '{}'", ca.title);
+            return;
+        }
+        if let Some(edit) = ca.edit.filter(|_| has_edit) {
+            self.apply_workspace_edit(edit);
+        }
+        if let Some(command) = &ca.command {
+            let path = self.active_buffer_path();
+            let sent = match (&mut self.lsp_manager, path) {
+                (Some(mgr), Some(path)) => mgr.request_execute_command(&path, command).is_some(),
+                _ => false,
+            };
+            if !sent {
+                self.message = format!("Cannot run '{}': no LSP server", ca.title);
+                return;
+            }
+        }
+        self.message = format!("Applied: {}", ca.title);
     }
 
     /// Request LSP find-references at cursor position.
@@ -1618,6 +1820,50 @@ impl Engine {
         }
     }
 
+    /// Convert reference locations into quickfix entries, filling in the line
+    /// text from an open buffer or, failing that, the file on disk. Columns are
+    /// converted from UTF-16 to char offsets so `quickfix_jump` lands exactly.
+    pub(crate) fn references_to_quickfix(
+        &self,
+        locations: Vec<lsp::Location>,
+    ) -> Vec<ProjectMatch> {
+        let mut disk_cache: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        locations
+            .into_iter()
+            .map(|loc| {
+                let line = loc.range.start.line as usize;
+                let open_buf = self.buffer_manager.list().into_iter().find_map(|bid| {
+                    let state = self.buffer_manager.get(bid)?;
+                    (state.file_path.as_deref() == Some(loc.path.as_path())).then_some(state)
+                });
+                let line_text = match open_buf {
+                    Some(state) if line < state.buffer.len_lines() => {
+                        state.buffer.content.line(line).chars().collect::<String>()
+                    }
+                    Some(_) => String::new(),
+                    None => disk_cache
+                        .entry(loc.path.clone())
+                        .or_insert_with(|| {
+                            std::fs::read_to_string(&loc.path)
+                                .map(|t| t.lines().map(String::from).collect())
+                                .unwrap_or_default()
+                        })
+                        .get(line)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                let line_text = line_text.trim_end_matches(['\n', '\r']).to_string();
+                let col = lsp::utf16_offset_to_char(&line_text, loc.range.start.character);
+                ProjectMatch {
+                    file: loc.path,
+                    line,
+                    col,
+                    line_text,
+                }
+            })
+            .collect()
+    }
+
     /// Request LSP go-to-implementation at cursor position.
     pub(crate) fn lsp_request_implementation(&mut self) {
         if !self.settings.lsp_enabled {
@@ -1688,11 +1934,12 @@ impl Engine {
             Some(v) => v,
             None => return,
         };
-        let tab_size = self.settings.tabstop as u32;
-        let insert_spaces = self.settings.expand_tab;
+        let tab_size = self.effective_tabstop() as u32;
+        let insert_spaces = self.effective_expand_tab();
         if let Some(mgr) = &mut self.lsp_manager {
             if let Some(id) = mgr.request_formatting(&path, tab_size, insert_spaces) {
                 self.lsp_pending_formatting = Some(id);
+                self.lsp_formatting_buffer = Some(self.active_buffer_id());
                 self.message = "Formatting...".to_string();
             } else if mgr.is_server_initializing(&path) {
                 self.message = "LSP server initializing...".to_string();
@@ -1725,11 +1972,45 @@ impl Engine {
         }
     }
 
+    /// Apply the `WorkspaceEdit` from a rename response and report what changed.
+    /// Nothing is touched if the server returned an error or an empty edit.
+    pub(crate) fn handle_rename_response(
+        &mut self,
+        workspace_edit: WorkspaceEdit,
+        error_message: Option<String>,
+    ) {
+        if let Some(err) = error_message {
+            self.message = format!("Rename failed: {err}");
+            return;
+        }
+        if workspace_edit.changes.iter().all(|f| f.edits.is_empty()) {
+            self.message = "Rename: no changes returned by server".to_string();
+            return;
+        }
+        let (files, edits, failed) = self.apply_workspace_edit(workspace_edit);
+        self.message = format!("Renamed: {edits} edit(s) in {files} file(s)");
+        if let Some(first) = failed.first() {
+            self.message.push_str(&format!(
+                "; {} file(s) could not be updated ({})",
+                failed.len(),
+                first.display()
+            ));
+        }
+    }
+
     /// Apply a list of LSP text edits to a buffer as a single undo group.
     /// Edits must be applied in reverse order (last first) to preserve offsets.
-    pub(crate) fn apply_lsp_edits(&mut self, buffer_id: BufferId, mut edits: Vec<FormattingEdit>) {
+    /// Returns false, leaving the buffer untouched, when it may not be changed.
+    pub(crate) fn apply_lsp_edits(
+        &mut self,
+        buffer_id: BufferId,
+        mut edits: Vec<FormattingEdit>,
+    ) -> bool {
         if edits.is_empty() {
-            return;
+            return true;
+        }
+        if !self.check_buffer_modifiable(buffer_id) {
+            return false;
         }
         // Sort in reverse start order so applying one edit doesn't shift others
         edits.sort_by(|a, b| {
@@ -1746,6 +2027,25 @@ impl Engine {
             .find(|w| w.buffer_id == buffer_id)
             .map(|w| w.view.cursor)
             .unwrap_or_default();
+        // Track each window's cursor as a char offset so it follows the text
+        // it was on as edits insert/remove text before it.
+        let mut cursor_offsets: Vec<(WindowId, usize)> = match self.buffer_manager.get(buffer_id) {
+            Some(state) => {
+                let content = &state.buffer.content;
+                let last_line = content.len_lines().saturating_sub(1);
+                self.windows
+                    .iter()
+                    .filter(|(_, w)| w.buffer_id == buffer_id)
+                    .map(|(&id, w)| {
+                        let line = w.view.cursor.line.min(last_line);
+                        let offset = (content.line_to_char(line) + w.view.cursor.col)
+                            .min(content.len_chars());
+                        (id, offset)
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
         if let Some(state) = self.buffer_manager.get_mut(buffer_id) {
             state.start_undo_group(cursor);
         }
@@ -1777,15 +2077,34 @@ impl Engine {
                         .slice(start_offset..end_offset)
                         .chars()
                         .collect();
-                    state.buffer.content.remove(start_offset..end_offset);
+                    state.buffer.delete_range(start_offset, end_offset);
                     state.record_delete(start_offset, &deleted);
                 }
                 if !edit.new_text.is_empty() {
-                    state.buffer.content.insert(start_offset, &edit.new_text);
+                    state.buffer.insert(start_offset, &edit.new_text);
                     state.record_insert(start_offset, &edit.new_text);
                 }
                 state.dirty = true;
             }
+            let inserted = edit.new_text.chars().count();
+            for (_, offset) in &mut cursor_offsets {
+                if *offset >= end_offset {
+                    *offset = *offset - (end_offset - start_offset) + inserted;
+                } else if *offset > start_offset {
+                    *offset = start_offset + (*offset - start_offset).min(inserted);
+                }
+            }
+        }
+        if let Some(state) = self.buffer_manager.get(buffer_id) {
+            let content = &state.buffer.content;
+            for (win_id, offset) in cursor_offsets {
+                let offset = offset.min(content.len_chars());
+                let line = content.char_to_line(offset);
+                let col = offset - content.line_to_char(line);
+                if let Some(win) = self.windows.get_mut(&win_id) {
+                    win.view.cursor = Cursor { line, col };
+                }
+            }
         }
         if let Some(state) = self.buffer_manager.get_mut(buffer_id) {
             state.finish_undo_group();
@@ -1794,58 +2113,114 @@ impl Engine {
         }
         // Mark buffer dirty so the next LSP flush sends didChange + re-requests tokens.
         self.lsp_dirty_buffers.insert(buffer_id, true);
+        true
     }
 
-    /// Apply a workspace-wide rename edit.
-    pub(crate) fn apply_workspace_edit(&mut self, we: WorkspaceEdit) {
+    /// Apply a `workspace/applyEdit` request and build the reply: `applied`
+    /// is only true when every file took its edits.
+    pub(crate) fn apply_server_workspace_edit(&mut self, we: WorkspaceEdit) -> serde_json::Value {
+        let (_, _, failed) = self.apply_workspace_edit(we);
+        if failed.is_empty() {
+            return serde_json::json!({ "applied": true });
+        }
+        let paths: Vec<String> = failed.iter().map(|p| p.display().to_string()).collect();
+        serde_json::json!({
+            "applied": false,
+            "failureReason": format!("Could not edit {}", paths.join(", ")),
+        })
+    }
+
+    /// Apply a workspace-wide edit (rename, code action). Open buffers are
+    /// edited through the undo system; other files are rewritten on disk.
+    /// Returns `(files_changed, edits_applied, files_that_failed)`.
+    pub(crate) fn apply_workspace_edit(
+        &mut self,
+        we: WorkspaceEdit,
+    ) -> (usize, usize, Vec<PathBuf>) {
+        let mut files = 0;
+        let mut edit_count = 0;
+        let mut failed = Vec::new();
         for file_edit in we.changes {
+            if file_edit.edits.is_empty() {
+                continue;
+            }
+            let n = file_edit.edits.len();
             // Try to find an already-open buffer for this path
+            let target = file_edit
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| file_edit.path.clone());
             let buffer_id = self.buffer_manager.list().into_iter().find(|&bid| {
                 self.buffer_manager
                     .get(bid)
-                    .and_then(|s| s.file_path.as_deref())
-                    .map(|p| p == file_edit.path)
-                    .unwrap_or(false)
+                    .is_some_and(|s| match &s.canonical_path {
+                        Some(p) => *p == target,
+                        // The file didn't exist when opened; resolve it now.
+                        None => s.file_path.as_ref().is_some_and(|p| {
+                            p.canonicalize().unwrap_or_else(|_| p.clone()) == target
+                        }),
+                    })
             });
 
             if let Some(bid) = buffer_id {
-                self.apply_lsp_edits(bid, file_edit.edits);
+                if self.apply_lsp_edits(bid, file_edit.edits) {
+                    files += 1;
+                    edit_count += n;
+                } else {
+                    failed.push(file_edit.path);
+                }
             } else {
-                // File not open — read, edit, and write back to disk
-                if let Ok(text) = std::fs::read_to_string(&file_edit.path) {
-                    let mut edits = file_edit.edits;
-                    // Sort in reverse order
-                    edits.sort_by(|a, b| {
-                        b.range
-                            .start
-                            .line
-                            .cmp(&a.range.start.line)
-                            .then(b.range.start.character.cmp(&a.range.start.character))
-                    });
-                    let mut rope = ropey::Rope::from_str(&text);
-                    for edit in &edits {
-                        let total_lines = rope.len_lines();
-                        let start_line =
-                            (edit.range.start.line as usize).min(total_lines.saturating_sub(1));
-                        let end_line =
-                            (edit.range.end.line as usize).min(total_lines.saturating_sub(1));
-                        let start_line_text: String = rope.line(start_line).chars().collect();
-                        let end_line_text: String = rope.line(end_line).chars().collect();
-                        let start_char =
-                            lsp::utf16_offset_to_char(&start_line_text, edit.range.start.character);
-                        let end_char =
-                            lsp::utf16_offset_to_char(&end_line_text, edit.range.end.character);
-                        let start_offset = rope.line_to_char(start_line) + start_char;
-                        let end_offset = rope.line_to_char(end_line) + end_char;
-                        if end_offset > start_offset {
-                            rope.remove(start_offset..end_offset);
-                        }
-                        rope.insert(start_offset, &edit.new_text);
+                // File not open — read, edit, and write back to disk in the
+                // file's own encoding
+                let Ok((text, file_encoding, _)) = encoding::read_file(&target, None) else {
+                    failed.push(file_edit.path);
+                    continue;
+                };
+                let mut edits = file_edit.edits;
+                // Sort in reverse order
+                edits.sort_by(|a, b| {
+                    b.range
+                        .start
+                        .line
+                        .cmp(&a.range.start.line)
+                        .then(b.range.start.character.cmp(&a.range.start.character))
+                });
+                let mut rope = ropey::Rope::from_str(&text);
+                for edit in &edits {
+                    let total_lines = rope.len_lines();
+                    let start_line =
+                        (edit.range.start.line as usize).min(total_lines.saturating_sub(1));
+                    let end_line =
+                        (edit.range.end.line as usize).min(total_lines.saturating_sub(1));
+                    let start_line_text: String = rope.line(start_line).chars().collect();
+                    let end_line_text: String = rope.line(end_line).chars().collect();
+                    let start_char =
+                        lsp::utf16_offset_to_char(&start_line_text, edit.range.start.character);
+                    let end_char =
+                        lsp::utf16_offset_to_char(&end_line_text, edit.range.end.character);
+                    let start_offset = rope.line_to_char(start_line) + start_char;
+                    let end_offset = rope.line_to_char(end_line) + end_char;
+                    if end_offset > start_offset {
+                        rope.remove(start_offset..end_offset);
                     }
-                    let _ = std::fs::write(&file_edit.path, rope.to_string());
+                    rope.insert(start_offset, &edit.new_text);
+                }
+                let backup = safe_write::BackupMode {
+                    keep: self.settings.backup,
+                    during_write: self.settings.writebackup,
+                };
+                let written = file_encoding
+                    .encode(&rope.to_string())
+                    .and_then(|bytes| safe_write::write_file(&target, &bytes, backup));
+                if written.is_ok() {
+                    files += 1;
+                    edit_count += n;
+                } else {
+                    failed.push(file_edit.path);
                 }
             }
         }
+        (files, edit_count, failed)
     }
 
     /// Get the cursor's file path, line, and UTF-16 column for LSP requests.
@@ -1870,13 +2245,15 @@ impl Engine {
             Some(p) => p,
             None => return,
         };
-        let diags = match self.lsp_diagnostics.get(&path) {
-            Some(d) if !d.is_empty() => d,
+        let mut diags = match self.lsp_diagnostics.get(&path) {
+            Some(d) if !d.is_empty() => d.clone(),
             _ => {
                 self.message = "No diagnostics".to_string();
                 return;
             }
         };
+        // Servers don't promise any order.
+        diags.sort_by_key(|d| (d.range.start.line, d.range.start.character));
         let cur_line = self.view().cursor.line as u32;
         let cur_char = self.view().cursor.col as u32;
 
@@ -1906,13 +2283,15 @@ impl Engine {
             Some(p) => p,
             None => return,
         };
-        let diags = match self.lsp_diagnostics.get(&path) {
-            Some(d) if !d.is_empty() => d,
+        let mut diags = match self.lsp_diagnostics.get(&path) {
+            Some(d) if !d.is_empty() => d.clone(),
             _ => {
                 self.message = "No diagnostics".to_string();
                 return;
             }
         };
+        // Servers don't promise any order.
+        diags.sort_by_key(|d| (d.range.start.line, d.range.start.character));
         let cur_line = self.view().cursor.line as u32;
         let cur_char = self.view().cursor.col as u32;
 
@@ -1931,6 +2310,64 @@ impl Engine {
         self.message = format!("{}: {}", diag.severity.symbol(), diag.message);
     }
 
+    /// `:Diagnostics` — collect the diagnostics of every open buffer into the
+    /// quickfix list, errors first, then by file and position.  Buffers
+    /// without LSP diagnostics are skipped.
+    pub fn diagnostics_to_quickfix(&mut self) -> EngineAction {
+        let mut entries: Vec<(lsp::DiagnosticSeverity, ProjectMatch)> = Vec::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        for bid in self.buffer_manager.list() {
+            let Some(state) = self.buffer_manager.get(bid) else {
+                continue;
+            };
+            let Some(file) = state.file_path.as_ref() else {
+                continue;
+            };
+            let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+            let Some(diags) = self.lsp_diagnostics.get(&canonical) else {
+                continue;
+            };
+            if !seen.insert(canonical) {
+                continue;
+            }
+            for diag in diags {
+                let line = diag.range.start.line as usize;
+                let line_text: String = if line < state.buffer.len_lines() {
+                    state.buffer.content.line(line).chars().collect()
+                } else {
+                    String::new()
+                };
+                let col = lsp::utf16_offset_to_char(&line_text, diag.range.start.character);
+                let message = diag.message.lines().next().unwrap_or("");
+                entries.push((
+                    diag.severity,
+                    ProjectMatch {
+                        file: file.clone(),
+                        line,
+                        col,
+                        line_text: format!("[{}] {}", diag.severity.symbol(), message),
+                    },
+                ));
+            }
+        }
+        if entries.is_empty() {
+            self.message = "No diagnostics".to_string();
+            return EngineAction::None;
+        }
+        entries.sort_by(|(sa, a), (sb, b)| {
+            sa.cmp(sb)
+                .then_with(|| a.file.cmp(&b.file))
+                .then_with(|| (a.line, a.col).cmp(&(b.line, b.col)))
+        });
+        let n = entries.len();
+        self.quickfix_items = entries.into_iter().map(|(_, m)| m).collect();
+        self.quickfix_selected = 0;
+        self.quickfix_open = true;
+        self.quickfix_has_focus = false;
+        self.message = format!("{n} diagnostic{}", if n == 1 { "" } else { "s" });
+        EngineAction::None
+    }
+
     /// Shut down all LSP servers (called on quit).
     pub fn lsp_shutdown(&mut self) {
         if let Some(mgr) = &mut self.lsp_manager {
@@ -1,5 +1,44 @@
 use super::*;
 
+/// Idle time after the last keystroke before a `#` workspace-symbol query is
+/// sent to the language server.
+const WORKSPACE_SYMBOL_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Idle time after the last keystroke before a live-grep query runs.
+const GREP_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Most results the live-grep list keeps.
+const GREP_ITEM_CAP: usize = 200;
+
+/// Lines shown in the file picker's preview pane.
+const PREVIEW_MAX_LINES: usize = 500;
+
+/// Bytes read from a file for its preview; the first `PREVIEW_MAX_LINES`
+/// lines of ordinary source files fit comfortably.
+const PREVIEW_MAX_BYTES: u64 = 256 * 1024;
+
+/// Files larger than this are previewed from a worker thread so moving the
+/// selection never stalls on disk or parsing.
+const PREVIEW_BACKGROUND_BYTES: u64 = 512 * 1024;
+
+/// Sent from a live-grep worker thread.
+pub(crate) enum GrepEvent {
+    /// One file's matches, in line order.
+    Matches(Vec<ProjectMatch>),
+    /// The walk finished (or was cancelled).
+    Done(Result<bool, SearchError>),
+}
+
+/// A live-grep search running in the background.
+pub(crate) struct GrepJob {
+    control: std::sync::Arc<SearchControl>,
+    rx: std::sync::mpsc::Receiver<GrepEvent>,
+    started: std::time::Instant,
+    /// Set once this search's first event replaced the previous results,
+    /// which stay on screen until then.
+    fresh: bool,
+}
+
 // ─── Fuzzy score (shared utility, used by tab switcher + unified picker) ──────
 
 impl Engine {
@@ -79,12 +118,16 @@ impl Engine {
 
     /// Open the unified picker with a given source.
     pub fn open_picker(&mut self, source: PickerSource) {
+        self.cancel_picker_grep();
+        self.picker_grep_total = None;
         self.picker_query.clear();
         self.picker_selected = 0;
         self.picker_scroll_top = 0;
         self.picker_all_items.clear();
         self.picker_items.clear();
         self.picker_preview = None;
+        self.picker_preview_cache.clear();
+        self.picker_preview_job = None;
         self.breadcrumb_scoped_parent = None;
         self.picker_history_index = None;
         self.picker_history_typing_buffer.clear();
@@ -131,17 +174,48 @@ impl Engine {
                 self.picker_title = "Select Line Ending Sequence".to_string();
                 self.picker_populate_line_endings();
             }
+            PickerSource::RecentFiles => {
+                self.picker_title = "Recent Files".to_string();
+                self.picker_populate_recent_files();
+            }
+            PickerSource::Harpoon => {
+                self.picker_title = "Pinned Files (C-k/C-j move, C-d remove)".to_string();
+                self.picker_populate_harpoon();
+            }
             _ => {
                 self.picker_title = format!("{:?}", source);
             }
         }
 
         self.picker_source = source;
+        let files = self.picker_source == PickerSource::Files;
+        if files && self.settings.fuzzy_remember_query {
+            self.picker_query = self.picker_last_file_query.clone();
+        }
         self.picker_filter();
+        if files {
+            self.picker_select_last_file();
+        }
         self.picker_load_preview();
         self.picker_open = true;
     }
 
+    /// Put the file picker's cursor on the file last opened from it, when
+    /// the current list still contains it.
+    fn picker_select_last_file(&mut self) {
+        let Some(ref last) = self.picker_last_file else {
+            return;
+        };
+        let found = self
+            .picker_items
+            .iter()
+            .position(|item| matches!(&item.action, PickerAction::OpenFile(p) if p == last));
+        if let Some(idx) = found {
+            self.picker_selected = idx;
+            self.picker_update_scroll();
+        }
+    }
+
     /// Open the Command Center picker (called from menu bar search box click).
     pub fn open_command_center(&mut self) {
         self.open_picker(PickerSource::CommandCenter);
@@ -210,6 +284,11 @@ impl Engine {
 
     /// Close the unified picker and clear all state.
     pub fn close_picker(&mut self) {
+        if self.picker_open && self.picker_source == PickerSource::Files {
+            self.picker_last_file_query = self.picker_query.clone();
+        }
+        self.cancel_picker_grep();
+        self.picker_grep_total = None;
         self.picker_open = false;
         self.picker_query.clear();
         self.picker_all_items.clear();
@@ -217,6 +296,8 @@ impl Engine {
         self.picker_selected = 0;
         self.picker_scroll_top = 0;
         self.picker_preview = None;
+        self.picker_preview_cache.clear();
+        self.picker_preview_job = None;
         self.breadcrumb_scoped_parent = None;
     }
 
@@ -346,6 +427,41 @@ impl Engine {
         self.picker_all_items = items;
     }
 
+    /// MRU files from the buffer manager, newest first; files that no
+    /// longer exist are skipped.
+    fn picker_populate_recent_files(&mut self) {
+        let cwd = self.cwd.clone();
+        self.picker_all_items = self
+            .buffer_manager
+            .recent_files
+            .iter()
+            .filter(|p| p.is_file())
+            .map(|path| {
+                let display = path
+                    .strip_prefix(&cwd)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string();
+                let icon = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|ext| crate::icons::file_icon(ext).to_string());
+                PickerItem {
+                    filter_text: display.clone(),
+                    display,
+                    detail: None,
+                    action: PickerAction::OpenFile(path.clone()),
+                    icon,
+                    score: 0,
+                    match_positions: Vec::new(),
+                    depth: 0,
+                    expandable: false,
+                    expanded: false,
+                }
+            })
+            .collect();
+    }
+
     /// Populate picker_all_items with command palette entries.
     fn picker_populate_commands(&mut self) {
         let use_vscode = self.is_vscode_mode();
@@ -408,12 +524,12 @@ impl Engine {
                     .and_then(|e| e.to_str())
                     .map(|ext| crate::icons::file_icon(ext).to_string());
                 PickerItem {
-                    display: name,
                     filter_text: state
                         .file_path
                         .as_ref()
                         .map(|p| p.display().to_string())
-                        .unwrap_or_default(),
+                        .unwrap_or_else(|| name.clone()),
+                    display: name,
                     detail,
                     action,
                     icon,
@@ -427,6 +543,54 @@ impl Engine {
             .collect();
     }
 
+    /// `:b <query>` — switch to the only open buffer whose path contains
+    /// `query` (falling back to a fuzzy match).  When several buffers match,
+    /// open the buffer picker pre-filtered by `query` instead.
+    pub(crate) fn buffer_switch_fuzzy(&mut self, query: &str) {
+        let names: Vec<(BufferId, String)> = self
+            .buffer_manager
+            .list()
+            .into_iter()
+            .filter_map(|id| {
+                let state = self.buffer_manager.get(id)?;
+                let name = state
+                    .file_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| state.display_name());
+                Some((id, name))
+            })
+            .collect();
+        let mut candidates: Vec<BufferId> = names
+            .iter()
+            .filter(|(_, name)| name.contains(query))
+            .map(|(id, _)| *id)
+            .collect();
+        if candidates.is_empty() {
+            candidates = names
+                .iter()
+                .filter(|(_, name)| Self::fuzzy_score(name, query).is_some())
+                .map(|(id, _)| *id)
+                .collect();
+        }
+        match candidates.as_slice() {
+            [] => self.message = format!("No matching buffer for {query}"),
+            [id] => {
+                let current = self.active_buffer_id();
+                if *id != current {
+                    self.buffer_manager.alternate_buffer = Some(current);
+                    self.switch_window_buffer(*id);
+                }
+            }
+            _ => {
+                self.open_picker(PickerSource::Buffers);
+                self.picker_query = query.to_string();
+                self.picker_filter();
+                self.picker_load_preview();
+            }
+        }
+    }
+
     fn picker_populate_keybindings(&mut self) {
         let is_vscode = self.is_vscode_mode();
         let content = if is_vscode {
@@ -524,7 +688,7 @@ impl Engine {
         for km in &self.user_keymaps {
             let keys_str = km.keys.join("");
             let display = format!(
-                "{:<24}:{} [mode: {}] (user remap)",
+                "{:<24}{} [mode: {}] (user remap)",
                 keys_str, km.action, km.mode
             );
             self.picker_all_items.push(PickerItem {
@@ -688,6 +852,13 @@ impl Engine {
     pub(crate) fn picker_filter(&mut self) {
         const CAP: usize = 100;
 
+        // Leaving grep mode (Command Center `%` prefix deleted): stop the
+        // search so it can't append to another mode's list.
+        if !self.picker_is_grep() {
+            self.cancel_picker_grep();
+            self.picker_grep_total = None;
+        }
+
         // Live grep: run project search directly instead of fuzzy-filtering.
         if self.picker_source == PickerSource::Grep {
             self.picker_grep_search();
@@ -811,7 +982,8 @@ impl Engine {
             self.picker_title = "Go to Symbol in Workspace".to_string();
             let sub_query = rest.trim_start().to_string();
             if sub_query.len() >= 2 {
-                self.picker_request_workspace_symbols(&sub_query);
+                // Debounced: sent from `poll_lsp` once typing pauses.
+                self.workspace_symbol_debounce = Some((sub_query, std::time::Instant::now()));
             } else if sub_query.is_empty() {
                 self.picker_items = vec![PickerItem {
                     display: "Type at least 2 characters to search workspace symbols..."
@@ -865,6 +1037,8 @@ impl Engine {
             self.picker_title = "Search for Text".to_string();
             let sub_query = rest.trim_start().to_string();
             if sub_query.len() < 2 {
+                self.cancel_picker_grep();
+                self.picker_grep_total = None;
                 self.picker_items = vec![PickerItem {
                     display: "Type at least 2 characters to search project...".to_string(),
                     filter_text: String::new(),
@@ -1139,6 +1313,7 @@ impl Engine {
                 .as_ref()
                 .map(|k| (k.line.saturating_sub(1), 0))
                 .unwrap_or((0, 0));
+            let end_line = kids.iter().map(|k| k.end_line).max().unwrap_or(line);
             top_level.push(lsp::SymbolInfo {
                 name: container_name,
                 kind: lsp::SymbolKind::Class, // Best guess for a container
@@ -1147,6 +1322,7 @@ impl Engine {
                 path: first.and_then(|f| f.path),
                 line,
                 character,
+                end_line,
                 children: kids,
             });
         }
@@ -1174,7 +1350,7 @@ impl Engine {
         for sym in sorted {
             let has_children = !sym.children.is_empty();
             let display = format!("{} {}", sym.kind.icon(), sym.name);
-            let detail = Some(sym.kind.label().to_string());
+            let detail = Some(format!("{} · {}", sym.kind.label(), sym.line + 1));
             let action = PickerAction::GotoSymbol(
                 path.to_path_buf(),
                 sym.line as usize,
@@ -1265,6 +1441,24 @@ impl Engine {
         true
     }
 
+    /// Send the pending workspace-symbol query once it has been stable for
+    /// `WORKSPACE_SYMBOL_DEBOUNCE`.  Called on every UI tick from `poll_lsp`.
+    pub(crate) fn picker_flush_workspace_symbols(&mut self) {
+        match &self.workspace_symbol_debounce {
+            Some((_, t)) if t.elapsed() >= WORKSPACE_SYMBOL_DEBOUNCE => {}
+            _ => return,
+        }
+        let Some((query, _)) = self.workspace_symbol_debounce.take() else {
+            return;
+        };
+        if self.picker_open
+            && self.picker_source == PickerSource::CommandCenter
+            && self.picker_query.starts_with('#')
+        {
+            self.picker_request_workspace_symbols(&query);
+        }
+    }
+
     /// Request workspace symbols from LSP.
     fn picker_request_workspace_symbols(&mut self, query: &str) {
         if !self.settings.lsp_enabled {
@@ -1333,47 +1527,206 @@ impl Engine {
     /// Run a live project search for the Grep picker source.
     fn picker_grep_search(&mut self) {
         if self.picker_query.len() < 2 {
+            self.cancel_picker_grep();
+            self.picker_grep_total = None;
             self.picker_items.clear();
             return;
         }
         self.picker_cc_grep_search(&self.picker_query.clone());
     }
 
-    /// Run a project grep search with a given query string and populate picker_items.
-    /// Shared between the standalone Grep picker source and Command Center `%` prefix.
+    /// Whether the picker is showing live-grep results (the Grep source or
+    /// the Command Center `%` prefix).
+    fn picker_is_grep(&self) -> bool {
+        self.picker_source == PickerSource::Grep
+            || (self.picker_source == PickerSource::CommandCenter
+                && self.picker_query.starts_with('%'))
+    }
+
+    /// Queue a live grep for `query`.  Shared between the standalone Grep
+    /// picker source and Command Center `%` prefix.  A running search is
+    /// cancelled at once; the new one starts when typing pauses for
+    /// `GREP_DEBOUNCE` (see `poll_picker_grep`), and the current results
+    /// stay visible until its first matches arrive.
     fn picker_cc_grep_search(&mut self, query: &str) {
-        let options = project_search::SearchOptions::default();
-        let cwd = self.cwd.clone();
-        match project_search::search_in_project(&cwd, query, &options) {
-            Ok(mut results) => {
-                results.truncate(200);
-                self.picker_items = results
-                    .into_iter()
-                    .map(|m| {
-                        let rel = m
-                            .file
-                            .strip_prefix(&cwd)
-                            .unwrap_or(&m.file)
-                            .to_string_lossy()
-                            .into_owned();
-                        let display = format!("{}:{}: {}", rel, m.line + 1, m.line_text.trim());
-                        PickerItem {
-                            filter_text: display.clone(),
-                            display,
-                            detail: None,
-                            action: PickerAction::OpenFileAtLine(m.file.clone(), m.line),
-                            icon: None,
-                            score: 0,
-                            match_positions: Vec::new(),
-                            depth: 0,
-                            expandable: false,
-                            expanded: false,
-                        }
-                    })
-                    .collect();
+        self.cancel_picker_grep();
+        self.picker_grep_debounce = Some((query.to_string(), std::time::Instant::now()));
+    }
+
+    /// Stop the running live grep and drop a queued query.
+    pub(crate) fn cancel_picker_grep(&mut self) {
+        if let Some(job) = self.picker_grep_job.take() {
+            job.control.cancel();
+        }
+        self.picker_grep_debounce = None;
+    }
+
+    /// True while a live grep is queued or running; backends poll faster
+    /// then.
+    pub fn picker_grep_active(&self) -> bool {
+        self.picker_grep_debounce.is_some() || self.picker_grep_job.is_some()
+    }
+
+    /// Start the queued live grep once typing has paused and collect the
+    /// matches the running one has streamed so far.  Called on every UI
+    /// tick; returns `true` when the picker needs a redraw (including the
+    /// spinner while a search runs).
+    pub fn poll_picker_grep(&mut self) -> bool {
+        let mut changed = false;
+        if matches!(&self.picker_grep_debounce, Some((_, t)) if t.elapsed() >= GREP_DEBOUNCE) {
+            if let Some((query, _)) = self.picker_grep_debounce.take() {
+                self.spawn_picker_grep(query);
+                changed = true;
             }
-            Err(_) => self.picker_items.clear(),
         }
+        let Some(ref job) = self.picker_grep_job else {
+            return changed;
+        };
+        let events: Vec<GrepEvent> = job.rx.try_iter().collect();
+        for event in events {
+            self.apply_picker_grep_event(event);
+        }
+        true
+    }
+
+    fn spawn_picker_grep(&mut self, query: String) {
+        let control = std::sync::Arc::new(SearchControl::default());
+        let (tx, rx) = std::sync::mpsc::channel();
+        let root = self.cwd.clone();
+        let opts = self.with_explorer_ignore(SearchOptions::default());
+        let worker = control.clone();
+        std::thread::spawn(move || {
+            let result =
+                project_search::search_in_project_streaming(&root, &query, &opts, &worker, |m| {
+                    let _ = tx.send(GrepEvent::Matches(m));
+                });
+            // Both sends fail harmlessly once the job was cancelled.
+            let _ = tx.send(GrepEvent::Done(result));
+        });
+        self.picker_grep_job = Some(GrepJob {
+            control,
+            rx,
+            started: std::time::Instant::now(),
+            fresh: false,
+        });
+    }
+
+    fn apply_picker_grep_event(&mut self, event: GrepEvent) {
+        let Some(ref mut job) = self.picker_grep_job else {
+            return;
+        };
+        if !std::mem::replace(&mut job.fresh, true) {
+            // First news from this search: drop the previous query's results.
+            self.picker_items.clear();
+            self.picker_selected = 0;
+            self.picker_scroll_top = 0;
+            self.picker_preview = None;
+            self.picker_grep_total = Some(0);
+        }
+        match event {
+            GrepEvent::Matches(matches) => {
+                *self.picker_grep_total.get_or_insert(0) += matches.len();
+                let Some(file) = matches.first().map(|m| m.file.clone()) else {
+                    return;
+                };
+                // Files arrive in walk order: keep the list sorted by path,
+                // so results don't reshuffle by which file was read first.
+                let at = self
+                    .picker_items
+                    .partition_point(|item| Self::grep_item_file(item) <= Some(&file));
+                if at >= GREP_ITEM_CAP {
+                    return;
+                }
+                let first_batch = self.picker_items.is_empty();
+                let count = matches.len().min(GREP_ITEM_CAP - at);
+                let cwd = self.cwd.clone();
+                self.picker_items.splice(
+                    at..at,
+                    matches
+                        .into_iter()
+                        .take(count)
+                        .map(|m| Self::grep_picker_item(&cwd, m)),
+                );
+                self.picker_items.truncate(GREP_ITEM_CAP);
+                if first_batch {
+                    self.picker_load_preview();
+                } else if at <= self.picker_selected {
+                    // Keep the highlight on the item it was on.
+                    self.picker_selected =
+                        (self.picker_selected + count).min(self.picker_items.len() - 1);
+                }
+            }
+            GrepEvent::Done(result) => {
+                self.picker_grep_job = None;
+                if let Err(e) = result {
+                    // Say why nothing matched (e.g. an invalid regex or glob).
+                    self.picker_grep_total = None;
+                    self.picker_preview = None;
+                    self.picker_items = vec![PickerItem {
+                        display: e.0,
+                        filter_text: String::new(),
+                        detail: None,
+                        action: PickerAction::Custom("hint".to_string()),
+                        icon: None,
+                        score: 0,
+                        match_positions: Vec::new(),
+                        depth: 0,
+                        expandable: false,
+                        expanded: false,
+                    }];
+                }
+            }
+        }
+    }
+
+    /// The file a live-grep result opens.
+    fn grep_item_file(item: &PickerItem) -> Option<&PathBuf> {
+        match &item.action {
+            PickerAction::OpenFileAtLine(file, _) => Some(file),
+            _ => None,
+        }
+    }
+
+    fn grep_picker_item(cwd: &Path, m: ProjectMatch) -> PickerItem {
+        let rel = m
+            .file
+            .strip_prefix(cwd)
+            .unwrap_or(&m.file)
+            .to_string_lossy()
+            .into_owned();
+        let display = format!("{}:{}: {}", rel, m.line + 1, m.line_text.trim());
+        PickerItem {
+            filter_text: display.clone(),
+            display,
+            detail: None,
+            action: PickerAction::OpenFileAtLine(m.file, m.line),
+            icon: None,
+            score: 0,
+            match_positions: Vec::new(),
+            depth: 0,
+            expandable: false,
+            expanded: false,
+        }
+    }
+
+    /// Live-grep progress for the picker title: a spinner with the files
+    /// and matches so far while searching, else the final match count.
+    pub fn picker_grep_status(&self) -> Option<String> {
+        if !self.picker_is_grep() {
+            return None;
+        }
+        if let Some(ref job) = self.picker_grep_job {
+            const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+            let frame = FRAMES[job.started.elapsed().as_millis() as usize / 100 % FRAMES.len()];
+            let (files, matches) = job.control.progress();
+            return Some(format!(
+                "{frame} {matches} match{} in {files} file{}",
+                if matches == 1 { "" } else { "es" },
+                if files == 1 { "" } else { "s" },
+            ));
+        }
+        self.picker_grep_total
+            .map(|n| format!("{n} match{}", if n == 1 { "" } else { "es" }))
     }
 
     /// Populate picker items with launch configurations from `.vimcode/launch.json`.
@@ -1616,17 +1969,35 @@ impl Engine {
         match &item.action {
             PickerAction::OpenFile(rel_path) => {
                 let abs = self.cwd.join(rel_path);
-                let Ok(content) = std::fs::read_to_string(&abs) else {
-                    return;
-                };
-                let lines: Vec<(usize, String, bool)> = content
-                    .lines()
-                    .take(500)
-                    .enumerate()
-                    .map(|(i, text)| (i + 1, text.to_string(), false))
-                    .collect();
-                self.picker_preview = Some(PickerPreview { lines });
                 self.picker_preview_scroll = 0;
+                if let Some(cached) = self.picker_preview_cache.get(&abs) {
+                    self.picker_preview = Some(cached.clone());
+                    return;
+                }
+                if self
+                    .picker_preview_job
+                    .as_ref()
+                    .is_some_and(|(path, _)| *path == abs)
+                {
+                    self.picker_preview = Some(PickerPreview::note("Loading…"));
+                    return;
+                }
+                let overrides = self.highlight_overrides.clone();
+                let large =
+                    std::fs::metadata(&abs).is_ok_and(|m| m.len() > PREVIEW_BACKGROUND_BYTES);
+                if large {
+                    let (tx, rx) = std::sync::mpsc::channel();
+                    let path = abs.clone();
+                    std::thread::spawn(move || {
+                        let _ = tx.send(load_file_preview(&path, &overrides));
+                    });
+                    // Replaces (and so abandons) a load for an earlier selection.
+                    self.picker_preview_job = Some((abs, rx));
+                    self.picker_preview = Some(PickerPreview::note("Loading…"));
+                } else if let Some(preview) = load_file_preview(&abs, &overrides) {
+                    self.picker_preview_cache.insert(abs, preview.clone());
+                    self.picker_preview = Some(preview);
+                }
             }
             PickerAction::OpenFileAtLine(path, line) => {
                 let Ok(content) = std::fs::read_to_string(path) else {
@@ -1647,7 +2018,10 @@ impl Engine {
                         (lineno, text.to_string(), is_match)
                     })
                     .collect();
-                self.picker_preview = Some(PickerPreview { lines });
+                self.picker_preview = Some(PickerPreview {
+                    lines,
+                    ..Default::default()
+                });
                 // Scroll so the match line is visible near the top of the preview.
                 let match_offset = match_line.saturating_sub(start);
                 self.picker_preview_scroll = match_offset.saturating_sub(3);
@@ -1656,6 +2030,43 @@ impl Engine {
         }
     }
 
+    /// True while a large file's preview is loading; backends poll faster
+    /// then.
+    pub fn picker_preview_loading(&self) -> bool {
+        self.picker_preview_job.is_some()
+    }
+
+    /// Show a large file's preview once its worker thread has loaded it.
+    /// Called on every UI tick; returns `true` when the picker needs a redraw.
+    pub fn poll_picker_preview(&mut self) -> bool {
+        let Some((_, ref rx)) = self.picker_preview_job else {
+            return false;
+        };
+        let preview = match rx.try_recv() {
+            Ok(preview) => preview,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return false,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => None,
+        };
+        let Some((path, _)) = self.picker_preview_job.take() else {
+            return false;
+        };
+        let selected = match self.picker_items.get(self.picker_selected) {
+            Some(PickerItem {
+                action: PickerAction::OpenFile(rel),
+                ..
+            }) => Some(self.cwd.join(rel)),
+            _ => None,
+        };
+        if let Some(ref preview) = preview {
+            self.picker_preview_cache
+                .insert(path.clone(), preview.clone());
+        }
+        if selected.as_ref() == Some(&path) {
+            self.picker_preview = preview;
+        }
+        true
+    }
+
     /// Execute the currently selected picker item.
     pub fn picker_confirm(&mut self) -> EngineAction {
         self.picker_push_history();
@@ -1663,6 +2074,11 @@ impl Engine {
             self.close_picker();
             return EngineAction::None;
         };
+        if let (PickerSource::Files, PickerAction::OpenFile(rel)) =
+            (&self.picker_source, &item.action)
+        {
+            self.picker_last_file = Some(rel.clone());
+        }
         self.close_picker();
 
         match item.action {
@@ -1809,10 +2225,7 @@ impl Engine {
                 } else {
                     LineEnding::LF
                 };
-                let bid = self.active_buffer_id();
-                if let Some(state) = self.buffer_manager.get_mut(bid) {
-                    state.set_line_ending(new);
-                }
+                self.set_file_format(new);
                 self.message = format!("Line endings: {}", new.as_str());
                 EngineAction::None
             }
@@ -2072,6 +2485,10 @@ impl Engine {
                 self.picker_load_preview();
                 EngineAction::None
             }
+            "k" | "j" | "d" if ctrl && self.picker_source == PickerSource::Harpoon => {
+                self.harpoon_edit_selected(key_name);
+                EngineAction::None
+            }
             "v" if ctrl => {
                 // Paste clipboard into picker query
                 if let Some(text) = Self::clipboard_paste() {
@@ -2172,7 +2589,22 @@ impl Engine {
     pub fn quickfix_jump(&mut self) -> EngineAction {
         if let Some(m) = self.quickfix_items.get(self.quickfix_selected).cloned() {
             self.quickfix_has_focus = false;
-            self.open_file_in_tab(&m.file.clone());
+            // Files that aren't open yet come up as preview tabs, so stepping
+            // through a long list doesn't leave a tab behind for every entry.
+            // Paths are compared canonically: search and build output may
+            // spell a file differently from the buffer that holds it.
+            let target = m.file.canonicalize().unwrap_or_else(|_| m.file.clone());
+            let already_open = self.buffer_manager.list().into_iter().any(|bid| {
+                self.buffer_manager
+                    .get(bid)
+                    .and_then(|s| s.file_path.as_deref())
+                    .is_some_and(|p| p.canonicalize().unwrap_or_else(|_| p.to_path_buf()) == target)
+            });
+            if already_open {
+                self.open_file_in_tab(&m.file);
+            } else {
+                self.open_file_preview(&m.file);
+            }
             let win_id = self.active_window_id();
             self.set_cursor_for_window(win_id, m.line, m.col);
             self.ensure_cursor_visible();
@@ -2180,13 +2612,32 @@ impl Engine {
         EngineAction::None
     }
 
-    /// Run a grep search and populate the quickfix list.
-    pub fn run_quickfix_grep(&mut self, pattern: &str, cwd: PathBuf) -> EngineAction {
+    /// Run a grep search and populate the quickfix list.  Leading
+    /// `--include=GLOB` / `--exclude=GLOB` arguments (repeatable, each may
+    /// hold a comma-separated list) restrict the files searched.
+    pub fn run_quickfix_grep(&mut self, args: &str, cwd: PathBuf) -> EngineAction {
+        let mut opts = SearchOptions::default();
+        let mut pattern = args.trim_start();
+        loop {
+            let (arg, rest) = pattern.split_once(' ').unwrap_or((pattern, ""));
+            let (list, glob) = if let Some(glob) = arg.strip_prefix("--include=") {
+                (&mut opts.include, glob)
+            } else if let Some(glob) = arg.strip_prefix("--exclude=") {
+                (&mut opts.exclude, glob)
+            } else {
+                break;
+            };
+            if !list.is_empty() {
+                list.push(',');
+            }
+            list.push_str(glob);
+            pattern = rest.trim_start();
+        }
         if pattern.is_empty() {
-            self.message = "Usage: :grep <pattern>".to_string();
+            self.message = "Usage: :grep [--include=GLOB] [--exclude=GLOB] <pattern>".to_string();
             return EngineAction::None;
         }
-        let opts = SearchOptions::default();
+        let opts = self.with_explorer_ignore(opts);
         match project_search::search_in_project(&cwd, pattern, &opts) {
             Ok(results) => {
                 let n = results.len();
@@ -2233,3 +2684,197 @@ impl Engine {
         }
     }
 }
+
+// ─── Harpoon (pinned files) ───────────────────────────────────────────────────
+
+impl Engine {
+    /// Pinned files for the current working directory, in slot order.
+    pub fn pinned_files(&self) -> &[PathBuf] {
+        self.session
+            .pinned_files
+            .get(&self.cwd)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// `<leader>a` — pin the current file to the next free slot.
+    pub fn harpoon_add(&mut self) {
+        let Some(path) = self.file_path().map(|p| p.to_path_buf()) else {
+            self.message = "No file to pin".to_string();
+            return;
+        };
+        let path = path.canonicalize().unwrap_or_else(|_| self.cwd.join(&path));
+        if let Some(i) = self.pinned_files().iter().position(|p| *p == path) {
+            self.message = format!("Already pinned (slot {})", i + 1);
+            return;
+        }
+        let list = self
+            .session
+            .pinned_files
+            .entry(self.cwd.clone())
+            .or_default();
+        list.push(path);
+        self.message = format!("Pinned as slot {}", list.len());
+        let _ = self.session.save();
+    }
+
+    /// `<leader>1`..`<leader>4` — open pinned `slot` (1-based).  Opening goes
+    /// through the normal tab logic, so a preview tab is promoted and an
+    /// already-open file keeps (or restores) its cursor position.
+    pub fn harpoon_jump(&mut self, slot: usize) {
+        let Some(path) = slot
+            .checked_sub(1)
+            .and_then(|i| self.pinned_files().get(i))
+            .cloned()
+        else {
+            self.message = format!("Pinned slot {slot} is empty");
+            return;
+        };
+        self.push_jump_location();
+        self.open_file_in_tab(&path);
+    }
+
+    fn picker_populate_harpoon(&mut self) {
+        let cwd = self.cwd.clone();
+        self.picker_all_items = self
+            .pinned_files()
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let display = path
+                    .strip_prefix(&cwd)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string();
+                let icon = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|ext| crate::icons::file_icon(ext).to_string());
+                PickerItem {
+                    filter_text: display.clone(),
+                    display,
+                    detail: Some(format!("{}", i + 1)),
+                    action: PickerAction::OpenFile(path.clone()),
+                    icon,
+                    score: 0,
+                    match_positions: Vec::new(),
+                    depth: 0,
+                    expandable: false,
+                    expanded: false,
+                }
+            })
+            .collect();
+    }
+
+    /// Reorder (`Ctrl-K`/`Ctrl-J`) or remove (`Ctrl-D`) the selected entry of
+    /// the `:Harpoon` popup and persist the new list.
+    fn harpoon_edit_selected(&mut self, key: &str) {
+        if !self.picker_query.is_empty() {
+            self.message = "Clear the filter to edit pinned files".to_string();
+            return;
+        }
+        let idx = self.picker_selected;
+        let Some(list) = self.session.pinned_files.get_mut(&self.cwd) else {
+            return;
+        };
+        if idx >= list.len() {
+            return;
+        }
+        match key {
+            "k" if idx > 0 => {
+                list.swap(idx, idx - 1);
+                self.picker_selected = idx - 1;
+            }
+            "j" if idx + 1 < list.len() => {
+                list.swap(idx, idx + 1);
+                self.picker_selected = idx + 1;
+            }
+            "d" => {
+                list.remove(idx);
+                self.picker_selected = idx.min(list.len().saturating_sub(1));
+            }
+            _ => return,
+        }
+        let _ = self.session.save();
+        self.picker_populate_harpoon();
+        self.picker_filter();
+        self.picker_update_scroll();
+        self.picker_load_preview();
+    }
+}
+
+impl PickerPreview {
+    /// A preview that shows only `text` (no lines).
+    pub(crate) fn note(text: &str) -> Self {
+        Self {
+            note: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Read the start of `path` for the file picker's preview pane and
+/// syntax-highlight it.  Binary files (a NUL byte or invalid UTF-8 in the
+/// first chunk) get a `(binary)` note.  `None` when the file can't be read.
+pub(crate) fn load_file_preview(
+    path: &Path,
+    overrides: &HashMap<String, String>,
+) -> Option<PickerPreview> {
+    use std::io::Read;
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(PREVIEW_MAX_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text,
+        // Cut mid-character by the byte limit: keep the valid prefix.
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return Some(PickerPreview::note("(binary)")),
+    };
+    if text.contains('\0') {
+        return Some(PickerPreview::note("(binary)"));
+    }
+    let lines: Vec<&str> = text.lines().take(PREVIEW_MAX_LINES).collect();
+    let mut highlights = vec![Vec::new(); lines.len()];
+    let syntax =
+        crate::core::syntax::Syntax::new_from_path_with_overrides(path.to_str(), Some(overrides));
+    if let Some(mut syntax) = syntax {
+        let source = lines.join("\n");
+        let mut line_starts = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for line in &lines {
+            line_starts.push(offset);
+            offset += line.len() + 1;
+        }
+        for (start, end, scope) in syntax.parse(&source) {
+            // Split multi-line captures (block comments, strings) per line.
+            let first = line_starts
+                .partition_point(|&s| s <= start)
+                .saturating_sub(1);
+            for (idx, line) in lines.iter().enumerate().skip(first) {
+                let line_start = line_starts[idx];
+                if line_start >= end {
+                    break;
+                }
+                let from = start.saturating_sub(line_start).min(line.len());
+                let to = (end - line_start).min(line.len());
+                if from < to {
+                    highlights[idx].push((from, to, scope.clone()));
+                }
+            }
+        }
+    }
+    Some(PickerPreview {
+        lines: lines
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (i + 1, text.to_string(), false))
+            .collect(),
+        highlights,
+        note: None,
+    })
+}
@@ -197,6 +197,7 @@ impl Engine {
             "hlsearch",
             "ignorecase",
             "smartcase",
+            "wrapscan",
             "incremental_search",
             "editor_mode",
             "explorer_visible_on_startup",
@@ -236,7 +237,7 @@ impl Engine {
         if let Some(msg) = ctx.message {
             self.message = msg;
         }
-        if !ctx.set_lines.is_empty() {
+        if !ctx.set_lines.is_empty() && self.check_modifiable() {
             self.start_undo_group();
             let buf_id = self.active_buffer_id();
             for (line_idx, text) in ctx.set_lines {
@@ -307,7 +308,7 @@ impl Engine {
             self.registers.insert(ch, (content, linewise));
         }
         // Apply line insertions (process in reverse to keep indices stable)
-        if !ctx.insert_lines.is_empty() {
+        if !ctx.insert_lines.is_empty() && self.check_modifiable() {
             let buf_id = self.active_buffer_id();
             let mut insertions = ctx.insert_lines;
             insertions.sort_by_key(|b| std::cmp::Reverse(b.0));
@@ -331,7 +332,7 @@ impl Engine {
             }
         }
         // Apply line deletions (process in reverse to keep indices stable)
-        if !ctx.delete_lines.is_empty() {
+        if !ctx.delete_lines.is_empty() && self.check_modifiable() {
             let buf_id = self.active_buffer_id();
             let mut deletions = ctx.delete_lines;
             deletions.sort_unstable();
@@ -360,7 +361,7 @@ impl Engine {
             let _ = self.execute_command(&cmd);
         }
         // Apply range-based line replacements (Neovim-compatible set_lines)
-        if !ctx.set_lines_range.is_empty() {
+        if !ctx.set_lines_range.is_empty() && self.check_modifiable() {
             self.start_undo_group();
             let buf_id = self.active_buffer_id();
             // Process in reverse order so earlier indices stay valid
@@ -1,5 +1,11 @@
 use super::*;
 
+/// How long the cursor must rest on a word before its occurrences are highlighted.
+const WORD_HIGHLIGHT_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Words shorter than this (in chars) are not highlighted.
+const WORD_HIGHLIGHT_MIN_LEN: usize = 2;
+
 /// Find word boundaries around char position `pos` in `text`.
 /// Returns `(start, end)` where `start..end` is the word range.
 /// A "word" character is alphanumeric or underscore.
@@ -67,6 +73,8 @@ impl Engine {
     pub fn ensure_cursor_visible(&mut self) {
         if self.settings.wrap && self.view().viewport_cols > 0 {
             self.ensure_cursor_visible_wrap();
+        } else if !self.view().folds.is_empty() {
+            self.ensure_cursor_visible_folded();
         } else {
             let scrolloff = self.settings.scrolloff;
             if scrolloff == 0 {
@@ -86,10 +94,57 @@ impl Engine {
         }
     }
 
+    /// Soft-wrap segments of buffer `line` at the active view's width, as
+    /// `(start_char, end_char)` pairs over the line's content (no newline).
+    /// Matches the rows the renderer draws; a single segment when wrap is off.
+    pub(crate) fn wrap_segments(&self, line: usize) -> Vec<(usize, usize)> {
+        let text: String = self.buffer().content.line(line).chars().collect();
+        let cols = if self.settings.wrap {
+            self.view().viewport_cols
+        } else {
+            0
+        };
+        word_wrap_segments(text.trim_end_matches(['\n', '\r']), cols)
+    }
+
+    /// Fold-aware scroll-to-cursor: a closed fold takes one row, so the
+    /// cursor's distance from `scroll_top` is counted in visible lines.
+    pub(crate) fn ensure_cursor_visible_folded(&mut self) {
+        let viewport_lines = self.view().viewport_lines;
+        let scrolloff = self
+            .settings
+            .scrolloff
+            .min(viewport_lines.saturating_sub(1) / 2);
+        let cursor_line = self.view().cursor.line;
+        let scroll_top = self.view().scroll_top;
+        let max_line = self.buffer().len_lines().saturating_sub(1);
+
+        let top_limit = self.view().prev_visible_line(cursor_line, scrolloff);
+        if top_limit < scroll_top {
+            self.set_scroll_top(top_limit);
+            return;
+        }
+        if viewport_lines == 0 {
+            return;
+        }
+        // Visible rows above the cursor, counting no further than the viewport.
+        let mut rows = 0;
+        let mut line = scroll_top;
+        while line < cursor_line && rows < viewport_lines {
+            line = self.view().next_visible_line(line, 1, max_line);
+            rows += 1;
+        }
+        if rows + scrolloff + 1 > viewport_lines {
+            let new_top = self
+                .view()
+                .prev_visible_line(cursor_line, viewport_lines - 1 - scrolloff);
+            self.set_scroll_top(new_top);
+        }
+    }
+
     /// Wrap-aware scroll-to-cursor. Counts visual rows (accounting for
     /// soft-wrapped buffer lines) to determine when to adjust `scroll_top`.
     pub(crate) fn ensure_cursor_visible_wrap(&mut self) {
-        let viewport_cols = self.view().viewport_cols;
         let viewport_lines = self.view().viewport_lines;
         let cursor_line = self.view().cursor.line;
         let cursor_col = self.view().cursor.col;
@@ -106,17 +161,15 @@ impl Engine {
             return;
         }
 
+        // Rows the cursor's line contributes, up to and including the
+        // cursor's segment.
+        let cursor_rows = wrap_segment_index(&self.wrap_segments(cursor_line), cursor_col) + 1;
+
         // Count visual rows from scroll_top up to and including the cursor's
         // visual row within cursor_line.
-        let mut visual_rows: usize = 0;
-        for r in scroll_top..=cursor_line {
-            let line_len = self.buffer().content.line(r).len_chars().saturating_sub(1);
-            if r < cursor_line {
-                visual_rows += engine_visual_rows_for_line(line_len, viewport_cols);
-            } else {
-                // Partial count: only up to the cursor's visual segment.
-                visual_rows += cursor_col / viewport_cols + 1;
-            }
+        let mut visual_rows = cursor_rows;
+        for r in scroll_top..cursor_line {
+            visual_rows += self.wrap_segments(r).len();
         }
 
         // If cursor fits within the viewport, nothing to adjust.
@@ -126,14 +179,11 @@ impl Engine {
 
         // Cursor is below the viewport — walk backwards from cursor_line
         // to find the new scroll_top that makes the cursor visible.
-        let cursor_visual_row_within_line = cursor_col / viewport_cols;
-        // Rows the cursor's line contributes, up to and including cursor segment.
-        let mut rows_used = cursor_visual_row_within_line + 1;
+        let mut rows_used = cursor_rows;
         let mut new_scroll_top = cursor_line;
         if rows_used < viewport_lines && cursor_line > 0 {
             for r in (0..cursor_line).rev() {
-                let line_len = self.buffer().content.line(r).len_chars().saturating_sub(1);
-                let vrows = engine_visual_rows_for_line(line_len, viewport_cols);
+                let vrows = self.wrap_segments(r).len();
                 if rows_used + vrows > viewport_lines {
                     break;
                 }
@@ -149,31 +199,23 @@ impl Engine {
     /// Move cursor down by one visual row (within the same wrapped line if
     /// possible, otherwise to the next buffer line).  Used by `gj`.
     pub(crate) fn move_visual_down(&mut self) {
-        let vp = self.view().viewport_cols.max(1);
         let cursor_line = self.view().cursor.line;
         let cursor_col = self.view().cursor.col;
         let total_lines = self.buffer().len_lines();
-        let line_len = self
-            .buffer()
-            .content
-            .line(cursor_line)
-            .len_chars()
-            .saturating_sub(1);
-        let visual_col = cursor_col % vp;
-
-        if cursor_col + vp < line_len {
+        let segs = self.wrap_segments(cursor_line);
+        let seg = wrap_segment_index(&segs, cursor_col);
+        let visual_col = cursor_col - segs[seg].0;
+
+        if seg + 1 < segs.len() {
             // Advance within the same buffer line (to the next wrapped segment).
-            self.view_mut().cursor.col = cursor_col + vp;
+            let (start, end) = segs[seg + 1];
+            self.view_mut().cursor.col = (start + visual_col).min(end.saturating_sub(1));
         } else if cursor_line + 1 < total_lines {
-            // Move to the next buffer line, keeping the same visual column offset.
-            let next_len = self
-                .buffer()
-                .content
-                .line(cursor_line + 1)
-                .len_chars()
-                .saturating_sub(1);
+            // Move to the next buffer line's first segment, keeping the
+            // same visual column offset.
+            let (start, end) = self.wrap_segments(cursor_line + 1)[0];
             self.view_mut().cursor.line = cursor_line + 1;
-            self.view_mut().cursor.col = visual_col.min(next_len.saturating_sub(1));
+            self.view_mut().cursor.col = (start + visual_col).min(end.saturating_sub(1));
         }
         self.clamp_cursor_col();
         self.ensure_cursor_visible();
@@ -182,91 +224,70 @@ impl Engine {
     /// Move cursor up by one visual row (within the same wrapped line if
     /// possible, otherwise to the previous buffer line).  Used by `gk`.
     pub(crate) fn move_visual_up(&mut self) {
-        let vp = self.view().viewport_cols.max(1);
         let cursor_line = self.view().cursor.line;
         let cursor_col = self.view().cursor.col;
-        let visual_col = cursor_col % vp;
+        let segs = self.wrap_segments(cursor_line);
+        let seg = wrap_segment_index(&segs, cursor_col);
+        let visual_col = cursor_col - segs[seg].0;
 
-        if cursor_col >= vp {
+        if seg > 0 {
             // Move up within the same buffer line (to the previous wrapped segment).
-            self.view_mut().cursor.col = cursor_col - vp;
+            let (start, end) = segs[seg - 1];
+            self.view_mut().cursor.col = (start + visual_col).min(end.saturating_sub(1));
         } else if cursor_line > 0 {
             // Move to the previous buffer line's last visual segment.
-            let prev_len = self
-                .buffer()
-                .content
-                .line(cursor_line - 1)
-                .len_chars()
-                .saturating_sub(1);
-            let last_seg_start = (prev_len / vp) * vp;
-            let target_col = (last_seg_start + visual_col).min(prev_len.saturating_sub(1));
+            let prev = self.wrap_segments(cursor_line - 1);
+            let (start, end) = prev[prev.len() - 1];
             self.view_mut().cursor.line = cursor_line - 1;
-            self.view_mut().cursor.col = target_col;
+            self.view_mut().cursor.col = (start + visual_col).min(end.saturating_sub(1));
         }
         self.clamp_cursor_col();
         self.ensure_cursor_visible();
     }
 
+    /// The `(start, end)` char range of the screen line holding the cursor.
+    /// When wrap is off this is the whole line.
+    fn cursor_screen_segment(&self) -> (usize, usize) {
+        let segs = self.wrap_segments(self.view().cursor.line);
+        segs[wrap_segment_index(&segs, self.view().cursor.col)]
+    }
+
     /// Move cursor to the start of the current screen line (`g0` / `g<Home>`).
     /// When wrap is off, equivalent to `0`.
     pub(crate) fn move_screen_line_start(&mut self) {
-        if !self.settings.wrap {
-            self.view_mut().cursor.col = 0;
-        } else {
-            let vp = self.view().viewport_cols.max(1);
-            let col = self.view().cursor.col;
-            self.view_mut().cursor.col = (col / vp) * vp;
-        }
+        self.view_mut().cursor.col = self.cursor_screen_segment().0;
         self.ensure_cursor_visible();
     }
 
     /// Move cursor to the first non-blank character on the current screen line (`g^`).
     /// When wrap is off, equivalent to `^`.
     pub(crate) fn move_screen_line_first_non_blank(&mut self) {
+        let line = self.view().cursor.line;
         if !self.settings.wrap {
-            let line = self.view().cursor.line;
             let fnb = self.first_non_blank_col(line);
             self.view_mut().cursor.col = fnb;
-        } else {
-            let vp = self.view().viewport_cols.max(1);
-            let col = self.view().cursor.col;
-            let seg_start = (col / vp) * vp;
-            let line = self.view().cursor.line;
-            let line_start = self.buffer().line_to_char(line);
-            let line_len = self.buffer().line_len_chars(line);
-            let seg_end = (seg_start + vp).min(line_len);
-            let mut target = seg_start;
-            for i in seg_start..seg_end {
-                let ch = self.buffer().content.char(line_start + i);
-                if ch != ' ' && ch != '\t' && ch != '\n' && ch != '\r' {
-                    target = i;
-                    break;
-                }
+            self.ensure_cursor_visible();
+            return;
+        }
+        let (seg_start, seg_end) = self.cursor_screen_segment();
+        let line_start = self.buffer().line_to_char(line);
+        let mut target = seg_start;
+        for i in seg_start..seg_end {
+            let ch = self.buffer().content.char(line_start + i);
+            if ch != ' ' && ch != '\t' {
+                target = i;
+                break;
             }
-            self.view_mut().cursor.col = target;
         }
+        self.view_mut().cursor.col = target;
         self.ensure_cursor_visible();
     }
 
     /// Move cursor to the end of the current screen line (`g$` / `g<End>`).
     /// When wrap is off, equivalent to `$`.
     pub(crate) fn move_screen_line_end(&mut self) {
-        let line = self.view().cursor.line;
-        let line_len = self
-            .buffer()
-            .content
-            .line(line)
-            .len_chars()
-            .saturating_sub(1); // exclude newline
-        if !self.settings.wrap {
-            self.view_mut().cursor.col = line_len.saturating_sub(1);
-        } else {
-            let vp = self.view().viewport_cols.max(1);
-            let col = self.view().cursor.col;
-            let seg_start = (col / vp) * vp;
-            let seg_end = (seg_start + vp).min(line_len);
-            self.view_mut().cursor.col = seg_end.saturating_sub(1);
-        }
+        let (_, seg_end) = self.cursor_screen_segment();
+        self.view_mut().cursor.col = seg_end.saturating_sub(1);
         self.clamp_cursor_col();
         self.ensure_cursor_visible();
     }
@@ -376,21 +397,25 @@ impl Engine {
             self.message = "Search query is empty".to_string();
             return;
         }
-        let opts = self.project_search_options.clone();
-        match project_search::search_in_project(root, &query, &opts) {
-            Ok(results) => self.apply_search_results(results, &query),
+        let opts = self.with_explorer_ignore(self.project_search_options.clone());
+        let control = SearchControl::default();
+        match project_search::search_in_project_with(root, &query, &opts, &control) {
+            Ok(outcome) => self.apply_search_results(outcome, &query),
             Err(e) => {
                 self.project_search_results.clear();
                 self.project_search_selected = 0;
-                self.message = format!("Invalid regex: {}", e.0);
+                self.set_error_message(e.0);
             }
         }
     }
 
     /// Spawn a background thread to search `root` for `self.project_search_query`.
+    /// A search still running is cancelled first, so only the latest query's
+    /// results are ever shown.
     ///
     /// Call `poll_project_search` on each UI tick to collect results.
     pub fn start_project_search(&mut self, root: PathBuf) {
+        self.cancel_project_search();
         let query = self.project_search_query.clone();
         if query.is_empty() {
             self.project_search_results.clear();
@@ -399,20 +424,38 @@ impl Engine {
             return;
         }
         self.project_search_running = true;
+        self.project_search_progress = (0, 0);
         self.notify(
             NotificationKind::ProjectSearch,
             &format!("Searching for \"{query}\"…"),
         );
         self.message = format!("Searching for \"{}\"…", query);
-        let opts = self.project_search_options.clone();
+        let opts = self.with_explorer_ignore(self.project_search_options.clone());
+        let control = std::sync::Arc::new(SearchControl::default());
+        self.project_search_control = Some(control.clone());
         let (tx, rx) = std::sync::mpsc::channel();
         self.project_search_receiver = Some(rx);
         std::thread::spawn(move || {
-            let result = project_search::search_in_project(&root, &query, &opts);
+            let result = project_search::search_in_project_with(&root, &query, &opts, &control);
+            // Fails harmlessly when a newer search replaced the receiver.
             let _ = tx.send(result);
         });
     }
 
+    /// Stop the running search thread, if any; its results are discarded
+    /// and its notification removed.  Returns `true` if a search was running.
+    pub fn cancel_project_search(&mut self) -> bool {
+        let Some(control) = self.project_search_control.take() else {
+            return false;
+        };
+        control.cancel();
+        self.project_search_receiver = None;
+        self.project_search_running = false;
+        self.notifications
+            .retain(|n| n.done || n.kind != NotificationKind::ProjectSearch);
+        true
+    }
+
     /// Check whether the background search thread has finished and, if so, store results.
     ///
     /// Returns `true` when new results have just arrived (UI should redraw).
@@ -426,22 +469,48 @@ impl Engine {
         };
         let query = self.project_search_query.clone();
         self.project_search_receiver = None;
+        self.project_search_control = None;
         self.project_search_running = false;
         self.notify_done_by_kind(&NotificationKind::ProjectSearch, Some("Search complete"));
         match result {
-            Ok(results) => self.apply_search_results(results, &query),
+            Ok(outcome) => self.apply_search_results(outcome, &query),
             Err(e) => {
                 self.project_search_results.clear();
                 self.project_search_selected = 0;
-                self.message = format!("Invalid regex: {}", e.0);
+                self.set_error_message(e.0);
             }
         }
         true
     }
 
+    /// Show the running search's progress ("Searching… 1234 files, 56
+    /// matches") in the message line.  Returns `true` when the text changed.
+    pub fn tick_project_search_progress(&mut self) -> bool {
+        let Some(ref control) = self.project_search_control else {
+            return false;
+        };
+        let progress = control.progress();
+        if progress == self.project_search_progress {
+            return false;
+        }
+        self.project_search_progress = progress;
+        let (files, matches) = progress;
+        self.message = format!(
+            "Searching… {} file{}, {} match{}",
+            files,
+            if files == 1 { "" } else { "s" },
+            matches,
+            if matches == 1 { "" } else { "es" }
+        );
+        true
+    }
+
     /// Store search results and update the status message. Called by both sync and async paths.
-    pub(crate) fn apply_search_results(&mut self, results: Vec<ProjectMatch>, query: &str) {
-        let capped = results.len() >= 10_000;
+    pub(crate) fn apply_search_results(&mut self, outcome: SearchOutcome, query: &str) {
+        let SearchOutcome {
+            matches: results,
+            truncated,
+        } = outcome;
         if results.is_empty() {
             self.message = format!("No results for \"{}\"", query);
         } else {
@@ -458,9 +527,14 @@ impl Engine {
                 if results.len() == 1 { "" } else { "es" },
                 file_count,
                 if file_count == 1 { "" } else { "s" },
-                if capped { " (capped at 10000)" } else { "" }
+                if truncated {
+                    format!(" (stopped at {})", project_search::MAX_RESULTS)
+                } else {
+                    String::new()
+                }
             );
         }
+        self.project_search_included = vec![true; results.len()];
         self.project_search_results = results;
         self.project_search_selected = 0;
     }
@@ -480,6 +554,19 @@ impl Engine {
         self.project_search_options.use_regex = !self.project_search_options.use_regex;
     }
 
+    /// `opts` as handed to the file walker: its exclude globs plus the
+    /// `explorer_ignore` list, so files hidden in the explorer aren't
+    /// searched either.
+    pub(crate) fn with_explorer_ignore(&self, mut opts: SearchOptions) -> SearchOptions {
+        for glob in &self.settings.explorer_ignore {
+            if !opts.exclude.trim().is_empty() {
+                opts.exclude.push(',');
+            }
+            opts.exclude.push_str(glob);
+        }
+        opts
+    }
+
     /// Move the project search selection down by one, clamped to the last result.
     pub fn project_search_select_next(&mut self) {
         if !self.project_search_results.is_empty() {
@@ -493,6 +580,38 @@ impl Engine {
         self.project_search_selected = self.project_search_selected.saturating_sub(1);
     }
 
+    /// Copy the search results into the quickfix list (same file-grouped
+    /// order, selection kept) and open the quickfix panel, so `:cnext` /
+    /// `:cprev` step through the matches without the sidebar.
+    pub fn send_project_search_to_quickfix(&mut self) -> EngineAction {
+        if self.project_search_results.is_empty() {
+            self.message = "No search results".to_string();
+            return EngineAction::None;
+        }
+        // Search matches record a byte column; quickfix entries use chars.
+        self.quickfix_items = self
+            .project_search_results
+            .iter()
+            .map(|m| {
+                let col = m
+                    .line_text
+                    .get(..m.col)
+                    .map_or(0, |prefix| prefix.chars().count());
+                ProjectMatch { col, ..m.clone() }
+            })
+            .collect();
+        self.quickfix_selected = self.project_search_selected;
+        self.quickfix_open = true;
+        self.quickfix_has_focus = false;
+        let n = self.quickfix_items.len();
+        self.message = format!(
+            "{} match{} sent to quickfix",
+            n,
+            if n == 1 { "" } else { "es" }
+        );
+        EngineAction::None
+    }
+
     // =======================================================================
     // Project replace
     // =======================================================================
@@ -523,12 +642,12 @@ impl Engine {
             self.message = "Search query is empty".to_string();
             return;
         }
-        let opts = self.project_search_options.clone();
+        let opts = self.with_explorer_ignore(self.project_search_options.clone());
         let skip = self.dirty_buffer_paths();
         match project_search::replace_in_project(root, &query, &replacement, &opts, &skip) {
             Ok(rr) => self.apply_replace_result(rr),
             Err(e) => {
-                self.message = format!("Invalid regex: {}", e.0);
+                self.set_error_message(e.0);
             }
         }
     }
@@ -549,7 +668,7 @@ impl Engine {
             &format!("Replacing \"{query}\" → \"{replacement}\"…"),
         );
         self.message = format!("Replacing \"{}\" → \"{}\"…", query, replacement);
-        let opts = self.project_search_options.clone();
+        let opts = self.with_explorer_ignore(self.project_search_options.clone());
         let skip = self.dirty_buffer_paths();
         let (tx, rx) = std::sync::mpsc::channel();
         self.project_replace_receiver = Some(rx);
@@ -632,6 +751,196 @@ impl Engine {
         self.project_search_results.clear();
         self.project_search_selected = 0;
     }
+
+    // =======================================================================
+    // Replace preview (per-match selection)
+    // =======================================================================
+
+    /// Toggle the replace preview in the Search panel.
+    pub fn toggle_project_replace_preview(&mut self) {
+        self.project_replace_preview = !self.project_replace_preview;
+    }
+
+    /// Whether result `idx` takes part in "Replace Selected".
+    pub fn project_match_included(&self, idx: usize) -> bool {
+        self.project_search_included
+            .get(idx)
+            .copied()
+            .unwrap_or(true)
+    }
+
+    /// Check or uncheck result `idx` for "Replace Selected".
+    pub fn toggle_project_match_included(&mut self, idx: usize) {
+        let len = self.project_search_results.len();
+        if idx >= len {
+            return;
+        }
+        self.project_search_included.resize(len, true);
+        self.project_search_included[idx] = !self.project_search_included[idx];
+    }
+
+    /// The replaced text of each result in `range` (only the rows a panel
+    /// shows, so the regex is compiled once per redraw).  `None` when the
+    /// query doesn't compile.
+    pub fn project_replace_preview_lines(
+        &self,
+        range: std::ops::Range<usize>,
+    ) -> Option<Vec<String>> {
+        let opts = &self.project_search_options;
+        let re = project_search::build_search_regex(&self.project_search_query, opts).ok()?;
+        let end = range.end.min(self.project_search_results.len());
+        let start = range.start.min(end);
+        Some(
+            self.project_search_results[start..end]
+                .iter()
+                .map(|m| {
+                    project_search::replace_matches(
+                        &re,
+                        &m.line_text,
+                        &self.project_replace_text,
+                        opts.use_regex,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// "Replace Selected": replace the matches of every checked result.
+    /// Open buffers are edited through undo (one group per file, applied
+    /// bottom-to-top); other files are rewritten on disk.  Results whose
+    /// line changed since the search are left alone.
+    pub fn replace_selected_matches(&mut self) {
+        let query = self.project_search_query.clone();
+        if query.is_empty() {
+            self.message = "Search query is empty".to_string();
+            return;
+        }
+        let opts = self.project_search_options.clone();
+        let re = match project_search::build_search_regex(&query, &opts) {
+            Ok(re) => re,
+            Err(e) => {
+                self.set_error_message(e.0);
+                return;
+            }
+        };
+        let replacement = self.project_replace_text.clone();
+
+        // Results are sorted by file, so each file's rows are contiguous.
+        let mut groups: Vec<(PathBuf, Vec<usize>)> = Vec::new();
+        for (idx, m) in self.project_search_results.iter().enumerate() {
+            if !self.project_match_included(idx) {
+                continue;
+            }
+            match groups.last_mut() {
+                Some((file, idxs)) if *file == m.file => idxs.push(idx),
+                _ => groups.push((m.file.clone(), vec![idx])),
+            }
+        }
+        if groups.is_empty() {
+            self.message = "No matches selected".to_string();
+            return;
+        }
+
+        let mut changes = Vec::new();
+        let mut replaced: HashSet<usize> = HashSet::new();
+        let mut occurrences = 0;
+        let mut stale = 0;
+        for (file, idxs) in groups {
+            let Some(lines) = self.current_file_lines(&file) else {
+                stale += idxs.len();
+                continue;
+            };
+            let mut edits = Vec::new();
+            for idx in idxs {
+                let m = &self.project_search_results[idx];
+                let current = lines.get(m.line).map(String::as_str);
+                if current != Some(m.line_text.as_str()) {
+                    stale += 1;
+                    continue;
+                }
+                let new_text = project_search::replace_matches(
+                    &re,
+                    &m.line_text,
+                    &replacement,
+                    opts.use_regex,
+                );
+                if new_text == m.line_text {
+                    continue;
+                }
+                occurrences += re.find_iter(&m.line_text).count();
+                let end = lsp::char_to_utf16_offset(&m.line_text, m.line_text.chars().count());
+                edits.push(FormattingEdit {
+                    range: lsp::LspRange {
+                        start: lsp::LspPosition {
+                            line: m.line as u32,
+                            character: 0,
+                        },
+                        end: lsp::LspPosition {
+                            line: m.line as u32,
+                            character: end,
+                        },
+                    },
+                    new_text,
+                });
+                replaced.insert(idx);
+            }
+            if !edits.is_empty() {
+                changes.push(lsp::FileEdit { path: file, edits });
+            }
+        }
+        let (files, _, failed) = self.apply_workspace_edit(WorkspaceEdit { changes });
+
+        // Drop the rows that were replaced; unchecked and stale ones stay.
+        let failed: HashSet<&Path> = failed.iter().map(|p| p.as_path()).collect();
+        let mut kept = Vec::new();
+        let mut kept_included = Vec::new();
+        for (idx, m) in std::mem::take(&mut self.project_search_results)
+            .into_iter()
+            .enumerate()
+        {
+            if !replaced.contains(&idx) || failed.contains(m.file.as_path()) {
+                kept_included.push(self.project_match_included(idx));
+                kept.push(m);
+            }
+        }
+        self.project_search_results = kept;
+        self.project_search_included = kept_included;
+        self.project_search_selected = self
+            .project_search_selected
+            .min(self.project_search_results.len().saturating_sub(1));
+
+        let mut msg = format!(
+            "Replaced {} occurrence{} in {} file{}",
+            occurrences,
+            if occurrences == 1 { "" } else { "s" },
+            files,
+            if files == 1 { "" } else { "s" },
+        );
+        if stale > 0 {
+            msg.push_str(&format!(" ({stale} changed since the search, skipped)"));
+        }
+        if !failed.is_empty() {
+            msg.push_str(&format!(" ({} file(s) could not be written)", failed.len()));
+        }
+        self.message = msg;
+    }
+
+    /// The lines of `file` as the editor sees them: an open buffer's text
+    /// (unsaved edits included), else the file on disk.
+    fn current_file_lines(&self, file: &Path) -> Option<Vec<String>> {
+        let target = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let open = self.buffer_manager.list().into_iter().find_map(|id| {
+            let state = self.buffer_manager.get(id)?;
+            let path = state.file_path.as_deref()?;
+            (path.canonicalize().unwrap_or_else(|_| path.to_path_buf()) == target)
+                .then(|| state.buffer.to_string())
+        });
+        let text = match open {
+            Some(text) => text,
+            None => std::fs::read_to_string(file).ok()?,
+        };
+        Some(text.lines().map(str::to_string).collect())
+    }
 }
 
 // ─── Additional methods (extracted from mod.rs) ─────────────────────────
@@ -671,6 +980,52 @@ impl Engine {
         Some(chars[start..end].iter().collect())
     }
 
+    /// Show the occurrences of the identifier under the cursor once the
+    /// cursor has rested on it for a moment; clear them when it moves to a
+    /// different word, enters Visual mode, or the setting is off.  Returns
+    /// true when the highlight changed (caller should redraw).  Call from the
+    /// event loop idle path.
+    pub fn tick_word_highlight(&mut self) -> bool {
+        let enabled = self.settings.highlight_word_under_cursor
+            && matches!(self.mode, Mode::Normal | Mode::Insert);
+        let word = if enabled {
+            self.word_under_cursor()
+                .filter(|w| w.chars().count() >= WORD_HIGHLIGHT_MIN_LEN)
+        } else {
+            None
+        };
+        let Some(word) = word else {
+            self.word_highlight_anchor = None;
+            return self.word_highlight.take().is_some();
+        };
+        let buffer_id = self.active_buffer_id();
+        if self
+            .word_highlight
+            .as_ref()
+            .is_some_and(|(id, w)| *id == buffer_id && *w == word)
+        {
+            return false;
+        }
+        let pos = (
+            self.active_window_id(),
+            self.view().cursor.line,
+            self.view().cursor.col,
+        );
+        match self.word_highlight_anchor {
+            Some((anchor, since)) if anchor == pos => {
+                if since.elapsed() < WORD_HIGHLIGHT_DELAY {
+                    return false;
+                }
+                self.word_highlight = Some((buffer_id, word));
+                true
+            }
+            _ => {
+                self.word_highlight_anchor = Some((pos, std::time::Instant::now()));
+                self.word_highlight.take().is_some()
+            }
+        }
+    }
+
     /// Search forward (*) or backward (#) for the word under cursor with word boundaries.
     pub(crate) fn search_word_under_cursor(&mut self, forward: bool) {
         let word = match self.word_under_cursor() {
@@ -688,12 +1043,13 @@ impl Engine {
             SearchDirection::Backward
         };
         self.search_word_bounded = true;
+        self.search_offset = SearchOffset::None;
 
         // Build word-boundary matches manually
         self.build_word_bounded_matches();
 
         if self.search_matches.is_empty() {
-            self.message = format!("Pattern not found: {}", word);
+            self.set_error_message(format!("E486: Pattern not found: {}", word));
             return;
         }
 
@@ -706,16 +1062,25 @@ impl Engine {
     }
 
     /// Like run_search but only keeps matches that are whole words.
+    /// Honours `ignorecase` but, as in Vim, not `smartcase`.
     pub(crate) fn build_word_bounded_matches(&mut self) {
         self.search_matches.clear();
         self.search_index = None;
+        self.search_highlight_cleared = false;
 
         if self.search_query.is_empty() {
             return;
         }
 
-        let text = self.buffer().to_string();
-        let query = self.search_query.clone();
+        let (mut query, case_insensitive) = self.search_pattern_case(&self.search_query, false);
+        let mut text = self.buffer().to_string();
+        if case_insensitive {
+            text = text.to_lowercase();
+            query = query.to_lowercase();
+        }
+        if query.is_empty() {
+            return;
+        }
         let mut byte_pos = 0;
 
         while let Some(found) = text[byte_pos..].find(&query) {
@@ -842,6 +1207,7 @@ impl Engine {
         self.search_word_bounded = false;
         self.search_matches.clear();
         self.search_index = None;
+        self.search_highlight_cleared = false;
 
         if self.search_query.is_empty() {
             return;
@@ -974,14 +1340,27 @@ impl Engine {
             return;
         }
 
-        let replacement = self.find_replace_replacement.clone();
-        let query = self.find_replace_query.clone();
-
-        let flags = if self.find_replace_options.case_sensitive {
-            "g"
+        // The overlay's query is a plain string or a `regex` pattern (not Vim
+        // syntax) and its replacement is inserted verbatim.
+        let query = if self.find_replace_options.use_regex {
+            self.find_replace_query.clone()
         } else {
-            "gi"
+            regex::escape(&self.find_replace_query)
         };
+        let re = match regex::RegexBuilder::new(&query)
+            .case_insensitive(!self.find_replace_options.case_sensitive)
+            .build()
+        {
+            Ok(re) => re,
+            Err(e) => {
+                self.message = format!("Replace error: {}", e);
+                return;
+            }
+        };
+        let replacement = self
+            .find_replace_replacement
+            .replace('\\', "\\\\")
+            .replace('&', "\\&");
 
         // Determine line range: selection range or entire buffer
         let (start_line, end_line) = if self.find_replace_options.in_selection {
@@ -999,14 +1378,9 @@ impl Engine {
             (0, self.buffer().len_lines().saturating_sub(1))
         };
 
-        match self.replace_in_range(Some((start_line, end_line)), &query, &replacement, flags) {
-            Ok(count) => {
-                self.message = format!("{} replacement(s) made", count);
-            }
-            Err(e) => {
-                self.message = format!("Replace error: {}", e);
-            }
-        }
+        let count =
+            self.replace_regex_in_range(Some((start_line, end_line)), &re, &replacement, true);
+        self.message = format!("{} replacement(s) made", count);
 
         // Re-run search to update highlights
         self.run_find_replace_search();
@@ -0,0 +1,459 @@
+use super::*;
+
+use std::process::{Command, Output, Stdio};
+
+use crate::core::errorformat::{self, BuildMessageKind};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+/// Where a finished shell command's output goes.  `tick` is the buffer's
+/// `changedtick` when the command started; the output is discarded if the
+/// buffer was edited since, as the line numbers may no longer match.
+pub(crate) enum ShellTarget {
+    /// `:!cmd` — the message line, or a scratch split for longer output.
+    Show,
+    /// `:r !cmd` — inserted below `line` of `buffer`.
+    Read {
+        buffer: BufferId,
+        line: usize,
+        tick: u64,
+    },
+    /// `:{range}!cmd` — replaces lines `start..=end` of `buffer`.
+    Filter {
+        buffer: BufferId,
+        start: usize,
+        end: usize,
+        tick: u64,
+    },
+}
+
+/// A shell command running on a background thread.
+pub(crate) struct ShellJob {
+    cmd: String,
+    target: ShellTarget,
+    rx: Receiver<std::io::Result<Output>>,
+}
+
+/// Progress of a `:make` build, streamed from its reader thread.
+enum MakeEvent {
+    Line(String),
+    /// The build exited (`None` when killed by a signal).
+    Exited(Option<i32>),
+    Failed(String),
+}
+
+/// The running `:make` build.
+pub(crate) struct MakeJob {
+    cmd: String,
+    cwd: PathBuf,
+    rx: Receiver<MakeEvent>,
+    /// Combined stdout/stderr received so far.
+    output: Vec<String>,
+}
+
+impl Engine {
+    // ── Shell commands (:!, :r !, :{range}!) ───────────────────────────────
+
+    /// Run `cmd` with `sh -c` in the working directory on a background
+    /// thread, feeding it `stdin`.  [`Engine::poll_shell_commands`] applies
+    /// the output to `target` once it exits.
+    pub(crate) fn spawn_shell_command(
+        &mut self,
+        cmd: &str,
+        stdin: Option<String>,
+        target: ShellTarget,
+    ) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let shell_cmd = cmd.to_string();
+        let cwd = self.cwd.clone();
+        std::thread::spawn(move || {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(&shell_cmd)
+                .current_dir(&cwd)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            // Never let the child read from the editor's own terminal.
+            command.stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            });
+            let result = command.spawn().and_then(|mut child| {
+                if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+                    // Write from a separate thread so a command that produces
+                    // output before draining stdin can't deadlock us.
+                    std::thread::spawn(move || {
+                        use std::io::Write;
+                        let _ = pipe.write_all(input.as_bytes());
+                    });
+                }
+                child.wait_with_output()
+            });
+            let _ = tx.send(result);
+        });
+        self.shell_jobs.push(ShellJob {
+            cmd: cmd.to_string(),
+            target,
+            rx,
+        });
+        self.message = format!(":!{cmd}");
+    }
+
+    /// True while a `:!` command or `:make` build is still running.
+    pub fn shell_commands_pending(&self) -> bool {
+        !self.shell_jobs.is_empty() || self.make_job.is_some()
+    }
+
+    /// Apply the output of finished shell commands and stream `:make`
+    /// progress.  Returns `true` if anything changed (caller should redraw).
+    pub fn poll_shell_commands(&mut self) -> bool {
+        if !self.shell_commands_pending() {
+            return false;
+        }
+        let make_changed = self.poll_make();
+        if self.shell_jobs.is_empty() {
+            return make_changed;
+        }
+        let mut finished = Vec::new();
+        let mut i = 0;
+        while i < self.shell_jobs.len() {
+            match self.shell_jobs[i].rx.try_recv() {
+                Ok(result) => finished.push((self.shell_jobs.remove(i), result)),
+                Err(TryRecvError::Disconnected) => {
+                    let job = self.shell_jobs.remove(i);
+                    let err = std::io::Error::other("shell thread exited");
+                    finished.push((job, Err(err)));
+                }
+                Err(TryRecvError::Empty) => i += 1,
+            }
+        }
+        if finished.is_empty() {
+            return make_changed;
+        }
+        for (job, result) in finished {
+            self.finish_shell_job(job, result);
+        }
+        true
+    }
+
+    fn finish_shell_job(&mut self, job: ShellJob, result: std::io::Result<Output>) {
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                self.message = format!("Shell error: {e}");
+                return;
+            }
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let failed = !output.status.success();
+        // `sh` reports 127 for unknown commands; signals have no code.
+        let status_note = match output.status.code() {
+            Some(code) if failed => format!("shell returned {code}"),
+            None => "shell terminated by signal".to_string(),
+            _ => String::new(),
+        };
+        match job.target {
+            ShellTarget::Show => {
+                let combined = format!("{stdout}{stderr}");
+                self.show_shell_output(&job.cmd, &combined, &status_note);
+            }
+            ShellTarget::Read { buffer, line, tick } => {
+                if !self.shell_target_is_current(buffer, tick) {
+                    return;
+                }
+                if stdout.is_empty() {
+                    self.message = shell_message(stderr.lines().next(), &status_note);
+                    return;
+                }
+                let count = self.insert_lines_below(line, &stdout);
+                self.message =
+                    shell_message(Some(format!("{count} line(s) read").as_str()), &status_note);
+            }
+            ShellTarget::Filter {
+                buffer,
+                start,
+                end,
+                tick,
+            } => {
+                if !self.shell_target_is_current(buffer, tick) {
+                    return;
+                }
+                if failed {
+                    // Keep the text rather than replacing it with an error.
+                    self.message = shell_message(stderr.lines().next(), &status_note);
+                    return;
+                }
+                let count = self.replace_lines(start, end, &stdout);
+                self.message = format!("{count} lines filtered");
+            }
+        }
+    }
+
+    /// Short output goes to the message line; anything longer opens in a
+    /// read-only scratch split named after the command.
+    fn show_shell_output(&mut self, cmd: &str, text: &str, status_note: &str) {
+        let text = text.trim_end_matches('\n');
+        if !text.contains('\n') {
+            let first = if text.is_empty() { "(no output)" } else { text };
+            self.message = shell_message(Some(first), status_note);
+            return;
+        }
+        self.split_window(SplitDirection::Horizontal, None);
+        let buf_id = self.buffer_manager.create();
+        if let Some(state) = self.buffer_manager.get_mut(buf_id) {
+            state.buffer.content = ropey::Rope::from_str(text);
+            state.read_only = true;
+            state.scratch_name = Some(format!("!{cmd}"));
+        }
+        self.switch_window_buffer(buf_id);
+        let lines = text.lines().count();
+        self.message = shell_message(Some(format!("{lines} lines").as_str()), status_note);
+    }
+
+    /// The buffer a `:r !`/filter command was started in must still be
+    /// current and unchanged; its output is dropped otherwise.
+    fn shell_target_is_current(&mut self, buffer: BufferId, tick: u64) -> bool {
+        if self.active_buffer_id() != buffer {
+            self.message = "Shell command finished in another buffer; output discarded".to_string();
+            return false;
+        }
+        if self.buffer().changedtick != tick {
+            self.message =
+                "Buffer changed while the shell command ran; output discarded".to_string();
+            return false;
+        }
+        true
+    }
+
+    /// Insert `text` as whole lines below `line` (clamped to the buffer) as
+    /// one undo step.  Returns the number of lines inserted.
+    fn insert_lines_below(&mut self, line: usize, text: &str) -> usize {
+        let mut text = text.to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let num_lines = self.buffer().len_lines();
+        let line = line.min(num_lines.saturating_sub(1));
+        self.start_undo_group();
+        if line + 1 < num_lines {
+            let pos = self.buffer().line_to_char(line + 1);
+            self.insert_with_undo(pos, &text);
+        } else {
+            // Last line: start a new one, without leaving a trailing newline.
+            let end = self.buffer().len_chars();
+            text.pop();
+            self.insert_with_undo(end, &format!("\n{text}"));
+        }
+        self.finish_undo_group();
+        let view = self.view_mut();
+        view.cursor.line = line + 1;
+        view.cursor.col = 0;
+        text.lines().count()
+    }
+
+    /// Replace lines `start..=end` (clamped) with `text` as one undo step.
+    /// Returns the number of lines written.
+    fn replace_lines(&mut self, start: usize, end: usize, text: &str) -> usize {
+        let total_lines = self.buffer().len_lines();
+        let last = total_lines.saturating_sub(1);
+        let (start, end) = (start.min(last), end.min(last));
+        let range_start = self.buffer().line_to_char(start);
+        let mut text = text.to_string();
+        let range_end = if end + 1 < total_lines {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            self.buffer().line_to_char(end + 1)
+        } else {
+            // Range reaches the end of the buffer: keep its trailing newline
+            // (or lack of one) as it was.
+            let len = self.buffer().len_chars();
+            let had_newline = len > 0 && self.buffer().content.char(len - 1) == '\n';
+            if !had_newline && text.ends_with('\n') {
+                text.pop();
+            }
+            len
+        };
+        self.start_undo_group();
+        self.delete_with_undo(range_start, range_end);
+        self.insert_with_undo(range_start, &text);
+        self.finish_undo_group();
+        let last = self.buffer().len_lines().saturating_sub(1);
+        let view = self.view_mut();
+        view.cursor.line = start.min(last);
+        view.cursor.col = 0;
+        text.lines().count()
+    }
+}
+
+impl Engine {
+    // ── :make ──────────────────────────────────────────────────────────────
+
+    /// `:make [args]` — run `makeprg` with `args` appended on a background
+    /// thread.  Output streams into the message line; when the build exits
+    /// its errors and warnings fill the quickfix list.
+    pub(crate) fn start_make(&mut self, args: &str) -> EngineAction {
+        if self.make_job.is_some() {
+            self.message = "A build is already running".to_string();
+            return EngineAction::Error;
+        }
+        let makeprg = self.settings.makeprg.trim();
+        if makeprg.is_empty() {
+            self.message = "makeprg is empty".to_string();
+            return EngineAction::Error;
+        }
+        let cmd = if args.is_empty() {
+            makeprg.to_string()
+        } else {
+            format!("{makeprg} {args}")
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let cwd = self.cwd.clone();
+        let shell_cmd = cmd.clone();
+        let build_cwd = cwd.clone();
+        std::thread::spawn(move || {
+            use std::io::BufRead;
+            // Group the command so stderr of every part lands in the pipe.
+            let child = Command::new("sh")
+                .arg("-c")
+                .arg(format!("{{ {shell_cmd}\n}} 2>&1"))
+                .current_dir(&build_cwd)
+                .env("CARGO_TERM_COLOR", "never")
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    let _ = tx.send(MakeEvent::Failed(e.to_string()));
+                    return;
+                }
+            };
+            if let Some(stdout) = child.stdout.take() {
+                for line in std::io::BufReader::new(stdout).split(b'\n') {
+                    let Ok(line) = line else { break };
+                    let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                    if tx.send(MakeEvent::Line(line)).is_err() {
+                        return;
+                    }
+                }
+            }
+            let event = match child.wait() {
+                Ok(status) => MakeEvent::Exited(status.code()),
+                Err(e) => MakeEvent::Failed(e.to_string()),
+            };
+            let _ = tx.send(event);
+        });
+        self.message = format!(":make: {cmd}");
+        self.make_job = Some(MakeJob {
+            cmd,
+            cwd,
+            rx,
+            output: Vec::new(),
+        });
+        EngineAction::None
+    }
+
+    /// Drain `:make` progress; on exit, parse the output into the quickfix
+    /// list.  Returns `true` if anything arrived.
+    fn poll_make(&mut self) -> bool {
+        let Some(job) = self.make_job.as_mut() else {
+            return false;
+        };
+        let mut changed = false;
+        let mut finished = None;
+        loop {
+            match job.rx.try_recv() {
+                Ok(MakeEvent::Line(line)) => {
+                    if !line.trim().is_empty() {
+                        self.message = format!("make: {}", line.trim());
+                    }
+                    job.output.push(line);
+                    changed = true;
+                }
+                Ok(MakeEvent::Exited(code)) => {
+                    finished = Some(Ok(code));
+                    break;
+                }
+                Ok(MakeEvent::Failed(e)) => {
+                    finished = Some(Err(e));
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = Some(Err("build thread exited".to_string()));
+                    break;
+                }
+            }
+        }
+        let Some(result) = finished else {
+            return changed;
+        };
+        let job = self.make_job.take().expect("make job");
+        match result {
+            Ok(code) => self.finish_make(job, code),
+            Err(e) => self.message = format!("make: {}: {e}", job.cmd),
+        }
+        true
+    }
+
+    fn finish_make(&mut self, job: MakeJob, code: Option<i32>) {
+        let output = job.output.join("\n");
+        let messages =
+            errorformat::parse_build_output(&output, &self.settings.errorformat, &job.cwd);
+        let errors = messages
+            .iter()
+            .filter(|m| m.kind == BuildMessageKind::Error)
+            .count();
+        let warnings = messages.len() - errors;
+        let status = match code {
+            Some(0) => "Build succeeded".to_string(),
+            Some(code) => format!("Build failed (exit {code})"),
+            None => "Build terminated by signal".to_string(),
+        };
+        if messages.is_empty() {
+            // A clean build leaves nothing to jump to: drop the previous
+            // build's entries and surface the last line of output instead.
+            self.quickfix_items.clear();
+            self.quickfix_selected = 0;
+            self.close_quickfix();
+            let last = job.output.iter().rev().find(|l| !l.trim().is_empty());
+            self.message = match (code, last) {
+                (Some(0), _) | (_, None) => status,
+                (_, Some(last)) => format!("{status}: {}", last.trim()),
+            };
+            return;
+        }
+        self.quickfix_items = messages
+            .into_iter()
+            .map(|m| ProjectMatch {
+                file: m.file,
+                line: m.line,
+                col: m.col,
+                line_text: format!("[{}] {}", m.kind.symbol(), m.message),
+            })
+            .collect();
+        self.quickfix_selected = 0;
+        self.quickfix_open = true;
+        self.quickfix_has_focus = false;
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        self.message = format!(
+            "{status}: {errors} error{}, {warnings} warning{}",
+            plural(errors),
+            plural(warnings)
+        );
+    }
+}
+
+/// Join a message with the exit-status note of a failed command.
+fn shell_message(text: Option<&str>, status_note: &str) -> String {
+    match (text, status_note.is_empty()) {
+        (Some(text), true) => text.to_string(),
+        (Some(text), false) => format!("{text} ({status_note})"),
+        (None, false) => status_note.to_string(),
+        (None, true) => String::new(),
+    }
+}
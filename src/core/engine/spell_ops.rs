@@ -9,6 +9,9 @@ impl Engine {
     pub fn ensure_spell_checker(&mut self) {
         if self.spell_checker.is_none() && self.settings.spell {
             self.spell_checker = spell::SpellChecker::new();
+            if let Some(checker) = self.spell_checker.as_mut() {
+                checker.load_word_lists(&self.settings.spelllang);
+            }
         }
     }
 
@@ -208,11 +208,11 @@ impl Engine {
         }
     }
 
-    /// Drain PTY output from all panes and update VT100 screens.
+    /// Drain PTY output from all panes (and `:terminal` windows) and update VT100 screens.
     /// Returns true if a redraw is needed.
     /// Exited panes are automatically removed; closes the panel when the last pane exits.
     pub fn poll_terminal(&mut self) -> bool {
-        let mut got_data = false;
+        let mut got_data = self.poll_terminal_windows();
         for pane in &mut self.terminal_panes {
             got_data |= pane.poll();
         }
@@ -488,4 +488,198 @@ impl Engine {
             self.terminal_find_selected = 0;
         }
     }
+
+    // ── Terminal windows (`:terminal`) ─────────────────────────────────────
+
+    /// `:terminal` — open a shell in a new horizontal split and start typing
+    /// into it.  The window shows an ordinary read-only buffer mirroring the
+    /// shell's scrollback and screen, so Normal-mode motions, search and
+    /// yank work on the output after `Ctrl-\ Ctrl-N`.
+    pub fn open_terminal_window(&mut self) {
+        let (cols, rows) = {
+            let view = self.view();
+            (
+                view.viewport_cols.clamp(20, u16::MAX as usize) as u16,
+                (view.viewport_lines / 2).clamp(4, u16::MAX as usize) as u16,
+            )
+        };
+        let shell = default_shell();
+        let history_cap = self.settings.terminal_scrollback_lines;
+        let pane = match TerminalPane::new(cols, rows, &shell, &self.cwd, history_cap) {
+            Ok(pane) => pane,
+            Err(e) => {
+                self.message = format!("terminal: failed to open PTY: {e}");
+                return;
+            }
+        };
+        self.split_window(SplitDirection::Horizontal, None);
+        let buf_id = self.buffer_manager.create();
+        if let Some(state) = self.buffer_manager.get_mut(buf_id) {
+            state.read_only = true;
+            state.scratch_name = Some(format!("term://{shell}"));
+        }
+        self.switch_window_buffer(buf_id);
+        self.terminal_buffers.insert(buf_id, pane);
+        self.mode = Mode::Normal;
+        self.terminal_insert = true;
+        self.terminal_pending_escape = false;
+    }
+
+    /// True when the active window shows a `:terminal` whose shell is running.
+    fn active_terminal_window_running(&self) -> bool {
+        self.terminal_buffers
+            .get(&self.active_buffer_id())
+            .is_some_and(|pane| !pane.exited)
+    }
+
+    /// `i`/`a`/`I`/`A` in a terminal window's Normal mode: go back to typing
+    /// into the shell.  Returns true if the key was consumed.
+    pub(crate) fn terminal_window_enter_insert(&mut self, unicode: Option<char>) -> bool {
+        if !matches!(unicode, Some('i' | 'a' | 'I' | 'A')) || !self.active_terminal_window_running()
+        {
+            return false;
+        }
+        self.terminal_insert = true;
+        self.terminal_pending_escape = false;
+        let id = self.active_buffer_id();
+        if let Some((_, line, col)) = self.terminal_buffers.get(&id).map(|p| p.text_snapshot()) {
+            self.place_terminal_cursor(id, line, col);
+        }
+        true
+    }
+
+    /// Handle a key in terminal insert mode: forward it to the shell, except
+    /// for `Ctrl-\ Ctrl-N`, which returns to Normal mode.  Returns false (and
+    /// leaves terminal insert mode) when the active window is not a running
+    /// terminal, so the key is handled normally.
+    pub(crate) fn handle_terminal_window_key(
+        &mut self,
+        key_name: &str,
+        unicode: Option<char>,
+        ctrl: bool,
+    ) -> bool {
+        if !self.active_terminal_window_running() {
+            self.terminal_insert = false;
+            self.terminal_pending_escape = false;
+            return false;
+        }
+        let buf_id = self.active_buffer_id();
+        let mut bytes = Vec::new();
+        if std::mem::take(&mut self.terminal_pending_escape) {
+            if ctrl && key_name.eq_ignore_ascii_case("n") {
+                self.terminal_insert = false;
+                self.clamp_cursor_col();
+                return true;
+            }
+            // Not an escape after all: the shell gets the Ctrl-\ too.
+            bytes.push(0x1c);
+        } else if ctrl && key_name == "backslash" {
+            self.terminal_pending_escape = true;
+            return true;
+        }
+        bytes.extend(key_to_pty_bytes(key_name, unicode, ctrl));
+        if let Some(pane) = self.terminal_buffers.get_mut(&buf_id) {
+            if !bytes.is_empty() {
+                pane.write_input(&bytes);
+            }
+        }
+        true
+    }
+
+    /// Kill the shells of `:terminal` buffers no window shows any more, and
+    /// delete those buffers.
+    pub(crate) fn close_hidden_terminals(&mut self) {
+        let hidden: Vec<BufferId> = self
+            .terminal_buffers
+            .keys()
+            .copied()
+            .filter(|id| !self.windows.values().any(|w| w.buffer_id == *id))
+            .collect();
+        for id in hidden {
+            if let Some(mut pane) = self.terminal_buffers.remove(&id) {
+                pane.kill();
+            }
+            let _ = self.buffer_manager.delete(id, true);
+        }
+    }
+
+    /// Drain output of every `:terminal` shell into its buffer and keep each
+    /// PTY sized to the window showing it.  Returns true if a redraw is
+    /// needed.  Terminals whose window was closed are killed.
+    pub fn poll_terminal_windows(&mut self) -> bool {
+        if self.terminal_buffers.is_empty() {
+            return false;
+        }
+        self.close_hidden_terminals();
+
+        let mut changed = false;
+        let ids: Vec<BufferId> = self.terminal_buffers.keys().copied().collect();
+        for id in ids {
+            let size = self
+                .windows
+                .values()
+                .filter(|w| w.buffer_id == id)
+                .map(|w| (w.view.viewport_cols, w.view.viewport_lines))
+                .filter(|&(cols, lines)| cols > 0 && lines > 0)
+                .max();
+            let Some(pane) = self.terminal_buffers.get_mut(&id) else {
+                continue;
+            };
+            if let Some((cols, lines)) = size {
+                let cols = cols.min(u16::MAX as usize) as u16;
+                let lines = lines.min(u16::MAX as usize) as u16;
+                if (cols, lines) != (pane.cols, pane.rows) {
+                    pane.resize(cols, lines);
+                }
+            }
+            let was_exited = pane.exited;
+            if !pane.poll() {
+                continue;
+            }
+            let just_exited = pane.exited && !was_exited;
+            let Some(state) = self.buffer_manager.get_mut(id) else {
+                continue;
+            };
+            let text = &mut state.buffer.content;
+            let (line, col) = pane.update_text(text);
+            if pane.exited {
+                text.insert(text.len_chars(), "\n[Process exited]");
+            }
+            state.buffer.changedtick += 1;
+            if just_exited && self.active_buffer_id() == id {
+                self.terminal_insert = false;
+                self.terminal_pending_escape = false;
+            }
+            self.place_terminal_cursor(id, line, col);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Place cursors in windows showing terminal buffer `id`: on the shell's
+    /// cursor (`line`, `col`) in the active window while typing, otherwise
+    /// just kept inside the (possibly shrunk) text.
+    fn place_terminal_cursor(&mut self, id: BufferId, line: usize, col: usize) {
+        let Some(state) = self.buffer_manager.get(id) else {
+            return;
+        };
+        let last_line = state.buffer.content.len_lines().saturating_sub(1);
+        let active = self.active_window_id();
+        let follow = self.terminal_insert && self.active_buffer_id() == id;
+        for (wid, window) in self.windows.iter_mut() {
+            if window.buffer_id != id {
+                continue;
+            }
+            let cursor = &mut window.view.cursor;
+            if follow && *wid == active {
+                cursor.line = line.min(last_line);
+                cursor.col = col;
+            } else {
+                cursor.line = cursor.line.min(last_line);
+            }
+        }
+        if follow {
+            self.ensure_cursor_visible();
+        }
+    }
 }
@@ -473,6 +473,49 @@ fn type_command_action(engine: &mut Engine, cmd: &str) -> EngineAction {
     engine.handle_key("Return", None, false)
 }
 
+#[test]
+fn test_command_history_up_down_filters_by_prefix() {
+    let mut engine = Engine::new();
+    engine.history.command_history.clear();
+    engine.history.add_command("set wrap");
+    engine.history.add_command("write");
+    engine.history.add_command("set list");
+
+    press_char(&mut engine, ':');
+    press_char(&mut engine, 's');
+    press_special(&mut engine, "Up");
+    assert_eq!(engine.command_buffer, "set list");
+    press_special(&mut engine, "Up");
+    assert_eq!(engine.command_buffer, "set wrap");
+    // No older entry starts with "s": stay put.
+    press_special(&mut engine, "Up");
+    assert_eq!(engine.command_buffer, "set wrap");
+    press_special(&mut engine, "Down");
+    assert_eq!(engine.command_buffer, "set list");
+    // Past the newest match: the typed text comes back.
+    press_special(&mut engine, "Down");
+    assert_eq!(engine.command_buffer, "s");
+}
+
+#[test]
+fn test_search_history_up_recalls_and_previews() {
+    let mut engine = engine_with_text("alpha beta gamma\n");
+    engine.history.search_history.clear();
+    engine.history.add_search("gamma");
+    engine.history.add_search("beta");
+    engine.settings.incremental_search = true;
+
+    press_char(&mut engine, '/');
+    press_special(&mut engine, "Up");
+    assert_eq!(engine.command_buffer, "beta");
+    press_special(&mut engine, "Up");
+    assert_eq!(engine.command_buffer, "gamma");
+    assert_eq!(engine.view().cursor.col, 11);
+    press_special(&mut engine, "Down");
+    press_special(&mut engine, "Down");
+    assert_eq!(engine.command_buffer, "");
+}
+
 #[test]
 fn test_history_search_basic() {
     let mut engine = Engine::new();
@@ -670,7 +713,52 @@ fn test_search_not_found() {
     press_special(&mut engine, "Return");
 
     assert!(engine.search_matches.is_empty());
-    assert!(engine.message.contains("Pattern not found"));
+    assert!(engine.message.starts_with("E486: Pattern not found"));
+    assert!(engine.message_is_error());
+
+    // A message assigned directly is plain, whatever its text.
+    engine.message = "E999: not from set_error_message".to_string();
+    assert!(!engine.message_is_error());
+}
+
+#[test]
+fn test_search_wrapscan_messages() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "foo\nbar\nfoo");
+
+    press_char(&mut engine, '/');
+    for ch in "foo".chars() {
+        engine.handle_key(&ch.to_string(), Some(ch), false);
+    }
+    press_special(&mut engine, "Return");
+    assert_eq!(engine.search_matches.len(), 2);
+    assert!(!engine.message_is_error());
+    engine.view_mut().cursor = Cursor { line: 2, col: 0 };
+
+    // wrapscan (default): n wraps back to the top with a warning.
+    press_char(&mut engine, 'n');
+    assert_eq!(engine.view().cursor.line, 0);
+    assert_eq!(engine.message, "search hit BOTTOM, continuing at TOP");
+    assert!(engine.message_is_error());
+    press_char(&mut engine, 'N');
+    assert_eq!(engine.view().cursor.line, 2);
+    assert_eq!(engine.message, "search hit TOP, continuing at BOTTOM");
+
+    // nowrapscan: n stops at the last match.
+    engine.settings.wrapscan = false;
+    press_char(&mut engine, 'n');
+    assert_eq!(engine.view().cursor.line, 2);
+    assert!(engine
+        .message
+        .contains("search hit BOTTOM without match for: foo"));
+    assert!(engine.message_is_error());
+    press_char(&mut engine, 'N');
+    assert_eq!(engine.view().cursor.line, 0);
+    press_char(&mut engine, 'N');
+    assert_eq!(engine.view().cursor.line, 0);
+    assert!(engine
+        .message
+        .contains("search hit TOP without match for: foo"));
 }
 
 #[test]
@@ -794,6 +882,93 @@ fn test_incremental_search_escape_restores_cursor() {
     assert_eq!(engine.view().cursor.col, 6);
 }
 
+#[test]
+fn test_incremental_search_escape_restores_scroll() {
+    let mut engine = Engine::new();
+    let text: String = (0..100).map(|i| format!("line {i}\n")).collect();
+    engine.buffer_mut().insert(0, &text);
+    let end = engine.buffer().len_chars();
+    engine.buffer_mut().insert(end, "needle\n");
+    engine.update_syntax();
+    engine.view_mut().viewport_lines = 20;
+
+    press_char(&mut engine, '/');
+    for ch in "needle".chars() {
+        press_char(&mut engine, ch);
+    }
+    // The preview scrolls the match into view.
+    assert_eq!(engine.view().cursor.line, 100);
+    assert!(engine.view().scroll_top > 0);
+
+    press_special(&mut engine, "Escape");
+    assert_eq!(engine.view().cursor.line, 0);
+    assert_eq!(engine.view().scroll_top, 0);
+}
+
+#[test]
+fn test_parse_search_offset() {
+    assert_eq!(
+        parse_search_offset("foo", '/'),
+        ("foo".to_string(), SearchOffset::None)
+    );
+    assert_eq!(
+        parse_search_offset("foo/e", '/'),
+        ("foo".to_string(), SearchOffset::End(0))
+    );
+    assert_eq!(
+        parse_search_offset("foo/e-1", '/'),
+        ("foo".to_string(), SearchOffset::End(-1))
+    );
+    assert_eq!(
+        parse_search_offset("foo/b+1", '/'),
+        ("foo".to_string(), SearchOffset::Start(1))
+    );
+    assert_eq!(
+        parse_search_offset("foo/+2", '/'),
+        ("foo".to_string(), SearchOffset::Lines(2))
+    );
+    assert_eq!(
+        parse_search_offset("foo?-", '?'),
+        ("foo".to_string(), SearchOffset::Lines(-1))
+    );
+    assert_eq!(
+        parse_search_offset("a\\/b/s", '/'),
+        ("a/b".to_string(), SearchOffset::Start(0))
+    );
+}
+
+#[test]
+fn test_search_offset_end_and_start() {
+    let mut engine = engine_with_text("one foobar two foobar\n");
+    engine.feed_keys("/foo/e<CR>");
+    assert_eq!(engine.view().cursor.col, 6);
+    // `n` reapplies the offset from the next match.
+    engine.feed_keys("n");
+    assert_eq!(engine.view().cursor.col, 17);
+
+    engine.view_mut().cursor.col = 0;
+    engine.feed_keys("/bar/b+1<CR>");
+    assert_eq!(engine.view().cursor.col, 8);
+    engine.feed_keys("n");
+    assert_eq!(engine.view().cursor.col, 19);
+    engine.feed_keys("N");
+    assert_eq!(engine.view().cursor.col, 8);
+}
+
+#[test]
+fn test_search_offset_lines() {
+    let mut engine = engine_with_text("a\nfoo\nb\nc\nfoo\nd\n");
+    engine.feed_keys("/foo/+2<CR>");
+    assert_eq!(engine.view().cursor.line, 3);
+    assert_eq!(engine.view().cursor.col, 0);
+    engine.feed_keys("n");
+    assert_eq!(engine.view().cursor.line, 5);
+    // Line offsets are clamped to the buffer.
+    engine.view_mut().cursor.line = 0;
+    engine.feed_keys("/d/+9<CR>");
+    assert_eq!(engine.view().cursor.line, 5);
+}
+
 #[test]
 fn test_incremental_search_backspace() {
     let mut engine = Engine::new();
@@ -1647,6 +1822,65 @@ fn test_undo_at_empty_stack() {
     assert!(engine.message.contains("Already at oldest"));
 }
 
+#[test]
+fn test_earlier_later_by_count() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abcdef");
+    engine.update_syntax();
+    for _ in 0..4 {
+        press_char(&mut engine, 'x');
+    }
+    assert_eq!(engine.buffer().to_string(), "ef");
+
+    engine.execute_command("earlier 3");
+    assert_eq!(engine.buffer().to_string(), "bcdef");
+    assert_eq!(engine.message, "3 changes; before #2");
+
+    engine.execute_command("later 2");
+    assert_eq!(engine.buffer().to_string(), "def");
+    assert_eq!(engine.message, "2 changes; after #3");
+
+    // Counts beyond the available history clamp instead of erroring.
+    engine.execute_command("lat 10");
+    assert_eq!(engine.buffer().to_string(), "ef");
+    assert_eq!(engine.message, "1 change; after #4");
+    engine.execute_command("ea 10");
+    assert_eq!(engine.buffer().to_string(), "abcdef");
+    engine.execute_command("earlier");
+    assert_eq!(engine.message, "Already at oldest change");
+
+    engine.execute_command("earlier 3x");
+    assert!(engine.message.starts_with("E475"));
+}
+
+#[test]
+fn test_earlier_later_by_time() {
+    use std::time::{Duration, SystemTime};
+
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abcd");
+    engine.update_syntax();
+    for _ in 0..3 {
+        press_char(&mut engine, 'x');
+    }
+    // Pretend the first change happened a minute before the other two.
+    let now = SystemTime::now();
+    let stack = &mut engine.active_buffer_state_mut().undo_stack;
+    stack[0].time = now - Duration::from_secs(60);
+    stack[1].time = now - Duration::from_secs(5);
+    stack[2].time = now;
+
+    engine.execute_command("earlier 10s");
+    assert_eq!(engine.buffer().to_string(), "bcd");
+    assert_eq!(engine.message, "2 changes; before #2");
+
+    engine.execute_command("later 1m");
+    assert_eq!(engine.buffer().to_string(), "d");
+
+    engine.execute_command("earlier 1h");
+    assert_eq!(engine.buffer().to_string(), "abcd");
+}
+
 #[test]
 fn test_undo_cursor_position_restored() {
     let mut engine = Engine::new();
@@ -1907,6 +2141,32 @@ fn test_named_register_yank() {
     assert_eq!(content2, "test line\n");
 }
 
+#[test]
+fn test_registers_round_trip_through_session() {
+    let mut engine = engine_with_text("alpha\nbeta\n");
+    send_keys(&mut engine, "\"ayyjdd");
+    engine
+        .registers
+        .insert('+', ("clipboard".to_string(), false));
+    engine.collect_session_open_files();
+    let json = serde_json::to_string(&engine.session).unwrap();
+
+    let mut engine = engine_with_text("x\n");
+    engine.session = serde_json::from_str(&json).unwrap();
+    engine.restore_session_registers();
+    assert_eq!(
+        engine.registers.get(&'a'),
+        Some(&("alpha\n".to_string(), true))
+    );
+    assert_eq!(
+        engine.registers.get(&'1'),
+        Some(&("beta\n".to_string(), true))
+    );
+    assert!(!engine.registers.contains_key(&'+'));
+    send_keys(&mut engine, "\"ap");
+    assert_eq!(engine.buffer().to_string(), "x\nalpha\n");
+}
+
 #[test]
 fn test_named_register_paste() {
     let mut engine = Engine::new();
@@ -2784,6 +3044,64 @@ fn test_count_scroll_commands() {
     assert_eq!(engine.view().cursor.line, 30);
 }
 
+#[test]
+fn test_page_scroll_skips_closed_folds() {
+    let mut engine = Engine::new();
+    let text: String = (0..100).map(|i| format!("Line {i}\n")).collect();
+    engine.buffer_mut().insert(0, &text);
+    engine.set_viewport_lines(10);
+    engine.settings.ctrl_f_action = "page_down".to_string();
+    engine.view_mut().close_fold(10, 39);
+    engine.view_mut().cursor.line = 2;
+
+    // Half pages move the view and the cursor together, so the cursor
+    // stays on the same screen row; the fold counts as one line.
+    press_ctrl(&mut engine, 'd');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (5, 7)
+    );
+    press_ctrl(&mut engine, 'd');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (10, 41)
+    );
+    press_ctrl(&mut engine, 'u');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (5, 7)
+    );
+
+    // Full pages, both ways.
+    press_ctrl(&mut engine, 'f');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (44, 46)
+    );
+    press_ctrl(&mut engine, 'b');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (5, 7)
+    );
+
+    // Clamped at the end: the last page stays full, the cursor stops on
+    // the last line; and at the start.
+    press_char(&mut engine, '2');
+    press_char(&mut engine, '0');
+    press_ctrl(&mut engine, 'f');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (90, 99)
+    );
+    press_char(&mut engine, '2');
+    press_char(&mut engine, '0');
+    press_ctrl(&mut engine, 'b');
+    assert_eq!(
+        (engine.view().scroll_top, engine.view().cursor.line),
+        (0, 0)
+    );
+}
+
 #[test]
 fn test_count_motion_bounds_checking() {
     let mut engine = Engine::new();
@@ -3296,6 +3614,46 @@ fn test_count_not_applied_to_visual_operators() {
     assert!(!text.contains("line 3"));
 }
 
+#[test]
+fn test_project_settings_overlay() {
+    use std::fs;
+
+    let root = std::env::temp_dir().join("vimcode_test_project_overlay");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join(".git")).unwrap();
+    let file = root.join(".vimcode.json");
+    fs::write(&file, r#"{"tabstop": 3, "format_on_save": true}"#).unwrap();
+
+    let mut engine = Engine::new();
+    let global_tabstop = engine.settings.tabstop;
+    engine.cwd = root.clone();
+    assert!(engine.apply_project_settings());
+    assert_eq!(
+        engine.project_settings_path.as_deref(),
+        Some(file.as_path())
+    );
+    assert_eq!(engine.settings.tabstop, 3);
+    assert!(engine.settings.format_on_save);
+
+    // Re-applying starts from the global settings, not the overlaid ones.
+    assert!(engine.apply_project_settings());
+    assert_eq!(engine.settings.tabstop, 3);
+
+    // Removing the file drops the overlay.
+    fs::remove_file(&file).unwrap();
+    assert!(engine.check_project_settings_changed());
+    assert_eq!(engine.settings.tabstop, global_tabstop);
+    assert!(!engine.check_project_settings_changed());
+
+    // A malformed file warns and keeps the global settings.
+    fs::write(&file, r#"{"tabstop": 3,"#).unwrap();
+    assert!(engine.check_project_settings_changed());
+    assert_eq!(engine.settings.tabstop, global_tabstop);
+    assert!(engine.message.contains("Ignoring"), "{}", engine.message);
+
+    let _ = fs::remove_dir_all(&root);
+}
+
 #[test]
 #[cfg(not(target_os = "windows"))] // settings path differs on Windows
 fn test_config_reload() {
@@ -6163,6 +6521,66 @@ fn test_macro_records_ctrl_keys() {
     assert_eq!(content, "<C-D><C-U>");
 }
 
+#[test]
+fn test_macro_append_with_uppercase_register() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd");
+
+    // qa A!<Esc> q — then qA j q appends the motion to the same macro.
+    for ch in "qaA!".chars() {
+        press_char(&mut engine, ch);
+    }
+    press_special(&mut engine, "Escape");
+    press_char(&mut engine, 'q');
+    for ch in "qAjq".chars() {
+        press_char(&mut engine, ch);
+    }
+    assert!(engine.macro_recording.is_none());
+    let (content, linewise) = engine.registers.get(&'a').unwrap();
+    assert_eq!(content, "A!\x1bj");
+    assert!(!linewise);
+    assert!(!engine.registers.contains_key(&'A'));
+
+    // The combined macro runs both parts.
+    press_char(&mut engine, '@');
+    press_char(&mut engine, 'a');
+    while !engine.macro_playback_queue.is_empty() {
+        let _ = engine.advance_macro_playback();
+    }
+    assert_eq!(engine.buffer().to_string(), "a!\nb!\nc\nd");
+    assert_eq!(engine.cursor().line, 2);
+}
+
+#[test]
+fn test_macro_edit_as_text_and_yank_back() {
+    let mut engine = engine_with_text("one\ntwo");
+    engine.set_register('a', "A!\x1bj".to_string(), false);
+
+    // :put a inserts the raw keystrokes for editing.
+    engine.execute_command("put a");
+    assert_eq!(engine.buffer().to_string(), "one\nA!\x1bj\ntwo");
+
+    // Edit the macro, yank it back linewise, remove the line and replay.
+    send_keys(&mut engine, "0f!r;\"ayydd");
+    assert_eq!(engine.buffer().to_string(), "one\ntwo");
+    assert_eq!(engine.registers.get(&'a').unwrap().0, "A;\x1bj\n");
+    send_keys(&mut engine, "gg@a");
+    while !engine.macro_playback_queue.is_empty() {
+        let _ = engine.advance_macro_playback();
+    }
+    assert_eq!(engine.buffer().to_string(), "one;\ntwo");
+    assert_eq!(engine.cursor().line, 1);
+
+    // Special keys can be spelled out when editing a macro by hand.
+    engine.set_register('b', "A.<Esc>k".to_string(), false);
+    send_keys(&mut engine, "@b");
+    while !engine.macro_playback_queue.is_empty() {
+        let _ = engine.advance_macro_playback();
+    }
+    assert_eq!(engine.buffer().to_string(), "one;\ntwo.");
+    assert_eq!(engine.cursor().line, 0);
+}
+
 #[test]
 fn test_macro_playback_with_arrow_keys() {
     let mut engine = Engine::new();
@@ -6394,44 +6812,188 @@ fn test_substitute_multiple_lines_undo() {
 }
 
 #[test]
-fn test_cw_cursor_position_after_last_word() {
-    // Verify cursor is positioned AFTER the space when using cw on last word
+fn test_substitute_confirm_prompts_per_match() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abc def");
-    engine.update_syntax();
+    engine.buffer_mut().insert(0, "foo foo\nbar\nfoo foo\n");
 
-    // Move to 'd' in "def"
-    engine.view_mut().cursor.col = 4;
+    engine.execute_command("%s/foo/baz/gc");
+    assert!(engine.substitute_confirm.is_some());
+    assert_eq!(engine.message, "replace with baz? (y/n/a/q/l)");
+    assert_eq!(engine.search_matches, vec![(0, 3)]);
 
-    // cw should delete "def" and position cursor after the space for insertion
-    press_char(&mut engine, 'c');
-    press_char(&mut engine, 'w');
+    press_char(&mut engine, 'y');
+    assert_eq!(engine.buffer().to_string(), "baz foo\nbar\nfoo foo\n");
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 4 });
 
-    assert_eq!(
-        engine.buffer().to_string(),
-        "abc ",
-        "cw should leave 'abc '"
-    );
-    assert_eq!(engine.mode, Mode::Insert, "should be in insert mode");
-    assert_eq!(
-        engine.view().cursor.col,
-        4,
-        "cursor should be after the space (col 4)"
-    );
+    press_char(&mut engine, 'n');
+    assert_eq!(engine.view().cursor, Cursor { line: 2, col: 0 });
+    press_char(&mut engine, 'x'); // not an answer: prompt stays
+    assert!(engine.substitute_confirm.is_some());
+
+    press_char(&mut engine, 'l');
+    assert!(engine.substitute_confirm.is_none());
+    assert!(engine.search_matches.is_empty());
+    assert_eq!(engine.buffer().to_string(), "baz foo\nbar\nbaz foo\n");
+    assert_eq!(engine.message, "2 substitutions");
+
+    // All answers form one undo step.
+    engine.undo();
+    assert_eq!(engine.buffer().to_string(), "foo foo\nbar\nfoo foo\n");
 }
 
 #[test]
-fn test_ce_cursor_position_after_last_word() {
-    // Verify cursor is positioned AFTER the space when using ce on last word
+fn test_substitute_confirm_all_and_quit() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abc def");
-    engine.update_syntax();
+    engine.buffer_mut().insert(0, "Foo\nfoo\nfoo\n");
 
-    // Move to 'd' in "def"
-    engine.view_mut().cursor.col = 4;
+    engine.execute_command("%s/foo/x/ci");
+    press_char(&mut engine, 'n');
+    press_char(&mut engine, 'a');
+    assert!(engine.substitute_confirm.is_none());
+    assert_eq!(engine.buffer().to_string(), "Foo\nx\nx\n");
 
-    // ce should delete "def" and position cursor after the space for insertion
-    press_char(&mut engine, 'c');
+    engine.execute_command("%s/x/y/c");
+    press_special(&mut engine, "Escape");
+    assert!(engine.substitute_confirm.is_none());
+    assert_eq!(engine.buffer().to_string(), "Foo\nx\nx\n");
+    assert_eq!(engine.message, "0 substitutions");
+}
+
+#[test]
+fn test_substitute_confirm_range_follows_inserted_lines() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\na\na\n");
+
+    engine.mode = Mode::VisualLine;
+    engine.visual_anchor = Some(Cursor { line: 0, col: 0 });
+    engine.view_mut().cursor = Cursor { line: 1, col: 0 };
+
+    // Each replacement splits a line, pushing the end of the range down.
+    engine.execute_command(r"'<,'>s/a/x\ry/c");
+    press_char(&mut engine, 'y');
+    press_char(&mut engine, 'y');
+    assert!(engine.substitute_confirm.is_none());
+    assert_eq!(engine.buffer().to_string(), "x\ny\nx\ny\na\n");
+    assert_eq!(engine.message, "2 substitutions");
+}
+
+#[test]
+fn test_substitute_capture_groups() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "john smith\nabc-123\nfoo\n");
+
+    engine.execute_command(r"s/\(\w\+\) \(\w\+\)/\2, \1/");
+    assert_eq!(engine.buffer().to_string(), "smith, john\nabc-123\nfoo\n");
+
+    // Nested groups are numbered by their opening parenthesis.
+    engine.execute_command(r"%s/\(\(\a\+\)-\(\d\+\)\)/[\1|\2|\3]/");
+    assert_eq!(
+        engine.buffer().to_string(),
+        "smith, john\n[abc-123|abc|123]\nfoo\n"
+    );
+
+    // `&` and `\0` are the whole match; `\&` and `\\` are literal.
+    engine.execute_command(r"%s/foo/<&\0>\&\\/");
+    assert_eq!(
+        engine.buffer().to_string(),
+        "smith, john\n[abc-123|abc|123]\n<foofoo>&\\\n"
+    );
+}
+
+#[test]
+fn test_substitute_case_modifiers() {
+    let mut engine = Engine::new();
+    engine
+        .buffer_mut()
+        .insert(0, "hello world\nhello world\nMIXED Case\n");
+
+    engine.execute_command(r"s/\w\+/\u&/g");
+    assert_eq!(
+        engine.buffer().to_string().lines().next(),
+        Some("Hello World")
+    );
+
+    // `\U` runs until `\E`; `\u` only touches the next character.
+    engine.view_mut().cursor.line = 1;
+    engine.execute_command(r"s/\(hello\) \(world\)/\U\1\E \u\2!/");
+    assert_eq!(
+        engine.buffer().to_string().lines().nth(1),
+        Some("HELLO World!")
+    );
+
+    // A one-shot modifier wins over the span for its character.
+    engine.view_mut().cursor.line = 2;
+    engine.execute_command(r"s/.*/\u\L&/");
+    assert_eq!(
+        engine.buffer().to_string().lines().nth(2),
+        Some("Mixed case")
+    );
+}
+
+#[test]
+fn test_substitute_replacement_line_break_and_nul() {
+    let mut engine = engine_with_text("a,b\nc,d\n");
+    // `\r` splits the line; `\n` inserts a NUL, as in Vim.
+    engine.execute_command(r"s/,/\r/");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc,d\n");
+    engine.view_mut().cursor.line = 2;
+    engine.execute_command(r"s/,/\n/");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\0d\n");
+}
+
+#[test]
+fn test_substitute_vim_pattern_syntax() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a+b (c) a.b axb\n");
+
+    // Bare `+ ( )` are literal; `\.` is a literal dot.
+    engine.execute_command(r"s/+b (c)/X/");
+    assert_eq!(engine.buffer().to_string(), "aX a.b axb\n");
+    engine.execute_command(r"s/a\.b/Y/g");
+    assert_eq!(engine.buffer().to_string(), "aX Y axb\n");
+    engine.execute_command(r"s/\<a.b\>/Z/");
+    assert_eq!(engine.buffer().to_string(), "aX Y Z\n");
+}
+
+#[test]
+fn test_cw_cursor_position_after_last_word() {
+    // Verify cursor is positioned AFTER the space when using cw on last word
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abc def");
+    engine.update_syntax();
+
+    // Move to 'd' in "def"
+    engine.view_mut().cursor.col = 4;
+
+    // cw should delete "def" and position cursor after the space for insertion
+    press_char(&mut engine, 'c');
+    press_char(&mut engine, 'w');
+
+    assert_eq!(
+        engine.buffer().to_string(),
+        "abc ",
+        "cw should leave 'abc '"
+    );
+    assert_eq!(engine.mode, Mode::Insert, "should be in insert mode");
+    assert_eq!(
+        engine.view().cursor.col,
+        4,
+        "cursor should be after the space (col 4)"
+    );
+}
+
+#[test]
+fn test_ce_cursor_position_after_last_word() {
+    // Verify cursor is positioned AFTER the space when using ce on last word
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abc def");
+    engine.update_syntax();
+
+    // Move to 'd' in "def"
+    engine.view_mut().cursor.col = 4;
+
+    // ce should delete "def" and position cursor after the space for insertion
+    press_char(&mut engine, 'c');
     press_char(&mut engine, 'e');
 
     assert_eq!(
@@ -6710,6 +7272,79 @@ fn test_completion_clear_on_other_key() {
     assert!(engine.completion_candidates.is_empty());
 }
 
+#[test]
+fn test_completion_sorted_by_proximity_then_frequency() {
+    let mut engine = Engine::new();
+    engine
+        .buffer_mut()
+        .insert(0, "foobar\nfooqux foozap foozap\n\nfoo\nfoocat");
+    send_keys(&mut engine, "4GA");
+    press_ctrl(&mut engine, 'n');
+    assert_eq!(
+        engine.completion_candidates,
+        vec!["foocat", "foozap", "fooqux", "foobar"]
+    );
+}
+
+#[test]
+fn test_ctrl_p_starts_at_nearest_match_above() {
+    let mut engine = Engine::new();
+    engine
+        .buffer_mut()
+        .insert(0, "foobar\nfooqux\nfoo\nfoocat\nfoozap");
+    send_keys(&mut engine, "3GA");
+    press_ctrl(&mut engine, 'p');
+    assert_eq!(
+        engine.buffer().to_string(),
+        "foobar\nfooqux\nfooqux\nfoocat\nfoozap"
+    );
+    press_ctrl(&mut engine, 'p');
+    assert_eq!(
+        engine.buffer().to_string(),
+        "foobar\nfooqux\nfoobar\nfoocat\nfoozap"
+    );
+    press_ctrl(&mut engine, 'p');
+    assert_eq!(
+        engine.buffer().to_string(),
+        "foobar\nfooqux\nfoozap\nfoocat\nfoozap"
+    );
+}
+
+#[test]
+fn test_completion_from_other_buffers() {
+    let complete = |all_buffers: bool| {
+        let mut engine = Engine::new();
+        engine.settings.complete_all_buffers = all_buffers;
+        engine.buffer_mut().insert(0, "foobar\nfoo");
+        let other = engine.buffer_manager.create();
+        engine
+            .buffer_manager
+            .get_mut(other)
+            .unwrap()
+            .buffer
+            .insert(0, "foreign food");
+        send_keys(&mut engine, "GA");
+        press_ctrl(&mut engine, 'n');
+        engine.completion_candidates
+    };
+    assert_eq!(complete(false), vec!["foobar"]);
+    assert_eq!(complete(true), vec!["foobar", "food"]);
+}
+
+#[test]
+fn test_completion_accept_with_ctrl_y_and_enter() {
+    for accept in ["<C-y>", "<CR>"] {
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "foobar\nfoo");
+        send_keys(&mut engine, "GA");
+        press_ctrl(&mut engine, 'n');
+        send_keys(&mut engine, accept);
+        assert!(engine.completion_idx.is_none());
+        assert_eq!(engine.buffer().to_string(), "foobar\nfoobar");
+        assert_eq!(engine.mode, Mode::Insert);
+    }
+}
+
 // ── Auto-popup completion tests ───────────────────────────────────────────
 
 #[test]
@@ -7538,6 +8173,36 @@ fn test_start_and_poll_project_search() {
     assert!(!engine.project_search_results.is_empty());
 }
 
+#[test]
+fn test_restarted_project_search_shows_latest_query() {
+    let dir = make_search_dir("engine_restart");
+    let mut engine = Engine::new();
+    engine.project_search_query = "world".to_string();
+    engine.start_project_search(dir.clone());
+    engine.project_search_query = "another".to_string();
+    engine.start_project_search(dir);
+    // Only one search notification remains for the superseded query.
+    let running = engine
+        .notifications
+        .iter()
+        .filter(|n| n.kind == NotificationKind::ProjectSearch && !n.done)
+        .count();
+    assert_eq!(running, 1);
+    for _ in 0..200 {
+        if engine.poll_project_search() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(!engine.project_search_running);
+    assert_eq!(engine.project_search_results.len(), 1);
+    assert_eq!(engine.project_search_results[0].line_text, "another line");
+    assert_eq!(engine.message, "1 match in 1 file");
+    // Nothing left to report.
+    assert!(!engine.tick_project_search_progress());
+    assert!(!engine.cancel_project_search());
+}
+
 // ── Project replace ──────────────────────────────────────────────────────
 
 #[test]
@@ -7634,6 +8299,77 @@ fn test_start_and_poll_project_replace() {
     assert!(content.contains("earth"));
 }
 
+fn make_replace_preview_dir(test_name: &str) -> std::path::PathBuf {
+    let dir = make_search_dir(test_name);
+    std::fs::write(dir.join("sample.txt"), "foo 1\nfoo 2\nfoo foo 3\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_replace_selected_skips_unchecked_matches() {
+    let dir = make_replace_preview_dir("engine_replace_selected");
+    let mut engine = Engine::new();
+    engine.project_search_query = "foo".to_string();
+    engine.project_replace_text = "bar".to_string();
+    engine.run_project_search(&dir);
+    assert_eq!(engine.project_search_results.len(), 3);
+    assert_eq!(
+        engine.project_replace_preview_lines(0..3),
+        Some(vec![
+            "bar 1".to_string(),
+            "bar 2".to_string(),
+            "bar bar 3".to_string()
+        ])
+    );
+    engine.toggle_project_match_included(1);
+    assert!(!engine.project_match_included(1));
+    engine.replace_selected_matches();
+    assert_eq!(engine.message, "Replaced 3 occurrences in 1 file");
+    let content = std::fs::read_to_string(dir.join("sample.txt")).unwrap();
+    assert_eq!(content, "bar 1\nfoo 2\nbar bar 3\n");
+    // Only the unchecked match is left, still unchecked.
+    assert_eq!(engine.project_search_results.len(), 1);
+    assert_eq!(engine.project_search_results[0].line_text, "foo 2");
+    assert!(!engine.project_match_included(0));
+}
+
+#[test]
+fn test_replace_selected_in_open_buffer_is_one_undo() {
+    let dir = make_replace_preview_dir("engine_replace_selected_open");
+    let path = dir.join("sample.txt");
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    engine.project_search_query = "foo".to_string();
+    engine.project_replace_text = "bar".to_string();
+    engine.run_project_search(&dir);
+    engine.toggle_project_match_included(0);
+    engine.replace_selected_matches();
+    assert_eq!(engine.buffer().to_string(), "foo 1\nbar 2\nbar bar 3\n");
+    // The open buffer was edited, not the file on disk.
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(content, "foo 1\nfoo 2\nfoo foo 3\n");
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "foo 1\nfoo 2\nfoo foo 3\n");
+}
+
+#[test]
+fn test_replace_selected_skips_changed_lines() {
+    let dir = make_replace_preview_dir("engine_replace_selected_stale");
+    let path = dir.join("sample.txt");
+    let mut engine = Engine::new();
+    engine.project_search_query = "foo".to_string();
+    engine.project_replace_text = "bar".to_string();
+    engine.run_project_search(&dir);
+    std::fs::write(&path, "foo 1\nchanged\nfoo foo 3\n").unwrap();
+    engine.replace_selected_matches();
+    assert_eq!(
+        engine.message,
+        "Replaced 3 occurrences in 1 file (1 changed since the search, skipped)"
+    );
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(content, "bar 1\nchanged\nbar bar 3\n");
+}
+
 // ── LSP integration tests ─────────────────────────────────────────────
 
 #[test]
@@ -8068,6 +8804,39 @@ fn test_zz_near_start_no_negative_scroll() {
     assert_eq!(engine.view().scroll_top, 0);
 }
 
+#[test]
+fn test_scroll_commands_count_closed_folds_as_one_line() {
+    let mut engine = Engine::new();
+    let content: String = (0..50).map(|i| format!("line {}\n", i)).collect();
+    engine.buffer_mut().insert(0, &content);
+    engine.set_viewport_lines(10);
+    engine.view_mut().close_fold(10, 19);
+    engine.view_mut().cursor.line = 25;
+
+    // zz: five visible lines above the cursor (20..=24).
+    send_keys(&mut engine, "zz");
+    assert_eq!(engine.view().scroll_top, 20);
+    // zb: nine visible lines above, the fold 10-19 counting as one.
+    send_keys(&mut engine, "zb");
+    assert_eq!(engine.view().scroll_top, 7);
+    // zt on a line inside a fold snaps to the fold header.
+    engine.view_mut().cursor.line = 15;
+    send_keys(&mut engine, "zt");
+    assert_eq!(engine.view().scroll_top, 10);
+
+    // Ctrl-Y keeps the cursor on its line until it would leave the view,
+    // then clamps it to the last visible line (not into the fold).
+    engine.view_mut().cursor.line = 25;
+    engine.view_mut().scroll_top = 7;
+    press_ctrl(&mut engine, 'y');
+    assert_eq!(engine.view().scroll_top, 6);
+    assert_eq!(engine.view().cursor.line, 24);
+    // Ctrl-E scrolls back without moving the cursor.
+    press_ctrl(&mut engine, 'e');
+    assert_eq!(engine.view().scroll_top, 7);
+    assert_eq!(engine.view().cursor.line, 24);
+}
+
 // =======================================================================
 // Tests: Join lines (J)
 // =======================================================================
@@ -8701,6 +9470,46 @@ fn test_norm_undo_single_group() {
     assert_eq!(restored, "aaa\nbbb");
 }
 
+#[test]
+fn test_norm_macro_over_visual_selection() {
+    // :'<,'>normal @a plays the macro once per selected line
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd");
+    engine.set_register('a', "A;\x1b".to_string(), false);
+    send_keys(&mut engine, "jVj:normal @a<CR>");
+    let content: String = engine.buffer().content.chars().collect();
+    assert_eq!(content, "a\nb;\nc;\nd");
+    assert_eq!(engine.message, "2 lines affected");
+    assert!(engine.macro_playback_queue.is_empty());
+    // One undo step reverts every line.
+    press_char(&mut engine, 'u');
+    let restored: String = engine.buffer().content.chars().collect();
+    assert_eq!(restored, "a\nb\nc\nd");
+
+    // After leaving Visual mode the range comes from the '< '> marks.
+    run_command(&mut engine, "'<,'>norm A.");
+    let content: String = engine.buffer().content.chars().collect();
+    assert_eq!(content, "a\nb.\nc.\nd");
+}
+
+#[test]
+fn test_norm_skips_lines_deleted_by_keys() {
+    // Lines are tracked by marker, so deleted lines are not visited
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "1\n2\n3\n4\n5");
+    run_command(&mut engine, "1,4norm jdd");
+    let content: String = engine.buffer().content.chars().collect();
+    assert_eq!(content, "1\n3\n5");
+    assert_eq!(engine.message, "2 lines affected");
+
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd");
+    run_command(&mut engine, "1,3norm dd");
+    let content: String = engine.buffer().content.chars().collect();
+    assert_eq!(content, "d");
+    assert_eq!(engine.message, "3 lines affected");
+}
+
 // ── Fuzzy finder tests ────────────────────────────────────────────────────
 
 #[test]
@@ -9068,72 +9877,298 @@ fn test_picker_files_populates_preview() {
     let _ = std::fs::remove_dir_all(&dir);
 }
 
+/// Select the Files picker entry whose path ends with `name`.
+fn select_picker_file(engine: &mut Engine, name: &str) {
+    let idx = engine
+        .picker_items
+        .iter()
+        .position(|item| matches!(&item.action, PickerAction::OpenFile(p) if p.ends_with(name)))
+        .unwrap_or_else(|| panic!("{name} not listed"));
+    engine.picker_selected = idx;
+    engine.picker_load_preview();
+}
+
 #[test]
-fn test_picker_grep_source_live_search() {
-    use std::io::Write;
-    let dir = std::env::temp_dir().join("vimcode_picker_grep");
+fn test_picker_files_preview_highlights_and_binary() {
+    let dir = std::env::temp_dir().join("vimcode_picker_preview_hl");
     let _ = std::fs::remove_dir_all(&dir);
     std::fs::create_dir_all(&dir).unwrap();
-    let mut f = std::fs::File::create(dir.join("sample.txt")).unwrap();
-    writeln!(f, "unique_grep_marker_xyz hello").unwrap();
-    drop(f);
+    std::fs::write(dir.join("main.rs"), "fn main() {\n    let x = 1;\n}\n").unwrap();
+    std::fs::write(dir.join("blob.bin"), b"\x7fELF\0\0\x01garbage").unwrap();
 
     let mut engine = Engine::new();
     engine.cwd = dir.clone();
-    engine.open_picker(PickerSource::Grep);
-
-    assert!(engine.picker_open);
-    assert_eq!(engine.picker_source, PickerSource::Grep);
-    assert_eq!(engine.picker_title, "Live Grep");
-
-    // Empty query — no results (live source, no pre-populate)
-    assert!(engine.picker_items.is_empty());
-
-    // Single char — still below 2-char threshold
-    engine.handle_picker_key("u", Some('u'), false);
-    assert!(engine.picker_items.is_empty(), "1 char should not search");
-
-    // Second char — search fires but unlikely to match our marker
-    // Type enough to match our unique marker
-    for c in "nique_grep_marker_xyz".chars() {
-        engine.handle_picker_key("", Some(c), false);
-    }
-    assert!(
-        !engine.picker_items.is_empty(),
-        "should find our marker: query='{}'",
-        engine.picker_query,
-    );
+    engine.open_picker(PickerSource::Files);
 
-    // Verify result is OpenFileAtLine action
-    let item = &engine.picker_items[0];
+    select_picker_file(&mut engine, "main.rs");
+    let preview = engine.picker_preview.clone().unwrap();
+    assert_eq!(preview.lines.len(), 3);
+    assert!(preview.note.is_none());
     assert!(
-        item.display.contains("unique_grep_marker_xyz"),
-        "display: {}",
-        item.display,
+        preview.highlights[0]
+            .iter()
+            .any(|(s, e, scope)| (*s, *e) == (0, 2) && scope.starts_with("keyword")),
+        "`fn` should be highlighted: {:?}",
+        preview.highlights[0]
     );
-    match &item.action {
-        PickerAction::OpenFileAtLine(_, _) => {}
-        other => panic!("expected OpenFileAtLine, got {:?}", other),
-    }
+    // Loaded previews are cached while the picker stays open.
+    assert!(engine
+        .picker_preview_cache
+        .contains_key(&dir.join("main.rs")));
 
-    // Preview should be loaded
-    assert!(engine.picker_preview.is_some());
+    select_picker_file(&mut engine, "blob.bin");
+    let preview = engine.picker_preview.clone().unwrap();
+    assert_eq!(preview.note.as_deref(), Some("(binary)"));
+    assert!(preview.lines.is_empty());
 
+    engine.close_picker();
+    assert!(engine.picker_preview_cache.is_empty());
     let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_picker_grep_confirm_opens_at_line() {
-    let mut engine = Engine::new();
-    let dir = std::env::temp_dir().join("vimcode_picker_grep_confirm");
+fn test_picker_files_large_preview_loads_in_background() {
+    let dir = std::env::temp_dir().join("vimcode_picker_preview_large");
     let _ = std::fs::remove_dir_all(&dir);
     std::fs::create_dir_all(&dir).unwrap();
-    let path = dir.join("target.txt");
-    std::fs::write(&path, "line0\nline1\nline2\nline3\n").unwrap();
+    let big: String = (0..40_000).map(|i| format!("line number {i}\n")).collect();
+    std::fs::write(dir.join("big.txt"), big).unwrap();
+    std::fs::write(dir.join("small.txt"), "tiny\n").unwrap();
 
+    let mut engine = Engine::new();
     engine.cwd = dir.clone();
-    engine.picker_open = true;
-    engine.picker_source = PickerSource::Grep;
+    engine.open_picker(PickerSource::Files);
+    select_picker_file(&mut engine, "big.txt");
+    assert!(engine.picker_preview_loading());
+    assert_eq!(
+        engine.picker_preview.as_ref().unwrap().note.as_deref(),
+        Some("Loading…")
+    );
+    for _ in 0..400 {
+        if engine.poll_picker_preview() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert!(!engine.picker_preview_loading());
+    let preview = engine.picker_preview.clone().unwrap();
+    assert!(preview.note.is_none());
+    assert_eq!(preview.lines.len(), 500);
+    assert_eq!(preview.lines[0].1, "line number 0");
+
+    // A load that finishes after the selection moved on only fills the cache.
+    engine.picker_preview_cache.clear();
+    select_picker_file(&mut engine, "big.txt");
+    select_picker_file(&mut engine, "small.txt");
+    for _ in 0..400 {
+        if engine.poll_picker_preview() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(engine.picker_preview.as_ref().unwrap().lines[0].1, "tiny");
+    assert!(engine
+        .picker_preview_cache
+        .contains_key(&dir.join("big.txt")));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_picker_files_remembers_last_file_and_query() {
+    let dir = std::env::temp_dir().join("vimcode_picker_remember");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in ["alpha.txt", "beta.txt", "gamma.txt"] {
+        std::fs::write(dir.join(name), "text\n").unwrap();
+    }
+
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.open_picker(PickerSource::Files);
+    select_picker_file(&mut engine, "gamma.txt");
+    engine.picker_confirm();
+
+    // Reopening puts the cursor back on the file opened last time.
+    engine.open_picker(PickerSource::Files);
+    assert!(engine.picker_query.is_empty());
+    assert_eq!(
+        engine.picker_items[engine.picker_selected].display,
+        "gamma.txt"
+    );
+
+    // Without the setting, the query typed before Escape is not restored.
+    engine.handle_picker_key("", Some('b'), false);
+    engine.handle_picker_key("Escape", None, false);
+    engine.open_picker(PickerSource::Files);
+    assert!(engine.picker_query.is_empty());
+
+    engine.settings.fuzzy_remember_query = true;
+    engine.handle_picker_key("", Some('b'), false);
+    engine.handle_picker_key("Escape", None, false);
+    engine.open_picker(PickerSource::Files);
+    assert_eq!(engine.picker_query, "b");
+    assert_eq!(engine.picker_items[0].display, "beta.txt");
+    // The last file no longer matches, so the first result stays selected.
+    assert_eq!(engine.picker_selected, 0);
+    engine.close_picker();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Let the debounced live grep start and run to completion.
+fn wait_for_picker_grep(engine: &mut Engine) {
+    for _ in 0..400 {
+        engine.poll_picker_grep();
+        if !engine.picker_grep_active() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    panic!("live grep did not finish");
+}
+
+#[test]
+fn test_picker_grep_source_live_search() {
+    use std::io::Write;
+    let dir = std::env::temp_dir().join("vimcode_picker_grep");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut f = std::fs::File::create(dir.join("sample.txt")).unwrap();
+    writeln!(f, "unique_grep_marker_xyz hello").unwrap();
+    drop(f);
+
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.open_picker(PickerSource::Grep);
+
+    assert!(engine.picker_open);
+    assert_eq!(engine.picker_source, PickerSource::Grep);
+    assert_eq!(engine.picker_title, "Live Grep");
+
+    // Empty query — no results (live source, no pre-populate)
+    assert!(engine.picker_items.is_empty());
+
+    // Single char — still below 2-char threshold
+    engine.handle_picker_key("u", Some('u'), false);
+    assert!(engine.picker_items.is_empty(), "1 char should not search");
+
+    // Second char — search fires but unlikely to match our marker
+    // Type enough to match our unique marker
+    for c in "nique_grep_marker_xyz".chars() {
+        engine.handle_picker_key("", Some(c), false);
+    }
+    // Debounced: nothing runs until typing pauses.
+    assert!(engine.picker_grep_active());
+    assert!(engine.picker_items.is_empty());
+    wait_for_picker_grep(&mut engine);
+    assert!(
+        !engine.picker_items.is_empty(),
+        "should find our marker: query='{}'",
+        engine.picker_query,
+    );
+
+    // Verify result is OpenFileAtLine action
+    let item = &engine.picker_items[0];
+    assert!(
+        item.display.contains("unique_grep_marker_xyz"),
+        "display: {}",
+        item.display,
+    );
+    match &item.action {
+        PickerAction::OpenFileAtLine(_, _) => {}
+        other => panic!("expected OpenFileAtLine, got {:?}", other),
+    }
+
+    // Preview should be loaded
+    assert!(engine.picker_preview.is_some());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_picker_grep_discards_superseded_query() {
+    let dir = std::env::temp_dir().join("vimcode_picker_grep_revision");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "alpha_rev_marker\nbeta_rev_marker\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.open_picker(PickerSource::Grep);
+    for c in "rev_marker".chars() {
+        engine.handle_picker_key("", Some(c), false);
+    }
+    wait_for_picker_grep(&mut engine);
+    assert_eq!(engine.picker_items.len(), 2);
+    assert_eq!(engine.picker_grep_total, Some(2));
+    assert_eq!(engine.picker_grep_status().as_deref(), Some("2 matches"));
+
+    // A new revision: the old results stay until the new search reports.
+    engine.handle_picker_key("", Some('x'), false);
+    assert!(engine.picker_grep_active());
+    assert_eq!(engine.picker_items.len(), 2);
+    wait_for_picker_grep(&mut engine);
+    assert!(engine.picker_items.is_empty());
+    assert_eq!(engine.picker_grep_status().as_deref(), Some("0 matches"));
+
+    // Closing the picker cancels a queued search.
+    engine.handle_picker_key("BackSpace", None, false);
+    assert!(engine.picker_grep_active());
+    engine.close_picker();
+    assert!(!engine.picker_grep_active());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_picker_grep_sorts_by_file_and_reports_errors() {
+    let dir = std::env::temp_dir().join("vimcode_picker_grep_sorted");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("m")).unwrap();
+    for name in ["z.txt", "a.txt", "m/k.txt"] {
+        std::fs::write(dir.join(name), "sorted_marker\nsorted_marker\n").unwrap();
+    }
+
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.open_picker(PickerSource::Grep);
+    for c in "sorted_marker".chars() {
+        engine.handle_picker_key("", Some(c), false);
+    }
+    wait_for_picker_grep(&mut engine);
+    let shown: Vec<&str> = engine
+        .picker_items
+        .iter()
+        .map(|i| i.display.split(':').next().unwrap())
+        .collect();
+    assert_eq!(
+        shown,
+        vec!["a.txt", "a.txt", "m/k.txt", "m/k.txt", "z.txt", "z.txt"]
+    );
+
+    // A search that can't run says why instead of showing nothing.
+    engine.settings.explorer_ignore = vec!["[".to_string()];
+    engine.handle_picker_key("BackSpace", None, false);
+    wait_for_picker_grep(&mut engine);
+    assert_eq!(engine.picker_items.len(), 1);
+    assert!(
+        engine.picker_items[0].display.starts_with("Invalid"),
+        "{}",
+        engine.picker_items[0].display
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_picker_grep_confirm_opens_at_line() {
+    let mut engine = Engine::new();
+    let dir = std::env::temp_dir().join("vimcode_picker_grep_confirm");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("target.txt");
+    std::fs::write(&path, "line0\nline1\nline2\nline3\n").unwrap();
+
+    engine.cwd = dir.clone();
+    engine.picker_open = true;
+    engine.picker_source = PickerSource::Grep;
     engine.picker_items = vec![PickerItem {
         display: "target.txt:3: line2".to_string(),
         filter_text: "target.txt:3: line2".to_string(),
@@ -9527,6 +10562,7 @@ fn test_command_center_grep_runs_search() {
     for ch in "%hello".chars() {
         engine.handle_picker_key(&ch.to_string(), Some(ch), false);
     }
+    wait_for_picker_grep(&mut engine);
     assert_eq!(engine.picker_title, "Search for Text");
     assert!(
         engine.picker_items.len() >= 2,
@@ -10102,6 +11138,7 @@ fn test_leader_sw_opens_grep_with_word() {
     assert!(engine.picker_open);
     assert_eq!(engine.picker_source, PickerSource::Grep);
     assert_eq!(engine.picker_query, "foobar");
+    wait_for_picker_grep(&mut engine);
     // Should have grep results
     assert!(
         !engine.picker_items.is_empty(),
@@ -10312,6 +11349,128 @@ fn test_vimgrep_alias() {
     assert!(engine.quickfix_open);
 }
 
+#[test]
+fn test_search_results_to_quickfix() {
+    let dir = make_search_dir("engine_to_quickfix");
+    std::fs::write(dir.join("other.txt"), "é hello\n").unwrap();
+    let mut engine = Engine::new();
+    engine.project_search_query = "hello".to_string();
+    engine.run_project_search(&dir);
+    assert_eq!(engine.project_search_results.len(), 2);
+    engine.project_search_selected = 1;
+    engine.execute_command("SearchToQuickfix");
+    assert!(engine.quickfix_open);
+    assert!(!engine.quickfix_has_focus);
+    assert_eq!(engine.message, "2 matches sent to quickfix");
+    // Same file-grouped order; the byte column becomes a char column.
+    let files: Vec<_> = engine
+        .quickfix_items
+        .iter()
+        .map(|m| m.file.clone())
+        .collect();
+    assert_eq!(files, vec![dir.join("other.txt"), dir.join("sample.txt")]);
+    assert_eq!(engine.quickfix_items[0].col, 2);
+    assert_eq!(engine.quickfix_selected, 1);
+
+    // Stepping through opens unopened files as previews.
+    engine.execute_command("cprevious");
+    assert_eq!(engine.view().cursor.col, 2);
+    let bid = engine.active_buffer_id();
+    assert!(engine.buffer_manager.get(bid).unwrap().preview);
+}
+
+#[test]
+fn test_search_to_quickfix_without_results() {
+    let mut engine = Engine::new();
+    engine.execute_command("SearchToQuickfix");
+    assert!(!engine.quickfix_open);
+    assert_eq!(engine.message, "No search results");
+}
+
+#[test]
+fn test_grep_include_exclude_globs() {
+    let dir = std::env::temp_dir().join("vimcode_qf_grep_globs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.rs"), "globmark rs\n").unwrap();
+    std::fs::write(dir.join("b.lock"), "globmark lock\n").unwrap();
+    std::fs::write(dir.join("c.txt"), "globmark txt\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.execute_command("grep --include=*.rs globmark");
+    assert_eq!(engine.quickfix_items.len(), 1);
+    assert_eq!(engine.quickfix_items[0].line_text, "globmark rs");
+
+    engine.execute_command("grep --exclude=*.lock globmark");
+    assert_eq!(engine.quickfix_items.len(), 2);
+
+    // The explorer ignore list applies too.
+    engine.settings.explorer_ignore = vec!["*.txt".to_string()];
+    engine.execute_command("grep --exclude=*.lock globmark");
+    assert_eq!(engine.quickfix_items.len(), 1);
+
+    engine.execute_command("grep --include=*.{rs globmark");
+    assert!(
+        engine.message.contains("Invalid include glob"),
+        "{}",
+        engine.message
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_project_search_invalid_glob_reports_error() {
+    let dir = make_search_dir("engine_bad_glob");
+    let mut engine = Engine::new();
+    engine.project_search_query = "hello".to_string();
+    engine.project_search_options.exclude = "[oops".to_string();
+    engine.run_project_search(&dir);
+    assert!(engine.project_search_results.is_empty());
+    assert!(
+        engine.message.starts_with("Invalid exclude glob '[oops'"),
+        "{}",
+        engine.message
+    );
+}
+
+#[test]
+fn test_references_to_quickfix_fills_line_text_and_char_col() {
+    let dir = std::env::temp_dir().join("vimcode_qf_refs");
+    std::fs::create_dir_all(&dir).unwrap();
+    let open = dir.join("open.rs");
+    let closed = dir.join("closed.rs");
+    std::fs::write(&open, "let 🦀 = foo();\n").unwrap();
+    std::fs::write(&closed, "use x;\n    foo();\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine
+        .open_file_with_mode(&open, OpenMode::Permanent)
+        .unwrap();
+    let loc = |path: &std::path::Path, line: u32, character: u32| lsp::Location {
+        path: path.to_path_buf(),
+        range: lsp::LspRange {
+            start: lsp::LspPosition { line, character },
+            end: lsp::LspPosition { line, character },
+        },
+    };
+    // "🦀" is two UTF-16 units, so `foo` sits at UTF-16 col 9 but char col 8.
+    let items = engine.references_to_quickfix(vec![loc(&open, 0, 9), loc(&closed, 1, 4)]);
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].line_text, "let 🦀 = foo();");
+    assert_eq!(items[0].col, 8);
+    assert_eq!(items[1].line_text, "    foo();");
+    assert_eq!((items[1].line, items[1].col), (1, 4));
+
+    // Jumping to a reference in a file that isn't open uses a preview tab.
+    engine.quickfix_items = items;
+    engine.quickfix_go(1);
+    assert_eq!(engine.preview_buffer_id, Some(engine.active_buffer_id()));
+    assert_eq!(engine.view().cursor.line, 1);
+    assert_eq!(engine.view().cursor.col, 4);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 // ─── rename_file / move_file tests ────────────────────────────────────────
 
 #[test]
@@ -10765,6 +11924,37 @@ fn test_diffthis_two_windows() {
     assert!(!engine.diff_results.is_empty(), "diff results should exist");
 }
 
+#[test]
+fn test_diffput_refused_for_read_only_buffer() {
+    let dir = std::env::temp_dir().join("vimcode_diffput_readonly");
+    std::fs::create_dir_all(&dir).unwrap();
+    let f1 = dir.join("file_a_dp.txt");
+    let f2 = dir.join("file_b_dp.txt");
+    std::fs::write(&f1, "line1\nline2\n").unwrap();
+    std::fs::write(&f2, "line1\nline3\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine
+        .open_file_with_mode(&f1, OpenMode::Permanent)
+        .unwrap();
+    engine.execute_command("diffthis");
+    engine.split_window(SplitDirection::Vertical, Some(&f2));
+    engine.execute_command("diffthis");
+    let other = engine.active_buffer_id();
+    engine.buffer_manager.get_mut(other).unwrap().read_only = true;
+
+    // Put line 2 of file_a into the read-only file_b.
+    let (first, _) = engine.diff_window_pair.unwrap();
+    engine.active_tab_mut().active_window = first;
+    engine.view_mut().cursor.line = 1;
+    press_char(&mut engine, 'd');
+    press_char(&mut engine, 'p');
+    let other_text = engine.buffer_manager.get(other).unwrap().buffer.to_string();
+    assert_eq!(other_text, "line1\nline3\n");
+    assert!(engine.message.starts_with("E45"), "{}", engine.message);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn test_diffsplit_command() {
     let dir = std::env::temp_dir().join("vimcode_diffsplit_vc");
@@ -10792,6 +11982,60 @@ fn test_diffsplit_command() {
     assert!(!engine.diff_results.is_empty());
 }
 
+#[test]
+fn test_build_unified_diff_orders_removed_before_added() {
+    use DiffLine::*;
+    let da = vec![Same, Removed, Removed, Same];
+    let db = vec![Same, Added, Same, Added];
+    let rows: Vec<(Option<usize>, Option<usize>)> = build_unified_diff(&da, &db)
+        .iter()
+        .map(|e| (e.source_line, e.removed_line))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (Some(0), None),
+            (None, Some(1)),
+            (None, Some(2)),
+            (Some(1), None),
+            (Some(2), None),
+            (Some(3), None),
+        ]
+    );
+}
+
+#[test]
+fn test_diffunified_command_and_diffoff() {
+    let dir = std::env::temp_dir().join("vimcode_diffunified_cmd");
+    std::fs::create_dir_all(&dir).unwrap();
+    let base = dir.join("base_du.txt");
+    std::fs::write(&base, "alpha\nbeta\ngamma\n").unwrap();
+
+    let mut engine = engine_with_text("alpha\ngamma\ndelta\n");
+    let win = engine.active_window_id();
+    engine.execute_command(&format!("diffunified {}", base.display()));
+
+    // Single window: no split is opened.
+    assert_eq!(engine.active_tab().layout.window_ids().len(), 1);
+    assert_eq!(engine.diff_unified.as_ref().map(|u| u.window), Some(win));
+    assert_eq!(engine.diff_results[&win][2], DiffLine::Added);
+    let removed: Vec<usize> = engine.diff_aligned[&win]
+        .iter()
+        .filter_map(|e| e.removed_line)
+        .collect();
+    assert_eq!(removed, vec![1]);
+    // Cursor starts on the first hunk: "gamma" follows the deleted "beta".
+    assert_eq!(engine.view().cursor.line, 1);
+
+    engine.execute_command("diffoff");
+    assert!(engine.diff_unified.is_none());
+    assert!(engine.diff_aligned.is_empty());
+
+    engine.execute_command("diffunified /nonexistent/vimcode_base.txt");
+    assert!(engine.diff_unified.is_none());
+    assert!(engine.message.starts_with("E484"));
+}
+
 // ── Diff toolbar + navigation tests ──────────────────────────────────────
 
 #[test]
@@ -11180,10 +12424,60 @@ fn test_git_diff_split_creates_pair() {
     );
 }
 
+#[test]
+fn test_diff_hunk_ranges_and_line_mapping() {
+    let a = ["a", "b", "c", "d"];
+    let b = ["new", "a", "c", "x", "d"];
+    let (da, db) = lcs_diff(&a, &b);
+    let hunks = diff_hunk_ranges(&da, &db);
+    assert_eq!(hunks, vec![(0..0, 0..1), (1..2, 2..2), (3..3, 3..4)]);
+    assert_eq!(hunk_at_line(&hunks, 0), Some(0));
+    assert_eq!(hunk_at_line(&hunks, 3), Some(2));
+    // Pure deletion of "b" is found from the line just after it.
+    assert_eq!(hunk_at_line(&hunks, 2), Some(1));
+    assert_eq!(saved_line_for(&hunks, 1), 0);
+    assert_eq!(saved_line_for(&hunks, 4), 3);
+}
+
 #[test]
 #[cfg(not(target_os = "windows"))]
-fn test_git_diff_split_left_readonly() {
-    let (dir, file) = setup_git_diff_split_repo("left_ro");
+fn test_git_stage_hunk_uses_saved_content_and_undo_hunk_reverts_buffer() {
+    let (dir, file) = setup_git_diff_split_repo("stage_undo_hunk");
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine
+        .open_file_with_mode(&file, OpenMode::Permanent)
+        .unwrap();
+    let saved = std::fs::read_to_string(&file).unwrap();
+    // An unsaved line above the hunk shifts buffer lines by one.
+    engine.buffer_mut().insert(0, "// note\n");
+    engine.set_dirty(true);
+    engine.view_mut().cursor.line = 2;
+
+    engine.execute_command("GitStageHunk");
+    assert!(engine.message.contains("staged"), "{}", engine.message);
+    let index = std::process::Command::new("git")
+        .args(["show", ":hello.rs"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&index.stdout), saved);
+
+    engine.execute_command("GitUndoHunk");
+    assert_eq!(
+        engine.buffer().to_string(),
+        "// note\nfn main() {\n    println!(\"hello\");\n}\n"
+    );
+    assert_eq!(engine.view().cursor.line, 2);
+    engine.undo();
+    assert_eq!(engine.buffer().to_string(), format!("// note\n{saved}"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(not(target_os = "windows"))]
+fn test_git_diff_split_left_readonly() {
+    let (dir, file) = setup_git_diff_split_repo("left_ro");
     let mut engine = Engine::new();
     engine.cwd = dir.clone();
     engine.cmd_git_diff_split(&file);
@@ -11253,6 +12547,24 @@ fn test_close_window_cleans_diff_state() {
     assert!(engine.diff_results.is_empty());
 }
 
+#[test]
+fn test_diff_split_redo_while_zoomed_drops_hidden_window() {
+    let (dir, file) = setup_git_diff_split_repo("zoomed_redo");
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    engine.cmd_git_diff_split(&file);
+    let (head_win, _) = engine.diff_window_pair.unwrap();
+    engine.toggle_window_zoom();
+    engine.cmd_git_diff_split(&file);
+    assert!(!engine.windows.contains_key(&head_win));
+    // The first diff's tab must not bring the closed window back on unzoom.
+    for tab in &engine.active_group().tabs {
+        for id in tab.window_ids() {
+            assert!(engine.windows.contains_key(&id), "stale window {id:?}");
+        }
+    }
+}
+
 // ── Help command tests ──────────────────────────────────────────────────
 
 #[test]
@@ -12586,13 +13898,51 @@ fn test_dap_eval_result_field_default() {
 }
 
 #[test]
-fn test_visual_rows_for_line() {
-    assert_eq!(engine_visual_rows_for_line(0, 80), 1); // empty line = 1 row
-    assert_eq!(engine_visual_rows_for_line(80, 80), 1); // exactly one row
-    assert_eq!(engine_visual_rows_for_line(81, 80), 2); // one char overflow
-    assert_eq!(engine_visual_rows_for_line(160, 80), 2); // exactly two rows
-    assert_eq!(engine_visual_rows_for_line(161, 80), 3);
-    assert_eq!(engine_visual_rows_for_line(10, 0), 1); // zero cols = 1 row
+fn test_word_wrap_segments() {
+    assert_eq!(word_wrap_segments("", 80), vec![(0, 0)]); // empty line = 1 row
+    assert_eq!(word_wrap_segments(&"a".repeat(80), 80), vec![(0, 80)]);
+    // Unbreakable runs hard-wrap at the viewport width.
+    assert_eq!(
+        word_wrap_segments(&"a".repeat(161), 80),
+        vec![(0, 80), (80, 160), (160, 161)]
+    );
+    // Breaks prefer the last space within the row.
+    assert_eq!(
+        word_wrap_segments("hello big world", 10),
+        vec![(0, 10), (10, 15)]
+    );
+    assert_eq!(
+        word_wrap_segments("hello worlds", 10),
+        vec![(0, 6), (6, 12)]
+    );
+    assert_eq!(word_wrap_segments("abc", 0), vec![(0, 3)]); // zero cols = 1 row
+
+    let segs = [(0, 6), (6, 12)];
+    assert_eq!(wrap_segment_index(&segs, 5), 0);
+    assert_eq!(wrap_segment_index(&segs, 6), 1);
+    assert_eq!(wrap_segment_index(&segs, 12), 1); // past the end → last
+}
+
+#[test]
+fn test_gj_gk_follow_word_wrapped_rows() {
+    // With 10 columns "hello worlds" wraps after "hello " (cols 0..6, 6..12).
+    let mut engine = engine_with_text("hello worlds\nab\n");
+    engine.settings.wrap = true;
+    engine.view_mut().viewport_cols = 10;
+    engine.view_mut().viewport_lines = 10;
+    engine.view_mut().cursor.col = 2;
+    engine.feed_keys("gj");
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 8 });
+    engine.feed_keys("gj");
+    assert_eq!(engine.view().cursor, Cursor { line: 1, col: 1 });
+    engine.feed_keys("gk");
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 7 });
+    engine.feed_keys("gk");
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 1 });
+    engine.feed_keys("g0");
+    assert_eq!(engine.view().cursor.col, 0);
+    engine.feed_keys("g$");
+    assert_eq!(engine.view().cursor.col, 5);
 }
 
 #[test]
@@ -13053,6 +14403,160 @@ fn test_open_folder_resets_cwd() {
     assert_eq!(engine.active_group().tabs.len(), 1);
 }
 
+#[test]
+fn test_open_directory_target_focuses_explorer_on_empty_buffer() {
+    let dir = std::env::temp_dir().join("vimcode_test_open_dir_target");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine.open_file_target(&paths::FileTarget::parse(&format!("{}/", dir.display())));
+    let expected = dir.canonicalize().unwrap_or(dir.clone());
+    assert_eq!(engine.cwd, expected);
+    assert_eq!(engine.workspace_root, Some(expected));
+    assert!(engine.explorer_focus_on_start);
+    assert_eq!(engine.file_path(), None);
+    assert_eq!(engine.active_buffer_state().display_name(), "[No Name]");
+
+    let mut engine = Engine::new();
+    engine.open_file_target(&paths::FileTarget::parse(&format!(
+        "{}/main.rs",
+        dir.display()
+    )));
+    assert!(!engine.explorer_focus_on_start);
+    assert!(engine.file_path().is_some());
+}
+
+#[test]
+fn test_cd_changes_cwd_and_keeps_buffers() {
+    let dir = std::env::temp_dir().join("vimcode_test_cd");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let file = dir.join("kept.txt");
+    std::fs::write(&file, "kept\n").unwrap();
+
+    let mut engine = Engine::new();
+    let original_cwd = engine.cwd.clone();
+    engine.open_file_in_tab(&file);
+    engine.execute_command(&format!("cd {}", dir.display()));
+    let expected = dir.canonicalize().unwrap();
+    assert_eq!(engine.cwd, expected);
+    assert!(engine.cwd_changed);
+    assert_eq!(engine.prev_cwd.as_ref(), Some(&original_cwd));
+    // Unlike opening a folder, the open buffers survive.
+    assert!(engine.file_path().is_some_and(|p| p.ends_with("kept.txt")));
+
+    engine.execute_command("cd sub");
+    assert_eq!(engine.cwd, expected.join("sub"));
+    engine.execute_command("pwd");
+    assert_eq!(engine.message, expected.join("sub").display().to_string());
+
+    engine.execute_command("cd missing");
+    assert!(engine.message.starts_with("E344"), "{}", engine.message);
+    assert_eq!(engine.cwd, expected.join("sub"));
+
+    engine.execute_command("cd -");
+    assert_eq!(engine.cwd, expected);
+    engine.execute_command(&format!("cd {}", original_cwd.display()));
+    assert_eq!(engine.cwd, original_cwd);
+}
+
+#[test]
+fn test_lcd_is_local_to_the_window() {
+    let dir = std::env::temp_dir().join("vimcode_test_lcd");
+    std::fs::create_dir_all(&dir).unwrap();
+    let expected = dir.canonicalize().unwrap();
+
+    let mut engine = Engine::new();
+    let global = engine.cwd.clone();
+    engine.execute_command(&format!("lcd {}", dir.display()));
+    assert_eq!(engine.cwd, global);
+    assert!(!engine.cwd_changed);
+    engine.execute_command("pwd");
+    assert_eq!(engine.message, expected.display().to_string());
+    // Relative paths typed in this window resolve against its directory.
+    assert_eq!(
+        engine.execute_command("e notes.txt"),
+        EngineAction::OpenFile(expected.join("notes.txt"))
+    );
+
+    // A split inherits the directory; a window's `:lcd` leaves others alone.
+    engine.execute_command("split");
+    assert_eq!(engine.effective_cwd(), expected.as_path());
+    engine.execute_command(&format!("lcd {}", global.display()));
+    engine.execute_command("wincmd w");
+    assert_eq!(engine.effective_cwd(), expected.as_path());
+}
+
+#[test]
+fn test_mksession_and_source_restore_layout() {
+    let dir = std::env::temp_dir().join("vimcode_test_named_session");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (a, b, c) = (dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt"));
+    std::fs::write(&a, "1\n2\n3\n4\n5\n6\n").unwrap();
+    std::fs::write(&b, "b\n").unwrap();
+    std::fs::write(&c, "c\n").unwrap();
+    let session = dir.join("work.json");
+    let _ = std::fs::remove_file(&session);
+
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&a);
+    engine.view_mut().cursor.line = 4;
+    engine.view_mut().folds = vec![FoldRegion { start: 1, end: 2 }];
+    engine.execute_command(&format!("vsplit {}", b.display()));
+    engine.execute_command(&format!("tabnew {}", c.display()));
+    engine.execute_command("tabprev");
+    engine.execute_command(&format!("mks {}", session.display()));
+    assert!(
+        engine.message.starts_with("Session saved"),
+        "{}",
+        engine.message
+    );
+    engine.execute_command(&format!("mksession {}", session.display()));
+    assert!(engine.message.starts_with("E189"), "{}", engine.message);
+
+    std::fs::remove_file(&c).unwrap();
+    let mut engine = Engine::new();
+    engine.execute_command(&format!("so {}", session.display()));
+    assert!(engine.message.contains("c.txt"), "{}", engine.message);
+    // The tab of the deleted file is skipped; the split is recreated.
+    assert_eq!(engine.active_group().tabs.len(), 1);
+    let tab = engine.active_tab();
+    assert_eq!(tab.layout.window_ids().len(), 2);
+    assert!(engine.file_path().is_some_and(|p| p.ends_with("b.txt")));
+    let window_a = tab.layout.window_ids().into_iter().find(|id| {
+        let buf = engine.windows[id].buffer_id;
+        engine
+            .buffer_manager
+            .get(buf)
+            .and_then(|s| s.file_path.as_ref())
+            .is_some_and(|p| p.ends_with("a.txt"))
+    });
+    let view = &engine.windows[&window_a.expect("a.txt window")].view;
+    assert_eq!(view.cursor.line, 4);
+    assert_eq!(view.folds, vec![FoldRegion { start: 1, end: 2 }]);
+}
+
+#[test]
+fn test_source_with_only_missing_files_keeps_cwd_and_layout() {
+    let dir = std::env::temp_dir().join("vimcode_test_named_session_missing");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("gone.txt");
+    std::fs::write(&file, "x\n").unwrap();
+
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&file);
+    let mut session = engine.named_session();
+    session.cwd = Some(dir.clone());
+    std::fs::remove_file(&file).unwrap();
+
+    let cwd = engine.cwd.clone();
+    let window = engine.active_window_id();
+    let missing = engine.restore_named_session(&session);
+    assert_eq!(missing.len(), 1);
+    assert_eq!(engine.cwd, cwd);
+    assert_eq!(engine.active_window_id(), window);
+}
+
 #[test]
 fn test_open_workspace_parses_json() {
     let dir = std::env::temp_dir().join("vimcode_test_workspace_json");
@@ -13714,6 +15218,63 @@ fn test_multi_cursor_undo() {
     );
 }
 
+#[test]
+fn test_multi_cursor_normal_x_deletes_at_every_cursor() {
+    let mut engine = engine_with_text("foo bar foo\nfoo\n");
+    engine.add_cursor_at_next_match();
+    engine.add_cursor_at_next_match();
+    assert_eq!(engine.view().extra_cursors.len(), 2);
+
+    engine.handle_key("x", Some('x'), false);
+    assert_eq!(engine.buffer().to_string(), "oo bar oo\noo\n");
+    // Cursors survive the edit and stay on the same text.
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 0 });
+    assert_eq!(
+        engine.view().extra_cursors,
+        vec![Cursor { line: 0, col: 7 }, Cursor { line: 1, col: 0 }]
+    );
+
+    // A count never reaches past a cursor's line end or into the next cursor.
+    engine.handle_key("9", Some('9'), false);
+    engine.handle_key("x", Some('x'), false);
+    assert_eq!(engine.buffer().to_string(), "\n\n");
+
+    // All deletions of one `x` undo together.
+    engine.handle_key("u", Some('u'), false);
+    assert_eq!(engine.buffer().to_string(), "oo bar oo\noo\n");
+}
+
+#[test]
+fn test_multi_cursor_word_motions_move_every_cursor() {
+    let mut engine = engine_with_text("foo one foo two\n");
+    engine.add_cursor_at_next_match();
+    assert_eq!(
+        engine.view().extra_cursors,
+        vec![Cursor { line: 0, col: 8 }]
+    );
+
+    engine.handle_key("w", Some('w'), false);
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 4 });
+    assert_eq!(
+        engine.view().extra_cursors,
+        vec![Cursor { line: 0, col: 12 }]
+    );
+
+    engine.handle_key("b", Some('b'), false);
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 0 });
+    assert_eq!(
+        engine.view().extra_cursors,
+        vec![Cursor { line: 0, col: 8 }]
+    );
+
+    // Cursors that collide are merged.
+    engine.handle_key("b", Some('b'), false);
+    engine.handle_key("b", Some('b'), false);
+    engine.handle_key("b", Some('b'), false);
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 0 });
+    assert!(engine.view().extra_cursors.is_empty());
+}
+
 #[test]
 fn test_add_cursor_keybinding_configurable() {
     // Verify that the default binding parses correctly
@@ -15325,6 +16886,7 @@ fn test_has_code_actions_on_line_with_actions() {
             title: "Extract function".to_string(),
             kind: Some("refactor.extract".to_string()),
             edit: None,
+            command: None,
         }],
     );
     e.lsp_code_actions.insert(path, line_map);
@@ -15363,23 +16925,111 @@ fn test_show_code_actions_popup_no_actions() {
 }
 
 #[test]
-fn test_show_code_actions_hover_opens_dialog() {
+fn test_open_code_action_menu_shows_popup() {
     let mut e = engine_with_text("hello\nworld\n");
     let actions = vec![
         lsp::CodeAction {
             title: "Quick fix".to_string(),
             kind: Some("quickfix".to_string()),
             edit: None,
+            command: None,
         },
         lsp::CodeAction {
             title: "Extract method".to_string(),
             kind: None,
             edit: None,
+            command: None,
         },
     ];
-    e.show_code_actions_hover(0, actions);
-    assert!(e.dialog.is_some());
-    assert_ne!(e.message, "No code actions available");
+    e.open_code_action_menu(actions);
+    assert_eq!(e.code_action_menu_idx, Some(0));
+    assert!(e.dialog.is_none());
+    assert_eq!(
+        e.code_action_menu_items(),
+        vec!["1. Quick fix (quickfix)", "2. Extract method"]
+    );
+}
+
+fn code_action_with_edit(title: &str, new_text: &str) -> lsp::CodeAction {
+    lsp::CodeAction {
+        title: title.to_string(),
+        kind: Some("quickfix".to_string()),
+        edit: Some(WorkspaceEdit {
+            changes: vec![lsp::FileEdit {
+                path: std::path::PathBuf::from("/tmp/vimcode_code_action_apply.rs"),
+                edits: vec![FormattingEdit {
+                    range: lsp::LspRange {
+                        start: lsp::LspPosition {
+                            line: 0,
+                            character: 0,
+                        },
+                        end: lsp::LspPosition {
+                            line: 0,
+                            character: 0,
+                        },
+                    },
+                    new_text: new_text.to_string(),
+                }],
+            }],
+        }),
+        command: None,
+    }
+}
+
+#[test]
+fn test_code_action_menu_navigate_and_apply() {
+    let mut e = engine_with_text("fn main() {}\n");
+    e.buffer_manager
+        .get_mut(e.active_window().buffer_id)
+        .unwrap()
+        .file_path = Some(std::path::PathBuf::from(
+        "/tmp/vimcode_code_action_apply.rs",
+    ));
+    e.open_code_action_menu(vec![
+        code_action_with_edit("Import A", "use a::A;\n"),
+        code_action_with_edit("Import B", "use b::B;\n"),
+    ]);
+    press_char(&mut e, 'j');
+    assert_eq!(e.code_action_menu_idx, Some(1));
+    press_special(&mut e, "Up");
+    press_special(&mut e, "Up");
+    assert_eq!(e.code_action_menu_idx, Some(1), "Up wraps to the last item");
+    press_special(&mut e, "Return");
+    assert_eq!(e.code_action_menu_idx, None);
+    assert_eq!(e.buffer().to_string(), "use b::B;\nfn main() {}\n");
+    assert_eq!(e.message, "Applied: Import B");
+    // Applied through the undo system.
+    press_char(&mut e, 'u');
+    assert_eq!(e.buffer().to_string(), "fn main() {}\n");
+}
+
+#[test]
+fn test_code_action_menu_escape_closes_without_applying() {
+    let mut e = engine_with_text("fn main() {}\n");
+    e.open_code_action_menu(vec![code_action_with_edit("Import A", "use a::A;\n")]);
+    press_special(&mut e, "Escape");
+    assert_eq!(e.code_action_menu_idx, None);
+    assert!(e.pending_code_action_choices.is_empty());
+    assert_eq!(e.buffer().to_string(), "fn main() {}\n");
+    assert_eq!(e.mode, Mode::Normal);
+}
+
+#[test]
+fn test_code_action_command_only_is_dispatchable() {
+    let mut e = engine_with_text("hello\n");
+    e.open_code_action_menu(vec![lsp::CodeAction {
+        title: "Run test".to_string(),
+        kind: None,
+        edit: None,
+        command: Some(lsp::LspCommand {
+            command: "rust-analyzer.runSingle".to_string(),
+            arguments: vec![],
+        }),
+    }]);
+    press_char(&mut e, '1');
+    assert_eq!(e.code_action_menu_idx, None);
+    // Not reported as "no edit"; with no server attached the command can't be sent.
+    assert_eq!(e.message, "Cannot run 'Run test': no LSP server");
 }
 
 #[test]
@@ -15398,6 +17048,7 @@ fn test_code_action_cache_cleared_on_edit() {
             title: "Fix".to_string(),
             kind: None,
             edit: None,
+            command: None,
         }],
     );
     e.lsp_code_actions.insert(path.clone(), line_map);
@@ -17016,6 +18667,66 @@ fn test_keybindings_picker_shows_user_remaps() {
     );
 }
 
+#[test]
+fn test_user_keymaps_leader_keys_and_timeout() {
+    let mut e = engine_with_text("one\ntwo\n");
+    e.settings.keymaps = vec![
+        "i jk <Esc>".to_string(),
+        "n <leader>l :set list<CR>".to_string(),
+        "n <leader>D dd".to_string(),
+    ];
+    e.rebuild_user_keymaps();
+    assert_eq!(
+        e.user_keymaps[1].action,
+        KeymapAction::Command("set list".to_string())
+    );
+
+    // Leader maps run ex commands or replay keys; unmatched leader
+    // sequences still reach the built-in handler.
+    send_keys(&mut e, " l");
+    assert!(e.settings.list);
+    send_keys(&mut e, " D");
+    assert_eq!(e.buffer().to_string(), "two\n");
+
+    // Insert-mode `jk` leaves Insert; `j` + other key types both.
+    send_keys(&mut e, "ija");
+    assert_eq!(e.buffer().to_string(), "jatwo\n");
+    send_keys(&mut e, "jk");
+    assert_eq!(e.mode, Mode::Normal);
+    assert_eq!(e.buffer().to_string(), "jatwo\n");
+
+    // A lone prefix is typed once `timeoutlen` passes.
+    e.settings.timeoutlen = 0;
+    send_keys(&mut e, "ij");
+    assert_eq!(e.buffer().to_string(), "jatwo\n");
+    assert!(e.tick_keymap_timeout().is_some());
+    assert_eq!(e.buffer().to_string(), "jjatwo\n");
+    assert_eq!(e.mode, Mode::Insert);
+    assert!(e.tick_keymap_timeout().is_none());
+
+    e.execute_command("inoremap kj <Esc>");
+    assert!(e.settings.keymaps.contains(&"i kj <Esc>".to_string()));
+}
+
+#[test]
+fn test_user_keymaps_fall_back_to_longest_match() {
+    let mut e = engine_with_text("one\ntwo\nthree\n");
+    e.settings.keymaps = vec!["n ab dd".to_string(), "n abc yy".to_string()];
+    e.rebuild_user_keymaps();
+
+    // `ab` matched before `x` broke the `abc` prefix: run `ab`, then `x`.
+    send_keys(&mut e, "abx");
+    assert_eq!(e.buffer().to_string(), "wo\nthree\n");
+
+    // A mapping resolved by the timeout hands its action to the caller.
+    let mut e = engine_with_text("one\n");
+    e.settings.keymaps = vec!["n ab :q<CR>".to_string(), "n abc yy".to_string()];
+    e.rebuild_user_keymaps();
+    e.settings.timeoutlen = 0;
+    send_keys(&mut e, "ab");
+    assert_eq!(e.tick_keymap_timeout(), Some(EngineAction::Quit));
+}
+
 #[test]
 fn test_keybindings_picker_filterable() {
     let mut e = engine_with_text("hello");
@@ -17286,6 +18997,126 @@ fn test_effective_shift_width_falls_back_to_setting() {
     assert_eq!(e.effective_shift_width(), 3);
 }
 
+fn buffer_state_for_file(text: &str, name: &str) -> BufferState {
+    let mut buf = crate::core::buffer::Buffer::new(crate::core::buffer::BufferId(999));
+    buf.insert(0, text);
+    let dir = std::env::temp_dir();
+    BufferState::with_file(buf, dir.join(name), &dir)
+}
+
+#[test]
+fn test_shebang_and_modeline_detect_filetype() {
+    let state = buffer_state_for_file(
+        "#!/usr/bin/env python3\nprint(1)\n# vim: ts=2 sw=2 et tw=60\n",
+        "vimcode_test_script",
+    );
+    assert_eq!(state.lsp_language_id.as_deref(), Some("python"));
+    assert!(
+        state.syntax.is_some(),
+        "python highlighting from the shebang"
+    );
+
+    let settings = Settings::default();
+    assert_eq!(state.tabstop(&settings), 2);
+    assert_eq!(state.shift_width(&settings), 2);
+    assert_eq!(state.textwidth(&settings), 60);
+    assert!(state.expand_tab(&settings));
+
+    // A modeline filetype beats the extension; the shebang does not.
+    let state = buffer_state_for_file("#!/bin/sh\n/* vim: set ft=c : */\n", "vimcode_test.txt");
+    assert_eq!(state.lsp_language_id.as_deref(), Some("c"));
+    let state = buffer_state_for_file("#!/bin/sh\nfn main() {}\n", "vimcode_test.rs");
+    assert_eq!(state.lsp_language_id.as_deref(), Some("rust"));
+}
+
+#[test]
+fn test_modeline_options_are_buffer_local() {
+    let mut e = engine_with_text("hello\n");
+    e.settings.tabstop = 8;
+    e.settings.expand_tab = false;
+    let state = buffer_state_for_file("x\n# vim: ts=3 et\n", "vimcode_test_local");
+    let id = e.active_buffer_id();
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    assert_eq!(e.effective_tabstop(), 3);
+    assert!(e.effective_expand_tab());
+    assert_eq!(e.settings.tabstop, 8, "global tabstop untouched");
+
+    // Tab in insert mode uses the buffer's tabstop.
+    e.view_mut().cursor.line = 0;
+    e.view_mut().cursor.col = 0;
+    e.handle_key("i", Some('i'), false);
+    e.handle_key("Tab", None, false);
+    assert!(e.buffer().to_string().starts_with("   x"));
+}
+
+/// A `BufferState` for `dir/file` (written to disk) under an `.editorconfig`.
+fn buffer_state_with_editorconfig(dir: &str, config: &str, file: &str, text: &str) -> BufferState {
+    let dir = std::env::temp_dir().join(dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(".editorconfig"), config).unwrap();
+    let path = dir.join(file);
+    std::fs::write(&path, text).unwrap();
+    let mut buf = crate::core::buffer::Buffer::new(crate::core::buffer::BufferId(999));
+    buf.insert(0, text);
+    BufferState::with_file(buf, path, &dir)
+}
+
+#[test]
+fn test_editorconfig_sets_buffer_indent() {
+    let config =
+        "root = true\n[*]\nindent_style = tab\n[*.py]\nindent_style = space\nindent_size = 2\n";
+    let mut e = engine_with_text("hello\n");
+    e.settings.expand_tab = false;
+    e.settings.shift_width = 8;
+    let state = buffer_state_with_editorconfig("vimcode_test_ec_indent", config, "a.py", "x\n");
+    let id = e.active_buffer_id();
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    assert!(e.effective_expand_tab());
+    assert_eq!(e.effective_shift_width(), 2);
+
+    // A modeline beats .editorconfig.
+    let state = buffer_state_with_editorconfig(
+        "vimcode_test_ec_modeline",
+        config,
+        "b.py",
+        "x\n# vim: sw=6 noet\n",
+    );
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    assert!(!e.effective_expand_tab());
+    assert_eq!(e.effective_shift_width(), 6);
+
+    // Keys set by the project .vimcode.json beat .editorconfig.
+    let state = buffer_state_with_editorconfig("vimcode_test_ec_project", config, "c.py", "x\n");
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    e.settings.project_keys = vec!["expand_tab".to_string()];
+    assert!(!e.effective_expand_tab());
+    assert_eq!(e.effective_shift_width(), 2);
+}
+
+#[test]
+fn test_editorconfig_save_rules() {
+    let config = "root = true\n[*.txt]\ntrim_trailing_whitespace = true\ninsert_final_newline = true\nend_of_line = crlf\n";
+    let mut e = engine_with_text("hello\n");
+    e.settings.strip_whitespace_on_save = false;
+    let state = buffer_state_with_editorconfig("vimcode_test_ec_save", config, "a.txt", "a  \nb");
+    let path = state.file_path.clone().unwrap();
+    let id = e.active_buffer_id();
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    e.save_with_format(false).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\nb\r\n");
+
+    // insert_final_newline = true only adds the final line break; blank
+    // lines at the end stay unless fixendofline is on.
+    e.settings.insert_final_newline = false;
+    let state =
+        buffer_state_with_editorconfig("vimcode_test_ec_save_eol", config, "b.txt", "a\n\n\n");
+    let path = state.file_path.clone().unwrap();
+    *e.buffer_manager.get_mut(id).unwrap() = state;
+    e.save_with_format(false).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\n\r\n\r\n");
+}
+
 #[test]
 fn test_smart_indent_uses_detected_width() {
     // File with 2-space indentation — smart indent should use 2 even if setting is 4
@@ -17608,6 +19439,7 @@ fn test_scoped_symbol_filtering() {
             path: None,
             line: 10,
             character: 0,
+            end_line: 10,
             children: Vec::new(),
         },
         crate::core::lsp::SymbolInfo {
@@ -17618,6 +19450,7 @@ fn test_scoped_symbol_filtering() {
             path: None,
             line: 5,
             character: 0,
+            end_line: 5,
             children: Vec::new(),
         },
         crate::core::lsp::SymbolInfo {
@@ -17628,6 +19461,7 @@ fn test_scoped_symbol_filtering() {
             path: None,
             line: 1,
             character: 0,
+            end_line: 1,
             children: Vec::new(),
         },
     ];
@@ -17656,6 +19490,7 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
             path: None,
             line: 10,
             character: 0,
+            end_line: 10,
             children: vec![
                 SymbolInfo {
                     name: "new".to_string(),
@@ -17665,6 +19500,7 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
                     path: None,
                     line: 20,
                     character: 0,
+                    end_line: 20,
                     children: Vec::new(),
                 },
                 SymbolInfo {
@@ -17675,6 +19511,7 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
                     path: None,
                     line: 30,
                     character: 0,
+                    end_line: 30,
                     children: Vec::new(),
                 },
             ],
@@ -17687,6 +19524,7 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
             path: None,
             line: 100,
             character: 0,
+            end_line: 100,
             children: Vec::new(),
         },
         SymbolInfo {
@@ -17697,6 +19535,7 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
             path: None,
             line: 50,
             character: 0,
+            end_line: 50,
             children: vec![SymbolInfo {
                 name: "load".to_string(),
                 kind: SymbolKind::Method,
@@ -17705,12 +19544,71 @@ fn make_hierarchical_symbols() -> Vec<crate::core::lsp::SymbolInfo> {
                 path: None,
                 line: 60,
                 character: 0,
+                end_line: 60,
                 children: Vec::new(),
             }],
         },
     ]
 }
 
+#[test]
+fn test_winbar_symbols_enclosing_cursor() {
+    let mut e = engine_with_text(&"x\n".repeat(120));
+    let bid = e.active_buffer_id();
+    let mut symbols = make_hierarchical_symbols();
+    symbols[0].end_line = 40;
+    symbols[0].children[0].end_line = 25;
+    symbols[0].children[1].end_line = 39;
+    symbols[1].end_line = 110;
+    e.buffer_manager.get_mut(bid).unwrap().document_symbols = symbols;
+    let wid = e.active_window_id();
+
+    e.view_mut().cursor.line = 32;
+    assert_eq!(e.winbar_symbols(wid), vec!["Engine", "handle_key"]);
+    e.view_mut().cursor.line = 12;
+    assert_eq!(e.winbar_symbols(wid), vec!["Engine"]);
+    e.view_mut().cursor.line = 105;
+    assert_eq!(e.winbar_symbols(wid), vec!["main"]);
+    // Outside every symbol (and in non-LSP buffers) the chain is empty.
+    e.view_mut().cursor.line = 5;
+    assert!(e.winbar_symbols(wid).is_empty());
+
+    assert!(e.settings.parse_set_option("winbar").is_ok());
+    assert!(e.settings.winbar);
+}
+
+#[test]
+fn test_sticky_scroll_lines_and_jump() {
+    let mut e = engine_with_text(&"x\n".repeat(120));
+    let bid = e.active_buffer_id();
+    let mut symbols = make_hierarchical_symbols();
+    symbols[0].end_line = 40;
+    symbols[0].children[0].end_line = 25;
+    symbols[0].children[1].end_line = 39;
+    e.buffer_manager.get_mut(bid).unwrap().document_symbols = symbols;
+    let wid = e.active_window_id();
+
+    e.view_mut().scroll_top = 32;
+    assert!(e.sticky_scroll_lines(wid, 20).is_empty(), "off by default");
+    assert!(e.settings.parse_set_option("stickyscroll").is_ok());
+    assert_eq!(e.sticky_scroll_lines(wid, 20), vec![10, 30]);
+    // Short windows keep only the innermost headers.
+    assert_eq!(e.sticky_scroll_lines(wid, 2), vec![30]);
+    // A header still on screen isn't pinned.
+    e.view_mut().scroll_top = 10;
+    assert!(e.sticky_scroll_lines(wid, 20).is_empty());
+    // Nor is a scope whose body ends under the pinned rows.
+    e.view_mut().scroll_top = 38;
+    assert_eq!(e.sticky_scroll_lines(wid, 20), vec![10]);
+
+    // Clicking `handle_key` shows it right below its pinned parent.
+    e.view_mut().scroll_top = 32;
+    e.sticky_scroll_jump(wid, 30);
+    assert_eq!(e.view().cursor.line, 30);
+    assert_eq!(e.view().scroll_top, 29);
+    assert_eq!(e.sticky_scroll_lines(wid, 20), vec![10]);
+}
+
 #[test]
 fn test_symbol_tree_populates_with_depth() {
     let mut e = engine_with_text("hello");
@@ -17990,6 +19888,7 @@ fn test_symbol_tree_from_flat_container_field() {
             path: None,
             line: 10,
             character: 0,
+            end_line: 10,
             children: Vec::new(), // No children — flat format
         },
         SymbolInfo {
@@ -18000,6 +19899,7 @@ fn test_symbol_tree_from_flat_container_field() {
             path: None,
             line: 30,
             character: 0,
+            end_line: 30,
             children: Vec::new(),
         },
         SymbolInfo {
@@ -18010,6 +19910,7 @@ fn test_symbol_tree_from_flat_container_field() {
             path: None,
             line: 20,
             character: 0,
+            end_line: 20,
             children: Vec::new(),
         },
         SymbolInfo {
@@ -18020,6 +19921,7 @@ fn test_symbol_tree_from_flat_container_field() {
             path: None,
             line: 100,
             character: 0,
+            end_line: 100,
             children: Vec::new(),
         },
     ];
@@ -18077,6 +19979,7 @@ fn test_symbol_tree_synthetic_container() {
             path: None,
             line: 10,
             character: 0,
+            end_line: 10,
             children: Vec::new(),
         },
         SymbolInfo {
@@ -18087,6 +19990,7 @@ fn test_symbol_tree_synthetic_container() {
             path: None,
             line: 20,
             character: 0,
+            end_line: 20,
             children: Vec::new(),
         },
     ];
@@ -18357,42 +20261,271 @@ fn test_status_action_toggle_panel() {
 }
 
 #[test]
-fn test_status_action_toggle_menu_bar() {
+fn test_terminal_panel_command_opens_panel() {
     let mut e = Engine::new();
-    assert!(!e.menu_bar_visible);
-    let result = e.handle_status_action(&StatusAction::ToggleMenuBar);
-    assert!(result.is_none());
-    assert!(e.menu_bar_visible);
-    // Toggle again
-    let result = e.handle_status_action(&StatusAction::ToggleMenuBar);
-    assert!(result.is_none());
-    assert!(!e.menu_bar_visible);
+    // `:terminal` opens a shell in a split; the bottom panel has its own command.
+    assert_eq!(
+        e.execute_command("TerminalPanel"),
+        EngineAction::OpenTerminal
+    );
+    assert!(e.terminal_buffers.is_empty());
+    assert_eq!(e.mode_str(), "NORMAL");
+}
+
+/// Poll a `:terminal` window until its buffer shows `line`.
+fn wait_for_terminal_line(engine: &mut Engine, line: &str) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    loop {
+        engine.poll_terminal_windows();
+        if engine
+            .buffer()
+            .to_string()
+            .lines()
+            .any(|l| l.trim_end() == line)
+        {
+            return;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "terminal never showed {line:?}: {:?}",
+            engine.buffer().to_string()
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
 }
 
 #[test]
-fn test_status_action_toggle_sidebar_returns_engine_action() {
+fn test_open_terminal_window_splits_into_read_only_buffer() {
     let mut e = Engine::new();
-    let result = e.handle_status_action(&StatusAction::ToggleSidebar);
-    assert_eq!(result, Some(EngineAction::ToggleSidebar));
+    let windows = e.active_tab().layout.window_ids().len();
+    e.execute_command("terminal");
+    assert_eq!(e.active_tab().layout.window_ids().len(), windows + 1);
+    let id = e.active_buffer_id();
+    assert!(e.terminal_buffers.contains_key(&id));
+    assert!(e.terminal_insert);
+    let state = e.active_buffer_state();
+    assert!(state.read_only);
+    assert!(state
+        .scratch_name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("term://")));
+}
+
+#[test]
+fn test_terminal_window_forwards_keys_to_shell() {
+    let mut e = Engine::new();
+    e.execute_command("terminal");
+    for ch in "echo vimcode-$((6*7))".chars() {
+        press_char(&mut e, ch);
+    }
+    press_special(&mut e, "Return");
+    wait_for_terminal_line(&mut e, "vimcode-42");
+
+    // Ctrl-\ Ctrl-N returns to Normal mode; `i` resumes typing into the shell.
+    e.handle_key("backslash", None, true);
+    press_ctrl(&mut e, 'n');
+    assert!(!e.terminal_insert);
+    assert_eq!(e.mode, Mode::Normal);
+    press_char(&mut e, 'i');
+    assert!(e.terminal_insert);
+    assert_eq!(e.mode, Mode::Normal);
 }
 
 #[test]
-fn test_status_action_existing_actions_return_none() {
+fn test_closing_terminal_window_kills_shell() {
     let mut e = Engine::new();
-    // Existing actions should return None (handled internally by engine)
-    assert!(e.handle_status_action(&StatusAction::GoToLine).is_none());
-    assert!(e
-        .handle_status_action(&StatusAction::ChangeLanguage)
-        .is_none());
-    assert!(e
-        .handle_status_action(&StatusAction::ChangeEncoding)
-        .is_none());
+    e.execute_command("terminal");
+    let id = e.active_buffer_id();
+    assert!(e.close_window());
+    assert!(e.terminal_buffers.is_empty());
+    assert!(e.buffer_manager.get(id).is_none());
 }
 
-// ── Notification system tests ───────────────────────────────────────────
-
-#[test]
-fn test_notify_creates_notification() {
+/// Poll until every background `:!` command has been applied.
+fn wait_for_shell_commands(engine: &mut Engine) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while engine.shell_commands_pending() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "shell command timed out"
+        );
+        engine.poll_shell_commands();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn test_bang_command_short_output_in_message() {
+    let mut e = Engine::new();
+    e.execute_command("!echo hello");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.message, "hello");
+
+    e.execute_command("!echo oops >&2; exit 3");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.message, "oops (shell returned 3)");
+}
+
+#[test]
+fn test_bang_command_long_output_opens_scratch_split() {
+    let mut e = Engine::new();
+    let windows = e.active_tab().layout.window_ids().len();
+    e.execute_command("!printf 'one\\ntwo\\nthree\\n'");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.active_tab().layout.window_ids().len(), windows + 1);
+    assert_eq!(e.buffer().to_string(), "one\ntwo\nthree");
+    let state = e.active_buffer_state();
+    assert!(state.read_only);
+    assert_eq!(
+        state.scratch_name.as_deref(),
+        Some("!printf 'one\\ntwo\\nthree\\n'")
+    );
+}
+
+#[test]
+fn test_read_bang_inserts_output_below_cursor() {
+    let mut e = engine_with_text("first\nlast\n");
+    run_command(&mut e, "r !printf 'a\\nb\\n'");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.buffer().to_string(), "first\na\nb\nlast\n");
+    assert_eq!(e.view().cursor.line, 1);
+    e.undo();
+    assert_eq!(e.buffer().to_string(), "first\nlast\n");
+}
+
+#[test]
+fn test_range_filter_is_one_undo_step() {
+    let mut e = engine_with_text("c\nb\na\nkeep\n");
+    e.execute_command("1,3!sort");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.buffer().to_string(), "a\nb\nc\nkeep\n");
+    assert_eq!(e.message, "3 lines filtered");
+    e.undo();
+    assert_eq!(e.buffer().to_string(), "c\nb\na\nkeep\n");
+}
+
+#[test]
+fn test_visual_range_filter() {
+    let mut e = engine_with_text("keep\nzz\nyy\n");
+    send_keys(&mut e, "jVj");
+    e.execute_command("'<,'>!sort");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.buffer().to_string(), "keep\nyy\nzz\n");
+}
+
+#[test]
+fn test_make_loads_errors_into_quickfix() {
+    let dir = std::env::temp_dir().join("vimcode_test_make_quickfix");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/lib.rs"), "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+    std::fs::write(
+        dir.join("build.sh"),
+        "echo '   Compiling demo v0.1.0'\n\
+         echo 'error[E0425]: cannot find value `x`' >&2\n\
+         echo ' --> src/lib.rs:2:4' >&2\n\
+         echo 'warning: unused import' >&2\n\
+         echo '  --> src/lib.rs:3:1' >&2\n\
+         exit 101\n",
+    )
+    .unwrap();
+    let mut e = Engine::new();
+    e.cwd = dir.clone();
+    e.settings.makeprg = "sh build.sh".to_string();
+    e.execute_command("make");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.message, "Build failed (exit 101): 1 error, 1 warning");
+    assert!(e.quickfix_open);
+    assert_eq!(e.quickfix_items.len(), 2);
+    assert_eq!(
+        e.quickfix_items[0].line_text,
+        "[E] E0425: cannot find value `x`"
+    );
+    assert_eq!(e.quickfix_items[0].file, dir.join("src/lib.rs"));
+
+    e.execute_command("cc 1");
+    assert_eq!(e.view().cursor.line, 1);
+    assert_eq!(e.view().cursor.col, 3);
+    e.execute_command("cnext");
+    assert_eq!(e.view().cursor.line, 2);
+    run_command(&mut e, "cprev");
+    assert_eq!(e.view().cursor.line, 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_make_success_without_messages() {
+    let mut e = Engine::new();
+    e.quickfix_items = vec![make_qf_item("stale.rs")];
+    e.quickfix_open = true;
+    e.settings.makeprg = "echo done".to_string();
+    e.execute_command("make");
+    assert!(e.shell_commands_pending());
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.message, "Build succeeded");
+    assert!(!e.quickfix_open);
+    assert!(e.quickfix_items.is_empty());
+}
+
+#[test]
+fn test_filter_output_discarded_when_buffer_changed() {
+    let mut e = engine_with_text("c\nb\na\n");
+    e.execute_command("2,3!sleep 0.2; sort");
+    send_keys(&mut e, "ggOnew<Esc>");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.buffer().to_string(), "new\nc\nb\na\n");
+    assert_eq!(
+        e.message,
+        "Buffer changed while the shell command ran; output discarded"
+    );
+}
+
+#[test]
+fn test_failed_filter_keeps_text() {
+    let mut e = engine_with_text("one\ntwo\n");
+    e.execute_command("%!echo bad >&2; exit 1");
+    wait_for_shell_commands(&mut e);
+    assert_eq!(e.buffer().to_string(), "one\ntwo\n");
+    assert_eq!(e.message, "bad (shell returned 1)");
+}
+
+#[test]
+fn test_status_action_toggle_menu_bar() {
+    let mut e = Engine::new();
+    assert!(!e.menu_bar_visible);
+    let result = e.handle_status_action(&StatusAction::ToggleMenuBar);
+    assert!(result.is_none());
+    assert!(e.menu_bar_visible);
+    // Toggle again
+    let result = e.handle_status_action(&StatusAction::ToggleMenuBar);
+    assert!(result.is_none());
+    assert!(!e.menu_bar_visible);
+}
+
+#[test]
+fn test_status_action_toggle_sidebar_returns_engine_action() {
+    let mut e = Engine::new();
+    let result = e.handle_status_action(&StatusAction::ToggleSidebar);
+    assert_eq!(result, Some(EngineAction::ToggleSidebar));
+}
+
+#[test]
+fn test_status_action_existing_actions_return_none() {
+    let mut e = Engine::new();
+    // Existing actions should return None (handled internally by engine)
+    assert!(e.handle_status_action(&StatusAction::GoToLine).is_none());
+    assert!(e
+        .handle_status_action(&StatusAction::ChangeLanguage)
+        .is_none());
+    assert!(e
+        .handle_status_action(&StatusAction::ChangeEncoding)
+        .is_none());
+}
+
+// ── Notification system tests ───────────────────────────────────────────
+
+#[test]
+fn test_notify_creates_notification() {
     let mut e = Engine::new();
     assert!(e.notifications.is_empty());
     let id = e.notify(NotificationKind::ProjectSearch, "Searching…");
@@ -18583,902 +20716,1472 @@ fn test_confirm_move_file_different_dir_shows_dialog() {
     assert!(engine.dialog.is_some());
 }
 
-// ── o / O with CRLF and lone-CR line endings ────────────────────────────
+// ── :set fileformat ─────────────────────────────────────────────────────
 
 #[test]
-fn test_o_with_crlf_line_endings() {
-    // Buffer uses CRLF line endings — `o` must create a new line, not
-    // get absorbed by the \r\n pair.
-    let mut engine = engine_with_text("AB\r\nCD\r\n");
-    engine.view_mut().cursor.line = 0;
-    engine.view_mut().cursor.col = 0;
+fn test_set_fileformat_converts_in_one_undo_step() {
+    let mut engine = engine_with_text("a\r\nb\nc\r\n");
+    engine.execute_command("set ff=unix");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
+    assert!(engine.dirty());
+    engine.execute_command("set ff?");
+    assert_eq!(engine.message, "fileformat=unix");
 
-    press_char(&mut engine, 'o');
-    assert_eq!(engine.mode, Mode::Insert);
-    assert_eq!(engine.view().cursor.line, 1);
-    // A new empty line should exist between AB and CD.
-    assert_eq!(engine.buffer().len_lines(), 3);
-    let line0: String = engine.buffer().content.line(0).chars().collect();
-    let line1: String = engine.buffer().content.line(1).chars().collect();
-    let line2: String = engine.buffer().content.line(2).chars().collect();
-    assert!(
-        line0.starts_with("AB"),
-        "line0 should start with AB, got: {:?}",
-        line0
-    );
-    assert!(
-        !line1.starts_with("CD"),
-        "line1 should be the new blank line, got: {:?}",
-        line1
-    );
-    assert!(
-        line2.starts_with("CD"),
-        "line2 should start with CD, got: {:?}",
-        line2
-    );
+    engine.execute_command("set fileformat=dos");
+    assert_eq!(engine.buffer().to_string(), "a\r\nb\r\nc\r\n");
+    assert_eq!(engine.active_buffer_state().line_ending, LineEnding::Crlf);
+
+    engine.undo();
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
+    engine.undo();
+    assert_eq!(engine.buffer().to_string(), "a\r\nb\nc\r\n");
+
+    engine.execute_command("set ff=mac");
+    assert!(engine.message.starts_with("E474"));
 }
 
 #[test]
-fn test_o_with_lone_cr_line_endings() {
-    // Buffer uses lone \r line endings (classic Mac) — `o` must still
-    // create a new line, not join lines.
-    let mut engine = engine_with_text("AB\rCD\r");
-    engine.view_mut().cursor.line = 0;
-    engine.view_mut().cursor.col = 0;
+fn test_dos_buffer_saves_added_lines_as_crlf() {
+    let path = std::env::temp_dir().join("vimcode_test_ff_dos.txt");
+    std::fs::write(&path, "a\r\nb\r\n").unwrap();
+    let mut buf = crate::core::buffer::Buffer::new(crate::core::buffer::BufferId(999));
+    buf.insert(0, "a\r\nb\r\n");
+    let mut state = BufferState::with_file(buf, path.clone(), &std::env::temp_dir());
+    assert_eq!(state.line_ending, LineEnding::Crlf);
+    state.buffer.insert(state.buffer.len_chars(), "c\n");
+    state.save(&Settings::default()).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\r\nb\r\nc\r\n");
+    let _ = std::fs::remove_file(&path);
+}
 
-    press_char(&mut engine, 'o');
-    assert_eq!(engine.mode, Mode::Insert);
-    assert_eq!(engine.view().cursor.line, 1);
-    // Should now have 3 lines: AB, (new blank), CD.
-    assert!(
-        engine.buffer().len_lines() >= 3,
-        "expected >= 3 lines, got {}",
-        engine.buffer().len_lines()
-    );
-    let full: String = engine.buffer().content.chars().collect();
-    // The new line must be a separate line — AB and CD must NOT be joined.
-    assert!(
-        !full.contains("ABCD"),
-        "lines should not be joined, got: {:?}",
-        full
-    );
+// ── File encodings ──────────────────────────────────────────────────────
+
+#[test]
+fn test_latin1_file_round_trips_and_converts() {
+    let path = std::env::temp_dir().join("vimcode_test_enc_latin1.txt");
+    std::fs::write(&path, b"caf\xE9\n").unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    assert_eq!(engine.buffer().to_string(), "caf\u{e9}\n");
+    assert_eq!(engine.active_buffer_state().encoding, FileEncoding::Latin1);
+    engine.execute_command("set fenc?");
+    assert_eq!(engine.message, "fileencoding=latin1");
+
+    engine.save().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"caf\xE9\n");
+
+    engine.execute_command("set fenc=utf-8");
+    assert!(engine.dirty());
+    engine.save().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), "caf\u{e9}\n".as_bytes());
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_o_with_crlf_preserves_content() {
-    // Pressing `o` on the first line of a CRLF file should not corrupt
-    // the second line's content.
-    let mut engine = engine_with_text("hello\r\nworld\r\n");
-    engine.view_mut().cursor.line = 0;
+fn test_edit_with_forced_encoding() {
+    let path = std::env::temp_dir().join("vimcode_test_enc_forced.txt");
+    std::fs::write(&path, "\u{e9}\n").unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    assert_eq!(engine.buffer().to_string(), "\u{e9}\n");
 
-    press_char(&mut engine, 'o');
-    press_special(&mut engine, "Escape");
+    engine.execute_command("e ++enc=latin1");
+    assert_eq!(engine.active_buffer_state().encoding, FileEncoding::Latin1);
+    assert_eq!(engine.buffer().to_string(), "\u{c3}\u{a9}\n");
 
-    let text = engine.buffer().to_string();
-    assert!(
-        text.contains("hello") && text.contains("world"),
-        "both lines should be preserved, got: {:?}",
-        text
-    );
-    // There should be exactly 3 non-empty content lines (hello, new, world).
-    let content_lines: Vec<&str> = text.lines().collect();
-    assert!(
-        content_lines.len() >= 3,
-        "expected >= 3 lines, got {}: {:?}",
-        content_lines.len(),
-        content_lines
-    );
+    engine.execute_command("e ++enc=ebcdic");
+    assert!(engine.message.starts_with("E474"));
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_o_with_crlf_indented_yaml() {
-    // Simulate YAML with CRLF — `o` on an indented line should create
-    // a new indented line below, not join with the next line.
-    let mut engine = engine_with_text("steps:\r\n  - script: |\r\n    echo hi\r\n");
-    engine.settings.auto_indent = true;
-    engine.view_mut().cursor.line = 2; // on "    echo hi"
+fn test_forced_encoding_reports_illegal_bytes() {
+    let path = std::env::temp_dir().join("vimcode_test_enc_illegal.txt");
+    std::fs::write(&path, b"ok\ncaf\xE9\n").unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    assert!(!engine.message.contains("ILLEGAL BYTE"));
 
-    press_char(&mut engine, 'o');
-    assert_eq!(engine.mode, Mode::Insert);
-    assert_eq!(engine.view().cursor.line, 3);
-    // The new line should have the same indent as "    echo hi" (4 spaces).
-    assert_eq!(engine.view().cursor.col, 4);
+    engine.execute_command("e ++enc=utf-8");
+    assert_eq!(engine.buffer().to_string(), "ok\ncaf\u{FFFD}\n");
     assert!(
-        engine.buffer().len_lines() >= 4,
-        "expected >= 4 lines, got {}",
-        engine.buffer().len_lines()
+        engine.message.ends_with("[ILLEGAL BYTE in line 2]"),
+        "{}",
+        engine.message
     );
+
+    engine.execute_command("e ++enc=latin1");
+    assert_eq!(engine.active_buffer_state().illegal_byte_line, None);
+    assert!(!engine.message.contains("ILLEGAL BYTE"));
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_status_line_above_terminal_setting_toggle() {
-    let mut engine = engine_with_text("hello\n");
-    // Default: status_line_above_terminal is true
-    assert!(engine.settings.status_line_above_terminal);
-
-    // Disable via :set
-    engine.execute_command("set nostatuslineaboveterminal");
-    assert!(!engine.settings.status_line_above_terminal);
+fn test_utf16_file_keeps_encoding_on_save() {
+    let path = std::env::temp_dir().join("vimcode_test_enc_utf16.txt");
+    let bytes = FileEncoding::Utf16Le { bom: true }.encode("hi\n").unwrap();
+    std::fs::write(&path, &bytes).unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    assert_eq!(engine.buffer().to_string(), "hi\n");
+    engine.insert_with_undo(0, "oh ");
+    engine.save().unwrap();
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        FileEncoding::Utf16Le { bom: true }
+            .encode("oh hi\n")
+            .unwrap()
+    );
+    let _ = std::fs::remove_file(&path);
+}
 
-    // Re-enable via abbreviation
-    engine.execute_command("set slat");
-    assert!(engine.settings.status_line_above_terminal);
+#[test]
+fn test_utf16_file_without_bom_saves_byte_identical() {
+    let path = std::env::temp_dir().join("vimcode_test_enc_utf16_nobom.txt");
+    let bytes = b"o\0k\0\n\0";
+    std::fs::write(&path, bytes).unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    assert_eq!(engine.buffer().to_string(), "ok\n");
+    engine.save().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+    let _ = std::fs::remove_file(&path);
+}
 
-    // Disable via abbreviation
-    engine.execute_command("set noslat");
-    assert!(!engine.settings.status_line_above_terminal);
+// ── Atomic saves and backups ────────────────────────────────────────────
 
-    // Query via :set slat?
-    engine.execute_command("set slat?");
-    assert_eq!(engine.message, "nostatuslineaboveterminal");
+#[test]
+fn test_save_keeps_backup_when_enabled() {
+    let dir = std::env::temp_dir().join("vimcode_test_backup");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    std::fs::write(&path, "v1\n").unwrap();
+    let mut engine = Engine::new();
+    engine.open_file_in_tab(&path);
+    engine.execute_command("set backup");
+    engine.insert_with_undo(0, "v2 ");
+    engine.save().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "v2 v1\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("notes.txt~")).unwrap(),
+        "v1\n"
+    );
+    // Only the file and its backup: no temporary files left behind.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_multi_group_window_rects_cover_all_groups() {
-    use crate::core::window::{SplitDirection, WindowRect};
+fn test_write_bang_overrides_read_only_file() {
+    let path = std::env::temp_dir().join("vimcode_test_readonly_save.txt");
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&path, "old\n").unwrap();
+    let mut perms = std::fs::metadata(&path).unwrap().permissions();
+    perms.set_readonly(true);
+    std::fs::set_permissions(&path, perms).unwrap();
 
     let mut engine = Engine::new();
-    // Create a second editor group via vertical split
-    engine.open_editor_group(SplitDirection::Vertical);
-    assert_eq!(engine.group_layout.leaf_count(), 2);
+    engine.open_file_in_tab(&path);
+    engine.execute_command("set noreadonly");
+    engine.insert_with_undo(0, "new ");
+    assert!(engine.save().is_err());
+    assert!(engine.message.contains("read-only"), "{}", engine.message);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "old\n");
+
+    engine.execute_command("w!");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "new old\n");
+    assert!(!engine.dirty());
+    let _ = std::fs::remove_file(&path);
+}
 
-    // Simulate realistic editor bounds (like Win-GUI would pass)
-    let tab_bar_height = 30.0;
-    let editor_bounds = WindowRect::new(200.0, 40.0, 1600.0, 900.0);
-    let (window_rects, _dividers) =
-        engine.calculate_group_window_rects(editor_bounds, tab_bar_height);
+// ── readonly / modifiable ───────────────────────────────────────────────
 
-    // Every group must have at least one window in the rects
-    let group_ids = engine.group_layout.group_ids();
-    assert_eq!(group_ids.len(), 2);
-    for &gid in &group_ids {
-        let group = engine.editor_groups.get(&gid).expect("group exists");
-        let wids = group.active_tab().layout.window_ids();
-        let matching: Vec<_> = window_rects
-            .iter()
-            .filter(|(wid, _)| wids.contains(wid))
-            .collect();
-        assert!(
-            !matching.is_empty(),
-            "Group {:?} has no matching window_rects. wids={:?}, all_rects={:?}",
-            gid,
-            wids,
-            window_rects.iter().map(|(w, _)| w).collect::<Vec<_>>()
-        );
-        // All matching rects should have non-zero dimensions
-        for (wid, wr) in &matching {
-            assert!(
-                wr.width > 0.0 && wr.height > 0.0,
-                "Window {:?} in group {:?} has zero dimensions: {:?}",
-                wid,
-                gid,
-                wr
-            );
-        }
-    }
+#[test]
+fn test_readonly_buffer_blocks_edits_and_writes() {
+    let path = std::env::temp_dir().join("vimcode_test_readonly_flag.txt");
+    std::fs::write(&path, "abc\n").unwrap();
+    let mut engine = Engine::new();
+    engine.execute_command(&format!("view {}", path.display()));
+    assert!(engine.active_buffer_state().read_only);
 
-    // Also test horizontal split
-    let mut engine2 = Engine::new();
-    engine2.open_editor_group(SplitDirection::Horizontal);
-    assert_eq!(engine2.group_layout.leaf_count(), 2);
-    let (rects2, _) = engine2.calculate_group_window_rects(editor_bounds, tab_bar_height);
-    let gids2 = engine2.group_layout.group_ids();
-    for &gid in &gids2 {
-        let group = engine2.editor_groups.get(&gid).expect("group exists");
-        let wids = group.active_tab().layout.window_ids();
-        let has_match = rects2.iter().any(|(wid, _)| wids.contains(wid));
+    for ch in ['x', 'i', 'd', 'p'] {
+        press_char(&mut engine, ch);
+        assert_eq!(engine.mode, Mode::Normal);
         assert!(
-            has_match,
-            "Horizontal split: group {:?} has no matching window_rects",
-            gid
+            engine.message.starts_with("E45"),
+            "{ch}: {}",
+            engine.message
         );
     }
-}
+    engine.insert_with_undo(0, "z");
+    assert_eq!(engine.buffer().to_string(), "abc\n");
+    engine.execute_command("w");
+    assert!(engine.message.starts_with("E45"), "{}", engine.message);
 
-// ─── Find/Replace overlay tests ──────────────────────────────────────────────
+    engine.execute_command("set noreadonly");
+    press_char(&mut engine, 'x');
+    assert_eq!(engine.buffer().to_string(), "bc\n");
+    engine.execute_command("set readonly?");
+    assert_eq!(engine.message, "noreadonly");
+    let _ = std::fs::remove_file(&path);
+}
 
 #[test]
-fn test_find_replace_open_close() {
+fn test_nomodifiable_blocks_changes_and_undo() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world hello");
-
-    // Ctrl+F opens find/replace
-    press_ctrl(&mut engine, 'f');
-    assert!(engine.find_replace_open);
-    assert_eq!(engine.find_replace_focus, 0);
-
-    // Escape closes it
-    press_special(&mut engine, "Escape");
-    assert!(!engine.find_replace_open);
+    engine.insert_with_undo(0, "text\n");
+    engine.execute_command("set nomodifiable");
+    press_char(&mut engine, 'x');
+    assert!(engine.message.starts_with("E21"), "{}", engine.message);
+    assert!(!engine.undo());
+    assert_eq!(engine.buffer().to_string(), "text\n");
+    engine.execute_command("set ma");
+    assert!(engine.active_buffer_state().modifiable);
 }
 
 #[test]
-fn test_find_replace_incremental_search() {
+fn test_unwritable_file_opens_read_only() {
+    let path = std::env::temp_dir().join("vimcode_test_unwritable.txt");
+    let _ = std::fs::remove_file(&path);
+    std::fs::write(&path, "x\n").unwrap();
+    let mut perms = std::fs::metadata(&path).unwrap().permissions();
+    perms.set_readonly(true);
+    std::fs::set_permissions(&path, perms).unwrap();
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "  foo bar foo baz foo");
-
-    // Put cursor on a space so word_under_cursor returns None
-    engine.view_mut().cursor.col = 0;
-    engine.open_find_replace();
-    assert_eq!(engine.find_replace_query, "");
-
-    // Type "foo" — use handle_key since find_replace_open intercepts
-    engine.handle_key("f", Some('f'), false);
-    assert_eq!(engine.find_replace_query, "f");
-    engine.handle_key("o", Some('o'), false);
-    assert_eq!(engine.find_replace_query, "fo");
-    engine.handle_key("o", Some('o'), false);
-    assert_eq!(engine.find_replace_query, "foo");
-    assert_eq!(engine.search_query, "foo");
-    assert_eq!(engine.search_matches.len(), 3);
+    engine.open_file_in_tab(&path);
+    assert!(engine.active_buffer_state().read_only);
+    let _ = std::fs::remove_file(&path);
 }
 
+// ── Auto-save ───────────────────────────────────────────────────────────
+
 #[test]
-fn test_find_replace_next_prev() {
+fn test_autosave_interval_waits_for_normal_mode() {
+    let path = std::env::temp_dir().join("vimcode_test_autosave_interval.txt");
+    std::fs::write(&path, "hello\n").unwrap();
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "aaa\naaa\naaa\n");
-    engine.update_syntax();
-
-    engine.open_find_replace();
-    engine.find_replace_query = "aaa".to_string();
-    engine.find_replace_cursor = 3;
-    engine.run_find_replace_search();
-
-    assert_eq!(engine.search_matches.len(), 3);
+    engine.open_file_in_tab(&path);
+    engine.execute_command("set autosave=interval");
+    engine.execute_command("set autosaveinterval=0");
+    assert_eq!(engine.settings.autosave_interval, 1000, "below the minimum");
+    engine.execute_command("set autosaveinterval=100");
+    engine.message = "kept".to_string();
 
-    // Next
-    engine.handle_find_replace_key("Down", None, false, false);
-    assert_eq!(engine.search_index, Some(1));
+    let pause = std::time::Duration::from_millis(100);
+    press_char(&mut engine, 'i');
+    press_char(&mut engine, 'X');
+    engine.autosave_key_at = engine.autosave_key_at.map(|t| t - pause);
+    assert!(!engine.tick_autosave(), "no save while still inserting");
+    assert!(engine.dirty());
 
-    // Prev
-    engine.handle_find_replace_key("Up", None, false, false);
-    assert_eq!(engine.search_index, Some(0));
+    press_special(&mut engine, "Escape");
+    engine.message = "kept".to_string();
+    assert!(!engine.tick_autosave(), "typing only just paused");
+    engine.autosave_key_at = engine.autosave_key_at.map(|t| t - pause);
+    assert!(engine.tick_autosave());
+    assert!(!engine.dirty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Xhello\n");
+    assert!(engine.autosaved_at.is_some());
+    assert_eq!(engine.message, "kept");
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_find_replace_replace_current() {
+fn test_autosave_on_focus_skips_externally_changed_files() {
+    let path = std::env::temp_dir().join("vimcode_test_autosave_focus.txt");
+    std::fs::write(&path, "one\n").unwrap();
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "aaa bbb aaa");
-    engine.update_syntax();
+    engine.open_file_in_tab(&path);
+    engine.insert_with_undo(0, "A");
+    engine.set_dirty(true);
+    assert!(!engine.on_focus_lost(), "autosave is off by default");
 
-    engine.open_find_replace();
-    engine.find_replace_query = "aaa".to_string();
-    engine.find_replace_replacement = "ccc".to_string();
-    engine.find_replace_cursor = 3;
-    engine.run_find_replace_search();
+    engine.execute_command("set autosave=focus");
+    assert!(engine.on_focus_lost());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Aone\n");
 
-    assert_eq!(engine.search_matches.len(), 2);
+    engine.insert_with_undo(0, "B");
+    engine.set_dirty(true);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(&path, "external\n").unwrap();
+    assert!(!engine.on_focus_lost());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "external\n");
+    assert!(engine.dirty());
 
-    engine.find_replace_replace_current();
-    assert_eq!(engine.buffer().to_string(), "ccc bbb aaa");
-    // After replace, should still have 1 match remaining
-    assert_eq!(engine.search_matches.len(), 1);
+    engine.execute_command("set autosave=sometimes");
+    assert_eq!(engine.settings.autosave, "focus");
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_find_replace_replace_all() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "aaa bbb aaa ccc aaa");
-    engine.update_syntax();
-
-    engine.open_find_replace();
-    engine.find_replace_query = "aaa".to_string();
-    engine.find_replace_replacement = "zzz".to_string();
-    engine.find_replace_cursor = 3;
-    engine.run_find_replace_search();
+fn test_autosave_on_focus_waits_for_insert_mode_to_end() {
+    let path = std::env::temp_dir().join("vimcode_test_autosave_focus_insert.txt");
+    std::fs::write(&path, "one\n").unwrap();
+    let mut engine = Engine::open(&path);
+    engine.execute_command("set autosave=focus");
+    send_keys(&mut engine, "ihi ");
+    assert!(!engine.on_focus_lost());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\n");
+    assert_eq!(engine.buffer().to_string(), "hi one\n");
 
-    engine.find_replace_replace_all();
-    assert_eq!(engine.buffer().to_string(), "zzz bbb zzz ccc zzz");
+    press_special(&mut engine, "Escape");
+    assert!(engine.on_focus_lost());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "hi one\n");
+    let _ = std::fs::remove_file(&path);
 }
 
-#[test]
-fn test_find_replace_case_sensitive() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "Hello hello HELLO");
-    engine.update_syntax();
-
-    engine.open_find_replace();
-    engine.find_replace_query = "hello".to_string();
-    engine.find_replace_cursor = 5;
-
-    // Default: case insensitive → 3 matches
-    engine.run_find_replace_search();
-    assert_eq!(engine.search_matches.len(), 3);
+// ── Final newline on save ───────────────────────────────────────────────
 
-    // Toggle case sensitive → 1 match
-    engine.toggle_find_replace_case();
-    assert_eq!(engine.search_matches.len(), 1);
+/// Open `content` from a temp file, add a line at the end with `Go`, save
+/// and return what was written.
+fn save_after_appending_line(name: &str, content: &str, setup: &str) -> String {
+    let path = std::env::temp_dir().join(format!("vimcode_test_eol_{name}.txt"));
+    std::fs::write(&path, content).unwrap();
+    let mut engine = Engine::open(&path);
+    if !setup.is_empty() {
+        engine.execute_command(setup);
+    }
+    press_char(&mut engine, 'G');
+    press_char(&mut engine, 'o');
+    press_char(&mut engine, 'c');
+    press_special(&mut engine, "Escape");
+    engine.execute_command("w");
+    let written = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    written
 }
 
 #[test]
-fn test_find_replace_whole_word() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "foo foobar barfoo foo");
-    engine.update_syntax();
-
-    engine.open_find_replace();
-    engine.find_replace_query = "foo".to_string();
-    engine.find_replace_cursor = 3;
-
-    // Without whole word → 4 matches (foo, foobar, barfoo, foo)
-    engine.run_find_replace_search();
-    assert_eq!(engine.search_matches.len(), 4);
+fn test_save_keeps_final_newline_as_loaded() {
+    let cases = [
+        ("none", "a\nb", "a\nb\nc"),
+        ("one", "a\nb\n", "a\nb\nc\n"),
+        ("many", "a\nb\n\n\n", "a\nb\n\n\nc\n"),
+    ];
+    for (name, content, want) in cases {
+        assert_eq!(save_after_appending_line(name, content, ""), want, "{name}");
+    }
 
-    // With whole word → 2 matches (only standalone "foo")
-    engine.toggle_find_replace_whole_word();
-    assert_eq!(engine.search_matches.len(), 2);
+    let path = std::env::temp_dir().join("vimcode_test_eol_unchanged.txt");
+    std::fs::write(&path, "a\n\n").unwrap();
+    let mut engine = Engine::open(&path);
+    assert!(
+        engine
+            .buffer_manager
+            .get(engine.active_buffer_id())
+            .unwrap()
+            .final_newline
+    );
+    engine.execute_command("w");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n\n");
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_find_replace_regex() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "foo123 bar456 baz");
-    engine.update_syntax();
+fn test_fixendofline_ensures_exactly_one_final_newline() {
+    let cases = [
+        ("fix_none", "a\nb", "a\nb\nc\n"),
+        ("fix_one", "a\nb\n", "a\nb\nc\n"),
+        ("fix_many", "a\nb\n\n\n", "a\nb\n\n\nc\n"),
+    ];
+    for (name, content, want) in cases {
+        let written = save_after_appending_line(name, content, "set fixeol");
+        assert_eq!(written, want, "{name}");
+    }
 
-    engine.open_find_replace();
-    engine.find_replace_query = r"\d+".to_string();
-    engine.find_replace_cursor = 3;
+    // Extra blank lines at the end are dropped too.
+    let path = std::env::temp_dir().join("vimcode_test_eol_fix_trailing.txt");
+    std::fs::write(&path, "a\n\n\n").unwrap();
+    let mut engine = Engine::open(&path);
+    engine.execute_command("set fixeol");
+    engine.execute_command("w");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+    let _ = std::fs::remove_file(&path);
 
-    // Without regex → 0 matches (literal "\d+" not found)
-    engine.run_find_replace_search();
-    assert_eq!(engine.search_matches.len(), 0);
+    // With fixendofline off, `:set noeol` drops the last line break.
+    let written = save_after_appending_line("noeol", "a\n", "set noeol");
+    assert_eq!(written, "a\nc");
 
-    // With regex → 2 matches (123, 456)
-    engine.toggle_find_replace_regex();
-    assert_eq!(engine.search_matches.len(), 2);
+    let mut engine = Engine::new();
+    engine.execute_command("set fixendofline?");
+    assert_eq!(engine.message, "nofixendofline");
+    engine.execute_command("set eol?");
+    assert_eq!(engine.message, "endofline");
 }
 
+// ── Large file mode ─────────────────────────────────────────────────────
+
 #[test]
-fn test_find_replace_regex_multiline() {
-    let mut engine = Engine::new();
-    engine
-        .buffer_mut()
-        .insert(0, "The cat\nThe dog\na The fox\n");
-    engine.update_syntax();
+fn test_large_file_opens_read_only_without_highlighting() {
+    let path = std::env::temp_dir().join("vimcode_test_large_file.rs");
+    let mut text = "fn main() {}\n".repeat(90_000);
+    text.push_str("needle\n");
+    std::fs::write(&path, &text).unwrap();
 
-    engine.open_find_replace();
-    engine.find_replace_query = "^The".to_string();
-    engine.find_replace_cursor = 4;
-    engine.find_replace_options.use_regex = true;
-    engine.run_find_replace_search();
+    let mut engine = Engine::new();
+    engine.settings.large_file_mb = 1;
+    engine.open_file_in_tab(&path);
+    // The text is read in the background; the buffer shows up at once.
+    assert!(engine.active_buffer_state().loading.is_some());
+    // Writing the partly read buffer would truncate the file.
+    engine.execute_command("w!");
+    assert!(
+        engine.message.contains("still loading"),
+        "{}",
+        engine.message
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while engine.active_buffer_state().loading.is_some() {
+        engine.poll_file_loads();
+        assert!(
+            std::time::Instant::now() < deadline,
+            "large file never loaded"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let state = engine.active_buffer_state();
+    assert!(state.loading.is_none());
+    assert!(state.large_file);
+    assert!(state.read_only);
+    assert!(state.syntax.is_none());
+    assert!(state.highlights.is_empty());
+    assert!(state.lsp_language_id.is_none());
+    assert_eq!(state.max_col, "fn main() {}".len());
+    assert!(!state.dirty);
+    assert_eq!(engine.buffer().len_lines(), 90_001);
+
+    // Motions and search still work.
+    press_char(&mut engine, '/');
+    for ch in "needle".chars() {
+        press_char(&mut engine, ch);
+    }
+    press_special(&mut engine, "Return");
+    assert_eq!(engine.view().cursor.line, 90_000);
+    press_char(&mut engine, 'g');
+    press_char(&mut engine, 'g');
+    assert_eq!(engine.view().cursor.line, 0);
 
-    // ^The should match "The" at the start of line 1 and line 2, but not "The" mid-line on line 3
-    assert_eq!(engine.search_matches.len(), 2);
+    // Below the threshold the same file opens normally.
+    let mut engine = Engine::new();
+    engine.settings.large_file_mb = 0;
+    engine.open_file_in_tab(&path);
+    assert!(!engine.active_buffer_state().large_file);
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_find_replace_n_after_close() {
+fn test_large_file_reload_keeps_cursor() {
+    let path = std::env::temp_dir().join("vimcode_test_large_reload.txt");
+    let text = "line\n".repeat(300_000);
+    std::fs::write(&path, &text).unwrap();
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "aaa bbb aaa ccc aaa");
-    engine.update_syntax();
-
-    engine.open_find_replace();
-    engine.find_replace_query = "aaa".to_string();
-    engine.find_replace_cursor = 3;
-    engine.run_find_replace_search();
-    engine.close_find_replace();
+    engine.settings.large_file_mb = 1;
+    engine.open_file_in_tab(&path);
+    let wait_for_load = |engine: &mut Engine| {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while engine.active_buffer_state().loading.is_some() {
+            engine.poll_file_loads();
+            assert!(std::time::Instant::now() < deadline, "never loaded");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    };
+    wait_for_load(&mut engine);
+    engine.view_mut().cursor.line = 250_000;
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(&path, text.replace("line", "LINE")).unwrap();
+    engine.checktime();
+    wait_for_load(&mut engine);
+    assert_eq!(engine.buffer().content.line(0).to_string(), "LINE\n");
+    assert_eq!(engine.view().cursor.line, 250_000);
+    let _ = std::fs::remove_file(&path);
+}
 
-    // search_query and search_matches should persist
-    assert_eq!(engine.search_query, "aaa");
-    assert_eq!(engine.search_matches.len(), 3);
+// ── o / O with CRLF and lone-CR line endings ────────────────────────────
 
-    // n should work after closing
-    press_char(&mut engine, 'n');
-    assert!(engine.search_index.is_some());
+#[test]
+fn test_o_with_crlf_line_endings() {
+    // Buffer uses CRLF line endings — `o` must create a new line, not
+    // get absorbed by the \r\n pair.
+    let mut engine = engine_with_text("AB\r\nCD\r\n");
+    engine.view_mut().cursor.line = 0;
+    engine.view_mut().cursor.col = 0;
+
+    press_char(&mut engine, 'o');
+    assert_eq!(engine.mode, Mode::Insert);
+    assert_eq!(engine.view().cursor.line, 1);
+    // A new empty line should exist between AB and CD.
+    assert_eq!(engine.buffer().len_lines(), 3);
+    let line0: String = engine.buffer().content.line(0).chars().collect();
+    let line1: String = engine.buffer().content.line(1).chars().collect();
+    let line2: String = engine.buffer().content.line(2).chars().collect();
+    assert!(
+        line0.starts_with("AB"),
+        "line0 should start with AB, got: {:?}",
+        line0
+    );
+    assert!(
+        !line1.starts_with("CD"),
+        "line1 should be the new blank line, got: {:?}",
+        line1
+    );
+    assert!(
+        line2.starts_with("CD"),
+        "line2 should start with CD, got: {:?}",
+        line2
+    );
 }
 
 #[test]
-fn test_find_replace_prefill_from_search() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world hello");
-    engine.update_syntax();
-
-    // Do a Vim / search first
-    engine.search_query = "hello".to_string();
-    engine.run_search();
+fn test_o_with_lone_cr_line_endings() {
+    // Buffer uses lone \r line endings (classic Mac) — `o` must still
+    // create a new line, not join lines.
+    let mut engine = engine_with_text("AB\rCD\r");
+    engine.view_mut().cursor.line = 0;
+    engine.view_mut().cursor.col = 0;
 
-    // Open find/replace — should pre-fill from search_query
-    engine.open_find_replace();
-    assert_eq!(engine.find_replace_query, "hello");
+    press_char(&mut engine, 'o');
+    assert_eq!(engine.mode, Mode::Insert);
+    assert_eq!(engine.view().cursor.line, 1);
+    // Should now have 3 lines: AB, (new blank), CD.
+    assert!(
+        engine.buffer().len_lines() >= 3,
+        "expected >= 3 lines, got {}",
+        engine.buffer().len_lines()
+    );
+    let full: String = engine.buffer().content.chars().collect();
+    // The new line must be a separate line — AB and CD must NOT be joined.
+    assert!(
+        !full.contains("ABCD"),
+        "lines should not be joined, got: {:?}",
+        full
+    );
 }
 
 #[test]
-fn test_ctrl_f_setting() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world\nline2\n");
-    engine.update_syntax();
-    engine.set_viewport_lines(10);
+fn test_o_with_crlf_preserves_content() {
+    // Pressing `o` on the first line of a CRLF file should not corrupt
+    // the second line's content.
+    let mut engine = engine_with_text("hello\r\nworld\r\n");
+    engine.view_mut().cursor.line = 0;
 
-    // Default: ctrl_f_action = "find" → opens find/replace
-    press_ctrl(&mut engine, 'f');
-    assert!(engine.find_replace_open);
+    press_char(&mut engine, 'o');
     press_special(&mut engine, "Escape");
 
-    // Change setting → Ctrl+F does page down
-    engine.settings.ctrl_f_action = "page_down".to_string();
-    press_ctrl(&mut engine, 'f');
-    assert!(!engine.find_replace_open);
+    let text = engine.buffer().to_string();
+    assert!(
+        text.contains("hello") && text.contains("world"),
+        "both lines should be preserved, got: {:?}",
+        text
+    );
+    // There should be exactly 3 non-empty content lines (hello, new, world).
+    let content_lines: Vec<&str> = text.lines().collect();
+    assert!(
+        content_lines.len() >= 3,
+        "expected >= 3 lines, got {}: {:?}",
+        content_lines.len(),
+        content_lines
+    );
 }
 
 #[test]
-fn test_find_replace_prefill_from_selection() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world hello");
-    engine.update_syntax();
-
-    // Select "world" using visual mode (v, then move to cover "world")
-    engine.view_mut().cursor.col = 6; // start of "world"
-    press_char(&mut engine, 'v'); // enter visual mode
-    engine.view_mut().cursor.col = 10; // end of "world"
-
-    // Open find/replace — should pre-fill from visual selection
-    engine.open_find_replace();
-    assert_eq!(engine.find_replace_query, "world");
-    // Mode is Normal but visual selection is preserved for highlighting
-    assert_eq!(engine.mode, Mode::Normal);
-    assert!(engine.visual_anchor.is_some());
-    assert_eq!(engine.command_from_visual, Some(Mode::Visual));
+fn test_o_with_crlf_indented_yaml() {
+    // Simulate YAML with CRLF — `o` on an indented line should create
+    // a new indented line below, not join with the next line.
+    let mut engine = engine_with_text("steps:\r\n  - script: |\r\n    echo hi\r\n");
+    engine.settings.auto_indent = true;
+    engine.view_mut().cursor.line = 2; // on "    echo hi"
 
-    // Closing find/replace clears the visual state
-    engine.close_find_replace();
-    assert!(engine.visual_anchor.is_none());
-    assert!(engine.command_from_visual.is_none());
+    press_char(&mut engine, 'o');
+    assert_eq!(engine.mode, Mode::Insert);
+    assert_eq!(engine.view().cursor.line, 3);
+    // The new line should have the same indent as "    echo hi" (4 spaces).
+    assert_eq!(engine.view().cursor.col, 4);
+    assert!(
+        engine.buffer().len_lines() >= 4,
+        "expected >= 4 lines, got {}",
+        engine.buffer().len_lines()
+    );
 }
 
-// ====================================================================
-// Visual-mode `:` command with '<,'> range prefix
-// ====================================================================
-
 #[test]
-fn test_visual_colon_enters_command_with_range() {
-    let mut engine = Engine::new();
-    engine
-        .buffer_mut()
-        .insert(0, "line one\nline two\nline three\n");
+fn test_status_line_above_terminal_setting_toggle() {
+    let mut engine = engine_with_text("hello\n");
+    // Default: status_line_above_terminal is true
+    assert!(engine.settings.status_line_above_terminal);
 
-    // Enter visual mode and select lines 1-2
-    press_char(&mut engine, 'V'); // VisualLine
-    press_char(&mut engine, 'j'); // extend to line 2
+    // Disable via :set
+    engine.execute_command("set nostatuslineaboveterminal");
+    assert!(!engine.settings.status_line_above_terminal);
 
-    assert_eq!(engine.mode, Mode::VisualLine);
-    assert!(engine.visual_anchor.is_some());
+    // Re-enable via abbreviation
+    engine.execute_command("set slat");
+    assert!(engine.settings.status_line_above_terminal);
 
-    // Press `:` — should enter Command mode with '<,'> prefix
-    press_char(&mut engine, ':');
-    assert_eq!(engine.mode, Mode::Command);
-    assert_eq!(engine.command_buffer, "'<,'>");
-    assert_eq!(engine.command_cursor, 5);
+    // Disable via abbreviation
+    engine.execute_command("set noslat");
+    assert!(!engine.settings.status_line_above_terminal);
 
-    // Visual anchor should still be set (selection visible)
-    assert!(engine.visual_anchor.is_some());
-    // command_from_visual should record the original visual mode
-    assert_eq!(engine.command_from_visual, Some(Mode::VisualLine));
+    // Query via :set slat?
+    engine.execute_command("set slat?");
+    assert_eq!(engine.message, "nostatuslineaboveterminal");
 }
 
 #[test]
-fn test_visual_colon_charwise_mode() {
+fn test_multi_group_window_rects_cover_all_groups() {
+    use crate::core::window::{SplitDirection, WindowRect};
+
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world\n");
+    // Create a second editor group via vertical split
+    engine.open_editor_group(SplitDirection::Vertical);
+    assert_eq!(engine.group_layout.leaf_count(), 2);
 
-    // Enter charwise visual mode
-    press_char(&mut engine, 'v');
-    assert_eq!(engine.mode, Mode::Visual);
+    // Simulate realistic editor bounds (like Win-GUI would pass)
+    let tab_bar_height = 30.0;
+    let editor_bounds = WindowRect::new(200.0, 40.0, 1600.0, 900.0);
+    let (window_rects, _dividers) =
+        engine.calculate_group_window_rects(editor_bounds, tab_bar_height);
 
-    press_char(&mut engine, ':');
-    assert_eq!(engine.mode, Mode::Command);
-    assert_eq!(engine.command_buffer, "'<,'>");
-    assert_eq!(engine.command_from_visual, Some(Mode::Visual));
-    assert!(engine.visual_anchor.is_some());
+    // Every group must have at least one window in the rects
+    let group_ids = engine.group_layout.group_ids();
+    assert_eq!(group_ids.len(), 2);
+    for &gid in &group_ids {
+        let group = engine.editor_groups.get(&gid).expect("group exists");
+        let wids = group.active_tab().layout.window_ids();
+        let matching: Vec<_> = window_rects
+            .iter()
+            .filter(|(wid, _)| wids.contains(wid))
+            .collect();
+        assert!(
+            !matching.is_empty(),
+            "Group {:?} has no matching window_rects. wids={:?}, all_rects={:?}",
+            gid,
+            wids,
+            window_rects.iter().map(|(w, _)| w).collect::<Vec<_>>()
+        );
+        // All matching rects should have non-zero dimensions
+        for (wid, wr) in &matching {
+            assert!(
+                wr.width > 0.0 && wr.height > 0.0,
+                "Window {:?} in group {:?} has zero dimensions: {:?}",
+                wid,
+                gid,
+                wr
+            );
+        }
+    }
+
+    // Also test horizontal split
+    let mut engine2 = Engine::new();
+    engine2.open_editor_group(SplitDirection::Horizontal);
+    assert_eq!(engine2.group_layout.leaf_count(), 2);
+    let (rects2, _) = engine2.calculate_group_window_rects(editor_bounds, tab_bar_height);
+    let gids2 = engine2.group_layout.group_ids();
+    for &gid in &gids2 {
+        let group = engine2.editor_groups.get(&gid).expect("group exists");
+        let wids = group.active_tab().layout.window_ids();
+        let has_match = rects2.iter().any(|(wid, _)| wids.contains(wid));
+        assert!(
+            has_match,
+            "Horizontal split: group {:?} has no matching window_rects",
+            gid
+        );
+    }
 }
 
+// ─── Find/Replace overlay tests ──────────────────────────────────────────────
+
 #[test]
-fn test_visual_colon_escape_clears_visual() {
+fn test_find_replace_open_close() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "line one\nline two\n");
+    engine.buffer_mut().insert(0, "hello world hello");
 
-    // Visual select → : → Escape should clear visual state
-    press_char(&mut engine, 'V');
-    press_char(&mut engine, ':');
-    assert_eq!(engine.mode, Mode::Command);
-    assert!(engine.visual_anchor.is_some());
+    // Ctrl+F opens find/replace
+    press_ctrl(&mut engine, 'f');
+    assert!(engine.find_replace_open);
+    assert_eq!(engine.find_replace_focus, 0);
 
+    // Escape closes it
     press_special(&mut engine, "Escape");
-    assert_eq!(engine.mode, Mode::Normal);
-    assert!(engine.visual_anchor.is_none());
-    assert!(engine.command_from_visual.is_none());
+    assert!(!engine.find_replace_open);
 }
 
 #[test]
-fn test_visual_colon_substitute_on_selection() {
+fn test_find_replace_incremental_search() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "foo bar\nfoo baz\nfoo qux\n");
-
-    // Select lines 1 and 2 (0-indexed)
-    press_char(&mut engine, 'j'); // move to line 1
-    press_char(&mut engine, 'V'); // visual line
-    press_char(&mut engine, 'j'); // extend to line 2
-
-    // Press : to enter command mode with range
-    press_char(&mut engine, ':');
-    assert_eq!(engine.command_buffer, "'<,'>");
-
-    // Type substitute command
-    for ch in "s/foo/replaced/g".chars() {
-        press_char(&mut engine, ch);
-    }
-    assert_eq!(engine.command_buffer, "'<,'>s/foo/replaced/g");
+    engine.buffer_mut().insert(0, "  foo bar foo baz foo");
 
-    // Execute
-    press_special(&mut engine, "Return");
-    assert_eq!(engine.mode, Mode::Normal);
-    assert!(engine.visual_anchor.is_none());
-    assert!(engine.command_from_visual.is_none());
+    // Put cursor on a space so word_under_cursor returns None
+    engine.view_mut().cursor.col = 0;
+    engine.open_find_replace();
+    assert_eq!(engine.find_replace_query, "");
 
-    // Line 0 should be unchanged, lines 1-2 should be replaced
-    let text = engine.buffer().content.to_string();
-    assert!(text.starts_with("foo bar\n"), "line 0 unchanged");
-    assert!(text.contains("replaced baz\n"), "line 1 replaced");
-    assert!(text.contains("replaced qux\n"), "line 2 replaced");
-    // Line 0 should still have foo
-    assert!(text.starts_with("foo bar\n"), "line 0 still has foo");
+    // Type "foo" — use handle_key since find_replace_open intercepts
+    engine.handle_key("f", Some('f'), false);
+    assert_eq!(engine.find_replace_query, "f");
+    engine.handle_key("o", Some('o'), false);
+    assert_eq!(engine.find_replace_query, "fo");
+    engine.handle_key("o", Some('o'), false);
+    assert_eq!(engine.find_replace_query, "foo");
+    assert_eq!(engine.search_query, "foo");
+    assert_eq!(engine.search_matches.len(), 3);
 }
 
 #[test]
-fn test_visual_colon_execute_clears_visual_state() {
+fn test_find_replace_next_prev() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "aaa\nbbb\nccc\n");
+    engine.buffer_mut().insert(0, "aaa\naaa\naaa\n");
+    engine.update_syntax();
 
-    press_char(&mut engine, 'V');
-    press_char(&mut engine, ':');
+    engine.open_find_replace();
+    engine.find_replace_query = "aaa".to_string();
+    engine.find_replace_cursor = 3;
+    engine.run_find_replace_search();
 
-    // Type a no-op command (just the range, which won't match anything)
-    // Use a real command: number (shows line numbers)
-    for ch in "number".chars() {
-        press_char(&mut engine, ch);
-    }
-    press_special(&mut engine, "Return");
+    assert_eq!(engine.search_matches.len(), 3);
 
-    assert_eq!(engine.mode, Mode::Normal);
-    assert!(engine.visual_anchor.is_none());
-    assert!(engine.command_from_visual.is_none());
+    // Next
+    engine.handle_find_replace_key("Down", None, false, false);
+    assert_eq!(engine.search_index, Some(1));
+
+    // Prev
+    engine.handle_find_replace_key("Up", None, false, false);
+    assert_eq!(engine.search_index, Some(0));
 }
 
 #[test]
-fn test_visual_block_colon_range() {
+fn test_find_replace_replace_current() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abc\ndef\nghi\n");
-
-    // Enter visual block mode
-    press_ctrl(&mut engine, 'v');
-    assert_eq!(engine.mode, Mode::VisualBlock);
-
-    press_char(&mut engine, ':');
-    assert_eq!(engine.mode, Mode::Command);
-    assert_eq!(engine.command_buffer, "'<,'>");
-    assert_eq!(engine.command_from_visual, Some(Mode::VisualBlock));
-    assert!(engine.visual_anchor.is_some());
-}
+    engine.buffer_mut().insert(0, "aaa bbb aaa");
+    engine.update_syntax();
 
-// ─── Find/replace shared dispatch tests ─────────────────────────────────────
+    engine.open_find_replace();
+    engine.find_replace_query = "aaa".to_string();
+    engine.find_replace_replacement = "ccc".to_string();
+    engine.find_replace_cursor = 3;
+    engine.run_find_replace_search();
 
-#[test]
-fn test_find_word_boundaries_basic() {
-    use super::find_word_boundaries;
-    assert_eq!(find_word_boundaries("hello world", 2), (0, 5));
-    assert_eq!(find_word_boundaries("hello world", 0), (0, 5));
-    assert_eq!(find_word_boundaries("hello world", 4), (0, 5));
-    assert_eq!(find_word_boundaries("hello world", 6), (6, 11));
-}
+    assert_eq!(engine.search_matches.len(), 2);
 
-#[test]
-fn test_find_word_boundaries_underscore() {
-    use super::find_word_boundaries;
-    assert_eq!(find_word_boundaries("foo_bar baz", 3), (0, 7));
-    assert_eq!(find_word_boundaries("foo_bar baz", 5), (0, 7));
+    engine.find_replace_replace_current();
+    assert_eq!(engine.buffer().to_string(), "ccc bbb aaa");
+    // After replace, should still have 1 match remaining
+    assert_eq!(engine.search_matches.len(), 1);
 }
 
 #[test]
-fn test_find_word_boundaries_non_word() {
-    use super::find_word_boundaries;
-    // Clicking on a space returns (pos, pos)
-    assert_eq!(find_word_boundaries("hello world", 5), (5, 5));
-    // Clicking on punctuation
-    assert_eq!(find_word_boundaries("a.b", 1), (1, 1));
-}
+fn test_find_replace_replace_all() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "aaa bbb aaa ccc aaa");
+    engine.update_syntax();
 
-#[test]
-fn test_find_word_boundaries_empty() {
-    use super::find_word_boundaries;
-    assert_eq!(find_word_boundaries("", 0), (0, 0));
-}
+    engine.open_find_replace();
+    engine.find_replace_query = "aaa".to_string();
+    engine.find_replace_replacement = "zzz".to_string();
+    engine.find_replace_cursor = 3;
+    engine.run_find_replace_search();
 
-#[test]
-fn test_find_word_boundaries_single_char() {
-    use super::find_word_boundaries;
-    assert_eq!(find_word_boundaries("x", 0), (0, 1));
+    engine.find_replace_replace_all();
+    assert_eq!(engine.buffer().to_string(), "zzz bbb zzz ccc zzz");
 }
 
 #[test]
-fn test_handle_find_replace_click_chevron() {
+fn test_find_replace_case_sensitive() {
     let mut engine = Engine::new();
-    engine.find_replace_open = true;
-    engine.find_replace_show_replace = false;
-    engine.handle_find_replace_click(FindReplaceClickTarget::Chevron);
-    assert!(engine.find_replace_show_replace);
-    engine.handle_find_replace_click(FindReplaceClickTarget::Chevron);
-    assert!(!engine.find_replace_show_replace);
-}
+    engine.buffer_mut().insert(0, "Hello hello HELLO");
+    engine.update_syntax();
 
-#[test]
-fn test_handle_find_replace_click_find_input() {
-    let mut engine = Engine::new();
-    engine.find_replace_open = true;
-    engine.find_replace_query = "hello world".to_string();
-    engine.find_replace_focus = 1; // start on replace
-    engine.handle_find_replace_click(FindReplaceClickTarget::FindInput(3));
-    assert_eq!(engine.find_replace_focus, 0);
-    assert_eq!(engine.find_replace_cursor, 3);
-    assert_eq!(engine.find_replace_sel_anchor, Some(3));
+    engine.open_find_replace();
+    engine.find_replace_query = "hello".to_string();
+    engine.find_replace_cursor = 5;
+
+    // Default: case insensitive → 3 matches
+    engine.run_find_replace_search();
+    assert_eq!(engine.search_matches.len(), 3);
+
+    // Toggle case sensitive → 1 match
+    engine.toggle_find_replace_case();
+    assert_eq!(engine.search_matches.len(), 1);
 }
 
 #[test]
-fn test_handle_find_replace_click_replace_input() {
+fn test_find_replace_whole_word() {
     let mut engine = Engine::new();
-    engine.find_replace_open = true;
-    engine.find_replace_replacement = "abc".to_string();
-    engine.handle_find_replace_click(FindReplaceClickTarget::ReplaceInput(5));
-    assert_eq!(engine.find_replace_focus, 1);
-    // Clamped to replacement length
-    assert_eq!(engine.find_replace_cursor, 3);
+    engine.buffer_mut().insert(0, "foo foobar barfoo foo");
+    engine.update_syntax();
+
+    engine.open_find_replace();
+    engine.find_replace_query = "foo".to_string();
+    engine.find_replace_cursor = 3;
+
+    // Without whole word → 4 matches (foo, foobar, barfoo, foo)
+    engine.run_find_replace_search();
+    assert_eq!(engine.search_matches.len(), 4);
+
+    // With whole word → 2 matches (only standalone "foo")
+    engine.toggle_find_replace_whole_word();
+    assert_eq!(engine.search_matches.len(), 2);
 }
 
 #[test]
-fn test_handle_find_replace_click_toggles() {
+fn test_find_replace_regex() {
     let mut engine = Engine::new();
-    engine.find_replace_open = true;
-    engine.buffer_mut().insert(0, "test content");
-
-    assert!(!engine.find_replace_options.case_sensitive);
-    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleCase);
-    assert!(engine.find_replace_options.case_sensitive);
-
-    assert!(!engine.find_replace_options.whole_word);
-    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleWholeWord);
-    assert!(engine.find_replace_options.whole_word);
+    engine.buffer_mut().insert(0, "foo123 bar456 baz");
+    engine.update_syntax();
 
-    assert!(!engine.find_replace_options.use_regex);
-    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleRegex);
-    assert!(engine.find_replace_options.use_regex);
+    engine.open_find_replace();
+    engine.find_replace_query = r"\d+".to_string();
+    engine.find_replace_cursor = 3;
 
-    assert!(!engine.find_replace_options.preserve_case);
-    engine.handle_find_replace_click(FindReplaceClickTarget::TogglePreserveCase);
-    assert!(engine.find_replace_options.preserve_case);
+    // Without regex → 0 matches (literal "\d+" not found)
+    engine.run_find_replace_search();
+    assert_eq!(engine.search_matches.len(), 0);
 
-    assert!(!engine.find_replace_options.in_selection);
-    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleInSelection);
-    assert!(engine.find_replace_options.in_selection);
+    // With regex → 2 matches (123, 456)
+    engine.toggle_find_replace_regex();
+    assert_eq!(engine.search_matches.len(), 2);
 }
 
 #[test]
-fn test_handle_find_replace_click_close() {
+fn test_find_replace_regex_multiline() {
     let mut engine = Engine::new();
-    engine.find_replace_open = true;
-    engine.handle_find_replace_click(FindReplaceClickTarget::Close);
-    assert!(!engine.find_replace_open);
-}
+    engine
+        .buffer_mut()
+        .insert(0, "The cat\nThe dog\na The fox\n");
+    engine.update_syntax();
 
-#[test]
-fn test_find_replace_hit_regions_count() {
-    use super::{compute_find_replace_hit_regions, FR_PANEL_WIDTH};
-    // Without replace: chevron + input + 3 toggles + 4 nav = 9
-    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, false, "1 of 5");
-    assert_eq!(regions.len(), 9);
+    engine.open_find_replace();
+    engine.find_replace_query = "^The".to_string();
+    engine.find_replace_cursor = 4;
+    engine.find_replace_options.use_regex = true;
+    engine.run_find_replace_search();
 
-    // With replace: + input + AB + replace + replace_all = 9 + 4 = 13
-    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, true, "1 of 5");
-    assert_eq!(regions.len(), 13);
+    // ^The should match "The" at the start of line 1 and line 2, but not "The" mid-line on line 3
+    assert_eq!(engine.search_matches.len(), 2);
 }
 
 #[test]
-fn test_find_replace_hit_regions_no_overlap() {
-    use super::{compute_find_replace_hit_regions, FindReplaceClickTarget, FR_PANEL_WIDTH};
-    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, true, "12 of 345");
+fn test_find_replace_n_after_close() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "aaa bbb aaa ccc aaa");
+    engine.update_syntax();
 
-    // Check that no two regions on the same row overlap
-    for i in 0..regions.len() {
-        for j in (i + 1)..regions.len() {
-            let (a, _) = &regions[i];
-            let (b, _) = &regions[j];
-            if a.row == b.row {
-                let a_end = a.col + a.width;
-                let b_end = b.col + b.width;
-                assert!(
-                    a_end <= b.col || b_end <= a.col,
-                    "Overlap on row {}: region {:?} (col {}..{}) overlaps {:?} (col {}..{})",
-                    a.row,
-                    regions[i].1,
-                    a.col,
-                    a_end,
-                    regions[j].1,
-                    b.col,
-                    b_end,
-                );
-            }
-        }
-    }
-}
+    engine.open_find_replace();
+    engine.find_replace_query = "aaa".to_string();
+    engine.find_replace_cursor = 3;
+    engine.run_find_replace_search();
+    engine.close_find_replace();
 
-#[test]
-fn test_find_replace_hit_regions_close_button_present() {
-    use super::{compute_find_replace_hit_regions, FindReplaceClickTarget, FR_PANEL_WIDTH};
-    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, false, "No results");
-    let has_close = regions
-        .iter()
-        .any(|(_, t)| matches!(t, FindReplaceClickTarget::Close));
-    assert!(has_close, "Close button must be in hit regions");
+    // search_query and search_matches should persist
+    assert_eq!(engine.search_query, "aaa");
+    assert_eq!(engine.search_matches.len(), 3);
+
+    // n should work after closing
+    press_char(&mut engine, 'n');
+    assert!(engine.search_index.is_some());
 }
 
 #[test]
-fn test_visual_selection_prefills_find_replace() {
+fn test_find_replace_prefill_from_search() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello world foo bar");
+    engine.buffer_mut().insert(0, "hello world hello");
+    engine.update_syntax();
 
-    // Select "world" (cols 6-10)
-    press_char(&mut engine, 'v'); // enter visual
-    for _ in 0..5 {
-        press_char(&mut engine, 'l');
-    }
-    // w is at col 0, need to position first
-    // Reset: go to "world"
-    engine.mode = Mode::Normal;
-    engine.view_mut().cursor.line = 0;
-    engine.view_mut().cursor.col = 6;
-    press_char(&mut engine, 'v');
-    for _ in 0..4 {
-        press_char(&mut engine, 'l');
-    }
-    // Now visual selection covers "world" (cols 6-10)
+    // Do a Vim / search first
+    engine.search_query = "hello".to_string();
+    engine.run_search();
 
+    // Open find/replace — should pre-fill from search_query
     engine.open_find_replace();
-    assert!(engine.find_replace_open);
-    assert_eq!(engine.find_replace_query, "world");
+    assert_eq!(engine.find_replace_query, "hello");
 }
 
-// ─── x with count + . repeat tests ──────────────────────────────────────────
-
 #[test]
-fn test_x_with_count_then_dot_repeat() {
+fn test_ctrl_f_setting() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abcdefghijklmnop");
+    engine.buffer_mut().insert(0, "hello world\nline2\n");
+    engine.update_syntax();
+    engine.set_viewport_lines(10);
 
-    // 4x deletes "abcd", leaving "efghijklmnop"
-    engine.handle_key("4", Some('4'), false);
-    press_char(&mut engine, 'x');
-    assert_eq!(engine.buffer().content.to_string(), "efghijklmnop");
-    assert_eq!(engine.view().cursor.col, 0);
+    // Default: ctrl_f_action = "find" → opens find/replace
+    press_ctrl(&mut engine, 'f');
+    assert!(engine.find_replace_open);
+    press_special(&mut engine, "Escape");
 
-    // . repeats: deletes "efgh", leaving "ijklmnop"
-    press_char(&mut engine, '.');
-    assert_eq!(engine.buffer().content.to_string(), "ijklmnop");
+    // Change setting → Ctrl+F does page down
+    engine.settings.ctrl_f_action = "page_down".to_string();
+    press_ctrl(&mut engine, 'f');
+    assert!(!engine.find_replace_open);
 }
 
 #[test]
-fn test_x_with_count_then_dot_with_new_count() {
+fn test_find_replace_prefill_from_selection() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abcdefghijklmnop");
+    engine.buffer_mut().insert(0, "hello world hello");
+    engine.update_syntax();
 
-    // 4x deletes "abcd"
-    engine.handle_key("4", Some('4'), false);
-    press_char(&mut engine, 'x');
-    assert_eq!(engine.buffer().content.to_string(), "efghijklmnop");
+    // Select "world" using visual mode (v, then move to cover "world")
+    engine.view_mut().cursor.col = 6; // start of "world"
+    press_char(&mut engine, 'v'); // enter visual mode
+    engine.view_mut().cursor.col = 10; // end of "world"
 
-    // 2. repeats with count 2: deletes "ef", leaving "ghijklmnop"
-    engine.handle_key("2", Some('2'), false);
-    press_char(&mut engine, '.');
-    assert_eq!(engine.buffer().content.to_string(), "ghijklmnop");
+    // Open find/replace — should pre-fill from visual selection
+    engine.open_find_replace();
+    assert_eq!(engine.find_replace_query, "world");
+    // Mode is Normal but visual selection is preserved for highlighting
+    assert_eq!(engine.mode, Mode::Normal);
+    assert!(engine.visual_anchor.is_some());
+    assert_eq!(engine.command_from_visual, Some(Mode::Visual));
+
+    // Closing find/replace clears the visual state
+    engine.close_find_replace();
+    assert!(engine.visual_anchor.is_none());
+    assert!(engine.command_from_visual.is_none());
 }
 
+// ====================================================================
+// Visual-mode `:` command with '<,'> range prefix
+// ====================================================================
+
 #[test]
-fn test_dd_then_dot_repeat() {
+fn test_visual_colon_enters_command_with_range() {
     let mut engine = Engine::new();
     engine
         .buffer_mut()
-        .insert(0, "line1\nline2\nline3\nline4\nline5\n");
-
-    // 2dd deletes lines 1-2
-    engine.handle_key("2", Some('2'), false);
-    press_char(&mut engine, 'd');
-    press_char(&mut engine, 'd');
-    assert_eq!(engine.buffer().content.to_string(), "line3\nline4\nline5\n");
+        .insert(0, "line one\nline two\nline three\n");
 
-    // . repeats: deletes lines 3-4
-    press_char(&mut engine, '.');
-    assert_eq!(engine.buffer().content.to_string(), "line5\n");
-}
+    // Enter visual mode and select lines 1-2
+    press_char(&mut engine, 'V'); // VisualLine
+    press_char(&mut engine, 'j'); // extend to line 2
 
-// ─── Colorcolumn parsing tests ───────────────────────────────────────────────
+    assert_eq!(engine.mode, Mode::VisualLine);
+    assert!(engine.visual_anchor.is_some());
 
-#[test]
-fn test_colorcolumn_positions_single() {
-    let mut settings = Settings::default();
-    settings.colorcolumn = "80".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![80]);
-}
+    // Press `:` — should enter Command mode with '<,'> prefix
+    press_char(&mut engine, ':');
+    assert_eq!(engine.mode, Mode::Command);
+    assert_eq!(engine.command_buffer, "'<,'>");
+    assert_eq!(engine.command_cursor, 5);
 
-#[test]
-fn test_colorcolumn_positions_multiple() {
-    let mut settings = Settings::default();
-    settings.colorcolumn = "80,120".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![80, 120]);
+    // Visual anchor should still be set (selection visible)
+    assert!(engine.visual_anchor.is_some());
+    // command_from_visual should record the original visual mode
+    assert_eq!(engine.command_from_visual, Some(Mode::VisualLine));
 }
 
 #[test]
-fn test_colorcolumn_positions_relative_plus() {
-    let mut settings = Settings::default();
-    settings.textwidth = 80;
-    settings.colorcolumn = "+1".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![81]);
-}
+fn test_visual_colon_charwise_mode() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "hello world\n");
 
-#[test]
-fn test_colorcolumn_positions_relative_minus() {
-    let mut settings = Settings::default();
-    settings.textwidth = 80;
-    settings.colorcolumn = "-2".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![78]);
-}
+    // Enter charwise visual mode
+    press_char(&mut engine, 'v');
+    assert_eq!(engine.mode, Mode::Visual);
 
-#[test]
-fn test_colorcolumn_positions_mixed() {
-    let mut settings = Settings::default();
-    settings.textwidth = 80;
-    settings.colorcolumn = "100,+1,72".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![72, 81, 100]);
+    press_char(&mut engine, ':');
+    assert_eq!(engine.mode, Mode::Command);
+    assert_eq!(engine.command_buffer, "'<,'>");
+    assert_eq!(engine.command_from_visual, Some(Mode::Visual));
+    assert!(engine.visual_anchor.is_some());
 }
 
 #[test]
-fn test_colorcolumn_positions_empty() {
-    let settings = Settings::default();
-    assert_eq!(settings.colorcolumn_positions(), Vec::<usize>::new());
-}
+fn test_visual_colon_escape_clears_visual() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "line one\nline two\n");
 
-#[test]
-fn test_colorcolumn_positions_dedup() {
-    let mut settings = Settings::default();
-    settings.colorcolumn = "80,80,80".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![80]);
-}
+    // Visual select → : → Escape should clear visual state
+    press_char(&mut engine, 'V');
+    press_char(&mut engine, ':');
+    assert_eq!(engine.mode, Mode::Command);
+    assert!(engine.visual_anchor.is_some());
 
-#[test]
-fn test_colorcolumn_positions_zero_ignored() {
-    let mut settings = Settings::default();
-    settings.colorcolumn = "0,80".to_string();
-    assert_eq!(settings.colorcolumn_positions(), vec![80]);
+    press_special(&mut engine, "Escape");
+    assert_eq!(engine.mode, Mode::Normal);
+    assert!(engine.visual_anchor.is_none());
+    assert!(engine.command_from_visual.is_none());
 }
 
 #[test]
-fn test_set_colorcolumn_stores_value() {
+fn test_visual_colon_substitute_on_selection() {
     let mut engine = Engine::new();
-    engine.execute_command("set cc=80,120");
-    assert_eq!(engine.settings.colorcolumn, "80,120");
-}
+    engine.buffer_mut().insert(0, "foo bar\nfoo baz\nfoo qux\n");
 
-#[test]
-fn test_set_colorcolumn_query() {
-    let mut engine = Engine::new();
-    engine.settings.colorcolumn = "80".to_string();
-    engine.execute_command("set cc?");
-    assert_eq!(engine.message, "colorcolumn=80");
-}
+    // Select lines 1 and 2 (0-indexed)
+    press_char(&mut engine, 'j'); // move to line 1
+    press_char(&mut engine, 'V'); // visual line
+    press_char(&mut engine, 'j'); // extend to line 2
 
-// ============================================================================
-// Vim Conformance Matrix Tests
-// ============================================================================
+    // Press : to enter command mode with range
+    press_char(&mut engine, ':');
+    assert_eq!(engine.command_buffer, "'<,'>");
 
-/// Send a sequence of keys to the engine, parsing special key notation.
-/// Examples: "dw", "d2w", "gg", "<Esc>", "<C-a>", "<CR>", "gUiw"
-fn send_keys(engine: &mut Engine, keys: &str) {
-    let mut chars = keys.chars().peekable();
+    // Type substitute command
+    for ch in "s/foo/replaced/g".chars() {
+        press_char(&mut engine, ch);
+    }
+    assert_eq!(engine.command_buffer, "'<,'>s/foo/replaced/g");
+
+    // Execute
+    press_special(&mut engine, "Return");
+    assert_eq!(engine.mode, Mode::Normal);
+    assert!(engine.visual_anchor.is_none());
+    assert!(engine.command_from_visual.is_none());
+
+    // Line 0 should be unchanged, lines 1-2 should be replaced
+    let text = engine.buffer().content.to_string();
+    assert!(text.starts_with("foo bar\n"), "line 0 unchanged");
+    assert!(text.contains("replaced baz\n"), "line 1 replaced");
+    assert!(text.contains("replaced qux\n"), "line 2 replaced");
+    // Line 0 should still have foo
+    assert!(text.starts_with("foo bar\n"), "line 0 still has foo");
+}
+
+#[test]
+fn test_visual_colon_execute_clears_visual_state() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "aaa\nbbb\nccc\n");
+
+    press_char(&mut engine, 'V');
+    press_char(&mut engine, ':');
+
+    // Type a no-op command (just the range, which won't match anything)
+    // Use a real command: number (shows line numbers)
+    for ch in "number".chars() {
+        press_char(&mut engine, ch);
+    }
+    press_special(&mut engine, "Return");
+
+    assert_eq!(engine.mode, Mode::Normal);
+    assert!(engine.visual_anchor.is_none());
+    assert!(engine.command_from_visual.is_none());
+}
+
+#[test]
+fn test_visual_block_colon_range() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abc\ndef\nghi\n");
+
+    // Enter visual block mode
+    press_ctrl(&mut engine, 'v');
+    assert_eq!(engine.mode, Mode::VisualBlock);
+
+    press_char(&mut engine, ':');
+    assert_eq!(engine.mode, Mode::Command);
+    assert_eq!(engine.command_buffer, "'<,'>");
+    assert_eq!(engine.command_from_visual, Some(Mode::VisualBlock));
+    assert!(engine.visual_anchor.is_some());
+}
+
+// ─── Find/replace shared dispatch tests ─────────────────────────────────────
+
+#[test]
+fn test_find_word_boundaries_basic() {
+    use super::find_word_boundaries;
+    assert_eq!(find_word_boundaries("hello world", 2), (0, 5));
+    assert_eq!(find_word_boundaries("hello world", 0), (0, 5));
+    assert_eq!(find_word_boundaries("hello world", 4), (0, 5));
+    assert_eq!(find_word_boundaries("hello world", 6), (6, 11));
+}
+
+#[test]
+fn test_find_word_boundaries_underscore() {
+    use super::find_word_boundaries;
+    assert_eq!(find_word_boundaries("foo_bar baz", 3), (0, 7));
+    assert_eq!(find_word_boundaries("foo_bar baz", 5), (0, 7));
+}
+
+#[test]
+fn test_find_word_boundaries_non_word() {
+    use super::find_word_boundaries;
+    // Clicking on a space returns (pos, pos)
+    assert_eq!(find_word_boundaries("hello world", 5), (5, 5));
+    // Clicking on punctuation
+    assert_eq!(find_word_boundaries("a.b", 1), (1, 1));
+}
+
+#[test]
+fn test_find_word_boundaries_empty() {
+    use super::find_word_boundaries;
+    assert_eq!(find_word_boundaries("", 0), (0, 0));
+}
+
+#[test]
+fn test_find_word_boundaries_single_char() {
+    use super::find_word_boundaries;
+    assert_eq!(find_word_boundaries("x", 0), (0, 1));
+}
+
+#[test]
+fn test_handle_find_replace_click_chevron() {
+    let mut engine = Engine::new();
+    engine.find_replace_open = true;
+    engine.find_replace_show_replace = false;
+    engine.handle_find_replace_click(FindReplaceClickTarget::Chevron);
+    assert!(engine.find_replace_show_replace);
+    engine.handle_find_replace_click(FindReplaceClickTarget::Chevron);
+    assert!(!engine.find_replace_show_replace);
+}
+
+#[test]
+fn test_handle_find_replace_click_find_input() {
+    let mut engine = Engine::new();
+    engine.find_replace_open = true;
+    engine.find_replace_query = "hello world".to_string();
+    engine.find_replace_focus = 1; // start on replace
+    engine.handle_find_replace_click(FindReplaceClickTarget::FindInput(3));
+    assert_eq!(engine.find_replace_focus, 0);
+    assert_eq!(engine.find_replace_cursor, 3);
+    assert_eq!(engine.find_replace_sel_anchor, Some(3));
+}
+
+#[test]
+fn test_handle_find_replace_click_replace_input() {
+    let mut engine = Engine::new();
+    engine.find_replace_open = true;
+    engine.find_replace_replacement = "abc".to_string();
+    engine.handle_find_replace_click(FindReplaceClickTarget::ReplaceInput(5));
+    assert_eq!(engine.find_replace_focus, 1);
+    // Clamped to replacement length
+    assert_eq!(engine.find_replace_cursor, 3);
+}
+
+#[test]
+fn test_handle_find_replace_click_toggles() {
+    let mut engine = Engine::new();
+    engine.find_replace_open = true;
+    engine.buffer_mut().insert(0, "test content");
+
+    assert!(!engine.find_replace_options.case_sensitive);
+    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleCase);
+    assert!(engine.find_replace_options.case_sensitive);
+
+    assert!(!engine.find_replace_options.whole_word);
+    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleWholeWord);
+    assert!(engine.find_replace_options.whole_word);
+
+    assert!(!engine.find_replace_options.use_regex);
+    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleRegex);
+    assert!(engine.find_replace_options.use_regex);
+
+    assert!(!engine.find_replace_options.preserve_case);
+    engine.handle_find_replace_click(FindReplaceClickTarget::TogglePreserveCase);
+    assert!(engine.find_replace_options.preserve_case);
+
+    assert!(!engine.find_replace_options.in_selection);
+    engine.handle_find_replace_click(FindReplaceClickTarget::ToggleInSelection);
+    assert!(engine.find_replace_options.in_selection);
+}
+
+#[test]
+fn test_handle_find_replace_click_close() {
+    let mut engine = Engine::new();
+    engine.find_replace_open = true;
+    engine.handle_find_replace_click(FindReplaceClickTarget::Close);
+    assert!(!engine.find_replace_open);
+}
+
+#[test]
+fn test_find_replace_hit_regions_count() {
+    use super::{compute_find_replace_hit_regions, FR_PANEL_WIDTH};
+    // Without replace: chevron + input + 3 toggles + 4 nav = 9
+    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, false, "1 of 5");
+    assert_eq!(regions.len(), 9);
+
+    // With replace: + input + AB + replace + replace_all = 9 + 4 = 13
+    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, true, "1 of 5");
+    assert_eq!(regions.len(), 13);
+}
+
+#[test]
+fn test_find_replace_hit_regions_no_overlap() {
+    use super::{compute_find_replace_hit_regions, FindReplaceClickTarget, FR_PANEL_WIDTH};
+    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, true, "12 of 345");
+
+    // Check that no two regions on the same row overlap
+    for i in 0..regions.len() {
+        for j in (i + 1)..regions.len() {
+            let (a, _) = &regions[i];
+            let (b, _) = &regions[j];
+            if a.row == b.row {
+                let a_end = a.col + a.width;
+                let b_end = b.col + b.width;
+                assert!(
+                    a_end <= b.col || b_end <= a.col,
+                    "Overlap on row {}: region {:?} (col {}..{}) overlaps {:?} (col {}..{})",
+                    a.row,
+                    regions[i].1,
+                    a.col,
+                    a_end,
+                    regions[j].1,
+                    b.col,
+                    b_end,
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_find_replace_hit_regions_close_button_present() {
+    use super::{compute_find_replace_hit_regions, FindReplaceClickTarget, FR_PANEL_WIDTH};
+    let (regions, _) = compute_find_replace_hit_regions(FR_PANEL_WIDTH, false, "No results");
+    let has_close = regions
+        .iter()
+        .any(|(_, t)| matches!(t, FindReplaceClickTarget::Close));
+    assert!(has_close, "Close button must be in hit regions");
+}
+
+#[test]
+fn test_visual_selection_prefills_find_replace() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "hello world foo bar");
+
+    // Select "world" (cols 6-10)
+    press_char(&mut engine, 'v'); // enter visual
+    for _ in 0..5 {
+        press_char(&mut engine, 'l');
+    }
+    // w is at col 0, need to position first
+    // Reset: go to "world"
+    engine.mode = Mode::Normal;
+    engine.view_mut().cursor.line = 0;
+    engine.view_mut().cursor.col = 6;
+    press_char(&mut engine, 'v');
+    for _ in 0..4 {
+        press_char(&mut engine, 'l');
+    }
+    // Now visual selection covers "world" (cols 6-10)
+
+    engine.open_find_replace();
+    assert!(engine.find_replace_open);
+    assert_eq!(engine.find_replace_query, "world");
+}
+
+// ─── x with count + . repeat tests ──────────────────────────────────────────
+
+#[test]
+fn test_x_with_count_then_dot_repeat() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abcdefghijklmnop");
+
+    // 4x deletes "abcd", leaving "efghijklmnop"
+    engine.handle_key("4", Some('4'), false);
+    press_char(&mut engine, 'x');
+    assert_eq!(engine.buffer().content.to_string(), "efghijklmnop");
+    assert_eq!(engine.view().cursor.col, 0);
+
+    // . repeats: deletes "efgh", leaving "ijklmnop"
+    press_char(&mut engine, '.');
+    assert_eq!(engine.buffer().content.to_string(), "ijklmnop");
+}
+
+#[test]
+fn test_x_with_count_then_dot_with_new_count() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abcdefghijklmnop");
+
+    // 4x deletes "abcd"
+    engine.handle_key("4", Some('4'), false);
+    press_char(&mut engine, 'x');
+    assert_eq!(engine.buffer().content.to_string(), "efghijklmnop");
+
+    // 2. repeats with count 2: deletes "ef", leaving "ghijklmnop"
+    engine.handle_key("2", Some('2'), false);
+    press_char(&mut engine, '.');
+    assert_eq!(engine.buffer().content.to_string(), "ghijklmnop");
+}
+
+#[test]
+fn test_dd_then_dot_repeat() {
+    let mut engine = Engine::new();
+    engine
+        .buffer_mut()
+        .insert(0, "line1\nline2\nline3\nline4\nline5\n");
+
+    // 2dd deletes lines 1-2
+    engine.handle_key("2", Some('2'), false);
+    press_char(&mut engine, 'd');
+    press_char(&mut engine, 'd');
+    assert_eq!(engine.buffer().content.to_string(), "line3\nline4\nline5\n");
+
+    // . repeats: deletes lines 3-4
+    press_char(&mut engine, '.');
+    assert_eq!(engine.buffer().content.to_string(), "line5\n");
+}
+
+// ─── Colorcolumn parsing tests ───────────────────────────────────────────────
+
+#[test]
+fn test_colorcolumn_positions_single() {
+    let mut settings = Settings::default();
+    settings.colorcolumn = "80".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![80]);
+}
+
+#[test]
+fn test_colorcolumn_positions_multiple() {
+    let mut settings = Settings::default();
+    settings.colorcolumn = "80,120".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![80, 120]);
+}
+
+#[test]
+fn test_colorcolumn_positions_relative_plus() {
+    let mut settings = Settings::default();
+    settings.textwidth = 80;
+    settings.colorcolumn = "+1".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![81]);
+}
+
+#[test]
+fn test_colorcolumn_positions_relative_minus() {
+    let mut settings = Settings::default();
+    settings.textwidth = 80;
+    settings.colorcolumn = "-2".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![78]);
+}
+
+#[test]
+fn test_colorcolumn_positions_mixed() {
+    let mut settings = Settings::default();
+    settings.textwidth = 80;
+    settings.colorcolumn = "100,+1,72".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![72, 81, 100]);
+}
+
+#[test]
+fn test_colorcolumn_positions_empty() {
+    let settings = Settings::default();
+    assert_eq!(settings.colorcolumn_positions(), Vec::<usize>::new());
+}
+
+#[test]
+fn test_colorcolumn_positions_dedup() {
+    let mut settings = Settings::default();
+    settings.colorcolumn = "80,80,80".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![80]);
+}
+
+#[test]
+fn test_colorcolumn_positions_zero_ignored() {
+    let mut settings = Settings::default();
+    settings.colorcolumn = "0,80".to_string();
+    assert_eq!(settings.colorcolumn_positions(), vec![80]);
+}
+
+#[test]
+fn test_set_colorcolumn_stores_value() {
+    let mut engine = Engine::new();
+    engine.execute_command("set cc=80,120");
+    assert_eq!(engine.settings.colorcolumn, "80,120");
+}
+
+#[test]
+fn test_set_colorcolumn_query() {
+    let mut engine = Engine::new();
+    engine.settings.colorcolumn = "80".to_string();
+    engine.execute_command("set cc?");
+    assert_eq!(engine.message, "colorcolumn=80");
+}
+
+#[test]
+fn test_set_colorcolumn_rejects_garbage() {
+    let mut engine = Engine::new();
+    engine.execute_command("set cc=80,+1");
+    engine.execute_command("set cc=80,abc");
+    assert_eq!(engine.settings.colorcolumn, "80,+1");
+    engine.execute_command("set cc=");
+    assert!(engine.settings.colorcolumn_positions().is_empty());
+}
+
+#[test]
+fn test_set_background_swaps_default_colorscheme() {
+    let mut engine = Engine::new();
+    engine.settings.colorscheme = "onedark".to_string();
+    engine.execute_command("set background=light");
+    assert_eq!(engine.settings.background, "light");
+    assert_eq!(engine.settings.colorscheme, "vscode-light");
+    engine.execute_command("set bg?");
+    assert_eq!(engine.message, "background=light");
+
+    engine.execute_command("set bg=dark");
+    assert_eq!(engine.settings.colorscheme, "onedark");
+
+    // Non-default schemes are left alone.
+    engine.settings.colorscheme = "gruvbox-dark".to_string();
+    engine.execute_command("set background=light");
+    assert_eq!(engine.settings.colorscheme, "gruvbox-dark");
+
+    engine.execute_command("set background=sepia");
+    assert_eq!(engine.settings.background, "light");
+}
+
+#[test]
+fn test_set_cursorstyle() {
+    use crate::core::settings::CursorStyleShape;
+
+    let mut engine = Engine::new();
+    let styles = engine.settings.cursor_styles();
+    assert_eq!(styles.normal.shape, CursorStyleShape::Block);
+    assert!(styles.insert.blink);
+    assert!(!styles.normal.blink);
+
+    engine.execute_command("set cursorstyle=normal:underline-blink-#ff8800,visual:bar");
+    let styles = engine.settings.cursor_styles();
+    assert_eq!(styles.normal.shape, CursorStyleShape::Underline);
+    assert!(styles.normal.blink);
+    assert_eq!(styles.normal.color.as_deref(), Some("#ff8800"));
+    assert_eq!(styles.visual.shape, CursorStyleShape::Bar);
+    // Unlisted modes keep their defaults.
+    assert_eq!(styles.insert.shape, CursorStyleShape::Bar);
+    assert!(styles.insert.blink);
+
+    // Invalid entries are rejected and leave the setting alone.
+    engine.execute_command("set cursorstyle=normal:triangle");
+    assert_eq!(
+        engine.settings.cursorstyle,
+        "normal:underline-blink-#ff8800,visual:bar"
+    );
+}
+
+#[test]
+fn test_set_list_and_listchars() {
+    let mut engine = Engine::new();
+    engine.execute_command("set list");
+    assert!(engine.settings.list);
+    let lc = engine.settings.list_chars();
+    assert_eq!(lc.tab, Some(('→', ' ')));
+    assert_eq!(lc.trail, Some('·'));
+    assert_eq!(lc.eol, Some('¬'));
+    assert_eq!(lc.space, None);
+
+    engine.execute_command("set lcs=tab:>-,space:.,eol:$");
+    let lc = engine.settings.list_chars();
+    assert_eq!(lc.tab, Some(('>', '-')));
+    assert_eq!(lc.space, Some('.'));
+    assert_eq!(lc.trail, None);
+
+    // Escaped space and invalid entries.
+    engine.execute_command("set lcs=tab:>\\ ,eol:$");
+    assert_eq!(engine.settings.list_chars().tab, Some(('>', ' ')));
+    engine.execute_command("set lcs=trail:ab");
+    assert_eq!(engine.settings.listchars, "tab:> ,eol:$");
+
+    engine.execute_command("set nolist");
+    assert!(!engine.settings.list);
+}
+
+// ============================================================================
+// Vim Conformance Matrix Tests
+// ============================================================================
+
+/// Send a sequence of keys to the engine, parsing special key notation.
+/// Examples: "dw", "d2w", "gg", "<Esc>", "<C-a>", "<CR>", "gUiw"
+fn send_keys(engine: &mut Engine, keys: &str) {
+    let mut chars = keys.chars().peekable();
     while let Some(ch) = chars.next() {
         if ch == '<' {
             // Only parse as <SpecialKey> if the remaining string contains a matching '>'.
@@ -20768,14 +23471,24 @@ fn test_set_option_round_trip() {
         ("hlsearch", "false"),
         ("ignorecase", "true"),
         ("smartcase", "true"),
+        ("wrapscan", "false"),
+        ("fuzzy_remember_query", "true"),
         ("incremental_search", "false"),
         ("splitbelow", "true"),
         ("splitright", "true"),
         ("autoread", "true"),
         ("swap_file", "false"),
+        ("backup", "true"),
+        ("writebackup", "true"),
+        ("insert_final_newline", "true"),
         ("updatetime", "2000"),
         ("hover_delay", "500"),
         ("match_brackets", "false"),
+        ("highlight_word_under_cursor", "false"),
+        ("complete_all_buffers", "true"),
+        ("explorer_ignore", "target/,*.o"),
+        ("respect_gitignore", "true"),
+        ("diagnostic_virtual_text", "false"),
         ("auto_pairs", "true"),
         ("colorcolumn", "80,120"),
         ("colorscheme", "gruvbox-dark"),
@@ -20786,6 +23499,16 @@ fn test_set_option_round_trip() {
         ("ctrl_f_action", "page_down"),
         ("hide_single_tab", "true"),
         ("breadcrumbs", "false"),
+        ("sticky_scroll", "true"),
+        ("minimap", "true"),
+        ("smooth_scroll", "true"),
+        ("smooth_scroll_duration", "150"),
+        ("large_file_mb", "200"),
+        ("autosave", "interval"),
+        ("autosave_interval", "2500"),
+        ("cursorstyle", "normal:bar-#ff0000"),
+        ("cursor_blink_interval", "400"),
+        ("background", "light"),
         ("indent_guides", "false"),
         ("terminal_scrollback_lines", "5000"),
         ("show_hidden_files", "true"),
@@ -20928,6 +23651,28 @@ fn test_set_option_smartcase_behavior() {
     );
 }
 
+#[test]
+fn test_search_case_overrides_and_star() {
+    let mut engine = setup_engine("Hello World hello world", 0, 0);
+
+    // `\c` forces a case-insensitive match even with noignorecase.
+    send_keys(&mut engine, "/hello\\c<CR>");
+    assert_eq!(engine.search_matches.len(), 2);
+
+    // `\C` forces a case-sensitive match over ignorecase.
+    engine.settings.ignorecase = true;
+    send_keys(&mut engine, "/\\Chello<CR>");
+    assert_eq!(engine.search_matches.len(), 1);
+
+    // `*` honours ignorecase but not smartcase.
+    engine.settings.smartcase = true;
+    send_keys(&mut engine, "gg0*");
+    assert_eq!(engine.search_matches.len(), 2);
+    engine.settings.ignorecase = false;
+    send_keys(&mut engine, "gg0*");
+    assert_eq!(engine.search_matches.len(), 1);
+}
+
 #[test]
 fn test_set_option_scrolloff_behavior() {
     // scrolloff keeps N lines visible above/below cursor when scrolling
@@ -20983,6 +23728,28 @@ fn test_set_option_auto_pairs_behavior() {
     assert_eq!(content, "()", "auto_pairs should insert matching paren");
 }
 
+#[test]
+fn test_auto_pairs_skip_comments_and_single_undo() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "// note\n");
+    engine.settings.auto_pairs = true;
+    // Mark line 0 as a comment span, as the syntax highlighter would.
+    engine
+        .active_buffer_state_mut()
+        .highlights
+        .push((0, 7, "comment".to_string()));
+
+    send_keys(&mut engine, "A(<Esc>");
+    assert_eq!(engine.buffer().to_string(), "// note(\n");
+
+    // Outside the comment the pair is inserted and undone in one step
+    // (the new line is indented after the open paren).
+    send_keys(&mut engine, "o[<Esc>");
+    assert_eq!(engine.buffer().to_string(), "// note(\n    []\n");
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "// note(\n");
+}
+
 #[test]
 fn test_set_option_auto_pairs_disabled() {
     let mut engine = Engine::new();
@@ -21513,6 +24280,29 @@ fn test_lua_set_lines_insert_at_beginning() {
     assert_eq!(engine.buffer().to_string(), "new first\naaa\nbbb\n");
 }
 
+#[test]
+fn test_lua_set_lines_respects_nomodifiable() {
+    let dir = write_plugin_lua(
+        "test_setlines_nomod",
+        r#"vimcode.command("NoModLines", function()
+            vimcode.buf.set_lines(0, 1, {"changed"})
+        end)"#,
+    );
+    let mut engine = Engine::new();
+    match plugin::PluginManager::new() {
+        Ok(mut mgr) => {
+            mgr.load_plugins_dir(&dir, &[]);
+            engine.plugin_manager = Some(mgr);
+        }
+        Err(_) => return,
+    }
+    engine.buffer_mut().insert(0, "aaa\nbbb\n");
+    engine.execute_command("set nomodifiable");
+    engine.execute_command("NoModLines");
+    assert_eq!(engine.buffer().to_string(), "aaa\nbbb\n");
+    assert!(engine.message.starts_with("E21"), "{}", engine.message);
+}
+
 // ── Phase 4: Neovim test suite mining ────────────────────────────────────
 // Tests translated from Neovim's test_normal.vim and test_textobjects.vim.
 // Reference values verified against Neovim 0.12.1.
@@ -22484,6 +25274,33 @@ fn test_nvim_black_hole_register() {
     assert_eq!(engine.buffer().to_string(), "bbb\n");
 }
 
+#[test]
+fn test_black_hole_register_leaves_unnamed_and_numbered() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "aaa\nbbb\nccc\n");
+    engine.update_syntax();
+    engine.feed_keys("yy");
+    engine.feed_keys("j\"_dd");
+    assert_eq!(engine.buffer().to_string(), "aaa\nccc\n");
+    assert_eq!(engine.registers.get(&'"').unwrap().0, "aaa\n");
+    assert!(!engine.registers.contains_key(&'1'));
+    assert!(!engine.registers.contains_key(&'_'));
+    // Pasting the unnamed register still yields the earlier yank.
+    engine.feed_keys("p");
+    assert_eq!(engine.buffer().to_string(), "aaa\nccc\naaa\n");
+}
+
+#[test]
+fn test_small_delete_register_paste() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "hello world\n");
+    engine.update_syntax();
+    engine.feed_keys("dw");
+    engine.feed_keys("\"ayy");
+    engine.feed_keys("$\"-p");
+    assert_eq!(engine.buffer().to_string(), "worldhello \n");
+}
+
 #[test]
 fn test_nvim_dot_register() {
     // @. contains last inserted text
@@ -22892,6 +25709,37 @@ fn test_nvim_di_quote_empty() {
     nvim_case("say \"\" end\n", 0, 4, "di\"", "say \"\" end\n", 0, 4);
 }
 
+#[test]
+fn test_nvim_di_quote_cursor_before_string() {
+    // No quote before the cursor: Vim uses the first string on the line.
+    nvim_case("say \"hello\" end\n", 0, 0, "di\"", "say \"\" end\n", 0, 5);
+}
+
+#[test]
+fn test_nvim_di_quote_cursor_on_closing_quote() {
+    nvim_case("say \"hello\" end\n", 0, 10, "di\"", "say \"\" end\n", 0, 5);
+}
+
+#[test]
+fn test_nvim_di_paren_cursor_on_closing_paren() {
+    nvim_case("f(a, (b)) x\n", 0, 8, "di(", "f() x\n", 0, 2);
+}
+
+#[test]
+fn test_nvim_ya_brace_then_paste() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "x {a {b}} y\n");
+    engine.update_syntax();
+    engine.view_mut().cursor.col = 6;
+    engine.feed_keys("ya{");
+    assert_eq!(engine.registers.get(&'"').unwrap().0, "{b}");
+    engine.feed_keys("di{");
+    assert_eq!(engine.buffer().to_string(), "x {a {}} y\n");
+    // One undo restores the whole deletion.
+    engine.feed_keys("u");
+    assert_eq!(engine.buffer().to_string(), "x {a {b}} y\n");
+}
+
 // ── Phase 4 Batch 6: J edge cases ───────────────────────────────────────
 
 #[test]
@@ -24364,9 +27212,28 @@ fn test_nvim_R_replaces_chars() {
 }
 
 #[test]
-fn test_nvim_R_past_eol_appends() {
-    // R past end-of-line appends rather than overwriting
-    nvim_case("ab\n", 0, 0, "RXYZ<Esc>", "XYZ\n", 0, 2);
+fn test_nvim_R_past_eol_appends() {
+    // R past end-of-line appends rather than overwriting
+    nvim_case("ab\n", 0, 0, "RXYZ<Esc>", "XYZ\n", 0, 2);
+}
+
+#[test]
+fn test_replace_mode_backspace_restores_original_chars() {
+    // Backspace undoes overwrites and removes appended chars; going back
+    // past where R started only moves the cursor.
+    nvim_case("ab\n", 0, 1, "RXYZ<BS><BS><BS><BS><Esc>", "ab\n", 0, 0);
+    nvim_case("abcd\n", 0, 0, "RXYZ<BS>W<Esc>", "XYWd\n", 0, 2);
+}
+
+#[test]
+fn test_replace_mode_session_is_one_undo_group() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abcdef\n");
+    engine.update_syntax();
+    engine.feed_keys("RXYZ<Esc>");
+    assert_eq!(engine.buffer().to_string(), "XYZdef\n");
+    engine.feed_keys("u");
+    assert_eq!(engine.buffer().to_string(), "abcdef\n");
 }
 
 #[test]
@@ -24940,1458 +27807,2327 @@ fn test_nvim_ctrl_w_w_cycles_windows() {
 }
 
 #[test]
-fn test_nvim_ctrl_w_q_closes_window() {
-    // Ctrl-W q: close current window
+fn test_nvim_ctrl_w_q_closes_window() {
+    // Ctrl-W q: close current window
+    let mut engine = Engine::new();
+    engine.feed_keys(":split<CR>");
+    assert_eq!(engine.windows.len(), 2);
+    engine.feed_keys("<C-w>q");
+    assert_eq!(engine.windows.len(), 1);
+}
+
+#[test]
+fn test_nvim_ctrl_w_o_only() {
+    // Ctrl-W o: close all windows except current
+    let mut engine = Engine::new();
+    engine.feed_keys(":split<CR>");
+    engine.feed_keys(":vsplit<CR>");
+    assert!(engine.windows.len() >= 2);
+    engine.feed_keys("<C-w>o");
+    assert_eq!(engine.windows.len(), 1);
+}
+
+// -- :echo --
+
+#[test]
+fn test_nvim_echo_sets_message() {
+    let mut engine = Engine::new();
+    engine.feed_keys(":echo hello world<CR>");
+    assert_eq!(engine.message, "hello world");
+}
+
+#[test]
+fn test_nvim_echo_empty() {
+    let mut engine = Engine::new();
+    engine.message = "prior".to_string();
+    engine.feed_keys(":echo<CR>");
+    assert_eq!(engine.message, "", "bare :echo clears the message");
+}
+
+// -- Buffer operations --
+
+#[test]
+fn test_nvim_w_save_no_file_errors() {
+    // :w without a filename on an unnamed buffer should set an error message
+    let mut engine = Engine::new();
+    engine.feed_keys(":w<CR>");
+    assert!(
+        !engine.message.is_empty(),
+        ":w on unnamed buffer should produce a message"
+    );
+}
+
+#[test]
+fn test_nvim_bn_with_one_buffer_noop_or_msg() {
+    // :bn with only one buffer — either noop or informs user
+    let mut engine = Engine::new();
+    let buf_before = engine.active_buffer_id();
+    engine.feed_keys(":bn<CR>");
+    let buf_after = engine.active_buffer_id();
+    assert_eq!(
+        buf_before, buf_after,
+        "single-buffer :bn should stay on same buffer"
+    );
+}
+
+// -- Motions: word/bigword boundaries --
+
+#[test]
+fn test_nvim_e_word_end_inner() {
+    // e goes to end of current word
+    nvim_case("hello world\n", 0, 0, "e", "hello world\n", 0, 4);
+}
+
+#[test]
+fn test_nvim_e_word_end_from_middle() {
+    // From middle of word, e still goes to end
+    nvim_case("hello world\n", 0, 2, "e", "hello world\n", 0, 4);
+}
+
+#[test]
+fn test_nvim_ge_word_end_backward() {
+    // ge goes to end of previous word
+    nvim_case("hello world\n", 0, 7, "ge", "hello world\n", 0, 4);
+}
+
+// -- Increment / decrement edge cases --
+
+#[test]
+fn test_nvim_ctrl_a_on_hex() {
+    // Ctrl-A on 0x09 → 0x0a (Vim treats 0x-prefix as hex)
+    nvim_case("val=0x09\n", 0, 0, "<C-a>", "val=0x0a\n", 0, 7);
+}
+
+#[test]
+fn test_nvim_ctrl_a_on_hex_cursor_inside() {
+    // Cursor on the final '9' of 0x09; Ctrl-A should still increment hex.
+    nvim_case("v=0x09\n", 0, 5, "<C-a>", "v=0x0a\n", 0, 5);
+}
+
+#[test]
+fn test_nvim_ctrl_x_on_hex_decrement() {
+    // Ctrl-X on 0x10 → 0x0f
+    nvim_case("n=0x10\n", 0, 0, "<C-x>", "n=0x0f\n", 0, 5);
+}
+
+#[test]
+fn test_nvim_ctrl_x_past_zero() {
+    // Ctrl-X on 0 becomes -1
+    nvim_case("n=0\n", 0, 0, "<C-x>", "n=-1\n", 0, 3);
+}
+
+// -- Search history --
+
+#[test]
+fn test_nvim_slash_reuses_prev_pattern_with_n() {
+    // After /foo<CR>, pressing n finds next occurrence
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "foo bar foo baz foo\n");
+    engine.update_syntax();
+    engine.feed_keys("/foo<CR>");
+    engine.feed_keys("nn");
+    assert_eq!(engine.view().cursor.col, 16);
+}
+
+// -- Ex command ranges --
+
+#[test]
+fn test_nvim_colon_N_goto_line() {
+    // :3<CR> goes to line 3 (1-indexed)
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
+    engine.update_syntax();
+    engine.feed_keys(":3<CR>");
+    assert_eq!(engine.view().cursor.line, 2);
+}
+
+#[test]
+fn test_nvim_range_delete_two_lines() {
+    // :2,3d deletes lines 2 and 3
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.update_syntax();
+    engine.feed_keys(":2,3d<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nd\n");
+}
+
+// ── Phase 4 Batch 15: :copy/:move/:sort, gi/gv, jumplist, etc. ──────────
+// Mined from test_ex_cmds.vim, test_sort.vim, test_jumplist.vim, test_normal.vim
+
+// -- :copy / :t (simple form with space) --
+
+#[test]
+fn test_nvim_copy_current_to_after() {
+    // :copy 2 copies current line (line 1 = "a") to after line 2 ("b").
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.update_syntax();
+    engine.feed_keys(":copy 2<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\na\nc\n");
+}
+
+// -- :move (simple form with space) --
+
+#[test]
+fn test_nvim_move_current_to_bottom() {
+    // :move $ moves current line to end
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.update_syntax();
+    engine.feed_keys(":move $<CR>");
+    // move current (line 0 "a") past $ → content ends with "a\n"
+    assert!(
+        engine.buffer().to_string().ends_with("a\n"),
+        "expected :move $ to place 'a' at end, got {:?}",
+        engine.buffer().to_string()
+    );
+}
+
+// -- :sort --
+
+#[test]
+fn test_nvim_sort_basic() {
+    // :sort sorts the whole buffer alphabetically
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "charlie\nalpha\nbravo\n");
+    engine.update_syntax();
+    engine.feed_keys(":sort<CR>");
+    assert_eq!(engine.buffer().to_string(), "alpha\nbravo\ncharlie\n");
+}
+
+#[test]
+fn test_nvim_sort_reverse_r_flag() {
+    // :sort r (VimCode flag form) reverses alphabetical order
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
+    engine.update_syntax();
+    engine.feed_keys(":sort r<CR>");
+    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
+}
+
+#[test]
+fn test_nvim_sort_unique() {
+    // :sort u removes duplicates
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "b\na\nb\nc\na\n");
+    engine.update_syntax();
+    engine.feed_keys(":sort u<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
+}
+
+// -- gi: restart insert at last position --
+
+#[test]
+fn test_nvim_gi_restart_insert() {
+    // After iXYZ<Esc>, cursor returns to the char before, then moving away
+    // and pressing gi re-enters insert at the last insert position.
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "abc\ndef\n");
+    engine.update_syntax();
+    // Insert "XY" at start of line 1
+    engine.feed_keys("iXY<Esc>");
+    // Move away
+    engine.feed_keys("jG");
+    // gi — restart insert mode at last insert position
+    engine.feed_keys("gi");
+    assert_eq!(engine.mode, Mode::Insert);
+}
+
+// -- gv: reselect last visual --
+
+#[test]
+fn test_nvim_gv_reselects_last_visual() {
+    // After Vjd then gv: restore visual selection of what was deleted (on that range).
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "alpha\nbeta\ngamma\n");
+    engine.update_syntax();
+    // Visual-select the first line + part of second, then cancel
+    engine.feed_keys("v3l<Esc>");
+    // gv reselects
+    engine.feed_keys("gv");
+    assert_eq!(engine.mode, Mode::Visual);
+}
+
+// -- Jump list (Ctrl-O / Ctrl-I) --
+
+#[test]
+fn test_nvim_ctrl_o_after_G_jumps_back() {
+    // G pushes jump location; <C-o> goes back to line 0
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
+    engine.update_syntax();
+    engine.feed_keys("G");
+    assert_eq!(engine.view().cursor.line, 4);
+    engine.feed_keys("<C-o>");
+    assert_eq!(engine.view().cursor.line, 0);
+}
+
+#[test]
+fn test_nvim_ctrl_i_after_ctrl_o_jumps_forward() {
+    // After <C-o> goes back, <C-i> goes forward
     let mut engine = Engine::new();
-    engine.feed_keys(":split<CR>");
-    assert_eq!(engine.windows.len(), 2);
-    engine.feed_keys("<C-w>q");
-    assert_eq!(engine.windows.len(), 1);
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
+    engine.update_syntax();
+    engine.feed_keys("G");
+    engine.feed_keys("<C-o>");
+    assert_eq!(engine.view().cursor.line, 0);
+    engine.feed_keys("<C-i>");
+    assert_eq!(engine.view().cursor.line, 4);
 }
 
+// -- Change list (g;) --
+
 #[test]
-fn test_nvim_ctrl_w_o_only() {
-    // Ctrl-W o: close all windows except current
+fn test_nvim_g_semicolon_jumps_to_change() {
+    // g; jumps to previous change position. Exact landing line depends on
+    // implementation, but it must move cursor off line 0 onto a change location.
     let mut engine = Engine::new();
-    engine.feed_keys(":split<CR>");
-    engine.feed_keys(":vsplit<CR>");
-    assert!(engine.windows.len() >= 2);
-    engine.feed_keys("<C-w>o");
-    assert_eq!(engine.windows.len(), 1);
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.update_syntax();
+    engine.feed_keys("Ghello<Esc>"); // change at bottom
+    engine.feed_keys("gg"); // move to line 0
+    assert_eq!(engine.view().cursor.line, 0);
+    engine.feed_keys("g;"); // jump back to change
+    assert_ne!(engine.view().cursor.line, 0, "g; should leave line 0");
 }
 
-// -- :echo --
+// -- Buffer commands --
 
 #[test]
-fn test_nvim_echo_sets_message() {
+fn test_nvim_enew_creates_empty_buffer() {
+    // :enew opens a new empty buffer
     let mut engine = Engine::new();
-    engine.feed_keys(":echo hello world<CR>");
-    assert_eq!(engine.message, "hello world");
+    engine.buffer_mut().insert(0, "stuff\n");
+    engine.update_syntax();
+    let before = engine.active_buffer_id();
+    engine.feed_keys(":enew<CR>");
+    let after = engine.active_buffer_id();
+    assert_ne!(before, after, ":enew should switch to a new buffer");
 }
 
+// -- Window move (<C-w>H/J/K/L) --
+
 #[test]
-fn test_nvim_echo_empty() {
+fn test_nvim_ctrl_w_H_moves_window_far_left() {
+    // <C-w>H: move current window to far left (make full-height)
+    // At minimum, it shouldn't crash.
     let mut engine = Engine::new();
-    engine.message = "prior".to_string();
-    engine.feed_keys(":echo<CR>");
-    assert_eq!(engine.message, "", "bare :echo clears the message");
+    engine.feed_keys(":split<CR>");
+    engine.feed_keys("<C-w>H");
+    // Still has 2 windows
+    assert_eq!(engine.windows.len(), 2);
 }
 
-// -- Buffer operations --
+// -- gu/gU with operator+motion combinations --
 
 #[test]
-fn test_nvim_w_save_no_file_errors() {
-    // :w without a filename on an unnamed buffer should set an error message
-    let mut engine = Engine::new();
-    engine.feed_keys(":w<CR>");
-    assert!(
-        !engine.message.is_empty(),
-        ":w on unnamed buffer should produce a message"
-    );
+fn test_nvim_gU_word_forward() {
+    // gUw uppercases current word forward
+    nvim_case("hello world\n", 0, 0, "gUw", "HELLO world\n", 0, 0);
 }
 
 #[test]
-fn test_nvim_bn_with_one_buffer_noop_or_msg() {
-    // :bn with only one buffer — either noop or informs user
+fn test_nvim_gu_inner_word() {
+    // guiw lowercases word under cursor; cursor stays at invocation position in
+    // VimCode (Vim moves to start of text object — see #[ignore] test for that nuance).
     let mut engine = Engine::new();
-    let buf_before = engine.active_buffer_id();
-    engine.feed_keys(":bn<CR>");
-    let buf_after = engine.active_buffer_id();
-    assert_eq!(
-        buf_before, buf_after,
-        "single-buffer :bn should stay on same buffer"
-    );
+    engine.buffer_mut().insert(0, "HELLO WORLD\n");
+    engine.update_syntax();
+    engine.view_mut().cursor.col = 2;
+    engine.feed_keys("guiw");
+    assert_eq!(engine.buffer().to_string(), "hello WORLD\n");
 }
 
-// -- Motions: word/bigword boundaries --
-
 #[test]
-fn test_nvim_e_word_end_inner() {
-    // e goes to end of current word
-    nvim_case("hello world\n", 0, 0, "e", "hello world\n", 0, 4);
+fn test_nvim_g_tilde_word() {
+    // g~w toggles case of word
+    nvim_case("Hello\n", 0, 0, "g~w", "hELLO\n", 0, 0);
 }
 
+// -- Counts with operators --
+
 #[test]
-fn test_nvim_e_word_end_from_middle() {
-    // From middle of word, e still goes to end
-    nvim_case("hello world\n", 0, 2, "e", "hello world\n", 0, 4);
+fn test_nvim_count_dw_deletes_n_words() {
+    // 3dw deletes 3 words
+    nvim_case("a b c d e\n", 0, 0, "3dw", "d e\n", 0, 0);
 }
 
 #[test]
-fn test_nvim_ge_word_end_backward() {
-    // ge goes to end of previous word
-    nvim_case("hello world\n", 0, 7, "ge", "hello world\n", 0, 4);
+fn test_nvim_count_cw_changes_n_words() {
+    // 2cwXYZ<Esc> changes 2 words to XYZ
+    nvim_case(
+        "aaa bbb ccc ddd\n",
+        0,
+        0,
+        "2cwXYZ<Esc>",
+        "XYZ ccc ddd\n",
+        0,
+        2,
+    );
 }
 
-// -- Increment / decrement edge cases --
+// -- Text object edges --
 
 #[test]
-fn test_nvim_ctrl_a_on_hex() {
-    // Ctrl-A on 0x09 → 0x0a (Vim treats 0x-prefix as hex)
-    nvim_case("val=0x09\n", 0, 0, "<C-a>", "val=0x0a\n", 0, 7);
+fn test_nvim_daw_at_word_boundary() {
+    // daw on "b" of "bar" deletes "bar " — cursor ends on "b" of "baz" (col 4)
+    nvim_case("foo bar baz\n", 0, 4, "daw", "foo baz\n", 0, 4);
 }
 
 #[test]
-fn test_nvim_ctrl_a_on_hex_cursor_inside() {
-    // Cursor on the final '9' of 0x09; Ctrl-A should still increment hex.
-    nvim_case("v=0x09\n", 0, 5, "<C-a>", "v=0x0a\n", 0, 5);
+fn test_nvim_das_deletes_sentence() {
+    // das deletes current sentence including trailing space
+    let mut engine = Engine::new();
+    engine
+        .buffer_mut()
+        .insert(0, "First one. Second one. Third.\n");
+    engine.update_syntax();
+    engine.view_mut().cursor.col = 2;
+    engine.feed_keys("das");
+    assert_eq!(engine.buffer().to_string(), "Second one. Third.\n");
 }
 
+// -- :set query and toggle --
+
 #[test]
-fn test_nvim_ctrl_x_on_hex_decrement() {
-    // Ctrl-X on 0x10 → 0x0f
-    nvim_case("n=0x10\n", 0, 0, "<C-x>", "n=0x0f\n", 0, 5);
+fn test_nvim_set_nu_enables_number() {
+    // :set number enables line numbers
+    let mut engine = Engine::new();
+    engine.feed_keys(":set number<CR>");
+    assert_eq!(
+        engine.settings.line_numbers,
+        crate::core::settings::LineNumberMode::Absolute
+    );
 }
 
 #[test]
-fn test_nvim_ctrl_x_past_zero() {
-    // Ctrl-X on 0 becomes -1
-    nvim_case("n=0\n", 0, 0, "<C-x>", "n=-1\n", 0, 3);
+fn test_nvim_set_nonu_disables_number() {
+    // :set nonumber disables line numbers
+    let mut engine = Engine::new();
+    engine.feed_keys(":set number<CR>");
+    engine.feed_keys(":set nonumber<CR>");
+    assert_eq!(
+        engine.settings.line_numbers,
+        crate::core::settings::LineNumberMode::None
+    );
 }
 
-// -- Search history --
+// -- Colon command abbreviations --
 
 #[test]
-fn test_nvim_slash_reuses_prev_pattern_with_n() {
-    // After /foo<CR>, pressing n finds next occurrence
+fn test_nvim_q_abbrev_for_quit() {
+    // :q is not abbrev but explicit command — verify not an error
+    // The dispatcher should accept :q; we check by ensuring no panic and a known message.
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "foo bar foo baz foo\n");
-    engine.update_syntax();
-    engine.feed_keys("/foo<CR>");
-    engine.feed_keys("nn");
-    assert_eq!(engine.view().cursor.col, 16);
+    let _ = engine.execute_command("q");
+    // No assertion on result: just confirm it doesn't panic
 }
 
-// -- Ex command ranges --
+// -- :pwd --
 
 #[test]
-fn test_nvim_colon_N_goto_line() {
-    // :3<CR> goes to line 3 (1-indexed)
+fn test_nvim_pwd_sets_message() {
+    // :pwd reports the current working directory in the message bar
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
-    engine.update_syntax();
-    engine.feed_keys(":3<CR>");
-    assert_eq!(engine.view().cursor.line, 2);
+    engine.feed_keys(":pwd<CR>");
+    assert!(
+        !engine.message.is_empty(),
+        ":pwd should populate the message"
+    );
 }
 
+// ── Phase 4 Batch 15.5: #112 ex-command form fixes ────────────────────────
+// Ranged :copy/:move, concatenated :tN/:mN/:coN, and :sort! bang.
+
+// -- Ranged :copy (:N,Mco<dest>) --
+
 #[test]
-fn test_nvim_range_delete_two_lines() {
-    // :2,3d deletes lines 2 and 3
+fn test_nvim_copy_range_to_after() {
+    // :1,2co3 copies lines 1-2 ("a", "b") to after line 3 ("c").
     let mut engine = Engine::new();
     engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":2,3d<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nd\n");
+    engine.feed_keys(":1,2co3<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\na\nb\nd\n");
 }
 
-// ── Phase 4 Batch 15: :copy/:move/:sort, gi/gv, jumplist, etc. ──────────
-// Mined from test_ex_cmds.vim, test_sort.vim, test_jumplist.vim, test_normal.vim
-
-// -- :copy / :t (simple form with space) --
-
 #[test]
-fn test_nvim_copy_current_to_after() {
-    // :copy 2 copies current line (line 1 = "a") to after line 2 ("b").
+fn test_nvim_copy_single_line_to_after() {
+    // :2co4 copies line 2 to after line 4
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":copy 2<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\na\nc\n");
+    engine.feed_keys(":2co4<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\nd\nb\n");
 }
 
-// -- :move (simple form with space) --
+// -- Ranged :move --
 
 #[test]
-fn test_nvim_move_current_to_bottom() {
-    // :move $ moves current line to end
+fn test_nvim_move_single_line_forward() {
+    // :1m3 moves line 1 ("a") to after line 3 ("c").
     let mut engine = Engine::new();
     engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":move $<CR>");
-    // move current (line 0 "a") past $ → content ends with "a\n"
-    assert!(
-        engine.buffer().to_string().ends_with("a\n"),
-        "expected :move $ to place 'a' at end, got {:?}",
-        engine.buffer().to_string()
-    );
+    engine.feed_keys(":1m3<CR>");
+    assert_eq!(engine.buffer().to_string(), "b\nc\na\nd\n");
 }
 
-// -- :sort --
-
 #[test]
-fn test_nvim_sort_basic() {
-    // :sort sorts the whole buffer alphabetically
+fn test_nvim_move_line_to_top() {
+    // :3m0 moves line 3 to the top
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "charlie\nalpha\nbravo\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":sort<CR>");
-    assert_eq!(engine.buffer().to_string(), "alpha\nbravo\ncharlie\n");
+    engine.feed_keys(":3m0<CR>");
+    assert_eq!(engine.buffer().to_string(), "c\na\nb\nd\n");
 }
 
 #[test]
-fn test_nvim_sort_reverse_r_flag() {
-    // :sort r (VimCode flag form) reverses alphabetical order
+fn test_nvim_move_range_forward() {
+    // :1,2m4 moves lines 1-2 to after line 4
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":sort r<CR>");
-    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
+    engine.feed_keys(":1,2m4<CR>");
+    assert_eq!(engine.buffer().to_string(), "c\nd\na\nb\n");
 }
 
+// -- Concatenated forms (no space): :tN, :mN, :coN --
+
 #[test]
-fn test_nvim_sort_unique() {
-    // :sort u removes duplicates
+fn test_nvim_t_concat_form() {
+    // :t3 copies current line (line 1 = "a") to after line 3 ("c").
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "b\na\nb\nc\na\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    engine.feed_keys(":sort u<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
+    engine.feed_keys(":t3<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\na\nd\n");
 }
 
-// -- gi: restart insert at last position --
-
 #[test]
-fn test_nvim_gi_restart_insert() {
-    // After iXYZ<Esc>, cursor returns to the char before, then moving away
-    // and pressing gi re-enters insert at the last insert position.
+fn test_nvim_m_concat_form() {
+    // :m3 moves current line ("a") to after line 3 ("c").
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "abc\ndef\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
     engine.update_syntax();
-    // Insert "XY" at start of line 1
-    engine.feed_keys("iXY<Esc>");
-    // Move away
-    engine.feed_keys("jG");
-    // gi — restart insert mode at last insert position
-    engine.feed_keys("gi");
-    assert_eq!(engine.mode, Mode::Insert);
+    engine.feed_keys(":m3<CR>");
+    assert_eq!(engine.buffer().to_string(), "b\nc\na\nd\n");
 }
 
-// -- gv: reselect last visual --
-
 #[test]
-fn test_nvim_gv_reselects_last_visual() {
-    // After Vjd then gv: restore visual selection of what was deleted (on that range).
+fn test_nvim_co_concat_form() {
+    // :co2 copies current line ("a") to after line 2 ("b").
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "alpha\nbeta\ngamma\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
     engine.update_syntax();
-    // Visual-select the first line + part of second, then cancel
-    engine.feed_keys("v3l<Esc>");
-    // gv reselects
-    engine.feed_keys("gv");
-    assert_eq!(engine.mode, Mode::Visual);
+    engine.feed_keys(":co2<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nb\na\nc\n");
 }
 
-// -- Jump list (Ctrl-O / Ctrl-I) --
+// -- :sort! bang for reverse --
 
 #[test]
-fn test_nvim_ctrl_o_after_G_jumps_back() {
-    // G pushes jump location; <C-o> goes back to line 0
+fn test_nvim_sort_bang_reverses() {
+    // :sort! reverses alphabetical order (synonym for :sort r)
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
+    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
     engine.update_syntax();
-    engine.feed_keys("G");
-    assert_eq!(engine.view().cursor.line, 4);
-    engine.feed_keys("<C-o>");
-    assert_eq!(engine.view().cursor.line, 0);
+    engine.feed_keys(":sort!<CR>");
+    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
 }
 
 #[test]
-fn test_nvim_ctrl_i_after_ctrl_o_jumps_forward() {
-    // After <C-o> goes back, <C-i> goes forward
+fn test_nvim_sort_bang_with_flags() {
+    // :sort! u combines reverse with unique
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\ne\n");
+    engine.buffer_mut().insert(0, "a\nb\na\nc\nb\n");
     engine.update_syntax();
-    engine.feed_keys("G");
-    engine.feed_keys("<C-o>");
-    assert_eq!(engine.view().cursor.line, 0);
-    engine.feed_keys("<C-i>");
-    assert_eq!(engine.view().cursor.line, 4);
+    engine.feed_keys(":sort! u<CR>");
+    assert_eq!(engine.buffer().to_string(), "c\nb\na\n");
 }
 
-// -- Change list (g;) --
+// -- Regressions: make sure existing forms still work --
 
 #[test]
-fn test_nvim_g_semicolon_jumps_to_change() {
-    // g; jumps to previous change position. Exact landing line depends on
-    // implementation, but it must move cursor off line 0 onto a change location.
+fn test_nvim_colon_zero_still_goes_to_first() {
+    // :0 must still move cursor to line 0 (not be rejected as invalid range)
     let mut engine = Engine::new();
     engine.buffer_mut().insert(0, "a\nb\nc\n");
     engine.update_syntax();
-    engine.feed_keys("Ghello<Esc>"); // change at bottom
-    engine.feed_keys("gg"); // move to line 0
+    engine.view_mut().cursor.line = 2;
+    engine.feed_keys(":0<CR>");
     assert_eq!(engine.view().cursor.line, 0);
-    engine.feed_keys("g;"); // jump back to change
-    assert_ne!(engine.view().cursor.line, 0, "g; should leave line 0");
 }
 
-// -- Buffer commands --
-
 #[test]
-fn test_nvim_enew_creates_empty_buffer() {
-    // :enew opens a new empty buffer
+fn test_nvim_sort_with_existing_r_flag_still_works() {
+    // :sort r (old form) should keep working unchanged.
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "stuff\n");
+    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
     engine.update_syntax();
-    let before = engine.active_buffer_id();
-    engine.feed_keys(":enew<CR>");
-    let after = engine.active_buffer_id();
-    assert_ne!(before, after, ":enew should switch to a new buffer");
+    engine.feed_keys(":sort r<CR>");
+    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
 }
 
-// -- Window move (<C-w>H/J/K/L) --
+// ── #114 follow-up: 1-based line addresses ─────────────────────────────
 
 #[test]
-fn test_nvim_ctrl_w_H_moves_window_far_left() {
-    // <C-w>H: move current window to far left (make full-height)
-    // At minimum, it shouldn't crash.
+fn test_nvim_copy_address_is_1_based() {
+    // :copy 1 copies current line to after line 1 (= "a", the first line).
+    // Under VimCode's old 0-based parse_line_address, this would have put
+    // the copy after "b" instead.
     let mut engine = Engine::new();
-    engine.feed_keys(":split<CR>");
-    engine.feed_keys("<C-w>H");
-    // Still has 2 windows
-    assert_eq!(engine.windows.len(), 2);
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.update_syntax();
+    engine.view_mut().cursor.line = 2; // yanking "c"
+    engine.feed_keys(":copy 1<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nc\nb\nc\n");
 }
 
-// -- gu/gU with operator+motion combinations --
-
 #[test]
-fn test_nvim_gU_word_forward() {
-    // gUw uppercases current word forward
-    nvim_case("hello world\n", 0, 0, "gUw", "HELLO world\n", 0, 0);
+fn test_nvim_move_address_is_1_based() {
+    // :3m1 moves line 3 ("c") to after line 1 ("a").
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.update_syntax();
+    engine.feed_keys(":3m1<CR>");
+    assert_eq!(engine.buffer().to_string(), "a\nc\nb\nd\n");
 }
 
 #[test]
-fn test_nvim_gu_inner_word() {
-    // guiw lowercases word under cursor; cursor stays at invocation position in
-    // VimCode (Vim moves to start of text object — see #[ignore] test for that nuance).
+fn test_nvim_copy_to_0_inserts_before_first_line() {
+    // :copy 0 (or :co0) inserts the copy at the very top (before line 1).
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "HELLO WORLD\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
     engine.update_syntax();
-    engine.view_mut().cursor.col = 2;
-    engine.feed_keys("guiw");
-    assert_eq!(engine.buffer().to_string(), "hello WORLD\n");
+    engine.view_mut().cursor.line = 2; // yank "c"
+    engine.feed_keys(":copy 0<CR>");
+    assert_eq!(engine.buffer().to_string(), "c\na\nb\nc\n");
 }
 
 #[test]
-fn test_nvim_g_tilde_word() {
-    // g~w toggles case of word
-    nvim_case("Hello\n", 0, 0, "g~w", "hELLO\n", 0, 0);
+fn test_nvim_move_to_0_moves_to_top() {
+    // :3m0 moves line 3 ("c") to the very top.
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.update_syntax();
+    engine.feed_keys(":3m0<CR>");
+    assert_eq!(engine.buffer().to_string(), "c\na\nb\nd\n");
 }
 
-// -- Counts with operators --
+// ── Phase 4 Batch 16: visual block I/A, :retab, :noh, marks, more edges ──
+// Mined from test_visual.vim, test_retab.vim, test_mark.vim, test_search.vim
 
-#[test]
-fn test_nvim_count_dw_deletes_n_words() {
-    // 3dw deletes 3 words
-    nvim_case("a b c d e\n", 0, 0, "3dw", "d e\n", 0, 0);
-}
+// -- Visual block Insert (I) --
 
 #[test]
-fn test_nvim_count_cw_changes_n_words() {
-    // 2cwXYZ<Esc> changes 2 words to XYZ
-    nvim_case(
-        "aaa bbb ccc ddd\n",
-        0,
-        0,
-        "2cwXYZ<Esc>",
-        "XYZ ccc ddd\n",
-        0,
-        2,
-    );
+fn test_nvim_visual_block_I_inserts_prefix() {
+    // Select a column of 3 lines, press I<text><Esc>; text prepended to each line
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "one\ntwo\nthree\n");
+    engine.update_syntax();
+    // Visual-block selection down 2 lines
+    engine.feed_keys("<C-v>jjI# <Esc>");
+    assert_eq!(engine.buffer().to_string(), "# one\n# two\n# three\n");
 }
 
-// -- Text object edges --
-
-#[test]
-fn test_nvim_daw_at_word_boundary() {
-    // daw on "b" of "bar" deletes "bar " — cursor ends on "b" of "baz" (col 4)
-    nvim_case("foo bar baz\n", 0, 4, "daw", "foo baz\n", 0, 4);
-}
+// -- Visual block Append (A) --
 
 #[test]
-fn test_nvim_das_deletes_sentence() {
-    // das deletes current sentence including trailing space
+fn test_nvim_visual_block_A_appends_suffix() {
+    // Block-select with $ inside visual mode; A appends at each line's actual
+    // end (Vim "virtual-end" behaviour). Pressing $ BEFORE <C-v> is different
+    // — it anchors the block at a fixed column and does NOT virtualise the
+    // right edge. This test exercises the virtual-end path.
     let mut engine = Engine::new();
-    engine
-        .buffer_mut()
-        .insert(0, "First one. Second one. Third.\n");
+    engine.buffer_mut().insert(0, "one\ntwo\nthree\n");
     engine.update_syntax();
-    engine.view_mut().cursor.col = 2;
-    engine.feed_keys("das");
-    assert_eq!(engine.buffer().to_string(), "Second one. Third.\n");
+    engine.feed_keys("<C-v>jj$A!<Esc>");
+    assert_eq!(engine.buffer().to_string(), "one!\ntwo!\nthree!\n");
 }
 
-// -- :set query and toggle --
+// -- :nohlsearch / :noh --
 
 #[test]
-fn test_nvim_set_nu_enables_number() {
-    // :set number enables line numbers
+fn test_nvim_noh_clears_search_highlight() {
+    // After /foo, search_matches is set. :noh should clear it.
     let mut engine = Engine::new();
-    engine.feed_keys(":set number<CR>");
-    assert_eq!(
-        engine.settings.line_numbers,
-        crate::core::settings::LineNumberMode::Absolute
+    engine.buffer_mut().insert(0, "foo bar foo\n");
+    engine.update_syntax();
+    engine.feed_keys("/foo<CR>");
+    // Something searched; now clear highlights
+    engine.feed_keys(":noh<CR>");
+    // search_matches (the highlight list) should be empty
+    assert!(
+        engine.search_matches.is_empty(),
+        "expected no search_matches after :noh"
     );
 }
 
 #[test]
-fn test_nvim_set_nonu_disables_number() {
-    // :set nonumber disables line numbers
+fn test_noh_keeps_pattern_for_n() {
+    let mut engine = engine_with_text("bar foo bar foo\n");
+    engine.feed_keys("/foo<CR>");
+    assert_eq!(engine.view().cursor.col, 4);
+    engine.feed_keys(":nohlsearch<CR>");
+    assert!(engine.search_matches.is_empty());
+    assert!(engine.search_highlight_cleared);
+    assert_eq!(engine.search_query, "foo");
+    // `n` re-runs the search and brings highlights back.
+    engine.feed_keys("n");
+    assert_eq!(engine.view().cursor.col, 12);
+    assert_eq!(engine.search_matches.len(), 2);
+    assert!(!engine.search_highlight_cleared);
+}
+
+// -- :r[ead] {file} --
+
+#[test]
+fn test_nvim_read_nonexistent_file_errors() {
+    // :r on a file that doesn't exist should produce a "Cannot read file" message.
     let mut engine = Engine::new();
-    engine.feed_keys(":set number<CR>");
-    engine.feed_keys(":set nonumber<CR>");
-    assert_eq!(
-        engine.settings.line_numbers,
-        crate::core::settings::LineNumberMode::None
+    engine.feed_keys(":r /path/does/not/exist/xyz\n<CR>");
+    assert!(
+        engine.message.to_lowercase().contains("cannot read")
+            || engine.message.to_lowercase().contains("no such"),
+        "expected read-file error message, got: {:?}",
+        engine.message
     );
 }
 
-// -- Colon command abbreviations --
+// -- :retab --
 
 #[test]
-fn test_nvim_q_abbrev_for_quit() {
-    // :q is not abbrev but explicit command — verify not an error
-    // The dispatcher should accept :q; we check by ensuring no panic and a known message.
+fn test_nvim_retab_converts_tabs_to_spaces() {
+    // With expandtab and tabstop=4, :retab converts tabs to spaces.
     let mut engine = Engine::new();
-    let _ = engine.execute_command("q");
-    // No assertion on result: just confirm it doesn't panic
+    engine.settings.expand_tab = true;
+    engine.settings.tabstop = 4;
+    engine.buffer_mut().insert(0, "\thello\n");
+    engine.update_syntax();
+    engine.feed_keys(":retab<CR>");
+    assert_eq!(engine.buffer().to_string(), "    hello\n");
 }
 
-// -- :pwd --
+// -- Marks --
 
 #[test]
-fn test_nvim_pwd_sets_message() {
-    // :pwd reports the current working directory in the message bar
+fn test_nvim_lowercase_mark_set_and_jump() {
+    // ma on line 1, move away, then 'a jumps back to line 1
     let mut engine = Engine::new();
-    engine.feed_keys(":pwd<CR>");
-    assert!(
-        !engine.message.is_empty(),
-        ":pwd should populate the message"
-    );
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.update_syntax();
+    engine.view_mut().cursor.line = 1;
+    engine.feed_keys("ma"); // set mark 'a' here
+    engine.view_mut().cursor.line = 0;
+    engine.feed_keys("'a"); // jump to mark
+    assert_eq!(engine.view().cursor.line, 1);
 }
 
-// ── Phase 4 Batch 15.5: #112 ex-command form fixes ────────────────────────
-// Ranged :copy/:move, concatenated :tN/:mN/:coN, and :sort! bang.
-
-// -- Ranged :copy (:N,Mco<dest>) --
-
 #[test]
-fn test_nvim_copy_range_to_after() {
-    // :1,2co3 copies lines 1-2 ("a", "b") to after line 3 ("c").
+fn test_nvim_backtick_mark_preserves_col() {
+    // `a jumps to exact line+col of mark, not first-non-blank
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "hello\nworld\n");
     engine.update_syntax();
-    engine.feed_keys(":1,2co3<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\nc\na\nb\nd\n");
+    engine.view_mut().cursor.line = 1;
+    engine.view_mut().cursor.col = 3;
+    engine.feed_keys("ma");
+    engine.view_mut().cursor.line = 0;
+    engine.view_mut().cursor.col = 0;
+    engine.feed_keys("`a");
+    assert_eq!(engine.view().cursor.line, 1);
+    assert_eq!(engine.view().cursor.col, 3);
 }
 
+// -- Search highlighting / n with no match --
+
 #[test]
-fn test_nvim_copy_single_line_to_after() {
-    // :2co4 copies line 2 to after line 4
+fn test_nvim_n_with_no_prior_search_errors() {
+    // n without a prior search pattern should not move and should error.
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "hello\n");
     engine.update_syntax();
-    engine.feed_keys(":2co4<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\nc\nd\nb\n");
+    engine.feed_keys("n");
+    assert_eq!(engine.view().cursor.line, 0);
+    assert_eq!(engine.view().cursor.col, 0);
 }
 
-// -- Ranged :move --
+// -- Word motions edge cases --
 
 #[test]
-fn test_nvim_move_single_line_forward() {
-    // :1m3 moves line 1 ("a") to after line 3 ("c").
+fn test_nvim_w_at_end_of_file() {
+    // w at last word should stay within bounds
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "last\n");
     engine.update_syntax();
-    engine.feed_keys(":1m3<CR>");
-    assert_eq!(engine.buffer().to_string(), "b\nc\na\nd\n");
+    engine.feed_keys("$w");
+    // Cursor shouldn't panic or go past buffer; should stay at or near end.
+    assert!(engine.view().cursor.line <= 1);
 }
 
 #[test]
-fn test_nvim_move_line_to_top() {
-    // :3m0 moves line 3 to the top
+fn test_nvim_b_at_start_of_file() {
+    // b at col 0 line 0 stays put
+    nvim_case("hello\n", 0, 0, "b", "hello\n", 0, 0);
+}
+
+// -- Indent/dedent in visual mode --
+
+#[test]
+fn test_nvim_vis_greater_indents_selection() {
+    // Vj> indents two selected lines one shiftwidth
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "a\nb\nc\n");
     engine.update_syntax();
-    engine.feed_keys(":3m0<CR>");
-    assert_eq!(engine.buffer().to_string(), "c\na\nb\nd\n");
+    engine.settings.expand_tab = true;
+    engine.settings.shift_width = 2;
+    engine.feed_keys("Vj>");
+    assert_eq!(engine.buffer().to_string(), "  a\n  b\nc\n");
 }
 
 #[test]
-fn test_nvim_move_range_forward() {
-    // :1,2m4 moves lines 1-2 to after line 4
+fn test_nvim_vis_less_dedents_selection() {
+    // Vj< dedents two indented lines
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "  a\n  b\nc\n");
     engine.update_syntax();
-    engine.feed_keys(":1,2m4<CR>");
-    assert_eq!(engine.buffer().to_string(), "c\nd\na\nb\n");
+    engine.settings.expand_tab = true;
+    engine.settings.shift_width = 2;
+    engine.feed_keys("Vj<");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
 }
 
-// -- Concatenated forms (no space): :tN, :mN, :coN --
+// -- Count prefix with r --
 
 #[test]
-fn test_nvim_t_concat_form() {
-    // :t3 copies current line (line 1 = "a") to after line 3 ("c").
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
-    engine.update_syntax();
-    engine.feed_keys(":t3<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\nc\na\nd\n");
+fn test_nvim_5rX_replaces_5_chars() {
+    nvim_case("aaaaab\n", 0, 0, "5rX", "XXXXXb\n", 0, 4);
 }
 
+// -- Swap (xp) variations --
+
 #[test]
-fn test_nvim_m_concat_form() {
-    // :m3 moves current line ("a") to after line 3 ("c").
+fn test_nvim_dap_delete_around_paragraph() {
+    // dap deletes current paragraph (including trailing blank line)
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+    engine.buffer_mut().insert(0, "first\n\nsecond\n\nthird\n");
     engine.update_syntax();
-    engine.feed_keys(":m3<CR>");
-    assert_eq!(engine.buffer().to_string(), "b\nc\na\nd\n");
+    engine.view_mut().cursor.line = 2;
+    engine.feed_keys("dap");
+    // paragraph "second\n" with surrounding blanks collapsed
+    assert!(
+        engine.buffer().to_string().contains("first")
+            && engine.buffer().to_string().contains("third")
+            && !engine.buffer().to_string().contains("second"),
+        "dap should have removed 'second' paragraph: {:?}",
+        engine.buffer().to_string()
+    );
 }
 
+// -- Repeat (.) basic --
+
 #[test]
-fn test_nvim_co_concat_form() {
-    // :co2 copies current line ("a") to after line 2 ("b").
+fn test_nvim_dot_repeats_last_change() {
+    // After dw, moving and pressing . repeats the dw
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
+    engine.buffer_mut().insert(0, "one two three four\n");
     engine.update_syntax();
-    engine.feed_keys(":co2<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nb\na\nc\n");
+    engine.feed_keys("dw"); // delete "one "
+    engine.feed_keys("."); // delete "two "
+    assert_eq!(engine.buffer().to_string(), "three four\n");
 }
 
-// -- :sort! bang for reverse --
+// -- u / Ctrl-R undo/redo --
 
 #[test]
-fn test_nvim_sort_bang_reverses() {
-    // :sort! reverses alphabetical order (synonym for :sort r)
+fn test_nvim_u_undoes_insert() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
+    engine.buffer_mut().insert(0, "hello\n");
     engine.update_syntax();
-    engine.feed_keys(":sort!<CR>");
-    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
+    engine.feed_keys("iWORLD<Esc>");
+    assert_eq!(engine.buffer().to_string(), "WORLDhello\n");
+    engine.feed_keys("u");
+    assert_eq!(engine.buffer().to_string(), "hello\n");
 }
 
 #[test]
-fn test_nvim_sort_bang_with_flags() {
-    // :sort! u combines reverse with unique
+fn test_nvim_ctrl_r_redoes_undo() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\na\nc\nb\n");
+    engine.buffer_mut().insert(0, "hello\n");
     engine.update_syntax();
-    engine.feed_keys(":sort! u<CR>");
-    assert_eq!(engine.buffer().to_string(), "c\nb\na\n");
+    engine.feed_keys("iX<Esc>"); // insert X → "Xhello\n"
+    engine.feed_keys("u"); // undo → "hello\n"
+    engine.feed_keys("<C-r>"); // redo
+    assert_eq!(engine.buffer().to_string(), "Xhello\n");
 }
 
-// -- Regressions: make sure existing forms still work --
+// -- Command abbreviations --
 
 #[test]
-fn test_nvim_colon_zero_still_goes_to_first() {
-    // :0 must still move cursor to line 0 (not be rejected as invalid range)
+fn test_nvim_q_abbrev_does_not_explode() {
+    // :q on an unmodified empty buffer — just ensure dispatch doesn't panic.
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
-    engine.update_syntax();
-    engine.view_mut().cursor.line = 2;
-    engine.feed_keys(":0<CR>");
-    assert_eq!(engine.view().cursor.line, 0);
+    let _ = engine.execute_command("q");
 }
 
+// -- Settings query --
+
 #[test]
-fn test_nvim_sort_with_existing_r_flag_still_works() {
-    // :sort r (old form) should keep working unchanged.
+fn test_nvim_set_query_tabstop() {
+    // :set tabstop? should leave a message containing the current value
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "alpha\nbravo\ncharlie\n");
-    engine.update_syntax();
-    engine.feed_keys(":sort r<CR>");
-    assert_eq!(engine.buffer().to_string(), "charlie\nbravo\nalpha\n");
+    engine.settings.tabstop = 8;
+    engine.feed_keys(":set tabstop?<CR>");
+    assert!(
+        engine.message.contains('8'),
+        "expected tabstop=8 in message, got: {:?}",
+        engine.message
+    );
 }
 
-// ── #114 follow-up: 1-based line addresses ─────────────────────────────
+// --- g<Tab>: jump to last-accessed tab ---
 
 #[test]
-fn test_nvim_copy_address_is_1_based() {
-    // :copy 1 copies current line to after line 1 (= "a", the first line).
-    // Under VimCode's old 0-based parse_line_address, this would have put
-    // the copy after "b" instead.
+fn test_gtab_jumps_to_last_accessed_tab() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
-    engine.update_syntax();
-    engine.view_mut().cursor.line = 2; // yanking "c"
-    engine.feed_keys(":copy 1<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nc\nb\nc\n");
+    engine.new_tab(None); // tab 1
+    engine.new_tab(None); // tab 2 (active)
+    assert_eq!(engine.active_group().active_tab, 2);
+
+    // Go to tab 0
+    engine.goto_tab(0);
+    assert_eq!(engine.active_group().active_tab, 0);
+
+    // g<Tab> should jump back to tab 2 (last accessed)
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 2);
 }
 
 #[test]
-fn test_nvim_move_address_is_1_based() {
-    // :3m1 moves line 3 ("c") to after line 1 ("a").
+fn test_gtab_toggles_between_two_tabs() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
-    engine.update_syntax();
-    engine.feed_keys(":3m1<CR>");
-    assert_eq!(engine.buffer().to_string(), "a\nc\nb\nd\n");
+    engine.new_tab(None); // tab 1
+    engine.goto_tab(0);
+    assert_eq!(engine.active_group().active_tab, 0);
+
+    // Switch to tab 1 via gt
+    engine.feed_keys("gt");
+    assert_eq!(engine.active_group().active_tab, 1);
+
+    // g<Tab> should go back to tab 0
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 0);
+
+    // Another g<Tab> should toggle back to tab 1
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 1);
 }
 
 #[test]
-fn test_nvim_copy_to_0_inserts_before_first_line() {
-    // :copy 0 (or :co0) inserts the copy at the very top (before line 1).
+fn test_gtab_noop_with_single_tab() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
-    engine.update_syntax();
-    engine.view_mut().cursor.line = 2; // yank "c"
-    engine.feed_keys(":copy 0<CR>");
-    assert_eq!(engine.buffer().to_string(), "c\na\nb\nc\n");
+    // Only one tab — g<Tab> should be a no-op
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 0);
 }
 
 #[test]
-fn test_nvim_move_to_0_moves_to_top() {
-    // :3m0 moves line 3 ("c") to the very top.
+fn test_gtab_with_three_tabs() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
-    engine.update_syntax();
-    engine.feed_keys(":3m0<CR>");
-    assert_eq!(engine.buffer().to_string(), "c\na\nb\nd\n");
-}
+    engine.new_tab(None); // tab 1
+    engine.new_tab(None); // tab 2 (active)
 
-// ── Phase 4 Batch 16: visual block I/A, :retab, :noh, marks, more edges ──
-// Mined from test_visual.vim, test_retab.vim, test_mark.vim, test_search.vim
+    // Visit tab 0
+    engine.goto_tab(0);
+    // Visit tab 1
+    engine.goto_tab(1);
+    assert_eq!(engine.active_group().active_tab, 1);
 
-// -- Visual block Insert (I) --
+    // g<Tab> should go to tab 0 (the last one before tab 1)
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 0);
 
-#[test]
-fn test_nvim_visual_block_I_inserts_prefix() {
-    // Select a column of 3 lines, press I<text><Esc>; text prepended to each line
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "one\ntwo\nthree\n");
-    engine.update_syntax();
-    // Visual-block selection down 2 lines
-    engine.feed_keys("<C-v>jjI# <Esc>");
-    assert_eq!(engine.buffer().to_string(), "# one\n# two\n# three\n");
+    // g<Tab> again should go back to tab 1
+    engine.feed_keys("g<Tab>");
+    assert_eq!(engine.active_group().active_tab, 1);
 }
 
-// -- Visual block Append (A) --
+// --- gF: open file under cursor + jump to line ---
 
 #[test]
-fn test_nvim_visual_block_A_appends_suffix() {
-    // Block-select with $ inside visual mode; A appends at each line's actual
-    // end (Vim "virtual-end" behaviour). Pressing $ BEFORE <C-v> is different
-    // — it anchors the block at a fixed column and does NOT virtualise the
-    // right edge. This test exercises the virtual-end path.
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "one\ntwo\nthree\n");
-    engine.update_syntax();
-    engine.feed_keys("<C-v>jj$A!<Esc>");
-    assert_eq!(engine.buffer().to_string(), "one!\ntwo!\nthree!\n");
-}
-
-// -- :nohlsearch / :noh --
+fn test_gf_open_file_at_line() {
+    use std::io::Write;
+    // Create a temp file with 10 lines
+    let dir = std::env::temp_dir().join("vimcode_test_gf");
+    let _ = std::fs::create_dir_all(&dir);
+    let file = dir.join("target.txt");
+    {
+        let mut f = std::fs::File::create(&file).unwrap();
+        for i in 1..=10 {
+            writeln!(f, "line {i}").unwrap();
+        }
+    }
 
-#[test]
-fn test_nvim_noh_clears_search_highlight() {
-    // After /foo, search_matches is set. :noh should clear it.
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "foo bar foo\n");
+    engine.cwd = dir.clone();
+    // Buffer contains "target.txt:5"
+    let text = format!("{}:5", file.display());
+    engine.buffer_mut().insert(0, &text);
     engine.update_syntax();
-    engine.feed_keys("/foo<CR>");
-    // Something searched; now clear highlights
-    engine.feed_keys(":noh<CR>");
-    // search_matches (the highlight list) should be empty
-    assert!(
-        engine.search_matches.is_empty(),
-        "expected no search_matches after :noh"
-    );
-}
 
-// -- :r[ead] {file} --
+    // gF should open the file and jump to line 5 (0-based: 4)
+    press_char(&mut engine, 'g');
+    press_char(&mut engine, 'F');
 
-#[test]
-fn test_nvim_read_nonexistent_file_errors() {
-    // :r on a file that doesn't exist should produce a "Cannot read file" message.
-    let mut engine = Engine::new();
-    engine.feed_keys(":r /path/does/not/exist/xyz\n<CR>");
-    assert!(
-        engine.message.to_lowercase().contains("cannot read")
-            || engine.message.to_lowercase().contains("no such"),
-        "expected read-file error message, got: {:?}",
-        engine.message
+    // Verify we're in the target file at line 4 (0-based)
+    assert_eq!(
+        engine.active_buffer_state().file_path.as_deref(),
+        Some(file.as_path())
     );
-}
+    assert_eq!(engine.view().cursor.line, 4);
 
-// -- :retab --
+    // Cleanup
+    let _ = std::fs::remove_dir_all(&dir);
+}
 
 #[test]
-fn test_nvim_retab_converts_tabs_to_spaces() {
-    // With expandtab and tabstop=4, :retab converts tabs to spaces.
+fn test_gf_open_file_no_line_suffix() {
+    use std::io::Write;
+    let dir = std::env::temp_dir().join("vimcode_test_gf2");
+    let _ = std::fs::create_dir_all(&dir);
+    let file = dir.join("plain.txt");
+    {
+        let mut f = std::fs::File::create(&file).unwrap();
+        writeln!(f, "hello").unwrap();
+    }
+
     let mut engine = Engine::new();
-    engine.settings.expand_tab = true;
-    engine.settings.tabstop = 4;
-    engine.buffer_mut().insert(0, "\thello\n");
+    engine.cwd = dir.clone();
+    // Buffer contains just the file path, no :N suffix
+    let text = format!("{}", file.display());
+    engine.buffer_mut().insert(0, &text);
     engine.update_syntax();
-    engine.feed_keys(":retab<CR>");
-    assert_eq!(engine.buffer().to_string(), "    hello\n");
-}
 
-// -- Marks --
+    // gF should still open the file (at line 0, no line suffix)
+    press_char(&mut engine, 'g');
+    press_char(&mut engine, 'F');
 
-#[test]
-fn test_nvim_lowercase_mark_set_and_jump() {
-    // ma on line 1, move away, then 'a jumps back to line 1
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
-    engine.update_syntax();
-    engine.view_mut().cursor.line = 1;
-    engine.feed_keys("ma"); // set mark 'a' here
-    engine.view_mut().cursor.line = 0;
-    engine.feed_keys("'a"); // jump to mark
-    assert_eq!(engine.view().cursor.line, 1);
+    assert_eq!(
+        engine.active_buffer_state().file_path.as_deref(),
+        Some(file.as_path())
+    );
+    assert_eq!(engine.view().cursor.line, 0);
+
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_nvim_backtick_mark_preserves_col() {
-    // `a jumps to exact line+col of mark, not first-non-blank
+fn test_gf_open_file_with_line_and_col_suffix() {
+    use std::io::Write;
+    let dir = std::env::temp_dir().join("vimcode_test_gf3");
+    let _ = std::fs::create_dir_all(&dir);
+    let file = dir.join("multi.txt");
+    {
+        let mut f = std::fs::File::create(&file).unwrap();
+        for i in 1..=20 {
+            writeln!(f, "content line {i}").unwrap();
+        }
+    }
+
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello\nworld\n");
+    engine.cwd = dir.clone();
+    // Buffer contains "multi.txt:10:3" (line:col format from grep output)
+    let text = format!("{}:10:3", file.display());
+    engine.buffer_mut().insert(0, &text);
     engine.update_syntax();
-    engine.view_mut().cursor.line = 1;
-    engine.view_mut().cursor.col = 3;
-    engine.feed_keys("ma");
-    engine.view_mut().cursor.line = 0;
-    engine.view_mut().cursor.col = 0;
-    engine.feed_keys("`a");
-    assert_eq!(engine.view().cursor.line, 1);
-    assert_eq!(engine.view().cursor.col, 3);
-}
 
-// -- Search highlighting / n with no match --
+    press_char(&mut engine, 'g');
+    press_char(&mut engine, 'F');
 
-#[test]
-fn test_nvim_n_with_no_prior_search_errors() {
-    // n without a prior search pattern should not move and should error.
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello\n");
-    engine.update_syntax();
-    engine.feed_keys("n");
-    assert_eq!(engine.view().cursor.line, 0);
-    assert_eq!(engine.view().cursor.col, 0);
-}
+    assert_eq!(
+        engine.active_buffer_state().file_path.as_deref(),
+        Some(file.as_path())
+    );
+    // Line 10 → 0-based index 9
+    assert_eq!(engine.view().cursor.line, 9);
 
-// -- Word motions edge cases --
+    let _ = std::fs::remove_dir_all(&dir);
+}
 
 #[test]
-fn test_nvim_w_at_end_of_file() {
-    // w at last word should stay within bounds
+fn test_gf_no_file_shows_message() {
     let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "last\n");
+    engine.buffer_mut().insert(0, "nonexistent_file_xyz.txt:42");
     engine.update_syntax();
-    engine.feed_keys("$w");
-    // Cursor shouldn't panic or go past buffer; should stay at or near end.
-    assert!(engine.view().cursor.line <= 1);
+
+    press_char(&mut engine, 'g');
+    press_char(&mut engine, 'F');
+
+    assert!(
+        engine.message.contains("No file path"),
+        "expected error message, got: {:?}",
+        engine.message
+    );
 }
 
+// --- g0/g^/g$: screen-line motions ---
+
 #[test]
-fn test_nvim_b_at_start_of_file() {
-    // b at col 0 line 0 stays put
-    nvim_case("hello\n", 0, 0, "b", "hello\n", 0, 0);
+fn test_g0_no_wrap_goes_to_col_zero() {
+    let mut engine = engine_with_text("    hello world\n");
+    engine.settings.wrap = false;
+    engine.view_mut().cursor.col = 8;
+    engine.feed_keys("g0");
+    assert_eq!(engine.view().cursor.col, 0);
 }
 
-// -- Indent/dedent in visual mode --
-
 #[test]
-fn test_nvim_vis_greater_indents_selection() {
-    // Vj> indents two selected lines one shiftwidth
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "a\nb\nc\n");
-    engine.update_syntax();
-    engine.settings.expand_tab = true;
-    engine.settings.shift_width = 2;
-    engine.feed_keys("Vj>");
-    assert_eq!(engine.buffer().to_string(), "  a\n  b\nc\n");
+fn test_g0_wrap_goes_to_segment_start() {
+    let mut engine = engine_with_text("abcdefghijklmnopqrstuvwxyz0123456789\n");
+    engine.settings.wrap = true;
+    engine.view_mut().viewport_cols = 10;
+    // Cursor at col 15 → segment 1 (cols 10..19) → g0 should go to 10
+    engine.view_mut().cursor.col = 15;
+    engine.feed_keys("g0");
+    assert_eq!(engine.view().cursor.col, 10);
 }
 
 #[test]
-fn test_nvim_vis_less_dedents_selection() {
-    // Vj< dedents two indented lines
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "  a\n  b\nc\n");
-    engine.update_syntax();
-    engine.settings.expand_tab = true;
-    engine.settings.shift_width = 2;
-    engine.feed_keys("Vj<");
-    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n");
+fn test_g_caret_no_wrap_goes_to_first_non_blank() {
+    let mut engine = engine_with_text("    hello\n");
+    engine.settings.wrap = false;
+    engine.view_mut().cursor.col = 8;
+    engine.feed_keys("g^");
+    assert_eq!(engine.view().cursor.col, 4);
 }
 
-// -- Count prefix with r --
-
 #[test]
-fn test_nvim_5rX_replaces_5_chars() {
-    nvim_case("aaaaab\n", 0, 0, "5rX", "XXXXXb\n", 0, 4);
+fn test_g_caret_wrap_goes_to_first_non_blank_in_segment() {
+    // Line: "          abcdefghij..." (10 spaces + text)
+    // With viewport=10, segment 0 = spaces, segment 1 starts at col 10 = 'a'
+    let mut engine = engine_with_text("          abcdefghijklmno\n");
+    engine.settings.wrap = true;
+    engine.view_mut().viewport_cols = 10;
+    // Cursor at col 15 → segment 1 (cols 10..19) → first non-blank is col 10 ('a')
+    engine.view_mut().cursor.col = 15;
+    engine.feed_keys("g^");
+    assert_eq!(engine.view().cursor.col, 10);
 }
 
-// -- Swap (xp) variations --
-
 #[test]
-fn test_nvim_dap_delete_around_paragraph() {
-    // dap deletes current paragraph (including trailing blank line)
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "first\n\nsecond\n\nthird\n");
-    engine.update_syntax();
-    engine.view_mut().cursor.line = 2;
-    engine.feed_keys("dap");
-    // paragraph "second\n" with surrounding blanks collapsed
-    assert!(
-        engine.buffer().to_string().contains("first")
-            && engine.buffer().to_string().contains("third")
-            && !engine.buffer().to_string().contains("second"),
-        "dap should have removed 'second' paragraph: {:?}",
-        engine.buffer().to_string()
-    );
+fn test_g_dollar_no_wrap_goes_to_end_of_line() {
+    let mut engine = engine_with_text("hello\n");
+    engine.settings.wrap = false;
+    engine.view_mut().cursor.col = 0;
+    engine.feed_keys("g$");
+    assert_eq!(engine.view().cursor.col, 4); // 'o' is at col 4
 }
 
-// -- Repeat (.) basic --
+#[test]
+fn test_g_dollar_wrap_goes_to_end_of_segment() {
+    let mut engine = engine_with_text("abcdefghijklmnopqrstuvwxyz0123456789\n");
+    engine.settings.wrap = true;
+    engine.view_mut().viewport_cols = 10;
+    // Cursor at col 3 → segment 0 (cols 0..9) → g$ should go to col 9
+    engine.view_mut().cursor.col = 3;
+    engine.feed_keys("g$");
+    assert_eq!(engine.view().cursor.col, 9);
+}
 
 #[test]
-fn test_nvim_dot_repeats_last_change() {
-    // After dw, moving and pressing . repeats the dw
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "one two three four\n");
-    engine.update_syntax();
-    engine.feed_keys("dw"); // delete "one "
-    engine.feed_keys("."); // delete "two "
-    assert_eq!(engine.buffer().to_string(), "three four\n");
+fn test_g_dollar_wrap_last_segment_goes_to_line_end() {
+    let mut engine = engine_with_text("abcdefghijklmno\n"); // 15 chars + newline
+    engine.settings.wrap = true;
+    engine.view_mut().viewport_cols = 10;
+    // Cursor at col 12 → segment 1 (cols 10..14) → g$ should go to col 14
+    engine.view_mut().cursor.col = 12;
+    engine.feed_keys("g$");
+    assert_eq!(engine.view().cursor.col, 14);
 }
 
-// -- u / Ctrl-R undo/redo --
+// --- g@: user-defined operatorfunc ---
 
 #[test]
-fn test_nvim_u_undoes_insert() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello\n");
-    engine.update_syntax();
-    engine.feed_keys("iWORLD<Esc>");
-    assert_eq!(engine.buffer().to_string(), "WORLDhello\n");
-    engine.feed_keys("u");
-    assert_eq!(engine.buffer().to_string(), "hello\n");
+fn test_g_at_sets_pending_operator() {
+    // g@ without a registered operatorfunc should still accept motions
+    // (it just does nothing when the callback is missing)
+    let mut engine = engine_with_text("hello world\n");
+    engine.feed_keys("g@w");
+    // No crash, cursor should still be valid
+    assert!(engine.view().cursor.line == 0);
 }
 
 #[test]
-fn test_nvim_ctrl_r_redoes_undo() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "hello\n");
-    engine.update_syntax();
-    engine.feed_keys("iX<Esc>"); // insert X → "Xhello\n"
-    engine.feed_keys("u"); // undo → "hello\n"
-    engine.feed_keys("<C-r>"); // redo
-    assert_eq!(engine.buffer().to_string(), "Xhello\n");
-}
+fn test_g_at_calls_operatorfunc_linewise() {
+    // Register a Lua operatorfunc that records the motion type in a message
+    let dir = write_plugin_lua(
+        "test_opfunc_line",
+        r#"
+vimcode.set_operatorfunc(function(motion_type)
+    vimcode.message("opfunc:" .. motion_type)
+end)
+"#,
+    );
+    let mut engine = engine_with_text("line one\nline two\nline three\n");
+    match plugin::PluginManager::new() {
+        Ok(mut mgr) => {
+            mgr.load_plugins_dir(&dir, &[]);
+            engine.plugin_manager = Some(mgr);
+        }
+        Err(_) => return,
+    }
 
-// -- Command abbreviations --
+    // g@j should trigger linewise operatorfunc (j is a linewise motion)
+    engine.feed_keys("g@j");
+    assert_eq!(engine.message, "opfunc:line");
 
-#[test]
-fn test_nvim_q_abbrev_does_not_explode() {
-    // :q on an unmodified empty buffer — just ensure dispatch doesn't panic.
-    let mut engine = Engine::new();
-    let _ = engine.execute_command("q");
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
-// -- Settings query --
-
 #[test]
-fn test_nvim_set_query_tabstop() {
-    // :set tabstop? should leave a message containing the current value
-    let mut engine = Engine::new();
-    engine.settings.tabstop = 8;
-    engine.feed_keys(":set tabstop?<CR>");
-    assert!(
-        engine.message.contains('8'),
-        "expected tabstop=8 in message, got: {:?}",
-        engine.message
+fn test_g_at_calls_operatorfunc_charwise() {
+    let dir = write_plugin_lua(
+        "test_opfunc_char",
+        r#"
+vimcode.set_operatorfunc(function(motion_type)
+    vimcode.message("opfunc:" .. motion_type)
+end)
+"#,
     );
-}
+    let mut engine = engine_with_text("hello world\n");
+    match plugin::PluginManager::new() {
+        Ok(mut mgr) => {
+            mgr.load_plugins_dir(&dir, &[]);
+            engine.plugin_manager = Some(mgr);
+        }
+        Err(_) => return,
+    }
 
-// --- g<Tab>: jump to last-accessed tab ---
+    // g@w should trigger charwise operatorfunc (w is a charwise motion)
+    engine.feed_keys("g@w");
+    assert_eq!(engine.message, "opfunc:char");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
 
 #[test]
-fn test_gtab_jumps_to_last_accessed_tab() {
-    let mut engine = Engine::new();
-    engine.new_tab(None); // tab 1
-    engine.new_tab(None); // tab 2 (active)
-    assert_eq!(engine.active_group().active_tab, 2);
+fn test_g_at_operatorfunc_can_modify_buffer() {
+    // Register an operatorfunc that uppercases all lines via set_lines
+    let dir = write_plugin_lua(
+        "test_opfunc_modify",
+        r#"
+vimcode.set_operatorfunc(function(motion_type)
+    local lines = vimcode.buf.lines()
+    for i, line in ipairs(lines) do
+        vimcode.buf.set_line(i, string.upper(line))
+    end
+end)
+"#,
+    );
+    let mut engine = engine_with_text("hello\nworld\n");
+    match plugin::PluginManager::new() {
+        Ok(mut mgr) => {
+            mgr.load_plugins_dir(&dir, &[]);
+            engine.plugin_manager = Some(mgr);
+        }
+        Err(_) => return,
+    }
 
-    // Go to tab 0
-    engine.goto_tab(0);
-    assert_eq!(engine.active_group().active_tab, 0);
+    engine.feed_keys("g@j");
+    assert_eq!(engine.buffer().to_string(), "HELLO\nWORLD\n");
 
-    // g<Tab> should jump back to tab 2 (last accessed)
-    engine.feed_keys("g<Tab>");
-    assert_eq!(engine.active_group().active_tab, 2);
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
+// --- Tab bar hit regions ---
+
 #[test]
-fn test_gtab_toggles_between_two_tabs() {
+fn test_tab_bar_handle_click_switches_tab() {
     let mut engine = Engine::new();
     engine.new_tab(None); // tab 1
-    engine.goto_tab(0);
-    assert_eq!(engine.active_group().active_tab, 0);
-
-    // Switch to tab 1 via gt
-    engine.feed_keys("gt");
     assert_eq!(engine.active_group().active_tab, 1);
 
-    // g<Tab> should go back to tab 0
-    engine.feed_keys("g<Tab>");
+    // Switch to tab 0 via handle_tab_bar_click
+    let group_id = engine.active_group;
+    engine.handle_tab_bar_click(group_id, TabBarClickTarget::Tab(0));
     assert_eq!(engine.active_group().active_tab, 0);
-
-    // Another g<Tab> should toggle back to tab 1
-    engine.feed_keys("g<Tab>");
-    assert_eq!(engine.active_group().active_tab, 1);
 }
 
 #[test]
-fn test_gtab_noop_with_single_tab() {
+fn test_tab_bar_handle_click_close_tab() {
     let mut engine = Engine::new();
-    // Only one tab — g<Tab> should be a no-op
-    engine.feed_keys("g<Tab>");
-    assert_eq!(engine.active_group().active_tab, 0);
+    engine.new_tab(None); // tab 1
+    assert_eq!(engine.active_group().tabs.len(), 2);
+
+    let group_id = engine.active_group;
+    let needs_confirm = engine.handle_tab_bar_click(group_id, TabBarClickTarget::CloseTab(1));
+    assert!(!needs_confirm); // Not dirty
+    assert_eq!(engine.active_group().tabs.len(), 1);
 }
 
 #[test]
-fn test_gtab_with_three_tabs() {
+fn test_tab_bar_handle_click_close_dirty_tab_returns_true() {
     let mut engine = Engine::new();
     engine.new_tab(None); // tab 1
-    engine.new_tab(None); // tab 2 (active)
+                          // Make the buffer dirty
+    engine.buffer_mut().insert(0, "dirty");
+    engine
+        .buffer_manager
+        .get_mut(engine.active_buffer_id())
+        .unwrap()
+        .dirty = true;
 
-    // Visit tab 0
-    engine.goto_tab(0);
-    // Visit tab 1
-    engine.goto_tab(1);
-    assert_eq!(engine.active_group().active_tab, 1);
+    let group_id = engine.active_group;
+    let needs_confirm = engine.handle_tab_bar_click(group_id, TabBarClickTarget::CloseTab(1));
+    assert!(needs_confirm); // Should request confirmation
+                            // Tab should NOT be closed yet
+    assert_eq!(engine.active_group().tabs.len(), 2);
+}
+
+// --- Ctrl-]: tag jump (LSP go-to-definition) ---
 
-    // g<Tab> should go to tab 0 (the last one before tab 1)
-    engine.feed_keys("g<Tab>");
-    assert_eq!(engine.active_group().active_tab, 0);
+#[test]
+fn test_ctrl_bracket_right_pushes_jump_location() {
+    // Ctrl-] should push jump location (same as gd)
+    let mut engine = engine_with_text("hello world\nfoo bar\n");
+    engine.view_mut().cursor.line = 1;
+    engine.view_mut().cursor.col = 0;
 
-    // g<Tab> again should go back to tab 1
-    engine.feed_keys("g<Tab>");
-    assert_eq!(engine.active_group().active_tab, 1);
+    // Ctrl-] — no LSP in tests, but jump list should be updated
+    engine.handle_key("bracketright", None, true);
+
+    // Jump list should have the position before the Ctrl-] press
+    assert!(
+        !engine.jump_list.is_empty(),
+        "jump list should have an entry"
+    );
 }
 
-// --- gF: open file under cursor + jump to line ---
+// --- Activity bar hit regions ---
 
 #[test]
-fn test_gf_open_file_at_line() {
-    use std::io::Write;
-    // Create a temp file with 10 lines
-    let dir = std::env::temp_dir().join("vimcode_test_gf");
-    let _ = std::fs::create_dir_all(&dir);
-    let file = dir.join("target.txt");
-    {
-        let mut f = std::fs::File::create(&file).unwrap();
-        for i in 1..=10 {
-            writeln!(f, "line {i}").unwrap();
-        }
-    }
+fn test_activity_bar_resolve_builtin_panels() {
+    use crate::core::engine::{resolve_activity_bar_click, ActivityBarTarget, SidebarPanel};
+    let ext_names: Vec<String> = vec![];
 
-    let mut engine = Engine::new();
-    engine.cwd = dir.clone();
-    // Buffer contains "target.txt:5"
-    let text = format!("{}:5", file.display());
-    engine.buffer_mut().insert(0, &text);
-    engine.update_syntax();
+    assert_eq!(
+        resolve_activity_bar_click(0, 30, &ext_names),
+        Some(ActivityBarTarget::MenuToggle)
+    );
+    assert_eq!(
+        resolve_activity_bar_click(1, 30, &ext_names),
+        Some(ActivityBarTarget::Panel(SidebarPanel::Explorer))
+    );
+    assert_eq!(
+        resolve_activity_bar_click(4, 30, &ext_names),
+        Some(ActivityBarTarget::Panel(SidebarPanel::Git))
+    );
+    assert_eq!(
+        resolve_activity_bar_click(6, 30, &ext_names),
+        Some(ActivityBarTarget::Panel(SidebarPanel::Ai))
+    );
+    // Settings at bottom row
+    assert_eq!(
+        resolve_activity_bar_click(29, 30, &ext_names),
+        Some(ActivityBarTarget::Settings)
+    );
+    // Empty gap between panels and settings
+    assert_eq!(resolve_activity_bar_click(10, 30, &ext_names), None);
+}
 
-    // gF should open the file and jump to line 5 (0-based: 4)
-    press_char(&mut engine, 'g');
-    press_char(&mut engine, 'F');
+#[test]
+fn test_activity_bar_resolve_extension_panels() {
+    use crate::core::engine::{resolve_activity_bar_click, ActivityBarTarget};
+    let ext_names = vec!["git-insights".to_string(), "todo-panel".to_string()];
 
-    // Verify we're in the target file at line 4 (0-based)
+    // Row 7 = first extension panel
     assert_eq!(
-        engine.active_buffer_state().file_path.as_deref(),
-        Some(file.as_path())
+        resolve_activity_bar_click(7, 30, &ext_names),
+        Some(ActivityBarTarget::ExtensionPanel(
+            "git-insights".to_string()
+        ))
     );
-    assert_eq!(engine.view().cursor.line, 4);
-
-    // Cleanup
-    let _ = std::fs::remove_dir_all(&dir);
+    // Row 8 = second extension panel
+    assert_eq!(
+        resolve_activity_bar_click(8, 30, &ext_names),
+        Some(ActivityBarTarget::ExtensionPanel("todo-panel".to_string()))
+    );
+    // Row 9 with only 2 extensions = None (gap)
+    assert_eq!(resolve_activity_bar_click(9, 30, &ext_names), None);
 }
 
+// --- Context menu hit regions ---
+
 #[test]
-fn test_gf_open_file_no_line_suffix() {
-    use std::io::Write;
-    let dir = std::env::temp_dir().join("vimcode_test_gf2");
-    let _ = std::fs::create_dir_all(&dir);
-    let file = dir.join("plain.txt");
-    {
-        let mut f = std::fs::File::create(&file).unwrap();
-        writeln!(f, "hello").unwrap();
-    }
+fn test_context_menu_click_item() {
+    use crate::core::engine::{
+        resolve_context_menu_click, ContextMenuClickResult, ContextMenuItem,
+    };
+    let items = vec![
+        ContextMenuItem {
+            label: "Cut".to_string(),
+            action: "cut".to_string(),
+            shortcut: "".to_string(),
+            separator_after: false,
+            enabled: true,
+        },
+        ContextMenuItem {
+            label: "Copy".to_string(),
+            action: "copy".to_string(),
+            shortcut: "".to_string(),
+            separator_after: true, // separator after this item
+            enabled: true,
+        },
+        ContextMenuItem {
+            label: "Paste".to_string(),
+            action: "paste".to_string(),
+            shortcut: "".to_string(),
+            separator_after: false,
+            enabled: true,
+        },
+    ];
+    // Popup at (10, 5), terminal 80x24
+    // popup_h = 3 items + 1 separator + 2 border = 6
+    // Item 0 "Cut" at visual row 0 (inner_row 0)
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 6),
+        ContextMenuClickResult::Item(0)
+    );
+    // Item 1 "Copy" at visual row 1 (inner_row 1)
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 7),
+        ContextMenuClickResult::Item(1)
+    );
+    // Separator at visual row 2 → InsidePopup
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 8),
+        ContextMenuClickResult::InsidePopup
+    );
+    // Item 2 "Paste" at visual row 3 (inner_row 3)
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 9),
+        ContextMenuClickResult::Item(2)
+    );
+    // Outside
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 0, 0),
+        ContextMenuClickResult::Outside
+    );
+}
 
-    let mut engine = Engine::new();
-    engine.cwd = dir.clone();
-    // Buffer contains just the file path, no :N suffix
-    let text = format!("{}", file.display());
-    engine.buffer_mut().insert(0, &text);
-    engine.update_syntax();
+#[test]
+fn test_context_menu_disabled_item() {
+    use crate::core::engine::{
+        resolve_context_menu_click, ContextMenuClickResult, ContextMenuItem,
+    };
+    let items = vec![ContextMenuItem {
+        label: "Disabled".to_string(),
+        action: "noop".to_string(),
+        shortcut: "".to_string(),
+        separator_after: false,
+        enabled: false,
+    }];
+    // Click on disabled item → InsidePopup (not Item)
+    assert_eq!(
+        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 6),
+        ContextMenuClickResult::InsidePopup
+    );
+}
 
-    // gF should still open the file (at line 0, no line suffix)
-    press_char(&mut engine, 'g');
-    press_char(&mut engine, 'F');
+// --- Dialog hit regions ---
 
+#[test]
+fn test_dialog_click_button() {
+    use crate::core::engine::{resolve_dialog_click, DialogButton, DialogClickResult};
+    let buttons = vec![
+        DialogButton {
+            label: "Cancel".to_string(),
+            hotkey: 'c',
+            action: "cancel".to_string(),
+        },
+        DialogButton {
+            label: "OK".to_string(),
+            hotkey: 'o',
+            action: "ok".to_string(),
+        },
+    ];
+    // Dialog at (20, 10), width 40, height 6, buttons on row 14
+    let layout = DialogLayout {
+        x: 20,
+        y: 10,
+        width: 40,
+        height: 6,
+        btn_y: 14,
+    };
+    let fmt = |label: &str, _hotkey: char| label.to_string();
+    // Button 0 "Cancel" starts at col 22 (layout.x + 2), width ~10
     assert_eq!(
-        engine.active_buffer_state().file_path.as_deref(),
-        Some(file.as_path())
+        resolve_dialog_click(&buttons, &layout, 23, 14, &fmt),
+        DialogClickResult::Button(0)
     );
-    assert_eq!(engine.view().cursor.line, 0);
+    // Outside dialog
+    assert_eq!(
+        resolve_dialog_click(&buttons, &layout, 0, 0, &fmt),
+        DialogClickResult::Outside
+    );
+    // Inside but not on buttons
+    assert_eq!(
+        resolve_dialog_click(&buttons, &layout, 30, 12, &fmt),
+        DialogClickResult::InsideDialog
+    );
+}
 
-    let _ = std::fs::remove_dir_all(&dir);
+fn rename_edit(line: u32, start: u32, end: u32, new_text: &str) -> FormattingEdit {
+    FormattingEdit {
+        range: lsp::LspRange {
+            start: lsp::LspPosition {
+                line,
+                character: start,
+            },
+            end: lsp::LspPosition {
+                line,
+                character: end,
+            },
+        },
+        new_text: new_text.to_string(),
+    }
 }
 
 #[test]
-fn test_gf_open_file_with_line_and_col_suffix() {
-    use std::io::Write;
-    let dir = std::env::temp_dir().join("vimcode_test_gf3");
-    let _ = std::fs::create_dir_all(&dir);
-    let file = dir.join("multi.txt");
-    {
-        let mut f = std::fs::File::create(&file).unwrap();
-        for i in 1..=20 {
-            writeln!(f, "content line {i}").unwrap();
-        }
-    }
+fn test_lsp_rename_applies_open_buffer_and_disk_file() {
+    let dir = std::env::temp_dir().join("vimcode_lsp_rename_ws");
+    std::fs::create_dir_all(&dir).unwrap();
+    let open = dir.join("open.rs");
+    let closed = dir.join("closed.rs");
+    std::fs::write(&open, "fn foo() {}\nfoo();\n").unwrap();
+    std::fs::write(&closed, "use crate::foo;\n").unwrap();
 
     let mut engine = Engine::new();
-    engine.cwd = dir.clone();
-    // Buffer contains "multi.txt:10:3" (line:col format from grep output)
-    let text = format!("{}:10:3", file.display());
-    engine.buffer_mut().insert(0, &text);
-    engine.update_syntax();
-
-    press_char(&mut engine, 'g');
-    press_char(&mut engine, 'F');
+    engine
+        .open_file_with_mode(&open, OpenMode::Permanent)
+        .unwrap();
 
+    let we = WorkspaceEdit {
+        changes: vec![
+            lsp::FileEdit {
+                path: open.clone(),
+                edits: vec![rename_edit(0, 3, 6, "bar"), rename_edit(1, 0, 3, "bar")],
+            },
+            lsp::FileEdit {
+                path: closed.clone(),
+                edits: vec![rename_edit(0, 11, 14, "bar")],
+            },
+        ],
+    };
+    engine.handle_rename_response(we, None);
+
+    assert_eq!(engine.buffer().to_string(), "fn bar() {}\nbar();\n");
     assert_eq!(
-        engine.active_buffer_state().file_path.as_deref(),
-        Some(file.as_path())
+        std::fs::read_to_string(&closed).unwrap(),
+        "use crate::bar;\n"
     );
-    // Line 10 → 0-based index 9
-    assert_eq!(engine.view().cursor.line, 9);
+    assert_eq!(engine.message, "Renamed: 3 edit(s) in 2 file(s)");
 
+    // All edits to the open buffer undo as one step.
+    press_char(&mut engine, 'u');
+    assert_eq!(engine.buffer().to_string(), "fn foo() {}\nfoo();\n");
     let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_gf_no_file_shows_message() {
-    let mut engine = Engine::new();
-    engine.buffer_mut().insert(0, "nonexistent_file_xyz.txt:42");
-    engine.update_syntax();
+fn test_lsp_rename_keeps_disk_file_encoding() {
+    let dir = std::env::temp_dir().join("vimcode_lsp_rename_enc");
+    std::fs::create_dir_all(&dir).unwrap();
+    let closed = dir.join("closed.rs");
+    std::fs::write(&closed, b"// caf\xE9\nfoo();\n").unwrap();
 
-    press_char(&mut engine, 'g');
-    press_char(&mut engine, 'F');
+    let mut engine = Engine::new();
+    let we = WorkspaceEdit {
+        changes: vec![lsp::FileEdit {
+            path: closed.clone(),
+            edits: vec![rename_edit(1, 0, 3, "bar")],
+        }],
+    };
+    engine.handle_rename_response(we, None);
 
-    assert!(
-        engine.message.contains("No file path"),
-        "expected error message, got: {:?}",
-        engine.message
-    );
+    assert_eq!(std::fs::read(&closed).unwrap(), b"// caf\xE9\nbar();\n");
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
-// --- g0/g^/g$: screen-line motions ---
-
 #[test]
-fn test_g0_no_wrap_goes_to_col_zero() {
-    let mut engine = engine_with_text("    hello world\n");
-    engine.settings.wrap = false;
-    engine.view_mut().cursor.col = 8;
-    engine.feed_keys("g0");
-    assert_eq!(engine.view().cursor.col, 0);
-}
+fn test_lsp_rename_error_leaves_buffer_untouched() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "fn foo() {}\n");
+    let we = WorkspaceEdit { changes: vec![] };
+    engine.handle_rename_response(we, Some("No references found".to_string()));
+    assert_eq!(engine.message, "Rename failed: No references found");
+    assert_eq!(engine.buffer().to_string(), "fn foo() {}\n");
 
-#[test]
-fn test_g0_wrap_goes_to_segment_start() {
-    let mut engine = engine_with_text("abcdefghijklmnopqrstuvwxyz0123456789\n");
-    engine.settings.wrap = true;
-    engine.view_mut().viewport_cols = 10;
-    // Cursor at col 15 → segment 1 (cols 10..19) → g0 should go to 10
-    engine.view_mut().cursor.col = 15;
-    engine.feed_keys("g0");
-    assert_eq!(engine.view().cursor.col, 10);
+    engine.handle_rename_response(WorkspaceEdit { changes: vec![] }, None);
+    assert_eq!(engine.message, "Rename: no changes returned by server");
 }
 
 #[test]
-fn test_g_caret_no_wrap_goes_to_first_non_blank() {
-    let mut engine = engine_with_text("    hello\n");
-    engine.settings.wrap = false;
-    engine.view_mut().cursor.col = 8;
-    engine.feed_keys("g^");
-    assert_eq!(engine.view().cursor.col, 4);
+fn test_lsp_format_edits_keep_cursor_on_same_text() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "fn main(){\nlet x=1;\n}\n");
+    engine.view_mut().cursor = Cursor { line: 1, col: 4 }; // on 'x'
+    let id = engine.active_buffer_id();
+    engine.apply_lsp_edits(
+        id,
+        vec![
+            rename_edit(0, 9, 9, " "),
+            rename_edit(1, 0, 0, "    "),
+            rename_edit(1, 5, 6, " = "),
+        ],
+    );
+    assert_eq!(
+        engine.buffer().to_string(),
+        "fn main() {\n    let x = 1;\n}\n"
+    );
+    assert_eq!(engine.view().cursor, Cursor { line: 1, col: 8 });
 }
 
 #[test]
-fn test_g_caret_wrap_goes_to_first_non_blank_in_segment() {
-    // Line: "          abcdefghij..." (10 spaces + text)
-    // With viewport=10, segment 0 = spaces, segment 1 starts at col 10 = 'a'
-    let mut engine = engine_with_text("          abcdefghijklmno\n");
-    engine.settings.wrap = true;
-    engine.view_mut().viewport_cols = 10;
-    // Cursor at col 15 → segment 1 (cols 10..19) → first non-blank is col 10 ('a')
-    engine.view_mut().cursor.col = 15;
-    engine.feed_keys("g^");
-    assert_eq!(engine.view().cursor.col, 10);
+fn test_lsp_edits_refused_for_nomodifiable_buffer() {
+    let mut engine = Engine::new();
+    engine.buffer_mut().insert(0, "fn main(){}\n");
+    engine.execute_command("set nomodifiable");
+    let id = engine.active_buffer_id();
+    assert!(!engine.apply_lsp_edits(id, vec![rename_edit(0, 9, 9, " ")]));
+    assert_eq!(engine.buffer().to_string(), "fn main(){}\n");
+    assert!(engine.message.starts_with("E21"), "{}", engine.message);
 }
 
 #[test]
-fn test_g_dollar_no_wrap_goes_to_end_of_line() {
-    let mut engine = engine_with_text("hello\n");
-    engine.settings.wrap = false;
-    engine.view_mut().cursor.col = 0;
-    engine.feed_keys("g$");
-    assert_eq!(engine.view().cursor.col, 4); // 'o' is at col 4
-}
+fn test_server_apply_edit_reports_whether_it_applied() {
+    let path = std::env::temp_dir().join("vimcode_lsp_apply_edit.rs");
+    std::fs::write(&path, "fn foo() {}\n").unwrap();
+    let mut engine = Engine::new();
+    engine
+        .open_file_with_mode(&path, OpenMode::Permanent)
+        .unwrap();
+    let edit = || WorkspaceEdit {
+        changes: vec![lsp::FileEdit {
+            path: path.clone(),
+            edits: vec![rename_edit(0, 3, 6, "bar")],
+        }],
+    };
 
-#[test]
-fn test_g_dollar_wrap_goes_to_end_of_segment() {
-    let mut engine = engine_with_text("abcdefghijklmnopqrstuvwxyz0123456789\n");
-    engine.settings.wrap = true;
-    engine.view_mut().viewport_cols = 10;
-    // Cursor at col 3 → segment 0 (cols 0..9) → g$ should go to col 9
-    engine.view_mut().cursor.col = 3;
-    engine.feed_keys("g$");
-    assert_eq!(engine.view().cursor.col, 9);
+    engine.execute_command("set nomodifiable");
+    let result = engine.apply_server_workspace_edit(edit());
+    assert_eq!(result["applied"], false);
+    assert!(result["failureReason"].as_str().is_some());
+    assert_eq!(engine.buffer().to_string(), "fn foo() {}\n");
+
+    engine.execute_command("set modifiable");
+    let result = engine.apply_server_workspace_edit(edit());
+    assert_eq!(result, serde_json::json!({ "applied": true }));
+    assert_eq!(engine.buffer().to_string(), "fn bar() {}\n");
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_g_dollar_wrap_last_segment_goes_to_line_end() {
-    let mut engine = engine_with_text("abcdefghijklmno\n"); // 15 chars + newline
-    engine.settings.wrap = true;
-    engine.view_mut().viewport_cols = 10;
-    // Cursor at col 12 → segment 1 (cols 10..14) → g$ should go to col 14
-    engine.view_mut().cursor.col = 12;
-    engine.feed_keys("g$");
-    assert_eq!(engine.view().cursor.col, 14);
-}
+fn test_format_on_save_timeout_saves_unformatted() {
+    let path = std::env::temp_dir().join("vimcode_format_on_save_timeout.rs");
+    std::fs::write(&path, "fn main(){}\n").unwrap();
+    let mut engine = Engine::new();
+    engine
+        .open_file_with_mode(&path, OpenMode::Permanent)
+        .unwrap();
+    engine.buffer_mut().insert(0, "// x\n");
 
-// --- g@: user-defined operatorfunc ---
+    // Simulate an in-flight format-on-save whose deadline has passed.
+    let id = engine.active_buffer_id();
+    engine.lsp_pending_formatting = Some(7);
+    engine.lsp_formatting_buffer = Some(id);
+    engine.format_on_save_pending = Some(id);
+    engine.format_on_save_deadline = Some(std::time::Instant::now());
+    engine.quit_after_format_save = true;
 
-#[test]
-fn test_g_at_sets_pending_operator() {
-    // g@ without a registered operatorfunc should still accept motions
-    // (it just does nothing when the callback is missing)
-    let mut engine = engine_with_text("hello world\n");
-    engine.feed_keys("g@w");
-    // No crash, cursor should still be valid
-    assert!(engine.view().cursor.line == 0);
+    assert!(engine.check_format_on_save_timeout());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// x\nfn main(){}\n"
+    );
+    assert!(engine.message.starts_with("Formatting timed out"));
+    assert!(engine.lsp_pending_formatting.is_none());
+    assert!(engine.format_save_quit_ready);
+    // Nothing left pending.
+    assert!(!engine.check_format_on_save_timeout());
+    let _ = std::fs::remove_file(&path);
 }
 
 #[test]
-fn test_g_at_calls_operatorfunc_linewise() {
-    // Register a Lua operatorfunc that records the motion type in a message
-    let dir = write_plugin_lua(
-        "test_opfunc_line",
-        r#"
-vimcode.set_operatorfunc(function(motion_type)
-    vimcode.message("opfunc:" .. motion_type)
-end)
-"#,
+fn test_git_blame_uses_cache_and_drops_it_on_edit() {
+    use crate::core::git::BlameInfo;
+    let info = |author: &str, not_committed: bool| BlameInfo {
+        hash: "0123abcd".to_string(),
+        author: author.to_string(),
+        timestamp: 0,
+        tz_offset: 0,
+        message: "init".to_string(),
+        relative_date: "2 days ago".to_string(),
+        not_committed,
+    };
+    let mut engine = engine_with_text("one\ntwo\n");
+    let bid = engine.active_buffer_id();
+    engine
+        .blame_cache
+        .insert(bid, vec![info("alice", false), info("", true)]);
+
+    engine.execute_command("GitBlame");
+    assert!(engine.blame_annotations_active);
+    assert_eq!(
+        engine.line_annotations.get(&0).map(String::as_str),
+        Some("alice, 2 days ago — init")
     );
-    let mut engine = engine_with_text("line one\nline two\nline three\n");
-    match plugin::PluginManager::new() {
-        Ok(mut mgr) => {
-            mgr.load_plugins_dir(&dir, &[]);
-            engine.plugin_manager = Some(mgr);
-        }
-        Err(_) => return,
-    }
+    assert_eq!(
+        engine.line_annotations.get(&1).map(String::as_str),
+        Some("Not committed")
+    );
+    engine.execute_command("GitBlame");
+    assert!(!engine.blame_annotations_active);
+    assert!(engine.line_annotations.is_empty());
 
-    // g@j should trigger linewise operatorfunc (j is a linewise motion)
-    engine.feed_keys("g@j");
-    assert_eq!(engine.message, "opfunc:line");
+    // Uncommitted lines of a dirty buffer read "(modified)".
+    engine.buffer_manager.get_mut(bid).unwrap().dirty = true;
+    engine.execute_command("GitBlame");
+    assert_eq!(
+        engine.line_annotations.get(&1).map(String::as_str),
+        Some("(modified)")
+    );
 
-    let _ = std::fs::remove_dir_all(&dir);
+    // Editing invalidates the cached blame for the buffer; the re-run waits
+    // for the edits to pause.
+    press_char(&mut engine, 'x');
+    assert!(!engine.blame_cache.contains_key(&bid));
+    assert_eq!(engine.blame_debounce.map(|(b, _)| b), Some(bid));
+    engine.poll_blame();
+    assert!(engine.blame_debounce.is_some());
 }
 
 #[test]
-fn test_g_at_calls_operatorfunc_charwise() {
-    let dir = write_plugin_lua(
-        "test_opfunc_char",
-        r#"
-vimcode.set_operatorfunc(function(motion_type)
-    vimcode.message("opfunc:" .. motion_type)
-end)
-"#,
-    );
-    let mut engine = engine_with_text("hello world\n");
-    match plugin::PluginManager::new() {
-        Ok(mut mgr) => {
-            mgr.load_plugins_dir(&dir, &[]);
-            engine.plugin_manager = Some(mgr);
-        }
-        Err(_) => return,
+fn test_harpoon_pin_reorder_remove_and_jump() {
+    let dir = std::env::temp_dir().join("vimcode_harpoon_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let files: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"]
+        .iter()
+        .map(|n| {
+            let p = dir.join(n);
+            std::fs::write(&p, format!("{n}\n")).unwrap();
+            p
+        })
+        .collect();
+    let mut engine = Engine::new();
+    engine.cwd = dir.clone();
+    for f in &files {
+        engine.open_file_in_tab(f);
+        press_char(&mut engine, ' ');
+        press_char(&mut engine, 'a');
     }
+    assert_eq!(engine.pinned_files(), files.as_slice());
+    press_char(&mut engine, ' ');
+    press_char(&mut engine, 'a');
+    assert_eq!(engine.message, "Already pinned (slot 3)");
 
-    // g@w should trigger charwise operatorfunc (w is a charwise motion)
-    engine.feed_keys("g@w");
-    assert_eq!(engine.message, "opfunc:char");
+    engine.execute_command("Harpoon");
+    assert!(engine.picker_open);
+    assert_eq!(engine.picker_items.len(), 3);
+    engine.picker_selected = 2;
+    press_ctrl(&mut engine, 'k');
+    assert_eq!(
+        engine.pinned_files(),
+        &[files[0].clone(), files[2].clone(), files[1].clone()]
+    );
+    assert_eq!(engine.picker_selected, 1);
+    press_ctrl(&mut engine, 'd');
+    assert_eq!(engine.pinned_files(), &[files[0].clone(), files[1].clone()]);
+    press_special(&mut engine, "Escape");
 
+    press_char(&mut engine, ' ');
+    press_char(&mut engine, '1');
+    assert_eq!(
+        engine.file_path().map(|p| p.as_path()),
+        Some(files[0].as_path())
+    );
+    press_char(&mut engine, ' ');
+    press_char(&mut engine, '4');
+    assert_eq!(engine.message, "Pinned slot 4 is empty");
     let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_g_at_operatorfunc_can_modify_buffer() {
-    // Register an operatorfunc that uppercases all lines via set_lines
-    let dir = write_plugin_lua(
-        "test_opfunc_modify",
-        r#"
-vimcode.set_operatorfunc(function(motion_type)
-    local lines = vimcode.buf.lines()
-    for i, line in ipairs(lines) do
-        vimcode.buf.set_line(i, string.upper(line))
-    end
-end)
-"#,
-    );
-    let mut engine = engine_with_text("hello\nworld\n");
-    match plugin::PluginManager::new() {
-        Ok(mut mgr) => {
-            mgr.load_plugins_dir(&dir, &[]);
-            engine.plugin_manager = Some(mgr);
-        }
-        Err(_) => return,
+fn test_buffer_command_fuzzy_switch_and_picker() {
+    let dir = std::env::temp_dir().join("vimcode_buffer_fuzzy_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut engine = Engine::new();
+    for name in ["engine_keys.rs", "engine_render.rs", "notes.md"] {
+        let p = dir.join(name);
+        std::fs::write(&p, "x\n").unwrap();
+        engine.open_file_in_tab(&p);
     }
-
-    engine.feed_keys("g@j");
-    assert_eq!(engine.buffer().to_string(), "HELLO\nWORLD\n");
-
+    // Unique fuzzy match switches directly.
+    engine.execute_command("b ntmd");
+    assert!(engine.file_path().unwrap().ends_with("notes.md"));
+    // Several matches open the picker filtered by the query.
+    engine.execute_command("b engine");
+    assert!(engine.picker_open);
+    assert_eq!(engine.picker_source, PickerSource::Buffers);
+    assert_eq!(engine.picker_items.len(), 2);
+    press_special(&mut engine, "Escape");
+    engine.execute_command("b zzz");
+    assert_eq!(engine.message, "No matching buffer for zzz");
     let _ = std::fs::remove_dir_all(&dir);
 }
 
-// --- Tab bar hit regions ---
-
 #[test]
-fn test_tab_bar_handle_click_switches_tab() {
-    let mut engine = Engine::new();
-    engine.new_tab(None); // tab 1
-    assert_eq!(engine.active_group().active_tab, 1);
+fn test_workspace_symbol_query_is_debounced_and_outline_shows_lines() {
+    let mut e = Engine::new();
+    e.execute_command("WorkspaceSymbols");
+    assert!(e.picker_open);
+    for c in "ab".chars() {
+        press_char(&mut e, c);
+    }
+    assert_eq!(
+        e.workspace_symbol_debounce
+            .as_ref()
+            .map(|(q, _)| q.as_str()),
+        Some("ab")
+    );
+    // Still inside the debounce window: nothing is sent yet.
+    e.picker_flush_workspace_symbols();
+    assert!(e.workspace_symbol_debounce.is_some());
+    e.workspace_symbol_debounce = Some((
+        "ab".to_string(),
+        std::time::Instant::now() - std::time::Duration::from_secs(1),
+    ));
+    e.picker_flush_workspace_symbols();
+    assert!(e.workspace_symbol_debounce.is_none());
+    press_special(&mut e, "Escape");
 
-    // Switch to tab 0 via handle_tab_bar_click
-    let group_id = engine.active_group;
-    engine.handle_tab_bar_click(group_id, TabBarClickTarget::Tab(0));
-    assert_eq!(engine.active_group().active_tab, 0);
+    e.open_picker(PickerSource::CommandCenter);
+    e.picker_query = "@".to_string();
+    e.picker_populate_document_symbols(make_hierarchical_symbols());
+    // Symbols are sorted by kind then name, so `Config` (0-based line 50)
+    // comes before `Engine` (line 10); details show 1-based lines.
+    let detail = |name: &str| {
+        e.picker_items
+            .iter()
+            .find(|item| item.display.contains(name))
+            .and_then(|item| item.detail.clone())
+    };
+    assert_eq!(detail("Config").as_deref(), Some("struct · 51"));
+    assert_eq!(detail("Engine").as_deref(), Some("struct · 11"));
 }
 
 #[test]
-fn test_tab_bar_handle_click_close_tab() {
+fn test_recent_files_picker_and_reopen_last_closed() {
+    let dir = std::env::temp_dir().join("vimcode_mru_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let (a, b, gone) = (dir.join("a.rs"), dir.join("b.rs"), dir.join("gone.rs"));
+    for p in [&a, &b, &gone] {
+        std::fs::write(p, "x\n").unwrap();
+    }
     let mut engine = Engine::new();
-    engine.new_tab(None); // tab 1
-    assert_eq!(engine.active_group().tabs.len(), 2);
+    engine.cwd = dir.clone();
+    engine.open_file_in_tab(&gone);
+    engine.open_file_in_tab(&a);
+    engine.open_file_in_tab(&b);
+    assert_eq!(
+        engine.buffer_manager.recent_files[..3],
+        [b.clone(), a.clone(), gone.clone()]
+    );
+    engine.collect_session_open_files();
+    assert_eq!(
+        engine.session.recent_files,
+        engine.buffer_manager.recent_files
+    );
+
+    std::fs::remove_file(&gone).unwrap();
+    engine.execute_command("History");
+    assert_eq!(engine.picker_source, PickerSource::RecentFiles);
+    let shown: Vec<&str> = engine
+        .picker_items
+        .iter()
+        .map(|i| i.display.as_str())
+        .collect();
+    assert_eq!(shown, vec!["b.rs", "a.rs"]);
+    press_special(&mut engine, "Escape");
 
-    let group_id = engine.active_group;
-    let needs_confirm = engine.handle_tab_bar_click(group_id, TabBarClickTarget::CloseTab(1));
-    assert!(!needs_confirm); // Not dirty
-    assert_eq!(engine.active_group().tabs.len(), 1);
+    engine.execute_command("tabclose");
+    assert_ne!(engine.file_path().map(|p| p.as_path()), Some(b.as_path()));
+    engine.execute_command("e#");
+    assert_eq!(engine.file_path().map(|p| p.as_path()), Some(b.as_path()));
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_tab_bar_handle_click_close_dirty_tab_returns_true() {
-    let mut engine = Engine::new();
-    engine.new_tab(None); // tab 1
-                          // Make the buffer dirty
-    engine.buffer_mut().insert(0, "dirty");
-    engine
-        .buffer_manager
-        .get_mut(engine.active_buffer_id())
-        .unwrap()
-        .dirty = true;
-
-    let group_id = engine.active_group;
-    let needs_confirm = engine.handle_tab_bar_click(group_id, TabBarClickTarget::CloseTab(1));
-    assert!(needs_confirm); // Should request confirmation
-                            // Tab should NOT be closed yet
-    assert_eq!(engine.active_group().tabs.len(), 2);
+fn test_surround_add_change_delete() {
+    // ysiw) wraps the word; the cursor lands on the opening delimiter and
+    // one `u` removes both delimiters.
+    let mut engine = engine_with_text("say hello world\n");
+    send_keys(&mut engine, "wysiw)");
+    assert_eq!(engine.buffer().to_string(), "say (hello) world\n");
+    assert_eq!(engine.view().cursor.col, 4);
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "say hello world\n");
+
+    // Opening brackets pad with spaces; yss wraps the whole line.
+    send_keys(&mut engine, "ysiw(");
+    assert_eq!(engine.buffer().to_string(), "say ( hello ) world\n");
+    send_keys(&mut engine, "uyss]");
+    assert_eq!(engine.buffer().to_string(), "[say hello world]\n");
+
+    // cs"' swaps the quotes, ds( removes the parens and inner padding.
+    let mut engine = engine_with_text("x = \"hi\" + f( a )\n");
+    send_keys(&mut engine, "fhcs\"'");
+    assert_eq!(engine.buffer().to_string(), "x = 'hi' + f( a )\n");
+    assert_eq!(engine.view().cursor.col, 4);
+    send_keys(&mut engine, "fads(");
+    assert_eq!(engine.buffer().to_string(), "x = 'hi' + fa\n");
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "x = 'hi' + f( a )\n");
 }
 
-// --- Ctrl-]: tag jump (LSP go-to-definition) ---
-
 #[test]
-fn test_ctrl_bracket_right_pushes_jump_location() {
-    // Ctrl-] should push jump location (same as gd)
-    let mut engine = engine_with_text("hello world\nfoo bar\n");
-    engine.view_mut().cursor.line = 1;
-    engine.view_mut().cursor.col = 0;
-
-    // Ctrl-] — no LSP in tests, but jump list should be updated
-    engine.handle_key("bracketright", None, true);
+fn test_surround_visual_selection() {
+    let mut engine = engine_with_text("let a = b;\n");
+    send_keys(&mut engine, "wvS\"");
+    assert_eq!(engine.mode, Mode::Normal);
+    assert_eq!(engine.buffer().to_string(), "let \"a\" = b;\n");
 
-    // Jump list should have the position before the Ctrl-] press
-    assert!(
-        !engine.jump_list.is_empty(),
-        "jump list should have an entry"
-    );
+    // Linewise selections put the delimiters on their own lines.
+    let mut engine = engine_with_text("  foo\n  bar\n");
+    send_keys(&mut engine, "VjS}");
+    assert_eq!(engine.buffer().to_string(), "  {\n  foo\n  bar\n  }\n");
+    assert_eq!(engine.view().cursor, Cursor { line: 0, col: 2 });
 }
 
-// --- Activity bar hit regions ---
-
 #[test]
-fn test_activity_bar_resolve_builtin_panels() {
-    use crate::core::engine::{resolve_activity_bar_click, ActivityBarTarget, SidebarPanel};
-    let ext_names: Vec<String> = vec![];
+fn test_gc_motion_and_commentstring() {
+    // No file type → built-in fallback `#`; gc{motion} is one undo step.
+    let mut engine = engine_with_text("a\n  b\nc\n");
+    send_keys(&mut engine, "gcj");
+    assert_eq!(engine.buffer().to_string(), "# a\n  # b\nc\n");
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "a\n  b\nc\n");
 
+    // commentstring overrides the per-language table; gc on an already
+    // commented block uncomments it.
+    engine
+        .settings
+        .parse_set_option("commentstring=//\\ %s")
+        .unwrap();
+    assert_eq!(engine.settings.commentstring, "// %s");
+    send_keys(&mut engine, "gcip");
+    assert_eq!(engine.buffer().to_string(), "// a\n  // b\n// c\n");
+    send_keys(&mut engine, "gcip");
+    assert_eq!(engine.buffer().to_string(), "a\n  b\nc\n");
+    assert!(engine.settings.parse_set_option("cms=#").is_err());
+    assert!(engine.settings.set_value_str("commentstring", "#").is_err());
+    assert_eq!(engine.settings.commentstring, "// %s");
+
+    // :set commentstring only changes the current buffer.
+    engine.execute_command("set cms=--\\ %s");
+    send_keys(&mut engine, "gcc");
+    assert_eq!(engine.buffer().to_string(), "-- a\n  b\nc\n");
+    engine.execute_command("set cms?");
+    assert_eq!(engine.message, "commentstring=-- %s");
+    engine.execute_command("set cms=#");
+    assert!(engine.message.contains("must contain %s"));
+    engine.execute_command("enew");
+    engine.execute_command("set cms?");
+    assert_eq!(engine.message, "commentstring=// %s");
+}
+
+#[test]
+fn test_strip_whitespace_command() {
+    let mut engine = engine_with_text("a  \nb\t\nc\n  \n");
+    engine.view_mut().cursor = Cursor { line: 2, col: 0 };
+    engine.execute_command("StripWhitespace");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n\n");
+    assert_eq!(engine.view().cursor, Cursor { line: 2, col: 0 });
+    assert!(engine.message.contains("3 line"));
+
+    // One undo restores everything; the :%s idiom does the same job.
+    send_keys(&mut engine, "u");
+    assert_eq!(engine.buffer().to_string(), "a  \nb\t\nc\n  \n");
+    engine.execute_command("%s/\\s\\+$//");
+    assert_eq!(engine.buffer().to_string(), "a\nb\nc\n\n");
     assert_eq!(
-        resolve_activity_bar_click(0, 30, &ext_names),
-        Some(ActivityBarTarget::MenuToggle)
-    );
-    assert_eq!(
-        resolve_activity_bar_click(1, 30, &ext_names),
-        Some(ActivityBarTarget::Panel(SidebarPanel::Explorer))
-    );
+        engine.last_substitute,
+        Some(("\\s\\+$".to_string(), String::new(), String::new()))
+    );
+}
+
+#[test]
+fn test_which_key_entries_for_pending_prefix() {
+    let mut e = engine_with_text("one\ntwo\n");
+    assert!(e.which_key_entries().is_none());
+
+    // Built-in leader groups and leaves.
+    send_keys(&mut e, " ");
+    let (prefix, entries) = e.which_key_entries().expect("leader pending");
+    assert_eq!(prefix, "<leader>");
+    assert!(entries.contains(&("s".to_string(), "+search".to_string())));
+    assert!(entries.contains(&("b".to_string(), "Breadcrumb navigation".to_string())));
+    send_keys(&mut e, "s");
+    let (prefix, entries) = e.which_key_entries().unwrap();
+    assert_eq!(prefix, "<leader>s");
+    assert!(entries.contains(&("f".to_string(), "Search files".to_string())));
+    send_keys(&mut e, "<Esc>");
+    assert!(e.which_key_entries().is_none());
+
+    // g / z prefixes; an operator-pending `d` is not a which-key prefix.
+    send_keys(&mut e, "g");
+    let (prefix, entries) = e.which_key_entries().unwrap();
+    assert_eq!(prefix, "g");
+    assert!(entries.iter().any(|(k, _)| k == "d"));
+    send_keys(&mut e, "<Esc>d");
+    assert!(e.which_key_entries().is_none());
+    send_keys(&mut e, "<Esc>");
+
+    // User maps appear with their right-hand side as description.
+    e.settings.keymaps = vec!["n <leader>xa :set list<CR>".to_string()];
+    e.rebuild_user_keymaps();
+    send_keys(&mut e, " ");
+    let (_, entries) = e.which_key_entries().unwrap();
+    assert!(entries.contains(&("x".to_string(), "+prefix".to_string())));
+    send_keys(&mut e, "x");
+    let (prefix, entries) = e.which_key_entries().unwrap();
+    assert_eq!(prefix, "<leader>x");
+    assert_eq!(entries, vec![("a".to_string(), ":set list".to_string())]);
+
+    // The popup only shows after the delay, and hides once resolved.
+    assert!(!e.tick_which_key());
+    assert!(!e.which_key_visible);
+    send_keys(&mut e, "a");
+    assert!(e.settings.list);
+    assert!(!e.tick_which_key());
+    assert!(!e.which_key_visible);
+}
+
+#[test]
+fn test_word_highlight_after_cursor_settles() {
+    let mut e = engine_with_text("foo bar foo\na x\n");
+    // Pretend the cursor has rested where it is for longer than the delay.
+    let settle = |e: &mut Engine| {
+        let delay = std::time::Duration::from_millis(300);
+        e.word_highlight_anchor = e.word_highlight_anchor.map(|(pos, t)| (pos, t - delay));
+    };
+
+    // The highlight only appears once the cursor has rested on the word.
+    assert!(!e.tick_word_highlight());
+    assert!(e.word_highlight.is_none());
+    settle(&mut e);
+    assert!(e.tick_word_highlight());
     assert_eq!(
-        resolve_activity_bar_click(4, 30, &ext_names),
-        Some(ActivityBarTarget::Panel(SidebarPanel::Git))
+        e.word_highlight.as_ref().map(|(_, w)| w.as_str()),
+        Some("foo")
+    );
+
+    // Moving within the same word keeps it; moving to another word clears it.
+    send_keys(&mut e, "l");
+    assert!(!e.tick_word_highlight());
+    assert!(e.word_highlight.is_some());
+    send_keys(&mut e, "w");
+    assert!(e.tick_word_highlight());
+    assert!(e.word_highlight.is_none());
+
+    // Single-character words and Visual mode are skipped.
+    send_keys(&mut e, "j0");
+    settle(&mut e);
+    e.tick_word_highlight();
+    e.tick_word_highlight();
+    assert!(e.word_highlight.is_none());
+    send_keys(&mut e, "gg");
+    e.tick_word_highlight();
+    settle(&mut e);
+    assert!(e.tick_word_highlight());
+    send_keys(&mut e, "v");
+    assert!(e.tick_word_highlight());
+    assert!(e.word_highlight.is_none());
+    send_keys(&mut e, "<Esc>");
+
+    e.settings.highlight_word_under_cursor = false;
+    e.tick_word_highlight();
+    settle(&mut e);
+    assert!(!e.tick_word_highlight());
+    assert!(e.word_highlight.is_none());
+}
+
+#[test]
+fn test_diagnostics_command_fills_quickfix() {
+    let dir = std::env::temp_dir().join("vimcode_test_diagnostics_cmd");
+    let _ = std::fs::create_dir_all(&dir);
+    let a = dir.join("a.rs");
+    let b = dir.join("b.rs");
+    let c = dir.join("c.rs");
+    std::fs::write(&a, "fn a() {}\nlet x = 1;\n").unwrap();
+    std::fs::write(&b, "  fn b() {}\n").unwrap();
+    std::fs::write(&c, "no lsp here\n").unwrap();
+
+    let diag = |line: u32, character: u32, severity, message: &str| lsp::Diagnostic {
+        range: lsp::LspRange {
+            start: lsp::LspPosition { line, character },
+            end: lsp::LspPosition {
+                line,
+                character: character + 1,
+            },
+        },
+        severity,
+        message: message.to_string(),
+        source: None,
+        code: None,
+    };
+    let mut e = Engine::new();
+    e.open_file_with_mode(&c, OpenMode::Permanent).unwrap();
+    e.open_file_with_mode(&b, OpenMode::Permanent).unwrap();
+    e.open_file_with_mode(&a, OpenMode::Permanent).unwrap();
+    e.lsp_diagnostics.insert(
+        a.canonicalize().unwrap(),
+        vec![
+            diag(1, 4, lsp::DiagnosticSeverity::Warning, "unused"),
+            diag(0, 3, lsp::DiagnosticSeverity::Error, "bad\nmore detail"),
+        ],
     );
-    assert_eq!(
-        resolve_activity_bar_click(6, 30, &ext_names),
-        Some(ActivityBarTarget::Panel(SidebarPanel::Ai))
+    e.lsp_diagnostics.insert(
+        b.canonicalize().unwrap(),
+        vec![diag(0, 2, lsp::DiagnosticSeverity::Hint, "tip")],
     );
-    // Settings at bottom row
+
+    e.execute_command("Diagnostics");
+    assert!(e.quickfix_open);
+    assert_eq!(e.message, "3 diagnostics");
+    let items: Vec<(String, usize, usize, &str)> = e
+        .quickfix_items
+        .iter()
+        .map(|m| {
+            (
+                m.file.file_name().unwrap().to_string_lossy().into_owned(),
+                m.line,
+                m.col,
+                m.line_text.as_str(),
+            )
+        })
+        .collect();
     assert_eq!(
-        resolve_activity_bar_click(29, 30, &ext_names),
-        Some(ActivityBarTarget::Settings)
-    );
-    // Empty gap between panels and settings
-    assert_eq!(resolve_activity_bar_click(10, 30, &ext_names), None);
+        items,
+        vec![
+            ("a.rs".to_string(), 0, 3, "[E] bad"),
+            ("a.rs".to_string(), 1, 4, "[W] unused"),
+            ("b.rs".to_string(), 0, 2, "[H] tip"),
+        ]
+    );
+
+    // ]d / [d follow position order even when the server sent them unsorted.
+    send_keys(&mut e, "gg0]d");
+    assert_eq!((e.view().cursor.line, e.view().cursor.col), (0, 3));
+    send_keys(&mut e, "]d");
+    assert_eq!((e.view().cursor.line, e.view().cursor.col), (1, 4));
+    send_keys(&mut e, "]d");
+    assert_eq!((e.view().cursor.line, e.view().cursor.col), (0, 3));
+    send_keys(&mut e, "[d");
+    assert_eq!((e.view().cursor.line, e.view().cursor.col), (1, 4));
+
+    e.lsp_diagnostics.clear();
+    e.quickfix_open = false;
+    e.execute_command("Diagnostics");
+    assert!(!e.quickfix_open);
+    assert_eq!(e.message, "No diagnostics");
+
+    let _ = std::fs::remove_dir_all(&dir);
 }
 
 #[test]
-fn test_activity_bar_resolve_extension_panels() {
-    use crate::core::engine::{resolve_activity_bar_click, ActivityBarTarget};
-    let ext_names = vec!["git-insights".to_string(), "todo-panel".to_string()];
+fn test_editor_hover_paging_and_wrapping() {
+    let mut e = engine_with_text("foo\n");
+    let body: Vec<String> = (0..50).map(|i| format!("line {i}\n")).collect();
+    e.show_editor_hover(0, 0, &body.join("\n"), EditorHoverSource::Lsp, true, false);
+    let scroll = |e: &Engine| e.editor_hover.as_ref().unwrap().scroll_top;
+    let total = e.editor_hover.as_ref().unwrap().rendered.lines.len();
+    let max_scroll = total - 20;
+
+    press_ctrl(&mut e, 'f');
+    assert_eq!(scroll(&e), 20.min(max_scroll));
+    press_ctrl(&mut e, 'f');
+    press_ctrl(&mut e, 'f');
+    press_ctrl(&mut e, 'f');
+    assert_eq!(scroll(&e), max_scroll);
+    press_ctrl(&mut e, 'b');
+    assert_eq!(scroll(&e), max_scroll - 20);
+    press_ctrl(&mut e, 'u');
+    assert_eq!(scroll(&e), max_scroll - 30);
+    press_char(&mut e, 'j');
+    assert_eq!(scroll(&e), max_scroll - 29);
+    press_special(&mut e, "Escape");
+    assert!(e.editor_hover.is_none());
+    assert!(!e.editor_hover_has_focus);
 
-    // Row 7 = first extension panel
-    assert_eq!(
-        resolve_activity_bar_click(7, 30, &ext_names),
-        Some(ActivityBarTarget::ExtensionPanel(
-            "git-insights".to_string()
-        ))
-    );
-    // Row 8 = second extension panel
-    assert_eq!(
-        resolve_activity_bar_click(8, 30, &ext_names),
-        Some(ActivityBarTarget::ExtensionPanel("todo-panel".to_string()))
-    );
-    // Row 9 with only 2 extensions = None (gap)
-    assert_eq!(resolve_activity_bar_click(9, 30, &ext_names), None);
+    // Long lines wrap to the popup width instead of running off the edge.
+    let long = "word ".repeat(60);
+    e.show_editor_hover(0, 0, &long, EditorHoverSource::Lsp, true, false);
+    let hover = e.editor_hover.as_ref().unwrap();
+    assert!(hover.rendered.lines.len() > 1);
+    assert!(hover.rendered.lines.iter().all(|l| l.chars().count() <= 80));
+    assert!(hover.popup_width <= 80);
+    assert_eq!(hover.rendered.lines.concat().trim_end(), long.trim_end());
 }
 
-// --- Context menu hit regions ---
+#[test]
+fn test_snippet_completion_tab_stops() {
+    use crate::core::lsp::CompletionItem;
+
+    let mut engine = engine_with_text("\n");
+    press_char(&mut engine, 'i');
+    engine.completion_items = vec![CompletionItem {
+        label: "push(…)".to_string(),
+        kind: None,
+        detail: None,
+        insert_text: Some("push(${1:value}, ${2:n})$0".to_string()),
+        is_snippet: true,
+        sort_text: None,
+        documentation: None,
+        raw: serde_json::Value::Null,
+    }];
+    engine.completion_candidates = vec!["push(value, n)".to_string()];
+    engine.completion_idx = Some(0);
+    engine.completion_start_col = 0;
+    engine.completion_display_only = true;
+
+    // Accepting inserts the plain text and selects the first placeholder.
+    press_special(&mut engine, "Tab");
+    assert_eq!(engine.buffer().to_string(), "push(value, n)\n");
+    assert_eq!(engine.view().cursor.col, 5);
+    let session = engine.snippet_session.as_ref().unwrap();
+    assert!(session.selected);
+    assert_eq!(session.stops[session.current], (5, 5));
+
+    // Typing replaces the placeholder; later stops shift.
+    press_char(&mut engine, 'x');
+    assert_eq!(engine.buffer().to_string(), "push(x, n)\n");
+    press_special(&mut engine, "Tab");
+    assert_eq!(engine.view().cursor.col, 8);
+    press_char(&mut engine, 'y');
+    press_char(&mut engine, 'z');
+    assert_eq!(engine.buffer().to_string(), "push(x, yz)\n");
+
+    // Shift-Tab goes back; Tab past the last placeholder lands on $0.
+    press_special(&mut engine, "ISO_Left_Tab");
+    assert_eq!(engine.view().cursor.col, 5);
+    press_special(&mut engine, "Tab");
+    press_special(&mut engine, "Tab");
+    assert_eq!(engine.view().cursor.col, 11);
+    assert!(engine.snippet_session.is_none());
+    assert_eq!(engine.mode, Mode::Insert);
+}
 
 #[test]
-fn test_context_menu_click_item() {
-    use crate::core::engine::{
-        resolve_context_menu_click, ContextMenuClickResult, ContextMenuItem,
+fn test_snippet_session_ends_on_escape() {
+    let mut engine = engine_with_text("\n");
+    press_char(&mut engine, 'i');
+    let snippet = crate::core::snippet::parse("f(${1:a}, ${2:b})");
+    engine.buffer_mut().insert(0, &snippet.text);
+    engine.start_snippet_session(&snippet, 0);
+    assert_eq!(engine.view().cursor.col, 2);
+    press_special(&mut engine, "Escape");
+    assert!(engine.snippet_session.is_none());
+    assert_eq!(engine.mode, Mode::Normal);
+}
+
+#[test]
+fn test_completion_fuzzy_ranking() {
+    use crate::core::lsp::CompletionItem;
+
+    let item = |label: &str, sort: &str| CompletionItem {
+        label: label.to_string(),
+        kind: None,
+        detail: None,
+        insert_text: None,
+        is_snippet: false,
+        sort_text: Some(sort.to_string()),
+        documentation: None,
+        raw: serde_json::Value::Null,
     };
     let items = vec![
-        ContextMenuItem {
-            label: "Cut".to_string(),
-            action: "cut".to_string(),
-            shortcut: "".to_string(),
-            separator_after: false,
-            enabled: true,
-        },
-        ContextMenuItem {
-            label: "Copy".to_string(),
-            action: "copy".to_string(),
-            shortcut: "".to_string(),
-            separator_after: true, // separator after this item
-            enabled: true,
-        },
-        ContextMenuItem {
-            label: "Paste".to_string(),
-            action: "paste".to_string(),
-            shortcut: "".to_string(),
-            separator_after: false,
-            enabled: true,
-        },
+        item("transform", "1"),
+        item("to_formatted_message", "3"),
+        item("tfm_b", "2"),
+        item("tfm_a", "4"),
+        item("other", "0"),
     ];
-    // Popup at (10, 5), terminal 80x24
-    // popup_h = 3 items + 1 separator + 2 border = 6
-    // Item 0 "Cut" at visual row 0 (inner_row 0)
-    assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 6),
-        ContextMenuClickResult::Item(0)
-    );
-    // Item 1 "Copy" at visual row 1 (inner_row 1)
-    assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 7),
-        ContextMenuClickResult::Item(1)
-    );
-    // Separator at visual row 2 → InsidePopup
-    assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 8),
-        ContextMenuClickResult::InsidePopup
-    );
-    // Item 2 "Paste" at visual row 3 (inner_row 3)
-    assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 9),
-        ContextMenuClickResult::Item(2)
-    );
-    // Outside
+    // Equal scores fall back to the server's sortText.
     assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 0, 0),
-        ContextMenuClickResult::Outside
+        Engine::rank_completion_items(&items, "tfm"),
+        vec!["tfm_b", "to_formatted_message", "tfm_a", "transform"]
     );
+
+    // Typing re-ranks the server's items for the same word.
+    let mut engine = engine_with_text("\n");
+    send_keys(&mut engine, "itf");
+    engine.completion_items = items;
+    engine.completion_items_origin = Some((engine.active_buffer_id(), 0, 0));
+    press_char(&mut engine, 'm');
+    assert_eq!(engine.completion_candidates[1], "to_formatted_message");
+    assert_eq!(engine.completion_idx, Some(0));
+
+    // A moved selection follows its candidate when the list changes.
+    press_special(&mut engine, "Down");
+    engine.set_completion_candidates(vec![
+        "to_formatted_message".to_string(),
+        "tfm_b".to_string(),
+    ]);
+    assert_eq!(engine.completion_idx, Some(0));
+    engine.set_completion_candidates(vec!["tfm_a".to_string()]);
+    assert_eq!(engine.completion_idx, Some(0));
+
+    // Moving to another word drops the stale items.
+    send_keys(&mut engine, " x");
+    assert!(engine.completion_items.is_empty());
 }
 
 #[test]
-fn test_context_menu_disabled_item() {
-    use crate::core::engine::{
-        resolve_context_menu_click, ContextMenuClickResult, ContextMenuItem,
-    };
-    let items = vec![ContextMenuItem {
-        label: "Disabled".to_string(),
-        action: "noop".to_string(),
-        shortcut: "".to_string(),
-        separator_after: false,
-        enabled: false,
-    }];
-    // Click on disabled item → InsidePopup (not Item)
+fn test_iabbrev_expands_on_non_keyword_char() {
+    let mut engine = engine_with_text("");
+    engine.execute_command("iab teh the");
+    engine.execute_command("iabbrev fn() function");
+    assert_eq!(engine.settings.abbreviations.get("teh").unwrap(), "the");
+
+    send_keys(&mut engine, "iteh cat, tehx teh.");
+    assert_eq!(engine.buffer().to_string(), "the cat, tehx the.");
+
+    // A non-keyword lhs needs whitespace around it.
+    send_keys(&mut engine, " fn() x<CR>afn() ");
     assert_eq!(
-        resolve_context_menu_click(&items, 10, 5, 80, 24, 15, 6),
-        ContextMenuClickResult::InsidePopup
+        engine.buffer().to_string(),
+        "the cat, tehx the. function x\nafn() "
     );
-}
 
-// --- Dialog hit regions ---
+    // The expansion is part of the insert: one undo removes it all.
+    send_keys(&mut engine, "<Esc>u");
+    assert_eq!(engine.buffer().to_string(), "");
+}
 
 #[test]
-fn test_dialog_click_button() {
-    use crate::core::engine::{resolve_dialog_click, DialogButton, DialogClickResult};
-    let buttons = vec![
-        DialogButton {
-            label: "Cancel".to_string(),
-            hotkey: 'c',
-            action: "cancel".to_string(),
-        },
-        DialogButton {
-            label: "OK".to_string(),
-            hotkey: 'o',
-            action: "ok".to_string(),
-        },
-    ];
-    // Dialog at (20, 10), width 40, height 6, buttons on row 14
-    let layout = DialogLayout {
-        x: 20,
-        y: 10,
-        width: 40,
-        height: 6,
-        btn_y: 14,
-    };
-    let fmt = |label: &str, _hotkey: char| label.to_string();
-    // Button 0 "Cancel" starts at col 22 (layout.x + 2), width ~10
-    assert_eq!(
-        resolve_dialog_click(&buttons, &layout, 23, 14, &fmt),
-        DialogClickResult::Button(0)
-    );
-    // Outside dialog
-    assert_eq!(
-        resolve_dialog_click(&buttons, &layout, 0, 0, &fmt),
-        DialogClickResult::Outside
-    );
-    // Inside but not on buttons
-    assert_eq!(
-        resolve_dialog_click(&buttons, &layout, 30, 12, &fmt),
-        DialogClickResult::InsideDialog
-    );
+fn test_iabbrev_ctrl_v_suppresses_and_unabbreviate() {
+    let mut engine = engine_with_text("");
+    engine.execute_command("iabbrev teh the");
+    send_keys(&mut engine, "iteh<C-v> teh<CR>");
+    assert_eq!(engine.buffer().to_string(), "teh the\n");
+
+    send_keys(&mut engine, "<Esc>");
+    engine.execute_command("una teh");
+    assert!(engine.settings.abbreviations.is_empty());
+    send_keys(&mut engine, "Ateh ");
+    assert_eq!(engine.buffer().to_string(), "teh the\nteh ");
+    engine.execute_command("unabbreviate teh");
+    assert!(engine.message.starts_with("E24"));
 }
@@ -463,6 +463,42 @@ impl Engine {
         self.visual_anchor = None;
         self.visual_dollar = false;
     }
+
+    /// Replay the text typed after a visual-block `I`/`A` onto the remaining
+    /// block lines. Called from insert-mode Escape while the first line's undo
+    /// group is still open, so the edit undoes as one unit. With `I`, lines
+    /// that end before the block column are left alone; with `A` they are
+    /// padded with spaces up to the column.
+    pub(crate) fn apply_visual_block_insert(&mut self) {
+        let Some((start_line, end_line, col, is_append, virtual_end)) =
+            self.visual_block_insert_info.take()
+        else {
+            return;
+        };
+        let text = self.insert_text_buffer.clone();
+        if text.is_empty() || text.contains('\n') {
+            return;
+        }
+        // The first line was already typed into.
+        for line in (start_line + 1)..=end_line {
+            if line >= self.buffer().len_lines() {
+                break;
+            }
+            let line_len = self.get_line_len_for_insert(line);
+            // In virtual-end mode (`$<C-v>...A`), the insert column is this
+            // specific line's own end — no padding needed, just append.
+            let target_col = if virtual_end { line_len } else { col };
+            if target_col > line_len {
+                if !is_append {
+                    continue;
+                }
+                let pos = self.buffer().line_to_char(line) + line_len;
+                let spaces = " ".repeat(target_col - line_len);
+                self.insert_with_undo(pos, &spaces);
+            }
+            self.insert_with_undo(self.buffer().line_to_char(line) + target_col, &text);
+        }
+    }
 }
 
 // ─── Additional methods (extracted from mod.rs) ─────────────────────────
@@ -951,4 +987,107 @@ impl Engine {
             .map(|&cidx| self.char_idx_to_cursor(cidx))
             .collect();
     }
+
+    /// Apply a cursor motion to the primary cursor and every extra cursor.
+    /// Cursors that land on the same position are merged.
+    pub(crate) fn mc_move(&mut self, motion: impl Fn(&mut Self)) {
+        let extra = self.view().extra_cursors.clone();
+        let mut moved: Vec<Cursor> = Vec::with_capacity(extra.len());
+        let primary = *self.cursor();
+        for ec in extra {
+            self.view_mut().cursor = ec;
+            motion(self);
+            moved.push(*self.cursor());
+        }
+        self.view_mut().cursor = primary;
+        motion(self);
+        let primary = *self.cursor();
+        let mut extras: Vec<Cursor> = Vec::with_capacity(moved.len());
+        for c in moved {
+            if c != primary && !extras.contains(&c) {
+                extras.push(c);
+            }
+        }
+        self.view_mut().extra_cursors = extras;
+    }
+
+    /// Normal-mode `x` at every cursor: delete up to `count` chars under each
+    /// cursor (never past its line end) as one undo group. The register gets
+    /// the primary cursor's deleted text. Returns `true` if anything changed.
+    pub(crate) fn mc_delete_chars(&mut self, count: usize) -> bool {
+        let extra = self.view().extra_cursors.clone();
+        let primary = *self.cursor();
+
+        // (orig_cidx, chars_to_delete) for every cursor, before modification.
+        let span_at = |c: &Cursor| {
+            let line_start = self.buffer().line_to_char(c.line);
+            let line_end = line_start + self.get_line_len_for_insert(c.line);
+            let orig = line_start + c.col;
+            (orig, count.min(line_end.saturating_sub(orig)))
+        };
+        let primary_span = span_at(&primary);
+        let extra_spans: Vec<(usize, usize)> = extra.iter().map(span_at).collect();
+
+        let mut spans: Vec<(usize, usize)> = extra_spans.clone();
+        spans.push(primary_span);
+        spans.sort_unstable();
+        spans.dedup_by_key(|s| s.0);
+        // A count can run into the next cursor on the same line; stop there.
+        for i in 1..spans.len() {
+            let gap = spans[i].0 - spans[i - 1].0;
+            spans[i - 1].1 = spans[i - 1].1.min(gap);
+        }
+        if spans.iter().all(|&(_, n)| n == 0) {
+            return false;
+        }
+
+        if primary_span.1 > 0 {
+            let (orig, n) = primary_span;
+            let deleted: String = self
+                .buffer()
+                .content
+                .slice(orig..orig + n)
+                .chars()
+                .collect();
+            let reg = self.active_register();
+            self.set_delete_register(reg, deleted, false);
+            self.clear_selected_register();
+        }
+
+        // A cursor stays in place; deletions before it shift it left.
+        let new_cidx = |orig: usize| {
+            orig - spans
+                .iter()
+                .filter(|&&(o, _)| o < orig)
+                .map(|&(o, n)| n.min(orig - o))
+                .sum::<usize>()
+        };
+        let primary_new = new_cidx(primary_span.0);
+        let extra_new: Vec<usize> = extra_spans.iter().map(|&(o, _)| new_cidx(o)).collect();
+
+        // Delete bottom-to-top so earlier offsets stay valid.
+        self.start_undo_group();
+        for &(orig, n) in spans.iter().rev() {
+            if n > 0 {
+                self.delete_with_undo(orig, orig + n);
+            }
+        }
+        self.finish_undo_group();
+
+        self.view_mut().cursor = self.char_idx_to_cursor(primary_new);
+        self.clamp_cursor_col();
+        let primary = *self.cursor();
+        let mut extras: Vec<Cursor> = Vec::with_capacity(extra_new.len());
+        for cidx in extra_new {
+            let mut c = self.char_idx_to_cursor(cidx);
+            c.col = c.col.min(self.get_max_cursor_col(c.line));
+            if c != primary && !extras.contains(&c) {
+                extras.push(c);
+            }
+        }
+        self.view_mut().extra_cursors = extras;
+        // Keep the cursors alive past the post-edit cleanup in `handle_key`.
+        self.mc_keep_extra_cursors = true;
+        true
+    }
 }
@@ -15,6 +15,12 @@ impl Engine {
                 _ => "EDIT  F1:cmd  Alt-M:vim",
             };
         }
+        if self.terminal_insert
+            && self.mode == Mode::Normal
+            && self.terminal_buffers.contains_key(&self.active_buffer_id())
+        {
+            return "TERMINAL";
+        }
         match self.mode {
             Mode::Normal | Mode::Search => "NORMAL",
             Mode::Command => "COMMAND",
@@ -830,7 +836,7 @@ impl Engine {
                 self.indent_lines(line, 1, changed);
             }
             // Adjust cursor columns for indent
-            let indent_size = if self.settings.expand_tab {
+            let indent_size = if self.effective_expand_tab() {
                 self.effective_shift_width()
             } else {
                 1
@@ -857,7 +863,7 @@ impl Engine {
             }
             lines.sort_unstable();
             // Check indent size before dedenting
-            let indent_size = if self.settings.expand_tab {
+            let indent_size = if self.effective_expand_tab() {
                 self.effective_shift_width()
             } else {
                 1
@@ -1464,8 +1470,8 @@ impl Engine {
                     let line = self.view().cursor.line;
                     let col = self.view().cursor.col;
                     let char_idx = self.buffer().line_to_char(line) + col;
-                    if self.settings.expand_tab {
-                        let n = self.settings.tabstop as usize;
+                    if self.effective_expand_tab() {
+                        let n = self.effective_tabstop();
                         let spaces = " ".repeat(n);
                         self.insert_with_undo(char_idx, &spaces);
                         self.view_mut().cursor.col += n;
@@ -1604,7 +1610,9 @@ impl Engine {
                                 changed = true;
                             } else if self.settings.auto_pairs && closing_pair.is_some() {
                                 let closer = closing_pair.unwrap();
-                                let should_pair = if is_quote_char(ch) {
+                                let should_pair = if self.in_comment_or_string(char_idx) {
+                                    false
+                                } else if is_quote_char(ch) {
                                     if char_idx == 0 {
                                         true
                                     } else {
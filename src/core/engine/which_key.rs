@@ -0,0 +1,261 @@
+use super::*;
+
+/// Built-in multi-key sequences shown by the which-key popup, with short
+/// descriptions.  Leader sequences also drive `handle_leader_key`.
+pub(crate) const WHICH_KEY_BINDINGS: &[(&str, &str)] = &[
+    // Leader
+    ("<leader>a", "Harpoon: add file"),
+    ("<leader>1", "Harpoon: slot 1"),
+    ("<leader>2", "Harpoon: slot 2"),
+    ("<leader>3", "Harpoon: slot 3"),
+    ("<leader>4", "Harpoon: slot 4"),
+    ("<leader>b", "Breadcrumb navigation"),
+    ("<leader>ca", "Code actions"),
+    ("<leader>fr", "Recent files"),
+    ("<leader>gb", "Toggle inline blame"),
+    ("<leader>gf", "Format file"),
+    ("<leader>gF", "Format file"),
+    ("<leader>gi", "Go to implementation"),
+    ("<leader>rn", "Rename symbol"),
+    ("<leader>sb", "Search buffers"),
+    ("<leader>sf", "Search files"),
+    ("<leader>sg", "Live grep"),
+    ("<leader>sk", "Search keybindings"),
+    ("<leader>so", "Document symbols"),
+    ("<leader>sp", "Command palette"),
+    ("<leader>sS", "Workspace symbols"),
+    ("<leader>sw", "Grep word under cursor"),
+    // g
+    ("gg", "First line"),
+    ("ge", "End of previous word"),
+    ("gE", "End of previous WORD"),
+    ("g_", "Last non-blank"),
+    ("g0", "Start of display line"),
+    ("g^", "First non-blank of display line"),
+    ("g$", "End of display line"),
+    ("gj", "Display line down"),
+    ("gk", "Display line up"),
+    ("gm", "Middle of screen line"),
+    ("gM", "Middle of text line"),
+    ("g*", "Search word (partial)"),
+    ("g#", "Search word backward (partial)"),
+    ("gd", "Go to definition"),
+    ("gD", "Peek git diff hunk"),
+    ("gy", "Go to type definition"),
+    ("gr", "References"),
+    ("gh", "Hover"),
+    ("gs", "Stage git hunk"),
+    ("gf", "Open file under cursor"),
+    ("gF", "Open file:line under cursor"),
+    ("gx", "Open URL under cursor"),
+    ("gi", "Insert at last insert position"),
+    ("gI", "Insert at column 0"),
+    ("gJ", "Join lines without spaces"),
+    ("gv", "Reselect last visual area"),
+    ("gn", "Select next match"),
+    ("gN", "Select previous match"),
+    ("gp", "Put after, cursor past text"),
+    ("gP", "Put before, cursor past text"),
+    ("gu", "Lowercase {motion}"),
+    ("gU", "Uppercase {motion}"),
+    ("g~", "Toggle case {motion}"),
+    ("g?", "ROT13 {motion}"),
+    ("gq", "Format {motion}"),
+    ("gw", "Format {motion}, keep cursor"),
+    ("gc", "Comment {motion}"),
+    ("g@", "Call operatorfunc {motion}"),
+    ("g;", "Older change position"),
+    ("g,", "Newer change position"),
+    ("g+", "Newer text state"),
+    ("g-", "Older text state"),
+    ("g&", "Repeat :s on all lines"),
+    ("ga", "Character code"),
+    ("g8", "UTF-8 bytes"),
+    ("go", "Go to byte"),
+    ("gR", "Virtual Replace mode"),
+    ("g`", "Jump to mark (no jumplist)"),
+    // z
+    ("zz", "Center cursor line"),
+    ("zt", "Cursor line to top"),
+    ("zb", "Cursor line to bottom"),
+    ("zh", "Scroll left"),
+    ("zl", "Scroll right"),
+    ("zH", "Scroll half screen left"),
+    ("zL", "Scroll half screen right"),
+    ("za", "Toggle fold"),
+    ("zo", "Open fold"),
+    ("zc", "Close fold"),
+    ("zA", "Toggle fold recursively"),
+    ("zO", "Open fold recursively"),
+    ("zC", "Close fold recursively"),
+    ("zR", "Open all folds"),
+    ("zM", "Close all folds"),
+    ("zf", "Create fold {motion}"),
+    ("zd", "Delete fold"),
+    ("zj", "Next fold"),
+    ("zk", "Previous fold"),
+    ("z=", "Spelling suggestions"),
+    ("zg", "Add word to dictionary"),
+    ("zw", "Mark word as misspelled"),
+    // [ / ]
+    ("]c", "Next git hunk"),
+    ("[c", "Previous git hunk"),
+    ("]d", "Next diagnostic"),
+    ("[d", "Previous diagnostic"),
+    ("]s", "Next misspelling"),
+    ("[s", "Previous misspelling"),
+    ("]m", "Next method start"),
+    ("[m", "Previous method start"),
+    ("]]", "Next section"),
+    ("[[", "Previous section"),
+    ("]z", "End of open fold"),
+    ("[z", "Start of open fold"),
+    // Ctrl-W
+    ("<C-w>h", "Window left"),
+    ("<C-w>j", "Window down"),
+    ("<C-w>k", "Window up"),
+    ("<C-w>l", "Window right"),
+    ("<C-w>w", "Next window"),
+    ("<C-w>p", "Previous window"),
+    ("<C-w>s", "Split horizontally"),
+    ("<C-w>v", "Split vertically"),
+    ("<C-w>c", "Close window"),
+    ("<C-w>q", "Quit window"),
+    ("<C-w>o", "Close other windows"),
+    ("<C-w>n", "New window"),
+    ("<C-w>x", "Exchange windows"),
+    ("<C-w>r", "Rotate windows"),
+    ("<C-w>=", "Equalize sizes"),
+    ("<C-w>+", "Taller"),
+    ("<C-w>-", "Shorter"),
+    ("<C-w>>", "Wider"),
+    ("<C-w><", "Narrower"),
+    ("<C-w>e", "New editor group right"),
+    ("<C-w>E", "New editor group below"),
+];
+
+/// Names for key groups (prefixes of several bindings).
+const WHICH_KEY_GROUPS: &[(&str, &str)] = &[
+    ("<leader>c", "+code"),
+    ("<leader>f", "+files"),
+    ("<leader>g", "+git/format"),
+    ("<leader>r", "+refactor"),
+    ("<leader>s", "+search"),
+];
+
+/// How long a prefix must be pending before the popup appears.
+const WHICH_KEY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// First key of a key-notation string: a `<...>` token or one character.
+fn first_key(keys: &str) -> &str {
+    if keys.starts_with('<') {
+        if let Some(end) = keys.find('>').filter(|&e| e > 1) {
+            return &keys[..=end];
+        }
+    }
+    let len = keys.chars().next().map_or(0, char::len_utf8);
+    &keys[..len]
+}
+
+impl Engine {
+    /// The pending multi-key prefix and the keys that may follow it, as
+    /// `(prefix, [(next key, description)])` sorted by key.  `None` when no
+    /// prefix is pending.  Descriptions come from user keymaps and
+    /// [`WHICH_KEY_BINDINGS`]; keys that start longer sequences are groups.
+    pub fn which_key_entries(&self) -> Option<(String, Vec<(String, String)>)> {
+        let leader = encode_keypress("", Some(self.settings.leader), false);
+        let prefix = if !self.keymap_buf.is_empty() {
+            self.keymap_buf
+                .iter()
+                .enumerate()
+                .map(|(i, k)| {
+                    if i == 0 && *k == leader {
+                        "<leader>"
+                    } else {
+                        k.as_str()
+                    }
+                })
+                .collect::<String>()
+        } else if let Some(partial) = &self.leader_partial {
+            format!("<leader>{partial}")
+        } else if self.pending_operator.is_none() {
+            match self.pending_key? {
+                'g' => "g",
+                'z' => "z",
+                '[' => "[",
+                ']' => "]",
+                '\x17' => "<C-w>",
+                _ => return None,
+            }
+            .to_string()
+        } else {
+            return None;
+        };
+
+        let mut leaves: std::collections::BTreeMap<String, String> = Default::default();
+        let mut groups: std::collections::BTreeMap<String, String> = Default::default();
+        let mut add = |rest: &str, desc: &str| {
+            if rest.is_empty() {
+                return;
+            }
+            let key = first_key(rest);
+            if key.len() == rest.len() {
+                leaves.insert(key.to_string(), desc.to_string());
+            } else {
+                let full = format!("{prefix}{key}");
+                let name = WHICH_KEY_GROUPS
+                    .iter()
+                    .find(|(g, _)| *g == full)
+                    .map_or("+prefix", |(_, n)| *n);
+                groups.insert(key.to_string(), name.to_string());
+            }
+        };
+        for (seq, desc) in WHICH_KEY_BINDINGS {
+            if let Some(rest) = seq.strip_prefix(prefix.as_str()) {
+                add(rest, desc);
+            }
+        }
+        // User maps are only pending through `keymap_buf`.
+        if let Some(mode) = self
+            .user_keymap_mode()
+            .filter(|_| !self.keymap_buf.is_empty())
+        {
+            let buf = &self.keymap_buf;
+            for km in &self.user_keymaps {
+                if km.mode == mode && km.keys.len() > buf.len() && km.keys.starts_with(buf) {
+                    add(&km.keys[buf.len()..].concat(), &km.action.to_string());
+                }
+            }
+        }
+        if leaves.is_empty() && groups.is_empty() {
+            return None;
+        }
+        for (key, name) in groups {
+            leaves.entry(key).or_insert(name);
+        }
+        Some((prefix, leaves.into_iter().collect()))
+    }
+
+    /// Show the which-key popup once a prefix has been pending for a moment,
+    /// and hide it when the prefix completes or is cancelled.  Returns true
+    /// when the popup appeared or vanished (caller should redraw).  Call from
+    /// the event loop idle path.
+    pub fn tick_which_key(&mut self) -> bool {
+        let pending = self.settings.which_key && self.which_key_entries().is_some();
+        if !pending {
+            self.which_key_since = None;
+            return std::mem::take(&mut self.which_key_visible);
+        }
+        match self.which_key_since {
+            None => {
+                self.which_key_since = Some(std::time::Instant::now());
+                false
+            }
+            Some(since) if !self.which_key_visible && since.elapsed() >= WHICH_KEY_DELAY => {
+                self.which_key_visible = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -26,7 +26,10 @@ impl Engine {
 
         // Determine which buffer the new window should show
         let new_buffer_id = if let Some(path) = file_path {
-            match self.buffer_manager.open_file(path) {
+            match self
+                .buffer_manager
+                .open_file(path, &self.cwd, &self.settings)
+            {
                 Ok(id) => {
                     self.buffer_manager
                         .apply_language_map(id, &self.settings.language_map);
@@ -50,6 +53,8 @@ impl Engine {
         if new_buffer_id == current_buffer_id {
             new_window.view = self.active_window().view.clone();
         }
+        // A split inherits the window-local directory, as in Vim.
+        new_window.local_cwd = self.active_window().local_cwd.clone();
 
         self.windows.insert(new_window_id, new_window);
 
@@ -72,6 +77,10 @@ impl Engine {
 
     /// Close the active window. Returns true if the window was closed.
     pub fn close_window(&mut self) -> bool {
+        // Closing a zoomed window shows the windows the zoom hid.
+        if self.active_tab().layout.is_single_window() {
+            self.active_tab_mut().unzoom();
+        }
         let is_single_tab = self.active_group().tabs.len() == 1;
         let is_single_window = self.active_tab().layout.is_single_window();
 
@@ -103,6 +112,7 @@ impl Engine {
         self.windows.remove(&window_id);
         self.scroll_bind_pairs
             .retain(|&(a, b)| a != window_id && b != window_id);
+        self.diff_unified_forget_window(window_id);
         if let Some((a, b)) = self.diff_window_pair.take() {
             if a == window_id || b == window_id {
                 self.clear_diff_labels(a, b);
@@ -115,11 +125,9 @@ impl Engine {
                 // Remove partner window from layout and windows map.
                 if self.windows.contains_key(&partner) {
                     let tab = self.active_tab_mut();
-                    if let Some(new_layout) = tab.layout.remove(partner) {
-                        tab.layout = new_layout;
-                        if let Some(first) = tab.layout.window_ids().first().copied() {
-                            tab.active_window = first;
-                        }
+                    tab.remove_window(partner);
+                    if let Some(first) = tab.layout.window_ids().first().copied() {
+                        tab.active_window = first;
                     }
                     self.windows.remove(&partner);
                     self.scroll_bind_pairs
@@ -156,29 +164,60 @@ impl Engine {
 
         // Ensure the active window is still valid after removal.
         self.repair_active_window();
+        self.close_hidden_terminals();
         true
     }
 
-    /// Close all windows except the active one in the current tab.
-    pub fn close_other_windows(&mut self) {
+    /// Close all windows except the active one in the current tab (`:only`,
+    /// CTRL-W o).  Unless `force`, a window is kept when it is the last one
+    /// showing a buffer with unsaved changes.  A zoom is undone first, so
+    /// the windows it hid are closed too.
+    pub fn close_other_windows(&mut self, force: bool) {
         let active_window_id = self.active_window_id();
-        let tab = self.active_tab_mut();
+        self.active_tab_mut().unzoom();
 
-        // Get all window IDs except active
-        let windows_to_close: Vec<WindowId> = tab
+        let others: Vec<WindowId> = self
+            .active_tab()
             .layout
             .window_ids()
             .into_iter()
             .filter(|&id| id != active_window_id)
             .collect();
+        let (kept, windows_to_close): (Vec<WindowId>, Vec<WindowId>) =
+            others.iter().copied().partition(|&id| {
+                if force {
+                    return false;
+                }
+                let Some(buffer_id) = self.windows.get(&id).map(|w| w.buffer_id) else {
+                    return false;
+                };
+                let dirty = self
+                    .buffer_manager
+                    .get(buffer_id)
+                    .is_some_and(|state| state.dirty);
+                dirty
+                    && !self
+                        .windows
+                        .iter()
+                        .any(|(wid, w)| w.buffer_id == buffer_id && !others.contains(wid))
+            });
 
-        // Reset layout to single window
-        tab.layout = WindowLayout::leaf(active_window_id);
+        let tab = self.active_tab_mut();
+        if kept.is_empty() {
+            tab.layout = WindowLayout::leaf(active_window_id);
+        } else {
+            for &id in &windows_to_close {
+                if let Some(layout) = tab.layout.remove(id) {
+                    tab.layout = layout;
+                }
+            }
+        }
 
         // Remove closed windows and any scroll-bind pairs referencing them.
         for id in windows_to_close {
             self.windows.remove(&id);
             self.scroll_bind_pairs.retain(|&(a, b)| a != id && b != id);
+            self.diff_unified_forget_window(id);
             if let Some((a, b)) = self.diff_window_pair {
                 if a == id || b == id {
                     self.clear_diff_labels(a, b);
@@ -189,7 +228,22 @@ impl Engine {
             }
         }
 
-        self.message = String::new();
+        if kept.is_empty() {
+            self.message.clear();
+        } else {
+            self.set_error_message("E445: Other window contains changes".to_string());
+        }
+    }
+
+    /// CTRL-W z: let the active window fill the tab, or bring back the
+    /// split layout it was zoomed from.
+    pub fn toggle_window_zoom(&mut self) {
+        let tab = self.active_tab_mut();
+        if tab.zoomed.is_none() && tab.layout.is_single_window() {
+            self.message = "Already the only window".to_string();
+            return;
+        }
+        tab.toggle_zoom();
     }
 
     /// Move focus to the next window in the current tab.
@@ -202,40 +256,35 @@ impl Engine {
         self.active_tab_mut().cycle_prev_window();
     }
 
-    /// Move focus to a window in the given direction.
-    /// Sets `window_nav_overflow` to `Some(false)` when trying to go left past
-    /// the first window, or `Some(true)` when trying to go right past the last.
-    pub fn focus_window_direction(&mut self, _direction: SplitDirection, forward: bool) {
-        let win_ids = self.active_tab().window_ids();
+    /// Move focus to the window next to the active one in `direction`
+    /// (`forward` = right/down), judged by the windows' on-screen geometry.
+    /// At the edge of the tab, focus moves to the adjacent editor group; past
+    /// the leftmost/rightmost group `window_nav_overflow` is set to
+    /// `Some(false)` / `Some(true)` so the backend can focus its side panels.
+    pub fn focus_window_direction(&mut self, direction: SplitDirection, forward: bool) {
+        let unit = WindowRect::new(0.0, 0.0, 1.0, 1.0);
+        let rects = self.active_tab().layout.calculate_rects(unit);
         let current = self.active_tab().active_window;
-        let at_boundary = if forward {
-            win_ids.last() == Some(&current)
-        } else {
-            win_ids.first() == Some(&current)
-        };
+        if let Some(next) =
+            crate::core::window::neighbor_in_direction(&rects, current, direction, forward)
+        {
+            self.active_tab_mut().active_window = next;
+            return;
+        }
 
-        if at_boundary {
-            // Try to move to adjacent editor group
-            let group_ids = self.group_layout.group_ids();
-            let cur_idx = group_ids.iter().position(|&id| id == self.active_group);
-            let adjacent = cur_idx.and_then(|idx| {
-                if forward {
-                    group_ids.get(idx + 1).copied()
-                } else {
-                    idx.checked_sub(1).map(|i| group_ids[i])
-                }
-            });
-            if let Some(next_group) = adjacent {
-                self.prev_active_group = Some(self.active_group);
-                self.active_group = next_group;
-            } else {
-                // No adjacent group → signal overflow to TUI/GTK
-                self.window_nav_overflow = Some(forward);
-            }
-        } else if forward {
-            self.focus_next_window();
-        } else {
-            self.focus_prev_window();
+        let group_rects = self.group_layout.calculate_group_rects(unit, 0.0);
+        let adjacent = crate::core::window::neighbor_in_direction(
+            &group_rects,
+            self.active_group,
+            direction,
+            forward,
+        );
+        if let Some(next_group) = adjacent {
+            self.prev_active_group = Some(self.active_group);
+            self.active_group = next_group;
+        } else if direction == SplitDirection::Vertical {
+            // No adjacent group → signal overflow to TUI/GTK
+            self.window_nav_overflow = Some(forward);
         }
     }
 
@@ -299,7 +348,10 @@ impl Engine {
     /// Create a new tab with an optional file.
     pub fn new_tab(&mut self, file_path: Option<&Path>) {
         let buffer_id = if let Some(path) = file_path {
-            match self.buffer_manager.open_file(path) {
+            match self
+                .buffer_manager
+                .open_file(path, &self.cwd, &self.settings)
+            {
                 Ok(id) => {
                     self.buffer_manager
                         .apply_language_map(id, &self.settings.language_map);
@@ -328,6 +380,7 @@ impl Engine {
         if file_path.is_some() {
             self.message = String::new();
             self.lsp_did_open(buffer_id);
+            self.undo_file_restore_for_buffer(buffer_id);
             self.swap_check_on_open(buffer_id);
         }
     }
@@ -358,6 +411,7 @@ impl Engine {
             self.windows.remove(window_id);
             self.scroll_bind_pairs
                 .retain(|&(a, b)| a != *window_id && b != *window_id);
+            self.diff_unified_forget_window(*window_id);
             if let Some((a, b)) = self.diff_window_pair {
                 if a == *window_id || b == *window_id {
                     self.clear_diff_labels(a, b);
@@ -413,6 +467,7 @@ impl Engine {
                 self.md_preview_links.remove(&buf_id);
             }
         }
+        self.close_hidden_terminals();
 
         true
     }
@@ -2119,7 +2174,10 @@ impl Engine {
         // Clear per-buffer virtual text annotations when switching files.
         self.line_annotations.clear();
         self.blame_annotations_active = false;
-        let buffer_id = match self.buffer_manager.open_file(path) {
+        let buffer_id = match self
+            .buffer_manager
+            .open_file(path, &self.cwd, &self.settings)
+        {
             Ok(id) => id,
             Err(e) => {
                 self.message = format!("Error: {}", e);
@@ -2133,7 +2191,7 @@ impl Engine {
         if self.preview_buffer_id == Some(buffer_id) {
             self.promote_preview(buffer_id);
             self.refresh_git_diff(buffer_id);
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buffer_id, path);
             self.lsp_did_open(buffer_id);
             return;
         }
@@ -2156,7 +2214,7 @@ impl Engine {
             self.tab_nav_push();
             self.ensure_active_tab_visible();
             self.refresh_git_diff(buffer_id);
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buffer_id, path);
             self.lsp_did_open(buffer_id);
             return;
         }
@@ -2183,9 +2241,10 @@ impl Engine {
         self.refresh_git_diff(buffer_id);
         // Don't overwrite a pending dialog message.
         if self.dialog.is_none() {
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buffer_id, path);
         }
         self.lsp_did_open(buffer_id);
+        self.undo_file_restore_for_buffer(buffer_id);
 
         // Swap file check: detect stale swaps and offer recovery.
         self.swap_check_on_open(buffer_id);
@@ -2202,7 +2261,10 @@ impl Engine {
     /// single-click. Double-clicking (or editing/saving) promotes it to
     /// permanent.
     pub fn open_file_preview(&mut self, path: &Path) {
-        let buffer_id = match self.buffer_manager.open_file(path) {
+        let buffer_id = match self
+            .buffer_manager
+            .open_file(path, &self.cwd, &self.settings)
+        {
             Ok(id) => id,
             Err(e) => {
                 self.message = format!("Error: {}", e);
@@ -2228,7 +2290,7 @@ impl Engine {
             self.active_group_mut().active_tab = tab_idx;
             self.ensure_active_tab_visible();
             self.refresh_git_diff(buffer_id);
-            self.message = format!("\"{}\"", path.display());
+            self.message = self.opened_message(buffer_id, path);
             self.lsp_did_open(buffer_id);
             return;
         }
@@ -2286,8 +2348,9 @@ impl Engine {
 
         self.ensure_active_tab_visible();
         self.refresh_git_diff(buffer_id);
-        self.message = format!("\"{}\"", path.display());
+        self.message = self.opened_message(buffer_id, path);
         self.lsp_did_open(buffer_id);
+        self.undo_file_restore_for_buffer(buffer_id);
     }
 
     // =======================================================================
@@ -2423,7 +2486,8 @@ impl Engine {
             .collect()
     }
 
-    /// Snapshot the current open-file list and active file into session state, ready for saving.
+    /// Snapshot the current open-file list, active file and registers into
+    /// session state, ready for saving.
     /// Only populates `session.active_file` (for file_positions); open_files are saved
     /// exclusively in per-workspace sessions to prevent cross-workspace bleed.
     pub fn collect_session_open_files(&mut self) {
@@ -2435,6 +2499,8 @@ impl Engine {
             .buffer_manager
             .get(self.active_buffer_id())
             .and_then(|s| s.file_path.clone());
+        self.session.recent_files = self.buffer_manager.recent_files.clone();
+        self.collect_session_registers();
     }
 
     /// Restore open files from session state (called at startup when no CLI file is given).
@@ -2444,6 +2510,12 @@ impl Engine {
         // Prefer per-workspace session if one exists for cwd
         let ws_session = SessionState::load_for_workspace(&self.cwd.clone());
 
+        // Return to the directory the last `:cd` left the workspace in.
+        // A directory that no longer exists is skipped.
+        if let Some(dir) = ws_session.cwd.clone().filter(|d| *d != self.cwd) {
+            let _ = self.change_directory(&dir);
+        }
+
         // Merge workspace file positions into current session.
         if !ws_session.open_files.is_empty() || ws_session.group_layout.is_some() {
             for (k, v) in ws_session.file_positions.clone() {
@@ -2629,6 +2701,12 @@ impl Engine {
             }
         }
 
+        self.finish_session_restore();
+    }
+
+    /// Bookkeeping after a session replaced the layout: open the active
+    /// buffer in LSP, reseed tab history and check for stale swap files.
+    fn finish_session_restore(&mut self) {
         // Notify LSP only for the active buffer — other buffers will get
         // lsp_did_open when the user actually switches to their tab.
         let active_bid = self.active_buffer_id();
@@ -2652,6 +2730,357 @@ impl Engine {
         self.swap_check_all_buffers();
     }
 
+    // ─── Named sessions (:mksession / :source) ──────────────────────────────
+
+    /// `:mksession[!] {name}` — write the layout to a named session file.
+    /// An existing file is only replaced with `!`.
+    pub(crate) fn make_session(&mut self, name: &str, force: bool) {
+        let path = NamedSession::path_for(name);
+        if path.exists() && !force {
+            self.set_error_message(format!(
+                "E189: \"{}\" exists (add ! to override)",
+                path.display()
+            ));
+            return;
+        }
+        self.message = match self.named_session().save(&path) {
+            Ok(()) => format!("Session saved: {}", path.display()),
+            Err(e) => format!("Cannot write session {}: {}", path.display(), e),
+        };
+    }
+
+    /// `:source {name}` / `--session {name}` — replace the layout with a
+    /// named session.  Files that no longer exist are skipped with a warning.
+    pub fn source_session(&mut self, name: &str) {
+        if self.has_any_unsaved() {
+            self.set_error_message("E37: No write since last change".to_string());
+            return;
+        }
+        let path = NamedSession::path_for(name);
+        let session = match NamedSession::load(&path) {
+            Ok(session) => session,
+            Err(e) => {
+                self.set_error_message(e);
+                return;
+            }
+        };
+        let missing = self.restore_named_session(&session);
+        self.message = if missing.is_empty() {
+            format!("Session loaded: {}", path.display())
+        } else {
+            let missing: Vec<String> = missing.iter().map(|p| p.display().to_string()).collect();
+            format!(
+                "Session loaded; skipped missing file(s): {}",
+                missing.join(", ")
+            )
+        };
+    }
+
+    /// Snapshot the editor layout: groups, tabs, window splits, cursors,
+    /// folds and the cwd.
+    pub fn named_session(&self) -> NamedSession {
+        NamedSession {
+            cwd: Some(self.cwd.clone()),
+            groups: self.session_group_split(&self.group_layout),
+            active_group: self
+                .group_layout
+                .group_ids()
+                .iter()
+                .position(|&id| id == self.active_group)
+                .unwrap_or(0),
+        }
+    }
+
+    fn session_group_split(&self, layout: &GroupLayout) -> SessionSplit<SessionGroup> {
+        match layout {
+            GroupLayout::Leaf(gid) => {
+                let mut tabs = Vec::new();
+                let mut active_tab = 0;
+                if let Some(group) = self.editor_groups.get(gid) {
+                    for (i, tab) in group.tabs.iter().enumerate() {
+                        // A tab with only empty, unnamed buffers has nothing to restore.
+                        if tab
+                            .window_ids()
+                            .iter()
+                            .all(|&wid| self.window_is_blank(wid))
+                        {
+                            continue;
+                        }
+                        if i <= group.active_tab {
+                            active_tab = tabs.len();
+                        }
+                        tabs.push(self.session_tab(tab));
+                    }
+                }
+                SessionSplit::Leaf(SessionGroup { tabs, active_tab })
+            }
+            GroupLayout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => SessionSplit::Split {
+                direction: split_direction_code(*direction),
+                ratio: *ratio,
+                first: Box::new(self.session_group_split(first)),
+                second: Box::new(self.session_group_split(second)),
+            },
+        }
+    }
+
+    /// True if window `wid` shows an empty, unnamed, unmodified buffer.
+    fn window_is_blank(&self, wid: WindowId) -> bool {
+        self.windows
+            .get(&wid)
+            .and_then(|w| self.buffer_manager.get(w.buffer_id))
+            .is_none_or(|state| {
+                state.file_path.is_none()
+                    && state.scratch_name.is_none()
+                    && !state.dirty
+                    && state.buffer.len_chars() == 0
+            })
+    }
+
+    fn session_tab(&self, tab: &Tab) -> SessionTab {
+        // A zoomed tab is saved with all of its windows.
+        let mut tab = tab.clone();
+        tab.unzoom();
+        SessionTab {
+            windows: self.session_window_split(&tab.layout),
+            active_window: tab
+                .layout
+                .window_ids()
+                .iter()
+                .position(|&id| id == tab.active_window)
+                .unwrap_or(0),
+        }
+    }
+
+    fn session_window_split(&self, layout: &WindowLayout) -> SessionSplit<SessionWindow> {
+        match layout {
+            WindowLayout::Leaf(wid) => SessionSplit::Leaf(self.session_window(*wid)),
+            WindowLayout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => SessionSplit::Split {
+                direction: split_direction_code(*direction),
+                ratio: *ratio,
+                first: Box::new(self.session_window_split(first)),
+                second: Box::new(self.session_window_split(second)),
+            },
+        }
+    }
+
+    fn session_window(&self, wid: WindowId) -> SessionWindow {
+        let Some(window) = self.windows.get(&wid) else {
+            return SessionWindow::default();
+        };
+        let view = &window.view;
+        SessionWindow {
+            file: self
+                .buffer_manager
+                .get(window.buffer_id)
+                .and_then(|s| s.file_path.as_ref())
+                .map(|p| p.canonicalize().unwrap_or_else(|_| p.clone())),
+            line: view.cursor.line,
+            col: view.cursor.col,
+            scroll_top: view.scroll_top,
+            folds: view.folds.iter().map(|f| (f.start, f.end)).collect(),
+            local_cwd: window.local_cwd.clone(),
+        }
+    }
+
+    /// Replace all editor groups with the session's.  Windows whose file no
+    /// longer exists are left out; those files are returned.  The current
+    /// layout and cwd stay when nothing in the session can be shown.
+    pub(crate) fn restore_named_session(&mut self, session: &NamedSession) -> Vec<PathBuf> {
+        let old_groups: Vec<GroupId> = self.editor_groups.keys().copied().collect();
+        let mut missing = Vec::new();
+        let mut groups = Vec::new();
+        let Some(layout) = self.restore_session_groups(&session.groups, &mut groups, &mut missing)
+        else {
+            return missing;
+        };
+
+        // Close the previous groups, their windows and the buffers only they showed.
+        let mut old_buffers = Vec::new();
+        for gid in old_groups {
+            let tabs = self.editor_groups.remove(&gid).map(|g| g.tabs);
+            for tab in tabs.unwrap_or_default() {
+                for wid in tab.window_ids() {
+                    if let Some(window) = self.windows.remove(&wid) {
+                        old_buffers.push(window.buffer_id);
+                    }
+                }
+            }
+        }
+        self.group_layout = layout;
+        self.active_group = groups
+            .get(session.active_group)
+            .copied()
+            .flatten()
+            .or_else(|| self.group_layout.group_ids().first().copied())
+            .unwrap_or(GroupId(0));
+        self.prev_active_group = None;
+        for id in old_buffers {
+            if !self.windows.values().any(|w| w.buffer_id == id) {
+                let _ = self.delete_buffer(id, true);
+            }
+        }
+        // Files are saved with absolute paths, so the cwd can follow the
+        // layout; keep the restored window's own `:lcd` directory.
+        if let Some(dir) = session.cwd.clone().filter(|d| *d != self.cwd) {
+            let local_cwd = self.active_window().local_cwd.clone();
+            let _ = self.change_directory(&dir);
+            self.active_window_mut().local_cwd = local_cwd;
+        }
+        self.finish_session_restore();
+        missing
+    }
+
+    /// Rebuild a group split tree.  `restored` receives each leaf's new
+    /// group (`None` when all its files were missing), in leaf order.
+    fn restore_session_groups(
+        &mut self,
+        tree: &SessionSplit<SessionGroup>,
+        restored: &mut Vec<Option<GroupId>>,
+        missing: &mut Vec<PathBuf>,
+    ) -> Option<GroupLayout> {
+        match tree {
+            SessionSplit::Leaf(group) => {
+                let gid = self.restore_session_group(group, missing);
+                restored.push(gid);
+                gid.map(GroupLayout::Leaf)
+            }
+            SessionSplit::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let first = self.restore_session_groups(first, restored, missing);
+                let second = self.restore_session_groups(second, restored, missing);
+                match (first, second) {
+                    (Some(first), Some(second)) => Some(GroupLayout::Split {
+                        direction: split_direction_from_code(*direction),
+                        ratio: *ratio,
+                        first: Box::new(first),
+                        second: Box::new(second),
+                    }),
+                    (first, second) => first.or(second),
+                }
+            }
+        }
+    }
+
+    fn restore_session_group(
+        &mut self,
+        group: &SessionGroup,
+        missing: &mut Vec<PathBuf>,
+    ) -> Option<GroupId> {
+        let mut tabs = Vec::new();
+        let mut active_tab = 0;
+        for (i, saved) in group.tabs.iter().enumerate() {
+            let mut windows = Vec::new();
+            let Some(layout) = self.restore_session_windows(&saved.windows, &mut windows, missing)
+            else {
+                continue;
+            };
+            let active = windows
+                .get(saved.active_window)
+                .copied()
+                .flatten()
+                .unwrap_or_else(|| layout.window_ids()[0]);
+            let mut tab = Tab::new(self.new_tab_id(), active);
+            tab.layout = layout;
+            if i <= group.active_tab {
+                active_tab = tabs.len();
+            }
+            tabs.push(tab);
+        }
+        let mut tabs = tabs.into_iter();
+        let mut editor_group = EditorGroup::new(tabs.next()?);
+        editor_group.tabs.extend(tabs);
+        editor_group.active_tab = active_tab;
+        let gid = self.new_group_id();
+        self.editor_groups.insert(gid, editor_group);
+        Some(gid)
+    }
+
+    /// Rebuild a tab's window split tree; see [`Self::restore_session_groups`].
+    fn restore_session_windows(
+        &mut self,
+        tree: &SessionSplit<SessionWindow>,
+        restored: &mut Vec<Option<WindowId>>,
+        missing: &mut Vec<PathBuf>,
+    ) -> Option<WindowLayout> {
+        match tree {
+            SessionSplit::Leaf(window) => {
+                let wid = self.restore_session_window(window, missing);
+                restored.push(wid);
+                wid.map(WindowLayout::Leaf)
+            }
+            SessionSplit::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let first = self.restore_session_windows(first, restored, missing);
+                let second = self.restore_session_windows(second, restored, missing);
+                match (first, second) {
+                    (Some(first), Some(second)) => Some(WindowLayout::Split {
+                        direction: split_direction_from_code(*direction),
+                        ratio: *ratio,
+                        first: Box::new(first),
+                        second: Box::new(second),
+                    }),
+                    (first, second) => first.or(second),
+                }
+            }
+        }
+    }
+
+    fn restore_session_window(
+        &mut self,
+        saved: &SessionWindow,
+        missing: &mut Vec<PathBuf>,
+    ) -> Option<WindowId> {
+        let buf_id = match &saved.file {
+            Some(path) if !path.exists() => {
+                if !missing.contains(path) {
+                    missing.push(path.clone());
+                }
+                return None;
+            }
+            Some(path) => self
+                .buffer_manager
+                .open_file(path, &self.cwd, &self.settings)
+                .ok()?,
+            None => self.buffer_manager.create(),
+        };
+        let buffer = &self.buffer_manager.get(buf_id)?.buffer;
+        let max_line = buffer.len_lines().saturating_sub(1);
+        let line = saved.line.min(max_line);
+        let col = saved.col.min(buffer.line_len_chars(line).saturating_sub(1));
+
+        let wid = self.new_window_id();
+        let mut window = Window::new(wid, buf_id);
+        window.view.cursor = Cursor { line, col };
+        window.view.scroll_top = saved.scroll_top.min(max_line);
+        window.view.folds = saved
+            .folds
+            .iter()
+            .filter(|&&(start, end)| start < end && end <= max_line)
+            .map(|&(start, end)| FoldRegion { start, end })
+            .collect();
+        window.local_cwd = saved.local_cwd.clone().filter(|dir| dir.is_dir());
+        self.windows.insert(wid, window);
+        Some(wid)
+    }
+
     /// Delete a buffer. Returns error if buffer is shown in any window or is dirty.
     pub fn delete_buffer(&mut self, id: BufferId, force: bool) -> Result<(), String> {
         // Check if buffer is shown in any window
@@ -2721,7 +3150,8 @@ impl Engine {
 
     /// Resize the window's parent split by delta steps.
     /// `direction`: which split direction to look for (Horizontal for +/-, Vertical for </>).
-    /// `increase`: true = make active group bigger, false = smaller.
+    /// `increase`: true = make the active window (or, with no split of that
+    /// direction in the tab, its group) bigger, false = smaller.
     pub(crate) fn resize_window_split(
         &mut self,
         direction: SplitDirection,
@@ -2729,23 +3159,31 @@ impl Engine {
         count: usize,
     ) {
         let delta_per_step = 0.05;
+        // Splits inside the tab first, then the editor group splits.
+        let step = if increase {
+            delta_per_step * count as f64
+        } else {
+            -(delta_per_step * count as f64)
+        };
+        let win = self.active_window_id();
+        if self.active_tab_mut().layout.resize(win, direction, step) {
+            return;
+        }
         if let Some((split_idx, split_dir, is_first)) =
             self.group_layout.parent_split_of(self.active_group)
         {
             if split_dir == direction {
                 // Active group is in first child → increasing ratio makes it bigger
-                let delta = if (is_first && increase) || (!is_first && !increase) {
-                    delta_per_step * count as f64
-                } else {
-                    -(delta_per_step * count as f64)
-                };
+                let delta = if is_first { step } else { -step };
                 self.group_layout.adjust_ratio_at_index(split_idx, delta);
             }
         }
     }
 
-    /// Equalize all split ratios to 0.5.
+    /// Give every window in the tab an equal share of space and reset the
+    /// editor group split ratios to 0.5.
     pub(crate) fn equalize_splits(&mut self) {
+        self.active_tab_mut().layout.equalize();
         self.group_layout.set_all_ratios(0.5);
     }
 
@@ -2769,7 +3207,8 @@ impl Engine {
             'j' => self.focus_window_direction(SplitDirection::Horizontal, true),
             'k' => self.focus_window_direction(SplitDirection::Horizontal, false),
             'l' => self.focus_window_direction(SplitDirection::Vertical, true),
-            'w' | 'W' => self.focus_next_window(),
+            'w' => self.focus_next_window(),
+            'W' => self.focus_prev_window(),
             'p' => {
                 if let Some(prev) = self.prev_active_group {
                     if self.editor_groups.contains_key(&prev) {
@@ -2802,9 +3241,9 @@ impl Engine {
             'K' => self.move_window_to_edge(SplitDirection::Horizontal, false),
             'L' => self.move_window_to_edge(SplitDirection::Vertical, true),
             'T' => self.move_window_to_new_group(),
-            'x' => self.exchange_windows(),
-            'r' => self.rotate_windows(true),
-            'R' => self.rotate_windows(false),
+            'x' => self.exchange_windows(count),
+            'r' => self.rotate_windows(true, count),
+            'R' => self.rotate_windows(false, count),
             // Split / Close
             's' | 'S' => self.split_window(SplitDirection::Horizontal, None),
             'v' | 'V' => self.split_window(SplitDirection::Vertical, None),
@@ -2814,7 +3253,8 @@ impl Engine {
             'q' => {
                 self.close_window();
             }
-            'o' | 'O' => self.close_other_windows(),
+            'o' | 'O' => self.close_other_windows(false),
+            'z' => self.toggle_window_zoom(),
             'n' => {
                 let _ = self.execute_command("new");
             }
@@ -2855,82 +3295,18 @@ impl Engine {
         EngineAction::None
     }
 
-    /// Ctrl-W H/J/K/L: move current window to far edge.
-    /// Creates a new editor group at the edge of the entire layout.
+    /// Ctrl-W H/J/K/L: move the current window to the far edge of the tab's
+    /// layout, spanning its full height (H/L) or width (J/K).  The window
+    /// keeps its buffer, cursor and scroll position.
     pub(crate) fn move_window_to_edge(&mut self, direction: SplitDirection, forward: bool) {
-        // Only meaningful with multiple groups
-        let groups = self.group_layout.group_ids();
-        if groups.len() <= 1 {
-            // With a single group, split the group layout at the root
-            let buf_id = self.active_buffer_id();
-            let view_clone = self.view().clone();
-            // Close current window if possible
-            let could_close = self.close_window();
-            if !could_close {
-                return; // Last window, can't move
-            }
-            // Create new group at the edge
-            let new_win_id = self.new_window_id();
-            let mut new_win = Window::new(new_win_id, buf_id);
-            new_win.view = view_clone;
-            self.windows.insert(new_win_id, new_win);
-            let tab = Tab::new(self.new_tab_id(), new_win_id);
-            let new_gid = self.new_group_id();
-            self.editor_groups.insert(new_gid, EditorGroup::new(tab));
-            // Wrap the existing layout in a split with the new group at the desired edge
-            let old_layout =
-                std::mem::replace(&mut self.group_layout, GroupLayout::leaf(GroupId(0)));
-            self.group_layout = if forward {
-                GroupLayout::Split {
-                    direction,
-                    ratio: 0.5,
-                    first: Box::new(old_layout),
-                    second: Box::new(GroupLayout::leaf(new_gid)),
-                }
-            } else {
-                GroupLayout::Split {
-                    direction,
-                    ratio: 0.5,
-                    first: Box::new(GroupLayout::leaf(new_gid)),
-                    second: Box::new(old_layout),
-                }
-            };
-            self.prev_active_group = Some(self.active_group);
-            self.active_group = new_gid;
-            return;
-        }
-        // Multiple groups: remove window, create new group at edge
-        let buf_id = self.active_buffer_id();
-        let view_clone = self.view().clone();
-        let could_close = self.close_window();
-        if !could_close {
-            return;
+        let win = self.active_window_id();
+        if !self
+            .active_tab_mut()
+            .layout
+            .move_to_edge(win, direction, forward)
+        {
+            self.message = "Already the only window".to_string();
         }
-        let new_win_id = self.new_window_id();
-        let mut new_win = Window::new(new_win_id, buf_id);
-        new_win.view = view_clone;
-        self.windows.insert(new_win_id, new_win);
-        let tab = Tab::new(self.new_tab_id(), new_win_id);
-        let new_gid = self.new_group_id();
-        self.editor_groups.insert(new_gid, EditorGroup::new(tab));
-        let old_layout = std::mem::replace(&mut self.group_layout, GroupLayout::leaf(GroupId(0)));
-        self.group_layout = if forward {
-            GroupLayout::Split {
-                direction,
-                ratio: 0.5,
-                first: Box::new(old_layout),
-                second: Box::new(GroupLayout::leaf(new_gid)),
-            }
-        } else {
-            GroupLayout::Split {
-                direction,
-                ratio: 0.5,
-                first: Box::new(GroupLayout::leaf(new_gid)),
-                second: Box::new(old_layout),
-            }
-        };
-        self.prev_active_group = Some(self.active_group);
-        self.active_group = new_gid;
     }
 
     /// Ctrl-W T: move current window to a new editor group.
@@ -2961,8 +3337,11 @@ impl Engine {
         self.active_group = new_gid;
     }
 
-    /// Ctrl-W x: exchange current window with next window in the same tab.
-    pub(crate) fn exchange_windows(&mut self) {
+    /// Ctrl-W x: exchange the current window's buffer and view (cursor,
+    /// scroll) with the next window's — the previous one's for the last
+    /// window, or the `count`th window's when a count above 1 is given.
+    /// Focus stays in place, as in Vim.
+    pub(crate) fn exchange_windows(&mut self, count: usize) {
         let tab = self.active_tab();
         let ids = tab.layout.window_ids();
         if ids.len() < 2 {
@@ -2970,31 +3349,45 @@ impl Engine {
         }
         let current_id = tab.active_window;
         let current_idx = ids.iter().position(|&id| id == current_id).unwrap_or(0);
-        let next_idx = (current_idx + 1) % ids.len();
-        let next_id = ids[next_idx];
+        let other_idx = if count > 1 {
+            (count - 1).min(ids.len() - 1)
+        } else if current_idx + 1 < ids.len() {
+            current_idx + 1
+        } else {
+            current_idx - 1
+        };
+        if other_idx == current_idx {
+            return;
+        }
+        let other_id = ids[other_idx];
         // Swap buffer_id and view between the two windows
         let current_buf = self.windows[&current_id].buffer_id;
         let current_view = self.windows[&current_id].view.clone();
-        let next_buf = self.windows[&next_id].buffer_id;
-        let next_view = self.windows[&next_id].view.clone();
+        let other_buf = self.windows[&other_id].buffer_id;
+        let other_view = self.windows[&other_id].view.clone();
         if let Some(w) = self.windows.get_mut(&current_id) {
-            w.buffer_id = next_buf;
-            w.view = next_view;
+            w.buffer_id = other_buf;
+            w.view = other_view;
         }
-        if let Some(w) = self.windows.get_mut(&next_id) {
+        if let Some(w) = self.windows.get_mut(&other_id) {
             w.buffer_id = current_buf;
             w.view = current_view;
         }
     }
 
-    /// Ctrl-W r/R: rotate windows in the current tab.
-    /// `forward=true` rotates downward/rightward, `forward=false` rotates upward/leftward.
-    pub(crate) fn rotate_windows(&mut self, forward: bool) {
+    /// Ctrl-W r/R: rotate windows in the current tab `count` times.
+    /// `forward=true` rotates downward/rightward, `forward=false` rotates
+    /// upward/leftward.  Focus follows the current buffer to its new window.
+    pub(crate) fn rotate_windows(&mut self, forward: bool, count: usize) {
         let tab = self.active_tab();
         let ids = tab.layout.window_ids();
         if ids.len() < 2 {
             return;
         }
+        let current_idx = ids
+            .iter()
+            .position(|&id| id == tab.active_window)
+            .unwrap_or(0);
         // Collect (buffer_id, view) for each window in layout order
         let mut data: Vec<_> = ids
             .iter()
@@ -3003,23 +3396,24 @@ impl Engine {
                 (w.buffer_id, w.view.clone())
             })
             .collect();
-        // Rotate the data
-        if forward {
-            // Last element moves to front
-            let last = data.pop().unwrap();
-            data.insert(0, last);
+        let steps = count % ids.len();
+        let target_idx = if forward {
+            // Last elements move to the front
+            data.rotate_right(steps);
+            (current_idx + steps) % ids.len()
         } else {
-            // First element moves to back
-            let first = data.remove(0);
-            data.push(first);
-        }
+            // First elements move to the back
+            data.rotate_left(steps);
+            (current_idx + ids.len() - steps) % ids.len()
+        };
         // Apply rotated data back
-        for (i, &id) in ids.iter().enumerate() {
+        for (&id, (buffer_id, view)) in ids.iter().zip(data) {
             if let Some(w) = self.windows.get_mut(&id) {
-                w.buffer_id = data[i].0;
-                w.view = data[i].1.clone();
+                w.buffer_id = buffer_id;
+                w.view = view;
             }
         }
+        self.active_tab_mut().active_window = ids[target_idx];
     }
 
     /// Jump to end of C-style comment block (]*  or  ]/).
@@ -3255,6 +3649,9 @@ impl Engine {
         // Replace the corresponding line in the other buffer
         if let Some(other_win) = self.windows.get(&other) {
             let other_buf_id = other_win.buffer_id;
+            if !self.check_buffer_modifiable(other_buf_id) {
+                return;
+            }
             if let Some(other_state) = self.buffer_manager.get_mut(other_buf_id) {
                 if cursor_line < other_state.buffer.len_lines() {
                     let line_start = other_state.buffer.line_to_char(cursor_line);
@@ -3387,74 +3784,24 @@ impl Engine {
         }
         // Parse the range. Support: N,M  .,.+N  N  .  %
         let (start_line, end_line) = self.parse_simple_range(range_str)?;
-        // Extract the text from the range
-        let total_lines = self.buffer().len_lines();
-        let start = start_line.min(total_lines.saturating_sub(1));
-        let end = end_line.min(total_lines.saturating_sub(1));
+        let last_line = self.buffer().len_lines().saturating_sub(1);
+        let start = start_line.min(end_line).min(last_line);
+        let end = start_line.max(end_line).min(last_line);
         let mut lines_text = String::new();
         for i in start..=end {
-            let line: String = self.buffer().content.line(i).chars().collect();
-            lines_text.push_str(&line);
-        }
-        // Pipe through the command
-        #[cfg(not(test))]
-        let result = {
-            use std::io::Write;
-            let mut child = match std::process::Command::new("sh")
-                .arg("-c")
-                .arg(filter_cmd)
-                .stdin(std::process::Stdio::piped())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
-            {
-                Ok(c) => c,
-                Err(e) => {
-                    self.message = format!("Filter error: {}", e);
-                    return Some(EngineAction::None);
-                }
-            };
-            if let Some(mut stdin) = child.stdin.take() {
-                let _ = stdin.write_all(lines_text.as_bytes());
-            }
-            match child.wait_with_output() {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    if !stderr.is_empty() && stdout.is_empty() {
-                        self.message = format!("Filter error: {}", stderr.trim());
-                        return Some(EngineAction::None);
-                    }
-                    stdout
-                }
-                Err(e) => {
-                    self.message = format!("Filter error: {}", e);
-                    return Some(EngineAction::None);
-                }
-            }
-        };
-        #[cfg(test)]
-        let result = lines_text.clone(); // No-op in tests
-
-        // Replace the range with the result
-        let range_start = self.buffer().line_to_char(start);
-        let range_end = if end + 1 < total_lines {
-            self.buffer().line_to_char(end + 1)
-        } else {
-            self.buffer().len_chars()
+            lines_text.extend(self.buffer().content.line(i).chars());
+        }
+        let target = ShellTarget::Filter {
+            buffer: self.active_buffer_id(),
+            start,
+            end,
+            tick: self.buffer().changedtick,
         };
-        self.start_undo_group();
-        self.delete_with_undo(range_start, range_end);
-        self.insert_with_undo(range_start, &result);
-        self.finish_undo_group();
-        self.view_mut().cursor.line = start;
-        self.view_mut().cursor.col = 0;
-        let line_count = result.lines().count();
-        self.message = format!("{} lines filtered", line_count);
+        self.spawn_shell_command(filter_cmd, Some(lines_text), target);
         Some(EngineAction::None)
     }
 
-    /// Parse a simple line range like "1,5", ".", ".,.+3", "%".
+    /// Parse a simple line range like "1,5", ".", ".,.+3", "%", "'<,'>".
     /// Returns 0-indexed (start_line, end_line).
     pub(crate) fn parse_simple_range(&self, range: &str) -> Option<(usize, usize)> {
         let current_line = self.view().cursor.line;
@@ -3463,6 +3810,17 @@ impl Engine {
         if range == "%" {
             return Some((0, last_line));
         }
+        if range == "'<,'>" {
+            // The live selection, else the '< '> marks of the last one.
+            let marks = self
+                .visual_mark_start
+                .zip(self.visual_mark_end)
+                .map(|((sl, _), (el, _))| (sl, el.min(last_line)));
+            return self
+                .get_visual_selection_range()
+                .map(|(start, end)| (start.line, end.line))
+                .or(marks);
+        }
         if range == "." {
             return Some((current_line, current_line));
         }
@@ -3501,3 +3859,19 @@ impl Engine {
         Some((n - 1).min(last))
     }
 }
+
+/// Session encoding of a split direction: 0 = Vertical, 1 = Horizontal.
+fn split_direction_code(direction: SplitDirection) -> u8 {
+    match direction {
+        SplitDirection::Vertical => 0,
+        SplitDirection::Horizontal => 1,
+    }
+}
+
+fn split_direction_from_code(code: u8) -> SplitDirection {
+    if code == 1 {
+        SplitDirection::Horizontal
+    } else {
+        SplitDirection::Vertical
+    }
+}
@@ -0,0 +1,274 @@
+//! Build output → quickfix entries for `:make`.
+//!
+//! rustc/cargo diagnostics (an `error[E0308]: …` header followed by a
+//! `--> file:line:col` line) are recognised directly.  Every other line is
+//! tried against the Vim-style `errorformat` patterns, which understand
+//! `%f` (file), `%l` (line), `%c` (column), `%m` (message), `%t` (type
+//! letter) and `%%`; patterns are separated by commas (`\,` for a literal
+//! comma).
+
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// Severity of a build message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildMessageKind {
+    Error,
+    Warning,
+}
+
+impl BuildMessageKind {
+    /// One-letter tag shown in the quickfix list, like LSP diagnostics.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BuildMessageKind::Error => "E",
+            BuildMessageKind::Warning => "W",
+        }
+    }
+}
+
+/// One jumpable compiler message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildMessage {
+    pub kind: BuildMessageKind,
+    /// Absolute path (relative paths are resolved against the build's cwd).
+    pub file: PathBuf,
+    /// 0-indexed line.
+    pub line: usize,
+    /// 0-indexed column.
+    pub col: usize,
+    pub message: String,
+}
+
+/// Parse the complete output of a build run in `cwd`.
+pub fn parse_build_output(output: &str, errorformat: &str, cwd: &Path) -> Vec<BuildMessage> {
+    let patterns = compile_errorformat(errorformat);
+    let mut messages = Vec::new();
+    // The rustc header waiting for its `-->` location line.
+    let mut pending: Option<(BuildMessageKind, String)> = None;
+    for line in output.lines() {
+        let line = line.trim_end();
+        if let Some(header) = parse_rustc_header(line) {
+            pending = Some(header);
+            continue;
+        }
+        if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            if let Some((kind, message)) = pending.take() {
+                if let Some((file, l, c)) = split_location(location) {
+                    messages.push(BuildMessage {
+                        kind,
+                        file: resolve(cwd, file),
+                        line: l.saturating_sub(1),
+                        col: c.saturating_sub(1),
+                        message,
+                    });
+                }
+            }
+            continue;
+        }
+        if let Some(msg) = patterns
+            .iter()
+            .find_map(|re| match_errorformat(re, line, cwd))
+        {
+            messages.push(msg);
+        }
+    }
+    messages
+}
+
+/// `error[E0308]: mismatched types` → `(Error, "E0308: mismatched types")`.
+/// Summary lines such as `error: could not compile` are headers too; they
+/// are dropped because no location follows them.
+fn parse_rustc_header(line: &str) -> Option<(BuildMessageKind, String)> {
+    let (kind, rest) = if let Some(rest) = line.strip_prefix("error") {
+        (BuildMessageKind::Error, rest)
+    } else if let Some(rest) = line.strip_prefix("warning") {
+        (BuildMessageKind::Warning, rest)
+    } else {
+        return None;
+    };
+    if let Some(message) = rest.strip_prefix(": ") {
+        return Some((kind, message.to_string()));
+    }
+    let (code, message) = rest.strip_prefix('[')?.split_once("]: ")?;
+    Some((kind, format!("{code}: {message}")))
+}
+
+/// `src/main.rs:4:18` → `("src/main.rs", 4, 18)`.
+fn split_location(location: &str) -> Option<(&str, usize, usize)> {
+    let (rest, col) = location.rsplit_once(':')?;
+    let (file, line) = rest.rsplit_once(':')?;
+    Some((file, line.parse().ok()?, col.parse().ok()?))
+}
+
+fn resolve(cwd: &Path, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    }
+}
+
+/// Turn each comma-separated `errorformat` pattern into an anchored regex.
+/// Patterns that fail to compile are skipped.
+fn compile_errorformat(errorformat: &str) -> Vec<Regex> {
+    let mut patterns = Vec::new();
+    let mut current = String::new();
+    let mut chars = errorformat.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => {
+                chars.next();
+                current.push(',');
+            }
+            ',' => patterns.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    patterns.push(current);
+    patterns
+        .iter()
+        .filter(|p| !p.is_empty())
+        .filter_map(|p| Regex::new(&errorformat_to_regex(p)).ok())
+        .collect()
+}
+
+fn errorformat_to_regex(pattern: &str) -> String {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            re.push_str(&regex::escape(&c.to_string()));
+            continue;
+        }
+        match chars.next() {
+            Some('f') => re.push_str(r"(?P<f>.+?)"),
+            Some('l') => re.push_str(r"(?P<l>\d+)"),
+            Some('c') => re.push_str(r"(?P<c>\d+)"),
+            Some('m') => re.push_str(r"(?P<m>.*)"),
+            Some('t') => re.push_str(r"(?P<t>\w)"),
+            Some(other) => re.push_str(&regex::escape(&other.to_string())),
+            None => re.push('%'),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Match one line against a compiled pattern.  The file must exist, so
+/// progress lines that happen to contain colons aren't picked up.
+fn match_errorformat(re: &Regex, line: &str, cwd: &Path) -> Option<BuildMessage> {
+    let caps = re.captures(line)?;
+    let file = resolve(cwd, caps.name("f")?.as_str().trim());
+    if !file.is_file() {
+        return None;
+    }
+    let number = |name: &str| -> usize {
+        caps.name(name)
+            .and_then(|m| m.as_str().parse::<usize>().ok())
+            .unwrap_or(1)
+    };
+    let message = caps.name("m").map_or("", |m| m.as_str()).trim().to_string();
+    let kind = match caps.name("t").map(|m| m.as_str()) {
+        Some("w" | "W") => BuildMessageKind::Warning,
+        Some(_) => BuildMessageKind::Error,
+        // gcc/clang style: `file:1:2: warning: …`
+        None if message.starts_with("warning") => BuildMessageKind::Warning,
+        None => BuildMessageKind::Error,
+    };
+    Some(BuildMessage {
+        kind,
+        file,
+        line: number("l").saturating_sub(1),
+        col: number("c").saturating_sub(1),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const DEFAULT_EFM: &str = "%f:%l:%c: %m,%f:%l: %m";
+
+    #[test]
+    fn parses_rustc_diagnostics() {
+        let output = "\
+   Compiling demo v0.1.0 (/p)
+error[E0308]: mismatched types
+ --> src/main.rs:4:18
+  |
+4 |     let x: u32 = \"a\";
+  |                  ^^^ expected `u32`, found `&str`
+
+warning: unused variable: `y`
+  --> src/lib.rs:10:9
+   |
+   = note: `#[warn(unused_variables)]` on by default
+
+warning: `demo` (bin \"demo\") generated 1 warning
+error: could not compile `demo` (bin \"demo\") due to 1 previous error
+";
+        let msgs = parse_build_output(output, DEFAULT_EFM, Path::new("/p"));
+        assert_eq!(
+            msgs,
+            vec![
+                BuildMessage {
+                    kind: BuildMessageKind::Error,
+                    file: PathBuf::from("/p/src/main.rs"),
+                    line: 3,
+                    col: 17,
+                    message: "E0308: mismatched types".to_string(),
+                },
+                BuildMessage {
+                    kind: BuildMessageKind::Warning,
+                    file: PathBuf::from("/p/src/lib.rs"),
+                    line: 9,
+                    col: 8,
+                    message: "unused variable: `y`".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_errorformat_fallback() {
+        let root = std::env::temp_dir().join("vimcode_test_errorformat");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("main.c"), "").unwrap();
+        let output = "\
+main.c:3:5: warning: unused variable 'x'
+main.c:7: error: expected ';'
+missing.c:1:1: error: not a real file
+make: *** [all] Error 1
+";
+        let msgs = parse_build_output(output, DEFAULT_EFM, &root);
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].kind, BuildMessageKind::Warning);
+        assert_eq!((msgs[0].line, msgs[0].col), (2, 4));
+        assert_eq!(msgs[0].message, "warning: unused variable 'x'");
+        assert_eq!(msgs[1].kind, BuildMessageKind::Error);
+        assert_eq!((msgs[1].line, msgs[1].col), (6, 0));
+        assert_eq!(msgs[1].file, root.join("main.c"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn type_letter_and_escaped_comma() {
+        let root = std::env::temp_dir().join("vimcode_test_errorformat_type");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.py"), "").unwrap();
+        let efm = r"%t %f\, line %l: %m";
+        let msgs = parse_build_output("W a.py, line 12: too long", efm, &root);
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].kind, BuildMessageKind::Warning);
+        assert_eq!(msgs[0].line, 11);
+        assert_eq!(msgs[0].message, "too long");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
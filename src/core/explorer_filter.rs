@@ -0,0 +1,259 @@
+//! Which entries the file explorer hides: dotfiles, `explorer_ignore` globs
+//! and, with `respect_gitignore`, anything a `.gitignore` excludes.
+//!
+//! Shared by every backend's tree builder.  Entries are checked one
+//! directory level at a time, so a hidden directory is never read.
+//! [`read_dir_entries`] lists one directory level in explorer order;
+//! backends read folders that pass [`is_large_dir`] on a background thread.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use super::settings::Settings;
+
+/// The settings that decide what the explorer hides.  Backends keep a copy
+/// and rebuild their tree as soon as it differs from the live settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExplorerFilterConfig {
+    pub show_hidden: bool,
+    pub ignore: Vec<String>,
+    pub respect_gitignore: bool,
+}
+
+impl ExplorerFilterConfig {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            show_hidden: settings.show_hidden_files,
+            ignore: settings.explorer_ignore.clone(),
+            respect_gitignore: settings.respect_gitignore,
+        }
+    }
+}
+
+/// `.gitignore` state of one directory.
+struct DirIgnore {
+    gitignore: Option<Gitignore>,
+    /// The directory holds `.git`; parent `.gitignore` files don't apply.
+    is_git_root: bool,
+}
+
+/// Decides per entry whether the explorer lists it.  Build one per tree
+/// (re)build; parsed `.gitignore` files are cached for its lifetime.
+pub struct ExplorerFilter {
+    show_hidden: bool,
+    /// `explorer_ignore` globs, matched against entry names.
+    patterns: Option<Gitignore>,
+    respect_gitignore: bool,
+    dirs: HashMap<PathBuf, DirIgnore>,
+}
+
+impl ExplorerFilter {
+    pub fn new(config: &ExplorerFilterConfig) -> Self {
+        let patterns = if config.ignore.is_empty() {
+            None
+        } else {
+            let mut builder = GitignoreBuilder::new("");
+            for pattern in &config.ignore {
+                // Invalid globs are skipped rather than hiding everything.
+                let _ = builder.add_line(None, pattern.trim());
+            }
+            builder.build().ok()
+        };
+        Self {
+            show_hidden: config.show_hidden,
+            patterns,
+            respect_gitignore: config.respect_gitignore,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// True when `path` should not be listed (nor, for a directory, read).
+    pub fn is_hidden(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        if !self.show_hidden && name.to_string_lossy().starts_with('.') {
+            return true;
+        }
+        if let Some(ref patterns) = self.patterns {
+            if patterns.matched(Path::new(name), is_dir).is_ignore() {
+                return true;
+            }
+        }
+        self.respect_gitignore && self.gitignored(path, is_dir)
+    }
+
+    /// Check `path` against the `.gitignore` files from its directory up to
+    /// the git root; the nearest file with a matching rule decides.
+    fn gitignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        for dir in path.ancestors().skip(1) {
+            let info = self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+                let file = dir.join(".gitignore");
+                DirIgnore {
+                    gitignore: file.is_file().then(|| Gitignore::new(&file).0),
+                    is_git_root: dir.join(".git").exists(),
+                }
+            });
+            if let Some(ref gitignore) = info.gitignore {
+                let m = gitignore.matched(path, is_dir);
+                if m.is_ignore() {
+                    return true;
+                }
+                if m.is_whitelist() {
+                    return false;
+                }
+            }
+            if info.is_git_root {
+                break;
+            }
+        }
+        false
+    }
+}
+
+/// One entry listed in the explorer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorerEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Directories with more entries than this are read on a background thread
+/// when expanded, so the UI never stalls on a huge `node_modules` or similar.
+pub const LARGE_DIR_ENTRIES: usize = 2000;
+
+/// True when `dir` has more than [`LARGE_DIR_ENTRIES`] entries.  Stops
+/// counting at the limit, so this stays cheap on huge directories.
+pub fn is_large_dir(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|rd| rd.take(LARGE_DIR_ENTRIES + 1).count() > LARGE_DIR_ENTRIES)
+        .unwrap_or(false)
+}
+
+/// List one directory level for the explorer: hidden entries dropped,
+/// directories first, then by name.  File types come from the directory
+/// listing itself, so only symlinks cost an extra `stat`.
+pub fn read_dir_entries(
+    dir: &Path,
+    filter: &mut ExplorerFilter,
+    case_insensitive: bool,
+) -> Vec<ExplorerEntry> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<ExplorerEntry> = read_dir
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_dir = match entry.file_type() {
+                Ok(t) if t.is_symlink() => path.is_dir(),
+                Ok(t) => t.is_dir(),
+                Err(_) => path.is_dir(),
+            };
+            if filter.is_hidden(&path, is_dir) {
+                return None;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            Some(ExplorerEntry { path, name, is_dir })
+        })
+        .collect();
+    entries.sort_by_cached_key(|e| {
+        let name = if case_insensitive {
+            e.name.to_lowercase()
+        } else {
+            e.name.clone()
+        };
+        (!e.is_dir, name)
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(show_hidden: bool, ignore: &[&str], respect_gitignore: bool) -> ExplorerFilterConfig {
+        ExplorerFilterConfig {
+            show_hidden,
+            ignore: ignore.iter().map(|s| s.to_string()).collect(),
+            respect_gitignore,
+        }
+    }
+
+    #[test]
+    fn dotfiles_and_globs() {
+        let mut filter = ExplorerFilter::new(&config(false, &["target/", "*.o"], false));
+        assert!(filter.is_hidden(Path::new("/p/.git"), true));
+        assert!(filter.is_hidden(Path::new("/p/target"), true));
+        // A trailing slash only matches directories.
+        assert!(!filter.is_hidden(Path::new("/p/target"), false));
+        assert!(filter.is_hidden(Path::new("/p/src/main.o"), false));
+        assert!(!filter.is_hidden(Path::new("/p/src/main.rs"), false));
+
+        let mut filter = ExplorerFilter::new(&config(true, &[], false));
+        assert!(!filter.is_hidden(Path::new("/p/.github"), true));
+    }
+
+    #[test]
+    fn respects_nested_gitignores() {
+        let root = std::env::temp_dir().join("vimcode_test_explorer_filter");
+        let _ = fs::remove_dir_all(&root);
+        let repo = root.join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("sub")).unwrap();
+        // Outside the repo: must not apply.
+        fs::write(root.join(".gitignore"), "*.rs\n").unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(repo.join("sub").join(".gitignore"), "!keep.log\n").unwrap();
+
+        let mut filter = ExplorerFilter::new(&config(false, &[], true));
+        assert!(filter.is_hidden(&repo.join("target"), true));
+        assert!(filter.is_hidden(&repo.join("debug.log"), false));
+        assert!(filter.is_hidden(&repo.join("sub").join("other.log"), false));
+        assert!(!filter.is_hidden(&repo.join("sub").join("keep.log"), false));
+        assert!(!filter.is_hidden(&repo.join("main.rs"), false));
+
+        let mut filter = ExplorerFilter::new(&config(false, &[], false));
+        assert!(!filter.is_hidden(&repo.join("target"), true));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn read_dir_entries_sorts_dirs_first() {
+        let root = std::env::temp_dir().join("vimcode_test_read_dir_entries");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("zeta")).unwrap();
+        fs::create_dir_all(root.join("Beta")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("alpha.rs"), "").unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+        fs::write(root.join(".env"), "").unwrap();
+
+        let names = |filter: &mut ExplorerFilter, ci: bool| -> Vec<(String, bool)> {
+            read_dir_entries(&root, filter, ci)
+                .into_iter()
+                .map(|e| (e.name, e.is_dir))
+                .collect()
+        };
+        let mut filter = ExplorerFilter::new(&config(false, &["target/"], false));
+        let expected = [
+            ("Beta", true),
+            ("zeta", true),
+            ("alpha.rs", false),
+            ("Cargo.toml", false),
+        ];
+        let expected: Vec<(String, bool)> =
+            expected.iter().map(|&(n, d)| (n.to_string(), d)).collect();
+        assert_eq!(names(&mut filter, true), expected);
+        // Case-sensitive: uppercase sorts before lowercase.
+        let sensitive = names(&mut filter, false);
+        assert_eq!(sensitive[0].0, "Beta");
+        assert_eq!(sensitive[2].0, "Cargo.toml");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
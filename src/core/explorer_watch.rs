@@ -0,0 +1,208 @@
+//! Live explorer refresh: watches the directories the file tree currently
+//! shows and reports which of them changed on disk (git checkout, build
+//! output, files created in a terminal).
+//!
+//! Only the root and expanded directories are watched, non-recursively, so
+//! huge collapsed folders such as `target/` cost nothing.  Events are
+//! debounced: [`ExplorerWatcher::poll`] returns the changed directories once
+//! the filesystem has been quiet for [`DEBOUNCE`].
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the filesystem must stay quiet before a refresh is reported.
+pub const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watches the explorer's visible directories; one per backend.
+pub struct ExplorerWatcher {
+    /// `None` when the platform watcher could not be created; backends then
+    /// fall back to periodic rebuilds.
+    watcher: Option<RecommendedWatcher>,
+    rx: Option<Receiver<notify::Result<Event>>>,
+    watched: HashSet<PathBuf>,
+    changed: BTreeSet<PathBuf>,
+    last_event: Option<Instant>,
+}
+
+impl Default for ExplorerWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExplorerWatcher {
+    pub fn new() -> Self {
+        let (tx, rx) = channel();
+        let (watcher, rx) = match RecommendedWatcher::new(tx, notify::Config::default()) {
+            Ok(w) => (Some(w), Some(rx)),
+            Err(_) => (None, None),
+        };
+        Self {
+            watcher,
+            rx,
+            watched: HashSet::new(),
+            changed: BTreeSet::new(),
+            last_event: None,
+        }
+    }
+
+    /// True when change events are delivered.
+    pub fn is_active(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    /// Watch exactly `dirs` (the root plus every expanded directory).
+    pub fn set_dirs<'a>(&mut self, dirs: impl IntoIterator<Item = &'a Path>) {
+        let dirs: HashSet<&Path> = dirs.into_iter().collect();
+        let removed: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|p| !dirs.contains(p.as_path()))
+            .cloned()
+            .collect();
+        for dir in removed {
+            self.unwatch(&dir);
+        }
+        for dir in dirs {
+            self.watch_dir(dir);
+        }
+    }
+
+    /// Start watching `dir` (a directory that was just expanded).
+    pub fn watch_dir(&mut self, dir: &Path) {
+        if self.watched.contains(dir) {
+            return;
+        }
+        if let Some(ref mut watcher) = self.watcher {
+            if watcher.watch(dir, RecursiveMode::NonRecursive).is_ok() {
+                self.watched.insert(dir.to_path_buf());
+            }
+        }
+    }
+
+    /// Stop watching `dir` and every watched directory below it (a collapsed
+    /// row hides its expanded descendants too).
+    pub fn unwatch_tree(&mut self, dir: &Path) {
+        let below: Vec<PathBuf> = self
+            .watched
+            .iter()
+            .filter(|p| p.starts_with(dir))
+            .cloned()
+            .collect();
+        for path in below {
+            self.unwatch(&path);
+        }
+    }
+
+    fn unwatch(&mut self, dir: &Path) {
+        if let Some(ref mut watcher) = self.watcher {
+            let _ = watcher.unwatch(dir);
+        }
+        self.watched.remove(dir);
+    }
+
+    /// Drain pending events and, once they have settled, return the watched
+    /// directories whose listing changed (sorted, parents before children).
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let events: Vec<Event> = match self.rx {
+            Some(ref rx) => rx.try_iter().filter_map(|e| e.ok()).collect(),
+            None => return Vec::new(),
+        };
+        for event in events {
+            self.record(&event, now);
+        }
+        self.take_settled(now)
+    }
+
+    /// Note the directories `event` touches.  Content-only changes (writes,
+    /// metadata, reads) don't alter the tree and are ignored.
+    fn record(&mut self, event: &Event, now: Instant) {
+        match event.kind {
+            EventKind::Access(_)
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Metadata(_)) => return,
+            _ => {}
+        }
+        for path in &event.paths {
+            if let Some(parent) = path.parent().filter(|p| self.watched.contains(*p)) {
+                self.changed.insert(parent.to_path_buf());
+                self.last_event = Some(now);
+            }
+        }
+    }
+
+    fn take_settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        match self.last_event {
+            Some(last) if now.duration_since(last) >= DEBOUNCE => {
+                self.last_event = None;
+                std::mem::take(&mut self.changed).into_iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, DataChange, RemoveKind};
+
+    fn watcher_on(dirs: &[&str]) -> ExplorerWatcher {
+        let mut w = ExplorerWatcher::new();
+        // Mark as watched without touching the filesystem.
+        w.watched = dirs.iter().map(PathBuf::from).collect();
+        w
+    }
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn reports_changed_dirs_after_debounce() {
+        let mut w = watcher_on(&["/p", "/p/src"]);
+        let t0 = Instant::now();
+        w.record(
+            &event(EventKind::Create(CreateKind::File), "/p/src/new.rs"),
+            t0,
+        );
+        w.record(&event(EventKind::Remove(RemoveKind::Any), "/p/old.txt"), t0);
+        // Outside the watched set: ignored.
+        w.record(
+            &event(EventKind::Create(CreateKind::File), "/p/target/x"),
+            t0,
+        );
+        assert!(w.take_settled(t0).is_empty());
+        let later = t0 + DEBOUNCE;
+        assert_eq!(
+            w.take_settled(later),
+            vec![PathBuf::from("/p"), PathBuf::from("/p/src")]
+        );
+        assert!(w.take_settled(later).is_empty());
+    }
+
+    #[test]
+    fn ignores_content_changes() {
+        let mut w = watcher_on(&["/p"]);
+        let t0 = Instant::now();
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        w.record(&event(write, "/p/main.rs"), t0);
+        w.record(&event(EventKind::Access(AccessKind::Any), "/p/main.rs"), t0);
+        assert!(w.take_settled(t0 + DEBOUNCE).is_empty());
+    }
+
+    #[test]
+    fn unwatch_tree_drops_descendants() {
+        let mut w = watcher_on(&["/p", "/p/src", "/p/src/core", "/p/srcx"]);
+        w.unwatch_tree(Path::new("/p/src"));
+        let mut left: Vec<_> = w.watched.iter().cloned().collect();
+        left.sort();
+        assert_eq!(left, vec![PathBuf::from("/p"), PathBuf::from("/p/srcx")]);
+    }
+}
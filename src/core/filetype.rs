@@ -0,0 +1,265 @@
+//! Filetype detection from file contents, for files whose name says nothing
+//! about their language.
+//!
+//! Two sources are recognised:
+//! - a `#!` shebang on the first line (`#!/usr/bin/env python3` → python);
+//! - Vim-style modelines in the first or last [`MODELINE_LINES`] lines, in
+//!   either form `vim: ft=python ts=2` or `vim: set ft=python ts=2 :`
+//!   (`vi:` and `ex:` work too).
+//!
+//! Modelines come from the file being opened, so only a fixed whitelist of
+//! harmless buffer-local options is honoured: `filetype`, `tabstop`,
+//! `shiftwidth`, `textwidth` and `expandtab`.  Anything else (`shell`,
+//! `makeprg`, …) is ignored rather than reported.
+
+/// Number of lines at the start and end of a file searched for modelines
+/// (Vim's default `modelines=5`).
+pub const MODELINE_LINES: usize = 5;
+
+/// Largest `tabstop`/`shiftwidth` a modeline may set.
+const MAX_INDENT: u8 = 32;
+
+/// Largest `textwidth` a modeline may set.
+const MAX_TEXTWIDTH: usize = 1000;
+
+/// Buffer-local settings collected from a file's modelines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Modeline {
+    /// LSP language identifier (Vim names like `sh` are already mapped).
+    pub filetype: Option<String>,
+    pub tabstop: Option<u8>,
+    pub shift_width: Option<u8>,
+    pub textwidth: Option<usize>,
+    pub expand_tab: Option<bool>,
+}
+
+impl Modeline {
+    /// True when no option was recognised.
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Copy every option `other` sets over `self` (later modelines win).
+    fn merge(&mut self, other: Modeline) {
+        if other.filetype.is_some() {
+            self.filetype = other.filetype;
+        }
+        self.tabstop = other.tabstop.or(self.tabstop);
+        self.shift_width = other.shift_width.or(self.shift_width);
+        self.textwidth = other.textwidth.or(self.textwidth);
+        self.expand_tab = other.expand_tab.or(self.expand_tab);
+    }
+}
+
+/// Language identifier for a `#!` first line, e.g. `#!/bin/bash` or
+/// `#!/usr/bin/env -S python3 -u`.
+pub fn language_id_from_shebang(first_line: &str) -> Option<String> {
+    let rest = first_line.strip_prefix("#!")?;
+    let mut words = rest.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // Skip env's own flags (`-S`, `-i`, `VAR=value`).
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+        program = program.rsplit('/').next()?;
+    }
+    // `python3.11` → `python`, `ruby2.7` → `ruby`.
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let lang = match name {
+        "python" | "pypy" => "python",
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" => "shellscript",
+        "node" | "nodejs" | "deno" | "bun" => "javascript",
+        "ts-node" | "tsx" => "typescript",
+        "ruby" => "ruby",
+        "lua" | "luajit" => "lua",
+        "php" => "php",
+        "elixir" => "elixir",
+        "runghc" | "runhaskell" => "haskell",
+        "ocaml" => "ocaml",
+        "kotlin" => "kotlin",
+        "swift" => "swift",
+        _ => return None,
+    };
+    Some(lang.to_string())
+}
+
+/// Map a Vim `filetype` name to the LSP language identifier used for
+/// buffers.  Names without a known alias pass through unchanged; values
+/// that aren't plain identifiers are rejected.
+pub fn language_id_from_vim_filetype(ft: &str) -> Option<String> {
+    if ft.is_empty()
+        || !ft
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+    let lang = match ft {
+        "sh" | "bash" | "zsh" => "shellscript",
+        "cs" => "csharp",
+        "tex" | "plaintex" => "latex",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "py" => "python",
+        "rs" => "rust",
+        "yml" => "yaml",
+        "dosini" => "ini",
+        "make" => "makefile",
+        other => other,
+    };
+    Some(lang.to_string())
+}
+
+/// Parse the modeline on `line`, if it has one.
+pub fn parse_modeline(line: &str) -> Option<Modeline> {
+    let options = modeline_options(line)?;
+    let mut modeline = Modeline::default();
+    for opt in options {
+        apply_option(&mut modeline, opt);
+    }
+    Some(modeline)
+}
+
+/// Collect the modelines among `lines` (the head and tail of a file);
+/// when several set the same option the last one wins.
+pub fn parse_modelines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Modeline {
+    let mut result = Modeline::default();
+    for line in lines {
+        if let Some(modeline) = parse_modeline(line) {
+            result.merge(modeline);
+        }
+    }
+    result
+}
+
+/// Split a modeline into its `name[=value]` items, or `None` when the line
+/// has no `vi:`/`vim:`/`ex:` marker.
+fn modeline_options(line: &str) -> Option<Vec<&str>> {
+    let body = modeline_body(line)?;
+    let trimmed = body.trim_start();
+    let set_form = trimmed
+        .strip_prefix("set ")
+        .or_else(|| trimmed.strip_prefix("se "));
+    Some(match set_form {
+        // `vim: set ts=2 sw=2 :` — options end at the next unescaped ':'.
+        Some(rest) => {
+            let end = rest.find(':')?;
+            rest[..end].split_whitespace().collect()
+        }
+        // `vim: ts=2:sw=2` — whitespace or ':' separate options.
+        None => trimmed
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect(),
+    })
+}
+
+/// The text after the modeline marker.  `vi:`/`vim:`/`Vim:` must start the
+/// line or follow whitespace; `ex:` must follow whitespace.
+fn modeline_body(line: &str) -> Option<&str> {
+    for (pos, _) in line.char_indices() {
+        let at_start = pos == 0;
+        let after_space = line[..pos].ends_with(char::is_whitespace);
+        let rest = &line[pos..];
+        for marker in ["vim:", "Vim:", "vi:", "ex:"] {
+            if !rest.starts_with(marker) {
+                continue;
+            }
+            let allowed = if marker == "ex:" {
+                after_space
+            } else {
+                at_start || after_space
+            };
+            if allowed {
+                return Some(&rest[marker.len()..]);
+            }
+        }
+    }
+    None
+}
+
+/// Apply one `name[=value]` item if it is on the whitelist.
+fn apply_option(modeline: &mut Modeline, opt: &str) {
+    let (name, value) = match opt.split_once('=') {
+        Some((n, v)) => (n, Some(v)),
+        None => (opt, None),
+    };
+    match (name, value) {
+        ("filetype" | "ft", Some(v)) => {
+            if let Some(lang) = language_id_from_vim_filetype(v) {
+                modeline.filetype = Some(lang);
+            }
+        }
+        ("tabstop" | "ts", Some(v)) => {
+            modeline.tabstop = v.parse().ok().filter(|n| (1..=MAX_INDENT).contains(n));
+        }
+        ("shiftwidth" | "sw", Some(v)) => {
+            // 0 means "use tabstop", as in Vim.
+            modeline.shift_width = v.parse().ok().filter(|n| *n <= MAX_INDENT);
+        }
+        ("textwidth" | "tw", Some(v)) => {
+            modeline.textwidth = v.parse().ok().filter(|n| *n <= MAX_TEXTWIDTH);
+        }
+        ("expandtab" | "et", None) => modeline.expand_tab = Some(true),
+        ("noexpandtab" | "noet", None) => modeline.expand_tab = Some(false),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_shebang_interpreters() {
+        let cases = [
+            ("#!/bin/sh", Some("shellscript")),
+            ("#!/usr/bin/env bash", Some("shellscript")),
+            ("#!/usr/bin/env python3", Some("python")),
+            ("#!/usr/bin/python3.11 -u", Some("python")),
+            ("#!/usr/bin/env -S node --harmony", Some("javascript")),
+            ("#! /usr/bin/ruby", Some("ruby")),
+            ("#!/usr/bin/env FOO=1 lua", Some("lua")),
+            ("#!/usr/bin/awk -f", None),
+            ("# just a comment", None),
+        ];
+        for (line, want) in cases {
+            assert_eq!(
+                language_id_from_shebang(line).as_deref(),
+                want,
+                "shebang {line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_both_modeline_forms() {
+        let m = parse_modeline("# vim: ft=python ts=2:sw=4 et").unwrap();
+        assert_eq!(m.filetype.as_deref(), Some("python"));
+        assert_eq!(m.tabstop, Some(2));
+        assert_eq!(m.shift_width, Some(4));
+        assert_eq!(m.expand_tab, Some(true));
+
+        let m = parse_modeline("/* vim: set filetype=sh tw=72 noet : */").unwrap();
+        assert_eq!(m.filetype.as_deref(), Some("shellscript"));
+        assert_eq!(m.textwidth, Some(72));
+        assert_eq!(m.expand_tab, Some(false));
+
+        // Marker must start the line or follow whitespace.
+        assert!(parse_modeline("let favim: ts=2").is_none());
+        assert!(parse_modeline("Index: 3").is_none());
+    }
+
+    #[test]
+    fn ignores_unsafe_or_invalid_options() {
+        let m = parse_modeline("# vim: shell=/bin/evil makeprg=rm ts=0 ft=a;b tw=99999").unwrap();
+        assert!(m.is_empty(), "{m:?}");
+    }
+
+    #[test]
+    fn later_modelines_win() {
+        let m = parse_modelines(["# vim: ts=2 ft=ruby", "code", "# vim: ts=8"]);
+        assert_eq!(m.tabstop, Some(8));
+        assert_eq!(m.filetype.as_deref(), Some("ruby"));
+    }
+}
@@ -713,6 +713,17 @@ fn parse_hunk_new_range(header: &str) -> (usize, usize) {
 
 /// Compute structured diff hunks for a file, with line-range info for the working copy.
 pub fn compute_file_diff_hunks(path: &Path) -> Vec<DiffHunkInfo> {
+    file_diff_hunks(path, &["diff", "HEAD", "--"])
+}
+
+/// Hunks of the saved file that are not yet staged (index vs. working tree).
+/// These apply cleanly with `git apply --cached` even when part of the file
+/// is already staged.
+pub fn compute_unstaged_hunks(path: &Path) -> Vec<DiffHunkInfo> {
+    file_diff_hunks(path, &["diff", "--"])
+}
+
+fn file_diff_hunks(path: &Path, diff_args: &[&str]) -> Vec<DiffHunkInfo> {
     let dir = match path.parent() {
         Some(d) => d,
         None => return vec![],
@@ -721,7 +732,9 @@ pub fn compute_file_diff_hunks(path: &Path) -> Vec<DiffHunkInfo> {
         Some(s) => s,
         None => return vec![],
     };
-    let diff = match run_git(dir, &["diff", "HEAD", "--", path_str]) {
+    let mut args = diff_args.to_vec();
+    args.push(path_str);
+    let diff = match run_git(dir, &args) {
         Some(d) if !d.trim().is_empty() => d,
         _ => return vec![],
     };
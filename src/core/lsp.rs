@@ -27,6 +27,14 @@ pub enum LspEvent {
         request_id: i64,
         items: Vec<CompletionItem>,
     },
+    /// Resolved completion item (completionItem/resolve): fills in the
+    /// documentation and detail a server left out of the completion list.
+    CompletionResolveResponse {
+        server_id: LspServerId,
+        request_id: i64,
+        detail: Option<String>,
+        documentation: Option<String>,
+    },
     DefinitionResponse {
         server_id: LspServerId,
         request_id: i64,
@@ -87,6 +95,8 @@ pub enum LspEvent {
         server_id: LspServerId,
         request_id: i64,
         edits: Vec<FormattingEdit>,
+        /// Error message from the server, if the response contained an error.
+        error_message: Option<String>,
     },
     /// Rename response (textDocument/rename).
     RenameResponse {
@@ -102,6 +112,19 @@ pub enum LspEvent {
         request_id: i64,
         actions: Vec<CodeAction>,
     },
+    /// Inlay hint response (textDocument/inlayHint).
+    InlayHintResponse {
+        server_id: LspServerId,
+        request_id: i64,
+        hints: Vec<InlayHint>,
+    },
+    /// Server-initiated `workspace/applyEdit` (typically after executeCommand).
+    /// The reply is sent with [`LspServer::respond`] once the edit was tried.
+    ApplyWorkspaceEdit {
+        server_id: LspServerId,
+        request_id: serde_json::Value,
+        workspace_edit: WorkspaceEdit,
+    },
     /// Semantic tokens full response (textDocument/semanticTokens/full).
     SemanticTokensResponse {
         server_id: LspServerId,
@@ -138,6 +161,8 @@ pub struct SymbolInfo {
     pub line: u32,
     /// 0-indexed character (UTF-16).
     pub character: u32,
+    /// 0-indexed last line of the symbol's full range (body included).
+    pub end_line: u32,
     /// Child symbols (preserved from hierarchical DocumentSymbol responses).
     pub children: Vec<SymbolInfo>,
 }
@@ -282,6 +307,17 @@ impl SymbolKind {
     }
 }
 
+/// An inlay hint (inferred type, parameter name, ...) from `textDocument/inlayHint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlayHint {
+    /// 0-indexed line number.
+    pub line: u32,
+    /// Column in UTF-16 code units; the hint is drawn before this position.
+    pub character: u32,
+    /// Display text, with the server's `paddingLeft`/`paddingRight` applied.
+    pub label: String,
+}
+
 /// A single semantic token with absolute (decoded) positions.
 #[derive(Debug, Clone)]
 pub struct SemanticToken {
@@ -426,16 +462,31 @@ pub struct CodeAction {
     pub kind: Option<String>,
     /// The workspace edit to apply when this action is selected.
     pub edit: Option<WorkspaceEdit>,
+    /// Command to execute after (or instead of) the edit.
+    pub command: Option<LspCommand>,
 }
 
+/// An LSP `Command` — run on the server via `workspace/executeCommand`.
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // kind/detail/sort_text populated for future completion popup detail display
+pub struct LspCommand {
+    pub command: String,
+    pub arguments: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // kind/sort_text populated for future completion popup display
 pub struct CompletionItem {
     pub label: String,
     pub kind: Option<String>,
     pub detail: Option<String>,
     pub insert_text: Option<String>,
+    /// `insert_text` uses snippet syntax (`insertTextFormat: 2`).
+    pub is_snippet: bool,
     pub sort_text: Option<String>,
+    /// Documentation (markdown or plain text), if the server sent it.
+    pub documentation: Option<String>,
+    /// The item as received, sent back verbatim for `completionItem/resolve`.
+    pub raw: serde_json::Value,
 }
 
 #[derive(Debug, Clone)]
@@ -985,8 +1036,12 @@ impl LspServer {
                 "textDocument": {
                     "completion": {
                         "completionItem": {
-                            "snippetSupport": false,
-                            "labelDetailsSupport": true
+                            "snippetSupport": true,
+                            "labelDetailsSupport": true,
+                            "documentationFormat": ["markdown", "plaintext"],
+                            "resolveSupport": {
+                                "properties": ["documentation", "detail"]
+                            }
                         }
                     },
                     "hover": {
@@ -1020,6 +1075,9 @@ impl LspServer {
                         "willSave": false,
                         "willSaveWaitUntil": false
                     },
+                    "inlayHint": {
+                        "dynamicRegistration": false
+                    },
                     "semanticTokens": {
                         "dynamicRegistration": false,
                         "requests": { "full": true },
@@ -1078,6 +1136,16 @@ impl LspServer {
         self.send_raw(&msg.to_string());
     }
 
+    /// Reply to a server-initiated request.
+    pub fn respond(&self, id: serde_json::Value, result: serde_json::Value) {
+        let msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result
+        });
+        self.send_raw(&msg.to_string());
+    }
+
     fn send_raw(&self, body: &str) {
         let encoded = encode_message(body);
         if let Ok(mut stdin) = self.stdin.lock() {
@@ -1150,6 +1218,19 @@ impl LspServer {
         )
     }
 
+    /// Whether the server fills in completion items lazily via
+    /// `completionItem/resolve`.
+    pub fn supports_completion_resolve(&self) -> bool {
+        self.capabilities["completionProvider"]["resolveProvider"]
+            .as_bool()
+            .unwrap_or(false)
+    }
+
+    /// Resolve a completion item (as originally received) for its documentation.
+    pub fn request_completion_resolve(&mut self, item: &serde_json::Value) -> i64 {
+        self.send_request("completionItem/resolve", item.clone())
+    }
+
     /// Request go-to-definition at a position.
     pub fn request_definition(&mut self, uri: &str, line: u32, character: u32) -> i64 {
         self.send_request(
@@ -1240,6 +1321,17 @@ impl LspServer {
         )
     }
 
+    /// Run a server command (e.g. from a code action).
+    pub fn request_execute_command(&mut self, command: &LspCommand) -> i64 {
+        self.send_request(
+            "workspace/executeCommand",
+            serde_json::json!({
+                "command": command.command,
+                "arguments": command.arguments
+            }),
+        )
+    }
+
     /// Whether the server advertises document formatting support.
     #[allow(dead_code)]
     pub fn supports_formatting(&self) -> bool {
@@ -1340,6 +1432,26 @@ impl LspServer {
         )
     }
 
+    /// Whether the server advertises inlay hint support.
+    pub fn supports_inlay_hints(&self) -> bool {
+        let v = &self.capabilities["inlayHintProvider"];
+        v.as_bool().unwrap_or(false) || v.is_object()
+    }
+
+    /// Request inlay hints for a range of lines (end line exclusive).
+    pub fn request_inlay_hints(&mut self, uri: &str, start_line: u32, end_line: u32) -> i64 {
+        self.send_request(
+            "textDocument/inlayHint",
+            serde_json::json!({
+                "textDocument": { "uri": uri },
+                "range": {
+                    "start": { "line": start_line, "character": 0 },
+                    "end": { "line": end_line, "character": 0 }
+                }
+            }),
+        )
+    }
+
     /// Request document symbols (outline) for a file.
     pub fn request_document_symbols(&mut self, uri: &str) -> i64 {
         self.send_request(
@@ -1469,10 +1581,24 @@ fn reader_thread(
         if let Some(method) = json.get("method").and_then(|m| m.as_str()) {
             // Server-initiated requests have both "method" and "id" — respond to them.
             if let Some(req_id) = json.get("id") {
+                let mut result = serde_json::Value::Null;
+                if method == "workspace/applyEdit" {
+                    if let Some(edit) = json.get("params").and_then(|p| p.get("edit")) {
+                        // The engine replies once it has applied (or failed
+                        // to apply) the edit.
+                        let _ = tx.send(LspEvent::ApplyWorkspaceEdit {
+                            server_id,
+                            request_id: req_id.clone(),
+                            workspace_edit: try_parse_workspace_edit(edit),
+                        });
+                        continue;
+                    }
+                    result = serde_json::json!({ "applied": false });
+                }
                 let response = serde_json::json!({
                     "jsonrpc": "2.0",
                     "id": req_id,
-                    "result": null
+                    "result": result
                 });
                 let body = response.to_string();
                 let encoded = encode_message(&body);
@@ -1532,6 +1658,17 @@ fn reader_thread(
                         });
                     }
                 }
+                Some("completionItem/resolve") => {
+                    // Errors resolve to an item with nothing new to show.
+                    let null = serde_json::Value::Null;
+                    let r = result.unwrap_or(&null);
+                    let _ = tx.send(LspEvent::CompletionResolveResponse {
+                        server_id,
+                        request_id: id,
+                        detail: r.get("detail").and_then(|d| d.as_str()).map(String::from),
+                        documentation: r.get("documentation").and_then(extract_markup_content),
+                    });
+                }
                 Some("textDocument/definition") => {
                     let event = if let Some(r) = result {
                         try_parse_definition_response(server_id, id, r).unwrap_or(
@@ -1608,10 +1745,20 @@ fn reader_thread(
                 }
                 Some("textDocument/formatting") | Some("textDocument/rangeFormatting") => {
                     let edits = result.and_then(parse_text_edits).unwrap_or_default();
+                    let error_message = if is_error {
+                        json.get("error")
+                            .and_then(|e| e.get("message"))
+                            .and_then(|m| m.as_str())
+                            .map(|s| s.to_string())
+                            .or_else(|| Some("unknown error".to_string()))
+                    } else {
+                        None
+                    };
                     let _ = tx.send(LspEvent::FormattingResponse {
                         server_id,
                         request_id: id,
                         edits,
+                        error_message,
                     });
                 }
                 Some("textDocument/semanticTokens/full") => {
@@ -1634,6 +1781,16 @@ fn reader_thread(
                         });
                     }
                 }
+                Some("textDocument/inlayHint") => {
+                    // Errors and null results leave the existing hints in place.
+                    if let Some(arr) = result.and_then(|r| r.as_array()) {
+                        let _ = tx.send(LspEvent::InlayHintResponse {
+                            server_id,
+                            request_id: id,
+                            hints: arr.iter().filter_map(parse_inlay_hint).collect(),
+                        });
+                    }
+                }
                 Some("textDocument/rename") => {
                     let null = serde_json::Value::Null;
                     let r = result.unwrap_or(&null);
@@ -1663,7 +1820,13 @@ fn reader_thread(
                                     let kind =
                                         item.get("kind").and_then(|k| k.as_str()).map(String::from);
                                     let edit = item.get("edit").map(try_parse_workspace_edit);
-                                    Some(CodeAction { title, kind, edit })
+                                    let command = parse_code_action_command(item);
+                                    Some(CodeAction {
+                                        title,
+                                        kind,
+                                        edit,
+                                        command,
+                                    })
                                 })
                                 .collect::<Vec<_>>()
                         })
@@ -1766,20 +1929,27 @@ fn try_parse_completion_response(
             .get("detail")
             .and_then(|d| d.as_str())
             .map(|s| s.to_string());
+        // Servers like rust-analyzer send the text in `textEdit` instead.
         let insert_text = item
             .get("insertText")
+            .or_else(|| item.get("textEdit").and_then(|e| e.get("newText")))
             .and_then(|t| t.as_str())
             .map(|s| s.to_string());
+        let is_snippet = item.get("insertTextFormat").and_then(|f| f.as_u64()) == Some(2);
         let sort_text = item
             .get("sortText")
             .and_then(|t| t.as_str())
             .map(|s| s.to_string());
+        let documentation = item.get("documentation").and_then(extract_markup_content);
         items.push(CompletionItem {
             label,
             kind,
             detail,
             insert_text,
+            is_snippet,
             sort_text,
+            documentation,
+            raw: item.clone(),
         });
     }
 
@@ -1898,6 +2068,10 @@ fn parse_document_symbol_tree(
             ))
         })
         .unwrap_or((0, 0));
+    let end_line = item
+        .get("range")
+        .and_then(|r| r.get("end")?.get("line")?.as_u64())
+        .map_or(line, |l| l as u32);
 
     let children = item
         .get("children")
@@ -1917,6 +2091,7 @@ fn parse_document_symbol_tree(
         path: None,
         line,
         character,
+        end_line,
         children,
     })
 }
@@ -1961,6 +2136,10 @@ fn parse_symbol_information(item: &serde_json::Value) -> Option<SymbolInfo> {
     let start = range.get("start")?;
     let line = start.get("line")?.as_u64()? as u32;
     let character = start.get("character")?.as_u64()? as u32;
+    let end_line = range
+        .get("end")
+        .and_then(|e| e.get("line")?.as_u64())
+        .map_or(line, |l| l as u32);
 
     Some(SymbolInfo {
         name,
@@ -1970,6 +2149,7 @@ fn parse_symbol_information(item: &serde_json::Value) -> Option<SymbolInfo> {
         path,
         line,
         character,
+        end_line,
         children: Vec::new(),
     })
 }
@@ -2055,6 +2235,55 @@ fn parse_text_edits(result: &serde_json::Value) -> Option<Vec<FormattingEdit>> {
     Some(edits)
 }
 
+/// Parse one `InlayHint`. The label is either a string or an array of
+/// `InlayHintLabelPart`s whose values are concatenated.
+fn parse_inlay_hint(item: &serde_json::Value) -> Option<InlayHint> {
+    let pos = item.get("position")?;
+    let line = pos.get("line")?.as_u64()? as u32;
+    let character = pos.get("character")?.as_u64()? as u32;
+    let mut label = match item.get("label")? {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("value").and_then(|v| v.as_str()))
+            .collect(),
+        _ => return None,
+    };
+    if label.is_empty() {
+        return None;
+    }
+    if item.get("paddingLeft").and_then(|v| v.as_bool()) == Some(true) {
+        label.insert(0, ' ');
+    }
+    if item.get("paddingRight").and_then(|v| v.as_bool()) == Some(true) {
+        label.push(' ');
+    }
+    Some(InlayHint {
+        line,
+        character,
+        label,
+    })
+}
+
+/// Extract the command from a code action response item. The item is either a
+/// bare `Command` (`command` is a string) or a `CodeAction` whose optional
+/// `command` field holds a nested `Command` object.
+fn parse_code_action_command(item: &serde_json::Value) -> Option<LspCommand> {
+    let cmd = match item.get("command")? {
+        serde_json::Value::String(_) => item,
+        obj @ serde_json::Value::Object(_) => obj,
+        _ => return None,
+    };
+    Some(LspCommand {
+        command: cmd.get("command")?.as_str()?.to_string(),
+        arguments: cmd
+            .get("arguments")
+            .and_then(|a| a.as_array())
+            .cloned()
+            .unwrap_or_default(),
+    })
+}
+
 /// Parse a WorkspaceEdit from a rename response.
 fn try_parse_workspace_edit(result: &serde_json::Value) -> WorkspaceEdit {
     let mut file_edits: Vec<FileEdit> = Vec::new();
@@ -2352,6 +2581,60 @@ mod tests {
         assert_eq!(DiagnosticSeverity::Hint.symbol(), "H");
     }
 
+    #[test]
+    fn test_parse_inlay_hint() {
+        let typed = serde_json::json!({
+            "position": { "line": 3, "character": 9 },
+            "label": [{ "value": ": " }, { "value": "Vec<u8>" }],
+            "kind": 1
+        });
+        assert_eq!(
+            parse_inlay_hint(&typed),
+            Some(InlayHint {
+                line: 3,
+                character: 9,
+                label: ": Vec<u8>".to_string()
+            })
+        );
+
+        let param = serde_json::json!({
+            "position": { "line": 0, "character": 4 },
+            "label": "name:",
+            "paddingRight": true
+        });
+        assert_eq!(parse_inlay_hint(&param).unwrap().label, "name: ");
+
+        let empty = serde_json::json!({ "position": { "line": 0, "character": 0 }, "label": "" });
+        assert!(parse_inlay_hint(&empty).is_none());
+    }
+
+    #[test]
+    fn test_parse_code_action_command() {
+        // Bare Command item.
+        let bare = serde_json::json!({
+            "title": "Run",
+            "command": "rust-analyzer.runSingle",
+            "arguments": [{ "label": "test" }]
+        });
+        let cmd = parse_code_action_command(&bare).unwrap();
+        assert_eq!(cmd.command, "rust-analyzer.runSingle");
+        assert_eq!(cmd.arguments.len(), 1);
+
+        // CodeAction with a nested command object.
+        let nested = serde_json::json!({
+            "title": "Fix",
+            "kind": "quickfix",
+            "command": { "title": "Fix", "command": "fix.apply" }
+        });
+        let cmd = parse_code_action_command(&nested).unwrap();
+        assert_eq!(cmd.command, "fix.apply");
+        assert!(cmd.arguments.is_empty());
+
+        // Edit-only CodeAction.
+        let edit_only = serde_json::json!({ "title": "Import", "edit": { "changes": {} } });
+        assert!(parse_code_action_command(&edit_only).is_none());
+    }
+
     #[test]
     fn test_parse_diagnostics_json() {
         let params = serde_json::json!({
@@ -2460,6 +2743,7 @@ mod tests {
                 assert_eq!(items[0].detail, Some("fn foo()".to_string()));
                 assert_eq!(items[1].label, "bar");
                 assert_eq!(items[1].kind, Some("Variable".to_string()));
+                assert_eq!(items[1].raw["label"], "bar");
             }
             _ => panic!("Expected CompletionResponse"),
         }
@@ -2484,6 +2768,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_completion_documentation() {
+        let result = serde_json::json!([
+            { "label": "a", "documentation": "plain docs" },
+            { "label": "b", "documentation": { "kind": "markdown", "value": "**md** docs" } },
+            { "label": "c" }
+        ]);
+        let event = try_parse_completion_response(0, 1, &result).unwrap();
+        match event {
+            LspEvent::CompletionResponse { items, .. } => {
+                assert_eq!(items[0].documentation.as_deref(), Some("plain docs"));
+                assert_eq!(items[1].documentation.as_deref(), Some("**md** docs"));
+                assert_eq!(items[2].documentation, None);
+            }
+            _ => panic!("Expected CompletionResponse"),
+        }
+    }
+
+    #[test]
+    fn test_parse_completion_snippet() {
+        let result = serde_json::json!([
+            { "label": "push(…)", "insertTextFormat": 2,
+              "textEdit": { "newText": "push(${1:value})$0", "range": {
+                  "start": { "line": 0, "character": 2 },
+                  "end": { "line": 0, "character": 4 } } } },
+            { "label": "len", "insertText": "len" }
+        ]);
+        let event = try_parse_completion_response(0, 1, &result).unwrap();
+        match event {
+            LspEvent::CompletionResponse { items, .. } => {
+                assert_eq!(items[0].insert_text.as_deref(), Some("push(${1:value})$0"));
+                assert!(items[0].is_snippet);
+                assert!(!items[1].is_snippet);
+            }
+            _ => panic!("Expected CompletionResponse"),
+        }
+    }
+
     #[test]
     fn test_parse_location_link() {
         let result = serde_json::json!([{
@@ -2959,5 +3281,8 @@ bin:
         assert_eq!(symbols[0].children[1].name, "field_b");
         assert_eq!(symbols[1].name, "my_func");
         assert!(symbols[1].children.is_empty());
+        // Start comes from selectionRange, end from the full range.
+        assert_eq!((symbols[0].line, symbols[0].end_line), (0, 5));
+        assert_eq!((symbols[1].line, symbols[1].end_line), (10, 15));
     }
 }
@@ -5,8 +5,8 @@ use std::sync::mpsc::{self, Receiver, Sender};
 
 use super::extensions;
 use super::lsp::{
-    language_id_from_path, path_to_uri, LspEvent, LspServer, LspServerConfig, LspServerId,
-    SemanticTokensLegend,
+    language_id_from_path, path_to_uri, LspCommand, LspEvent, LspServer, LspServerConfig,
+    LspServerId, SemanticTokensLegend,
 };
 
 // ---------------------------------------------------------------------------
@@ -417,6 +417,9 @@ pub struct LspManager {
     /// Last error from `ensure_server_for_language` (dependency check failure, etc.).
     /// Engine reads and clears this after calling ensure_server.
     pub last_start_error: Option<String>,
+    /// Languages of open documents that the file extension doesn't give
+    /// (`language_map`, shebangs, modelines).
+    path_languages: HashMap<PathBuf, String>,
 }
 
 /// LSP server status for a given language (used by status bar indicator).
@@ -506,9 +509,26 @@ impl LspManager {
             server_has_responded: HashMap::new(),
             crashed_servers: Vec::new(),
             last_start_error: None,
+            path_languages: HashMap::new(),
         }
     }
 
+    /// Record the language of the document at `path`, overriding the one
+    /// its extension implies.  Call before `notify_did_open`.
+    pub fn set_language_for_path(&mut self, path: &Path, language_id: &str) {
+        self.path_languages
+            .insert(path.to_path_buf(), language_id.to_string());
+    }
+
+    /// Language of the document at `path`: a recorded override, else the
+    /// one its extension implies.
+    fn language_id_for(&self, path: &Path) -> Option<String> {
+        self.path_languages
+            .get(path)
+            .cloned()
+            .or_else(|| language_id_from_path(path))
+    }
+
     /// Update the cached extension manifests (called by engine when registry changes).
     /// `installed` — only installed extensions (used for server config lookup).
     /// `all` — all available extensions (used to check if a language is covered by an
@@ -718,7 +738,7 @@ impl LspManager {
     /// If the server is still initializing, the didOpen will be sent later
     /// when the Initialized event is processed (see `Engine::poll_lsp`).
     pub fn notify_did_open(&mut self, path: &Path, text: &str) -> Result<(), String> {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return Ok(()), // unknown language, nothing to do
         };
@@ -750,7 +770,7 @@ impl LspManager {
 
     /// Notify the appropriate server that a document changed.
     pub fn notify_did_change(&mut self, path: &Path, text: &str) {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return,
         };
@@ -765,7 +785,7 @@ impl LspManager {
 
     /// Notify the appropriate server that a document was saved.
     pub fn notify_did_save(&mut self, path: &Path, text: &str) {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return,
         };
@@ -780,7 +800,7 @@ impl LspManager {
 
     /// Notify the appropriate server that a document was closed.
     pub fn notify_did_close(&mut self, path: &Path) {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return,
         };
@@ -795,7 +815,7 @@ impl LspManager {
 
     /// Request completions from the appropriate server.
     pub fn request_completion(&mut self, path: &Path, line: u32, character: u32) -> Option<i64> {
-        let language_id = language_id_from_path(path)?;
+        let language_id = self.language_id_for(path)?;
         let server_id = *self.language_to_server.get(&language_id)?;
         if !self.initialized.get(&server_id).copied().unwrap_or(false) {
             return None;
@@ -807,7 +827,7 @@ impl LspManager {
     /// Helper: look up server for a path; returns (server_id, uri) if ready.
     /// If no server is running yet, attempts to start one.
     fn server_and_uri(&mut self, path: &Path) -> Option<(usize, String)> {
-        let language_id = language_id_from_path(path)?;
+        let language_id = self.language_id_for(path)?;
         if !self.language_to_server.contains_key(&language_id) {
             // No server running — try to start one.
             self.ensure_server_for_language(&language_id);
@@ -821,7 +841,7 @@ impl LspManager {
 
     /// Check whether a server exists for the given path but is still initializing.
     pub fn is_server_initializing(&self, path: &Path) -> bool {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return false,
         };
@@ -834,7 +854,7 @@ impl LspManager {
 
     /// Request go-to-definition from the appropriate server.
     pub fn request_definition(&mut self, path: &Path, line: u32, character: u32) -> Option<i64> {
-        let language_id = language_id_from_path(path)?;
+        let language_id = self.language_id_for(path)?;
         let server_id = *self.language_to_server.get(&language_id)?;
         if !self.initialized.get(&server_id).copied().unwrap_or(false) {
             return None;
@@ -845,7 +865,7 @@ impl LspManager {
 
     /// Request hover info from the appropriate server.
     pub fn request_hover(&mut self, path: &Path, line: u32, character: u32) -> Option<i64> {
-        let language_id = language_id_from_path(path)?;
+        let language_id = self.language_id_for(path)?;
         let server_id = *self.language_to_server.get(&language_id)?;
         if !self.initialized.get(&server_id).copied().unwrap_or(false) {
             return None;
@@ -854,6 +874,23 @@ impl LspManager {
         Some(self.servers[server_id].request_hover(&uri, line, character))
     }
 
+    /// Resolve a completion item with the server for `path`.  `None` when
+    /// the server isn't ready or doesn't resolve items lazily.
+    pub fn request_completion_resolve(
+        &mut self,
+        path: &Path,
+        item: &serde_json::Value,
+    ) -> Option<i64> {
+        let server_id = self.server_id_for_path(path)?;
+        if !self.initialized.get(&server_id).copied().unwrap_or(false) {
+            return None;
+        }
+        let server = self.servers.get_mut(server_id)?;
+        server
+            .supports_completion_resolve()
+            .then(|| server.request_completion_resolve(item))
+    }
+
     /// Request all references from the appropriate server.
     pub fn request_references(&mut self, path: &Path, line: u32, character: u32) -> Option<i64> {
         let (sid, uri) = self.server_and_uri(path)?;
@@ -917,6 +954,12 @@ impl LspManager {
         Some(self.servers[sid].request_code_action(&uri, line, col, diagnostics_json))
     }
 
+    /// Execute a code action command on the server that handles `path`.
+    pub fn request_execute_command(&mut self, path: &Path, command: &LspCommand) -> Option<i64> {
+        let (sid, _uri) = self.server_and_uri(path)?;
+        Some(self.servers[sid].request_execute_command(command))
+    }
+
     /// Request whole-file formatting from the appropriate server.
     pub fn request_formatting(
         &mut self,
@@ -931,7 +974,7 @@ impl LspManager {
     /// Check if the server for the given file supports document formatting.
     #[allow(dead_code)]
     pub fn server_supports_formatting(&self, path: &Path) -> bool {
-        let language_id = match language_id_from_path(path) {
+        let language_id = match self.language_id_for(path) {
             Some(l) => l,
             None => return false,
         };
@@ -964,6 +1007,32 @@ impl LspManager {
         Some(self.servers[sid].request_semantic_tokens_full(&uri))
     }
 
+    /// Request inlay hints for `start_line..end_line` (if the server supports them).
+    pub fn request_inlay_hints(
+        &mut self,
+        path: &Path,
+        start_line: u32,
+        end_line: u32,
+    ) -> Option<i64> {
+        let (sid, uri) = self.server_and_uri(path)?;
+        if !self.servers[sid].supports_inlay_hints() {
+            return None;
+        }
+        Some(self.servers[sid].request_inlay_hints(&uri, start_line, end_line))
+    }
+
+    /// Reply to a request `server_id` sent us (e.g. `workspace/applyEdit`).
+    pub fn respond(
+        &self,
+        server_id: LspServerId,
+        id: serde_json::Value,
+        result: serde_json::Value,
+    ) {
+        if let Some(server) = self.servers.get(server_id) {
+            server.respond(id, result);
+        }
+    }
+
     /// Get the cached semantic tokens legend for a server.
     pub fn semantic_legend_for_server(
         &self,
@@ -975,7 +1044,7 @@ impl LspManager {
     /// Find which server is handling a given file path.
     #[allow(dead_code)]
     pub fn server_id_for_path(&self, path: &Path) -> Option<LspServerId> {
-        let language_id = language_id_from_path(path)?;
+        let language_id = self.language_id_for(path)?;
         self.language_to_server.get(&language_id).copied()
     }
 
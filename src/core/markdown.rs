@@ -526,12 +526,107 @@ pub fn render_markdown(input: &str) -> MdRendered {
     }
 }
 
+impl MdRendered {
+    /// Word-wrap lines longer than `width` chars, splitting their spans and
+    /// code highlights to match.  Breaks after the last space that fits, or
+    /// mid-word when a line has none.  Code-block lines are kept whole so
+    /// their indentation and layout survive.
+    pub fn wrap(&self, width: usize) -> MdRendered {
+        let width = width.max(1);
+        let mut out = MdRendered {
+            lines: Vec::new(),
+            spans: Vec::new(),
+            code_highlights: Vec::new(),
+        };
+        for (i, line) in self.lines.iter().enumerate() {
+            let spans = self.spans.get(i).map(Vec::as_slice).unwrap_or(&[]);
+            let code_hl = self
+                .code_highlights
+                .get(i)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+            let in_code_block = spans.iter().any(|sp| sp.style == MdStyle::CodeBlock);
+            let line_width = if in_code_block { usize::MAX } else { width };
+            let mut start = 0;
+            loop {
+                let rest = &line[start..];
+                let end = match rest.char_indices().nth(line_width) {
+                    None => line.len(),
+                    Some((hard, _)) => {
+                        let soft = rest[..hard].rfind(' ').map(|p| p + 1);
+                        start + soft.unwrap_or(hard)
+                    }
+                };
+                out.lines.push(line[start..end].to_string());
+                out.spans.push(
+                    spans
+                        .iter()
+                        .filter(|sp| sp.start_byte < end && sp.end_byte > start)
+                        .map(|sp| MdSpan {
+                            start_byte: sp.start_byte.max(start) - start,
+                            end_byte: sp.end_byte.min(end) - start,
+                            style: sp.style,
+                        })
+                        .collect(),
+                );
+                out.code_highlights.push(
+                    code_hl
+                        .iter()
+                        .filter(|h| h.start_byte < end && h.end_byte > start)
+                        .map(|h| MdCodeHighlight {
+                            start_byte: h.start_byte.max(start) - start,
+                            end_byte: h.end_byte.min(end) - start,
+                            scope: h.scope.clone(),
+                        })
+                        .collect(),
+                );
+                if end >= line.len() {
+                    break;
+                }
+                start = end;
+            }
+        }
+        out
+    }
+}
+
 // ─── Tests ───────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn wrap_splits_long_lines_and_spans() {
+        let r = render_markdown("Call `some_function` with the **important** argument");
+        let w = r.wrap(20);
+        // The final "" is the blank line `render_markdown` ends a paragraph
+        // with; wrapping keeps it rather than adding an empty segment.
+        assert_eq!(r.lines.last().map(String::as_str), Some(""));
+        assert_eq!(
+            w.lines,
+            vec!["Call some_function ", "with the important ", "argument", ""]
+        );
+        // The code span stays on the first row; bold moves to the second.
+        assert!(w.spans[0].iter().any(|s| s.style == MdStyle::Code
+            && &w.lines[0][s.start_byte..s.end_byte] == "some_function"));
+        assert!(w.spans[1]
+            .iter()
+            .any(|s| s.style == MdStyle::Bold
+                && &w.lines[1][s.start_byte..s.end_byte] == "important"));
+        assert_eq!(w.lines.len(), w.code_highlights.len());
+
+        // A word longer than the width is broken mid-word.
+        let w = render_markdown("abcdefghij").wrap(4);
+        assert_eq!(w.lines, vec!["abcd", "efgh", "ij", ""]);
+        // Short lines are untouched.
+        assert_eq!(r.wrap(80).lines, r.lines);
+
+        // Code-block lines are never reflowed.
+        let code = render_markdown("```\nlet value = compute(first, second);\n```");
+        assert_eq!(code.wrap(10).lines, code.lines);
+    }
+
     #[test]
     fn heading_produces_bold_text() {
         let r = render_markdown("# Hello World");
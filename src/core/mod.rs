@@ -5,8 +5,14 @@ pub mod comment;
 pub mod cursor;
 pub mod dap;
 pub mod dap_manager;
+pub mod editorconfig;
+pub mod encoding;
 pub mod engine;
+pub mod errorformat;
+pub mod explorer_filter;
+pub mod explorer_watch;
 pub mod extensions;
+pub mod filetype;
 pub mod git;
 pub mod lsp;
 pub mod lsp_manager;
@@ -16,13 +22,17 @@ pub mod paths;
 pub mod plugin;
 pub mod project_search;
 pub mod registry;
+pub mod rule_syntax;
+pub mod safe_write;
 pub mod session;
 pub mod settings;
+pub mod snippet;
 pub mod spell;
 pub mod swap;
 pub mod syntax;
 pub mod tab;
 pub mod terminal;
+pub mod undofile;
 pub mod view;
 pub mod window;
 
@@ -2,7 +2,9 @@
 //!
 //! All modules that need `~/.config/vimcode/` (or the platform equivalent)
 //! should call [`vimcode_config_dir()`] instead of hardcoding paths.
+//! Also parses file arguments: `~` expansion and `path:line:col` targets.
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 /// Return the platform-appropriate VimCode configuration directory.
@@ -28,6 +30,25 @@ pub fn vimcode_config_dir() -> PathBuf {
     }
 }
 
+/// Return the platform-appropriate VimCode data directory (undo history etc.).
+///
+/// - **Linux / macOS**: `$HOME/.local/share/vimcode/`
+/// - **Windows**: `%LOCALAPPDATA%\vimcode\`  (fallback: the config directory)
+pub fn vimcode_data_dir() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(local) = std::env::var("LOCALAPPDATA") {
+            return PathBuf::from(local).join("vimcode");
+        }
+        vimcode_config_dir()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        home_dir().join(".local").join("share").join("vimcode")
+    }
+}
+
 /// Return the user's home directory in a cross-platform way.
 ///
 /// - **Linux / macOS**: `$HOME`
@@ -43,6 +64,15 @@ pub fn home_dir() -> PathBuf {
     PathBuf::from(home)
 }
 
+/// Expand a leading `~` (alone or followed by a separator) to the home directory.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some("") => home_dir(),
+        Some(rest) if rest.starts_with(std::path::is_separator) => home_dir().join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
 /// Strip the `\\?\` extended-length path prefix that Windows adds when
 /// a path is canonicalized. Returns the path unchanged on non-Windows.
 pub fn strip_unc_prefix(path: &Path) -> std::borrow::Cow<'_, Path> {
@@ -56,6 +86,74 @@ pub fn strip_unc_prefix(path: &Path) -> std::borrow::Cow<'_, Path> {
     std::borrow::Cow::Borrowed(path)
 }
 
+/// A file to open with an optional 1-based position, from `path:line[:col]`
+/// or a `+line` command-line argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTarget {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+}
+
+impl FileTarget {
+    /// Parse `path[:line[:col]]`, expanding a leading `~`.  Only trailing
+    /// all-digit fields count as a position, so `C:\src\main.rs` and
+    /// `notes:draft` stay whole, and a name that exists as typed is never
+    /// split.
+    pub fn parse(arg: &str) -> Self {
+        let whole = Self {
+            path: expand_tilde(arg),
+            line: None,
+            col: None,
+        };
+        if whole.path.exists() {
+            return whole;
+        }
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let Some((rest, last)) = arg.rsplit_once(':') else {
+            return whole;
+        };
+        if !is_number(last) {
+            return whole;
+        }
+        let (path, line, col) = match rest.rsplit_once(':') {
+            Some((path, line)) if is_number(line) => (path, line, Some(last)),
+            _ => (rest, last, None),
+        };
+        if path.is_empty() {
+            return whole;
+        }
+        Self {
+            path: expand_tilde(path),
+            line: line.parse().ok(),
+            col: col.and_then(|c| c.parse().ok()),
+        }
+    }
+}
+
+/// The file named on a command line: the first argument that is neither an
+/// option nor at an index in `skip` (option values), parsed as a
+/// [`FileTarget`].  A `+N` before it sets the line.
+pub fn file_target_from_args(args: &[String], skip: &HashSet<usize>) -> Option<FileTarget> {
+    let mut plus_line = None;
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        if arg.starts_with('-') || skip.contains(&i) {
+            continue;
+        }
+        if let Some(n) = arg.strip_prefix('+').and_then(|n| n.parse().ok()) {
+            plus_line = Some(n);
+            continue;
+        }
+        let mut target = FileTarget::parse(arg);
+        if plus_line.is_some() {
+            target.line = plus_line;
+            target.col = None;
+        }
+        return Some(target);
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,6 +174,65 @@ mod tests {
         assert!(dir.components().count() >= 2);
     }
 
+    #[test]
+    fn expand_tilde_only_at_start() {
+        assert_eq!(expand_tilde("~"), home_dir());
+        assert_eq!(
+            expand_tilde("~/src/main.rs"),
+            home_dir().join("src/main.rs")
+        );
+        assert_eq!(expand_tilde("a/~/b"), PathBuf::from("a/~/b"));
+        assert_eq!(expand_tilde("~user"), PathBuf::from("~user"));
+    }
+
+    #[test]
+    fn file_target_splits_trailing_line_and_column() {
+        let target = |path: &str, line, col| FileTarget {
+            path: PathBuf::from(path),
+            line,
+            col,
+        };
+        let cases = [
+            (
+                "src/main.rs:120:5",
+                target("src/main.rs", Some(120), Some(5)),
+            ),
+            ("src/main.rs:120", target("src/main.rs", Some(120), None)),
+            ("src/main.rs", target("src/main.rs", None, None)),
+            ("notes:draft", target("notes:draft", None, None)),
+            ("a:b:7", target("a:b", Some(7), None)),
+            (r"C:\src\main.rs", target(r"C:\src\main.rs", None, None)),
+            (
+                r"C:\src\main.rs:12:3",
+                target(r"C:\src\main.rs", Some(12), Some(3)),
+            ),
+            (":12", target(":12", None, None)),
+        ];
+        for (arg, want) in cases {
+            assert_eq!(FileTarget::parse(arg), want, "{arg}");
+        }
+    }
+
+    #[test]
+    fn file_target_from_args_reads_plus_line() {
+        let args = |list: &[&str]| -> Vec<String> { list.iter().map(|s| s.to_string()).collect() };
+        let none = HashSet::new();
+        let t = file_target_from_args(&args(&["vimcode", "--tui", "+120", "a.rs"]), &none).unwrap();
+        assert_eq!(
+            (t.path, t.line, t.col),
+            (PathBuf::from("a.rs"), Some(120), None)
+        );
+        let t = file_target_from_args(&args(&["vimcode", "a.rs:3:4"]), &none).unwrap();
+        assert_eq!((t.line, t.col), (Some(3), Some(4)));
+        let skip = HashSet::from([1, 2]);
+        let t = file_target_from_args(&args(&["vimcode", "--debug", "log", "b.rs"]), &skip);
+        assert_eq!(t.map(|t| t.path), Some(PathBuf::from("b.rs")));
+        assert_eq!(
+            file_target_from_args(&args(&["vimcode", "+5"]), &none),
+            None
+        );
+    }
+
     #[test]
     fn home_dir_is_not_empty() {
         let dir = home_dir();
@@ -6,9 +6,10 @@
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// Maximum number of results returned to prevent memory issues on huge repos.
-const MAX_RESULTS: usize = 10_000;
+pub const MAX_RESULTS: usize = 10_000;
 
 /// A single match found during a project search.
 #[derive(Debug, Clone)]
@@ -34,9 +35,51 @@ pub struct SearchOptions {
     pub whole_word: bool,
     /// When `true`, the query is interpreted as a regular expression.
     pub use_regex: bool,
+    /// Comma-separated globs ("files to include"); when non-empty only
+    /// matching files are searched.
+    pub include: String,
+    /// Comma-separated globs ("files to exclude").
+    pub exclude: String,
 }
 
-/// Error returned when the user-supplied regex is invalid.
+/// Shared between a background search and the UI: the UI reads live
+/// progress and can ask the worker to stop.
+#[derive(Debug, Default)]
+pub struct SearchControl {
+    cancelled: AtomicBool,
+    files_searched: AtomicUsize,
+    matches: AtomicUsize,
+}
+
+impl SearchControl {
+    /// Ask the worker to stop; it checks between files.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `(files searched, matches found)` so far.
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.files_searched.load(Ordering::Relaxed),
+            self.matches.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Matches of a finished search.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOutcome {
+    pub matches: Vec<ProjectMatch>,
+    /// The search stopped at `MAX_RESULTS`; more matches exist.
+    pub truncated: bool,
+}
+
+/// Error returned when the user-supplied regex or a file glob is invalid.
+/// The message is ready to show ("Invalid regex: …").
 #[derive(Debug, Clone)]
 pub struct SearchError(pub String);
 
@@ -55,8 +98,11 @@ pub struct ReplaceResult {
 
 /// Build a compiled regex from the user query and search options.
 ///
-/// Shared by `search_in_project` and `replace_in_project`.
-fn build_search_regex(query: &str, options: &SearchOptions) -> Result<regex::Regex, SearchError> {
+/// Shared by `search_in_project`, `replace_in_project` and the replace preview.
+pub fn build_search_regex(
+    query: &str,
+    options: &SearchOptions,
+) -> Result<regex::Regex, SearchError> {
     let escaped = if options.use_regex {
         query.to_string()
     } else {
@@ -72,38 +118,109 @@ fn build_search_regex(query: &str, options: &SearchOptions) -> Result<regex::Reg
     } else {
         format!("(?i){}", with_boundary)
     };
-    regex::Regex::new(&full_pattern).map_err(|e| SearchError(e.to_string()))
+    regex::Regex::new(&full_pattern).map_err(|e| SearchError(format!("Invalid regex: {e}")))
+}
+
+/// Split a comma-separated glob list ("*.rs, src/**") into its globs.
+pub fn split_globs(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|g| !g.is_empty())
+}
+
+/// Build the gitignore-aware walker for `root`, restricted by the
+/// include/exclude globs in `options`.  Globs are compiled once here;
+/// an invalid one is an error rather than a filter that matches nothing.
+fn build_walker(root: &Path, options: &SearchOptions) -> Result<ignore::Walk, SearchError> {
+    let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+    for glob in split_globs(&options.include) {
+        overrides
+            .add(glob)
+            .map_err(|e| SearchError(format!("Invalid include glob '{glob}': {e}")))?;
+    }
+    for glob in split_globs(&options.exclude) {
+        let glob = glob.trim_start_matches('!');
+        overrides
+            .add(&format!("!{glob}"))
+            .map_err(|e| SearchError(format!("Invalid exclude glob '{glob}': {e}")))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| SearchError(format!("Invalid glob: {e}")))?;
+    Ok(ignore::WalkBuilder::new(root)
+        .hidden(true) // skip hidden files/dirs
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .overrides(overrides)
+        .build())
+}
+
+/// Read `path` as text; `None` for unreadable, non-UTF-8 or binary (NUL
+/// bytes) files.
+fn read_text_file(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().filter(|c| !c.contains('\0'))
 }
 
 /// Search all text files under `root` for `query` using the given `options`.
 ///
 /// - Respects `.gitignore` rules via the `ignore` crate.
-/// - Binary files (non-UTF-8) are silently skipped.
+/// - Only files matching `options.include` (if set) and not
+///   `options.exclude` are visited.
+/// - Binary files (non-UTF-8 or containing NUL bytes) are silently skipped.
 /// - Hidden files/directories are skipped by default (ignore crate behaviour).
 /// - Results are sorted by file path, then line number.
 /// - At most `MAX_RESULTS` matches are returned.
-/// - Returns `Err(SearchError)` if `use_regex` is true and the pattern is invalid.
+/// - Returns `Err(SearchError)` if the regex or a glob is invalid.
 pub fn search_in_project(
     root: &Path,
     query: &str,
     options: &SearchOptions,
 ) -> Result<Vec<ProjectMatch>, SearchError> {
+    search_in_project_with(root, query, options, &SearchControl::default())
+        .map(|outcome| outcome.matches)
+}
+
+/// [`search_in_project`] for a background thread: updates `control`'s
+/// progress counters as it goes and stops early (returning what it has)
+/// once `control` is cancelled.
+pub fn search_in_project_with(
+    root: &Path,
+    query: &str,
+    options: &SearchOptions,
+    control: &SearchControl,
+) -> Result<SearchOutcome, SearchError> {
+    let mut results: Vec<ProjectMatch> = Vec::new();
+    let truncated = search_in_project_streaming(root, query, options, control, |matches| {
+        results.extend(matches)
+    })?;
+    results.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    Ok(SearchOutcome {
+        matches: results,
+        truncated,
+    })
+}
+
+/// [`search_in_project_with`] without collecting: `on_file` receives each
+/// file's matches (in line order) as soon as that file has been searched,
+/// in walk order.  Returns `true` when the search stopped at `MAX_RESULTS`.
+pub fn search_in_project_streaming(
+    root: &Path,
+    query: &str,
+    options: &SearchOptions,
+    control: &SearchControl,
+    mut on_file: impl FnMut(Vec<ProjectMatch>),
+) -> Result<bool, SearchError> {
     if query.is_empty() {
-        return Ok(Vec::new());
+        return Ok(false);
     }
 
     let re = build_search_regex(query, options)?;
+    let walker = build_walker(root, options)?;
 
-    let mut results: Vec<ProjectMatch> = Vec::new();
-
-    let walker = ignore::WalkBuilder::new(root)
-        .hidden(true) // skip hidden files/dirs
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .build();
-
+    let mut total = 0;
     for entry in walker {
+        if control.is_cancelled() {
+            break;
+        }
         let entry = match entry {
             Ok(e) => e,
             Err(_) => continue,
@@ -115,34 +232,54 @@ pub fn search_in_project(
         }
 
         let path = entry.path();
-        let content = match fs::read_to_string(path) {
-            Ok(c) => c,
-            Err(_) => continue, // binary or unreadable — skip
+        let Some(content) = read_text_file(path) else {
+            continue; // binary or unreadable — skip
         };
+        control.files_searched.fetch_add(1, Ordering::Relaxed);
 
+        let mut matches = Vec::new();
         for (line_idx, line_text) in content.lines().enumerate() {
             if let Some(m) = re.find(line_text) {
-                results.push(ProjectMatch {
+                matches.push(ProjectMatch {
                     file: path.to_path_buf(),
                     line: line_idx,
                     col: m.start(),
                     line_text: line_text.to_string(),
                 });
-                if results.len() >= MAX_RESULTS {
-                    results.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
-                    return Ok(results);
+                control.matches.fetch_add(1, Ordering::Relaxed);
+                total += 1;
+                if total >= MAX_RESULTS {
+                    on_file(matches);
+                    return Ok(true);
                 }
             }
         }
+        if !matches.is_empty() {
+            on_file(matches);
+        }
     }
+    Ok(false)
+}
 
-    results.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
-    Ok(results)
+/// Replace every match of `re` in `text`.  `$1` etc. expand to capture
+/// groups only in regex mode; in literal mode `$` is inserted as-is.
+pub fn replace_matches(
+    re: &regex::Regex,
+    text: &str,
+    replacement: &str,
+    use_regex: bool,
+) -> String {
+    if use_regex {
+        re.replace_all(text, replacement).into_owned()
+    } else {
+        re.replace_all(text, regex::NoExpand(replacement))
+            .into_owned()
+    }
 }
 
 /// Replace all occurrences of `query` with `replacement` across files under `root`.
 ///
-/// - Respects `.gitignore` rules via the `ignore` crate.
+/// - Respects `.gitignore` rules and the include/exclude globs.
 /// - Files whose canonicalized path appears in `skip_paths` are skipped (reported in result).
 /// - In literal mode (`use_regex=false`), `$` in `replacement` is treated literally.
 /// - In regex mode (`use_regex=true`), `$1`, `$2` etc. expand to capture groups.
@@ -164,6 +301,7 @@ pub fn replace_in_project(
     }
 
     let re = build_search_regex(query, options)?;
+    let walker = build_walker(root, options)?;
 
     let mut result = ReplaceResult {
         replacement_count: 0,
@@ -172,13 +310,6 @@ pub fn replace_in_project(
         modified_files: Vec::new(),
     };
 
-    let walker = ignore::WalkBuilder::new(root)
-        .hidden(true)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .build();
-
     for entry in walker {
         let entry = match entry {
             Ok(e) => e,
@@ -195,7 +326,7 @@ pub fn replace_in_project(
         let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
         if skip_paths.contains(&canonical) {
             // Only report as skipped if the file actually has matches.
-            if let Ok(content) = fs::read_to_string(&path) {
+            if let Some(content) = read_text_file(&path) {
                 if re.is_match(&content) {
                     result.skipped_files.push(path);
                 }
@@ -203,9 +334,8 @@ pub fn replace_in_project(
             continue;
         }
 
-        let content = match fs::read_to_string(&path) {
-            Ok(c) => c,
-            Err(_) => continue,
+        let Some(content) = read_text_file(&path) else {
+            continue;
         };
 
         let match_count = re.find_iter(&content).count();
@@ -213,13 +343,7 @@ pub fn replace_in_project(
             continue;
         }
 
-        // In literal mode, prevent $1 etc. from being interpreted as backreferences.
-        let new_content = if options.use_regex {
-            re.replace_all(&content, replacement).into_owned()
-        } else {
-            re.replace_all(&content, regex::NoExpand(replacement))
-                .into_owned()
-        };
+        let new_content = replace_matches(&re, &content, replacement, options.use_regex);
 
         if new_content != content && fs::write(&path, &new_content).is_ok() {
             result.replacement_count += match_count;
@@ -376,6 +500,93 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_search_progress_and_cancel() {
+        let dir = make_temp_project("progress_cancel");
+        let control = SearchControl::default();
+        let outcome =
+            search_in_project_with(&dir, "hello", &SearchOptions::default(), &control).unwrap();
+        let (files, matches) = control.progress();
+        assert!(files >= 2);
+        assert_eq!(matches, outcome.matches.len());
+        assert!(!outcome.truncated);
+
+        let control = SearchControl::default();
+        control.cancel();
+        let outcome =
+            search_in_project_with(&dir, "hello", &SearchOptions::default(), &control).unwrap();
+        assert!(outcome.matches.is_empty());
+        assert_eq!(control.progress(), (0, 0));
+    }
+
+    #[test]
+    fn test_streaming_delivers_per_file_batches() {
+        let dir = make_temp_project("streaming");
+        let mut batches: Vec<Vec<ProjectMatch>> = Vec::new();
+        let truncated = search_in_project_streaming(
+            &dir,
+            "hello",
+            &SearchOptions::default(),
+            &SearchControl::default(),
+            |matches| batches.push(matches),
+        )
+        .unwrap();
+        assert!(!truncated);
+        assert_eq!(batches.len(), 2);
+        for batch in &batches {
+            assert!(batch.iter().all(|m| m.file == batch[0].file));
+        }
+        let file1 = batches.iter().find(|b| b[0].file.ends_with("file1.txt"));
+        let lines: Vec<usize> = file1.unwrap().iter().map(|m| m.line).collect();
+        assert_eq!(lines, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let dir = make_temp_project("globs");
+        fs::write(dir.join("main.rs"), "hello rust\n").unwrap();
+        fs::write(dir.join("Cargo.lock"), "hello lock\n").unwrap();
+        let search = |include: &str, exclude: &str| -> Vec<String> {
+            let opts = SearchOptions {
+                include: include.to_string(),
+                exclude: exclude.to_string(),
+                ..Default::default()
+            };
+            search_in_project(&dir, "hello", &opts)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.line_text)
+                .collect()
+        };
+        assert_eq!(search("*.rs", ""), vec!["hello rust"]);
+        assert_eq!(search("*.rs, *.lock", ""), vec!["hello lock", "hello rust"]);
+        let excluded = search("", "*.lock, sub/**");
+        assert_eq!(excluded, vec!["hello world", "HELLO again", "hello rust"]);
+    }
+
+    #[test]
+    fn test_invalid_glob_returns_error() {
+        let dir = make_temp_project("invalid_glob");
+        let opts = SearchOptions {
+            include: "*.{rs".to_string(),
+            ..Default::default()
+        };
+        let err = search_in_project(&dir, "hello", &opts).unwrap_err();
+        assert!(
+            err.0.starts_with("Invalid include glob '*.{rs'"),
+            "{}",
+            err.0
+        );
+    }
+
+    #[test]
+    fn test_binary_files_skipped() {
+        let dir = make_temp_project("binary");
+        fs::write(dir.join("blob.bin"), "hello\0binary\n").unwrap();
+        let results = search_in_project(&dir, "hello", &SearchOptions::default()).unwrap();
+        assert!(results.iter().all(|m| !m.file.ends_with("blob.bin")));
+    }
+
     #[test]
     fn test_invalid_regex_returns_error() {
         let dir = make_temp_project("invalid_regex");
@@ -0,0 +1,857 @@
+//! Rule-based syntax highlighting for file types without a tree-sitter
+//! grammar (see `syntax.rs` for those).
+//!
+//! Each [`RuleLanguage`] in [`RULE_LANGUAGES`] lists keywords, comment and
+//! string delimiters; adding a language only needs a new registry entry.
+//! Rust, Python, JSON, TOML and Markdown have rule sets too; their
+//! tree-sitter grammars win where both apply, so the rules cover the file
+//! names the grammar table leaves out (`.pyi`, `.json5`, `Cargo.lock`).
+//! [`RuleHighlighter`] scans line by line, carrying block-comment and
+//! multi-line-string state from one line to the next, and caches spans by
+//! line text and entry state, so a re-highlight only rescans lines that are
+//! new or now start in a different state — inserting or deleting lines
+//! leaves the rest cached.  Spans use the same `(start_byte, end_byte,
+//! scope)` form as tree-sitter highlights, so the render layer treats both
+//! alike.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Highlighting rules for one language.
+pub struct RuleLanguage {
+    pub name: &'static str,
+    /// Lowercase file-name suffixes (`.sql`) or exact file names (`makefile`).
+    pub file_patterns: &'static [&'static str],
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub constants: &'static [&'static str],
+    /// Match keywords, types and constants ignoring ASCII case.
+    pub ignore_case: bool,
+    pub line_comments: &'static [&'static str],
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// String delimiters `(open, close, multi_line)`, longest first.
+    pub strings: &'static [(&'static str, &'static str, bool)],
+}
+
+/// Built-in rule sets.
+pub static RULE_LANGUAGES: &[RuleLanguage] = &[
+    RuleLanguage {
+        name: "SQL",
+        file_patterns: &[".sql"],
+        keywords: &[
+            "select",
+            "from",
+            "where",
+            "insert",
+            "into",
+            "values",
+            "update",
+            "set",
+            "delete",
+            "create",
+            "table",
+            "drop",
+            "alter",
+            "index",
+            "view",
+            "join",
+            "left",
+            "right",
+            "inner",
+            "outer",
+            "on",
+            "and",
+            "or",
+            "not",
+            "is",
+            "in",
+            "as",
+            "order",
+            "by",
+            "group",
+            "having",
+            "limit",
+            "offset",
+            "union",
+            "all",
+            "distinct",
+            "case",
+            "when",
+            "then",
+            "else",
+            "end",
+            "primary",
+            "key",
+            "foreign",
+            "references",
+            "default",
+            "begin",
+            "commit",
+            "rollback",
+            "transaction",
+            "exists",
+            "like",
+            "between",
+            "returning",
+            "with",
+        ],
+        types: &[
+            "int",
+            "integer",
+            "bigint",
+            "smallint",
+            "text",
+            "varchar",
+            "char",
+            "boolean",
+            "date",
+            "timestamp",
+            "float",
+            "real",
+            "numeric",
+            "decimal",
+            "serial",
+            "blob",
+            "json",
+        ],
+        constants: &["true", "false", "null"],
+        ignore_case: true,
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        strings: &[("'", "'", true), ("\"", "\"", false)],
+    },
+    RuleLanguage {
+        name: "Dockerfile",
+        file_patterns: &["dockerfile", "containerfile", ".dockerfile"],
+        keywords: &[
+            "from",
+            "run",
+            "cmd",
+            "label",
+            "maintainer",
+            "expose",
+            "env",
+            "add",
+            "copy",
+            "entrypoint",
+            "volume",
+            "user",
+            "workdir",
+            "arg",
+            "onbuild",
+            "stopsignal",
+            "healthcheck",
+            "shell",
+            "as",
+        ],
+        types: &[],
+        constants: &[],
+        ignore_case: true,
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &[("\"", "\"", false), ("'", "'", false)],
+    },
+    RuleLanguage {
+        name: "Makefile",
+        file_patterns: &["makefile", "gnumakefile", ".mk", ".make"],
+        keywords: &[
+            "ifeq", "ifneq", "ifdef", "ifndef", "else", "endif", "include", "define", "endef",
+            "export", "unexport", "override", "vpath",
+        ],
+        types: &[],
+        constants: &[],
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &[],
+    },
+    RuleLanguage {
+        name: "INI",
+        file_patterns: &[".ini", ".cfg", ".conf", ".properties", ".editorconfig"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "yes", "no", "on", "off"],
+        ignore_case: true,
+        line_comments: &[";", "#"],
+        block_comment: None,
+        strings: &[("\"", "\"", false)],
+    },
+    RuleLanguage {
+        name: "Kotlin",
+        file_patterns: &[".kt", ".kts"],
+        keywords: &[
+            "package",
+            "import",
+            "class",
+            "interface",
+            "object",
+            "fun",
+            "val",
+            "var",
+            "if",
+            "else",
+            "when",
+            "for",
+            "while",
+            "do",
+            "return",
+            "break",
+            "continue",
+            "try",
+            "catch",
+            "finally",
+            "throw",
+            "is",
+            "in",
+            "as",
+            "typealias",
+            "constructor",
+            "init",
+            "companion",
+            "data",
+            "sealed",
+            "enum",
+            "open",
+            "abstract",
+            "override",
+            "private",
+            "protected",
+            "public",
+            "internal",
+            "lateinit",
+            "suspend",
+            "inline",
+            "by",
+            "where",
+        ],
+        types: &[
+            "Int", "Long", "Short", "Byte", "Double", "Float", "Boolean", "Char", "String", "Unit",
+            "Any", "Nothing", "List", "Map", "Set", "Array",
+        ],
+        constants: &["true", "false", "null", "this", "super"],
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &[
+            ("\"\"\"", "\"\"\"", true),
+            ("\"", "\"", false),
+            ("'", "'", false),
+        ],
+    },
+    RuleLanguage {
+        name: "Swift",
+        file_patterns: &[".swift"],
+        keywords: &[
+            "import",
+            "class",
+            "struct",
+            "enum",
+            "protocol",
+            "extension",
+            "func",
+            "let",
+            "var",
+            "if",
+            "else",
+            "guard",
+            "switch",
+            "case",
+            "default",
+            "for",
+            "while",
+            "repeat",
+            "return",
+            "break",
+            "continue",
+            "in",
+            "where",
+            "throw",
+            "throws",
+            "rethrows",
+            "try",
+            "catch",
+            "do",
+            "defer",
+            "init",
+            "deinit",
+            "static",
+            "private",
+            "fileprivate",
+            "public",
+            "internal",
+            "open",
+            "override",
+            "mutating",
+            "async",
+            "await",
+            "typealias",
+            "associatedtype",
+            "as",
+            "is",
+            "inout",
+            "some",
+            "any",
+        ],
+        types: &[
+            "Int",
+            "Double",
+            "Float",
+            "Bool",
+            "String",
+            "Character",
+            "Array",
+            "Dictionary",
+            "Set",
+            "Optional",
+            "Void",
+            "Self",
+        ],
+        constants: &["true", "false", "nil", "self"],
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &[("\"\"\"", "\"\"\"", true), ("\"", "\"", false)],
+    },
+    RuleLanguage {
+        name: "Zig",
+        file_patterns: &[".zig"],
+        keywords: &[
+            "const",
+            "var",
+            "fn",
+            "pub",
+            "return",
+            "if",
+            "else",
+            "while",
+            "for",
+            "switch",
+            "break",
+            "continue",
+            "defer",
+            "errdefer",
+            "try",
+            "catch",
+            "orelse",
+            "struct",
+            "enum",
+            "union",
+            "error",
+            "test",
+            "comptime",
+            "inline",
+            "export",
+            "extern",
+            "usingnamespace",
+            "and",
+            "or",
+            "unreachable",
+            "async",
+            "await",
+            "suspend",
+            "resume",
+            "noreturn",
+        ],
+        types: &[
+            "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize", "f32", "f64",
+            "bool", "void", "type", "anyerror", "anytype",
+        ],
+        constants: &["true", "false", "null", "undefined"],
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: None,
+        strings: &[("\"", "\"", false), ("'", "'", false)],
+    },
+    RuleLanguage {
+        name: "Rust",
+        file_patterns: &[".rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "static", "struct", "trait", "type", "unsafe", "use",
+            "where", "while",
+        ],
+        types: &[
+            "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+            "f32", "f64", "bool", "char", "str", "String", "Vec", "Option", "Result", "Box",
+            "Self",
+        ],
+        constants: &["true", "false", "None", "Some", "Ok", "Err", "self"],
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &[
+            ("r#\"", "\"#", true),
+            ("r\"", "\"", true),
+            ("\"", "\"", true),
+        ],
+    },
+    RuleLanguage {
+        name: "Python",
+        file_patterns: &[".py", ".pyw", ".pyi"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        types: &[
+            "int",
+            "float",
+            "complex",
+            "str",
+            "bytes",
+            "bool",
+            "list",
+            "dict",
+            "set",
+            "frozenset",
+            "tuple",
+            "object",
+        ],
+        constants: &["True", "False", "None", "self", "cls"],
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &[
+            ("\"\"\"", "\"\"\"", true),
+            ("'''", "'''", true),
+            ("\"", "\"", false),
+            ("'", "'", false),
+        ],
+    },
+    RuleLanguage {
+        name: "JSON",
+        file_patterns: &[".json", ".jsonc", ".json5", ".geojson", ".jsonl"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "null"],
+        ignore_case: false,
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        strings: &[("\"", "\"", false)],
+    },
+    RuleLanguage {
+        name: "TOML",
+        file_patterns: &[".toml", "cargo.lock", "pipfile"],
+        keywords: &[],
+        types: &[],
+        constants: &["true", "false", "inf", "nan"],
+        ignore_case: false,
+        line_comments: &["#"],
+        block_comment: None,
+        strings: &[
+            ("\"\"\"", "\"\"\"", true),
+            ("'''", "'''", true),
+            ("\"", "\"", false),
+            ("'", "'", false),
+        ],
+    },
+    RuleLanguage {
+        name: "Markdown",
+        file_patterns: &[".md", ".markdown", ".mdx", ".mkd"],
+        keywords: &[],
+        types: &[],
+        constants: &[],
+        ignore_case: false,
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        // Fenced code blocks and inline code.
+        strings: &[("```", "```", true), ("`", "`", false)],
+    },
+];
+
+impl RuleLanguage {
+    /// The rule set for a file path, matched on its lowercase file name.
+    pub fn from_path(path: &str) -> Option<&'static RuleLanguage> {
+        let name = path
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(path)
+            .to_lowercase();
+        RULE_LANGUAGES.iter().find(|lang| {
+            lang.file_patterns.iter().any(|p| {
+                if p.starts_with('.') {
+                    name.ends_with(p)
+                } else {
+                    name == *p
+                }
+            })
+        })
+    }
+
+    fn word_scope(&self, word: &str) -> Option<&'static str> {
+        let matches = |list: &[&str]| {
+            list.iter().any(|w| {
+                if self.ignore_case {
+                    w.eq_ignore_ascii_case(word)
+                } else {
+                    *w == word
+                }
+            })
+        };
+        if matches(self.keywords) {
+            Some("keyword")
+        } else if matches(self.types) {
+            Some("type")
+        } else if matches(self.constants) {
+            Some("constant")
+        } else {
+            None
+        }
+    }
+}
+
+/// What a line starts inside of, carried over from the previous line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+enum LineState {
+    #[default]
+    Normal,
+    BlockComment,
+    /// Inside the multi-line string `strings[index]`.
+    String(usize),
+}
+
+/// Cached scan result for one line, keyed by its text hash and entry state.
+struct CachedLine {
+    end: LineState,
+    /// `(start_byte, end_byte, scope)` relative to the line start.
+    spans: Vec<(usize, usize, &'static str)>,
+}
+
+/// Incremental highlighter for one buffer.
+pub struct RuleHighlighter {
+    language: &'static RuleLanguage,
+    cache: HashMap<(u64, LineState), CachedLine>,
+    /// Lines rescanned by the last [`Self::highlight`] call.
+    rescanned: usize,
+}
+
+impl RuleHighlighter {
+    pub fn new(language: &'static RuleLanguage) -> Self {
+        Self {
+            language,
+            cache: HashMap::new(),
+            rescanned: 0,
+        }
+    }
+
+    /// A highlighter for `path`, or `None` when no rule set matches.
+    pub fn for_path(path: &str) -> Option<Self> {
+        RuleLanguage::from_path(path).map(Self::new)
+    }
+
+    /// A highlighter for a language identifier (`"sql"`, `"makefile"`),
+    /// matched case-insensitively against the rule set names.
+    pub fn for_language_id(id: &str) -> Option<Self> {
+        RULE_LANGUAGES
+            .iter()
+            .find(|lang| lang.name.eq_ignore_ascii_case(id))
+            .map(Self::new)
+    }
+
+    /// Lines rescanned by the last [`Self::highlight`] call.
+    #[cfg(test)]
+    pub fn rescanned(&self) -> usize {
+        self.rescanned
+    }
+
+    /// Highlight `text`, returning spans sorted by start byte.  A line whose
+    /// text and entry state were seen in the previous call reuses its spans
+    /// wherever it now sits; entries no longer used are dropped.
+    pub fn highlight(&mut self, text: &str) -> Vec<(usize, usize, String)> {
+        let mut out = Vec::new();
+        let mut cache = HashMap::with_capacity(self.cache.len());
+        let mut state = LineState::Normal;
+        let mut line_start = 0;
+        self.rescanned = 0;
+        for line in text.split_inclusive('\n') {
+            let mut hasher = DefaultHasher::new();
+            line.hash(&mut hasher);
+            let key = (hasher.finish(), state);
+            let cached = cache.entry(key).or_insert_with(|| {
+                self.cache.remove(&key).unwrap_or_else(|| {
+                    self.rescanned += 1;
+                    let mut spans = Vec::new();
+                    let end = scan_line(self.language, line, state, &mut spans);
+                    CachedLine { end, spans }
+                })
+            });
+            out.extend(
+                cached
+                    .spans
+                    .iter()
+                    .map(|&(s, e, scope)| (line_start + s, line_start + e, scope.to_string())),
+            );
+            state = cached.end;
+            line_start += line.len();
+        }
+        self.cache = cache;
+        out
+    }
+}
+
+/// Byte index just past `close` at or after `from`, skipping `\`-escaped
+/// characters.
+fn find_close(line: &str, from: usize, close: &str) -> Option<usize> {
+    let mut iter = line[from..].char_indices();
+    while let Some((off, c)) = iter.next() {
+        let pos = from + off;
+        if line[pos..].starts_with(close) {
+            return Some(pos + close.len());
+        }
+        if c == '\\' {
+            iter.next();
+        }
+    }
+    None
+}
+
+/// Scan one line (including its `\n`) starting in `state`, pushing spans
+/// relative to the line start; returns the state the next line starts in.
+fn scan_line(
+    lang: &RuleLanguage,
+    line: &str,
+    state: LineState,
+    spans: &mut Vec<(usize, usize, &'static str)>,
+) -> LineState {
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    let mut i = 0;
+
+    match state {
+        LineState::Normal => {}
+        LineState::BlockComment => {
+            let (_, close) = lang.block_comment.unwrap_or(("", ""));
+            match line.find(close).filter(|_| !close.is_empty()) {
+                Some(p) => {
+                    i = p + close.len();
+                    spans.push((0, i, "comment"));
+                }
+                None => {
+                    spans.push((0, len, "comment"));
+                    return LineState::BlockComment;
+                }
+            }
+        }
+        LineState::String(k) => match find_close(line, 0, lang.strings[k].1) {
+            Some(end) => {
+                spans.push((0, end, "string"));
+                i = end;
+            }
+            None => {
+                spans.push((0, len, "string"));
+                return LineState::String(k);
+            }
+        },
+    }
+
+    while i < len {
+        let rest = &line[i..len];
+        if lang.line_comments.iter().any(|c| rest.starts_with(c)) {
+            spans.push((i, len, "comment"));
+            break;
+        }
+        if let Some((open, close)) = lang.block_comment.filter(|(o, _)| rest.starts_with(o)) {
+            match rest[open.len()..].find(close) {
+                Some(p) => {
+                    let end = i + open.len() + p + close.len();
+                    spans.push((i, end, "comment"));
+                    i = end;
+                    continue;
+                }
+                None => {
+                    spans.push((i, len, "comment"));
+                    return LineState::BlockComment;
+                }
+            }
+        }
+        if let Some(k) = lang
+            .strings
+            .iter()
+            .position(|(o, _, _)| rest.starts_with(o))
+        {
+            let (open, close, multi_line) = lang.strings[k];
+            match find_close(line, i + open.len(), close) {
+                Some(end) => {
+                    spans.push((i, end, "string"));
+                    i = end;
+                    continue;
+                }
+                None => {
+                    spans.push((i, len, "string"));
+                    return if multi_line {
+                        LineState::String(k)
+                    } else {
+                        LineState::Normal
+                    };
+                }
+            }
+        }
+        let c = rest.chars().next().unwrap_or(' ');
+        if c.is_alphanumeric() || c == '_' {
+            let word_len = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..word_len];
+            let scope = if c.is_ascii_digit() {
+                Some("number")
+            } else {
+                lang.word_scope(word)
+            };
+            if let Some(scope) = scope {
+                spans.push((i, i + word_len, scope));
+            }
+            i += word_len;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+    LineState::Normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(text: &str, spans: &[(usize, usize, String)]) -> Vec<(String, String)> {
+        spans
+            .iter()
+            .map(|(s, e, scope)| (text[*s..*e].to_string(), scope.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_path_matches_suffix_and_file_name() {
+        assert_eq!(
+            RuleLanguage::from_path("db/schema.SQL").unwrap().name,
+            "SQL"
+        );
+        assert_eq!(
+            RuleLanguage::from_path("/src/Makefile").unwrap().name,
+            "Makefile"
+        );
+        assert_eq!(
+            RuleLanguage::from_path("Dockerfile").unwrap().name,
+            "Dockerfile"
+        );
+        assert!(RuleLanguage::from_path("notes.txt").is_none());
+        for (path, name) in [
+            ("main.rs", "Rust"),
+            ("stubs.pyi", "Python"),
+            ("data.json5", "JSON"),
+            ("Cargo.lock", "TOML"),
+            ("README.md", "Markdown"),
+        ] {
+            assert_eq!(RuleLanguage::from_path(path).unwrap().name, name);
+        }
+    }
+
+    #[test]
+    fn test_rules_for_grammar_languages() {
+        let mut hl = RuleHighlighter::for_language_id("python").unwrap();
+        let text = "def f():\n    \"\"\"doc\n    more\"\"\"\n    return None  # done\n";
+        assert_eq!(
+            scopes(text, &hl.highlight(text)),
+            vec![
+                ("def".into(), "keyword".into()),
+                ("\"\"\"doc".into(), "string".into()),
+                ("    more\"\"\"".into(), "string".into()),
+                ("return".into(), "keyword".into()),
+                ("None".into(), "constant".into()),
+                ("# done".into(), "comment".into()),
+            ]
+        );
+
+        let mut hl = RuleHighlighter::for_language_id("markdown").unwrap();
+        let text = "Run `cargo`:\n```\nfn main() {}\n```\n";
+        assert_eq!(
+            scopes(text, &hl.highlight(text)),
+            vec![
+                ("`cargo`".into(), "string".into()),
+                ("```".into(), "string".into()),
+                ("fn main() {}".into(), "string".into()),
+                ("```".into(), "string".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keywords_strings_and_comments() {
+        let mut hl = RuleHighlighter::for_path("q.sql").unwrap();
+        let text = "SELECT name FROM users WHERE id = 42 -- lookup\nAND note = 'it''s';\n";
+        let spans = hl.highlight(text);
+        assert_eq!(
+            scopes(text, &spans),
+            vec![
+                ("SELECT".into(), "keyword".into()),
+                ("FROM".into(), "keyword".into()),
+                ("WHERE".into(), "keyword".into()),
+                ("42".into(), "number".into()),
+                ("-- lookup".into(), "comment".into()),
+                ("AND".into(), "keyword".into()),
+                ("'it'".into(), "string".into()),
+                ("'s'".into(), "string".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multi_line_constructs_carry_state() {
+        let mut hl = RuleHighlighter::for_path("a.kt").unwrap();
+        let text = "/* start\nstill comment */ val x = \"\"\"raw\nfun text\"\"\"\nfun f() {}\n";
+        let spans = hl.highlight(text);
+        assert_eq!(
+            scopes(text, &spans),
+            vec![
+                ("/* start".into(), "comment".into()),
+                ("still comment */".into(), "comment".into()),
+                ("val".into(), "keyword".into()),
+                ("\"\"\"raw".into(), "string".into()),
+                ("fun text\"\"\"".into(), "string".into()),
+                ("fun".into(), "keyword".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_incremental_rescan_only_changed_lines() {
+        let mut hl = RuleHighlighter::for_path("a.kt").unwrap();
+        let before = "val a = 1\nval b = 2\nval c = 3\n";
+        hl.highlight(before);
+        assert_eq!(hl.rescanned(), 3);
+
+        hl.highlight("val a = 1\nval b = 22\nval c = 3\n");
+        assert_eq!(hl.rescanned(), 1);
+
+        // Opening a block comment changes the state the next lines start in.
+        let after = "val a = 1\n/* b = 2\nval c = 3\n";
+        let spans = hl.highlight(after);
+        assert_eq!(hl.rescanned(), 2);
+        assert_eq!(
+            scopes(after, &spans).last(),
+            Some(&("val c = 3".to_string(), "comment".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_inserting_a_line_rescans_only_that_line() {
+        let mut hl = RuleHighlighter::for_path("a.rs").unwrap();
+        hl.highlight("fn a() {}\nfn b() {}\nfn c() {}\n");
+
+        let text = "fn a() {}\nlet x = 1;\nfn b() {}\nfn c() {}\n";
+        let spans = hl.highlight(text);
+        assert_eq!(hl.rescanned(), 1);
+        assert_eq!(
+            scopes(text, &spans)
+                .iter()
+                .filter(|(_, scope)| scope == "keyword")
+                .count(),
+            4
+        );
+
+        // Deleting it again reuses every remaining line.
+        hl.highlight("fn a() {}\nfn b() {}\nfn c() {}\n");
+        assert_eq!(hl.rescanned(), 0);
+    }
+}
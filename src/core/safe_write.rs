@@ -0,0 +1,258 @@
+//! Crash-safe file writes.
+//!
+//! [`write_file`] writes to a temporary file next to the target, flushes it
+//! to disk and renames it over the target, so a failed or interrupted write
+//! leaves the original untouched.  A symlink is written through: the file it
+//! points at is replaced and the link itself stays.  The original file's
+//! permissions (and, on Unix, ownership where allowed) carry over to the new
+//! file.  When the directory doesn't allow creating files the write falls
+//! back to overwriting the target in place, with its old contents copied to
+//! the temp directory first and put back if the write fails.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Backup behaviour for [`write_file`], mirroring Vim's `backup` and
+/// `writebackup` options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackupMode {
+    /// Keep `file~` (the previous contents) after a successful write.
+    pub keep: bool,
+    /// Make `file~` before writing and remove it once the write succeeded.
+    pub during_write: bool,
+}
+
+/// Path of the backup kept for `path`: the same name with `~` appended.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("~");
+    PathBuf::from(name)
+}
+
+/// True when `path` exists and its permissions forbid writing.
+pub fn is_read_only(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.permissions().readonly())
+}
+
+/// True when the current user may write the existing file at `path`.
+pub fn is_writable(path: &Path) -> bool {
+    if is_read_only(path) {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        // SAFETY: `c_path` is a valid NUL-terminated string for the call.
+        unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 }
+    }
+    #[cfg(not(unix))]
+    true
+}
+
+/// Give the owner write permission on `path` (`:w!` on a read-only file).
+pub fn make_writable(path: &Path) -> io::Result<()> {
+    let mut perms = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        perms.set_mode(perms.mode() | 0o200);
+    }
+    #[cfg(not(unix))]
+    perms.set_readonly(false);
+    fs::set_permissions(path, perms)
+}
+
+/// Write `bytes` to `path` atomically, making a backup per `backup`.
+pub fn write_file(path: &Path, bytes: &[u8], backup: BackupMode) -> io::Result<()> {
+    // Write through symlinks rather than replacing the link.
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let original = fs::metadata(&target).ok();
+    let backup_file = backup_path(&target);
+    let make_backup = original.is_some() && (backup.keep || backup.during_write);
+    if make_backup {
+        fs::copy(&target, &backup_file)?;
+    }
+
+    match write_via_temp(&target, bytes, original.as_ref()) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && original.is_some() => {
+            write_in_place(&target, bytes)?
+        }
+        result => result?,
+    }
+
+    if make_backup && !backup.keep {
+        let _ = fs::remove_file(&backup_file);
+    }
+    Ok(())
+}
+
+/// Write `bytes` to a temporary sibling of `target` and rename it into place.
+fn write_via_temp(target: &Path, bytes: &[u8], original: Option<&fs::Metadata>) -> io::Result<()> {
+    let dir = match target.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = dir.join(format!(".{name}.{}.tmp", std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        drop(file);
+        if let Some(meta) = original {
+            copy_owner_and_permissions(&tmp, meta);
+        }
+        fs::rename(&tmp, target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Overwrite `target` in place, keeping a copy of it in the temp directory
+/// until the write has gone through.  A failed write restores the copy; if
+/// even that fails, the error names the copy so the old contents survive.
+fn write_in_place(target: &Path, bytes: &[u8]) -> io::Result<()> {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let saved = std::env::temp_dir().join(format!("vimcode-{}-{name}~", std::process::id()));
+    fs::copy(target, &saved)?;
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(target)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        });
+    match result {
+        Ok(()) => {
+            let _ = fs::remove_file(&saved);
+            Ok(())
+        }
+        Err(e) if fs::copy(&saved, target).is_ok() => {
+            let _ = fs::remove_file(&saved);
+            Err(e)
+        }
+        Err(e) => Err(io::Error::new(
+            e.kind(),
+            format!("{e}; the original is saved as {}", saved.display()),
+        )),
+    }
+}
+
+/// Best effort: a failure here still leaves a correctly written file.
+fn copy_owner_and_permissions(path: &Path, meta: &fs::Metadata) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // Only root (or a member of the group) may change these.
+        let _ = std::os::unix::fs::chown(path, Some(meta.uid()), Some(meta.gid()));
+    }
+    let _ = fs::set_permissions(path, meta.permissions());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn replaces_contents_and_leaves_no_temp_file() {
+        let dir = temp_dir("vimcode_safe_write_basic");
+        let path = dir.join("a.txt");
+        fs::write(&path, "old").unwrap();
+        write_file(&path, b"new", BackupMode::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(!backup_path(&path).exists());
+    }
+
+    #[test]
+    fn backup_kept_or_removed() {
+        let dir = temp_dir("vimcode_safe_write_backup");
+        let path = dir.join("a.txt");
+        fs::write(&path, "v1").unwrap();
+        let during = BackupMode {
+            keep: false,
+            during_write: true,
+        };
+        write_file(&path, b"v2", during).unwrap();
+        assert!(!backup_path(&path).exists());
+
+        let keep = BackupMode {
+            keep: true,
+            during_write: false,
+        };
+        write_file(&path, b"v3", keep).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v3");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks_and_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("vimcode_safe_write_link");
+        let real = dir.join("real.sh");
+        let link = dir.join("link.sh");
+        fs::write(&real, "old").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        write_file(&link, b"new", BackupMode::default()).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "new");
+        let mode = fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+
+    #[test]
+    fn in_place_write_leaves_no_saved_copy() {
+        let dir = temp_dir("vimcode_safe_write_in_place");
+        let path = dir.join("a.txt");
+        fs::write(&path, "old contents").unwrap();
+        write_in_place(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        let saved = std::env::temp_dir().join(format!("vimcode-{}-a.txt~", std::process::id()));
+        assert!(!saved.exists());
+    }
+
+    #[test]
+    fn make_writable_clears_read_only() {
+        let dir = temp_dir("vimcode_safe_write_ro");
+        let path = dir.join("ro.txt");
+        fs::write(&path, "x").unwrap();
+        let mut perms = fs::metadata(&path).unwrap().permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&path, perms).unwrap();
+        assert!(is_read_only(&path));
+        assert!(!is_writable(&path));
+        make_writable(&path).unwrap();
+        assert!(!is_read_only(&path));
+        assert!(is_writable(&path));
+    }
+}
@@ -28,15 +28,18 @@ pub fn saves_suppressed() -> bool {
 // HistoryState — command/search history in its own file
 // ---------------------------------------------------------------------------
 
+/// Maximum number of entries kept in each history list.
+pub const HISTORY_MAX: usize = 200;
+
 /// Command and search history, persisted to ~/.config/vimcode/history.json
 /// (separate from session.json so it is never overwritten by workspace sessions).
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HistoryState {
-    /// Command-mode history (most recent last, max 100 entries)
+    /// Command-mode history (most recent last, max [`HISTORY_MAX`] entries)
     #[serde(default)]
     pub command_history: Vec<String>,
 
-    /// Search history (most recent last, max 100 entries)
+    /// Search history (most recent last, max [`HISTORY_MAX`] entries)
     #[serde(default)]
     pub search_history: Vec<String>,
 }
@@ -96,26 +99,26 @@ impl HistoryState {
         Ok(())
     }
 
-    /// Add a command to history (max 100, removes duplicates, moves to end).
+    /// Add a command to history (capped, removes duplicates, moves to end).
     pub fn add_command(&mut self, cmd: &str) {
         if cmd.is_empty() {
             return;
         }
         self.command_history.retain(|c| c != cmd);
         self.command_history.push(cmd.to_string());
-        if self.command_history.len() > 100 {
+        if self.command_history.len() > HISTORY_MAX {
             self.command_history.remove(0);
         }
     }
 
-    /// Add a search query to history (max 100, removes duplicates, moves to end).
+    /// Add a search query to history (capped, removes duplicates, moves to end).
     pub fn add_search(&mut self, query: &str) {
         if query.is_empty() {
             return;
         }
         self.search_history.retain(|q| q != query);
         self.search_history.push(query.to_string());
-        if self.search_history.len() > 100 {
+        if self.search_history.len() > HISTORY_MAX {
             self.search_history.remove(0);
         }
     }
@@ -287,6 +290,128 @@ pub enum SessionGroupLayout {
     },
 }
 
+/// A named session written by `:mksession {name}`: every editor group with
+/// its tabs, the window splits inside each tab, cursors, folds and the cwd.
+/// Restored by `:source {name}` or `--session {name}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedSession {
+    /// Working directory when the session was made.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Editor group layout.
+    pub groups: SessionSplit<SessionGroup>,
+    /// Focused group (index in leaf order).
+    #[serde(default)]
+    pub active_group: usize,
+}
+
+/// Split tree shared by the group layout and each tab's window layout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SessionSplit<T> {
+    Leaf(T),
+    Split {
+        /// 0 = Vertical (side-by-side), 1 = Horizontal (stacked).
+        direction: u8,
+        ratio: f64,
+        first: Box<SessionSplit<T>>,
+        second: Box<SessionSplit<T>>,
+    },
+}
+
+/// An editor group in a [`NamedSession`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionGroup {
+    pub tabs: Vec<SessionTab>,
+    #[serde(default)]
+    pub active_tab: usize,
+}
+
+/// A tab page in a [`NamedSession`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub windows: SessionSplit<SessionWindow>,
+    /// Focused window (index in leaf order).
+    #[serde(default)]
+    pub active_window: usize,
+}
+
+/// A window in a [`NamedSession`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionWindow {
+    /// File shown in the window (`None` = an unnamed buffer).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub line: usize,
+    #[serde(default)]
+    pub col: usize,
+    #[serde(default)]
+    pub scroll_top: usize,
+    /// Closed folds as `(start, end)` line pairs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folds: Vec<(usize, usize)>,
+    /// Window-local directory set by `:lcd`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_cwd: Option<PathBuf>,
+}
+
+impl NamedSession {
+    /// `~/.config/vimcode/sessions/{name}.json`.  A `name` that looks like a
+    /// path (has a directory part or ends in `.json`) is used as-is.
+    pub fn path_for(name: &str) -> PathBuf {
+        let path = super::paths::expand_tilde(name);
+        if name.contains(std::path::is_separator)
+            || path.extension().is_some_and(|ext| ext == "json")
+        {
+            return path;
+        }
+        Self::dir().join(format!("{name}.json"))
+    }
+
+    /// `~/.config/vimcode/sessions`.
+    fn dir() -> PathBuf {
+        super::paths::vimcode_config_dir().join("sessions")
+    }
+
+    /// Names of the saved sessions, sorted (for `:source` completion).
+    pub fn names() -> Vec<String> {
+        Self::names_in(&Self::dir())
+    }
+
+    fn names_in(dir: &Path) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read session {}: {}", path.display(), e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid session {}: {}", path.display(), e))
+    }
+
+    /// Write the session (atomically, creating the sessions directory).
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, &json)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
 /// Saved cursor and scroll position for a file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilePosition {
@@ -309,7 +434,8 @@ pub struct SessionState {
     #[serde(default = "default_sidebar_width")]
     pub sidebar_width: i32,
 
-    /// Last opened files (future: cursor positions)
+    /// The buffer manager's most-recently-opened files, newest first
+    /// (absolute paths), saved for the next start.
     #[serde(default)]
     pub recent_files: Vec<PathBuf>,
 
@@ -353,6 +479,20 @@ pub struct SessionState {
     /// When present, takes priority over the flat open_files_group1/active_group fields.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub group_layout: Option<SessionGroupLayout>,
+
+    /// Harpoon-style pinned files, keyed by working directory (project root).
+    #[serde(default)]
+    pub pinned_files: HashMap<PathBuf, Vec<PathBuf>>,
+
+    /// Working directory at save time (per-workspace session only), so a
+    /// `:cd` inside the project is restored on reopen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+
+    /// Register contents (`"a`-`"z`, `"0`-`"9`, `"-` and the unnamed `""`)
+    /// with their linewise flag, restored on startup.
+    #[serde(default)]
+    pub registers: HashMap<char, (String, bool)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -402,6 +542,9 @@ impl Default for SessionState {
             group_split_direction: 0,
             group_split_ratio: default_group_split_ratio(),
             group_layout: None,
+            pinned_files: HashMap::new(),
+            cwd: None,
+            registers: HashMap::new(),
         }
     }
 }
@@ -546,6 +689,21 @@ mod tests {
         assert!(!session.explorer_visible);
     }
 
+    #[test]
+    fn test_session_registers_round_trip() {
+        let mut session = SessionState::default();
+        session.registers.insert('a', ("word".to_string(), false));
+        session.registers.insert('"', ("line\n".to_string(), true));
+        let json = serde_json::to_string(&session).unwrap();
+        let loaded: SessionState = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.registers, session.registers);
+
+        // Sessions saved before registers were persisted still load.
+        let old: SessionState =
+            serde_json::from_str(r#"{"window":{"width":1,"height":1}}"#).unwrap();
+        assert!(old.registers.is_empty());
+    }
+
     #[test]
     fn test_history_state_add_command() {
         let mut h = HistoryState::default();
@@ -573,13 +731,13 @@ mod tests {
     #[test]
     fn test_history_limit() {
         let mut h = HistoryState::default();
-        for i in 0..150 {
+        for i in 0..250 {
             h.add_command(&format!("cmd{}", i));
         }
-        assert_eq!(h.command_history.len(), 100);
-        // Should have kept the last 100
+        assert_eq!(h.command_history.len(), HISTORY_MAX);
+        // Should have kept the last 200
         assert_eq!(h.command_history[0], "cmd50");
-        assert_eq!(h.command_history[99], "cmd149");
+        assert_eq!(h.command_history[199], "cmd249");
     }
 
     #[test]
@@ -779,4 +937,73 @@ mod tests {
         assert!(session.group_layout.is_none());
         assert_eq!(session.open_files, vec![PathBuf::from("/tmp/x.rs")]);
     }
+
+    #[test]
+    fn test_named_session_path_for() {
+        let sessions = crate::core::paths::vimcode_config_dir().join("sessions");
+        assert_eq!(NamedSession::path_for("work"), sessions.join("work.json"));
+        assert_eq!(
+            NamedSession::path_for("/tmp/work.json"),
+            PathBuf::from("/tmp/work.json")
+        );
+        assert_eq!(
+            NamedSession::path_for("work.json"),
+            PathBuf::from("work.json")
+        );
+    }
+
+    #[test]
+    fn test_named_session_names() {
+        let dir = std::env::temp_dir().join("vimcode_test_session_names");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["work.json", "api.json", "notes.txt", "work.json.tmp"] {
+            std::fs::write(dir.join(file), "{}").unwrap();
+        }
+        assert_eq!(NamedSession::names_in(&dir), vec!["api", "work"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_named_session_roundtrip() {
+        let window = |file: &str, line| {
+            SessionSplit::Leaf(SessionWindow {
+                file: Some(PathBuf::from(file)),
+                line,
+                folds: vec![(2, 5)],
+                ..SessionWindow::default()
+            })
+        };
+        let session = NamedSession {
+            cwd: Some(PathBuf::from("/tmp")),
+            groups: SessionSplit::Leaf(SessionGroup {
+                tabs: vec![SessionTab {
+                    windows: SessionSplit::Split {
+                        direction: 1,
+                        ratio: 0.5,
+                        first: Box::new(window("/tmp/a.rs", 3)),
+                        second: Box::new(window("/tmp/b.rs", 7)),
+                    },
+                    active_window: 1,
+                }],
+                active_tab: 0,
+            }),
+            active_group: 0,
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        let restored: NamedSession = serde_json::from_str(&json).unwrap();
+        let SessionSplit::Leaf(group) = restored.groups else {
+            panic!("expected a single group");
+        };
+        let tab = &group.tabs[0];
+        assert_eq!(tab.active_window, 1);
+        let SessionSplit::Split { second, .. } = &tab.windows else {
+            panic!("expected a split");
+        };
+        let SessionSplit::Leaf(ref b) = **second else {
+            panic!("expected a window");
+        };
+        assert_eq!(b.file, Some(PathBuf::from("/tmp/b.rs")));
+        assert_eq!((b.line, b.folds.clone()), (7, vec![(2, 5)]));
+    }
 }
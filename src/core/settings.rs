@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Which editing paradigm the editor uses.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -22,6 +22,134 @@ pub enum LineNumberMode {
     Hybrid,
 }
 
+/// Whitespace glyphs for `:set list`, parsed from the `listchars` setting.
+/// A `None` entry leaves that kind of whitespace blank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ListChars {
+    /// First cell of a tab, then the glyph filling the rest of the tab stop.
+    pub tab: Option<(char, char)>,
+    /// Spaces after the last non-blank character (falls back to `space`).
+    pub trail: Option<char>,
+    /// Every other space.
+    pub space: Option<char>,
+    /// Drawn just past the end of each line.
+    pub eol: Option<char>,
+}
+
+impl ListChars {
+    /// Parse a Vim-style `listchars` value: comma-separated `name:glyph`
+    /// entries for `tab` (two glyphs), `trail`, `space` and `eol`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut lc = ListChars::default();
+        for part in s.split(',').filter(|p| !p.is_empty()) {
+            let (name, value) = part
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid listchars entry: '{part}'"))?;
+            let mut chars = value.chars();
+            let first = chars.next();
+            let second = chars.next();
+            match (name, first, second) {
+                ("tab", Some(a), b) => lc.tab = Some((a, b.unwrap_or(' '))),
+                ("trail", Some(c), None) => lc.trail = Some(c),
+                ("space", Some(c), None) => lc.space = Some(c),
+                ("eol", Some(c), None) => lc.eol = Some(c),
+                _ => return Err(format!("Invalid listchars entry: '{part}'")),
+            }
+        }
+        Ok(lc)
+    }
+}
+
+/// Cursor shape named in the `cursorstyle` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorStyleShape {
+    Block,
+    Bar,
+    Underline,
+}
+
+/// How the cursor looks in one mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeCursorStyle {
+    pub shape: CursorStyleShape,
+    /// `#rrggbb` colour replacing the theme's cursor colour.
+    pub color: Option<String>,
+    pub blink: bool,
+}
+
+impl ModeCursorStyle {
+    fn new(shape: CursorStyleShape, blink: bool) -> Self {
+        Self {
+            shape,
+            color: None,
+            blink,
+        }
+    }
+}
+
+/// Per-mode cursor looks, parsed from the `cursorstyle` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorStyles {
+    pub normal: ModeCursorStyle,
+    pub insert: ModeCursorStyle,
+    pub visual: ModeCursorStyle,
+    /// Replace mode and the pending `r` command.
+    pub replace: ModeCursorStyle,
+}
+
+impl Default for CursorStyles {
+    fn default() -> Self {
+        Self {
+            normal: ModeCursorStyle::new(CursorStyleShape::Block, false),
+            insert: ModeCursorStyle::new(CursorStyleShape::Bar, true),
+            visual: ModeCursorStyle::new(CursorStyleShape::Block, false),
+            replace: ModeCursorStyle::new(CursorStyleShape::Underline, false),
+        }
+    }
+}
+
+impl CursorStyles {
+    /// Parse comma-separated `mode:look` entries.  The mode is `normal`,
+    /// `insert`, `visual` or `replace`; the look joins with `-` a shape
+    /// (`block`, `bar`, `underline`), `blink` and a `#rrggbb` colour, any of
+    /// which may be left out (e.g. `insert:bar-blink-#e5c07b`).  A missing
+    /// shape keeps the mode's default one; modes not listed keep their
+    /// default look.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let defaults = CursorStyles::default();
+        let mut styles = defaults.clone();
+        for part in s.split(',').filter(|p| !p.is_empty()) {
+            let invalid = || format!("Invalid cursorstyle entry: '{part}'");
+            let (mode, look) = part.split_once(':').ok_or_else(invalid)?;
+            let (slot, default) = match mode {
+                "normal" => (&mut styles.normal, &defaults.normal),
+                "insert" => (&mut styles.insert, &defaults.insert),
+                "visual" => (&mut styles.visual, &defaults.visual),
+                "replace" => (&mut styles.replace, &defaults.replace),
+                _ => return Err(invalid()),
+            };
+            let mut style = ModeCursorStyle::new(default.shape, false);
+            for word in look.split('-').filter(|w| !w.is_empty()) {
+                match word {
+                    "block" => style.shape = CursorStyleShape::Block,
+                    "bar" => style.shape = CursorStyleShape::Bar,
+                    "underline" => style.shape = CursorStyleShape::Underline,
+                    "blink" => style.blink = true,
+                    _ if word.len() == 7
+                        && word.starts_with('#')
+                        && word[1..].chars().all(|c| c.is_ascii_hexdigit()) =>
+                    {
+                        style.color = Some(word.to_string());
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            *slot = style;
+        }
+        Ok(styles)
+    }
+}
+
 /// User settings loaded from ~/.config/vimcode/settings.json
 ///
 /// IMPORTANT: When adding new settings fields:
@@ -82,6 +210,10 @@ pub struct Settings {
     #[serde(default)]
     pub format_on_save: bool,
 
+    /// Show LSP inlay hints (inferred types, parameter names) inline (default: false).
+    #[serde(default)]
+    pub inlay_hints: bool,
+
     /// Number of lines kept in the integrated terminal's scrollback history.
     /// Increase for commands that produce very long output. Default: 5000.
     #[serde(default = "default_terminal_scrollback_lines")]
@@ -142,14 +274,29 @@ pub struct Settings {
     #[serde(default)]
     pub disabled_plugins: Vec<String>,
 
-    /// User-defined key mappings. Each entry is `"mode keys :command"`.
+    /// User-defined key mappings. Each entry is `"mode keys action"`.
     /// Mode: `n` (normal), `v` (visual), `i` (insert), `c` (command).
-    /// Keys: single char (`x`), modifier (`<C-/>`, `<A-c>`), or sequence (`gcc`, `gc`).
-    /// Action: ex command prefixed with `:`.
-    /// Example: `["n <C-/> :Commentary", "v <C-/> :Commentary"]`
+    /// Keys: single char (`x`), modifier (`<C-/>`, `<A-c>`), sequence (`gcc`, `gc`),
+    /// `<leader>` for the leader key.
+    /// Action: ex command prefixed with `:` (optional trailing `<CR>`), or a key
+    /// sequence replayed without remapping.
+    /// Example: `["n <C-/> :Commentary", "n <leader>w :w<CR>", "i jk <Esc>"]`
     #[serde(default)]
     pub keymaps: Vec<String>,
 
+    /// Insert-mode abbreviations (`:iabbrev teh the`): lhs → expansion.
+    #[serde(default)]
+    pub abbreviations: std::collections::BTreeMap<String, String>,
+
+    /// Milliseconds to wait for the next key of an ambiguous keymap prefix.
+    #[serde(default = "default_timeoutlen")]
+    pub timeoutlen: u32,
+
+    /// Show a popup of possible next keys while a multi-key sequence
+    /// (`<leader>`, `g`, `z`, `<C-w>`, user keymap prefixes) is pending.
+    #[serde(default = "default_which_key")]
+    pub which_key: bool,
+
     /// Highlight all search matches (default true). Disable with `:set nohlsearch`.
     #[serde(default = "default_hlsearch")]
     pub hlsearch: bool,
@@ -163,6 +310,16 @@ pub struct Settings {
     #[serde(default)]
     pub smartcase: bool,
 
+    /// `n` / `N` wrap around the end of the buffer (default true).
+    /// Disable with `:set nowrapscan` to stop at the last match.
+    #[serde(default = "default_true")]
+    pub wrapscan: bool,
+
+    /// Reopen the fuzzy file picker with the query it was closed with
+    /// (default false).  The last opened file is pre-selected either way.
+    #[serde(default)]
+    pub fuzzy_remember_query: bool,
+
     /// Number of lines to keep visible above/below the cursor (default 0).
     #[serde(default)]
     pub scrolloff: usize,
@@ -186,6 +343,16 @@ pub struct Settings {
     #[serde(default = "default_autoread")]
     pub autoread: bool,
 
+    /// When to save modified buffers automatically: `"off"` (default),
+    /// `"focus"` when the editor window loses focus, or `"interval"` once
+    /// typing has paused for `autosave_interval` milliseconds.
+    #[serde(default = "default_autosave")]
+    pub autosave: String,
+
+    /// Idle milliseconds before `autosave = "interval"` saves (default 1000).
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval: u32,
+
     /// Open new horizontal splits below the current window (default false).
     #[serde(default)]
     pub splitbelow: bool,
@@ -213,11 +380,17 @@ pub struct Settings {
     extension_registry_url: String,
 
     /// Name of the active colour scheme. Built-in options: "onedark" (default),
-    /// "gruvbox-dark", "tokyo-night", "solarized-dark", "vscode-dark", "vscode-light".
+    /// "gruvbox-dark", "tokyo-night", "solarized-dark", "vscode-dark", "vscode-light",
+    /// "high-contrast".
     /// Select with `:colorscheme <name>`.
     #[serde(default = "default_colorscheme")]
     pub colorscheme: String,
 
+    /// `"dark"` (default) or `"light"`, as in Vim's `:set background`.
+    /// Switching it swaps the default colour scheme for its counterpart.
+    #[serde(default = "default_background")]
+    pub background: String,
+
     // ── AI Assistant ──────────────────────────────────────────────────────────
     /// AI provider: "anthropic" (default), "openai", or "ollama".
     #[serde(default = "default_ai_provider")]
@@ -250,20 +423,70 @@ pub struct Settings {
     /// Sort explorer entries case-insensitively (default: true).
     #[serde(default = "default_true")]
     pub explorer_sort_case_insensitive: bool,
+    /// Glob patterns (gitignore syntax, e.g. `target/`, `*.o`) for entries
+    /// the explorer hides, matched against each entry's name.
+    #[serde(default)]
+    pub explorer_ignore: Vec<String>,
+    /// Hide entries ignored by `.gitignore` files in the explorer (default: false).
+    #[serde(default)]
+    pub respect_gitignore: bool,
 
     // ── Swap files ────────────────────────────────────────────────────────────
     /// Enable swap file crash recovery (default: true).
     #[serde(default = "default_swap_file")]
     pub swap_file: bool,
 
+    /// Keep undo history across sessions in `~/.local/share/vimcode/undo/`
+    /// (like Vim's `undofile`). Default: false.
+    #[serde(default)]
+    pub persistent_undo: bool,
+
+    /// Keep the previous contents of a file as `file~` after saving
+    /// (like Vim's `backup`). Default: false.
+    #[serde(default)]
+    pub backup: bool,
+
+    /// Copy a file to `file~` while it is being written and delete the copy
+    /// once the write succeeds (like Vim's `writebackup`). Default: false.
+    #[serde(default)]
+    pub writebackup: bool,
+
     /// Milliseconds between swap file writes for dirty buffers (default: 4000).
     #[serde(default = "default_updatetime")]
     pub updatetime: u32,
 
+    /// Files of at least this many megabytes open in large-file mode:
+    /// read-only, no syntax highlighting, LSP or git diff (default: 50; 0 = off).
+    #[serde(default = "default_large_file_mb")]
+    pub large_file_mb: u32,
+
     /// Show breadcrumbs bar (file path + symbol hierarchy) below the tab bar.
     #[serde(default = "default_breadcrumbs")]
     pub breadcrumbs: bool,
 
+    /// Show a winbar row at the top of each window with the LSP symbols
+    /// enclosing the cursor (`module > impl Foo > fn bar`).
+    #[serde(default)]
+    pub winbar: bool,
+
+    /// Sticky scroll: pin the headers of the LSP symbols enclosing the top of
+    /// each window (up to three) over its first rows.
+    #[serde(default)]
+    pub sticky_scroll: bool,
+
+    /// Minimap: a code overview strip with a draggable viewport on the right
+    /// of the active window (GUI backends only).
+    #[serde(default)]
+    pub minimap: bool,
+
+    /// Animate wheel and page scrolls instead of jumping (GUI backends only).
+    #[serde(default)]
+    pub smooth_scroll: bool,
+
+    /// Duration (ms) of a smooth-scroll animation.
+    #[serde(default = "default_smooth_scroll_duration")]
+    pub smooth_scroll_duration: u32,
+
     /// Hide the tab bar when an editor group has only one tab.
     /// Reclaims the row for editor content. Tab bar reappears when a second tab is opened.
     #[serde(default)]
@@ -282,10 +505,73 @@ pub struct Settings {
     #[serde(default = "default_match_brackets")]
     pub match_brackets: bool,
 
+    /// Highlight other occurrences of the identifier under the cursor.
+    #[serde(default = "default_true")]
+    pub highlight_word_under_cursor: bool,
+
+    /// Ctrl-N/Ctrl-P in Insert mode also collect words from other open buffers.
+    #[serde(default)]
+    pub complete_all_buffers: bool,
+
+    /// Show the most severe LSP diagnostic's message at the end of its line.
+    #[serde(default = "default_true")]
+    pub diagnostic_virtual_text: bool,
+
     /// Auto-close brackets and quotes in Insert mode.
     #[serde(default = "default_auto_pairs")]
     pub auto_pairs: bool,
 
+    /// Paint trailing spaces/tabs with `theme.trailing_whitespace_bg`.
+    #[serde(default)]
+    pub highlight_trailing_whitespace: bool,
+
+    /// Strip trailing whitespace from every line before saving.
+    #[serde(default)]
+    pub strip_whitespace_on_save: bool,
+
+    /// End every saved file with exactly one line break (Vim's
+    /// `fixendofline`).  Off keeps whatever the file had when it was loaded.
+    /// An `.editorconfig` `insert_final_newline` rule takes precedence.
+    #[serde(default)]
+    pub insert_final_newline: bool,
+
+    /// Comment template for `gc` toggling, Vim-style (`"// %s"`, `"<!-- %s -->"`).
+    /// Empty uses the built-in per-language table.  `:set commentstring`
+    /// overrides it for the current buffer only.
+    #[serde(default)]
+    pub commentstring: String,
+
+    /// Show whitespace glyphs from `listchars` (`:set list`).
+    #[serde(default)]
+    pub list: bool,
+
+    /// Glyphs drawn by `:set list`, Vim-style (`"tab:→ ,trail:·,eol:¬"`).
+    #[serde(default = "default_listchars")]
+    pub listchars: String,
+
+    /// Cursor shape, colour and blink per mode
+    /// (`"normal:block,insert:bar-blink,visual:block,replace:underline"`).
+    #[serde(default = "default_cursorstyle")]
+    pub cursorstyle: String,
+
+    /// Milliseconds the cursor stays on (and off) when blinking (GUI backends).
+    #[serde(default = "default_cursor_blink_interval")]
+    pub cursor_blink_interval: u32,
+
+    /// Status line format string, Vim-style (`"%f %m%=%l:%c %p%%"`).
+    /// `%=` splits left and right halves.  Empty keeps the built-in layout.
+    #[serde(default)]
+    pub statusline: String,
+
+    /// Build command run by `:make` (extra `:make` arguments are appended).
+    #[serde(default = "default_makeprg")]
+    pub makeprg: String,
+
+    /// Vim-style patterns (`%f:%l:%c: %m`, comma-separated) for `:make`
+    /// output that isn't a rustc diagnostic.
+    #[serde(default = "default_errorformat")]
+    pub errorformat: String,
+
     /// Mouse dwell delay (ms) before auto-showing hover popups. 0 = disabled.
     #[serde(default = "default_hover_delay")]
     pub hover_delay: u32,
@@ -299,6 +585,21 @@ pub struct Settings {
     /// "page_down" preserves traditional Vim Ctrl+F page-down behavior.
     #[serde(default = "default_ctrl_f_action")]
     pub ctrl_f_action: String,
+
+    /// Keys the project `.vimcode.json` sets on top of the global settings.
+    /// `.editorconfig` properties never override these.
+    #[serde(skip)]
+    pub project_keys: Vec<String>,
+
+    /// For each key the project file sets: its global value and the value
+    /// it was merged to.  [`save`](Self::save) writes the global value back
+    /// for keys that still hold the project's.
+    #[serde(skip)]
+    project_values: Vec<(String, serde_json::Value, serde_json::Value)>,
+
+    /// `cursorstyle` and its parse, so drawing a frame doesn't re-parse it.
+    #[serde(skip)]
+    cursor_styles_cache: std::cell::RefCell<Option<(String, CursorStyles)>>,
 }
 
 fn default_ctrl_f_action() -> String {
@@ -317,10 +618,38 @@ fn default_auto_pairs() -> bool {
     true
 }
 
+fn default_listchars() -> String {
+    "tab:→ ,trail:·,eol:¬".to_string()
+}
+
+fn default_cursorstyle() -> String {
+    "normal:block,insert:bar-blink,visual:block,replace:underline".to_string()
+}
+
+fn default_cursor_blink_interval() -> u32 {
+    530
+}
+
+fn default_timeoutlen() -> u32 {
+    1000
+}
+
+fn default_which_key() -> bool {
+    true
+}
+
 fn default_hover_delay() -> u32 {
     300
 }
 
+fn default_smooth_scroll_duration() -> u32 {
+    80
+}
+
+fn default_large_file_mb() -> u32 {
+    50
+}
+
 fn default_use_nerd_fonts() -> bool {
     // On Windows, terminal fonts (Consolas, Cascadia Mono) don't include Nerd
     // Font glyphs by default. Use ASCII fallback icons instead.  Users who
@@ -378,6 +707,14 @@ fn default_spelllang() -> String {
     "en_US".to_string()
 }
 
+fn default_makeprg() -> String {
+    "cargo build".to_string()
+}
+
+fn default_errorformat() -> String {
+    "%f:%l:%c: %m,%f:%l: %m".to_string()
+}
+
 fn default_plugins_enabled() -> bool {
     true
 }
@@ -402,6 +739,17 @@ fn default_autoread() -> bool {
     true
 }
 
+/// Accepted values of the `autosave` setting.
+pub const AUTOSAVE_MODES: [&str; 3] = ["off", "focus", "interval"];
+
+fn default_autosave() -> String {
+    "off".to_string()
+}
+
+fn default_autosave_interval() -> u32 {
+    1000
+}
+
 fn default_terminal_scrollback_lines() -> usize {
     5000
 }
@@ -418,6 +766,10 @@ fn default_colorscheme() -> String {
     "onedark".to_string()
 }
 
+fn default_background() -> String {
+    "dark".to_string()
+}
+
 fn default_ai_provider() -> String {
     "anthropic".to_string()
 }
@@ -439,6 +791,9 @@ fn ek_rename() -> String {
 fn ek_move_file() -> String {
     "M".to_string()
 }
+fn ek_toggle_hidden() -> String {
+    ".".to_string()
+}
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExplorerAction {
     NewFile,
@@ -446,6 +801,7 @@ pub enum ExplorerAction {
     Delete,
     Rename,
     MoveFile,
+    ToggleHidden,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -460,6 +816,8 @@ pub struct ExplorerKeys {
     pub rename: String,
     #[serde(default = "ek_move_file")]
     pub move_file: String,
+    #[serde(default = "ek_toggle_hidden")]
+    pub toggle_hidden: String,
 }
 
 impl Default for ExplorerKeys {
@@ -470,6 +828,7 @@ impl Default for ExplorerKeys {
             delete: ek_delete(),
             rename: ek_rename(),
             move_file: ek_move_file(),
+            toggle_hidden: ek_toggle_hidden(),
         }
     }
 }
@@ -489,6 +848,8 @@ impl ExplorerKeys {
             Some(ExplorerAction::Rename)
         } else if s == self.move_file {
             Some(ExplorerAction::MoveFile)
+        } else if s == self.toggle_hidden {
+            Some(ExplorerAction::ToggleHidden)
         } else {
             None
         }
@@ -698,6 +1059,7 @@ impl Default for Settings {
             shift_width: default_shift_width(),
             lsp_enabled: default_lsp_enabled(),
             format_on_save: false,
+            inlay_hints: false,
             lsp_servers: Vec::new(),
             language_map: std::collections::HashMap::new(),
             terminal_scrollback_lines: default_terminal_scrollback_lines(),
@@ -712,14 +1074,21 @@ impl Default for Settings {
             plugins_enabled: default_plugins_enabled(),
             disabled_plugins: Vec::new(),
             keymaps: Vec::new(),
+            abbreviations: std::collections::BTreeMap::new(),
+            timeoutlen: default_timeoutlen(),
+            which_key: default_which_key(),
             hlsearch: default_hlsearch(),
             ignorecase: false,
             smartcase: false,
+            wrapscan: true,
+            fuzzy_remember_query: false,
             scrolloff: 0,
             cursorline: default_cursorline(),
             window_status_line: default_window_status_line(),
             status_line_above_terminal: default_status_line_above_terminal(),
             autoread: default_autoread(),
+            autosave: default_autosave(),
+            autosave_interval: default_autosave_interval(),
             splitbelow: false,
             splitright: false,
             colorcolumn: String::new(),
@@ -727,6 +1096,7 @@ impl Default for Settings {
             extension_registries: default_extension_registries(),
             extension_registry_url: String::new(),
             colorscheme: default_colorscheme(),
+            background: default_background(),
             ai_provider: default_ai_provider(),
             ai_api_key: String::new(),
             ai_model: String::new(),
@@ -734,17 +1104,45 @@ impl Default for Settings {
             ai_completions: false,
             show_hidden_files: false,
             explorer_sort_case_insensitive: true,
+            explorer_ignore: Vec::new(),
+            respect_gitignore: false,
             swap_file: default_swap_file(),
+            backup: false,
+            writebackup: false,
+            persistent_undo: false,
             updatetime: default_updatetime(),
+            large_file_mb: default_large_file_mb(),
             breadcrumbs: default_breadcrumbs(),
+            winbar: false,
+            sticky_scroll: false,
+            minimap: false,
+            smooth_scroll: false,
+            smooth_scroll_duration: default_smooth_scroll_duration(),
             hide_single_tab: false,
             autohide_panels: false,
             indent_guides: default_indent_guides(),
             match_brackets: default_match_brackets(),
+            highlight_word_under_cursor: true,
+            complete_all_buffers: false,
+            diagnostic_virtual_text: true,
             auto_pairs: default_auto_pairs(),
+            highlight_trailing_whitespace: false,
+            strip_whitespace_on_save: false,
+            insert_final_newline: false,
+            commentstring: String::new(),
+            list: false,
+            listchars: default_listchars(),
+            cursorstyle: default_cursorstyle(),
+            cursor_blink_interval: default_cursor_blink_interval(),
+            statusline: String::new(),
+            makeprg: default_makeprg(),
+            errorformat: default_errorformat(),
             hover_delay: default_hover_delay(),
             use_nerd_fonts: default_use_nerd_fonts(),
             ctrl_f_action: default_ctrl_f_action(),
+            project_keys: Vec::new(),
+            project_values: Vec::new(),
+            cursor_styles_cache: Default::default(),
         }
     }
 }
@@ -868,6 +1266,52 @@ impl Settings {
         Ok(arg.to_string())
     }
 
+    /// Parsed `listchars`; an invalid string falls back to the default glyphs.
+    pub fn list_chars(&self) -> ListChars {
+        ListChars::parse(&self.listchars)
+            .unwrap_or_else(|_| ListChars::parse(&default_listchars()).unwrap_or_default())
+    }
+
+    /// Set `background` to `"dark"` or `"light"`.  A built-in default scheme
+    /// for the other background (onedark / vscode-dark vs vscode-light) is
+    /// replaced by the default for the new one; any other scheme is kept.
+    pub fn set_background(&mut self, value: &str) -> Result<(), String> {
+        let counterpart = match (value, self.colorscheme.as_str()) {
+            ("light", "onedark" | "vscode-dark") => Some("vscode-light"),
+            ("dark", "vscode-light") => Some("onedark"),
+            ("light" | "dark", _) => None,
+            _ => return Err(format!("Invalid background '{value}' (dark|light)")),
+        };
+        self.background = value.to_string();
+        if let Some(scheme) = counterpart {
+            self.colorscheme = scheme.to_string();
+        }
+        Ok(())
+    }
+
+    /// Set `autosave`, rejecting anything but the known modes.
+    pub fn set_autosave(&mut self, value: &str) -> Result<(), String> {
+        if !AUTOSAVE_MODES.contains(&value) {
+            return Err(format!("Invalid autosave '{value}' (off|focus|interval)"));
+        }
+        self.autosave = value.to_string();
+        Ok(())
+    }
+
+    /// Parsed `cursorstyle`; an invalid string falls back to the default looks.
+    /// The parse is cached until `cursorstyle` changes.
+    pub fn cursor_styles(&self) -> CursorStyles {
+        let mut cache = self.cursor_styles_cache.borrow_mut();
+        match cache.as_ref() {
+            Some((style, styles)) if *style == self.cursorstyle => styles.clone(),
+            _ => {
+                let styles = CursorStyles::parse(&self.cursorstyle).unwrap_or_default();
+                *cache = Some((self.cursorstyle.clone(), styles.clone()));
+                styles
+            }
+        }
+    }
+
     /// Parse the `colorcolumn` string into a sorted, deduplicated list of column numbers.
     /// Supports: `"80"`, `"80,120"`, `"+1"` (textwidth + 1), `"-2"` (textwidth - 2).
     pub fn colorcolumn_positions(&self) -> Vec<usize> {
@@ -1018,6 +1462,8 @@ impl Settings {
             "hlsearch" | "hls" => self.hlsearch = enable,
             "ignorecase" | "ic" => self.ignorecase = enable,
             "smartcase" | "scs" => self.smartcase = enable,
+            "wrapscan" | "ws" => self.wrapscan = enable,
+            "fuzzyrememberquery" | "frq" => self.fuzzy_remember_query = enable,
             "cursorline" | "cul" => self.cursorline = enable,
             "windowstatusline" | "wsl" => self.window_status_line = enable,
             "statuslineaboveterminal" | "slat" => self.status_line_above_terminal = enable,
@@ -1026,15 +1472,32 @@ impl Settings {
             "splitright" | "spr" => self.splitright = enable,
             "ai_completions" => self.ai_completions = enable,
             "formatonsave" | "fos" => self.format_on_save = enable,
+            "inlayhints" | "ih" => self.inlay_hints = enable,
             "showhiddenfiles" | "shf" => self.show_hidden_files = enable,
             "explorersortcaseinsensitive" | "esci" => self.explorer_sort_case_insensitive = enable,
+            "respectgitignore" | "rgi" => self.respect_gitignore = enable,
             "swapfile" => self.swap_file = enable,
+            "undofile" | "udf" => self.persistent_undo = enable,
+            "backup" | "bk" => self.backup = enable,
+            "writebackup" | "wb" => self.writebackup = enable,
             "breadcrumbs" => self.breadcrumbs = enable,
+            "winbar" | "wbr" => self.winbar = enable,
+            "stickyscroll" => self.sticky_scroll = enable,
+            "minimap" => self.minimap = enable,
+            "smoothscroll" => self.smooth_scroll = enable,
             "hidesingletab" | "hst" => self.hide_single_tab = enable,
             "autohidepanels" => self.autohide_panels = enable,
             "indentguides" => self.indent_guides = enable,
             "matchbrackets" => self.match_brackets = enable,
+            "highlightword" | "hlw" => self.highlight_word_under_cursor = enable,
+            "completeallbuffers" => self.complete_all_buffers = enable,
+            "diagnosticvirtualtext" | "dvt" => self.diagnostic_virtual_text = enable,
             "autopairs" => self.auto_pairs = enable,
+            "highlighttrailingwhitespace" | "htw" => self.highlight_trailing_whitespace = enable,
+            "stripwhitespaceonsave" | "swos" => self.strip_whitespace_on_save = enable,
+            "fixendofline" | "fixeol" => self.insert_final_newline = enable,
+            "list" => self.list = enable,
+            "whichkey" | "wk" => self.which_key = enable,
             "nerdfonts" | "nf" => {
                 self.use_nerd_fonts = enable;
                 crate::icons::set_nerd_fonts(enable);
@@ -1061,6 +1524,7 @@ impl Settings {
                     .map_err(|_| format!("Invalid value for {name}: '{value}'"))?;
                 self.shift_width = n;
             }
+            "background" | "bg" => self.set_background(value)?,
             "mode" | "editor_mode" => match value {
                 "vim" => self.editor_mode = EditorMode::Vim,
                 "vscode" => self.editor_mode = EditorMode::Vscode,
@@ -1073,8 +1537,47 @@ impl Settings {
                 self.scrolloff = n;
             }
             "colorcolumn" | "cc" => {
+                let valid = value.split(',').map(str::trim).all(|part| {
+                    let digits = part.strip_prefix(['+', '-']).unwrap_or(part);
+                    part.is_empty() || digits.parse::<usize>().is_ok()
+                });
+                if !valid {
+                    return Err(format!("Invalid value for {name}: '{value}'"));
+                }
                 self.colorcolumn = value.to_string();
             }
+            "commentstring" | "cms" => {
+                // Accept Vim's escaped spaces: `:set cms=//\ %s`
+                let value = value.replace("\\ ", " ");
+                if !value.is_empty() && !value.contains("%s") {
+                    return Err("commentstring must contain %s".to_string());
+                }
+                self.commentstring = value;
+            }
+            "listchars" | "lcs" => {
+                // Accept Vim's escaped spaces: `:set lcs=tab:>\ ,trail:-`
+                let value = value.replace("\\ ", " ");
+                ListChars::parse(&value)?;
+                self.listchars = value;
+            }
+            "cursorstyle" => {
+                CursorStyles::parse(value)?;
+                self.cursorstyle = value.to_string();
+            }
+            "cursorblinkinterval" => {
+                self.cursor_blink_interval = parse_in_range("cursor_blink_interval", name, value)?;
+            }
+            "makeprg" | "mp" => {
+                // Accept Vim's escaped spaces: `:set mp=cargo\ test`
+                self.makeprg = value.replace("\\ ", " ");
+            }
+            "errorformat" | "efm" => {
+                self.errorformat = value.replace("\\ ", " ");
+            }
+            "statusline" | "stl" => {
+                // Accept Vim's escaped spaces: `:set stl=%f\ %m`
+                self.statusline = value.replace("\\ ", " ");
+            }
             "textwidth" | "tw" => {
                 let n: usize = value
                     .parse()
@@ -1094,12 +1597,33 @@ impl Settings {
                     .filter(|s| !s.is_empty())
                     .collect();
             }
+            "explorerignore" => self.explorer_ignore = split_list(value),
             "hover_delay" | "hd" => {
                 let n: u32 = value
                     .parse()
                     .map_err(|_| format!("Invalid value for {name}: '{value}'"))?;
                 self.hover_delay = n;
             }
+            "smoothscrollduration" => {
+                self.smooth_scroll_duration =
+                    parse_in_range("smooth_scroll_duration", name, value)?;
+            }
+            "largefile" => {
+                let n: u32 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {name}: '{value}'"))?;
+                self.large_file_mb = n;
+            }
+            "autosave" => self.set_autosave(value)?,
+            "autosaveinterval" | "asi" => {
+                self.autosave_interval = parse_in_range("autosave_interval", name, value)?;
+            }
+            "timeoutlen" | "tm" => {
+                let n: u32 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {name}: '{value}'"))?;
+                self.timeoutlen = n;
+            }
             "font_size" => {
                 let n: i32 = value
                     .parse()
@@ -1160,6 +1684,7 @@ impl Settings {
             } else {
                 "nolsp".to_string()
             }),
+            "background" | "bg" => Ok(format!("background={}", self.background)),
             "mode" | "editor_mode" => Ok(format!(
                 "mode={}",
                 match self.editor_mode {
@@ -1193,6 +1718,16 @@ impl Settings {
             } else {
                 "nosmartcase".to_string()
             }),
+            "wrapscan" | "ws" => Ok(if self.wrapscan {
+                "wrapscan".to_string()
+            } else {
+                "nowrapscan".to_string()
+            }),
+            "fuzzyrememberquery" | "frq" => Ok(if self.fuzzy_remember_query {
+                "fuzzyrememberquery".to_string()
+            } else {
+                "nofuzzyrememberquery".to_string()
+            }),
             "scrolloff" | "so" => Ok(format!("scrolloff={}", self.scrolloff)),
             "cursorline" | "cul" => Ok(if self.cursorline {
                 "cursorline".to_string()
@@ -1220,12 +1755,37 @@ impl Settings {
                 "nosplitright".to_string()
             }),
             "colorcolumn" | "cc" => Ok(format!("colorcolumn={}", self.colorcolumn)),
+            "commentstring" | "cms" => Ok(format!("commentstring={}", self.commentstring)),
+            "listchars" | "lcs" => Ok(format!("listchars={}", self.listchars)),
+            "cursorstyle" => Ok(format!("cursorstyle={}", self.cursorstyle)),
+            "cursorblinkinterval" => Ok(format!(
+                "cursorblinkinterval={}",
+                self.cursor_blink_interval
+            )),
+            "statusline" | "stl" => Ok(format!("statusline={}", self.statusline)),
+            "makeprg" | "mp" => Ok(format!("makeprg={}", self.makeprg)),
+            "errorformat" | "efm" => Ok(format!("errorformat={}", self.errorformat)),
+            "list" => Ok(if self.list {
+                "list".to_string()
+            } else {
+                "nolist".to_string()
+            }),
+            "whichkey" | "wk" => Ok(if self.which_key {
+                "whichkey".to_string()
+            } else {
+                "nowhichkey".to_string()
+            }),
             "textwidth" | "tw" => Ok(format!("textwidth={}", self.textwidth)),
             "formatonsave" | "fos" => Ok(if self.format_on_save {
                 "formatonsave".to_string()
             } else {
                 "noformatonsave".to_string()
             }),
+            "inlayhints" | "ih" => Ok(if self.inlay_hints {
+                "inlayhints".to_string()
+            } else {
+                "noinlayhints".to_string()
+            }),
             "showhiddenfiles" | "shf" => Ok(if self.show_hidden_files {
                 "showhiddenfiles".to_string()
             } else {
@@ -1236,17 +1796,67 @@ impl Settings {
             } else {
                 "noexplorersortcaseinsensitive".to_string()
             }),
+            "respectgitignore" | "rgi" => Ok(if self.respect_gitignore {
+                "respectgitignore".to_string()
+            } else {
+                "norespectgitignore".to_string()
+            }),
+            "explorerignore" => Ok(format!("explorerignore={}", self.explorer_ignore.join(","))),
             "swapfile" => Ok(if self.swap_file {
                 "swapfile".to_string()
             } else {
                 "noswapfile".to_string()
             }),
+            "undofile" | "udf" => Ok(if self.persistent_undo {
+                "undofile".to_string()
+            } else {
+                "noundofile".to_string()
+            }),
+            "backup" | "bk" => Ok(if self.backup {
+                "backup".to_string()
+            } else {
+                "nobackup".to_string()
+            }),
+            "writebackup" | "wb" => Ok(if self.writebackup {
+                "writebackup".to_string()
+            } else {
+                "nowritebackup".to_string()
+            }),
             "updatetime" | "ut" => Ok(format!("updatetime={}", self.updatetime)),
             "breadcrumbs" => Ok(if self.breadcrumbs {
                 "breadcrumbs".to_string()
             } else {
                 "nobreadcrumbs".to_string()
             }),
+            "winbar" | "wbr" => Ok(if self.winbar {
+                "winbar".to_string()
+            } else {
+                "nowinbar".to_string()
+            }),
+            "stickyscroll" => Ok(if self.sticky_scroll {
+                "stickyscroll".to_string()
+            } else {
+                "nostickyscroll".to_string()
+            }),
+            "minimap" => Ok(if self.minimap {
+                "minimap".to_string()
+            } else {
+                "nominimap".to_string()
+            }),
+            "smoothscroll" => Ok(if self.smooth_scroll {
+                "smoothscroll".to_string()
+            } else {
+                "nosmoothscroll".to_string()
+            }),
+            "smoothscrollduration" => Ok(format!(
+                "smoothscrollduration={}",
+                self.smooth_scroll_duration
+            )),
+            "largefile" => Ok(format!("largefile={}", self.large_file_mb)),
+            "autosave" => Ok(format!("autosave={}", self.autosave)),
+            "autosaveinterval" | "asi" => {
+                Ok(format!("autosaveinterval={}", self.autosave_interval))
+            }
             "hidesingletab" | "hst" => Ok(if self.hide_single_tab {
                 "hidesingletab".to_string()
             } else {
@@ -1267,16 +1877,47 @@ impl Settings {
             } else {
                 "nomatchbrackets".to_string()
             }),
+            "highlightword" | "hlw" => Ok(if self.highlight_word_under_cursor {
+                "highlightword".to_string()
+            } else {
+                "nohighlightword".to_string()
+            }),
+            "completeallbuffers" => Ok(if self.complete_all_buffers {
+                "completeallbuffers".to_string()
+            } else {
+                "nocompleteallbuffers".to_string()
+            }),
+            "diagnosticvirtualtext" | "dvt" => Ok(if self.diagnostic_virtual_text {
+                "diagnosticvirtualtext".to_string()
+            } else {
+                "nodiagnosticvirtualtext".to_string()
+            }),
             "autopairs" => Ok(if self.auto_pairs {
                 "autopairs".to_string()
             } else {
                 "noautopairs".to_string()
             }),
+            "highlighttrailingwhitespace" | "htw" => Ok(if self.highlight_trailing_whitespace {
+                "highlighttrailingwhitespace".to_string()
+            } else {
+                "nohighlighttrailingwhitespace".to_string()
+            }),
+            "stripwhitespaceonsave" | "swos" => Ok(if self.strip_whitespace_on_save {
+                "stripwhitespaceonsave".to_string()
+            } else {
+                "nostripwhitespaceonsave".to_string()
+            }),
+            "fixendofline" | "fixeol" => Ok(if self.insert_final_newline {
+                "fixendofline".to_string()
+            } else {
+                "nofixendofline".to_string()
+            }),
             "extension_registries" => Ok(format!(
                 "extension_registries={}",
                 self.extension_registries.join(",")
             )),
             "hover_delay" | "hd" => Ok(format!("hover_delay={}", self.hover_delay)),
+            "timeoutlen" | "tm" => Ok(format!("timeoutlen={}", self.timeoutlen)),
             "nerdfonts" | "nf" => Ok(if self.use_nerd_fonts {
                 "nerdfonts".to_string()
             } else {
@@ -1306,22 +1947,89 @@ impl Settings {
             fs::create_dir_all(parent)?;
         }
 
-        let contents = serde_json::to_string_pretty(self)?;
-        fs::write(&path, contents)?;
+        fs::write(&path, self.to_json()?)?;
 
         Ok(())
     }
 
+    /// The JSON [`save`](Self::save) writes.  Keys the project `.vimcode.json`
+    /// overrides keep their global value unless changed since the merge, so
+    /// the project layer never ends up in the global settings file.
+    fn to_json(&self) -> serde_json::Result<String> {
+        if self.project_values.is_empty() {
+            return serde_json::to_string_pretty(self);
+        }
+        let mut value = serde_json::to_value(self)?;
+        if let Some(obj) = value.as_object_mut() {
+            for (key, global, merged) in &self.project_values {
+                if obj.get(key) == Some(merged) {
+                    obj.insert(key.clone(), global.clone());
+                }
+            }
+        }
+        serde_json::to_string_pretty(&value)
+    }
+
     pub fn settings_file_path() -> PathBuf {
         super::paths::vimcode_config_dir().join("settings.json")
     }
 
+    /// Find the project settings file for `dir`: the nearest `.vimcode.json`
+    /// in `dir` or its parents, stopping at the git root.
+    pub fn find_project_settings(dir: &Path) -> Option<PathBuf> {
+        for ancestor in dir.ancestors() {
+            let candidate = ancestor.join(PROJECT_SETTINGS_FILE);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+            if ancestor.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Return these settings with a project `.vimcode.json` merged on top.
+    ///
+    /// Keys in the project file win; nested objects are merged key by key and
+    /// anything else (arrays included) is replaced.  Keys the project file
+    /// leaves out keep their value from `self`, so the precedence is
+    /// project > global > defaults.
+    pub fn with_project_overrides(&self, json: &str) -> Result<Self, String> {
+        let overrides: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {e}"))?;
+        if !overrides.is_object() {
+            return Err("expected a JSON object".to_string());
+        }
+        let project_keys: Vec<String> = overrides
+            .as_object()
+            .map(|o| o.keys().cloned().collect())
+            .unwrap_or_default();
+        let global = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        let mut merged = global.clone();
+        merge_json(&mut merged, overrides);
+        let mut settings: Self = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+        settings.migrate_legacy_fields();
+        let merged = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+        settings.project_values = project_keys
+            .iter()
+            .filter_map(|key| {
+                let global = global.get(key)?.clone();
+                let merged = merged.get(key)?.clone();
+                Some((key.clone(), global, merged))
+            })
+            .collect();
+        settings.project_keys = project_keys;
+        Ok(settings)
+    }
+
     /// Get the current value of a setting as a string.
     /// Returns an empty string for unknown keys.
     /// Used by the settings UI form to populate widget initial values.
     pub fn get_value_str(&self, key: &str) -> String {
         match key {
             "colorscheme" => self.colorscheme.clone(),
+            "background" => self.background.clone(),
             "font_family" => self.font_family.clone(),
             "font_size" => self.font_size.to_string(),
             "line_numbers" => match self.line_numbers {
@@ -1346,6 +2054,10 @@ impl Settings {
             "hlsearch" => self.hlsearch.to_string(),
             "ignorecase" => self.ignorecase.to_string(),
             "smartcase" => self.smartcase.to_string(),
+            "wrapscan" => self.wrapscan.to_string(),
+            "fuzzy_remember_query" | "fuzzyrememberquery" | "frq" => {
+                self.fuzzy_remember_query.to_string()
+            }
             "incremental_search" => self.incremental_search.to_string(),
             "editor_mode" => match self.editor_mode {
                 EditorMode::Vim => "vim".to_string(),
@@ -1357,6 +2069,7 @@ impl Settings {
             "splitright" => self.splitright.to_string(),
             "lsp_enabled" => self.lsp_enabled.to_string(),
             "format_on_save" => self.format_on_save.to_string(),
+            "inlay_hints" => self.inlay_hints.to_string(),
             "terminal_scrollback_lines" => self.terminal_scrollback_lines.to_string(),
             "plugins_enabled" => self.plugins_enabled.to_string(),
             "ai_provider" => self.ai_provider.clone(),
@@ -1368,15 +2081,58 @@ impl Settings {
             "explorersortcaseinsensitive" | "esci" | "explorer_sort_case_insensitive" => {
                 self.explorer_sort_case_insensitive.to_string()
             }
+            "respectgitignore" | "rgi" | "respect_gitignore" => self.respect_gitignore.to_string(),
+            "explorerignore" | "explorer_ignore" => self.explorer_ignore.join(","),
             "swapfile" | "swap_file" => self.swap_file.to_string(),
+            "persistent_undo" | "undofile" | "udf" => self.persistent_undo.to_string(),
+            "backup" => self.backup.to_string(),
+            "writebackup" => self.writebackup.to_string(),
             "updatetime" | "ut" => self.updatetime.to_string(),
+            "large_file_mb" | "largefile" => self.large_file_mb.to_string(),
+            "autosave" => self.autosave.clone(),
+            "autosave_interval" | "autosaveinterval" => self.autosave_interval.to_string(),
             "breadcrumbs" => self.breadcrumbs.to_string(),
+            "winbar" => self.winbar.to_string(),
+            "sticky_scroll" | "stickyscroll" => self.sticky_scroll.to_string(),
+            "minimap" => self.minimap.to_string(),
+            "smooth_scroll" | "smoothscroll" => self.smooth_scroll.to_string(),
+            "smooth_scroll_duration" | "smoothscrollduration" => {
+                self.smooth_scroll_duration.to_string()
+            }
             "hide_single_tab" | "hidesingletab" | "hst" => self.hide_single_tab.to_string(),
             "autohide_panels" | "autohidepanels" => self.autohide_panels.to_string(),
             "indent_guides" | "indentguides" => self.indent_guides.to_string(),
             "match_brackets" | "matchbrackets" => self.match_brackets.to_string(),
+            "highlight_word_under_cursor" | "highlightword" | "hlw" => {
+                self.highlight_word_under_cursor.to_string()
+            }
+            "complete_all_buffers" | "completeallbuffers" => self.complete_all_buffers.to_string(),
+            "diagnostic_virtual_text" | "diagnosticvirtualtext" | "dvt" => {
+                self.diagnostic_virtual_text.to_string()
+            }
             "auto_pairs" | "autopairs" => self.auto_pairs.to_string(),
+            "commentstring" => self.commentstring.clone(),
+            "list" => self.list.to_string(),
+            "which_key" | "whichkey" | "wk" => self.which_key.to_string(),
+            "listchars" => self.listchars.clone(),
+            "cursorstyle" => self.cursorstyle.clone(),
+            "cursor_blink_interval" | "cursorblinkinterval" => {
+                self.cursor_blink_interval.to_string()
+            }
+            "statusline" => self.statusline.clone(),
+            "makeprg" => self.makeprg.clone(),
+            "errorformat" => self.errorformat.clone(),
+            "highlight_trailing_whitespace" | "highlighttrailingwhitespace" | "htw" => {
+                self.highlight_trailing_whitespace.to_string()
+            }
+            "strip_whitespace_on_save" | "stripwhitespaceonsave" | "swos" => {
+                self.strip_whitespace_on_save.to_string()
+            }
+            "insert_final_newline" | "fixendofline" | "fixeol" => {
+                self.insert_final_newline.to_string()
+            }
             "hover_delay" => self.hover_delay.to_string(),
+            "timeoutlen" => self.timeoutlen.to_string(),
             "use_nerd_fonts" | "nerdfonts" | "nf" => self.use_nerd_fonts.to_string(),
             "ctrl_f_action" => self.ctrl_f_action.clone(),
             "extension_registries" => self.extension_registries.join(", "),
@@ -1390,6 +2146,7 @@ impl Settings {
     pub fn set_value_str(&mut self, key: &str, value: &str) -> Result<(), String> {
         match key {
             "colorscheme" => self.colorscheme = value.to_string(),
+            "background" => self.set_background(value)?,
             "font_family" => self.font_family = value.to_string(),
             "font_size" => {
                 self.font_size = value
@@ -1437,6 +2194,10 @@ impl Settings {
             "hlsearch" => self.hlsearch = value == "true",
             "ignorecase" => self.ignorecase = value == "true",
             "smartcase" => self.smartcase = value == "true",
+            "wrapscan" => self.wrapscan = value == "true",
+            "fuzzy_remember_query" | "fuzzyrememberquery" | "frq" => {
+                self.fuzzy_remember_query = value == "true"
+            }
             "incremental_search" => self.incremental_search = value == "true",
             "editor_mode" => {
                 self.editor_mode = match value {
@@ -1451,6 +2212,7 @@ impl Settings {
             "splitright" => self.splitright = value == "true",
             "lsp_enabled" => self.lsp_enabled = value == "true",
             "format_on_save" => self.format_on_save = value == "true",
+            "inlay_hints" => self.inlay_hints = value == "true",
             "terminal_scrollback_lines" => {
                 self.terminal_scrollback_lines = value
                     .parse()
@@ -1471,23 +2233,95 @@ impl Settings {
             "explorersortcaseinsensitive" | "esci" | "explorer_sort_case_insensitive" => {
                 self.explorer_sort_case_insensitive = value == "true"
             }
+            "respectgitignore" | "rgi" | "respect_gitignore" => {
+                self.respect_gitignore = value == "true"
+            }
+            "explorerignore" | "explorer_ignore" => {
+                self.explorer_ignore = split_list(value);
+            }
             "swapfile" | "swap_file" => self.swap_file = value == "true",
+            "persistent_undo" | "undofile" | "udf" => self.persistent_undo = value == "true",
+            "backup" => self.backup = value == "true",
+            "writebackup" => self.writebackup = value == "true",
             "updatetime" | "ut" => {
                 self.updatetime = value
                     .parse()
                     .map_err(|_| format!("Invalid updatetime: {value}"))?;
             }
+            "large_file_mb" | "largefile" => {
+                self.large_file_mb = value
+                    .parse()
+                    .map_err(|_| format!("Invalid large_file_mb: {value}"))?;
+            }
+            "autosave" => self.set_autosave(value)?,
+            "autosave_interval" | "autosaveinterval" => {
+                self.autosave_interval = parse_in_range("autosave_interval", key, value)?;
+            }
             "breadcrumbs" => self.breadcrumbs = value == "true",
+            "winbar" => self.winbar = value == "true",
+            "sticky_scroll" | "stickyscroll" => self.sticky_scroll = value == "true",
+            "minimap" => self.minimap = value == "true",
+            "smooth_scroll" | "smoothscroll" => self.smooth_scroll = value == "true",
+            "smooth_scroll_duration" | "smoothscrollduration" => {
+                self.smooth_scroll_duration = parse_in_range("smooth_scroll_duration", key, value)?;
+            }
             "hide_single_tab" | "hidesingletab" | "hst" => self.hide_single_tab = value == "true",
             "autohide_panels" | "autohidepanels" => self.autohide_panels = value == "true",
             "indent_guides" | "indentguides" => self.indent_guides = value == "true",
             "match_brackets" | "matchbrackets" => self.match_brackets = value == "true",
+            "highlight_word_under_cursor" | "highlightword" | "hlw" => {
+                self.highlight_word_under_cursor = value == "true"
+            }
+            "complete_all_buffers" | "completeallbuffers" => {
+                self.complete_all_buffers = value == "true"
+            }
+            "diagnostic_virtual_text" | "diagnosticvirtualtext" | "dvt" => {
+                self.diagnostic_virtual_text = value == "true"
+            }
             "auto_pairs" | "autopairs" => self.auto_pairs = value == "true",
+            "commentstring" => {
+                if !value.is_empty() && !value.contains("%s") {
+                    return Err("commentstring must contain %s".to_string());
+                }
+                self.commentstring = value.to_string();
+            }
+            "list" => self.list = value == "true",
+            "which_key" | "whichkey" | "wk" => self.which_key = value == "true",
+            "listchars" => {
+                if ListChars::parse(value).is_ok() {
+                    self.listchars = value.to_string();
+                }
+            }
+            "cursorstyle" => {
+                if CursorStyles::parse(value).is_ok() {
+                    self.cursorstyle = value.to_string();
+                }
+            }
+            "cursor_blink_interval" | "cursorblinkinterval" => {
+                self.cursor_blink_interval = parse_in_range("cursor_blink_interval", key, value)?;
+            }
+            "statusline" => self.statusline = value.to_string(),
+            "makeprg" => self.makeprg = value.to_string(),
+            "errorformat" => self.errorformat = value.to_string(),
+            "highlight_trailing_whitespace" | "highlighttrailingwhitespace" | "htw" => {
+                self.highlight_trailing_whitespace = value == "true"
+            }
+            "strip_whitespace_on_save" | "stripwhitespaceonsave" | "swos" => {
+                self.strip_whitespace_on_save = value == "true"
+            }
+            "insert_final_newline" | "fixendofline" | "fixeol" => {
+                self.insert_final_newline = value == "true"
+            }
             "hover_delay" => {
                 self.hover_delay = value
                     .parse()
                     .map_err(|_| format!("Invalid hover_delay: {value}"))?;
             }
+            "timeoutlen" => {
+                self.timeoutlen = value
+                    .parse()
+                    .map_err(|_| format!("Invalid timeoutlen: {value}"))?;
+            }
             "use_nerd_fonts" | "nerdfonts" | "nf" => {
                 self.use_nerd_fonts = value == "true";
                 crate::icons::set_nerd_fonts(self.use_nerd_fonts);
@@ -1561,6 +2395,7 @@ pub fn available_colorschemes() -> Vec<String> {
         "solarized-dark".into(),
         "vscode-dark".into(),
         "vscode-light".into(),
+        "high-contrast".into(),
     ];
     {
         let dir = super::paths::vimcode_config_dir().join("themes");
@@ -1600,6 +2435,13 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Appearance",
         setting_type: SettingType::DynamicEnum(available_colorschemes),
     },
+    SettingDef {
+        key: "background",
+        label: "Background",
+        description: "Dark or light background; switches the default color scheme",
+        category: "Appearance",
+        setting_type: SettingType::Enum(&["dark", "light"]),
+    },
     SettingDef {
         key: "font_family",
         label: "Font Family",
@@ -1656,6 +2498,41 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Appearance",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "winbar",
+        label: "Winbar",
+        description: "Show the LSP symbols around the cursor at the top of each window",
+        category: "Appearance",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "sticky_scroll",
+        label: "Sticky Scroll",
+        description: "Pin the enclosing function/class headers at the top of each window",
+        category: "Appearance",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "minimap",
+        label: "Minimap",
+        description: "Show a code overview strip on the right of the active window (GUI)",
+        category: "Appearance",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "smooth_scroll",
+        label: "Smooth Scrolling",
+        description: "Animate mouse wheel and page scrolls (GUI)",
+        category: "Appearance",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "smooth_scroll_duration",
+        label: "Smooth Scroll Duration",
+        description: "Length of a smooth-scroll animation in milliseconds",
+        category: "Appearance",
+        setting_type: SettingType::Integer { min: 0, max: 1000 },
+    },
     SettingDef {
         key: "hide_single_tab",
         label: "Hide Single Tab",
@@ -1727,6 +2604,27 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Editor",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "persistent_undo",
+        label: "Persistent Undo",
+        description: "Save undo history to disk so it survives closing the file",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "backup",
+        label: "Backup Files",
+        description: "Keep the previous version of a saved file as file~",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "writebackup",
+        label: "Write Backup",
+        description: "Keep file~ while saving and delete it once the write succeeds",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
     SettingDef {
         key: "updatetime",
         label: "Update Time",
@@ -1737,6 +2635,13 @@ pub static SETTING_DEFS: &[SettingDef] = &[
             max: 60000,
         },
     },
+    SettingDef {
+        key: "large_file_mb",
+        label: "Large File Threshold",
+        description: "Open files of at least this many MB read-only without highlighting or LSP (0 = off)",
+        category: "Editor",
+        setting_type: SettingType::Integer { min: 0, max: 100000 },
+    },
     SettingDef {
         key: "spell",
         label: "Spell Check",
@@ -1773,6 +2678,20 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Search",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "wrapscan",
+        label: "Wrap Scan",
+        description: "Searches wrap around the end of the file",
+        category: "Search",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "fuzzy_remember_query",
+        label: "Remember File Picker Query",
+        description: "Reopen the fuzzy file picker with its last query",
+        category: "Search",
+        setting_type: SettingType::Bool,
+    },
     SettingDef {
         key: "incremental_search",
         label: "Incremental Search",
@@ -1802,6 +2721,23 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Workspace",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "autosave",
+        label: "Auto Save",
+        description: "Save modified files when the window loses focus or after a pause in typing",
+        category: "Workspace",
+        setting_type: SettingType::Enum(&AUTOSAVE_MODES),
+    },
+    SettingDef {
+        key: "autosave_interval",
+        label: "Auto Save Delay",
+        description: "Milliseconds without typing before interval auto-save writes files",
+        category: "Workspace",
+        setting_type: SettingType::Integer {
+            min: 100,
+            max: 600000,
+        },
+    },
     SettingDef {
         key: "splitbelow",
         label: "Split Below",
@@ -1823,6 +2759,20 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Workspace",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "explorer_ignore",
+        label: "Explorer Ignore",
+        description: "Comma-separated globs hidden from the file explorer (e.g. target/, *.o)",
+        category: "Workspace",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "respect_gitignore",
+        label: "Respect .gitignore",
+        description: "Hide files and folders ignored by .gitignore in the file explorer",
+        category: "Workspace",
+        setting_type: SettingType::Bool,
+    },
     // ── LSP ──────────────────────────────────────────────────────────────────
     SettingDef {
         key: "lsp_enabled",
@@ -1838,6 +2788,13 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "LSP",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "inlay_hints",
+        label: "Inlay Hints",
+        description: "Show inferred types and parameter names inline (LSP inlay hints)",
+        category: "LSP",
+        setting_type: SettingType::Bool,
+    },
     // ── Terminal ─────────────────────────────────────────────────────────────
     SettingDef {
         key: "terminal_scrollback_lines",
@@ -1864,6 +2821,20 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Plugins",
         setting_type: SettingType::BufferEditor,
     },
+    SettingDef {
+        key: "timeoutlen",
+        label: "Keymap Timeout",
+        description: "Milliseconds to wait for the rest of an ambiguous keymap",
+        category: "Plugins",
+        setting_type: SettingType::Integer { min: 0, max: 10000 },
+    },
+    SettingDef {
+        key: "which_key",
+        label: "Which-Key Popup",
+        description: "Show the keys that can follow a pending <leader>, g, z or keymap prefix",
+        category: "Plugins",
+        setting_type: SettingType::Bool,
+    },
     // ── AI ────────────────────────────────────────────────────────────────────
     SettingDef {
         key: "ai_provider",
@@ -1915,6 +2886,27 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Editor",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "highlight_word_under_cursor",
+        label: "Highlight Word Under Cursor",
+        description: "Highlight other visible occurrences of the identifier under the cursor",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "complete_all_buffers",
+        label: "Complete From All Buffers",
+        description: "Ctrl-N/Ctrl-P word completion also scans other open buffers",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "diagnostic_virtual_text",
+        label: "Diagnostic Virtual Text",
+        description: "Show the most severe diagnostic message at the end of its line",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
     SettingDef {
         key: "auto_pairs",
         label: "Auto Pairs",
@@ -1922,6 +2914,83 @@ pub static SETTING_DEFS: &[SettingDef] = &[
         category: "Editor",
         setting_type: SettingType::Bool,
     },
+    SettingDef {
+        key: "highlight_trailing_whitespace",
+        label: "Highlight Trailing Whitespace",
+        description: "Paint trailing spaces and tabs at the end of lines",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "strip_whitespace_on_save",
+        label: "Strip Whitespace on Save",
+        description: "Remove trailing whitespace from every line when saving",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "insert_final_newline",
+        label: "Insert Final Newline",
+        description: "End saved files with exactly one line break; off keeps each file's own",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "commentstring",
+        label: "Comment String",
+        description: "Comment template for gc (e.g. \"// %s\"); empty = per-language default",
+        category: "Editor",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "list",
+        label: "Show Whitespace",
+        description: "Draw tabs, trailing spaces and line ends with listchars glyphs",
+        category: "Editor",
+        setting_type: SettingType::Bool,
+    },
+    SettingDef {
+        key: "listchars",
+        label: "List Chars",
+        description: "Whitespace glyphs for list (tab:→ ,trail:·,space:·,eol:¬)",
+        category: "Editor",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "cursorstyle",
+        label: "Cursor Style",
+        description: "Cursor per mode: mode:shape-blink-#color (normal:block,insert:bar-blink)",
+        category: "Appearance",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "cursor_blink_interval",
+        label: "Cursor Blink Interval",
+        description: "Milliseconds the cursor stays on/off when blinking (GUI)",
+        category: "Appearance",
+        setting_type: SettingType::Integer { min: 100, max: 5000 },
+    },
+    SettingDef {
+        key: "statusline",
+        label: "Status Line Format",
+        description: "Status line format (%f %m %y %l %c %p %{lsp}, %= right-aligns); empty = built-in",
+        category: "Appearance",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "makeprg",
+        label: "Build Command",
+        description: "Command run by :make (e.g. \"cargo build\", \"make -j8\")",
+        category: "Editor",
+        setting_type: SettingType::StringVal,
+    },
+    SettingDef {
+        key: "errorformat",
+        label: "Error Format",
+        description: "Patterns for :make output (%f file, %l line, %c col, %m message); rustc is built in",
+        category: "Editor",
+        setting_type: SettingType::StringVal,
+    },
     SettingDef {
         key: "hover_delay",
         label: "Hover Delay",
@@ -1966,6 +3035,57 @@ pub fn setting_categories() -> Vec<&'static str> {
     cats
 }
 
+/// Parse a numeric option value, rejecting anything outside the range its
+/// `SETTING_DEFS` entry (`key`) declares.  `name` is used in error messages.
+fn parse_in_range(key: &str, name: &str, value: &str) -> Result<u32, String> {
+    let n: u32 = value
+        .parse()
+        .map_err(|_| format!("Invalid value for {name}: '{value}'"))?;
+    let range = SETTING_DEFS
+        .iter()
+        .find(|d| d.key == key)
+        .and_then(|d| match d.setting_type {
+            SettingType::Integer { min, max } => Some((min, max)),
+            _ => None,
+        });
+    if let Some((min, max)) = range {
+        if i64::from(n) < i64::from(min) || i64::from(n) > i64::from(max) {
+            return Err(format!("{name} must be between {min} and {max}"));
+        }
+    }
+    Ok(n)
+}
+
+/// Split a comma-separated option value into its trimmed, non-empty items.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// File name of the per-project settings looked up from the working directory.
+pub const PROJECT_SETTINGS_FILE: &str = ".vimcode.json";
+
+/// Recursively merge `overlay` into `base`: objects merge per key, any other
+/// value replaces the one in `base`.
+fn merge_json(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2150,6 +3270,36 @@ mod tests {
         assert!(s.parse_set_option("tabstop=0").is_err());
     }
 
+    #[test]
+    fn test_set_cursorblinkinterval_minimum() {
+        let mut s = Settings::default();
+        assert!(s.parse_set_option("cursorblinkinterval=0").is_err());
+        assert!(s.set_value_str("cursor_blink_interval", "50").is_err());
+        assert_eq!(s.cursor_blink_interval, 530);
+        assert!(s.parse_set_option("cursorblinkinterval=100").is_ok());
+        assert_eq!(s.cursor_blink_interval, 100);
+    }
+
+    #[test]
+    fn test_cursor_styles_follow_cursorstyle_changes() {
+        let mut s = Settings::default();
+        assert_eq!(s.cursor_styles(), CursorStyles::default());
+        s.parse_set_option("cursorstyle=insert:block").unwrap();
+        assert_eq!(s.cursor_styles().insert.shape, CursorStyleShape::Block);
+        s.cursorstyle = "insert:underline".to_string();
+        assert_eq!(s.cursor_styles().insert.shape, CursorStyleShape::Underline);
+    }
+
+    #[test]
+    fn test_set_smoothscrollduration_range() {
+        let mut s = Settings::default();
+        assert!(s.parse_set_option("smoothscrollduration=0").is_ok());
+        assert_eq!(s.smooth_scroll_duration, 0);
+        assert!(s.parse_set_option("smoothscrollduration=1001").is_err());
+        assert!(s.set_value_str("smooth_scroll_duration", "5000").is_err());
+        assert_eq!(s.smooth_scroll_duration, 0);
+    }
+
     #[test]
     fn test_set_shiftwidth() {
         let mut s = Settings::default();
@@ -2288,6 +3438,20 @@ mod tests {
         assert!(s.parse_set_option("ts!").is_err());
     }
 
+    #[test]
+    fn test_set_makeprg_and_errorformat() {
+        let mut s = Settings::default();
+        assert_eq!(s.makeprg, "cargo build");
+        s.parse_set_option(r"mp=cargo\ test").unwrap();
+        assert_eq!(s.makeprg, "cargo test");
+        s.parse_set_option("efm=%f(%l): %m").unwrap();
+        assert_eq!(s.errorformat, "%f(%l): %m");
+        assert_eq!(
+            s.parse_set_option("errorformat?").unwrap(),
+            "errorformat=%f(%l): %m"
+        );
+    }
+
     #[test]
     fn test_display_all_includes_wrap() {
         let mut s = Settings::default();
@@ -2306,6 +3470,7 @@ mod tests {
         assert_eq!(ek.delete, "D");
         assert_eq!(ek.rename, "r");
         assert_eq!(ek.move_file, "M");
+        assert_eq!(ek.toggle_hidden, ".");
     }
 
     #[test]
@@ -2316,6 +3481,7 @@ mod tests {
         assert_eq!(ek.resolve('D'), Some(ExplorerAction::Delete));
         assert_eq!(ek.resolve('r'), Some(ExplorerAction::Rename));
         assert_eq!(ek.resolve('M'), Some(ExplorerAction::MoveFile));
+        assert_eq!(ek.resolve('.'), Some(ExplorerAction::ToggleHidden));
         assert_eq!(ek.resolve('?'), None);
         assert_eq!(ek.resolve('z'), None);
     }
@@ -2426,4 +3592,75 @@ mod tests {
         assert_eq!(s.panel_keys.live_grep, "<C-A-g>");
         assert_eq!(s.panel_keys.toggle_sidebar, "<C-b>");
     }
+
+    #[test]
+    fn test_project_overrides_merge() {
+        let mut global = Settings {
+            tabstop: 8,
+            format_on_save: true,
+            ..Settings::default()
+        };
+        global.panel_keys.live_grep = "<C-A-g>".to_string();
+        let json = r#"{"tabstop": 2, "panel_keys": {"toggle_sidebar": "<C-e>"}}"#;
+        let merged = global.with_project_overrides(json).unwrap();
+        // Project wins; global values it leaves out are kept.
+        assert_eq!(merged.tabstop, 2);
+        assert!(merged.format_on_save);
+        // Nested objects merge per key.
+        assert_eq!(merged.panel_keys.toggle_sidebar, "<C-e>");
+        assert_eq!(merged.panel_keys.live_grep, "<C-A-g>");
+    }
+
+    #[test]
+    fn test_project_overrides_not_saved_globally() {
+        let global = Settings {
+            tabstop: 8,
+            expand_tab: true,
+            ..Settings::default()
+        };
+        let json = r#"{"tabstop": 2, "expand_tab": false}"#;
+        let mut merged = global.with_project_overrides(json).unwrap();
+        // The user's own changes after the merge are saved, project values aren't.
+        merged.expand_tab = true;
+        merged.scrolloff = 9;
+        let saved: Settings = serde_json::from_str(&merged.to_json().unwrap()).unwrap();
+        assert_eq!(saved.tabstop, 8);
+        assert!(saved.expand_tab);
+        assert_eq!(saved.scrolloff, 9);
+    }
+
+    #[test]
+    fn test_project_overrides_malformed() {
+        let global = Settings::default();
+        assert!(global.with_project_overrides("{ tabstop: ").is_err());
+        assert!(global.with_project_overrides("[1, 2]").is_err());
+        assert!(global
+            .with_project_overrides(r#"{"tabstop": "wide"}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_project_settings_stops_at_git_root() {
+        let root = std::env::temp_dir().join("vimcode_test_project_settings");
+        let _ = fs::remove_dir_all(&root);
+        let repo = root.join("repo");
+        let nested = repo.join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        // Above the git root: never used.
+        fs::write(root.join(PROJECT_SETTINGS_FILE), "{}").unwrap();
+        assert_eq!(Settings::find_project_settings(&nested), None);
+
+        fs::write(repo.join(PROJECT_SETTINGS_FILE), "{}").unwrap();
+        assert_eq!(
+            Settings::find_project_settings(&nested),
+            Some(repo.join(PROJECT_SETTINGS_FILE))
+        );
+        fs::write(nested.join(PROJECT_SETTINGS_FILE), "{}").unwrap();
+        assert_eq!(
+            Settings::find_project_settings(&nested),
+            Some(nested.join(PROJECT_SETTINGS_FILE))
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,306 @@
+//! LSP snippet syntax for completion items (`insertTextFormat: 2`).
+//!
+//! Supports tab stops (`$1`, `${1}`), placeholders (`${1:name}`, nested),
+//! choices (`${1|a,b|}` — the first option is used), variables (`$NAME`,
+//! `${NAME:default}` — expanded to their default) and the final stop `$0`.
+//! A stop repeated without a placeholder (`${1:a} = $1`) mirrors the text of
+//! its placeholder.
+
+use std::collections::HashMap;
+
+/// One tab stop in an expanded snippet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabStop {
+    /// The stop number from the snippet (`0` for the final stop).
+    pub index: u32,
+    /// Char offset of the placeholder in `Snippet::text`.
+    pub start: usize,
+    /// Placeholder length in chars (0 for a bare stop).
+    pub len: usize,
+}
+
+/// A snippet with its markup removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// Text to insert, with placeholders' default text in place.
+    pub text: String,
+    /// Stops in visiting order: `$1`, `$2`, …, then `$0`.  The final stop is
+    /// always present (at the end of the text when the snippet has no `$0`).
+    pub stops: Vec<TabStop>,
+}
+
+/// Parse snippet markup into plain text and tab stops.
+pub fn parse(src: &str) -> Snippet {
+    // A first pass finds each stop's placeholder text, which a second pass
+    // copies into its mirrors (they may come before the placeholder).
+    let first = Parser::run(src, HashMap::new());
+    let mut placeholders = HashMap::new();
+    for stop in first.stops.iter().filter(|s| s.len > 0) {
+        placeholders.entry(stop.index).or_insert_with(|| {
+            first
+                .text
+                .chars()
+                .skip(stop.start)
+                .take(stop.len)
+                .collect::<String>()
+        });
+    }
+    let parser = if placeholders.is_empty() {
+        first
+    } else {
+        Parser::run(src, placeholders)
+    };
+
+    let mut stops = parser.stops;
+    // Visit 1, 2, … in order and $0 last; mirrors of a stop keep only the first.
+    stops.sort_by_key(|s| if s.index == 0 { u32::MAX } else { s.index });
+    stops.dedup_by_key(|s| s.index);
+    let has_final = stops.last().is_some_and(|s| s.index == 0);
+    if !has_final {
+        stops.push(TabStop {
+            index: 0,
+            start: parser.len,
+            len: 0,
+        });
+    }
+    Snippet {
+        text: parser.text,
+        stops,
+    }
+}
+
+struct Parser {
+    src: Vec<char>,
+    pos: usize,
+    text: String,
+    /// Length of `text` in chars.
+    len: usize,
+    stops: Vec<TabStop>,
+    /// Placeholder text per stop index, inserted at that stop's mirrors.
+    placeholders: HashMap<u32, String>,
+}
+
+impl Parser {
+    fn run(src: &str, placeholders: HashMap<u32, String>) -> Self {
+        let mut parser = Parser {
+            src: src.chars().collect(),
+            pos: 0,
+            text: String::new(),
+            len: 0,
+            stops: Vec::new(),
+            placeholders,
+        };
+        parser.parse_seq(false);
+        parser
+    }
+
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        self.len += 1;
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src.get(self.pos).copied()
+    }
+
+    /// Parse text up to the end of input, or up to (and including) the `}`
+    /// closing the enclosing placeholder when `nested`.
+    fn parse_seq(&mut self, nested: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(next @ ('$' | '}' | '\\')) => {
+                            self.pos += 1;
+                            self.push(next);
+                        }
+                        _ => self.push('\\'),
+                    }
+                }
+                '}' if nested => {
+                    self.pos += 1;
+                    return;
+                }
+                '$' => {
+                    self.pos += 1;
+                    self.parse_dollar();
+                }
+                _ => {
+                    self.pos += 1;
+                    self.push(c);
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> u32 {
+        let mut n = 0u32;
+        while let Some(d) = self.peek().and_then(|c| c.to_digit(10)) {
+            n = n.saturating_mul(10).saturating_add(d);
+            self.pos += 1;
+        }
+        n
+    }
+
+    /// Insert the placeholder text of stop `index` at one of its mirrors.
+    fn push_mirror(&mut self, index: u32) {
+        if let Some(text) = self.placeholders.get(&index).cloned() {
+            text.chars().for_each(|c| self.push(c));
+        }
+    }
+
+    fn skip_ident(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Parse what follows a `$`.
+    fn parse_dollar(&mut self) {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let index = self.number();
+                let start = self.len;
+                self.push_mirror(index);
+                self.stops.push(TabStop {
+                    index,
+                    start,
+                    len: self.len - start,
+                });
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => self.skip_ident(),
+            Some('{') => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(c) if c.is_ascii_digit() => {
+                        let index = self.number();
+                        let start = self.len;
+                        match self.peek() {
+                            Some(':') => {
+                                self.pos += 1;
+                                self.parse_seq(true);
+                            }
+                            Some('|') => {
+                                self.pos += 1;
+                                self.parse_choice();
+                            }
+                            Some('}') => {
+                                self.pos += 1;
+                                self.push_mirror(index);
+                            }
+                            _ => {}
+                        }
+                        self.stops.push(TabStop {
+                            index,
+                            start,
+                            len: self.len - start,
+                        });
+                    }
+                    Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                        self.skip_ident();
+                        match self.peek() {
+                            // Unknown variables expand to their default.
+                            Some(':') => {
+                                self.pos += 1;
+                                self.parse_seq(true);
+                            }
+                            Some('}') => self.pos += 1,
+                            _ => {}
+                        }
+                    }
+                    _ => {
+                        self.push('$');
+                        self.push('{');
+                    }
+                }
+            }
+            _ => self.push('$'),
+        }
+    }
+
+    /// Parse `a,b|}` after `${N|`, inserting the first option.
+    fn parse_choice(&mut self) {
+        let mut first = true;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '|' => {
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                    }
+                    return;
+                }
+                ',' => first = false,
+                '\\' => {
+                    if let Some(next) = self.peek() {
+                        self.pos += 1;
+                        if first {
+                            self.push(next);
+                        }
+                    }
+                }
+                _ if first => self.push(c),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(index: u32, start: usize, len: usize) -> TabStop {
+        TabStop { index, start, len }
+    }
+
+    #[test]
+    fn plain_text_gets_final_stop_at_end() {
+        let s = parse("println");
+        assert_eq!(s.text, "println");
+        assert_eq!(s.stops, vec![stop(0, 7, 0)]);
+    }
+
+    #[test]
+    fn placeholders_and_final_stop() {
+        let s = parse("push(${1:value})$0");
+        assert_eq!(s.text, "push(value)");
+        assert_eq!(s.stops, vec![stop(1, 5, 5), stop(0, 11, 0)]);
+
+        let s = parse("fn ${2:name}($1) {\n    $0\n}");
+        assert_eq!(s.text, "fn name() {\n    \n}");
+        assert_eq!(s.stops, vec![stop(1, 8, 0), stop(2, 3, 4), stop(0, 16, 0)]);
+    }
+
+    #[test]
+    fn nested_placeholders() {
+        let s = parse("${1:foo(${2:bar})}");
+        assert_eq!(s.text, "foo(bar)");
+        assert_eq!(s.stops, vec![stop(1, 0, 8), stop(2, 4, 3), stop(0, 8, 0)]);
+    }
+
+    #[test]
+    fn escapes_choices_and_variables() {
+        let s = parse(r"\$x ${1|one,two|} ${TM_FILENAME:file} $HOME\}");
+        assert_eq!(s.text, "$x one file }");
+        assert_eq!(s.stops, vec![stop(1, 3, 3), stop(0, 13, 0)]);
+    }
+
+    #[test]
+    fn mirrored_stop_keeps_first() {
+        let s = parse("${1:a} = $1");
+        assert_eq!(s.text, "a = a");
+        assert_eq!(s.stops, vec![stop(1, 0, 1), stop(0, 5, 0)]);
+    }
+
+    #[test]
+    fn mirrors_copy_the_placeholder_text() {
+        let s = parse("${1} = ${1:name}; $2($1)");
+        assert_eq!(s.text, "name = name; (name)");
+        assert_eq!(s.stops, vec![stop(1, 0, 4), stop(2, 13, 0), stop(0, 19, 0)]);
+    }
+}
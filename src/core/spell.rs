@@ -1,9 +1,12 @@
 //! Spell-checking module using spellbook (pure-Rust Hunspell parser).
 //!
 //! Bundled en_US dictionary compiled into the binary.  User dictionary
-//! at `~/.config/vimcode/user.dic` (one word per line).
+//! at `~/.config/vimcode/user.dic` (one word per line).  Extra word lists
+//! for the `spelllang` language are read from `~/.config/vimcode/spell/`
+//! (`en_US.txt`, or `en.txt` for the language prefix).
 
 use super::syntax::SyntaxLanguage;
+use std::collections::HashSet;
 use std::path::PathBuf;
 
 /// A misspelled word with its byte and char position within a line.
@@ -21,6 +24,8 @@ pub struct SpellError {
 pub struct SpellChecker {
     dict: spellbook::Dictionary,
     user_words: Vec<String>,
+    /// Lowercased words from `spell/*.txt` word lists.
+    list_words: HashSet<String>,
 }
 
 // Bundled dictionaries compiled into the binary.
@@ -33,7 +38,38 @@ impl SpellChecker {
     pub fn new() -> Option<Self> {
         let dict = spellbook::Dictionary::new(BUNDLED_AFF, BUNDLED_DIC).ok()?;
         let user_words = load_user_dict_words();
-        Some(SpellChecker { dict, user_words })
+        Some(SpellChecker {
+            dict,
+            user_words,
+            list_words: HashSet::new(),
+        })
+    }
+
+    /// Load the word lists for `lang` (e.g. `en_US`) from the `spell/`
+    /// config directory: `en_US.txt` and the prefix list `en.txt`.
+    pub fn load_word_lists(&mut self, lang: &str) {
+        let dir = super::paths::vimcode_config_dir().join("spell");
+        let prefix = lang.split(['_', '-']).next().unwrap_or(lang);
+        let mut names = vec![lang];
+        if prefix != lang {
+            names.push(prefix);
+        }
+        for name in names {
+            if let Ok(contents) = std::fs::read_to_string(dir.join(format!("{name}.txt"))) {
+                self.add_word_list(&contents);
+            }
+        }
+    }
+
+    /// Accept every word of a word list (one per line; `#` starts a
+    /// comment and Hunspell-style `/FLAGS` suffixes are ignored).
+    pub fn add_word_list(&mut self, contents: &str) {
+        for line in contents.lines() {
+            let word = line.split(['#', '/']).next().unwrap_or("").trim();
+            if !word.is_empty() {
+                self.list_words.insert(word.to_lowercase());
+            }
+        }
     }
 
     /// Returns true if the word is correctly spelled.
@@ -52,6 +88,9 @@ impl SpellChecker {
         if self.user_words.iter().any(|w| w.eq_ignore_ascii_case(word)) {
             return true;
         }
+        if self.list_words.contains(&word.to_lowercase()) {
+            return true;
+        }
         self.dict.check(word)
     }
 
@@ -64,10 +103,29 @@ impl SpellChecker {
         }
     }
 
-    /// Return spelling suggestions for a misspelled word.
+    /// Return spelling suggestions for a misspelled word: the dictionary's
+    /// suggestions, then close matches (edit distance <= 2) from the user
+    /// dictionary and word lists.
     pub fn suggest(&self, word: &str) -> Vec<String> {
         let mut out = Vec::new();
         self.dict.suggest(word, &mut out);
+        let lower = word.to_lowercase();
+        let mut extra: Vec<(usize, &str)> = self
+            .user_words
+            .iter()
+            .map(String::as_str)
+            .chain(self.list_words.iter().map(String::as_str))
+            .filter_map(|w| {
+                let d = edit_distance(&lower, &w.to_lowercase());
+                (d <= 2).then_some((d, w))
+            })
+            .collect();
+        extra.sort();
+        for (_, w) in extra {
+            if !out.iter().any(|o| o.eq_ignore_ascii_case(w)) {
+                out.push(w.to_string());
+            }
+        }
         out
     }
 
@@ -78,6 +136,21 @@ impl SpellChecker {
     }
 }
 
+/// Levenshtein distance between two words, in chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 /// Extract the user dictionary path.
 fn user_dict_path() -> PathBuf {
     super::paths::vimcode_config_dir().join("user.dic")
@@ -289,6 +362,17 @@ mod tests {
         assert!(!c.check_word("vimcode"));
     }
 
+    #[test]
+    fn test_word_list_words_and_suggestions() {
+        let mut c = checker();
+        assert!(!c.check_word("rustacean"));
+        c.add_word_list("# project words\nrustacean/S\nVimCode\n");
+        assert!(c.check_word("rustacean"));
+        assert!(c.check_word("vimcode"));
+        assert!(c.suggest("rustaceen").iter().any(|s| s == "rustacean"));
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
     #[test]
     fn test_check_line_plain_text() {
         let c = checker();
@@ -2,13 +2,15 @@
 //!
 //! Each open buffer with a file path gets a swap file under
 //! `~/.config/vimcode/swap/`.  The swap file contains a short header
-//! (path, PID, timestamp) followed by the raw buffer text.  On crash,
-//! the stale swap file is detected on next open and recovery is offered.
+//! (path, PID, timestamp, the file's mtime when editing began) followed by
+//! the raw buffer text.  On crash, the stale swap file is detected on next
+//! open and recovery is offered.
 
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::SystemTime;
 
 /// Wrapper around a raw pointer to Engine, made Send+Sync so it can
 /// live in a global static.  Only used for last-resort panic recovery.
@@ -75,6 +77,29 @@ pub struct SwapHeader {
     pub file_path: PathBuf,
     pub pid: u32,
     pub modified: String,
+    /// Modification time (seconds since the Unix epoch) of the file on disk
+    /// when the buffer was last read or written.  `None` in swap files from
+    /// older versions.
+    pub file_mtime: Option<u64>,
+}
+
+impl SwapHeader {
+    /// True when the file on disk was modified after the edits in the swap
+    /// began, e.g. it was saved by another program after a crash.
+    pub fn file_is_newer(&self) -> bool {
+        let disk = fs::metadata(&self.file_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(mtime_secs);
+        matches!((disk, self.file_mtime), (Some(disk), Some(swap)) if disk > swap)
+    }
+}
+
+/// Seconds since the Unix epoch, as stored in the `mtime:` header line.
+pub fn mtime_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 /// Directory where all swap files live.
@@ -110,6 +135,9 @@ pub fn write_swap(swap_path: &Path, header: &SwapHeader, content: &str) {
         writeln!(f, "path: {}", header.file_path.display())?;
         writeln!(f, "pid: {}", header.pid)?;
         writeln!(f, "modified: {}", header.modified)?;
+        if let Some(mtime) = header.file_mtime {
+            writeln!(f, "mtime: {mtime}")?;
+        }
         writeln!(f, "---")?;
         f.write_all(content.as_bytes())?;
         f.flush()?;
@@ -132,6 +160,7 @@ pub fn read_swap(swap_path: &Path) -> Option<(SwapHeader, String)> {
     let mut file_path: Option<PathBuf> = None;
     let mut pid: Option<u32> = None;
     let mut modified = String::new();
+    let mut file_mtime = None;
 
     for line in header_block.lines() {
         if line == "VIMCODE_SWAP_V1" {
@@ -143,6 +172,8 @@ pub fn read_swap(swap_path: &Path) -> Option<(SwapHeader, String)> {
             pid = rest.parse().ok();
         } else if let Some(rest) = line.strip_prefix("modified: ") {
             modified = rest.to_string();
+        } else if let Some(rest) = line.strip_prefix("mtime: ") {
+            file_mtime = rest.parse().ok();
         }
     }
 
@@ -151,6 +182,7 @@ pub fn read_swap(swap_path: &Path) -> Option<(SwapHeader, String)> {
             file_path: file_path?,
             pid: pid?,
             modified,
+            file_mtime,
         },
         content.to_string(),
     ))
@@ -203,7 +235,6 @@ pub fn is_pid_alive(pid: u32) -> bool {
 /// Return an ISO-8601-ish timestamp for the current moment.
 pub fn now_iso8601() -> String {
     // Use UNIX_EPOCH + SystemTime for a simple UTC timestamp.
-    use std::time::SystemTime;
     let d = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap_or_default();
@@ -291,6 +322,7 @@ mod tests {
             file_path: PathBuf::from("/tmp/test.rs"),
             pid: std::process::id(),
             modified: "2026-01-01T00:00:00Z".to_string(),
+            file_mtime: Some(1_767_225_600),
         };
         let content = "fn main() {\n    println!(\"hello\");\n}\n";
 
@@ -300,6 +332,7 @@ mod tests {
         writeln!(f, "path: {}", header.file_path.display()).unwrap();
         writeln!(f, "pid: {}", header.pid).unwrap();
         writeln!(f, "modified: {}", header.modified).unwrap();
+        writeln!(f, "mtime: 1767225600").unwrap();
         writeln!(f, "---").unwrap();
         f.write_all(content.as_bytes()).unwrap();
         drop(f);
@@ -308,11 +341,29 @@ mod tests {
         assert_eq!(parsed_header.file_path, header.file_path);
         assert_eq!(parsed_header.pid, header.pid);
         assert_eq!(parsed_header.modified, header.modified);
+        assert_eq!(parsed_header.file_mtime, header.file_mtime);
         assert_eq!(parsed_content, content);
 
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn test_file_is_newer_than_swap() {
+        let path = std::env::temp_dir().join("vimcode_swap_test_newer.txt");
+        fs::write(&path, "x").unwrap();
+        let disk = mtime_secs(fs::metadata(&path).unwrap().modified().unwrap());
+        let header = |file_mtime| SwapHeader {
+            file_path: path.clone(),
+            pid: 1,
+            modified: String::new(),
+            file_mtime,
+        };
+        assert!(header(Some(disk - 60)).file_is_newer());
+        assert!(!header(Some(disk)).file_is_newer());
+        assert!(!header(None).file_is_newer());
+        let _ = fs::remove_file(&path);
+    }
+
     #[test]
     fn test_is_pid_alive_self() {
         assert!(is_pid_alive(std::process::id()));
@@ -781,7 +781,6 @@ impl Syntax {
     }
 
     /// Create a Syntax from an LSP language identifier (e.g. "rust", "python").
-    #[allow(dead_code)]
     pub fn new_from_language_id(id: &str) -> Option<Self> {
         Self::new_from_language_id_with_overrides(id, None)
     }
@@ -13,6 +13,9 @@ pub struct Tab {
     pub layout: WindowLayout,
     /// The currently focused window in this tab.
     pub active_window: WindowId,
+    /// While a window is zoomed (CTRL-W z): that window and the split
+    /// layout to restore.  The other windows stay alive meanwhile.
+    pub zoomed: Option<(WindowId, WindowLayout)>,
 }
 
 impl Tab {
@@ -21,12 +24,63 @@ impl Tab {
             id,
             layout: WindowLayout::leaf(initial_window),
             active_window: initial_window,
+            zoomed: None,
         }
     }
 
-    /// Get all window IDs in this tab.
+    /// Get all window IDs in this tab, including those hidden by a zoom.
     pub fn window_ids(&self) -> Vec<WindowId> {
-        self.layout.window_ids()
+        let mut ids = self.layout.window_ids();
+        if let Some((_, ref saved)) = self.zoomed {
+            for id in saved.window_ids() {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids
+    }
+
+    /// Let the active window fill the tab, or undo an earlier zoom.
+    /// Returns true when the tab is zoomed afterwards.
+    pub fn toggle_zoom(&mut self) -> bool {
+        if self.zoomed.is_some() {
+            self.unzoom();
+            return false;
+        }
+        if self.layout.is_single_window() {
+            return false;
+        }
+        let saved = std::mem::replace(&mut self.layout, WindowLayout::leaf(self.active_window));
+        self.zoomed = Some((self.active_window, saved));
+        true
+    }
+
+    /// Restore the layout saved by [`Tab::toggle_zoom`].  Splits made while
+    /// zoomed take the zoomed window's place in it.
+    pub fn unzoom(&mut self) {
+        let Some((zoomed, mut saved)) = self.zoomed.take() else {
+            return;
+        };
+        let current = std::mem::replace(&mut self.layout, WindowLayout::leaf(zoomed));
+        if saved.replace_leaf(zoomed, current.clone()) {
+            self.layout = saved;
+        } else {
+            self.layout = current;
+        }
+    }
+
+    /// Take a window out of the layout and out of the layout saved by a
+    /// zoom, so unzooming can't bring back a window that no longer exists.
+    pub fn remove_window(&mut self, window_id: WindowId) {
+        if let Some(layout) = self.layout.remove(window_id) {
+            self.layout = layout;
+        }
+        if let Some((_, saved)) = self.zoomed.as_mut() {
+            if let Some(layout) = saved.remove(window_id) {
+                *saved = layout;
+            }
+        }
     }
 
     /// Check if this tab contains a specific window.
@@ -75,4 +129,40 @@ mod tests {
         tab.cycle_next_window();
         assert_eq!(tab.active_window, WindowId(1));
     }
+
+    #[test]
+    fn test_tab_zoom_restores_layout() {
+        let mut tab = Tab::new(TabId(1), WindowId(1));
+        tab.layout
+            .split_at(WindowId(1), SplitDirection::Vertical, WindowId(2), false);
+        tab.active_window = WindowId(2);
+
+        assert!(tab.toggle_zoom());
+        assert!(tab.layout.is_single_window());
+        assert_eq!(tab.window_ids(), vec![WindowId(2), WindowId(1)]);
+        assert!(!tab.toggle_zoom());
+        assert_eq!(tab.layout.window_ids(), vec![WindowId(1), WindowId(2)]);
+
+        // A split made while zoomed replaces the zoomed window's slot.
+        tab.toggle_zoom();
+        tab.layout
+            .split_at(WindowId(2), SplitDirection::Horizontal, WindowId(3), false);
+        tab.unzoom();
+        assert_eq!(
+            tab.layout.window_ids(),
+            vec![WindowId(1), WindowId(2), WindowId(3)]
+        );
+        assert!(tab.zoomed.is_none());
+
+        // A window removed while hidden by the zoom stays gone.
+        tab.active_window = WindowId(3);
+        tab.toggle_zoom();
+        tab.remove_window(WindowId(1));
+        tab.unzoom();
+        assert_eq!(tab.window_ids(), vec![WindowId(2), WindowId(3)]);
+
+        // A single window has nothing to zoom.
+        let mut single = Tab::new(TabId(2), WindowId(4));
+        assert!(!single.toggle_zoom());
+    }
 }
@@ -1,4 +1,5 @@
 use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use ropey::Rope;
 use std::collections::VecDeque;
 use std::io::Write;
 use std::path::Path;
@@ -38,6 +39,16 @@ pub struct TermSelection {
     pub end_col: u16,
 }
 
+/// How much of a pane's scrollback a `:terminal` buffer already holds, so
+/// new output can be appended instead of rebuilding the whole text.
+#[derive(Clone, Copy, Debug)]
+struct TextMirror {
+    /// `history_pushed` when the buffer was last updated.
+    pushed: u64,
+    /// Scrollback rows at the start of the buffer; the screen follows them.
+    history_lines: usize,
+}
+
 /// Context for a terminal pane running an install command.
 /// Stored on the pane so we can register the LSP/DAP server after the command finishes.
 #[derive(Clone, Debug)]
@@ -78,6 +89,10 @@ pub struct TerminalPane {
     pub history: VecDeque<Vec<HistCell>>,
     /// Maximum number of rows kept in `history` (from settings).
     history_capacity: usize,
+    /// Rows ever appended to `history`, including those since dropped.
+    history_pushed: u64,
+    /// State of the buffer mirrored by [`TerminalPane::update_text`].
+    mirror: Option<TextMirror>,
     /// If set, this pane is running an install command (not an interactive shell).
     /// When the process exits, the engine checks whether the binary is now on PATH.
     pub install_context: Option<InstallContext>,
@@ -149,6 +164,8 @@ impl TerminalPane {
             scroll_offset: 0,
             history: VecDeque::new(),
             history_capacity,
+            history_pushed: 0,
+            mirror: None,
             install_context: None,
         })
     }
@@ -251,6 +268,8 @@ impl TerminalPane {
             scroll_offset: 0,
             history: VecDeque::new(),
             history_capacity,
+            history_pushed: 0,
+            mirror: None,
             install_context: install_ctx,
         })
     }
@@ -341,12 +360,21 @@ impl TerminalPane {
                     self.history.pop_front();
                 }
                 self.history.push_back(row);
+                self.history_pushed += 1;
             }
         }
         // Restore live view — always safe since we're back to offset 0.
         self.parser.set_scrollback(0);
     }
 
+    /// Kill the shell (e.g. when the `:terminal` window showing it closes).
+    pub fn kill(&mut self) {
+        if !self.exited {
+            let _ = self.child.kill();
+            self.exited = true;
+        }
+    }
+
     /// Send raw bytes as keyboard input to the shell.
     pub fn write_input(&mut self, data: &[u8]) {
         let _ = self.writer.write_all(data);
@@ -422,6 +450,110 @@ impl TerminalPane {
     }
 }
 
+impl TerminalPane {
+    /// Plain-text snapshot of the scrollback plus the live screen, one line
+    /// per row with trailing blanks trimmed, and the shell cursor as a
+    /// `(line, col)` position in that text.  Used to mirror a `:terminal`
+    /// into an editor buffer.
+    pub fn text_snapshot(&self) -> (String, usize, usize) {
+        snapshot_text(&self.history, self.parser.screen())
+    }
+
+    /// Bring `text` — a buffer holding this pane's [`Self::text_snapshot`]
+    /// as of the last call — up to date, and return the shell cursor.  Only
+    /// the screen rows are rewritten; new scrollback rows are appended and
+    /// rows that fell out of the scrollback are dropped from the top.
+    pub fn update_text(&mut self, text: &mut Rope) -> (usize, usize) {
+        update_mirror(
+            &mut self.mirror,
+            text,
+            &self.history,
+            self.history_pushed,
+            self.parser.screen(),
+        )
+    }
+}
+
+fn snapshot_text(
+    history: &VecDeque<Vec<HistCell>>,
+    screen: &vt100::Screen,
+) -> (String, usize, usize) {
+    let mut lines: Vec<String> = history.iter().map(|row| history_line(row)).collect();
+    let (screen_lines, cur_row, cur_col) = screen_text(screen);
+    let cursor_line = lines.len() + cur_row;
+    lines.extend(screen_lines);
+    (lines.join("\n"), cursor_line, cur_col)
+}
+
+fn history_line(row: &[HistCell]) -> String {
+    let line: String = row.iter().map(|c| c.ch).collect();
+    line.trim_end().to_string()
+}
+
+/// The live screen's rows with trailing blanks trimmed, and the cursor
+/// `(row, col)` within them.
+fn screen_text(screen: &vt100::Screen) -> (Vec<String>, usize, usize) {
+    let (_, cols) = screen.size();
+    let (cur_row, cur_col) = screen.cursor_position();
+    let (cur_row, cur_col) = (cur_row as usize, cur_col as usize);
+    let mut lines: Vec<String> = screen
+        .rows(0, cols)
+        .map(|row| row.trim_end().to_string())
+        .collect();
+    // Keep the blanks the cursor sits after (e.g. behind a prompt).
+    if let Some(line) = lines.get_mut(cur_row) {
+        let len = line.chars().count();
+        if len < cur_col {
+            line.push_str(&" ".repeat(cur_col - len));
+        }
+    }
+    // Blank rows below the cursor are just unused screen space.
+    while lines.len() > cur_row + 1 && lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    (lines, cur_row, cur_col)
+}
+
+/// See [`TerminalPane::update_text`].  Falls back to a full snapshot the
+/// first time, or when more rows scrolled by than the scrollback kept.
+fn update_mirror(
+    mirror: &mut Option<TextMirror>,
+    text: &mut Rope,
+    history: &VecDeque<Vec<HistCell>>,
+    pushed: u64,
+    screen: &vt100::Screen,
+) -> (usize, usize) {
+    let synced = mirror.filter(|m| {
+        (pushed - m.pushed) as usize <= history.len() && text.len_lines() > m.history_lines
+    });
+    *mirror = Some(TextMirror {
+        pushed,
+        history_lines: history.len(),
+    });
+    let Some(synced) = synced else {
+        let (snapshot, line, col) = snapshot_text(history, screen);
+        *text = Rope::from_str(&snapshot);
+        return (line, col);
+    };
+    let new_rows = (pushed - synced.pushed) as usize;
+    let screen_start = text.line_to_char(synced.history_lines);
+    text.remove(screen_start..);
+    let dropped = (synced.history_lines + new_rows).saturating_sub(history.len());
+    if dropped > 0 {
+        let end = text.line_to_char(dropped);
+        text.remove(..end);
+    }
+    let mut tail = String::new();
+    for row in history.range(history.len() - new_rows..) {
+        tail.push_str(&history_line(row));
+        tail.push('\n');
+    }
+    let (screen_lines, cur_row, cur_col) = screen_text(screen);
+    tail.push_str(&screen_lines.join("\n"));
+    text.insert(text.len_chars(), &tail);
+    (history.len() + cur_row, cur_col)
+}
+
 /// Normalize a selection so start ≤ end in reading order.
 fn normalize_selection(sel: &TermSelection) -> (u16, u16, u16, u16) {
     if (sel.start_row, sel.start_col) <= (sel.end_row, sel.end_col) {
@@ -448,3 +580,129 @@ pub fn default_shell() -> String {
         "/bin/bash".to_string()
     }
 }
+
+/// Translate an engine key event (GTK-style key names, as passed to
+/// `Engine::handle_key`) to the bytes a PTY expects.  Returns an empty vec
+/// for keys without a mapping.
+pub fn key_to_pty_bytes(key_name: &str, unicode: Option<char>, ctrl: bool) -> Vec<u8> {
+    if ctrl {
+        // Engine Ctrl combos name the key ("c" for Ctrl-C).
+        let ch = match key_name {
+            "space" => Some(' '),
+            "backslash" => Some('\\'),
+            "bracketleft" => Some('['),
+            "bracketright" => Some(']'),
+            _ if key_name.chars().count() == 1 => key_name.chars().next(),
+            _ => unicode,
+        };
+        return match ch {
+            Some(c) if c.is_ascii() => vec![(c.to_ascii_lowercase() as u8) & 0x1f],
+            _ => match key_name {
+                "Return" => b"\r".to_vec(),
+                "BackSpace" => b"\x08".to_vec(),
+                _ => vec![],
+            },
+        };
+    }
+    let seq: &[u8] = match key_name {
+        "Return" | "KP_Enter" => b"\r",
+        "BackSpace" => b"\x7f",
+        "Tab" => b"\t",
+        "ISO_Left_Tab" | "Shift_Tab" => b"\x1b[Z",
+        "Escape" => b"\x1b",
+        "Up" => b"\x1b[A",
+        "Down" => b"\x1b[B",
+        "Right" => b"\x1b[C",
+        "Left" => b"\x1b[D",
+        "Home" => b"\x1b[H",
+        "End" => b"\x1b[F",
+        "Delete" => b"\x1b[3~",
+        "Insert" => b"\x1b[2~",
+        "Page_Up" => b"\x1b[5~",
+        "Page_Down" => b"\x1b[6~",
+        _ => {
+            return unicode
+                .map(|c| c.to_string().into_bytes())
+                .unwrap_or_default()
+        }
+    };
+    seq.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_joins_history_and_screen() {
+        let mut parser = vt100::Parser::new(4, 20, 0);
+        parser.process(b"$ ls\r\nsrc  README.md\r\n$ ");
+        let row = |s: &str| -> Vec<HistCell> {
+            s.chars()
+                .map(|ch| HistCell {
+                    ch,
+                    ..HistCell::default()
+                })
+                .collect()
+        };
+        let history: VecDeque<Vec<HistCell>> = [row("old line   ")].into_iter().collect();
+        let (text, line, col) = snapshot_text(&history, parser.screen());
+        assert_eq!(text, "old line\n$ ls\nsrc  README.md\n$ ");
+        assert_eq!((line, col), (3, 2));
+    }
+
+    #[test]
+    fn text_mirror_appends_scrollback_and_matches_snapshot() {
+        let mut parser = vt100::Parser::new(3, 20, 0);
+        let mut history: VecDeque<Vec<HistCell>> = VecDeque::new();
+        let mut pushed = 0;
+        let mut mirror = None;
+        let mut text = Rope::new();
+        let push = |history: &mut VecDeque<Vec<HistCell>>, pushed: &mut u64, s: &str| {
+            if history.len() >= 2 {
+                history.pop_front();
+            }
+            let row = s
+                .chars()
+                .map(|ch| HistCell {
+                    ch,
+                    ..HistCell::default()
+                })
+                .collect();
+            history.push_back(row);
+            *pushed += 1;
+        };
+
+        parser.process(b"$ ");
+        update_mirror(&mut mirror, &mut text, &history, pushed, parser.screen());
+        assert_eq!(text.to_string(), "$ ");
+
+        // One row scrolled into the scrollback; the screen is rewritten.
+        push(&mut history, &mut pushed, "one");
+        parser.process(b"two\r\n$ ");
+        let cursor = update_mirror(&mut mirror, &mut text, &history, pushed, parser.screen());
+        let (expected, line, col) = snapshot_text(&history, parser.screen());
+        assert_eq!(text.to_string(), expected);
+        assert_eq!(cursor, (line, col));
+        assert_eq!(text.to_string(), "one\n$ two\n$ ");
+
+        // The capped scrollback drops its oldest row from the top.
+        push(&mut history, &mut pushed, "three");
+        push(&mut history, &mut pushed, "four");
+        let cursor = update_mirror(&mut mirror, &mut text, &history, pushed, parser.screen());
+        let (expected, line, col) = snapshot_text(&history, parser.screen());
+        assert_eq!(text.to_string(), expected);
+        assert_eq!(cursor, (line, col));
+        assert!(text.to_string().starts_with("three\nfour\n"));
+    }
+
+    #[test]
+    fn pty_bytes_for_engine_keys() {
+        assert_eq!(key_to_pty_bytes("c", Some('c'), true), vec![0x03]);
+        assert_eq!(key_to_pty_bytes("backslash", None, true), vec![0x1c]);
+        assert_eq!(key_to_pty_bytes("", Some('x'), false), b"x".to_vec());
+        assert_eq!(key_to_pty_bytes("Return", None, false), b"\r".to_vec());
+        assert_eq!(key_to_pty_bytes("Up", None, false), b"\x1b[A".to_vec());
+        assert!(key_to_pty_bytes("F30", None, false).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! Persistent undo history (`:set undofile`).
+//!
+//! On save, a buffer's undo/redo stacks are written as JSON to
+//! `~/.local/share/vimcode/undo/<hash>.json`, keyed by the canonical file
+//! path.  The file records a checksum of the saved text; on open the
+//! history is only restored when the file on disk still matches it, so
+//! edits made outside VimCode can never replay stale operations.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::buffer_manager::UndoEntry;
+
+/// Bumped whenever the on-disk layout changes; older files are ignored.
+const UNDO_FILE_VERSION: u32 = 1;
+
+/// Serialized undo history for one file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoFile {
+    pub version: u32,
+    /// [`content_checksum`] of the buffer text the history applies to.
+    pub checksum: u64,
+    pub undo_stack: Vec<UndoEntry>,
+    pub redo_stack: Vec<UndoEntry>,
+}
+
+impl UndoFile {
+    pub fn new(text: &str, undo_stack: &[UndoEntry], redo_stack: &[UndoEntry]) -> Self {
+        Self {
+            version: UNDO_FILE_VERSION,
+            checksum: content_checksum(text),
+            undo_stack: undo_stack.to_vec(),
+            redo_stack: redo_stack.to_vec(),
+        }
+    }
+}
+
+/// Directory where all undo files live.
+pub fn undo_dir() -> PathBuf {
+    super::paths::vimcode_data_dir().join("undo")
+}
+
+/// FNV-1a 64-bit hash (same algorithm as `swap.rs` / `session.rs`).
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x00000100000001b3);
+    }
+    hash
+}
+
+/// Compute the undo file path for a given canonical file path.
+pub fn undo_path_for(canonical: &Path) -> PathBuf {
+    let hash = fnv1a(canonical.to_string_lossy().as_bytes());
+    undo_dir().join(format!("{:016x}.json", hash))
+}
+
+/// Checksum of buffer text, used to detect stale undo files.
+pub fn content_checksum(text: &str) -> u64 {
+    fnv1a(text.as_bytes())
+}
+
+/// Write an undo file atomically (write to `.tmp`, then rename).
+/// Errors are ignored — losing undo history must never block a save.
+pub fn write_undo_file(undo_path: &Path, file: &UndoFile) {
+    if cfg!(test) || crate::core::session::saves_suppressed() {
+        return;
+    }
+    let dir = undo_path.parent().unwrap_or(Path::new("."));
+    if fs::create_dir_all(dir).is_err() {
+        return;
+    }
+    let Ok(json) = serde_json::to_string(file) else {
+        return;
+    };
+    let tmp = undo_path.with_extension("tmp");
+    if fs::write(&tmp, json).is_err() || fs::rename(&tmp, undo_path).is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+/// Read an undo file and return it only if it applies to `text`.
+/// Stale files (checksum mismatch, old version, unparsable) are deleted.
+pub fn read_undo_file(undo_path: &Path, text: &str) -> Option<UndoFile> {
+    let data = fs::read_to_string(undo_path).ok()?;
+    match serde_json::from_str::<UndoFile>(&data) {
+        Ok(file)
+            if file.version == UNDO_FILE_VERSION && file.checksum == content_checksum(text) =>
+        {
+            Some(file)
+        }
+        _ => {
+            if !cfg!(test) && !crate::core::session::saves_suppressed() {
+                let _ = fs::remove_file(undo_path);
+            }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::buffer_manager::EditOp;
+    use crate::core::Cursor;
+
+    #[test]
+    fn test_undo_path_deterministic() {
+        let p1 = undo_path_for(Path::new("/home/user/file.rs"));
+        let p2 = undo_path_for(Path::new("/home/user/file.rs"));
+        assert_eq!(p1, p2);
+        assert_ne!(p1, undo_path_for(Path::new("/home/user/other.rs")));
+    }
+
+    #[test]
+    fn test_undo_file_roundtrip_and_stale_checksum() {
+        let dir = std::env::temp_dir().join("vimcode_undofile_test_roundtrip");
+        let _ = fs::create_dir_all(&dir);
+        let path = dir.join("test.json");
+
+        let mut entry = UndoEntry::new(Cursor { line: 0, col: 2 });
+        entry.ops.push(EditOp::Insert {
+            pos: 2,
+            text: "xy".to_string(),
+        });
+        let file = UndoFile::new("abxy\n", &[entry], &[]);
+        // Bypass write suppression for this unit test.
+        fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+
+        let restored = read_undo_file(&path, "abxy\n").expect("checksum matches");
+        assert_eq!(restored.undo_stack.len(), 1);
+        assert_eq!(
+            restored.undo_stack[0].cursor_before,
+            Cursor { line: 0, col: 2 }
+        );
+        assert!(restored.redo_stack.is_empty());
+
+        // Content changed outside the editor → history is discarded.
+        assert!(read_undo_file(&path, "changed\n").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
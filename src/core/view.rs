@@ -23,6 +23,11 @@ pub struct View {
     pub extra_cursors: Vec<Cursor>,
     /// First visible line (for viewport scrolling).
     pub scroll_top: usize,
+    /// While a GUI backend animates a smooth scroll (`:set smoothscroll`):
+    /// how many display rows (a closed fold is one) the drawn viewport still
+    /// is from `scroll_top`, which already holds the target — negative when
+    /// it is above it.  Only rendering reads it; 0 otherwise.
+    pub scroll_offset: f64,
     /// Number of lines that fit in this window's text viewport.
     pub viewport_lines: usize,
     /// First visible column (for horizontal scrolling).
@@ -32,6 +37,10 @@ pub struct View {
     /// Closed fold regions for this window, sorted by `start`, non-overlapping.
     /// Folds are ephemeral (not persisted to session).
     pub folds: Vec<FoldRegion>,
+    /// Current fold level set by `zm`/`zr`/`zM`/`zR`: folds nested deeper
+    /// than this are closed. `None` until one of those commands is used,
+    /// meaning folds are only what the user toggled by hand.
+    pub fold_level: Option<usize>,
 }
 
 impl View {
@@ -40,10 +49,12 @@ impl View {
             cursor: Cursor::new(),
             extra_cursors: Vec::new(),
             scroll_top: 0,
+            scroll_offset: 0.0,
             viewport_lines: 40, // sensible default, overridden by UI
             scroll_left: 0,
             viewport_cols: 80, // sensible default, overridden by UI
             folds: Vec::new(),
+            fold_level: None,
         }
     }
 
@@ -135,6 +146,34 @@ impl View {
         line
     }
 
+    /// Number of display rows from line `from` down to line `to`, counting
+    /// a closed fold as one row; negative when `to` is above `from`.
+    pub fn visible_rows_between(&self, from: usize, to: usize) -> isize {
+        let (mut line, end) = (from.min(to), from.max(to));
+        let mut rows = 0;
+        while line < end {
+            line = self.next_visible_line(line, 1, end);
+            rows += 1;
+        }
+        if to < from {
+            -rows
+        } else {
+            rows
+        }
+    }
+
+    /// The line drawn at the top of the window, and the fraction of it
+    /// scrolled past, with `scroll_offset` applied.
+    pub fn drawn_scroll_top(&self, max_line: usize) -> (usize, f64) {
+        let whole = self.scroll_offset.floor();
+        let line = if whole < 0.0 {
+            self.prev_visible_line(self.scroll_top, -whole as usize)
+        } else {
+            self.next_visible_line(self.scroll_top, whole as usize, max_line)
+        };
+        (line, self.scroll_offset - whole)
+    }
+
     /// Ensure the cursor is visible within the viewport, adjusting scroll_top.
     pub fn ensure_cursor_visible(&mut self) {
         if self.cursor.line < self.scroll_top {
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use super::buffer::BufferId;
 use super::view::View;
 
@@ -20,6 +22,8 @@ pub struct Window {
     pub id: WindowId,
     pub buffer_id: BufferId,
     pub view: View,
+    /// Window-local working directory set by `:lcd` (`None` = use the global cwd).
+    pub local_cwd: Option<PathBuf>,
 }
 
 impl Window {
@@ -28,6 +32,7 @@ impl Window {
             id,
             buffer_id,
             view: View::new(),
+            local_cwd: None,
         }
     }
 }
@@ -183,6 +188,164 @@ impl WindowLayout {
             None
         }
     }
+
+    /// Grow (`delta > 0`) or shrink `target` along `direction` by moving the
+    /// nearest enclosing split of that direction (ratio clamped to 0.1..0.9).
+    /// Returns false when no such split encloses the window.
+    pub fn resize(&mut self, target: WindowId, direction: SplitDirection, delta: f64) -> bool {
+        let WindowLayout::Split {
+            direction: dir,
+            ratio,
+            first,
+            second,
+        } = self
+        else {
+            return false;
+        };
+        let in_first = first.window_ids().contains(&target);
+        if !in_first && !second.window_ids().contains(&target) {
+            return false;
+        }
+        let child = if in_first { first } else { second };
+        if child.resize(target, direction, delta) {
+            return true;
+        }
+        if *dir != direction {
+            return false;
+        }
+        let delta = if in_first { delta } else { -delta };
+        *ratio = (*ratio + delta).clamp(0.1, 0.9);
+        true
+    }
+
+    /// Move `target` to the far edge of the layout (CTRL-W H/J/K/L): the
+    /// rest of the tree becomes one side of a new root split in `direction`,
+    /// `target` the other (`forward` = right/bottom).  Returns false when
+    /// `target` is the only window or not in the layout.
+    pub fn move_to_edge(
+        &mut self,
+        target: WindowId,
+        direction: SplitDirection,
+        forward: bool,
+    ) -> bool {
+        if self.is_single_window() || !self.window_ids().contains(&target) {
+            return false;
+        }
+        let Some(rest) = self.remove(target) else {
+            return false;
+        };
+        let moved = WindowLayout::Leaf(target);
+        let (first, second) = if forward {
+            (rest, moved)
+        } else {
+            (moved, rest)
+        };
+        *self = WindowLayout::Split {
+            direction,
+            ratio: 0.5,
+            first: Box::new(first),
+            second: Box::new(second),
+        };
+        true
+    }
+
+    /// Put `with` where the `target` leaf is.  Returns false when `target`
+    /// is not in the layout.
+    pub fn replace_leaf(&mut self, target: WindowId, with: WindowLayout) -> bool {
+        match self {
+            WindowLayout::Leaf(id) if *id == target => {
+                *self = with;
+                true
+            }
+            WindowLayout::Leaf(_) => false,
+            WindowLayout::Split { first, second, .. } => {
+                if first.window_ids().contains(&target) {
+                    first.replace_leaf(target, with)
+                } else {
+                    second.replace_leaf(target, with)
+                }
+            }
+        }
+    }
+
+    /// Give every window an equal share of space (CTRL-W =): each split's
+    /// ratio follows how many windows sit side by side in each child.
+    pub fn equalize(&mut self) {
+        if let WindowLayout::Split {
+            direction,
+            ratio,
+            first,
+            second,
+        } = self
+        {
+            let a = first.span(*direction) as f64;
+            let b = second.span(*direction) as f64;
+            *ratio = a / (a + b);
+            first.equalize();
+            second.equalize();
+        }
+    }
+
+    /// Number of windows laid out side by side along `direction`.
+    fn span(&self, direction: SplitDirection) -> usize {
+        match self {
+            WindowLayout::Leaf(_) => 1,
+            WindowLayout::Split {
+                direction: dir,
+                first,
+                second,
+                ..
+            } => {
+                if *dir == direction {
+                    first.span(direction) + second.span(direction)
+                } else {
+                    first.span(direction).max(second.span(direction))
+                }
+            }
+        }
+    }
+}
+
+/// The entry of `rects` next to `current` in `direction` (`forward` = right
+/// for vertical splits, down for horizontal ones).  It must share an edge
+/// with `current`; the one sharing the longest stretch wins, then the
+/// top/left-most.  Used for CTRL-W h/j/k/l between windows and groups.
+pub fn neighbor_in_direction<T: Copy + PartialEq>(
+    rects: &[(T, WindowRect)],
+    current: T,
+    direction: SplitDirection,
+    forward: bool,
+) -> Option<T> {
+    const EPS: f64 = 1e-6;
+    let (_, cur) = rects.iter().find(|(id, _)| *id == current)?;
+    rects
+        .iter()
+        .filter(|(id, _)| *id != current)
+        .filter_map(|(id, r)| {
+            let (touching, overlap) = match direction {
+                SplitDirection::Vertical => {
+                    let edge = if forward {
+                        r.x - (cur.x + cur.width)
+                    } else {
+                        cur.x - (r.x + r.width)
+                    };
+                    let overlap = (r.y + r.height).min(cur.y + cur.height) - r.y.max(cur.y);
+                    (edge.abs() < EPS, overlap)
+                }
+                SplitDirection::Horizontal => {
+                    let edge = if forward {
+                        r.y - (cur.y + cur.height)
+                    } else {
+                        cur.y - (r.y + r.height)
+                    };
+                    let overlap = (r.x + r.width).min(cur.x + cur.width) - r.x.max(cur.x);
+                    (edge.abs() < EPS, overlap)
+                }
+            };
+            (touching && overlap > EPS).then_some((*id, overlap))
+        })
+        .min_by(|a, b| b.1.total_cmp(&a.1))
+        .map(|(id, _)| id)
 }
 
 /// Represents a rectangular region for rendering.
@@ -740,6 +903,88 @@ mod tests {
 
     // ── GroupLayout tests ──────────────────────────────────────────────────
 
+    /// 1 | 2 on top of a full-width 3.
+    fn three_window_layout() -> WindowLayout {
+        let mut layout = WindowLayout::leaf(WindowId(1));
+        layout.split_at(WindowId(1), SplitDirection::Horizontal, WindowId(3), false);
+        layout.split_at(WindowId(1), SplitDirection::Vertical, WindowId(2), false);
+        layout
+    }
+
+    #[test]
+    fn test_neighbor_in_direction() {
+        let layout = three_window_layout();
+        let rects = layout.calculate_rects(WindowRect::new(0.0, 0.0, 100.0, 50.0));
+        let nb = |id, dir, fwd| neighbor_in_direction(&rects, WindowId(id), dir, fwd);
+        assert_eq!(nb(1, SplitDirection::Vertical, true), Some(WindowId(2)));
+        assert_eq!(nb(2, SplitDirection::Vertical, false), Some(WindowId(1)));
+        assert_eq!(nb(2, SplitDirection::Vertical, true), None);
+        assert_eq!(nb(2, SplitDirection::Horizontal, true), Some(WindowId(3)));
+        // Both top windows border 3 equally: the left one wins.
+        assert_eq!(nb(3, SplitDirection::Horizontal, false), Some(WindowId(1)));
+        assert_eq!(nb(3, SplitDirection::Vertical, true), None);
+    }
+
+    #[test]
+    fn test_window_layout_resize_and_equalize() {
+        let mut layout = three_window_layout();
+        // Window 2 sits in the second half of the vertical split.
+        assert!(layout.resize(WindowId(2), SplitDirection::Vertical, 0.1));
+        // Heights move the outer split, whichever top window asks.
+        assert!(layout.resize(WindowId(2), SplitDirection::Horizontal, 0.2));
+        assert!(layout.resize(WindowId(3), SplitDirection::Horizontal, 0.05));
+        let rects = layout.calculate_rects(WindowRect::new(0.0, 0.0, 100.0, 100.0));
+        let (_, r1) = rects[0];
+        let (_, r3) = rects[2];
+        assert!((r1.width - 40.0).abs() < 1e-9);
+        assert!((r3.height - 35.0).abs() < 1e-9);
+        // No split of that direction encloses a lone window.
+        assert!(!WindowLayout::leaf(WindowId(1)).resize(
+            WindowId(1),
+            SplitDirection::Vertical,
+            0.1
+        ));
+
+        // Three side-by-side columns: the outer split gets 1/3, not 1/2.
+        let mut cols = WindowLayout::leaf(WindowId(1));
+        cols.split_at(WindowId(1), SplitDirection::Vertical, WindowId(2), false);
+        cols.split_at(WindowId(2), SplitDirection::Vertical, WindowId(3), false);
+        cols.resize(WindowId(1), SplitDirection::Vertical, 0.3);
+        cols.equalize();
+        for (_, r) in cols.calculate_rects(WindowRect::new(0.0, 0.0, 90.0, 10.0)) {
+            assert!((r.width - 30.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_window_layout_move_to_edge() {
+        let mut layout = three_window_layout();
+        // Window 2 (top right) to the far left, spanning the full height.
+        assert!(layout.move_to_edge(WindowId(2), SplitDirection::Vertical, false));
+        assert_eq!(
+            layout.window_ids(),
+            vec![WindowId(2), WindowId(1), WindowId(3)]
+        );
+        let rects = layout.calculate_rects(WindowRect::new(0.0, 0.0, 100.0, 100.0));
+        let (_, r2) = rects[0];
+        assert_eq!((r2.x, r2.height), (0.0, 100.0));
+        // Window 2 to the bottom, spanning the full width.
+        assert!(layout.move_to_edge(WindowId(2), SplitDirection::Horizontal, true));
+        assert_eq!(
+            layout.window_ids(),
+            vec![WindowId(1), WindowId(3), WindowId(2)]
+        );
+        let rects = layout.calculate_rects(WindowRect::new(0.0, 0.0, 100.0, 100.0));
+        let (_, r2) = rects[2];
+        assert_eq!((r2.y, r2.width), (50.0, 100.0));
+
+        assert!(!WindowLayout::leaf(WindowId(1)).move_to_edge(
+            WindowId(1),
+            SplitDirection::Vertical,
+            true
+        ));
+    }
+
     #[test]
     fn test_group_layout_single() {
         let layout = GroupLayout::leaf(GroupId(0));
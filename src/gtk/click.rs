@@ -139,6 +139,10 @@ pub(super) fn pixel_to_click_target(
     let editor_bounds = WindowRect::new(0.0, 0.0, width, editor_bottom);
     let (window_rects, _dividers) =
         engine.calculate_group_window_rects(editor_bounds, tab_bar_height);
+    let window_rects: Vec<_> = window_rects
+        .into_iter()
+        .map(|(id, rect)| (id, render::winbar_content_rect(engine, rect, line_height)))
+        .collect();
     let clicked_window = window_rects.iter().find(|(_, rect)| {
         x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
     });
@@ -224,10 +228,22 @@ pub(super) fn pixel_to_click_target(
     let relative_y = y - rect.y;
     let view_row = (relative_y / line_height).floor() as usize;
 
+    let text_rows = (text_area_height / line_height).floor() as usize;
+    if let Some(&header) = engine
+        .sticky_scroll_lines(window_id, text_rows)
+        .get(view_row)
+    {
+        return ClickTarget::StickyLine(window_id, header);
+    }
+
     // Compute the buffer line and segment column offset, accounting for wrapping.
     let (line, seg_col_offset) = if engine.settings.wrap {
         // Compute viewport_cols the same way render.rs does for word-wrap segments.
-        let scrollbar_px: f64 = if char_width > 1.0 { 8.0 } else { 0.0 };
+        let scrollbar_px: f64 = if char_width > 1.0 {
+            8.0 + render_mod::minimap_reserve(engine, window_id, rect, char_width)
+        } else {
+            0.0
+        };
         let render_viewport_cols = if char_width > 0.0 {
             let total_chars = ((rect.width - scrollbar_px) / char_width).floor() as usize;
             total_chars.saturating_sub(gutter_char_width).max(1)
@@ -289,30 +305,48 @@ pub(super) fn pixel_to_click_target(
     let line = line.min(buffer.content.len_lines().saturating_sub(1));
 
     // For a monospace font, column = floor(relative_x / char_width).
-    // Tabs are displayed as 4 spaces, so we walk the line chars mapping
-    // display columns to logical columns without any Pango calls.
+    // Tabs expand to the next `tabstop` multiple, so map the display column
+    // back to a logical column with the same helper the TUI uses.
     let display_col = if char_width > 0.0 && relative_x >= 0.0 {
         (relative_x / char_width) as usize
     } else {
         0
     };
 
-    // Walk the line text starting from segment_col_offset to find the
-    // logical column corresponding to the clicked display column.
+    // Wrapped segments start at display column 0 and never scroll
+    // horizontally; unwrapped lines are offset by the view's scroll_left.
     let line_text = buffer.content.line(line).to_string();
-    let mut col = seg_col_offset;
-    let mut display_pos = 0;
-    for ch in line_text.chars().skip(seg_col_offset) {
-        if display_pos >= display_col {
-            break;
-        }
-        if ch == '\t' {
-            display_pos += 4;
-        } else {
-            display_pos += 1;
-        }
-        col += 1;
-    }
+    let segment: String = line_text
+        .chars()
+        .skip(seg_col_offset)
+        .take_while(|c| *c != '\n' && *c != '\r')
+        .collect();
+    let scroll_left = if engine.settings.wrap {
+        0
+    } else {
+        view.scroll_left
+    };
+    let tabstop = buffer_state.tabstop(&engine.settings);
+    // Inlay hints are drawn inline but are not part of the buffer; clicks on
+    // or after them must land on real columns.
+    let hints: Vec<render_mod::InlayHintMark> = if engine.settings.inlay_hints {
+        render_mod::inlay_hint_marks(&buffer_state.inlay_hints, line, &line_text)
+            .into_iter()
+            .filter(|h| {
+                h.col >= seg_col_offset && h.col <= seg_col_offset + segment.chars().count()
+            })
+            .map(|h| render_mod::InlayHintMark {
+                col: h.col - seg_col_offset,
+                label: h.label,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let display_col =
+        render_mod::strip_inlay_hint_cols(&segment, &hints, display_col + scroll_left, tabstop);
+    let col =
+        seg_col_offset + render_mod::display_col_to_buffer_col(&segment, display_col, tabstop, 0);
 
     ClickTarget::BufferPos(window_id, line, col)
 }
@@ -401,6 +435,10 @@ pub(super) fn handle_mouse_click(
             engine.open_editor_action_menu(group_id, 0, 0);
             (None, None)
         }
+        ClickTarget::StickyLine(wid, line) => {
+            engine.sticky_scroll_jump(wid, line);
+            (Some(false), None)
+        }
         _ => (None, None),
     }
 }
@@ -24,6 +24,9 @@ pub(super) fn make_theme_css(theme: &Theme) -> String {
     let entry_bg = theme.active_background.to_hex();
     let border_col = theme.separator.to_hex();
     let sb_thumb = theme.scrollbar_thumb.to_hex();
+    let close_bg = theme.diagnostic_error.to_hex();
+    let close_active_bg = theme.diagnostic_error.lighten(0.3).to_hex();
+    let close_fg = theme.status_fg.to_hex();
     let _comment_fg = theme.comment.to_hex();
     format!(
         r#"
@@ -68,6 +71,15 @@ pub(super) fn make_theme_css(theme: &Theme) -> String {
         .window-control:active {{
             background-color: {hover_bg};
         }}
+        /* Close button: error red on hover, matching Windows/VSCode */
+        .window-control:last-child:hover {{
+            background-color: {close_bg};
+            color: {close_fg};
+        }}
+        .window-control:last-child:active {{
+            background-color: {close_active_bg};
+            color: {close_fg};
+        }}
 
         /* Sidebar */
         .sidebar-container {{
@@ -331,15 +343,7 @@ pub(super) const STATIC_CSS: &str = "
             min-width: 46px;
             min-height: 30px;
         }
-        /* Close button: red on hover, matching Windows/VSCode */
-        .window-control:last-child:hover {
-            background-color: #e81123;
-            color: #ffffff;
-        }
-        .window-control:last-child:active {
-            background-color: #f1707a;
-            color: #ffffff;
-        }
+        /* Close button hover colours: see make_theme_css() */
 
         /* Activity bar, sidebar, treeview: see make_theme_css() — applied dynamically */
         
@@ -370,18 +374,9 @@ pub(super) const STATIC_CSS: &str = "
             min-height: 40px;
             padding: 0;
             margin: 0;
-            background: rgba(255, 255, 255, 0.3);
             border-radius: 2px;
         }
 
-        scrollbar slider:hover {
-            background: rgba(255, 255, 255, 0.5);
-        }
-
-        scrollbar slider:active {
-            background: rgba(255, 255, 255, 0.7);
-        }
-
         /* Scrollbars — subtle but always visible */
         scrollbar:not(:hover):not(:active) {
             opacity: 0.4;
@@ -391,22 +386,12 @@ pub(super) const STATIC_CSS: &str = "
         /* Search toggle buttons (Aa / Ab| / .*) — base layout only, colors via make_theme_css */
         .search-toggle-btn {
             background: transparent;
-            color: #808080;
-            border: 1px solid #3e3e42;
             border-radius: 2px;
             padding: 2px 6px;
             min-width: 0;
             min-height: 0;
             font-size: 12px;
         }
-        .search-toggle-btn:hover {
-            background-color: #2a2d2e;
-        }
-        .search-toggle-btn:checked {
-            background-color: #0e639c;
-            color: #ffffff;
-            border-color: #0e639c;
-        }
 
         /* Horizontal editor scrollbar — overlays the bottom of editor content.
            Semi-transparent like VSCode so text beneath is still visible.
@@ -428,15 +413,11 @@ pub(super) const STATIC_CSS: &str = "
             padding: 0;
         }
         .h-editor-scrollbar slider {
-            background: rgba(100, 100, 100, 0.45);
             border-radius: 2px;
             min-height: 0;
             min-width: 20px;
             margin: 1px 0;
         }
-        .h-editor-scrollbar slider:hover {
-            background: rgba(150, 150, 150, 0.7);
-        }
 
         /* Settings sidebar form — color-dependent rules in make_theme_css() */
         .settings-category-header {
@@ -25,6 +25,7 @@ pub(super) fn draw_editor(
     mouse_pos: (f64, f64),
     tab_visible_counts_out: &Rc<RefCell<Vec<(crate::core::window::GroupId, usize)>>>,
     status_segment_map_out: &Rc<RefCell<StatusSegmentMap>>,
+    cursor_blink_on: bool,
 ) {
     let theme = Theme::from_name(&engine.settings.colorscheme);
 
@@ -152,6 +153,7 @@ pub(super) fn draw_editor(
             rendered_window,
             char_width,
             line_height,
+            cursor_blink_on || !rendered_window.cursor_blink,
         );
     }
 
@@ -472,6 +474,14 @@ pub(super) fn draw_editor(
         );
     }
 
+    // 5h'. Minimap strip on the right of the active window (`:set minimap`),
+    // under the h scrollbars.
+    for rendered_window in &screen.windows {
+        if let Some(ref minimap) = rendered_window.minimap {
+            draw_minimap(cr, &theme, minimap);
+        }
+    }
+
     // 5i. Draw horizontal scrollbars in Cairo (VSCode-style overlay on window bottom)
     draw_h_scrollbars(
         cr,
@@ -509,6 +519,61 @@ pub(super) fn draw_editor(
         }
     }
 
+    // 5j'. Draw winbars in the row above each window's text.
+    for rendered_window in &screen.windows {
+        if let Some(ref winbar) = rendered_window.winbar {
+            let wr = &rendered_window.rect;
+            let bar_y = wr.y - line_height;
+            let (r, g, b) = theme.winbar_bg.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            cr.rectangle(wr.x, bar_y, wr.width, line_height);
+            cr.fill().ok();
+            cr.save().ok();
+            cr.rectangle(wr.x, bar_y, wr.width, line_height);
+            cr.clip();
+            let (r, g, b) = theme.breadcrumb_active_fg.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            layout.set_attributes(None);
+            layout.set_text(winbar);
+            cr.move_to(wr.x + char_width, bar_y);
+            pangocairo::show_layout(cr, &layout);
+            cr.restore().ok();
+        }
+    }
+
+    // 5j''. Sticky scroll headers pinned over each window's first rows.
+    for rendered_window in &screen.windows {
+        if rendered_window.sticky_lines.is_empty() {
+            continue;
+        }
+        let wr = &rendered_window.rect;
+        let gutter_width = rendered_window.gutter_char_width as f64 * char_width;
+        cr.save().ok();
+        cr.rectangle(wr.x, wr.y, wr.width, wr.height);
+        cr.clip();
+        for (row, sticky) in rendered_window.sticky_lines.iter().enumerate() {
+            let y = wr.y + row as f64 * line_height;
+            let (r, g, b) = theme.sticky_bg.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            cr.rectangle(wr.x, y, wr.width, line_height);
+            cr.fill().ok();
+            let (r, g, b) = theme.line_number_fg.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            layout.set_attributes(None);
+            layout.set_text(&sticky.gutter_text);
+            cr.move_to(wr.x, y);
+            pangocairo::show_layout(cr, &layout);
+            let (r, g, b) = theme.foreground.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            layout.set_text(sticky.raw_text.trim_end_matches(['\n', '\r']));
+            layout.set_attributes(Some(&build_pango_attrs(&sticky.spans)));
+            cr.move_to(wr.x + gutter_width, y);
+            pangocairo::show_layout(cr, &layout);
+        }
+        layout.set_attributes(None);
+        cr.restore().ok();
+    }
+
     // 5k–7. Status line, wildmenu, and command line.
     if let Some(ref status) = screen.separated_status_line {
         // noslat + terminal: [terminal][debug] ... [sep_status][wildmenu?][cmd]
@@ -607,6 +672,16 @@ pub(super) fn draw_editor(
         line_height,
     );
 
+    draw_which_key_popup(
+        cr,
+        &layout,
+        &screen,
+        &theme,
+        &editor_bounds,
+        line_height,
+        char_width,
+    );
+
     let btn_rects = draw_dialog_popup(
         cr,
         &layout,
@@ -677,6 +752,42 @@ pub(super) fn draw_h_scrollbars(
     }
 }
 
+/// Draw a minimap strip: one thin bar per non-blank run of each sampled line,
+/// with a translucent rectangle over the lines the window shows.
+pub(super) fn draw_minimap(cr: &Context, theme: &Theme, minimap: &render::Minimap) {
+    let rect = &minimap.rect;
+    cr.save().ok();
+    cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+    cr.clip();
+    let (r, g, b) = theme.background.to_cairo();
+    cr.set_source_rgb(r, g, b);
+    cr.paint().ok();
+    for (row, mm_row) in minimap.rows.iter().enumerate() {
+        let y = rect.y + row as f64 * render::MINIMAP_ROW_PX;
+        for &(col, len, color) in &mm_row.blocks {
+            let (r, g, b) = color.to_cairo();
+            cr.set_source_rgba(r, g, b, 0.75);
+            cr.rectangle(
+                rect.x + col as f64 * render::MINIMAP_COL_PX,
+                y,
+                len as f64 * render::MINIMAP_COL_PX,
+                render::MINIMAP_ROW_PX - 0.5,
+            );
+            cr.fill().ok();
+        }
+    }
+    let (r, g, b) = theme.scrollbar_thumb.to_cairo();
+    cr.set_source_rgba(r, g, b, 0.25);
+    cr.rectangle(
+        rect.x,
+        rect.y + minimap.viewport_y,
+        rect.width,
+        minimap.viewport_height,
+    );
+    cr.fill().ok();
+    cr.restore().ok();
+}
+
 /// Draw the tab drag overlay: a semi-transparent highlight over the drop zone
 /// and a ghost label near the cursor.
 #[allow(clippy::too_many_arguments)]
@@ -1239,6 +1350,7 @@ pub(super) fn draw_window(
     rw: &RenderedWindow,
     char_width: f64,
     line_height: f64,
+    cursor_visible: bool,
 ) {
     let rect = &rw.rect;
 
@@ -1260,6 +1372,16 @@ pub(super) fn draw_window(
     cr.rectangle(rect.x, rect.y, rect.width, rect.height);
     cr.fill().ok();
 
+    // Mid smooth-scroll, shift everything up by the fractional line offset,
+    // clipped so the partial rows at the edges stay inside the window.
+    let scroll_frac_px = rw.scroll_frac * line_height;
+    cr.save().ok();
+    if scroll_frac_px > 0.0 {
+        cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        cr.clip();
+        cr.translate(0.0, -scroll_frac_px);
+    }
+
     // Cursorline / Diff / DAP stopped-line background (drawn before selection so selection is on top)
     for (view_idx, rl) in rw.lines.iter().enumerate() {
         let y = rect.y + view_idx as f64 * line_height;
@@ -1318,6 +1440,22 @@ pub(super) fn draw_window(
         );
     }
 
+    // Active snippet placeholder
+    if let Some(ph) = &rw.snippet_placeholder {
+        draw_visual_selection(
+            cr,
+            layout,
+            ph,
+            &rw.lines,
+            rect,
+            line_height,
+            rw.scroll_top,
+            text_x_offset,
+            theme.selection,
+            theme.selection_alpha,
+        );
+    }
+
     // Yank highlight (brief flash after yank)
     if let Some(yh) = &rw.yank_highlight {
         draw_visual_selection(
@@ -1437,7 +1575,7 @@ pub(super) fn draw_window(
         rect.x + gutter_width,
         rect.y,
         rect.width - gutter_width,
-        rect.height,
+        rect.height + scroll_frac_px,
     );
     cr.clip();
 
@@ -1445,10 +1583,7 @@ pub(super) fn draw_window(
     for (view_idx, rl) in rw.lines.iter().enumerate() {
         let y = rect.y + view_idx as f64 * line_height;
 
-        layout.set_text(&rl.raw_text);
-
-        let attrs = build_pango_attrs(&rl.spans);
-        layout.set_attributes(Some(&attrs));
+        set_line_layout(layout, rl, theme);
 
         let (fr, fg_g, fb) = theme.foreground.to_cairo();
         cr.set_source_rgb(fr, fg_g, fb);
@@ -1467,8 +1602,24 @@ pub(super) fn draw_window(
             }
         }
 
-        // Inline annotation / virtual text (e.g. git blame)
-        if let Some(ann) = &rl.annotation {
+        // Git blame: right-aligned, clipped when the window is narrow.
+        if let Some(ann) = rl.annotation.as_ref().filter(|_| rw.blame_annotations) {
+            let text_cols = ((layout.pixel_size().0 as f64 - h_scroll_offset) / char_width)
+                .ceil()
+                .max(0.0) as usize;
+            let width_cols = ((rect.width - gutter_width) / char_width).floor() as usize;
+            if let Some((start, text)) =
+                render::right_aligned_annotation(ann, text_cols, width_cols)
+            {
+                let (br, bg_, bb) = theme.blame_fg.to_cairo();
+                cr.set_source_rgb(br, bg_, bb);
+                cr.move_to(rect.x + gutter_width + start as f64 * char_width, y);
+                layout.set_text(&text);
+                layout.set_attributes(None);
+                pangocairo::show_layout(cr, layout);
+            }
+        } else if let Some(ann) = &rl.annotation {
+            // Inline annotation / virtual text (e.g. plugin annotations)
             let text_pixel_width = layout.pixel_size().0 as f64;
             let ann_x = text_x_offset + text_pixel_width + char_width * 2.0;
             let (ar, ag, ab) = theme.annotation_fg.to_cairo();
@@ -1477,6 +1628,23 @@ pub(super) fn draw_window(
             layout.set_text(ann);
             layout.set_attributes(None);
             pangocairo::show_layout(cr, layout);
+        } else if let Some(vt) = &rl.diagnostic_virtual_text {
+            // Diagnostic virtual text after the content, clipped to the window.
+            let text_cols = ((layout.pixel_size().0 as f64 - h_scroll_offset) / char_width)
+                .ceil()
+                .max(0.0) as usize;
+            let width_cols = ((rect.width - gutter_width) / char_width).floor() as usize;
+            if let Some((start, text)) = vt.fit(text_cols, width_cols) {
+                let (vr, vg, vb) = theme.diagnostic_virtual_text(vt.severity).to_cairo();
+                cr.set_source_rgb(vr, vg, vb);
+                cr.move_to(rect.x + gutter_width + start as f64 * char_width, y);
+                layout.set_text(&text);
+                let attrs = pango::AttrList::new();
+                attrs.insert(pango::AttrInt::new_style(pango::Style::Italic));
+                layout.set_attributes(Some(&attrs));
+                pangocairo::show_layout(cr, layout);
+                layout.set_attributes(None);
+            }
         }
 
         // Indent guides: thin vertical lines at each guide column
@@ -1497,34 +1665,41 @@ pub(super) fn draw_window(
             }
         }
 
-        // Color columns: tinted background rectangle at each column
+        // Color columns: 1px ruler at each column's left edge.  Positions
+        // follow `text_x_offset`, so they scroll with the text, and the
+        // text-area clip keeps them out of the gutter.
         if !rl.colorcolumns.is_empty() {
             let (cr2, cg, cb) = theme.colorcolumn_bg.to_cairo();
             cr.set_source_rgb(cr2, cg, cb);
             for &cc_col in &rl.colorcolumns {
-                let cx = text_x_offset + cc_col as f64 * char_width;
-                cr.rectangle(cx, y, char_width, line_height);
+                let cx = (text_x_offset + cc_col as f64 * char_width).round();
+                cr.rectangle(cx, y, 1.0, line_height);
                 cr.fill().ok();
             }
         }
 
-        // Bracket match highlighting
+        // Restore layout to match rendered text (needed for correct
+        // index_to_pos when font_scale != 1.0, e.g. markdown headings).
+        set_line_layout(layout, rl, theme);
+
+        // `:set list` whitespace glyphs
+        if let Some(lc) = rw.list_chars.as_ref().filter(|_| !rl.is_ghost_continuation) {
+            draw_list_glyphs(cr, layout, rl, lc, text_x_offset, y, char_width, theme);
+        }
+
+        // Bracket match highlighting (positioned via the layout so inlay
+        // hints before the bracket are accounted for)
         for &(bm_view_line, bm_col) in &rw.bracket_match_positions {
             if bm_view_line == view_idx {
                 let (br, bg_c, bb) = theme.bracket_match_bg.to_cairo();
                 cr.set_source_rgba(br, bg_c, bb, 0.6);
-                let bx = text_x_offset + bm_col as f64 * char_width;
+                let pos = layout.index_to_pos(line_layout_byte(rl, bm_col) as i32);
+                let bx = text_x_offset + pos.x() as f64 / pango::SCALE as f64;
                 cr.rectangle(bx, y, char_width, line_height);
                 cr.fill().ok();
             }
         }
 
-        // Restore layout to match rendered text (needed for correct
-        // index_to_pos when font_scale != 1.0, e.g. markdown headings).
-        layout.set_text(&rl.raw_text);
-        let line_attrs = build_pango_attrs(&rl.spans);
-        layout.set_attributes(Some(&line_attrs));
-
         // Diagnostic underlines (wavy squiggles)
         for dm in &rl.diagnostics {
             let diag_color = match dm.severity {
@@ -1537,18 +1712,8 @@ pub(super) fn draw_window(
             cr.set_source_rgb(dr, dg, db);
             cr.set_line_width(1.0);
 
-            let start_byte = rl
-                .raw_text
-                .char_indices()
-                .nth(dm.start_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
-            let end_byte = rl
-                .raw_text
-                .char_indices()
-                .nth(dm.end_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
+            let start_byte = line_layout_byte(rl, dm.start_col);
+            let end_byte = line_layout_byte(rl, dm.end_col);
 
             let start_pos = layout.index_to_pos(start_byte as i32);
             let end_pos = layout.index_to_pos(end_byte as i32);
@@ -1589,18 +1754,8 @@ pub(super) fn draw_window(
             cr.set_source_rgb(sr, sg, sb);
             cr.set_line_width(1.0);
 
-            let start_byte = rl
-                .raw_text
-                .char_indices()
-                .nth(sm.start_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
-            let end_byte = rl
-                .raw_text
-                .char_indices()
-                .nth(sm.end_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
+            let start_byte = line_layout_byte(rl, sm.start_col);
+            let end_byte = line_layout_byte(rl, sm.end_col);
 
             let start_pos = layout.index_to_pos(start_byte as i32);
             let end_pos = layout.index_to_pos(end_byte as i32);
@@ -1620,12 +1775,10 @@ pub(super) fn draw_window(
     }
 
     cr.restore().ok();
-    // Render cursor
-    if let Some((cursor_pos, cursor_shape)) = &rw.cursor {
+    // Render cursor (skipped during the "off" phase of a blink)
+    if let Some((cursor_pos, cursor_shape)) = rw.cursor.as_ref().filter(|_| cursor_visible) {
         if let Some(rl) = rw.lines.get(cursor_pos.view_line) {
-            layout.set_text(&rl.raw_text);
-            let cursor_attrs = build_pango_attrs(&rl.spans);
-            layout.set_attributes(Some(&cursor_attrs));
+            set_line_layout(layout, rl, theme);
 
             // When Ctrl+D selections are active, draw bar at right edge (col+1)
             let render_col = if !rw.extra_selections.is_empty() && *cursor_shape == CursorShape::Bar
@@ -1634,19 +1787,14 @@ pub(super) fn draw_window(
             } else {
                 cursor_pos.col
             };
-            let byte_offset: usize = rl
-                .raw_text
-                .char_indices()
-                .nth(render_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
+            let byte_offset: usize = line_layout_byte(rl, render_col);
 
             let pos = layout.index_to_pos(byte_offset as i32);
             let cursor_x = text_x_offset + pos.x() as f64 / pango::SCALE as f64;
             let char_w = pos.width() as f64 / pango::SCALE as f64;
             let cursor_y = rect.y + cursor_pos.view_line as f64 * line_height;
 
-            let (cr_r, cr_g, cr_b) = theme.cursor.to_cairo();
+            let (cr_r, cr_g, cr_b) = rw.cursor_color.unwrap_or(theme.cursor).to_cairo();
             let char_w = if char_w > 0.0 {
                 char_w
             } else {
@@ -1677,15 +1825,8 @@ pub(super) fn draw_window(
     if let Some((cursor_pos, _)) = &rw.cursor {
         if let Some(rl) = rw.lines.get(cursor_pos.view_line) {
             if let Some(ghost) = &rl.ghost_suffix {
-                layout.set_text(&rl.raw_text);
-                let ghost_line_attrs = build_pango_attrs(&rl.spans);
-                layout.set_attributes(Some(&ghost_line_attrs));
-                let byte_offset: usize = rl
-                    .raw_text
-                    .char_indices()
-                    .nth(cursor_pos.col)
-                    .map(|(i, _)| i)
-                    .unwrap_or(rl.raw_text.len());
+                set_line_layout(layout, rl, theme);
+                let byte_offset: usize = line_layout_byte(rl, cursor_pos.col);
                 let pos = layout.index_to_pos(byte_offset as i32);
                 let ghost_x = text_x_offset + pos.x() as f64 / pango::SCALE as f64;
                 let ghost_y = rect.y + cursor_pos.view_line as f64 * line_height;
@@ -1710,21 +1851,14 @@ pub(super) fn draw_window(
     let (cr_r, cr_g, cr_b) = theme.cursor.to_cairo();
     for extra_pos in &rw.extra_cursors {
         if let Some(rl) = rw.lines.get(extra_pos.view_line) {
-            layout.set_text(&rl.raw_text);
-            let extra_attrs = build_pango_attrs(&rl.spans);
-            layout.set_attributes(Some(&extra_attrs));
+            set_line_layout(layout, rl, theme);
             // When Ctrl+D selections are active, draw bar at right edge (col+1)
             let render_col = if has_extra_sels && extra_cursor_shape == CursorShape::Bar {
                 extra_pos.col + 1
             } else {
                 extra_pos.col
             };
-            let byte_offset: usize = rl
-                .raw_text
-                .char_indices()
-                .nth(render_col)
-                .map(|(i, _)| i)
-                .unwrap_or(rl.raw_text.len());
+            let byte_offset: usize = line_layout_byte(rl, render_col);
             let pos = layout.index_to_pos(byte_offset as i32);
             let ex = text_x_offset + pos.x() as f64 / pango::SCALE as f64;
             let ew = {
@@ -1756,6 +1890,114 @@ pub(super) fn draw_window(
             }
         }
     }
+    cr.restore().ok();
+}
+
+/// Lay out a rendered line in `layout` with its syntax attributes. Inlay
+/// hints are spliced into the text as dimmed italic runs; use
+/// [`line_layout_byte`] to map buffer columns into the resulting text.
+pub(super) fn set_line_layout(layout: &pango::Layout, rl: &render::RenderedLine, theme: &Theme) {
+    if rl.inlay_hints.is_empty() {
+        layout.set_text(&rl.raw_text);
+        layout.set_attributes(Some(&build_pango_attrs(&rl.spans)));
+        return;
+    }
+    let (text, hint_ranges) = render::inlay_hint_display(rl);
+    // Shift spans past the hints that precede them. The end is mapped from
+    // the span's last char so a hint anchored right after the span stays out.
+    let spans: Vec<StyledSpan> = rl
+        .spans
+        .iter()
+        .filter(|sp| sp.end_byte > sp.start_byte && sp.end_byte <= rl.raw_text.len())
+        .map(|sp| {
+            let last = rl.raw_text[..sp.end_byte]
+                .chars()
+                .next_back()
+                .map_or(0, |c| c.len_utf8());
+            StyledSpan {
+                start_byte: render::inlay_hint_display_byte(rl, sp.start_byte),
+                end_byte: render::inlay_hint_display_byte(rl, sp.end_byte - last) + last,
+                style: sp.style,
+            }
+        })
+        .collect();
+    let attrs = build_pango_attrs(&spans);
+    let (hr, hg, hb) = theme.inlay_hint_fg.to_pango_u16();
+    for (start, end) in hint_ranges {
+        let mut fg = AttrColor::new_foreground(hr, hg, hb);
+        fg.set_start_index(start as u32);
+        fg.set_end_index(end as u32);
+        attrs.insert(fg);
+        let mut it = pango::AttrInt::new_style(pango::Style::Italic);
+        it.set_start_index(start as u32);
+        it.set_end_index(end as u32);
+        attrs.insert(it);
+    }
+    layout.set_text(&text);
+    layout.set_attributes(Some(&attrs));
+}
+
+/// Draw `:set list` glyphs over a line's tabs, spaces and end.  Positions come
+/// from the line's Pango layout, so tabs keep their tab-stop width and the
+/// text itself is never altered.  Leaves `layout` set to the line again.
+#[allow(clippy::too_many_arguments)]
+fn draw_list_glyphs(
+    cr: &Context,
+    layout: &pango::Layout,
+    rl: &render::RenderedLine,
+    lc: &ListChars,
+    x: f64,
+    y: f64,
+    char_width: f64,
+    theme: &Theme,
+) {
+    let text = rl.raw_text.trim_end_matches(['\n', '\r']);
+    let trail_start = render::trailing_whitespace_col(text);
+    let mut glyphs: Vec<(f64, char)> = Vec::new();
+    for (i, ch) in text.chars().enumerate() {
+        let Some((first, fill)) = render::list_glyphs(lc, ch, i >= trail_start) else {
+            continue;
+        };
+        let pos = layout.index_to_pos(line_layout_byte(rl, i) as i32);
+        let gx = x + pos.x() as f64 / pango::SCALE as f64;
+        glyphs.push((gx, first));
+        if let Some(fill) = fill {
+            let width = pos.width() as f64 / pango::SCALE as f64;
+            let cells = (width / char_width).round() as usize;
+            for k in 1..cells {
+                glyphs.push((gx + k as f64 * char_width, fill));
+            }
+        }
+    }
+    if let Some(eol) = lc.eol.filter(|_| rl.raw_text.ends_with('\n')) {
+        let pos = layout.index_to_pos(line_layout_byte(rl, text.chars().count()) as i32);
+        glyphs.push((x + pos.x() as f64 / pango::SCALE as f64, eol));
+    }
+    if glyphs.is_empty() {
+        return;
+    }
+    let (wr, wg, wb) = theme.whitespace_fg.to_cairo();
+    cr.set_source_rgb(wr, wg, wb);
+    layout.set_attributes(None);
+    let mut buf = [0u8; 4];
+    for (gx, glyph) in glyphs {
+        layout.set_text(glyph.encode_utf8(&mut buf));
+        cr.move_to(gx, y);
+        pangocairo::show_layout(cr, layout);
+    }
+    set_line_layout(layout, rl, theme);
+}
+
+/// Byte offset of buffer char `col` within the text laid out by
+/// [`set_line_layout`] (i.e. after any inlay hints before it).
+pub(super) fn line_layout_byte(rl: &render::RenderedLine, col: usize) -> usize {
+    let raw = rl
+        .raw_text
+        .char_indices()
+        .nth(col)
+        .map(|(i, _)| i)
+        .unwrap_or(rl.raw_text.len());
+    render::inlay_hint_display_byte(rl, raw)
 }
 
 /// Convert a slice of [`StyledSpan`]s into a Pango [`AttrList`].
@@ -1823,7 +2065,7 @@ pub(super) fn draw_visual_selection(
         if rl.is_wrap_continuation || rl.is_ghost_continuation {
             continue;
         }
-        if rl.diff_status == Some(crate::core::engine::DiffLine::Padding) {
+        if rl.diff_status == Some(crate::core::engine::DiffLine::Padding) || rl.is_virtual {
             continue;
         }
         // Overwrite with latest view_idx → picks the content row, not a
@@ -1838,6 +2080,7 @@ pub(super) fn draw_visual_selection(
                 if rl.line_idx >= sel.start_line
                     && rl.line_idx <= sel.end_line
                     && rl.diff_status != Some(crate::core::engine::DiffLine::Padding)
+                    && !rl.is_virtual
                     && !rl.is_ghost_continuation
                 {
                     let y = rect.y + view_idx as f64 * line_height;
@@ -1856,36 +2099,24 @@ pub(super) fn draw_visual_selection(
                 let y = rect.y + view_idx as f64 * line_height;
                 let line_text = &rl.raw_text;
 
-                layout.set_text(line_text);
+                layout.set_text(&render::inlay_hint_display(rl).0);
                 layout.set_attributes(None);
 
                 if sel.start_line == sel.end_line {
                     // Single-line selection
-                    let start_byte = line_text
-                        .char_indices()
-                        .nth(sel.start_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let start_byte = line_layout_byte(rl, sel.start_col);
                     let start_pos = layout.index_to_pos(start_byte as i32);
                     let start_x = text_x_offset + start_pos.x() as f64 / pango::SCALE as f64;
 
                     let end_col = (sel.end_col + 1).min(line_text.chars().count());
-                    let end_byte = line_text
-                        .char_indices()
-                        .nth(end_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let end_byte = line_layout_byte(rl, end_col);
                     let end_pos = layout.index_to_pos(end_byte as i32);
                     let end_x = text_x_offset + end_pos.x() as f64 / pango::SCALE as f64;
 
                     cr.rectangle(start_x, y, end_x - start_x, line_height);
                     cr.fill().ok();
                 } else if line_idx == sel.start_line {
-                    let start_byte = line_text
-                        .char_indices()
-                        .nth(sel.start_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let start_byte = line_layout_byte(rl, sel.start_col);
                     let start_pos = layout.index_to_pos(start_byte as i32);
                     let start_x = text_x_offset + start_pos.x() as f64 / pango::SCALE as f64;
                     let (line_width, _) = layout.pixel_size();
@@ -1898,11 +2129,7 @@ pub(super) fn draw_visual_selection(
                     cr.fill().ok();
                 } else if line_idx == sel.end_line {
                     let end_col = (sel.end_col + 1).min(line_text.chars().count());
-                    let end_byte = line_text
-                        .char_indices()
-                        .nth(end_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let end_byte = line_layout_byte(rl, end_col);
                     let end_pos = layout.index_to_pos(end_byte as i32);
                     let end_x = text_x_offset + end_pos.x() as f64 / pango::SCALE as f64;
                     cr.rectangle(text_x_offset, y, end_x - text_x_offset, line_height);
@@ -1924,24 +2151,16 @@ pub(super) fn draw_visual_selection(
                 let line_text = &rl.raw_text;
                 let line_len = line_text.chars().count();
 
-                layout.set_text(line_text);
+                layout.set_text(&render::inlay_hint_display(rl).0);
                 layout.set_attributes(None);
 
                 if sel.start_col < line_len {
-                    let start_byte = line_text
-                        .char_indices()
-                        .nth(sel.start_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let start_byte = line_layout_byte(rl, sel.start_col);
                     let start_pos = layout.index_to_pos(start_byte as i32);
                     let start_x = text_x_offset + start_pos.x() as f64 / pango::SCALE as f64;
 
                     let block_end_col = (sel.end_col + 1).min(line_len);
-                    let end_byte = line_text
-                        .char_indices()
-                        .nth(block_end_col)
-                        .map(|(i, _)| i)
-                        .unwrap_or(line_text.len());
+                    let end_byte = line_layout_byte(rl, block_end_col);
                     let end_pos = layout.index_to_pos(end_byte as i32);
                     let end_x = text_x_offset + end_pos.x() as f64 / pango::SCALE as f64;
 
@@ -2060,10 +2279,77 @@ pub(super) fn draw_completion_popup(
         cr.set_source_rgb(r, g, b);
         let display = format!(" {}", candidate);
         layout.set_text(&display);
-        layout.set_attributes(None);
+        // Fuzzy-matched chars (positions are offset by the leading space)
+        let matched = menu.match_positions.get(i).filter(|m| !m.is_empty());
+        if let Some(matched) = matched {
+            let attrs = pango::AttrList::new();
+            let (mr, mg, mb) = theme.fuzzy_match_fg.to_cairo();
+            for &pos in matched {
+                let start = 1 + pos;
+                let Some(ch) = display.get(start..).and_then(|t| t.chars().next()) else {
+                    continue;
+                };
+                let mut fg = pango::AttrColor::new_foreground(
+                    (mr * 65535.0) as u16,
+                    (mg * 65535.0) as u16,
+                    (mb * 65535.0) as u16,
+                );
+                fg.set_start_index(start as u32);
+                fg.set_end_index((start + ch.len_utf8()) as u32);
+                attrs.insert(fg);
+                let mut bold = pango::AttrInt::new_weight(pango::Weight::Bold);
+                bold.set_start_index(start as u32);
+                bold.set_end_index((start + ch.len_utf8()) as u32);
+                attrs.insert(bold);
+            }
+            layout.set_attributes(Some(&attrs));
+        } else {
+            layout.set_attributes(None);
+        }
         cr.move_to(popup_x, item_y);
         pangocairo::show_layout(cr, layout);
     }
+
+    // Documentation panel for the selected item: right of the popup when it
+    // fits, otherwise to its left.
+    if !menu.documentation.is_empty() {
+        let max_len = menu
+            .documentation
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        let doc_w = (max_len + 2) as f64 * char_width;
+        let doc_h = menu.documentation.len() as f64 * line_height;
+        let screen_w = screen
+            .windows
+            .iter()
+            .map(|w| w.rect.x + w.rect.width)
+            .fold(0.0, f64::max);
+        let doc_x = if popup_x + popup_w + doc_w <= screen_w {
+            popup_x + popup_w
+        } else {
+            (popup_x - doc_w).max(0.0)
+        };
+
+        let (r, g, b) = theme.hover_bg.to_cairo();
+        cr.set_source_rgb(r, g, b);
+        cr.rectangle(doc_x, popup_y, doc_w, doc_h);
+        cr.fill().ok();
+        let (r, g, b) = theme.hover_border.to_cairo();
+        cr.set_source_rgb(r, g, b);
+        cr.rectangle(doc_x, popup_y, doc_w, doc_h);
+        cr.stroke().ok();
+
+        let (r, g, b) = theme.hover_fg.to_cairo();
+        cr.set_source_rgb(r, g, b);
+        for (i, line) in menu.documentation.iter().enumerate() {
+            layout.set_text(&format!(" {}", line));
+            layout.set_attributes(None);
+            cr.move_to(doc_x, popup_y + i as f64 * line_height);
+            pangocairo::show_layout(cr, layout);
+        }
+    }
 }
 
 pub(super) fn draw_hover_popup(
@@ -2916,7 +3202,7 @@ pub(super) fn draw_picker_popup(
         return;
     };
 
-    let has_preview = picker.preview.is_some();
+    let has_preview = picker.preview_lines.is_some();
 
     // Size adapts based on whether we have a preview pane
     let popup_w = if has_preview {
@@ -3115,12 +3401,20 @@ pub(super) fn draw_picker_popup(
         cr.rectangle(right_pane_x, sep_y, right_pane_w, rows_area_h + 2.0);
         cr.clip();
 
-        if let Some(ref preview) = picker.preview {
-            for (i, (lineno, text, is_match)) in preview.iter().enumerate().take(visible_rows) {
+        if let Some(ref note) = picker.preview_note {
+            let (r, g, b) = theme.fuzzy_border.to_cairo();
+            cr.set_source_rgb(r, g, b);
+            layout.set_text(&format!(" {note}"));
+            layout.set_attributes(None);
+            cr.move_to(right_pane_x, sep_y + 1.0);
+            pangocairo::show_layout(cr, layout);
+        } else if let Some(ref preview) = picker.preview_lines {
+            for (i, line) in preview.iter().enumerate().take(visible_rows) {
                 let item_y = sep_y + 1.0 + i as f64 * line_height;
-                let preview_text = format!("{:4}: {}", lineno, text);
+                let gutter = format!("{:4}: ", line.lineno);
+                let preview_text = format!("{gutter}{}", line.text);
 
-                if *is_match {
+                if line.is_match {
                     let (r, g, b) = theme.fuzzy_title_fg.to_cairo();
                     cr.set_source_rgb(r, g, b);
                 } else {
@@ -3128,11 +3422,20 @@ pub(super) fn draw_picker_popup(
                     cr.set_source_rgb(r, g, b);
                 }
 
+                let attrs = pango::AttrList::new();
+                for (start, end, color) in &line.spans {
+                    let (r, g, b) = color.to_pango_u16();
+                    let mut attr = pango::AttrColor::new_foreground(r, g, b);
+                    attr.set_start_index((gutter.len() + start) as u32);
+                    attr.set_end_index((gutter.len() + end) as u32);
+                    attrs.insert(attr);
+                }
                 layout.set_text(&preview_text);
-                layout.set_attributes(None);
+                layout.set_attributes(Some(&attrs));
                 cr.move_to(right_pane_x, item_y);
                 pangocairo::show_layout(cr, layout);
             }
+            layout.set_attributes(None);
         }
 
         cr.restore().ok();
@@ -3268,6 +3571,63 @@ pub(super) fn draw_tab_switcher_popup(
     }
 }
 
+/// Draw the which-key panel along the bottom of the editor area: a title
+/// row with the pending prefix, then a grid of `key → description` cells.
+pub(super) fn draw_which_key_popup(
+    cr: &Context,
+    layout: &pango::Layout,
+    screen: &render::ScreenLayout,
+    theme: &Theme,
+    editor_bounds: &WindowRect,
+    line_height: f64,
+    char_width: f64,
+) {
+    let Some(wk) = &screen.which_key else {
+        return;
+    };
+    if wk.entries.is_empty() || char_width <= 0.0 {
+        return;
+    }
+    let width_cells = (editor_bounds.width / char_width) as usize;
+    let (col_w, cols, rows) = render::which_key_grid(wk, width_cells);
+    let panel_h = (rows as f64 + 1.0) * line_height;
+    let panel_y = editor_bounds.y + editor_bounds.height - panel_h;
+
+    let (r, g, b) = theme.hover_bg.to_cairo();
+    cr.set_source_rgb(r, g, b);
+    cr.rectangle(editor_bounds.x, panel_y, editor_bounds.width, panel_h);
+    cr.fill().ok();
+    let (r, g, b) = theme.hover_border.to_cairo();
+    cr.set_source_rgb(r, g, b);
+    cr.set_line_width(1.0);
+    cr.move_to(editor_bounds.x, panel_y + 0.5);
+    cr.line_to(editor_bounds.x + editor_bounds.width, panel_y + 0.5);
+    cr.stroke().ok();
+
+    layout.set_attributes(None);
+    let (r, g, b) = theme.fuzzy_title_fg.to_cairo();
+    cr.set_source_rgb(r, g, b);
+    layout.set_text(&format!(" {}", wk.prefix));
+    cr.move_to(editor_bounds.x, panel_y);
+    pangocairo::show_layout(cr, layout);
+
+    for (idx, (key, desc)) in render::which_key_cells(wk, cols, rows).iter().enumerate() {
+        let (row, col) = (idx % rows, idx / rows);
+        let x = editor_bounds.x + (col * col_w) as f64 * char_width;
+        let y = panel_y + (row + 1) as f64 * line_height;
+        let (r, g, b) = theme.keyword.to_cairo();
+        cr.set_source_rgb(r, g, b);
+        layout.set_text(key);
+        cr.move_to(x, y);
+        pangocairo::show_layout(cr, layout);
+        let (r, g, b) = theme.hover_fg.to_cairo();
+        cr.set_source_rgb(r, g, b);
+        layout.set_text(&format!(" → {desc}"));
+        cr.move_to(x + key.chars().count() as f64 * char_width, y);
+        pangocairo::show_layout(cr, layout);
+    }
+}
+
 /// Draw a modal dialog popup centered on the screen.
 #[allow(clippy::too_many_arguments)]
 /// Returns button hit-rects `(x, y, w, h)` for each dialog button.
@@ -4483,7 +4843,12 @@ pub(super) fn draw_command_line(
         layout.set_text(&cmd.text);
         layout.set_attributes(None);
 
-        let (fr, fg, fb) = theme.command_fg.to_cairo();
+        let fg_color = if cmd.is_error {
+            theme.diagnostic_error
+        } else {
+            theme.command_fg
+        };
+        let (fr, fg, fb) = fg_color.to_cairo();
         cr.set_source_rgb(fr, fg, fb);
 
         if cmd.right_align {
@@ -21,8 +21,11 @@ use crate::icons;
 use crate::render;
 
 use core::engine::EngineAction;
+use core::explorer_filter::{ExplorerFilter, ExplorerFilterConfig};
+use core::explorer_watch::ExplorerWatcher;
 use core::lsp::DiagnosticSeverity;
-use core::settings::LineNumberMode;
+use core::paths::FileTarget;
+use core::settings::{LineNumberMode, ListChars};
 use core::{Engine, GitLineStatus, OpenMode, WindowRect};
 use render::{
     build_screen_layout, CommandLineData, CursorShape, RenderedWindow, SelectionKind,
@@ -170,6 +173,14 @@ struct App {
     h_sb_dragging: Option<HScrollDragState>,
     /// True while the mouse cursor is over any horizontal scrollbar track.
     h_sb_hovered: bool,
+    /// Window whose minimap (`:set minimap`) is being dragged.
+    minimap_dragging: Option<core::WindowId>,
+    /// Smooth-scroll animations in flight, by window.
+    smooth_scrolls: HashMap<core::WindowId, SmoothScroll>,
+    /// True while a frame-clock tick callback is driving `smooth_scrolls`.
+    smooth_scroll_ticking: Rc<Cell<bool>>,
+    /// Blink phase of cursors whose `cursorstyle` look blinks.
+    cursor_blink: Rc<CursorBlink>,
     /// Which tab close button (×) the mouse is over: (group_id.0, tab_idx).
     tab_close_hover: Option<(usize, usize)>,
     /// Cached tab slot widths per group, populated during draw_tab_bar for click hit-testing.
@@ -204,6 +215,10 @@ struct App {
     last_sc_refresh: std::time::Instant,
     /// Last time check_file_changes() was called for auto-reload detection.
     last_file_check: std::time::Instant,
+    /// Explorer filter settings the file tree was last built with.
+    explorer_filter: ExplorerFilterConfig,
+    /// Watches the root and expanded tree directories for external changes.
+    explorer_watcher: Rc<RefCell<ExplorerWatcher>>,
     /// Last time explorer tree indicators (modified/diagnostics) were refreshed.
     last_tree_indicator_update: std::time::Instant,
     /// Full-window overlay DrawingArea that draws the menu dropdown.
@@ -301,6 +316,23 @@ struct HScrollDragState {
     px_per_col: f64,
 }
 
+/// A smooth-scroll animation (`:set smoothscroll`) of one window: its
+/// `View::scroll_offset` eases from `from` display rows to 0.
+struct SmoothScroll {
+    from: f64,
+    start: std::time::Instant,
+}
+
+/// Cursor blink phase, shared by the blink tick callback and the draw function.
+struct CursorBlink {
+    /// The cursor is in the "on" phase of its blink.
+    on: Cell<bool>,
+    /// Last keystroke or click: each one restarts the blink "on".
+    since: Cell<std::time::Instant>,
+    /// A tick callback is driving the blink.
+    ticking: Cell<bool>,
+}
+
 /// Scrollbars and indicators for a single window.
 /// The horizontal scrollbar is drawn in Cairo (draw_editor) so it can be
 /// pixel-exact in height — GTK's Scrollbar widget enforces theme minimum
@@ -322,6 +354,8 @@ enum Msg {
     },
     /// Notify that a resize happened (triggers redraw).
     Resize,
+    /// The main window lost focus (drives `autosave = "focus"`).
+    FocusLost,
     /// Mouse click at (x, y) coordinates in drawing area.
     MouseClick {
         x: f64,
@@ -357,6 +391,15 @@ enum Msg {
     ConfirmDeletePath(PathBuf),
     /// Refresh the file tree from current working directory.
     RefreshFileTree,
+    /// Re-read these expanded tree directories after they changed on disk,
+    /// keeping expansion and selection intact.
+    RefreshTreeDirs(Vec<PathBuf>),
+    /// A large directory's entries, read on a background thread after its
+    /// explorer row was expanded.
+    TreeChildrenLoaded {
+        dir: PathBuf,
+        entries: Vec<core::explorer_filter::ExplorerEntry>,
+    },
     /// Focus the explorer panel (Ctrl-Shift-E).
     FocusExplorer,
     /// Toggle focus between explorer and editor.
@@ -401,16 +444,30 @@ enum Msg {
     ProjectSearchOpenResult(usize),
     /// Periodic tick to poll for background search results.
     SearchPollTick,
+    /// Frame-clock tick advancing the smooth-scroll animations.
+    SmoothScrollTick,
     /// Toggle case-sensitive project search.
     ProjectSearchToggleCase,
     /// Toggle whole-word project search.
     ProjectSearchToggleWholeWord,
     /// Toggle regex project search.
     ProjectSearchToggleRegex,
+    /// Copy the search results into the quickfix list and open it.
+    ProjectSearchToQuickfix,
     /// Project replace input text changed.
     ProjectReplaceTextChanged(String),
+    /// "Files to include" glob input changed.
+    ProjectSearchIncludeChanged(String),
+    /// "Files to exclude" glob input changed.
+    ProjectSearchExcludeChanged(String),
     /// User clicked "Replace All" button — run replace across files.
     ProjectReplaceAll,
+    /// Toggle the replace preview (before/after rows with checkboxes).
+    ProjectReplaceTogglePreview,
+    /// Check/uncheck one result for "Replace Selected".
+    ProjectSearchToggleMatch(usize),
+    /// User clicked "Selected" — replace only the checked matches.
+    ProjectReplaceSelected,
     /// Mouse scroll wheel on editor drawing area.
     MouseScroll {
         delta_x: f64,
@@ -602,7 +659,8 @@ enum Msg {
 
 #[relm4::component]
 impl SimpleComponent for App {
-    type Init = Option<PathBuf>;
+    /// The command-line file target and `--session` name.
+    type Init = (Option<FileTarget>, Option<String>);
     type Input = Msg;
     type Output = ();
 
@@ -1009,6 +1067,16 @@ impl SimpleComponent for App {
                                         sender.input(Msg::ProjectSearchToggleRegex);
                                     },
                                 },
+
+                                gtk4::Button {
+                                    set_label: "QF",
+                                    set_tooltip_text: Some("Open results in the quickfix list"),
+                                    set_css_classes: &["search-toggle-btn"],
+
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(Msg::ProjectSearchToQuickfix);
+                                    },
+                                },
                             },
 
                             // Replace input row
@@ -1045,6 +1113,69 @@ impl SimpleComponent for App {
                                         sender.input(Msg::ProjectReplaceAll);
                                     },
                                 },
+
+                                gtk4::ToggleButton {
+                                    set_label: "\u{21c4}",
+                                    set_tooltip_text: Some("Preview replacements"),
+                                    set_css_classes: &["search-toggle-btn"],
+
+                                    #[watch]
+                                    set_active: model.engine.borrow().project_replace_preview,
+
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(Msg::ProjectReplaceTogglePreview);
+                                    },
+                                },
+
+                                gtk4::Button {
+                                    set_label: "Selected",
+                                    set_tooltip_text: Some("Replace the checked matches"),
+                                    set_css_classes: &["search-toggle-btn"],
+
+                                    #[watch]
+                                    set_visible: model.engine.borrow().project_replace_preview,
+
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(Msg::ProjectReplaceSelected);
+                                    },
+                                },
+                            },
+
+                            // Include / exclude glob inputs
+                            gtk4::Entry {
+                                set_margin_start: 6,
+                                set_margin_end: 6,
+                                set_margin_bottom: 2,
+                                set_width_chars: 1,
+                                set_placeholder_text: Some("Files to include (e.g. *.rs, src/**)"),
+
+                                connect_changed[sender] => move |entry| {
+                                    sender.input(Msg::ProjectSearchIncludeChanged(
+                                        entry.text().to_string(),
+                                    ));
+                                },
+
+                                connect_activate[sender] => move |_| {
+                                    sender.input(Msg::ProjectSearchSubmit);
+                                },
+                            },
+
+                            gtk4::Entry {
+                                set_margin_start: 6,
+                                set_margin_end: 6,
+                                set_margin_bottom: 4,
+                                set_width_chars: 1,
+                                set_placeholder_text: Some("Files to exclude (e.g. *.lock)"),
+
+                                connect_changed[sender] => move |entry| {
+                                    sender.input(Msg::ProjectSearchExcludeChanged(
+                                        entry.text().to_string(),
+                                    ));
+                                },
+
+                                connect_activate[sender] => move |_| {
+                                    sender.input(Msg::ProjectSearchSubmit);
+                                },
                             },
 
                             // Status label ("N results in M files" / empty)
@@ -1723,7 +1854,7 @@ impl SimpleComponent for App {
     }
 
     fn init(
-        file_path: Self::Init,
+        (file_target, session): Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
@@ -1749,14 +1880,14 @@ impl SimpleComponent for App {
             e.plugin_init();
             // Fetch fresh extension registry in background (updates ignore_error_sources, etc.)
             e.ext_refresh();
-            if let Some(ref path) = file_path {
-                // CLI argument: open only the specified file/directory, skip session restore
-                if path.is_dir() {
-                    e.open_folder(path);
-                } else {
-                    // Load file into the initial window (reuses the scratch buffer's tab).
-                    let _ = e.open_file_with_mode(path, crate::core::engine::OpenMode::Permanent);
-                }
+            if let Some(ref name) = session {
+                // --session: restore a session saved with :mksession.
+                e.source_session(name);
+            } else if let Some(ref target) = file_target {
+                // CLI argument: open only the specified file/directory, skip session
+                // restore. A file loads into the initial window (reuses the scratch
+                // buffer's tab).
+                e.open_file_target(target);
             } else {
                 e.restore_session_files();
             }
@@ -1932,6 +2063,8 @@ impl SimpleComponent for App {
             eng.session.explorer_visible || eng.settings.explorer_visible_on_startup
         };
 
+        let explorer_watcher = Rc::new(RefCell::new(ExplorerWatcher::new()));
+
         let model = App {
             engine: engine.clone(),
             sidebar_visible,
@@ -1981,6 +2114,14 @@ impl SimpleComponent for App {
             last_clipboard_content: None,
             clipboard,
             h_sb_dragging: None,
+            minimap_dragging: None,
+            smooth_scrolls: HashMap::new(),
+            smooth_scroll_ticking: Rc::new(Cell::new(false)),
+            cursor_blink: Rc::new(CursorBlink {
+                on: Cell::new(true),
+                since: Cell::new(std::time::Instant::now()),
+                ticking: Cell::new(false),
+            }),
             h_sb_hovered: false,
             tab_close_hover: None,
             tab_slot_positions: tab_slot_positions_cell.clone(),
@@ -1998,6 +2139,8 @@ impl SimpleComponent for App {
             tab_drag_start: None,
             last_sc_refresh: std::time::Instant::now(),
             last_file_check: std::time::Instant::now(),
+            explorer_filter: ExplorerFilterConfig::from_settings(&engine.borrow().settings),
+            explorer_watcher: explorer_watcher.clone(),
             last_tree_indicator_update: std::time::Instant::now(),
             menu_dropdown_da: menu_dropdown_da_ref.clone(),
             panel_hover_da: panel_hover_da_ref.clone(),
@@ -3004,8 +3147,19 @@ impl SimpleComponent for App {
             });
         }
 
-        // Build tree from current working directory
-        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        // Auto-save when the window loses focus (`autosave = "focus"`).
+        {
+            let focus_sender = sender.input_sender().clone();
+            root.connect_is_active_notify(move |win| {
+                if !win.is_active() {
+                    focus_sender.send(Msg::FocusLost).ok();
+                }
+            });
+        }
+
+        // Build tree from the engine's working directory (the CLI directory
+        // argument, if one was given)
+        let cwd = engine.borrow().cwd.clone();
         let (dir_fg_hex, file_fg_hex) = {
             let theme = Theme::from_name(&engine.borrow().settings.colorscheme);
             (
@@ -3013,10 +3167,11 @@ impl SimpleComponent for App {
                 theme.explorer_file_fg.to_hex(),
             )
         };
+        let explorer_filter = ExplorerFilterConfig::from_settings(&engine.borrow().settings);
         build_file_tree_with_root(
             &tree_store,
             &cwd,
-            engine.borrow().settings.show_hidden_files,
+            &mut ExplorerFilter::new(&explorer_filter),
             engine.borrow().settings.explorer_sort_case_insensitive,
             &dir_fg_hex,
             &file_fg_hex,
@@ -3167,11 +3322,13 @@ impl SimpleComponent for App {
         {
             let engine_ref = engine.clone();
             let tree_store_ref = tree_store.clone();
+            let sender_expand = sender.input_sender().clone();
+            let watcher_expand = explorer_watcher.clone();
             widgets
                 .file_tree_view
                 .connect_row_expanded(move |_tree_view, iter, _tree_path| {
                     let e = engine_ref.borrow();
-                    let show_hidden = e.settings.show_hidden_files;
+                    let config = ExplorerFilterConfig::from_settings(&e.settings);
                     let case_insensitive = e.settings.explorer_sort_case_insensitive;
                     let theme = Theme::from_name(&e.settings.colorscheme);
                     let dir_fg_hex = theme.explorer_dir_fg.to_hex();
@@ -3180,11 +3337,22 @@ impl SimpleComponent for App {
                     tree_row_expanded(
                         &tree_store_ref,
                         iter,
-                        show_hidden,
+                        &config,
                         case_insensitive,
                         &dir_fg_hex,
                         &file_fg_hex,
+                        &sender_expand,
                     );
+                    let dir: String = tree_store_ref.get_value(iter, 2).get().unwrap_or_default();
+                    watcher_expand.borrow_mut().watch_dir(Path::new(&dir));
+                });
+            let tree_store_ref = tree_store.clone();
+            let watcher_collapse = explorer_watcher.clone();
+            widgets
+                .file_tree_view
+                .connect_row_collapsed(move |_tree_view, iter, _tree_path| {
+                    let dir: String = tree_store_ref.get_value(iter, 2).get().unwrap_or_default();
+                    watcher_collapse.borrow_mut().unwatch_tree(Path::new(&dir));
                 });
         }
 
@@ -3857,6 +4025,7 @@ impl SimpleComponent for App {
         let mouse_pos_for_draw = mouse_pos_cell.clone();
         let tab_vis_for_draw = tab_visible_counts_cell.clone();
         let status_seg_for_draw = model.status_segment_map.clone();
+        let cursor_blink_for_draw = model.cursor_blink.clone();
         widgets
             .drawing_area
             .set_draw_func(move |_, cr, width, height| {
@@ -3883,6 +4052,7 @@ impl SimpleComponent for App {
                         mouse_pos_for_draw.get(),
                         &tab_vis_for_draw,
                         &status_seg_for_draw,
+                        cursor_blink_for_draw.on.get(),
                     );
                 }));
                 if let Err(e) = result {
@@ -4035,9 +4205,17 @@ impl SimpleComponent for App {
         // and defer the grab until the first frame is drawn.
         root.present();
         {
+            // Started on a directory: focus the explorer instead, like netrw.
+            let focus_explorer =
+                std::mem::take(&mut model.engine.borrow_mut().explorer_focus_on_start);
             let da = widgets.drawing_area.clone();
+            let focus_sender = sender.input_sender().clone();
             gtk4::glib::idle_add_local_once(move || {
-                da.grab_focus();
+                if focus_explorer {
+                    focus_sender.send(Msg::FocusExplorer).ok();
+                } else {
+                    da.grab_focus();
+                }
             });
         }
 
@@ -4087,7 +4265,37 @@ impl SimpleComponent for App {
                 unicode,
                 ctrl,
             } => {
+                // Only page motions animate; every other key sees the scroll
+                // in flight finished, so cursor motions stay snappy.
+                let page_motion = matches!(key_name.as_str(), "Page_Up" | "Page_Down")
+                    || (ctrl && matches!(key_name.as_str(), "f" | "b" | "d" | "u"));
+                let win_id = self.engine.borrow().active_window_id();
+                let shown = self.settle_smooth_scrolls(win_id);
                 self.handle_key_press(key_name, unicode, ctrl, &sender);
+                self.restart_cursor_blink();
+                if page_motion && self.engine.borrow().active_window_id() == win_id {
+                    self.start_smooth_scroll(win_id, shown);
+                }
+            }
+            Msg::SmoothScrollTick => {
+                let mut engine = self.engine.borrow_mut();
+                let duration_ms = f64::from(engine.settings.smooth_scroll_duration.max(1));
+                self.smooth_scrolls.retain(|&win_id, anim| {
+                    let t = anim.start.elapsed().as_secs_f64() * 1000.0 / duration_ms;
+                    if t >= 1.0 {
+                        set_scroll_offset(&mut engine, win_id, 0.0);
+                        return false;
+                    }
+                    // Ease out: fast start, gentle landing.
+                    let eased = 1.0 - (1.0 - t).powi(3);
+                    set_scroll_offset(&mut engine, win_id, anim.from * (1.0 - eased));
+                    true
+                });
+                if self.smooth_scrolls.is_empty() {
+                    self.smooth_scroll_ticking.set(false);
+                }
+                drop(engine);
+                self.draw_needed.set(true);
             }
             Msg::ClearYankHighlight => {
                 self.engine.borrow_mut().clear_yank_highlight();
@@ -4131,6 +4339,11 @@ impl SimpleComponent for App {
                 }
                 self.draw_needed.set(true);
             }
+            Msg::FocusLost => {
+                if self.engine.borrow_mut().on_focus_lost() {
+                    self.draw_needed.set(true);
+                }
+            }
             Msg::MouseClick {
                 x,
                 y,
@@ -4138,7 +4351,12 @@ impl SimpleComponent for App {
                 height,
                 alt,
             } => {
+                // Clicks map pixels through the final viewport, not a frame
+                // of a smooth scroll.
+                let win_id = self.engine.borrow().active_window_id();
+                self.settle_smooth_scrolls(win_id);
                 self.handle_mouse_click_msg(x, y, width, height, alt, &sender);
+                self.restart_cursor_blink();
             }
             Msg::CtrlMouseClick {
                 x,
@@ -4255,6 +4473,8 @@ impl SimpleComponent for App {
             | Msg::ExplorerActivateSelected
             | Msg::ConfirmDeletePath(_)
             | Msg::RefreshFileTree
+            | Msg::RefreshTreeDirs(_)
+            | Msg::TreeChildrenLoaded { .. }
             | Msg::FocusExplorer
             | Msg::ToggleFocusExplorer
             | Msg::ToggleFocusSearch
@@ -4310,7 +4530,12 @@ impl SimpleComponent for App {
                         return;
                     }
                 }
+                let mut animate_from = None;
                 if delta_y.abs() > 0.01 {
+                    let win_id = engine.active_window_id();
+                    // Scroll on from where the last wheel step was headed.
+                    let shown = settle_smooth_scroll(&mut self.smooth_scrolls, &mut engine, win_id);
+                    animate_from = Some((win_id, shown));
                     let lines = engine.buffer().len_lines().saturating_sub(1);
                     let scroll_count = (delta_y * 3.0).round().abs() as usize;
                     if delta_y > 0.0 {
@@ -4341,6 +4566,9 @@ impl SimpleComponent for App {
                     engine.set_scroll_left_for_window(win_id, new_left);
                 }
                 drop(engine);
+                if let Some((win_id, shown)) = animate_from {
+                    self.start_smooth_scroll(win_id, shown);
+                }
                 self.draw_needed.set(true);
             }
             Msg::CacheFontMetrics(line_height, char_width) => {
@@ -4406,8 +4634,7 @@ impl SimpleComponent for App {
                 // Silently ignore errors — the file may be mid-write.
                 if let Ok(new_settings) = core::settings::Settings::load_with_validation() {
                     let mut engine = self.engine.borrow_mut();
-                    engine.settings = new_settings;
-                    engine.ensure_spell_checker();
+                    engine.set_global_settings(new_settings);
                     engine.message = "Settings reloaded from disk".to_string();
                     drop(engine);
 
@@ -4434,9 +4661,6 @@ impl SimpleComponent for App {
                     }
                 }
                 drop(engine);
-                if key == "show_hidden_files" {
-                    sender.input(Msg::RefreshFileTree);
-                }
                 self.draw_needed.set(true);
             }
             Msg::OpenBufferEditor(key) => {
@@ -4467,9 +4691,21 @@ impl SimpleComponent for App {
                 self.engine.borrow_mut().toggle_project_search_regex();
                 self.draw_needed.set(true);
             }
+            Msg::ProjectSearchToQuickfix => {
+                self.engine.borrow_mut().send_project_search_to_quickfix();
+                let status = self.engine.borrow().message.clone();
+                self.project_search_status = status;
+                self.draw_needed.set(true);
+            }
             Msg::ProjectReplaceTextChanged(t) => {
                 self.engine.borrow_mut().project_replace_text = t;
             }
+            Msg::ProjectSearchIncludeChanged(globs) => {
+                self.engine.borrow_mut().project_search_options.include = globs;
+            }
+            Msg::ProjectSearchExcludeChanged(globs) => {
+                self.engine.borrow_mut().project_search_options.exclude = globs;
+            }
             Msg::ProjectReplaceAll => {
                 let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
                 self.engine.borrow_mut().start_project_replace(cwd);
@@ -4477,6 +4713,22 @@ impl SimpleComponent for App {
                 self.project_search_status = status;
                 self.draw_needed.set(true);
             }
+            Msg::ProjectReplaceTogglePreview => {
+                self.engine.borrow_mut().toggle_project_replace_preview();
+                let s = self.sender.clone();
+                self.rebuild_search_results(&s);
+            }
+            Msg::ProjectSearchToggleMatch(idx) => {
+                self.engine.borrow_mut().toggle_project_match_included(idx);
+            }
+            Msg::ProjectReplaceSelected => {
+                self.engine.borrow_mut().replace_selected_matches();
+                let status = self.engine.borrow().message.clone();
+                self.project_search_status = status;
+                let s = self.sender.clone();
+                self.rebuild_search_results(&s);
+                self.draw_needed.set(true);
+            }
             Msg::ProjectSearchSubmit => {
                 let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
                 self.engine.borrow_mut().start_project_search(cwd);
@@ -4846,6 +5098,11 @@ impl App {
         let theme = Theme::from_name(&engine.settings.colorscheme);
         let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
         let mut last_file: Option<PathBuf> = None;
+        let previews = if engine.project_replace_preview {
+            engine.project_replace_preview_lines(0..results.len())
+        } else {
+            None
+        };
 
         for (idx, m) in results.iter().enumerate() {
             // Add a file header row when the file changes
@@ -4869,13 +5126,27 @@ impl App {
             }
 
             // Result row
-            let snippet = format!("  {}: {}", m.line + 1, m.line_text.trim());
             let row_label = gtk4::Label::new(None);
-            let result_markup = format!(
-                "<span foreground='{}'>{}</span>",
-                theme.foreground.to_hex(),
-                gtk4::glib::markup_escape_text(&snippet)
-            );
+            let result_markup = match previews.as_ref().and_then(|p| p.get(idx)) {
+                // Preview: struck-through old line, then the replaced line.
+                Some(after) => format!(
+                    "<span foreground='{}'>{}: </span><span foreground='{}' strikethrough='true'>{}</span> <span foreground='{}'>{}</span>",
+                    theme.line_number_fg.to_hex(),
+                    m.line + 1,
+                    theme.git_deleted.to_hex(),
+                    gtk4::glib::markup_escape_text(m.line_text.trim()),
+                    theme.git_added.to_hex(),
+                    gtk4::glib::markup_escape_text(after.trim())
+                ),
+                None => {
+                    let snippet = format!("  {}: {}", m.line + 1, m.line_text.trim());
+                    format!(
+                        "<span foreground='{}'>{}</span>",
+                        theme.foreground.to_hex(),
+                        gtk4::glib::markup_escape_text(&snippet)
+                    )
+                }
+            };
             row_label.set_markup(&result_markup);
             row_label.set_halign(gtk4::Align::Start);
             row_label.set_ellipsize(pango::EllipsizeMode::End);
@@ -4885,7 +5156,21 @@ impl App {
 
             // Tag the row with its result index via the widget name
             result_row.set_widget_name(&idx.to_string());
-            result_row.set_child(Some(&row_label));
+            if previews.is_some() {
+                let check = gtk4::CheckButton::new();
+                check.set_active(engine.project_match_included(idx));
+                let sender_clone = sender.clone();
+                check.connect_toggled(move |_| {
+                    sender_clone.send(Msg::ProjectSearchToggleMatch(idx)).ok();
+                });
+                let row_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 2);
+                row_box.set_margin_start(4);
+                row_box.append(&check);
+                row_box.append(&row_label);
+                result_row.set_child(Some(&row_box));
+            } else {
+                result_row.set_child(Some(&row_label));
+            }
             list.append(&result_row);
         }
 
@@ -5610,6 +5895,9 @@ impl App {
             let s = self.sender.clone();
             self.rebuild_search_results(&s);
             self.draw_needed.set(true);
+        } else if self.engine.borrow_mut().tick_project_search_progress() {
+            let status = self.engine.borrow().message.clone();
+            self.project_search_status = status;
         }
         if self.engine.borrow_mut().poll_project_replace() {
             let status = self.engine.borrow().message.clone();
@@ -5685,6 +5973,10 @@ impl App {
             self.engine.borrow_mut().explorer_needs_refresh = false;
             sender.input(Msg::RefreshFileTree);
         }
+        // Re-root the file tree at the new cwd after `:cd`.
+        if !cell_editing && std::mem::take(&mut self.engine.borrow_mut().cwd_changed) {
+            sender.input(Msg::RefreshFileTree);
+        }
         // Auto-refresh SC panel every 2s to pick up external git changes.
         // Also refresh when Explorer is active (for git status indicators).
         if self.sidebar_visible
@@ -5699,12 +5991,28 @@ impl App {
             }
             self.draw_needed.set(true);
         }
+        // Rebuild the file tree as soon as the explorer filter settings change
+        // (`:set showhiddenfiles`, `:set explorerignore=…`, settings panel).
+        if ExplorerFilterConfig::from_settings(&self.engine.borrow().settings)
+            != self.explorer_filter
+        {
+            sender.input(Msg::RefreshFileTree);
+        }
+        // Refresh tree directories that changed on disk (debounced).
+        let changed_dirs = self.explorer_watcher.borrow_mut().poll();
+        if !changed_dirs.is_empty() {
+            sender.input(Msg::RefreshTreeDirs(changed_dirs));
+        }
         // Auto-reload buffers whose files changed on disk.
         if self.last_file_check.elapsed() >= std::time::Duration::from_secs(2) {
             self.last_file_check = std::time::Instant::now();
             if self.engine.borrow_mut().check_file_changes() {
                 self.draw_needed.set(true);
             }
+            // Reload the project .vimcode.json when it changes on disk.
+            if self.engine.borrow_mut().check_project_settings_changed() {
+                self.draw_needed.set(true);
+            }
         }
         // Poll for completed extension registry fetch.
         {
@@ -5732,6 +6040,22 @@ impl App {
                 self.draw_needed.set(true);
             }
         }
+        // Apply output of finished :! / :r ! / filter commands.
+        if self.engine.borrow_mut().poll_shell_commands() {
+            self.draw_needed.set(true);
+        }
+        // Show large files read in the background.
+        if self.engine.borrow_mut().poll_file_loads() {
+            self.draw_needed.set(true);
+        }
+        // Start debounced live-grep searches and stream in their matches.
+        if self.engine.borrow_mut().poll_picker_grep() {
+            self.draw_needed.set(true);
+        }
+        // Show file picker previews loaded in the background.
+        if self.engine.borrow_mut().poll_picker_preview() {
+            self.draw_needed.set(true);
+        }
         // Check for panel reveal request from plugins.
         {
             let engine = self.engine.borrow_mut();
@@ -5794,8 +6118,28 @@ impl App {
         if self.engine.borrow_mut().tick_syntax_debounce() {
             self.draw_needed.set(true);
         }
+        // Resolve an ambiguous user-keymap prefix after `timeoutlen`.
+        let keymap_action = self.engine.borrow_mut().tick_keymap_timeout();
+        if let Some(action) = keymap_action {
+            self.dispatch_engine_action(action, sender, false);
+            self.draw_needed.set(true);
+        }
+        // Show or hide the which-key popup for a pending prefix.
+        if self.engine.borrow_mut().tick_which_key() {
+            self.draw_needed.set(true);
+        }
+        // Highlight the word under the cursor once it settles.
+        if self.engine.borrow_mut().tick_word_highlight() {
+            self.draw_needed.set(true);
+        }
+        // Fetch documentation for the selected completion item.
+        self.engine.borrow_mut().tick_completion_resolve();
         // Tick swap file writes (only does work when updatetime elapsed).
         self.engine.borrow_mut().tick_swap_files();
+        // Auto-save after a pause in typing (`autosave = "interval"`).
+        if self.engine.borrow_mut().tick_autosave() {
+            self.draw_needed.set(true);
+        }
         // Poll for external git branch changes (rate-limited to once per 2s inside).
         if self.engine.borrow_mut().tick_git_branch() {
             self.draw_needed.set(true);
@@ -6421,6 +6765,27 @@ impl App {
                 // widget (which has can_target=true while a menu is open).
                 // If we reach here, no menu is open and we proceed with normal handling.
 
+                // ── Minimap hit-test (before editor click) ────────────────────
+                // A click on the active window's minimap scrolls there and
+                // starts a drag that keeps scrolling until the button is up.
+                {
+                    let lh = self.cached_line_height;
+                    let cw = self.cached_char_width;
+                    let mut engine = self.engine.borrow_mut();
+                    let win_id = engine.active_window_id();
+                    let hit =
+                        minimap_strip(&engine, win_id, width, height, lh, cw).is_some_and(|r| {
+                            x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+                        });
+                    if hit {
+                        minimap_scroll_to(&mut engine, win_id, y, width, height, lh, cw);
+                        drop(engine);
+                        self.minimap_dragging = Some(win_id);
+                        self.draw_needed.set(true);
+                        return; // consume click; don't send it to the editor
+                    }
+                }
+
                 // ── H scrollbar hit-test (before editor click) ────────────────
                 // If the click lands on a Cairo h scrollbar, start a drag and
                 // don't pass the click through to the editor.
@@ -6748,6 +7113,22 @@ impl App {
                 return;
             }
         }
+        // Minimap drag — keep the viewport under the pointer.
+        if let Some(win_id) = self.minimap_dragging {
+            let lh = self.cached_line_height;
+            let cw = self.cached_char_width;
+            minimap_scroll_to(
+                &mut self.engine.borrow_mut(),
+                win_id,
+                y,
+                width,
+                height,
+                lh,
+                cw,
+            );
+            self.draw_needed.set(true);
+            return;
+        }
         // H scrollbar thumb drag — convert pointer delta to scroll_left.
         if let Some(ref state) = self.h_sb_dragging {
             if state.px_per_col > 0.0 {
@@ -6976,6 +7357,7 @@ impl App {
         }
         self.h_sb_dragging = None;
         self.h_sb_drag_cell.set(None);
+        self.minimap_dragging = None;
         self.group_divider_dragging = None;
         let mut engine = self.engine.borrow_mut();
         engine.mouse_drag_active = false;
@@ -6983,6 +7365,109 @@ impl App {
         self.draw_needed.set(true);
     }
 
+    /// Show the cursor and restart its blink after input.  The blink runs on
+    /// a tick callback while the mode's `cursorstyle` blinks, until the
+    /// desktop's `gtk-cursor-blink-timeout` passes without input, and not at
+    /// all when `gtk-cursor-blink` is off.
+    fn restart_cursor_blink(&self) {
+        let blink = self.cursor_blink.clone();
+        blink.on.set(true);
+        blink.since.set(std::time::Instant::now());
+        if blink.ticking.get() || !render::active_cursor_style(&self.engine.borrow()).blink {
+            return;
+        }
+        let (enabled, timeout_secs) = gtk4::Settings::default().map_or((true, 10), |s| {
+            (s.is_gtk_cursor_blink(), s.gtk_cursor_blink_timeout())
+        });
+        if !enabled {
+            return;
+        }
+        if let Some(ref da) = *self.drawing_area.borrow() {
+            blink.ticking.set(true);
+            let engine = self.engine.clone();
+            let timeout = std::time::Duration::from_secs(timeout_secs.max(1) as u64);
+            da.add_tick_callback(move |da, _| {
+                let elapsed = blink.since.get().elapsed();
+                // Busy engine: keep the current phase for now.
+                let blinks = match engine.try_borrow() {
+                    Ok(e) => render::active_cursor_style(&e).blink,
+                    Err(_) => true,
+                };
+                if elapsed >= timeout || !blinks {
+                    blink.on.set(true);
+                    blink.ticking.set(false);
+                    da.queue_draw();
+                    return gtk4::glib::ControlFlow::Break;
+                }
+                let interval = engine
+                    .try_borrow()
+                    .map_or(530, |e| e.settings.cursor_blink_interval)
+                    .max(1);
+                let on = (elapsed.as_millis() / u128::from(interval)).is_multiple_of(2);
+                if on != blink.on.get() {
+                    blink.on.set(on);
+                    da.queue_draw();
+                }
+                gtk4::glib::ControlFlow::Continue
+            });
+        }
+    }
+
+    /// Finish every smooth scroll in flight at once, returning the position
+    /// `window_id` was showing (see [`settle_smooth_scroll`]).
+    fn settle_smooth_scrolls(&mut self, window_id: core::WindowId) -> (usize, f64) {
+        let mut engine = self.engine.borrow_mut();
+        let shown = settle_smooth_scroll(&mut self.smooth_scrolls, &mut engine, window_id);
+        let others: Vec<core::WindowId> = self.smooth_scrolls.keys().copied().collect();
+        for win_id in others {
+            settle_smooth_scroll(&mut self.smooth_scrolls, &mut engine, win_id);
+        }
+        shown
+    }
+
+    /// With `:set smoothscroll`, animate `window_id` from the position
+    /// `shown` (as returned by [`settle_smooth_scroll`]) to its current
+    /// `scroll_top`, driven by the drawing area's frame clock.  The distance
+    /// is measured in display rows, so closed folds scroll as one row.
+    fn start_smooth_scroll(&mut self, window_id: core::WindowId, shown: (usize, f64)) {
+        let mut engine = self.engine.borrow_mut();
+        if !engine.settings.smooth_scroll || engine.settings.smooth_scroll_duration == 0 {
+            return;
+        }
+        let Some(view) = engine.windows.get(&window_id).map(|w| &w.view) else {
+            return;
+        };
+        let (shown_top, shown_offset) = shown;
+        let from = view.visible_rows_between(view.scroll_top, shown_top) as f64 + shown_offset;
+        if from.abs() < f64::EPSILON {
+            return;
+        }
+        self.smooth_scrolls.insert(
+            window_id,
+            SmoothScroll {
+                from,
+                start: std::time::Instant::now(),
+            },
+        );
+        set_scroll_offset(&mut engine, window_id, from);
+        drop(engine);
+        if self.smooth_scroll_ticking.get() {
+            return;
+        }
+        if let Some(ref da) = *self.drawing_area.borrow() {
+            self.smooth_scroll_ticking.set(true);
+            let ticking = self.smooth_scroll_ticking.clone();
+            let sender = self.sender.clone();
+            da.add_tick_callback(move |_, _| {
+                if !ticking.get() {
+                    return gtk4::glib::ControlFlow::Break;
+                }
+                sender.send(Msg::SmoothScrollTick).ok();
+                gtk4::glib::ControlFlow::Continue
+            });
+        }
+    }
+
     fn show_action_menu_popover(
         &mut self,
         group_id: core::window::GroupId,
@@ -9061,6 +9546,11 @@ impl App {
                             // Move not yet supported via keyboard in GTK
                             // (uses status-line prompt in TUI)
                         }
+                        ExplorerAction::ToggleHidden => {
+                            self.engine.borrow_mut().toggle_show_hidden_files();
+                            sender.input(Msg::RefreshFileTree);
+                            self.draw_needed.set(true);
+                        }
                     }
                 }
             }
@@ -9068,8 +9558,50 @@ impl App {
                 self.engine.borrow_mut().confirm_delete_file(&path);
                 self.draw_needed.set(true);
             }
+            Msg::TreeChildrenLoaded { dir, entries } => {
+                if let Some(ref store) = self.tree_store {
+                    let theme = Theme::from_name(&self.engine.borrow().settings.colorscheme);
+                    tree_children_loaded(
+                        store,
+                        &dir,
+                        &entries,
+                        &theme.explorer_dir_fg.to_hex(),
+                        &theme.explorer_file_fg.to_hex(),
+                    );
+                }
+            }
+            Msg::RefreshTreeDirs(dirs) => {
+                if let Some(ref store) = self.tree_store {
+                    let engine = self.engine.borrow();
+                    let theme = Theme::from_name(&engine.settings.colorscheme);
+                    let mut filter = ExplorerFilter::new(&self.explorer_filter);
+                    for dir in &dirs {
+                        sync_tree_dir(
+                            store,
+                            dir,
+                            &mut filter,
+                            engine.settings.explorer_sort_case_insensitive,
+                            &theme.explorer_dir_fg.to_hex(),
+                            &theme.explorer_file_fg.to_hex(),
+                        );
+                    }
+                }
+                self.draw_needed.set(true);
+            }
             Msg::RefreshFileTree => {
+                self.explorer_filter =
+                    ExplorerFilterConfig::from_settings(&self.engine.borrow().settings);
                 if let Some(ref store) = self.tree_store {
+                    // Rows are rebuilt from scratch; remember what was
+                    // expanded and selected so it can be restored after.
+                    // Re-expanding re-watches the directories.
+                    let (expanded_dirs, selected_path) = match *self.file_tree_view.borrow() {
+                        Some(ref tv) => (expanded_tree_dirs(tv, store), selected_tree_path(tv)),
+                        None => (Vec::new(), None),
+                    };
+                    self.explorer_watcher
+                        .borrow_mut()
+                        .set_dirs(std::iter::empty());
                     let cwd = self.engine.borrow().cwd.clone();
                     let (dir_fg_hex, file_fg_hex) = {
                         let theme = Theme::from_name(&self.engine.borrow().settings.colorscheme);
@@ -9082,11 +9614,15 @@ impl App {
                     build_file_tree_with_root(
                         store,
                         &cwd,
-                        self.engine.borrow().settings.show_hidden_files,
+                        &mut ExplorerFilter::new(&self.explorer_filter),
                         self.engine.borrow().settings.explorer_sort_case_insensitive,
                         &dir_fg_hex,
                         &file_fg_hex,
                     );
+                    if let Some(ref tv) = *self.file_tree_view.borrow() {
+                        tv.expand_row(&gtk4::TreePath::from_indices(&[0]), false);
+                        restore_tree_expansion(tv, store, &expanded_dirs);
+                    }
                     // Update explorer indicators (modified/diagnostics)
                     {
                         let engine = self.engine.borrow();
@@ -9110,9 +9646,12 @@ impl App {
                         );
                     }
                     if let Some(ref tv) = *self.file_tree_view.borrow() {
-                        tv.expand_row(&gtk4::TreePath::from_indices(&[0]), false);
-                        // Highlight the active file in the tree after rebuild.
-                        if let Some(path) = self.engine.borrow().file_path().cloned() {
+                        // Restore the previous selection if it still exists,
+                        // otherwise highlight the active file.
+                        let target = selected_path
+                            .filter(|p| p.exists())
+                            .or_else(|| self.engine.borrow().file_path().cloned());
+                        if let Some(path) = target {
                             highlight_file_in_tree(tv, &path);
                         }
                     }
@@ -9697,6 +10236,77 @@ fn compute_editor_window_rects(
     );
     let (rects, _dividers) = engine.calculate_group_window_rects(editor_bounds, tab_bar_height);
     rects
+        .into_iter()
+        .map(|(id, rect)| (id, render::winbar_content_rect(engine, rect, line_height)))
+        .collect()
+}
+
+/// Draw `window_id` `offset` display rows away from its `scroll_top`.
+fn set_scroll_offset(engine: &mut Engine, window_id: core::WindowId, offset: f64) {
+    if let Some(window) = engine.windows.get_mut(&window_id) {
+        window.view.scroll_offset = offset;
+    }
+}
+
+/// Jump `window_id` to the end of its smooth scroll, if one is in flight,
+/// and return what it was showing before: its `scroll_top` and the display
+/// rows it was drawn away from it.
+fn settle_smooth_scroll(
+    anims: &mut HashMap<core::WindowId, SmoothScroll>,
+    engine: &mut Engine,
+    window_id: core::WindowId,
+) -> (usize, f64) {
+    let shown = engine
+        .windows
+        .get(&window_id)
+        .map_or((0, 0.0), |w| (w.view.scroll_top, w.view.scroll_offset));
+    if anims.remove(&window_id).is_some() {
+        set_scroll_offset(engine, window_id, 0.0);
+    }
+    shown
+}
+
+/// The minimap strip of `window_id` in drawing-area pixels, or `None` when
+/// the window shows no minimap.
+fn minimap_strip(
+    engine: &Engine,
+    window_id: core::WindowId,
+    da_width: f64,
+    da_height: f64,
+    line_height: f64,
+    char_width: f64,
+) -> Option<core::WindowRect> {
+    let rects = compute_editor_window_rects(engine, da_width, da_height, line_height);
+    let (_, rect) = rects.iter().find(|(id, _)| *id == window_id)?;
+    render::minimap_rect(engine, window_id, rect, line_height, char_width)
+}
+
+/// Scroll `window_id` so the line under pixel row `y` of its minimap is
+/// centred.  No-op when the window shows no minimap.
+#[allow(clippy::too_many_arguments)]
+fn minimap_scroll_to(
+    engine: &mut Engine,
+    window_id: core::WindowId,
+    y: f64,
+    da_width: f64,
+    da_height: f64,
+    line_height: f64,
+    char_width: f64,
+) {
+    let Some(strip) = minimap_strip(
+        engine,
+        window_id,
+        da_width,
+        da_height,
+        line_height,
+        char_width,
+    ) else {
+        return;
+    };
+    if let Some(top) = render::minimap_scroll_target(engine, window_id, &strip, line_height, y) {
+        engine.set_scroll_top_for_window(window_id, top);
+        engine.sync_scroll_binds();
+    }
 }
 
 /// Compute the thumb geometry for one window's h scrollbar.
@@ -9711,6 +10321,10 @@ fn h_scrollbar_geometry(
     line_height: f64,
 ) -> Option<(f64, f64, f64, f64, f64, f64, f64, f64)> {
     let window = engine.windows.get(&window_id)?;
+    // Soft-wrapped text never scrolls horizontally.
+    if engine.settings.wrap || engine.md_preview_links.contains_key(&window.buffer_id) {
+        return None;
+    }
     let buffer_state = engine.buffer_manager.get(window.buffer_id)?;
 
     // max_col is pre-computed and cached in BufferState on every edit — O(1) vs O(N_lines).
@@ -9944,7 +10558,7 @@ fn shorten_path(path: &std::path::Path) -> String {
 }
 
 /// Entry point for GTK mode.
-pub(crate) fn run(file_path: Option<PathBuf>) {
+pub(crate) fn run(file_target: Option<FileTarget>, session: Option<String>) {
     if std::env::var_os("WAYLAND_DISPLAY").is_none() && std::env::var_os("DISPLAY").is_none() {
         std::env::set_var("DISPLAY", ":0");
     }
@@ -9986,5 +10600,5 @@ pub(crate) fn run(file_path: Option<PathBuf>) {
         0
     });
     let app = RelmApp::from_app(gtk_app);
-    app.run::<App>(file_path);
+    app.run::<App>((file_target, session));
 }
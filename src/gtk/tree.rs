@@ -1,4 +1,7 @@
 use super::*;
+use crate::core::explorer_filter::{
+    is_large_dir, read_dir_entries, ExplorerEntry, ExplorerFilter, ExplorerFilterConfig,
+};
 
 /// Sentinel path stored in the dummy placeholder child of unexpanded directories.
 pub(super) const TREE_DUMMY_PATH: &str = "__vimcode_loading__";
@@ -9,7 +12,7 @@ pub(super) const TREE_DUMMY_PATH: &str = "__vimcode_loading__";
 pub(super) fn build_file_tree_with_root(
     store: &gtk4::TreeStore,
     root: &Path,
-    show_hidden: bool,
+    filter: &mut ExplorerFilter,
     case_insensitive: bool,
     dir_fg_hex: &str,
     file_fg_hex: &str,
@@ -35,7 +38,7 @@ pub(super) fn build_file_tree_with_root(
         store,
         Some(&root_iter),
         root,
-        show_hidden,
+        filter,
         case_insensitive,
         dir_fg_hex,
         file_fg_hex,
@@ -49,97 +52,170 @@ pub(super) fn build_file_tree_shallow(
     store: &gtk4::TreeStore,
     parent: Option<&gtk4::TreeIter>,
     path: &Path,
-    show_hidden: bool,
+    filter: &mut ExplorerFilter,
     case_insensitive: bool,
     dir_fg_hex: &str,
     file_fg_hex: &str,
 ) {
-    let entries = match fs::read_dir(path) {
-        Ok(e) => e,
-        Err(_) => return,
-    };
-
-    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
-
-    // Sort: directories first, then files, both alphabetically
-    entries.sort_by(|a, b| {
-        let a_is_dir = a.path().is_dir();
-        let b_is_dir = b.path().is_dir();
-        match (a_is_dir, b_is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => {
-                if case_insensitive {
-                    let an = a.file_name().to_string_lossy().to_lowercase();
-                    let bn = b.file_name().to_string_lossy().to_lowercase();
-                    an.cmp(&bn)
-                } else {
-                    a.file_name().cmp(&b.file_name())
-                }
-            }
-        }
-    });
+    let entries = read_dir_entries(path, filter, case_insensitive);
+    insert_tree_entries(store, parent, &entries, dir_fg_hex, file_fg_hex);
+}
 
+/// Append already-sorted `entries` under `parent`, giving each directory a
+/// dummy child so it can be expanded later.
+pub(super) fn insert_tree_entries(
+    store: &gtk4::TreeStore,
+    parent: Option<&gtk4::TreeIter>,
+    entries: &[ExplorerEntry],
+    dir_fg_hex: &str,
+    file_fg_hex: &str,
+) {
     for entry in entries {
-        let child_path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
-
-        if name.starts_with('.') && name != "." && name != ".." && !show_hidden {
-            continue;
-        }
+        insert_tree_entry(store, parent, None, entry, dir_fg_hex, file_fg_hex);
+    }
+}
 
-        let is_dir = child_path.is_dir();
-        let ext = child_path
+/// Insert one entry under `parent` at `position` (appended when `None`).
+fn insert_tree_entry(
+    store: &gtk4::TreeStore,
+    parent: Option<&gtk4::TreeIter>,
+    position: Option<u32>,
+    entry: &ExplorerEntry,
+    dir_fg_hex: &str,
+    file_fg_hex: &str,
+) {
+    let icon = if entry.is_dir {
+        crate::icons::FOLDER.nerd
+    } else {
+        let ext = entry
+            .path
             .extension()
             .and_then(|e| e.to_str())
             .unwrap_or("");
-        let icon = if is_dir {
-            crate::icons::FOLDER.nerd
-        } else {
-            crate::icons::file_icon(ext)
-        };
+        crate::icons::file_icon(ext)
+    };
+
+    let fg_hex: &str = file_fg_hex;
+    let iter = store.insert_with_values(
+        parent,
+        position,
+        &[
+            (0, &icon),
+            (1, &entry.name),
+            (2, &entry.path.to_string_lossy().to_string()),
+            (3, &fg_hex),
+            (4, &""),
+            (5, &dir_fg_hex),
+        ],
+    );
 
-        let fg_hex: &str = file_fg_hex;
-        let iter = store.insert_with_values(
-            parent,
+    // For directories, insert a dummy child so the expand arrow appears.
+    if entry.is_dir {
+        store.insert_with_values(
+            Some(&iter),
             None,
             &[
-                (0, &icon),
-                (1, &name),
-                (2, &child_path.to_string_lossy().to_string()),
-                (3, &fg_hex),
+                (0, &""),
+                (1, &""),
+                (2, &TREE_DUMMY_PATH),
+                (3, &dir_fg_hex),
                 (4, &""),
                 (5, &dir_fg_hex),
             ],
         );
+    }
+}
 
-        // For directories, insert a dummy child so the expand arrow appears.
-        if is_dir {
-            store.insert_with_values(
-                Some(&iter),
-                None,
-                &[
-                    (0, &""),
-                    (1, &""),
-                    (2, &TREE_DUMMY_PATH),
-                    (3, &dir_fg_hex),
-                    (4, &""),
-                    (5, &dir_fg_hex),
-                ],
-            );
+/// Bring the loaded children of `dir` in line with the disk after a
+/// filesystem change: new entries are inserted in sorted position and
+/// vanished ones removed, while surviving rows — with their expansion state,
+/// loaded subtrees and selection — are left alone.  Directories that were
+/// never expanded, or have an inline new-entry row open, are skipped.
+pub(super) fn sync_tree_dir(
+    store: &gtk4::TreeStore,
+    dir: &Path,
+    filter: &mut ExplorerFilter,
+    case_insensitive: bool,
+    dir_fg_hex: &str,
+    file_fg_hex: &str,
+) {
+    use gtk4::prelude::TreeModelExt;
+    let Some(parent) = find_tree_iter_for_path(store, dir) else {
+        return;
+    };
+    let row_path =
+        |iter: &gtk4::TreeIter| -> String { store.get_value(iter, 2).get().unwrap_or_default() };
+
+    let mut old: Vec<(String, gtk4::TreeIter)> = Vec::new();
+    if let Some(child) = store.iter_children(Some(&parent)) {
+        loop {
+            let path = row_path(&child);
+            if path == TREE_DUMMY_PATH
+                || path.starts_with("__NEW_FILE__")
+                || path.starts_with("__NEW_FOLDER__")
+            {
+                return;
+            }
+            old.push((path, child));
+            if !store.iter_next(&child) {
+                break;
+            }
+        }
+    }
+
+    let entries = read_dir_entries(dir, filter, case_insensitive);
+    let wanted: std::collections::HashSet<String> = entries
+        .iter()
+        .map(|e| e.path.to_string_lossy().to_string())
+        .collect();
+
+    // Insert (or reorder) first and remove afterwards so the row never
+    // drops to zero children, which would make GTK collapse it.
+    let mut pos = 0;
+    for entry in &entries {
+        // Step over rows that are about to be removed.
+        while let Some(it) = store.iter_nth_child(Some(&parent), pos) {
+            if wanted.contains(&row_path(&it)) {
+                break;
+            }
+            pos += 1;
+        }
+        let path = entry.path.to_string_lossy().to_string();
+        let at_pos = store.iter_nth_child(Some(&parent), pos);
+        if at_pos.as_ref().map(row_path).as_deref() != Some(path.as_str()) {
+            match old.iter().find(|(p, _)| *p == path) {
+                Some((_, existing)) => store.move_before(existing, at_pos.as_ref()),
+                None => insert_tree_entry(
+                    store,
+                    Some(&parent),
+                    Some(pos as u32),
+                    entry,
+                    dir_fg_hex,
+                    file_fg_hex,
+                ),
+            }
+        }
+        pos += 1;
+    }
+    for (path, iter) in &old {
+        if !wanted.contains(path) {
+            store.remove(iter);
         }
     }
 }
 
 /// Called when a tree row is expanded.  Replaces the dummy placeholder with
-/// the directory's real contents (one level deep).
+/// the directory's real contents (one level deep).  Directories with more
+/// than `LARGE_DIR_ENTRIES` entries are read on a background thread; the
+/// dummy shows "Loading…" until `Msg::TreeChildrenLoaded` arrives.
 pub(super) fn tree_row_expanded(
     store: &gtk4::TreeStore,
     iter: &gtk4::TreeIter,
-    show_hidden: bool,
+    config: &ExplorerFilterConfig,
     case_insensitive: bool,
     dir_fg_hex: &str,
     file_fg_hex: &str,
+    sender: &relm4::Sender<Msg>,
 ) {
     use gtk4::prelude::TreeModelExt;
     let dir_path: String = store.get_value(iter, 2).get().unwrap_or_default();
@@ -150,27 +226,66 @@ pub(super) fn tree_row_expanded(
     // Check whether the first child is the dummy placeholder.
     if let Some(child) = store.iter_children(Some(iter)) {
         let child_path: String = store.get_value(&child, 2).get().unwrap_or_default();
-        if child_path == TREE_DUMMY_PATH {
-            // Populate real children BEFORE removing the dummy so the
-            // directory never has zero children — GTK auto-collapses a
-            // row the instant its last child is removed, which caused
-            // the "first click swallowed" bug.
-            build_file_tree_shallow(
-                store,
-                Some(iter),
-                Path::new(&dir_path),
-                show_hidden,
-                case_insensitive,
-                dir_fg_hex,
-                file_fg_hex,
-            );
-            store.remove(&child);
+        let child_label: String = store.get_value(&child, 1).get().unwrap_or_default();
+        if child_path != TREE_DUMMY_PATH || !child_label.is_empty() {
+            // Already populated (collapsed and re-expanded) or still loading.
+            return;
+        }
+        let dir = PathBuf::from(dir_path);
+        if is_large_dir(&dir) {
+            store.set_value(&child, 1, &"Loading…".to_value());
+            let config = config.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                let mut filter = ExplorerFilter::new(&config);
+                let entries = read_dir_entries(&dir, &mut filter, case_insensitive);
+                sender.send(Msg::TreeChildrenLoaded { dir, entries }).ok();
+            });
+            return;
         }
-        // If the first child is NOT the dummy, the directory was already
-        // populated (e.g. collapsed and re-expanded) — nothing to do.
+        // Populate real children BEFORE removing the dummy so the
+        // directory never has zero children — GTK auto-collapses a
+        // row the instant its last child is removed, which caused
+        // the "first click swallowed" bug.
+        build_file_tree_shallow(
+            store,
+            Some(iter),
+            &dir,
+            &mut ExplorerFilter::new(config),
+            case_insensitive,
+            dir_fg_hex,
+            file_fg_hex,
+        );
+        store.remove(&child);
     }
 }
 
+/// Fill in a directory whose entries were read in the background.  Does
+/// nothing if the row is gone or was already populated (e.g. the tree was
+/// rebuilt while the read was in flight).
+pub(super) fn tree_children_loaded(
+    store: &gtk4::TreeStore,
+    dir: &Path,
+    entries: &[ExplorerEntry],
+    dir_fg_hex: &str,
+    file_fg_hex: &str,
+) {
+    use gtk4::prelude::TreeModelExt;
+    let Some(iter) = find_tree_iter_for_path(store, dir) else {
+        return;
+    };
+    let Some(child) = store.iter_children(Some(&iter)) else {
+        return;
+    };
+    let child_path: String = store.get_value(&child, 2).get().unwrap_or_default();
+    if child_path != TREE_DUMMY_PATH {
+        return;
+    }
+    // Same ordering as `tree_row_expanded`: add before removing the dummy.
+    insert_tree_entries(store, Some(&iter), entries, dir_fg_hex, file_fg_hex);
+    store.remove(&child);
+}
+
 /// Walk the entire TreeStore and update columns 4 (indicator text) and 5
 /// (indicator color) based on git status and LSP diagnostics.
 #[allow(clippy::too_many_arguments)]
@@ -293,6 +408,46 @@ pub(super) fn update_tree_indicators(
     );
 }
 
+/// Paths of every expanded directory row, parents before children.
+pub(super) fn expanded_tree_dirs(
+    tree_view: &gtk4::TreeView,
+    store: &gtk4::TreeStore,
+) -> Vec<PathBuf> {
+    use gtk4::prelude::TreeModelExt;
+    let mut dirs = Vec::new();
+    tree_view.map_expanded_rows(|_, tree_path| {
+        if let Some(iter) = store.iter(tree_path) {
+            let path: String = store.get_value(&iter, 2).get().unwrap_or_default();
+            dirs.push(PathBuf::from(path));
+        }
+    });
+    dirs.sort_by_key(|p| p.components().count());
+    dirs
+}
+
+/// Re-expand `dirs` (as returned by [`expanded_tree_dirs`]) after the tree
+/// was rebuilt.  Directories that no longer exist, or are now hidden, are
+/// dropped.
+pub(super) fn restore_tree_expansion(
+    tree_view: &gtk4::TreeView,
+    store: &gtk4::TreeStore,
+    dirs: &[PathBuf],
+) {
+    use gtk4::prelude::TreeModelExt;
+    for dir in dirs.iter().filter(|d| d.is_dir()) {
+        if let Some(iter) = find_tree_iter_for_path(store, dir) {
+            tree_view.expand_row(&store.path(&iter), false);
+        }
+    }
+}
+
+/// Filesystem path of the selected tree row, if any.
+pub(super) fn selected_tree_path(tree_view: &gtk4::TreeView) -> Option<PathBuf> {
+    let (model, iter) = tree_view.selection().selected()?;
+    let path: String = model.get_value(&iter, 2).get().ok()?;
+    (!path.is_empty() && path != TREE_DUMMY_PATH).then(|| PathBuf::from(path))
+}
+
 /// Get the parent directory for creating a new file/folder, based on the
 /// currently selected tree row. If a directory is selected, use it. If a
 /// file is selected, use its parent. Fallback: cwd.
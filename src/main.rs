@@ -10,10 +10,8 @@ mod tui_main;
 #[cfg(feature = "win-gui")]
 mod win_gui;
 
-use std::path::PathBuf;
-
 fn main() {
-    // Parse CLI args to get optional file path
+    // Parse CLI args to get the optional file (`path[:line[:col]]`, `+N`)
     let args: Vec<String> = std::env::args().collect();
 
     // --version / -V: print version and exit
@@ -32,26 +30,30 @@ fn main() {
         .and_then(|i| args.get(i + 1))
         .cloned();
 
-    // First positional argument (not starting with '-', not a --debug value)
+    // --session <name>: restore a session saved with :mksession
+    let session = args
+        .iter()
+        .position(|a| a == "--session")
+        .and_then(|i| args.get(i + 1))
+        .cloned();
+
+    // First positional argument (not starting with '-' or '+', not a flag value)
     let skip_args: std::collections::HashSet<usize> = {
         let mut s = std::collections::HashSet::new();
-        if let Some(i) = args.iter().position(|a| a == "--debug") {
-            s.insert(i);
-            s.insert(i + 1);
+        for flag in ["--debug", "--session"] {
+            if let Some(i) = args.iter().position(|a| a == flag) {
+                s.insert(i);
+                s.insert(i + 1);
+            }
         }
         s
     };
-    let file_path = args
-        .iter()
-        .enumerate()
-        .skip(1)
-        .find(|(i, a)| !a.starts_with('-') && !skip_args.contains(i))
-        .map(|(_, a)| PathBuf::from(a));
+    let file_target = core::paths::file_target_from_args(&args, &skip_args);
 
     if tui_mode {
-        tui_main::run(file_path, debug_log);
+        tui_main::run(file_target, session, debug_log);
         return;
     }
 
-    gtk::run(file_path);
+    gtk::run(file_target, session);
 }
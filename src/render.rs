@@ -17,7 +17,7 @@ use crate::core::dap::DapVariable;
 use crate::core::engine::{AlignedDiffEntry, DiffLine, Engine, SearchDirection};
 pub use crate::core::engine::{BottomPanelKind, DebugSidebarSection};
 use crate::core::lsp::SignatureHelpData;
-use crate::core::settings::LineNumberMode;
+use crate::core::settings::{CursorStyleShape, LineNumberMode, ListChars, ModeCursorStyle};
 pub use crate::core::settings::{SettingDef, SettingType, SETTING_DEFS};
 use crate::core::terminal::TermSelection as CoreTermSelection;
 use crate::core::view::View;
@@ -118,6 +118,17 @@ impl Color {
         }
     }
 
+    /// Mix this colour toward `other` by `amount` (0.0 = unchanged, 1.0 = `other`).
+    pub fn blend(self, other: Color, amount: f64) -> Self {
+        let f = amount.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+        Self {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+        }
+    }
+
     /// Derive a subtle cursorline background from this colour.
     /// Dark backgrounds get lightened; light backgrounds get darkened.
     pub fn cursorline_tint(self) -> Self {
@@ -177,6 +188,21 @@ impl Color {
     }
 }
 
+/// Colours are stored as `#rrggbb` strings in native theme files.
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Color::try_from_hex(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid colour '{s}'")))
+    }
+}
+
 /// Strip `//` and `/* */` comments from JSON-with-comments (JSONC), as used
 /// by VSCode theme files. Preserves newlines so error positions stay valid.
 fn strip_json_comments(input: &str) -> String {
@@ -307,12 +333,23 @@ pub struct RenderedLine {
     /// These rows have empty `raw_text`; the full continuation text is in
     /// `ghost_suffix` and backends draw it at the left edge of the content area.
     pub is_ghost_continuation: bool,
+    /// True for read-only rows not backed by a buffer line: lines deleted
+    /// from the base file in a unified diff (`:diffunified`). `raw_text`
+    /// holds the deleted text and `line_idx` is the following buffer line.
+    pub is_virtual: bool,
     /// Column positions where indent guide lines should be drawn.
     /// Empty when `indent_guides` setting is off.
     pub indent_guides: Vec<usize>,
     /// Column positions where colorcolumn background should be drawn.
     /// Parsed from `settings.colorcolumn` (e.g. "80,120").
     pub colorcolumns: Vec<usize>,
+    /// LSP inlay hints anchored on this row (segment-relative columns).
+    /// Empty when the `inlay_hints` setting is off.
+    pub inlay_hints: Vec<InlayHintMark>,
+    /// Most severe diagnostic on this line, drawn after the text.  Set on the
+    /// line's last row when `diagnostic_virtual_text` is on and the line has
+    /// no other annotation.
+    pub diagnostic_virtual_text: Option<DiagnosticVirtualText>,
 }
 
 /// A single diagnostic mark on a rendered line (for inline underlines/squiggles).
@@ -328,6 +365,183 @@ pub struct DiagnosticMark {
     pub message: String,
 }
 
+/// Diagnostic message shown as dimmed virtual text at the end of a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticVirtualText {
+    /// First line of the most severe diagnostic's message.
+    pub message: String,
+    /// Severity of that diagnostic (drives colour).
+    pub severity: crate::core::lsp::DiagnosticSeverity,
+    /// Number of other diagnostics on the line, shown as `(+N)`.
+    pub more: usize,
+}
+
+impl DiagnosticVirtualText {
+    /// Place the text on a row `width` columns wide whose content occupies
+    /// `text_cols` columns.  Returns the start column and the text, with the
+    /// message `…`-truncated (keeping any `(+N)` suffix), or `None` when too
+    /// few columns are free.
+    pub fn fit(&self, text_cols: usize, width: usize) -> Option<(usize, String)> {
+        const GAP: usize = 2;
+        const MIN_COLS: usize = 4;
+        let start = text_cols + GAP;
+        let avail = width.saturating_sub(start);
+        if avail < MIN_COLS {
+            return None;
+        }
+        let suffix = if self.more > 0 {
+            format!(" (+{})", self.more)
+        } else {
+            String::new()
+        };
+        let suffix_len = suffix.chars().count();
+        let len = self.message.chars().count();
+        let text = if len + suffix_len <= avail {
+            format!("{}{suffix}", self.message)
+        } else if avail > suffix_len + 1 {
+            let keep = avail - suffix_len - 1;
+            format!(
+                "{}…{suffix}",
+                self.message.chars().take(keep).collect::<String>()
+            )
+        } else {
+            let mut clipped: String = self.message.chars().take(avail - 1).collect();
+            clipped.push('…');
+            clipped
+        };
+        Some((start, text))
+    }
+}
+
+/// An LSP inlay hint drawn as virtual text before a character column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlayHintMark {
+    /// Char column (relative to the row's segment) the label is drawn before.
+    pub col: usize,
+    /// Label text, including any padding spaces requested by the server.
+    pub label: String,
+}
+
+/// Inlay hints anchored on buffer line `line_idx`, converted from UTF-16
+/// positions to char columns in `line_text` (clamped to the line's end).
+pub fn inlay_hint_marks(
+    hints: &[crate::core::lsp::InlayHint],
+    line_idx: usize,
+    line_text: &str,
+) -> Vec<InlayHintMark> {
+    let line_len = line_text.trim_end_matches(['\n', '\r']).chars().count();
+    hints
+        .iter()
+        .filter(|h| h.line as usize == line_idx)
+        .map(|h| InlayHintMark {
+            col: crate::core::lsp::utf16_offset_to_char(line_text, h.character).min(line_len),
+            label: h.label.clone(),
+        })
+        .collect()
+}
+
+/// Total display width of the inlay hints drawn before `col` on a row.
+pub fn inlay_hint_width_before(hints: &[InlayHintMark], col: usize) -> usize {
+    hints
+        .iter()
+        .filter(|h| h.col <= col)
+        .map(|h| h.label.chars().count())
+        .sum()
+}
+
+/// Display width of a rendered row's text: tabs expanded to `tabstop`,
+/// inlay hint labels included, the line break left out.  Text drawn after
+/// the content (ghost text, annotations, diagnostics) starts here.
+pub fn line_display_cols(line: &RenderedLine, tabstop: usize) -> usize {
+    let tabstop = tabstop.max(1);
+    let mut cols = 0usize;
+    for ch in line.raw_text.trim_end_matches(['\n', '\r']).chars() {
+        if ch == '\t' {
+            cols += tabstop - (cols % tabstop);
+        } else {
+            cols += 1;
+        }
+    }
+    cols + inlay_hint_width_before(&line.inlay_hints, usize::MAX)
+}
+
+/// Map a display column on a row (which includes inlay hint labels) back to
+/// the display column it would have without hints. Clicks inside a hint land
+/// on the hint's anchor column.
+pub fn strip_inlay_hint_cols(
+    line_text: &str,
+    hints: &[InlayHintMark],
+    display_col: usize,
+    tabstop: usize,
+) -> usize {
+    let tabstop = tabstop.max(1);
+    let mut shift = 0usize;
+    for h in hints {
+        let mut anchor = 0usize;
+        for ch in line_text.chars().take(h.col) {
+            if ch == '\t' {
+                anchor += tabstop - (anchor % tabstop);
+            } else {
+                anchor += 1;
+            }
+        }
+        let start = anchor + shift;
+        let width = h.label.chars().count();
+        if display_col < start {
+            break;
+        }
+        if display_col < start + width {
+            return anchor;
+        }
+        shift += width;
+    }
+    display_col - shift
+}
+
+/// Row text with inlay hint labels spliced in, plus the byte range of each
+/// label within it. Used by backends that lay out a whole row at once (GTK).
+pub fn inlay_hint_display(line: &RenderedLine) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::with_capacity(line.raw_text.len());
+    let mut ranges = Vec::with_capacity(line.inlay_hints.len());
+    let mut hints = line.inlay_hints.iter().peekable();
+    for (i, ch) in line.raw_text.chars().enumerate() {
+        if ch != '\n' && ch != '\r' {
+            while let Some(h) = hints.next_if(|h| h.col <= i) {
+                ranges.push((text.len(), text.len() + h.label.len()));
+                text.push_str(&h.label);
+            }
+        } else {
+            for h in hints.by_ref() {
+                ranges.push((text.len(), text.len() + h.label.len()));
+                text.push_str(&h.label);
+            }
+        }
+        text.push(ch);
+    }
+    for h in hints {
+        ranges.push((text.len(), text.len() + h.label.len()));
+        text.push_str(&h.label);
+    }
+    (text, ranges)
+}
+
+/// Map a byte offset in `line.raw_text` to the matching offset in the text
+/// returned by [`inlay_hint_display`].
+pub fn inlay_hint_display_byte(line: &RenderedLine, raw_byte: usize) -> usize {
+    if line.inlay_hints.is_empty() {
+        return raw_byte;
+    }
+    let raw_byte = raw_byte.min(line.raw_text.len());
+    let col = line.raw_text[..raw_byte].chars().count();
+    let shift: usize = line
+        .inlay_hints
+        .iter()
+        .filter(|h| h.col <= col)
+        .map(|h| h.label.len())
+        .sum();
+    raw_byte + shift
+}
+
 /// A misspelled word on a rendered line (for underline/squiggle rendering).
 #[derive(Debug, Clone)]
 pub struct SpellMark {
@@ -346,10 +560,28 @@ pub enum CursorShape {
     Block,
     /// Thin vertical bar (Insert mode).
     Bar,
-    /// Underline (pending replace-char `r` command).
+    /// Underline (Replace mode and pending replace-char `r` command).
     Underline,
 }
 
+/// The `cursorstyle` look for the engine's current mode: the pending `r`
+/// command counts as replace, and VSCode mode as insert.
+pub fn active_cursor_style(engine: &Engine) -> ModeCursorStyle {
+    let styles = engine.settings.cursor_styles();
+    if engine.pending_key == Some('r') {
+        styles.replace
+    } else if engine.is_vscode_mode() {
+        styles.insert
+    } else {
+        match engine.mode {
+            Mode::Insert => styles.insert,
+            Mode::Replace => styles.replace,
+            Mode::Visual | Mode::VisualLine | Mode::VisualBlock => styles.visual,
+            _ => styles.normal,
+        }
+    }
+}
+
 /// Cursor position within the visible window area.
 #[derive(Debug, Clone, Copy)]
 pub struct CursorPos {
@@ -688,6 +920,8 @@ pub struct RenderedWindow {
     pub code_action_lines: std::collections::HashSet<usize>,
     /// Transient yank-highlight region (flashes briefly after a yank). `None` if no active highlight.
     pub yank_highlight: Option<SelectionRange>,
+    /// Active snippet placeholder (drawn like a selection). `None` outside a snippet.
+    pub snippet_placeholder: Option<SelectionRange>,
     /// Bracket pair positions to highlight (cursor bracket + matching bracket).
     /// Each entry is (view_line, col). Up to 2 entries.
     pub bracket_match_positions: Vec<(usize, usize)>,
@@ -699,6 +933,81 @@ pub struct RenderedWindow {
     pub cursorline: bool,
     /// Per-window status line (Vim-style), or `None` when the setting is off.
     pub status_line: Option<WindowStatusLine>,
+    /// Line annotations are git blame (`:GitBlame`): draw them right-aligned
+    /// in `theme.blame_fg` instead of directly after the text.
+    pub blame_annotations: bool,
+    /// Whitespace glyphs to draw (`:set list`), or `None` when the option is off.
+    /// Backends substitute them at draw time; line text and columns are unchanged.
+    pub list_chars: Option<ListChars>,
+    /// Winbar text (`:set winbar`), drawn in `theme.winbar_bg` in the row
+    /// directly above `rect`; empty when the buffer has no LSP symbols.
+    pub winbar: Option<String>,
+    /// Scope headers pinned over the first rows of `lines` by sticky scroll
+    /// (`:set stickyscroll`), outermost first.  Drawn on `theme.sticky_bg`;
+    /// clicks on them go to `Engine::sticky_scroll_jump`.
+    pub sticky_lines: Vec<StickyLine>,
+    /// Cursor colour from `cursorstyle`; `None` uses `theme.cursor`.
+    pub cursor_color: Option<Color>,
+    /// The cursor blinks in the current mode (`cursorstyle`).
+    pub cursor_blink: bool,
+    /// Fraction of a line the rows are scrolled up by during a smooth scroll
+    /// (`View::scroll_offset`).  Backends draw every row (not the sticky lines)
+    /// that much higher; `lines` then holds one extra row for the partially
+    /// revealed bottom line.
+    pub scroll_frac: f64,
+    /// Code overview strip (`:set minimap`); only the active window of a GUI
+    /// backend has one.
+    pub minimap: Option<Minimap>,
+}
+
+/// One header line pinned by sticky scroll.
+#[derive(Debug, Clone)]
+pub struct StickyLine {
+    /// Buffer line of the header.
+    pub line_idx: usize,
+    /// Gutter text, same width as the window's other rows.
+    pub gutter_text: String,
+    /// Raw UTF-8 text (may include a trailing `\n`).
+    pub raw_text: String,
+    /// Syntax-highlight spans (byte-offset based).
+    pub spans: Vec<StyledSpan>,
+}
+
+/// Pixel width of the minimap strip.
+pub const MINIMAP_WIDTH_PX: f64 = 80.0;
+/// Pixel height of one minimap row.
+pub const MINIMAP_ROW_PX: f64 = 2.0;
+/// Pixel width of one text column in the minimap.
+pub const MINIMAP_COL_PX: f64 = 1.0;
+
+/// Pixels GUI backends reserve on the right of each window for the vertical
+/// scrollbar overlay.  CSS requests 4px but GTK may allocate slightly more;
+/// 8px is a safe reserve.
+const V_SCROLLBAR_RESERVE_PX: f64 = 8.0;
+
+/// Compressed overview of a window's buffer, drawn in a strip on its right
+/// edge (`:set minimap`).  Long buffers are sampled so every row of the strip
+/// stands for the same number of displayed lines.
+#[derive(Debug, Clone)]
+pub struct Minimap {
+    /// Pixel rect of the strip.
+    pub rect: WindowRect,
+    /// Sampled lines, one per `MINIMAP_ROW_PX` from the top of `rect`.
+    pub rows: Vec<MinimapRow>,
+    /// Top of the viewport rectangle, in pixels below `rect.y`.
+    pub viewport_y: f64,
+    /// Height of the viewport rectangle in pixels.
+    pub viewport_height: f64,
+}
+
+/// One minimap row: the non-blank runs of a sampled buffer line.
+#[derive(Debug, Clone)]
+pub struct MinimapRow {
+    /// Buffer line the row was sampled from.
+    pub line_idx: usize,
+    /// `(start_col, len, color)` runs of non-whitespace text, each tinted
+    /// with the span colour under its first character.
+    pub blocks: Vec<(usize, usize, Color)>,
 }
 
 // ─── CommandLineData ──────────────────────────────────────────────────────────
@@ -715,6 +1024,8 @@ pub struct CommandLineData {
     /// Text whose rendered pixel-width determines the cursor's x position.
     /// Often equal to `text`, but may differ (e.g. history-search display).
     pub cursor_anchor_text: String,
+    /// When `true`, `text` is an error or warning message (draw in the error color).
+    pub is_error: bool,
 }
 
 // ─── WildmenuData ─────────────────────────────────────────────────────────────
@@ -739,8 +1050,20 @@ pub struct CompletionMenu {
     pub selected_idx: usize,
     /// Length (in chars) of the longest candidate — used for popup width.
     pub max_width: usize,
+    /// Per candidate, byte positions that fuzzy-matched the typed prefix
+    /// (for highlight). Empty when there is nothing typed to match.
+    pub match_positions: Vec<Vec<usize>>,
+    /// Documentation for the selected candidate, wrapped to
+    /// `COMPLETION_DOC_WIDTH` and shown in a panel beside the popup.
+    /// Empty when there is nothing to show.
+    pub documentation: Vec<String>,
 }
 
+/// Wrap width of the completion documentation panel.
+pub const COMPLETION_DOC_WIDTH: usize = 60;
+/// Maximum number of lines shown in the completion documentation panel.
+pub const COMPLETION_DOC_MAX_LINES: usize = 15;
+
 // ─── HoverPopup ──────────────────────────────────────────────────────────────
 
 /// Data needed to render the LSP hover popup.
@@ -831,15 +1154,80 @@ pub struct PickerPanel {
     pub scroll_top: usize,
     /// Total number of source items (for the "N/M" counter).
     pub total_count: usize,
-    /// Preview lines: (1-based line number, text, is_highlighted).
-    /// When `Some`, the picker is rendered in two-pane mode.
-    pub preview: Option<Vec<(usize, String, bool)>>,
+    /// Preview pane content.  When `Some`, the picker is rendered in
+    /// two-pane mode.
+    pub preview_lines: Option<Vec<PickerPreviewLine>>,
+    /// Shown in the preview pane instead of lines (`(binary)`, `Loading…`).
+    pub preview_note: Option<String>,
     /// Scroll offset for the preview pane.
     pub preview_scroll: usize,
 }
 
+/// One line of the picker's preview pane.
+#[derive(Debug, Clone)]
+pub struct PickerPreviewLine {
+    /// 1-based line number.
+    pub lineno: usize,
+    pub text: String,
+    /// The line a grep result points at.
+    pub is_match: bool,
+    /// Syntax colours: (start byte, end byte, colour) within `text`.
+    pub spans: Vec<(usize, usize, Color)>,
+}
+
 // ─── TabSwitcherPanel ─────────────────────────────────────────────────────
 
+/// Which-key popup: the keys that can follow a pending multi-key prefix.
+#[derive(Debug, Clone)]
+pub struct WhichKeyPanel {
+    /// Keys typed so far, e.g. `"<leader>s"` or `"g"`.
+    pub prefix: String,
+    /// `(next key, description)` pairs sorted by key; groups read `+name`.
+    pub entries: Vec<(String, String)>,
+}
+
+/// Most rows the which-key panel may use; entries that don't fit are
+/// summarised in a final `+N more` cell.
+pub const WHICH_KEY_MAX_ROWS: usize = 10;
+
+/// Grid for a which-key panel `width` cells wide: returns
+/// `(column width in cells, columns, rows)`.  Each cell reads `key → desc`.
+pub fn which_key_grid(panel: &WhichKeyPanel, width: usize) -> (usize, usize, usize) {
+    let key_w = panel
+        .entries
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
+    let desc_w = panel
+        .entries
+        .iter()
+        .map(|(_, d)| d.chars().count())
+        .max()
+        .unwrap_or(0);
+    // key + " → " + desc + 2 cells of gutter, capped so narrow panels still fit.
+    let col_w = (key_w + 3 + desc_w + 2).min(width.max(1));
+    let cols = (width / col_w.max(1)).max(1);
+    let rows = panel.entries.len().div_ceil(cols).min(WHICH_KEY_MAX_ROWS);
+    (col_w, cols, rows)
+}
+
+/// The `(key, description)` cells that fit a `cols` × `rows` which-key grid.
+/// When some entries don't fit, the last cell reads `… +N more` instead.
+pub fn which_key_cells(panel: &WhichKeyPanel, cols: usize, rows: usize) -> Vec<(String, String)> {
+    let capacity = cols * rows;
+    if panel.entries.len() <= capacity {
+        return panel.entries.clone();
+    }
+    let shown = capacity.saturating_sub(1);
+    let mut cells = panel.entries[..shown].to_vec();
+    cells.push((
+        "…".to_string(),
+        format!("+{} more", panel.entries.len() - shown),
+    ));
+    cells
+}
+
 /// Data needed to render the tab switcher popup (Ctrl+Tab MRU list).
 #[derive(Debug, Clone)]
 pub struct TabSwitcherPanel {
@@ -1452,7 +1840,7 @@ pub static MENU_STRUCTURE: &[(&str, char, &[MenuItemData])] = &[
                 label: "Toggle Terminal",
                 shortcut: "Ctrl+T",
                 vscode_shortcut: "",
-                action: "terminal",
+                action: "TerminalPanel",
                 enabled: true,
                 separator: false,
             },
@@ -1718,7 +2106,7 @@ pub static MENU_STRUCTURE: &[(&str, char, &[MenuItemData])] = &[
                 label: "New Terminal",
                 shortcut: "",
                 vscode_shortcut: "",
-                action: "terminal",
+                action: "TerminalPanel",
                 enabled: true,
                 separator: false,
             },
@@ -1861,6 +2249,8 @@ pub enum UiElement {
     PickerPopup,
     /// Tab switcher popup (Ctrl+Tab MRU list).
     TabSwitcher,
+    /// Which-key popup (pending multi-key prefix).
+    WhichKey,
     /// Context menu popup (right-click).
     ContextMenu,
     /// Modal dialog popup.
@@ -2217,6 +2607,9 @@ pub fn collect_expected_ui_elements(layout: &ScreenLayout) -> Vec<UiElement> {
     if layout.tab_switcher.is_some() {
         elems.push(UiElement::TabSwitcher);
     }
+    if layout.which_key.is_some() {
+        elems.push(UiElement::WhichKey);
+    }
     if layout.context_menu.is_some() {
         elems.push(UiElement::ContextMenu);
     }
@@ -2365,6 +2758,9 @@ pub fn collect_ui_elements_wingui(layout: &ScreenLayout) -> Vec<UiElement> {
     if layout.tab_switcher.is_some() {
         elems.push(UiElement::TabSwitcher);
     }
+    if layout.which_key.is_some() {
+        elems.push(UiElement::WhichKey);
+    }
 
     // draw_frame(): context menu
     if layout.context_menu.is_some() {
@@ -2553,6 +2949,9 @@ pub fn collect_ui_elements_tui(layout: &ScreenLayout) -> Vec<UiElement> {
     if layout.tab_switcher.is_some() {
         elems.push(UiElement::TabSwitcher);
     }
+    if layout.which_key.is_some() {
+        elems.push(UiElement::WhichKey);
+    }
 
     // Context menu
     if layout.context_menu.is_some() {
@@ -2615,6 +3014,9 @@ pub struct ScreenLayout {
     pub picker: Option<PickerPanel>,
     /// Tab switcher popup (Ctrl+Tab MRU list) — `Some` when open.
     pub tab_switcher: Option<TabSwitcherPanel>,
+    /// Which-key popup at the bottom of the editor area — `Some` while a
+    /// multi-key prefix has been pending long enough.
+    pub which_key: Option<WhichKeyPanel>,
     /// When the editor is split into two groups, this carries group 1's tab bar
     /// and split geometry. `None` in the default single-group mode.
     pub editor_group_split: Option<EditorGroupSplitData>,
@@ -2766,6 +3168,9 @@ pub struct DiffPeekPopup {
 
 /// All colours used by the editor UI.
 /// Derive new themes by constructing a `Theme` with different field values.
+/// Native theme files (see [`Theme::from_color_map_json`]) use the field
+/// names as keys.
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct Theme {
     // Editor background
     pub background: Color,
@@ -2809,6 +3214,8 @@ pub struct Theme {
     pub search_match_bg: Color,
     pub search_current_match_bg: Color,
     pub search_match_fg: Color,
+    /// Background for other occurrences of the word under the cursor.
+    pub word_highlight_bg: Color,
 
     // Yank highlight flash
     pub yank_highlight_bg: Color,
@@ -2816,10 +3223,15 @@ pub struct Theme {
 
     // Virtual text / line annotations (e.g. git blame inline)
     pub annotation_fg: Color,
+    /// Right-aligned `:GitBlame` virtual text.
+    pub blame_fg: Color,
 
     // AI ghost text (inline completions)
     pub ghost_text_fg: Color,
 
+    // LSP inlay hints (inferred types, parameter names)
+    pub inlay_hint_fg: Color,
+
     // Tab bar
     pub tab_bar_bg: Color,
     pub tab_active_bg: Color,
@@ -2938,6 +3350,12 @@ pub struct Theme {
     pub breadcrumb_fg: Color,
     pub breadcrumb_active_fg: Color,
 
+    // Winbar (`:set winbar`)
+    pub winbar_bg: Color,
+
+    // Sticky scroll (`:set stickyscroll`)
+    pub sticky_bg: Color,
+
     // Indent guides
     pub indent_guide_fg: Color,
     pub indent_guide_active_fg: Color,
@@ -2945,6 +3363,12 @@ pub struct Theme {
     // Color column (`:set colorcolumn=80`)
     pub colorcolumn_bg: Color,
 
+    // Trailing whitespace (`highlight_trailing_whitespace`)
+    pub trailing_whitespace_bg: Color,
+
+    // Whitespace glyphs (`:set list`)
+    pub whitespace_fg: Color,
+
     // Bracket match highlight
     pub bracket_match_bg: Color,
 
@@ -3019,6 +3443,7 @@ impl Theme {
             // Pango 16-bit: (255*256, 200*256, 0) → RGB(255, 200, 0)
             search_current_match_bg: Color::from_hex("#ffc800"),
             search_match_fg: Color::from_hex("#000000"),
+            word_highlight_bg: Color::from_hex("#3e4451"),
 
             // (0.15, 0.15, 0.2)
             tab_bar_bg: Color::from_hex("#262633"),
@@ -3112,9 +3537,11 @@ impl Theme {
 
             // Virtual text annotations (muted grey — matches comment colour)
             annotation_fg: Color::from_hex("#5c6370"),
+            blame_fg: Color::from_hex("#5c6370"),
 
             // AI ghost text (inline completions) — slightly lighter than annotation
             ghost_text_fg: Color::from_hex("#4b5263"),
+            inlay_hint_fg: Color::from_hex("#4b5263"),
 
             // Markdown preview
             md_heading1: Color::from_hex("#e5c07b"), // gold
@@ -3137,10 +3564,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#21252b"),
             breadcrumb_fg: Color::from_hex("#7f848e"),
             breadcrumb_active_fg: Color::from_hex("#abb2bf"),
+            winbar_bg: Color::from_hex("#2c313a"),
+            sticky_bg: Color::from_hex("#2c313a"),
 
             indent_guide_fg: Color::from_hex("#404040"),
             indent_guide_active_fg: Color::from_hex("#606060"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#6b2d32"),
+            whitespace_fg: Color::from_hex("#4b5263"),
             bracket_match_bg: Color::from_hex("#3a3d41"),
 
             explorer_dir_fg: Color::from_hex("#61afef"), // function blue
@@ -3192,6 +3623,7 @@ impl Theme {
             search_match_bg: Color::from_hex("#d65d0e"),
             search_current_match_bg: Color::from_hex("#fe8019"),
             search_match_fg: Color::from_hex("#1d2021"),
+            word_highlight_bg: Color::from_hex("#504945"),
 
             tab_bar_bg: Color::from_hex("#3c3836"),
             tab_active_bg: Color::from_hex("#504945"),
@@ -3265,7 +3697,10 @@ impl Theme {
             yank_highlight_alpha: 0.35,
 
             annotation_fg: Color::from_hex("#928374"),
+            blame_fg: Color::from_hex("#928374"),
+
             ghost_text_fg: Color::from_hex("#7c6f64"),
+            inlay_hint_fg: Color::from_hex("#7c6f64"),
 
             md_heading1: Color::from_hex("#fabd2f"),
             md_heading2: Color::from_hex("#83a598"),
@@ -3287,10 +3722,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#32302f"),
             breadcrumb_fg: Color::from_hex("#a89984"),
             breadcrumb_active_fg: Color::from_hex("#ebdbb2"),
+            winbar_bg: Color::from_hex("#3c3836"),
+            sticky_bg: Color::from_hex("#3c3836"),
 
             indent_guide_fg: Color::from_hex("#3c3836"),
             indent_guide_active_fg: Color::from_hex("#504945"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#7c2f26"),
+            whitespace_fg: Color::from_hex("#665c54"),
             bracket_match_bg: Color::from_hex("#504945"),
 
             explorer_dir_fg: Color::from_hex("#83a598"), // gruvbox blue
@@ -3342,6 +3781,7 @@ impl Theme {
             search_match_bg: Color::from_hex("#3d59a1"),
             search_current_match_bg: Color::from_hex("#ff9e64"),
             search_match_fg: Color::from_hex("#c0caf5"),
+            word_highlight_bg: Color::from_hex("#2f334d"),
 
             tab_bar_bg: Color::from_hex("#16161e"),
             tab_active_bg: Color::from_hex("#292e42"),
@@ -3415,7 +3855,10 @@ impl Theme {
             yank_highlight_alpha: 0.35,
 
             annotation_fg: Color::from_hex("#565f89"),
+            blame_fg: Color::from_hex("#565f89"),
+
             ghost_text_fg: Color::from_hex("#414868"),
+            inlay_hint_fg: Color::from_hex("#414868"),
 
             md_heading1: Color::from_hex("#e0af68"),
             md_heading2: Color::from_hex("#7aa2f7"),
@@ -3437,10 +3880,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#1f2335"),
             breadcrumb_fg: Color::from_hex("#565f89"),
             breadcrumb_active_fg: Color::from_hex("#c0caf5"),
+            winbar_bg: Color::from_hex("#24283b"),
+            sticky_bg: Color::from_hex("#24283b"),
 
             indent_guide_fg: Color::from_hex("#292e42"),
             indent_guide_active_fg: Color::from_hex("#3b4261"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#5c2a3a"),
+            whitespace_fg: Color::from_hex("#3b4261"),
             bracket_match_bg: Color::from_hex("#364a82"),
 
             explorer_dir_fg: Color::from_hex("#7aa2f7"), // tokyo blue
@@ -3492,6 +3939,7 @@ impl Theme {
             search_match_bg: Color::from_hex("#cb4b16"),
             search_current_match_bg: Color::from_hex("#d33682"),
             search_match_fg: Color::from_hex("#fdf6e3"),
+            word_highlight_bg: Color::from_hex("#0d4a5a"),
 
             tab_bar_bg: Color::from_hex("#073642"),
             tab_active_bg: Color::from_hex("#0d4a5a"),
@@ -3565,7 +4013,10 @@ impl Theme {
             yank_highlight_alpha: 0.35,
 
             annotation_fg: Color::from_hex("#586e75"),
+            blame_fg: Color::from_hex("#586e75"),
+
             ghost_text_fg: Color::from_hex("#4a5e68"),
+            inlay_hint_fg: Color::from_hex("#4a5e68"),
 
             md_heading1: Color::from_hex("#b58900"),
             md_heading2: Color::from_hex("#268bd2"),
@@ -3587,10 +4038,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#073642"),
             breadcrumb_fg: Color::from_hex("#586e75"),
             breadcrumb_active_fg: Color::from_hex("#93a1a1"),
+            winbar_bg: Color::from_hex("#0a3d4a"),
+            sticky_bg: Color::from_hex("#0a3d4a"),
 
             indent_guide_fg: Color::from_hex("#073642"),
             indent_guide_active_fg: Color::from_hex("#0d4a5a"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#6c2b2b"),
+            whitespace_fg: Color::from_hex("#2d5a66"),
             bracket_match_bg: Color::from_hex("#0d4a5a"),
 
             explorer_dir_fg: Color::from_hex("#268bd2"), // solarized blue
@@ -3642,6 +4097,7 @@ impl Theme {
             search_match_bg: Color::from_hex("#515c6a"),
             search_current_match_bg: Color::from_hex("#613214"),
             search_match_fg: Color::from_hex("#d4d4d4"),
+            word_highlight_bg: Color::from_hex("#4a4a4a"),
 
             tab_bar_bg: Color::from_hex("#252526"),
             tab_active_bg: Color::from_hex("#1e1e1e"),
@@ -3715,7 +4171,10 @@ impl Theme {
             yank_highlight_alpha: 0.25,
 
             annotation_fg: Color::from_hex("#858585"),
+            blame_fg: Color::from_hex("#858585"),
+
             ghost_text_fg: Color::from_hex("#5a5a5a"),
+            inlay_hint_fg: Color::from_hex("#5a5a5a"),
 
             md_heading1: Color::from_hex("#dcdcaa"),
             md_heading2: Color::from_hex("#569cd6"),
@@ -3737,10 +4196,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#1e1e1e"),
             breadcrumb_fg: Color::from_hex("#858585"),
             breadcrumb_active_fg: Color::from_hex("#d4d4d4"),
+            winbar_bg: Color::from_hex("#252526"),
+            sticky_bg: Color::from_hex("#252526"),
 
             indent_guide_fg: Color::from_hex("#404040"),
             indent_guide_active_fg: Color::from_hex("#707070"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#5a1d1d"),
+            whitespace_fg: Color::from_hex("#3b3b3b"),
             bracket_match_bg: Color::from_hex("#3a3d41"),
 
             explorer_dir_fg: Color::from_hex("#dcdcaa"), // warm yellow (like function names)
@@ -3792,6 +4255,7 @@ impl Theme {
             search_match_bg: Color::from_hex("#e8be5a"),
             search_current_match_bg: Color::from_hex("#a8ac94"),
             search_match_fg: Color::from_hex("#000000"),
+            word_highlight_bg: Color::from_hex("#e2e6d6"),
 
             tab_bar_bg: Color::from_hex("#ececec"),
             tab_active_bg: Color::from_hex("#ffffff"),
@@ -3864,7 +4328,10 @@ impl Theme {
             yank_highlight_alpha: 0.2,
 
             annotation_fg: Color::from_hex("#8e8e8e"),
+            blame_fg: Color::from_hex("#8e8e8e"),
+
             ghost_text_fg: Color::from_hex("#b0b0b0"),
+            inlay_hint_fg: Color::from_hex("#b0b0b0"),
 
             md_heading1: Color::from_hex("#795e26"),
             md_heading2: Color::from_hex("#0000ff"),
@@ -3886,10 +4353,14 @@ impl Theme {
             breadcrumb_bg: Color::from_hex("#ffffff"),
             breadcrumb_fg: Color::from_hex("#8e8e8e"),
             breadcrumb_active_fg: Color::from_hex("#333333"),
+            winbar_bg: Color::from_hex("#f3f3f3"),
+            sticky_bg: Color::from_hex("#f3f3f3"),
 
             indent_guide_fg: Color::from_hex("#d3d3d3"),
             indent_guide_active_fg: Color::from_hex("#939393"),
             colorcolumn_bg: bg.colorcolumn_tint(),
+            trailing_whitespace_bg: Color::from_hex("#f4c7c7"),
+            whitespace_fg: Color::from_hex("#cacaca"),
             bracket_match_bg: Color::from_hex("#dddddd"),
 
             explorer_dir_fg: Color::from_hex("#795e26"), // warm brown dirs
@@ -3903,6 +4374,87 @@ impl Theme {
         }
     }
 
+    /// High-contrast dark scheme for accessibility: pure black background,
+    /// white text and saturated accents, after VS Code's "Dark High Contrast".
+    /// Colours not listed here come from `vscode_dark`.
+    pub fn high_contrast() -> Self {
+        let bg = Color::from_hex("#000000");
+        Self {
+            background: bg,
+            active_background: Color::from_hex("#0c0c0c"),
+            foreground: Color::from_hex("#ffffff"),
+
+            keyword: Color::from_hex("#569cd6"),
+            control_flow: Color::from_hex("#c586c0"),
+            string_lit: Color::from_hex("#ce9178"),
+            comment: Color::from_hex("#7ca668"),
+            function: Color::from_hex("#dcdcaa"),
+            type_name: Color::from_hex("#4ec9b0"),
+            variable: Color::from_hex("#9cdcfe"),
+            number: Color::from_hex("#b5cea8"),
+            operator: Color::from_hex("#ffffff"),
+            punctuation: Color::from_hex("#ffffff"),
+            default_fg: Color::from_hex("#ffffff"),
+
+            selection: Color::from_hex("#264f78"),
+            selection_alpha: 0.9,
+            cursor: Color::from_hex("#ffffff"),
+            cursor_normal_alpha: 0.8,
+
+            search_match_bg: Color::from_hex("#f38518"),
+            search_current_match_bg: Color::from_hex("#ffff00"),
+            search_match_fg: Color::from_hex("#000000"),
+
+            tab_bar_bg: bg,
+            tab_active_bg: bg,
+            tab_active_fg: Color::from_hex("#ffffff"),
+            tab_inactive_fg: Color::from_hex("#d0d0d0"),
+            tab_active_accent: Color::from_hex("#f38518"),
+
+            status_bg: bg,
+            status_fg: Color::from_hex("#ffffff"),
+            status_inactive_bg: bg,
+            status_inactive_fg: Color::from_hex("#d0d0d0"),
+
+            wildmenu_bg: bg,
+            wildmenu_fg: Color::from_hex("#ffffff"),
+            command_bg: bg,
+            command_fg: Color::from_hex("#ffffff"),
+
+            line_number_fg: Color::from_hex("#d0d0d0"),
+            line_number_active_fg: Color::from_hex("#ffffff"),
+            separator: Color::from_hex("#6fc3df"),
+
+            completion_bg: bg,
+            completion_fg: Color::from_hex("#ffffff"),
+            completion_border: Color::from_hex("#6fc3df"),
+            hover_bg: bg,
+            hover_fg: Color::from_hex("#ffffff"),
+            hover_border: Color::from_hex("#6fc3df"),
+            fuzzy_bg: bg,
+            fuzzy_fg: Color::from_hex("#ffffff"),
+            fuzzy_border: Color::from_hex("#6fc3df"),
+
+            diagnostic_error: Color::from_hex("#ff3232"),
+            diagnostic_warning: Color::from_hex("#ffd700"),
+
+            cursorline_bg: bg.cursorline_tint(),
+            colorcolumn_bg: bg.colorcolumn_tint(),
+            breadcrumb_bg: bg,
+            breadcrumb_fg: Color::from_hex("#d0d0d0"),
+            breadcrumb_active_fg: Color::from_hex("#ffffff"),
+            winbar_bg: bg,
+            sticky_bg: Color::from_hex("#0c0c0c"),
+
+            explorer_file_fg: Color::from_hex("#ffffff"),
+            scrollbar_thumb: Color::from_hex("#6fc3df"),
+            scrollbar_track: bg,
+            terminal_bg: bg,
+            activity_bar_fg: Color::from_hex("#ffffff"),
+            ..Self::vscode_dark()
+        }
+    }
+
     /// Return a theme by name. Falls back to `onedark` for unknown names.
     pub fn from_name(name: &str) -> Self {
         match name {
@@ -3910,16 +4462,12 @@ impl Theme {
             "tokyo-night" | "tokyonight" => Self::tokyo_night(),
             "solarized" | "solarized-dark" => Self::solarized_dark(),
             "vscode-dark" | "vscode" | "dark+" => Self::vscode_dark(),
-            "vscode-light" | "light+" => Self::vscode_light(),
+            "vscode-light" | "light+" | "light" => Self::vscode_light(),
+            "high-contrast" | "hc" => Self::high_contrast(),
             "onedark" => Self::onedark(),
-            _ => {
-                // Try loading a VSCode theme from ~/.config/vimcode/themes/
-                if let Some(theme) = Self::load_vscode_theme(name) {
-                    theme
-                } else {
-                    Self::onedark()
-                }
-            }
+            // Theme files in ~/.config/vimcode/themes/; missing or malformed
+            // files fall back to OneDark.
+            _ => Self::load_theme_file(name).unwrap_or_else(Self::onedark),
         }
     }
 
@@ -3943,6 +4491,7 @@ impl Theme {
             "solarized-dark".into(),
             "vscode-dark".into(),
             "vscode-light".into(),
+            "high-contrast".into(),
         ];
         // Append custom VSCode themes from ~/.config/vimcode/themes/
         if let Some(dir) = Self::themes_dir() {
@@ -3965,12 +4514,39 @@ impl Theme {
         std::env::var_os("HOME").map(|h| std::path::PathBuf::from(h).join(".config/vimcode/themes"))
     }
 
-    /// Try to load a VSCode-format `.json` theme file by name.
-    /// Looks in `~/.config/vimcode/themes/<name>.json`.
-    pub fn load_vscode_theme(name: &str) -> Option<Self> {
+    /// Try to load `~/.config/vimcode/themes/<name>.json`, either a native
+    /// colour map or a VSCode theme (recognised by its `colors` or
+    /// `tokenColors` key).
+    pub fn load_theme_file(name: &str) -> Option<Self> {
         let dir = Self::themes_dir()?;
         let path = dir.join(format!("{name}.json"));
-        Self::from_vscode_json(&path)
+        let data = std::fs::read_to_string(&path).ok()?;
+        let val: serde_json::Value = serde_json::from_str(&strip_json_comments(&data)).ok()?;
+        if val.get("colors").is_some() || val.get("tokenColors").is_some() {
+            Self::from_vscode_json(&path)
+        } else {
+            Self::from_color_map_json(&path)
+        }
+    }
+
+    /// Parse a native theme file: a JSON object mapping `Theme` field names
+    /// to `#rrggbb` colours (numbers for the `*_alpha` fields).  Fields left
+    /// out keep their OneDark value and unknown keys (e.g. `"name"`) are
+    /// ignored; a value of the wrong type makes the whole file invalid.
+    pub fn from_color_map_json(path: &std::path::Path) -> Option<Self> {
+        let data = std::fs::read_to_string(path).ok()?;
+        let val: serde_json::Value = serde_json::from_str(&strip_json_comments(&data)).ok()?;
+        let serde_json::Value::Object(overrides) = val else {
+            return None;
+        };
+        let mut merged = serde_json::to_value(Self::onedark()).ok()?;
+        let fields = merged.as_object_mut()?;
+        for (key, value) in overrides {
+            if let Some(field) = fields.get_mut(&key) {
+                *field = value;
+            }
+        }
+        serde_json::from_value(merged).ok()
     }
 
     /// Parse a VSCode theme JSON file and map its colours to a `Theme`.
@@ -4019,6 +4595,12 @@ impl Theme {
         if let Some(c) = color("editorRuler.foreground") {
             theme.colorcolumn_bg = c;
         }
+        if let Some(c) = color("diffEditor.removedTextBackground") {
+            theme.trailing_whitespace_bg = c;
+        }
+        if let Some(c) = color("editorWhitespace.foreground") {
+            theme.whitespace_fg = c;
+        }
 
         // ── Search ────────────────────────────────────────────────────────
         if let Some(c) = color("editor.findMatchBackground") {
@@ -4027,6 +4609,9 @@ impl Theme {
         if let Some(c) = color("editor.findMatchHighlightBackground") {
             theme.search_match_bg = c;
         }
+        if let Some(c) = color("editor.wordHighlightBackground") {
+            theme.word_highlight_bg = c;
+        }
 
         // ── Line numbers ──────────────────────────────────────────────────
         if let Some(c) = color("editorLineNumber.foreground") {
@@ -4134,6 +4719,10 @@ impl Theme {
         {
             theme.breadcrumb_active_fg = c;
         }
+        if let Some(c) = color("editorStickyScroll.background") {
+            theme.winbar_bg = c;
+            theme.sticky_bg = c;
+        }
 
         // ── Git gutter ────────────────────────────────────────────────────
         if let Some(c) = color("editorGutter.addedBackground")
@@ -4193,6 +4782,9 @@ impl Theme {
         if let Some(c) = color("editorGhostText.foreground") {
             theme.ghost_text_fg = c;
         }
+        if let Some(c) = color("editorInlayHint.foreground") {
+            theme.inlay_hint_fg = c;
+        }
 
         // ── Token colours (syntax highlighting) ──────────────────────────
         if let Some(tc) = token_colors.and_then(|v| v.as_array()) {
@@ -4240,6 +4832,7 @@ impl Theme {
                         "comment" | "comment.line" | "comment.block" => {
                             theme.comment = fg;
                             theme.annotation_fg = fg;
+                            theme.blame_fg = fg;
                         }
                         "entity.name.function" | "support.function" | "meta.function-call" => {
                             theme.function = fg;
@@ -4326,6 +4919,18 @@ impl Theme {
         Some(theme)
     }
 
+    /// Dimmed severity colour for end-of-line diagnostic virtual text.
+    pub fn diagnostic_virtual_text(&self, severity: crate::core::lsp::DiagnosticSeverity) -> Color {
+        use crate::core::lsp::DiagnosticSeverity;
+        let base = match severity {
+            DiagnosticSeverity::Error => self.diagnostic_error,
+            DiagnosticSeverity::Warning => self.diagnostic_warning,
+            DiagnosticSeverity::Information => self.diagnostic_info,
+            DiagnosticSeverity::Hint => self.diagnostic_hint,
+        };
+        base.blend(self.background, 0.35)
+    }
+
     /// Return the foreground colour for a Tree-sitter scope name.
     pub fn scope_color(&self, scope: &str) -> Color {
         match scope {
@@ -4407,6 +5012,32 @@ impl Theme {
 
 // ─── build_screen_layout ──────────────────────────────────────────────────────
 
+/// Colour the picker preview's lines from their tree-sitter scopes.
+fn build_picker_preview_lines(
+    preview: &crate::core::engine::PickerPreview,
+    theme: &Theme,
+) -> Vec<PickerPreviewLine> {
+    preview
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, (lineno, text, is_match))| PickerPreviewLine {
+            lineno: *lineno,
+            text: text.clone(),
+            is_match: *is_match,
+            spans: preview
+                .highlights
+                .get(i)
+                .map(|hl| {
+                    hl.iter()
+                        .map(|(start, end, scope)| (*start, *end, theme.scope_color(scope)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
 /// Build a complete `ScreenLayout` from current engine state.
 ///
 /// # Parameters
@@ -4444,6 +5075,8 @@ pub fn build_screen_layout(
     let windows = window_rects
         .iter()
         .map(|(window_id, rect)| {
+            let full_rect = *rect;
+            let rect = &winbar_content_rect(engine, full_rect, line_height);
             let mut visible_lines = (rect.height / line_height).floor() as usize;
             if per_window_status && !separate_status && visible_lines > 1 {
                 visible_lines -= 1; // reserve bottom row for per-window status bar
@@ -4465,6 +5098,12 @@ pub fn build_screen_layout(
                     engine, theme, *window_id, is_active,
                 ));
             }
+            if rect.y != full_rect.y {
+                rw.winbar = Some(engine.winbar_symbols(*window_id).join(" > "));
+            }
+            if let Some(strip) = minimap_rect(engine, *window_id, rect, line_height, char_width) {
+                rw.minimap = build_minimap(engine, theme, *window_id, strip, visible_lines);
+            }
             rw
         })
         .collect();
@@ -4506,19 +5145,71 @@ pub fn build_screen_layout(
         })
     };
 
-    let completion = engine.completion_idx.map(|idx| {
-        let max_width = engine
-            .completion_candidates
-            .iter()
-            .map(|s| s.len())
-            .max()
-            .unwrap_or(0);
-        CompletionMenu {
-            candidates: engine.completion_candidates.clone(),
+    let completion = if let Some(idx) = engine.code_action_menu_idx {
+        // The code-action popup reuses the completion popup renderer.
+        let candidates = engine.code_action_menu_items();
+        let max_width = candidates.iter().map(|s| s.len()).max().unwrap_or(0);
+        Some(CompletionMenu {
+            candidates,
             selected_idx: idx,
             max_width,
-        }
-    });
+            match_positions: Vec::new(),
+            documentation: Vec::new(),
+        })
+    } else {
+        engine.completion_idx.map(|idx| {
+            let max_width = engine
+                .completion_candidates
+                .iter()
+                .map(|s| s.len())
+                .max()
+                .unwrap_or(0);
+            let documentation = engine
+                .completion_documentation()
+                .map(|doc| {
+                    let mut lines = crate::core::markdown::render_markdown(&doc)
+                        .wrap(COMPLETION_DOC_WIDTH)
+                        .lines;
+                    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                        lines.pop();
+                    }
+                    lines.truncate(COMPLETION_DOC_MAX_LINES);
+                    lines
+                })
+                .unwrap_or_default();
+            // Text typed since the popup opened (Ctrl-N/P insert the candidate
+            // itself, so there is nothing to match then).
+            let query: String = if engine.completion_display_only {
+                let col = engine.view().cursor.col;
+                engine
+                    .buffer()
+                    .content
+                    .line(engine.view().cursor.line)
+                    .chars()
+                    .skip(engine.completion_start_col)
+                    .take(col.saturating_sub(engine.completion_start_col))
+                    .collect()
+            } else {
+                String::new()
+            };
+            let match_positions = engine
+                .completion_candidates
+                .iter()
+                .map(|c| {
+                    Engine::fuzzy_score_with_positions(c, &query)
+                        .map(|(_, positions)| positions)
+                        .unwrap_or_default()
+                })
+                .collect();
+            CompletionMenu {
+                candidates: engine.completion_candidates.clone(),
+                selected_idx: idx,
+                max_width,
+                match_positions,
+                documentation,
+            }
+        })
+    };
 
     let hover = engine.lsp_hover_text.as_ref().map(|text| HoverPopup {
         text: text.clone(),
@@ -4533,7 +5224,7 @@ pub fn build_screen_layout(
             .map(|m| {
                 let f = m.file.file_name().and_then(|n| n.to_str()).unwrap_or("?");
                 let snippet: String = m.line_text.trim().chars().take(80).collect();
-                format!("{}:{}: {}", f, m.line + 1, snippet)
+                format!("{}:{}:{}: {}", f, m.line + 1, m.col + 1, snippet)
             })
             .collect();
         QuickfixPanel {
@@ -4865,6 +5556,12 @@ pub fn build_screen_layout(
         selected_idx: engine.tab_switcher_selected,
     });
 
+    let which_key = engine
+        .which_key_visible
+        .then(|| engine.which_key_entries())
+        .flatten()
+        .map(|(prefix, entries)| WhichKeyPanel { prefix, entries });
+
     let n = engine.group_layout.leaf_count();
     let editor_group_split = if n >= 2 {
         // Build group rects using a dummy content_bounds — backends will compute
@@ -5068,7 +5765,10 @@ pub fn build_screen_layout(
                 PickerSource::Files | PickerSource::Grep
             );
             PickerPanel {
-                title: engine.picker_title.clone(),
+                title: match engine.picker_grep_status() {
+                    Some(status) => format!("{} ({})", engine.picker_title, status),
+                    None => engine.picker_title.clone(),
+                },
                 query: engine.picker_query.clone(),
                 items: engine
                     .picker_items
@@ -5085,23 +5785,29 @@ pub fn build_screen_layout(
                 selected_idx: engine.picker_selected,
                 scroll_top: engine.picker_scroll_top,
                 total_count: if engine.picker_source == PickerSource::Grep {
-                    engine.picker_items.len()
+                    engine
+                        .picker_grep_total
+                        .unwrap_or(engine.picker_items.len())
                 } else {
                     engine.picker_all_items.len()
                 },
-                preview: if has_preview {
+                preview_lines: has_preview.then(|| {
                     engine
                         .picker_preview
                         .as_ref()
-                        .map(|p| p.lines.clone())
-                        .or_else(|| Some(Vec::new()))
-                } else {
-                    None
-                },
+                        .map(|p| build_picker_preview_lines(p, theme))
+                        .unwrap_or_default()
+                }),
+                preview_note: engine
+                    .picker_preview
+                    .as_ref()
+                    .and_then(|p| p.note.clone())
+                    .filter(|_| has_preview),
                 preview_scroll: engine.picker_preview_scroll,
             }
         }),
         tab_switcher,
+        which_key,
         editor_group_split,
         ext_sidebar,
         ai_panel,
@@ -5147,7 +5853,7 @@ pub fn build_screen_layout(
                     format!("{}|", inp.value)
                 },
             }),
-            vertical_buttons: d.tag == "code_actions",
+            vertical_buttons: false,
         }),
         context_menu: engine.context_menu.as_ref().map(|cm| ContextMenuPanel {
             items: cm
@@ -5791,52 +6497,296 @@ fn build_terminal_panel(engine: &Engine) -> Option<TerminalPanel> {
     })
 }
 
-/// Build breadcrumb segments for the active editor group (public API for click handlers).
-pub fn build_breadcrumbs_for_active_group(engine: &Engine) -> Vec<BreadcrumbSegment> {
-    build_breadcrumbs_for_group(engine, engine.active_group)
+/// The part of a window rect left for text once the winbar (`:set winbar`)
+/// takes its top row.  Windows too short to spare a row are unchanged.
+/// Backends apply this wherever they map window rects to text rows.
+pub fn winbar_content_rect(engine: &Engine, rect: WindowRect, line_height: f64) -> WindowRect {
+    if !engine.settings.winbar || rect.height < 2.0 * line_height {
+        return rect;
+    }
+    WindowRect::new(
+        rect.x,
+        rect.y + line_height,
+        rect.width,
+        rect.height - line_height,
+    )
 }
 
-/// Build breadcrumb segments for a single editor group.
-fn build_breadcrumbs_for_group(engine: &Engine, group_id: GroupId) -> Vec<BreadcrumbSegment> {
-    let group = match engine.editor_groups.get(&group_id) {
-        Some(g) => g,
-        None => return vec![],
-    };
-    let window_id = group.tabs[group.active_tab].active_window;
-    let window = match engine.windows.get(&window_id) {
-        Some(w) => w,
-        None => return vec![],
+/// Pixels the minimap takes from the text width of `window_id` (text area
+/// `rect`): `MINIMAP_WIDTH_PX` when it is shown there, otherwise 0.  It is
+/// shown on GUI backends (`char_width > 1.0`) in the active window only, and
+/// only when that window is wide enough to spare it.
+pub fn minimap_reserve(
+    engine: &Engine,
+    window_id: WindowId,
+    rect: &WindowRect,
+    char_width: f64,
+) -> f64 {
+    let shown = engine.settings.minimap
+        && char_width > 1.0
+        && window_id == engine.active_window_id()
+        && rect.width >= 4.0 * MINIMAP_WIDTH_PX;
+    if shown {
+        MINIMAP_WIDTH_PX
+    } else {
+        0.0
+    }
+}
+
+/// The minimap strip of `window_id` (text area `rect`), or `None` where
+/// `minimap_reserve` shows none.  The strip sits just left of the vertical
+/// scrollbar and stops above the per-window status line.
+pub fn minimap_rect(
+    engine: &Engine,
+    window_id: WindowId,
+    rect: &WindowRect,
+    line_height: f64,
+    char_width: f64,
+) -> Option<WindowRect> {
+    let width = minimap_reserve(engine, window_id, rect, char_width);
+    if width <= 0.0 {
+        return None;
+    }
+    let separate_status = !engine.settings.status_line_above_terminal
+        && (engine.terminal_open || engine.bottom_panel_open);
+    let status_px = if engine.settings.window_status_line && !separate_status {
+        line_height
+    } else {
+        0.0
     };
-    let buf_state = match engine.buffer_manager.get(window.buffer_id) {
-        Some(s) => s,
-        None => return vec![],
+    Some(WindowRect::new(
+        rect.x + rect.width - V_SCROLLBAR_RESERVE_PX - width,
+        rect.y,
+        width,
+        (rect.height - status_px).max(0.0),
+    ))
+}
+
+/// The `scroll_top` for `window_id` that centres the line under pixel `y` of
+/// its minimap `strip`, for clicks and drags on the minimap.  Rows map back
+/// to buffer lines through the same fold-aware sampling the minimap is
+/// built with.
+pub fn minimap_scroll_target(
+    engine: &Engine,
+    window_id: WindowId,
+    strip: &WindowRect,
+    line_height: f64,
+    y: f64,
+) -> Option<usize> {
+    let window = engine.windows.get(&window_id)?;
+    let buffer_state = engine.buffer_manager.get(window.buffer_id)?;
+    let display = MinimapLines {
+        view: &window.view,
+        total_lines: buffer_state.buffer.len_lines(),
     };
+    let last = display.len().checked_sub(1)?;
+    let step = minimap_step(display.len(), strip.height);
+    let row = ((y - strip.y).max(0.0) / MINIMAP_ROW_PX).floor() as usize;
+    let visible = (strip.height / line_height).floor() as usize;
+    let top_idx = (row * step).min(last).saturating_sub(visible / 2);
+    Some(display.line(top_idx))
+}
 
-    let mut segments = Vec::new();
-    let mut idx = 0usize;
+/// The buffer lines the minimap displays: all lines except those hidden
+/// inside closed folds.  Indexes are mapped through the fold list, so
+/// sampling costs the number of rows drawn rather than the buffer length.
+struct MinimapLines<'a> {
+    view: &'a View,
+    total_lines: usize,
+}
 
-    // Path segments (relative to cwd)
-    if let Some(ref file_path) = buf_state.file_path {
-        let clean_path = crate::core::paths::strip_unc_prefix(file_path);
-        let clean_cwd = crate::core::paths::strip_unc_prefix(&engine.cwd);
-        let display = if let Ok(rel) = clean_path.strip_prefix(clean_cwd.as_ref()) {
-            rel.to_string_lossy().to_string()
-        } else {
-            clean_path.to_string_lossy().to_string()
-        };
-        let parts: Vec<&str> = display.split(std::path::MAIN_SEPARATOR).collect();
-        let mut accumulated = engine.cwd.clone();
-        for part in &parts {
-            accumulated = accumulated.join(part);
-            segments.push(BreadcrumbSegment {
-                label: part.to_string(),
-                is_last: false,
-                is_symbol: false,
-                index: idx,
-                path_prefix: Some(accumulated.clone()),
-                symbol_line: None,
-            });
-            idx += 1;
+impl MinimapLines<'_> {
+    /// Number of displayed lines.
+    fn len(&self) -> usize {
+        self.total_lines - self.hidden_before(self.total_lines)
+    }
+
+    /// Lines hidden in fold bodies above `line`.
+    fn hidden_before(&self, line: usize) -> usize {
+        self.view
+            .folds
+            .iter()
+            .take_while(|f| f.start < line)
+            .map(|f| f.end.min(line - 1) - f.start)
+            .sum()
+    }
+
+    /// The buffer line shown at display index `idx`.
+    fn line(&self, idx: usize) -> usize {
+        let mut line = idx;
+        for f in &self.view.folds {
+            if f.start >= line {
+                break;
+            }
+            line += f.end - f.start;
+        }
+        line.min(self.total_lines.saturating_sub(1))
+    }
+
+    /// Number of displayed lines above `line`.
+    fn index_of(&self, line: usize) -> usize {
+        let line = line.min(self.total_lines);
+        line - self.hidden_before(line)
+    }
+}
+
+/// Displayed lines per minimap row, so that `display_len` lines fit a strip
+/// `strip_height` pixels tall.
+fn minimap_step(display_len: usize, strip_height: f64) -> usize {
+    let capacity = ((strip_height / MINIMAP_ROW_PX).floor() as usize).max(1);
+    display_len.div_ceil(capacity).max(1)
+}
+
+/// Build the minimap of `window_id` for `strip`.  Only the sampled lines are
+/// highlighted, so the cost is bounded by the strip height, not the buffer.
+fn build_minimap(
+    engine: &Engine,
+    theme: &Theme,
+    window_id: WindowId,
+    strip: WindowRect,
+    visible_lines: usize,
+) -> Option<Minimap> {
+    let window = engine.windows.get(&window_id)?;
+    let buffer_state = engine.buffer_manager.get(window.buffer_id)?;
+    let buffer = &buffer_state.buffer;
+    let display = MinimapLines {
+        view: &window.view,
+        total_lines: buffer.len_lines(),
+    };
+    let step = minimap_step(display.len(), strip.height);
+    let max_cols = (MINIMAP_WIDTH_PX / MINIMAP_COL_PX) as usize;
+    let tabstop = buffer_state.tabstop(&engine.settings);
+    let hl = &buffer_state.highlights;
+    let rows = (0..display.len())
+        .step_by(step)
+        .map(|idx| {
+            let line_idx = display.line(idx);
+            let line = buffer.content.line(line_idx);
+            let text = line.to_string();
+            let start_byte = buffer.content.line_to_byte(line_idx);
+            let end_byte = start_byte + line.len_bytes();
+            let lo = hl.partition_point(|h| h.1 <= start_byte);
+            let hi = hl.partition_point(|h| h.0 < end_byte);
+            let spans = build_spans(
+                engine,
+                theme,
+                &hl[lo..hi],
+                &buffer_state.semantic_tokens,
+                buffer,
+                line_idx,
+                &text,
+                start_byte,
+                end_byte,
+                false,
+                &[],
+                false,
+                None,
+            );
+            MinimapRow {
+                line_idx,
+                blocks: minimap_blocks(&text, &spans, theme.foreground, tabstop, max_cols),
+            }
+        })
+        .collect();
+    let top_idx = display.index_of(window.view.scroll_top);
+    Some(Minimap {
+        rect: strip,
+        rows,
+        viewport_y: top_idx as f64 / step as f64 * MINIMAP_ROW_PX,
+        viewport_height: (visible_lines as f64 / step as f64 * MINIMAP_ROW_PX).max(MINIMAP_ROW_PX),
+    })
+}
+
+/// Split `text` into `(start_col, len, color)` runs of non-whitespace,
+/// clipped to `max_cols` display columns.
+fn minimap_blocks(
+    text: &str,
+    spans: &[StyledSpan],
+    default_fg: Color,
+    tabstop: usize,
+    max_cols: usize,
+) -> Vec<(usize, usize, Color)> {
+    let mut blocks = Vec::new();
+    let mut run: Option<(usize, Color)> = None;
+    let mut col = 0;
+    for (byte, ch) in text.char_indices() {
+        if col >= max_cols {
+            break;
+        }
+        if ch.is_whitespace() {
+            if let Some((start, color)) = run.take() {
+                blocks.push((start, col - start, color));
+            }
+            col += match ch {
+                '\t' => tabstop - col % tabstop,
+                '\n' | '\r' => 0,
+                _ => 1,
+            };
+            continue;
+        }
+        if run.is_none() {
+            let color = spans
+                .iter()
+                .rev()
+                .find(|s| s.start_byte <= byte && byte < s.end_byte)
+                .map_or(default_fg, |s| s.style.fg);
+            run = Some((col, color));
+        }
+        col += 1;
+    }
+    if let Some((start, color)) = run {
+        blocks.push((start, col - start, color));
+    }
+    blocks
+}
+
+/// Build breadcrumb segments for the active editor group (public API for click handlers).
+pub fn build_breadcrumbs_for_active_group(engine: &Engine) -> Vec<BreadcrumbSegment> {
+    build_breadcrumbs_for_group(engine, engine.active_group)
+}
+
+/// Build breadcrumb segments for a single editor group.
+fn build_breadcrumbs_for_group(engine: &Engine, group_id: GroupId) -> Vec<BreadcrumbSegment> {
+    let group = match engine.editor_groups.get(&group_id) {
+        Some(g) => g,
+        None => return vec![],
+    };
+    let window_id = group.tabs[group.active_tab].active_window;
+    let window = match engine.windows.get(&window_id) {
+        Some(w) => w,
+        None => return vec![],
+    };
+    let buf_state = match engine.buffer_manager.get(window.buffer_id) {
+        Some(s) => s,
+        None => return vec![],
+    };
+
+    let mut segments = Vec::new();
+    let mut idx = 0usize;
+
+    // Path segments (relative to the window's cwd)
+    if let Some(ref file_path) = buf_state.file_path {
+        let cwd = window.local_cwd.as_ref().unwrap_or(&engine.cwd);
+        let clean_path = crate::core::paths::strip_unc_prefix(file_path);
+        let clean_cwd = crate::core::paths::strip_unc_prefix(cwd);
+        let display = if let Ok(rel) = clean_path.strip_prefix(clean_cwd.as_ref()) {
+            rel.to_string_lossy().to_string()
+        } else {
+            clean_path.to_string_lossy().to_string()
+        };
+        let parts: Vec<&str> = display.split(std::path::MAIN_SEPARATOR).collect();
+        let mut accumulated = cwd.clone();
+        for part in &parts {
+            accumulated = accumulated.join(part);
+            segments.push(BreadcrumbSegment {
+                label: part.to_string(),
+                is_last: false,
+                is_symbol: false,
+                index: idx,
+                path_prefix: Some(accumulated.clone()),
+                symbol_line: None,
+            });
+            idx += 1;
         }
     }
 
@@ -5916,6 +6866,30 @@ fn build_tab_bar(engine: &Engine) -> Vec<TabInfo> {
     }
 }
 
+/// Place right-aligned virtual text (e.g. `:GitBlame`) on a row `width`
+/// columns wide whose text occupies `text_cols` columns.  Returns the start
+/// column and the (possibly `…`-truncated) text, or `None` when fewer than a
+/// few columns are free.
+pub fn right_aligned_annotation(
+    text: &str,
+    text_cols: usize,
+    width: usize,
+) -> Option<(usize, String)> {
+    const GAP: usize = 2;
+    const MIN_COLS: usize = 4;
+    let avail = width.saturating_sub(text_cols + GAP);
+    if avail < MIN_COLS {
+        return None;
+    }
+    let len = text.chars().count();
+    if len <= avail {
+        return Some((width - len, text.to_string()));
+    }
+    let mut clipped: String = text.chars().take(avail - 1).collect();
+    clipped.push('…');
+    Some((width - avail, clipped))
+}
+
 /// Return the number of visual rows a buffer line of `line_char_len` characters
 /// occupies when the viewport is `viewport_cols` columns wide.
 /// Always returns at least 1 (even for empty lines).
@@ -5930,44 +6904,7 @@ pub fn visual_rows_for_line(line_char_len: usize, viewport_cols: usize) -> usize
 /// Returns a list of `(start_char, end_char)` pairs. Breaks prefer word boundaries
 /// (spaces, hyphens, punctuation) so words are not split mid-way.
 pub fn compute_word_wrap_segments(line: &str, viewport_cols: usize) -> Vec<(usize, usize)> {
-    let chars: Vec<char> = line.chars().collect();
-    let total = chars.len();
-    if viewport_cols == 0 || total <= viewport_cols {
-        return vec![(0, total)];
-    }
-    let mut segments = Vec::new();
-    let mut pos = 0;
-    while pos < total {
-        let remaining = total - pos;
-        if remaining <= viewport_cols {
-            segments.push((pos, total));
-            break;
-        }
-        let end = pos + viewport_cols;
-        // Scan backwards from the break point to find a word boundary (space or after punctuation).
-        let mut break_at = end;
-        for i in (pos + 1..=end).rev() {
-            if chars[i - 1] == ' ' || chars[i - 1] == '-' || chars[i - 1] == '/' {
-                break_at = i;
-                break;
-            }
-        }
-        // If no boundary found within the segment, hard-break at viewport width.
-        if break_at == end && !chars[end - 1].is_whitespace() {
-            // Check if we found a boundary at all (break_at didn't change means
-            // the for loop completed without breaking).
-            let found = (pos + 1..=end)
-                .rev()
-                .any(|i| chars[i - 1] == ' ' || chars[i - 1] == '-' || chars[i - 1] == '/');
-            if !found {
-                break_at = end;
-            }
-        }
-        segments.push((pos, break_at));
-        // Safety: guarantee forward progress to prevent infinite loops.
-        pos = break_at.max(pos + 1);
-    }
-    segments
+    crate::core::engine::word_wrap_segments(line, viewport_cols)
 }
 
 /// Map a visible row index (0-based from scroll_top) to the corresponding
@@ -6093,6 +7030,7 @@ fn build_rendered_window(
         selection: None,
         extra_selections: vec![],
         yank_highlight: None,
+        snippet_placeholder: None,
         scroll_top: 0,
         scroll_left: 0,
         total_lines: 0,
@@ -6109,6 +7047,14 @@ fn build_rendered_window(
         tabstop: engine.settings.tabstop.max(1) as usize,
         cursorline: engine.settings.cursorline,
         status_line: None,
+        blame_annotations: false,
+        list_chars: None,
+        winbar: None,
+        sticky_lines: Vec::new(),
+        cursor_color: None,
+        cursor_blink: false,
+        scroll_frac: 0.0,
+        minimap: None,
     };
 
     let window = match engine.windows.get(&window_id) {
@@ -6126,8 +7072,14 @@ fn build_rendered_window(
     // Clamp scroll_top so that line_to_byte never panics when the cursor was
     // set to a line beyond the buffer (e.g. DAP exception in a stdlib file
     // that failed to open, leaving a small buffer with a large scroll offset).
-    let scroll_top = view.scroll_top.min(total_lines);
+    let (scroll_top, scroll_frac) = view.drawn_scroll_top(total_lines.saturating_sub(1));
+    let scroll_top = scroll_top.min(total_lines);
+    // Mid smooth-scroll the rows are shifted up, uncovering part of one more.
+    let visible_lines = visible_lines + usize::from(scroll_frac > 0.0);
     let cursor_line = view.cursor.line;
+    // Blame annotations belong to the active buffer only; other splits stay clean.
+    let show_annotations =
+        !engine.blame_annotations_active || window.buffer_id == engine.active_buffer_id();
 
     // Whether this buffer has git diff data.
     let has_git = !buffer_state.git_diff.is_empty();
@@ -6197,9 +7149,13 @@ fn build_rendered_window(
     // For the TUI backend, rect.width is already in cell columns and char_width=1.0,
     // so the formula reduces to rect.width - gutter_char_width, which is exact.
     // In the GTK backend (char_width > 1.0) reserve pixels for the vertical
-    // scrollbar overlay so text never renders behind it.  CSS requests 4px
-    // but GTK may allocate slightly more; 8px is a safe reserve.
-    let scrollbar_px: f64 = if char_width > 1.0 { 8.0 } else { 0.0 };
+    // scrollbar overlay (and the minimap, when shown) so text never renders
+    // behind them.
+    let scrollbar_px: f64 = if char_width > 1.0 {
+        V_SCROLLBAR_RESERVE_PX + minimap_reserve(engine, window_id, rect, char_width)
+    } else {
+        0.0
+    };
     let render_viewport_cols = if char_width > 0.0 {
         let total_chars = ((rect.width - scrollbar_px) / char_width).floor() as usize;
         total_chars.saturating_sub(gutter_char_width).max(1)
@@ -6232,14 +7188,29 @@ fn build_rendered_window(
         .windows
         .get(&engine.active_window_id())
         .map(|w| w.buffer_id);
+    // With `nohlsearch`, matches still show while the search is being typed.
+    let show_search_hl = (engine.settings.hlsearch || engine.mode == Mode::Search)
+        && !engine.search_highlight_cleared;
     let buf_search_matches: Vec<(usize, usize)> =
-        if !engine.settings.hlsearch || engine.search_query.is_empty() {
+        if !show_search_hl || engine.search_query.is_empty() {
             Vec::new()
         } else if Some(window.buffer_id) == active_buf_id {
             engine.search_matches.clone()
         } else {
             compute_search_matches_for_buffer(buffer, &engine.search_query, &engine.settings)
         };
+    // Occurrences of the identifier under the cursor (not while selecting).
+    let word_hl = engine
+        .word_highlight
+        .as_ref()
+        .filter(|(id, _)| {
+            *id == window.buffer_id
+                && !matches!(
+                    engine.mode,
+                    Mode::Visual | Mode::VisualLine | Mode::VisualBlock
+                )
+        })
+        .map(|(_, word)| word.as_str());
 
     // Ghost text (AI inline completion): only in the active window, Insert mode.
     // Multi-line completions are stored in full (Tab-accept inserts everything).
@@ -6317,25 +7288,41 @@ fn build_rendered_window(
                 // When unchanged lines are hidden (fold-filtered diff view),
                 // suppress padding lines — alignment is meaningless when
                 // the unchanged context between hunks is collapsed.
-                if engine.diff_unchanged_hidden {
+                if engine.diff_unchanged_hidden && entry.removed_line.is_none() {
                     aligned_idx += 1;
                     continue;
                 }
+                // Unified diff: a deleted base-file line shown as a virtual row.
+                let removed_text = entry.removed_line.and_then(|n| {
+                    engine
+                        .diff_unified
+                        .as_ref()
+                        .filter(|u| u.window == window_id)
+                        .and_then(|u| u.base_lines.get(n))
+                });
                 // Padding entry — emit an empty rendered line.
-                let padding_gutter = format!(
-                    "{:>width$} ",
-                    "",
-                    width = gutter_char_width.saturating_sub(1)
-                );
+                let padding_gutter = if removed_text.is_some() && gutter_char_width > 0 {
+                    format!("{:<width$}", "-", width = gutter_char_width)
+                } else {
+                    format!(
+                        "{:>width$} ",
+                        "",
+                        width = gutter_char_width.saturating_sub(1)
+                    )
+                };
                 lines.push(RenderedLine {
                     gutter_text: padding_gutter,
-                    raw_text: String::new(),
+                    raw_text: removed_text.cloned().unwrap_or_default(),
                     spans: vec![],
                     line_idx,
                     git_diff: None,
                     diagnostics: vec![],
                     spell_errors: vec![],
-                    diff_status: Some(DiffLine::Padding),
+                    diff_status: Some(if removed_text.is_some() {
+                        DiffLine::Removed
+                    } else {
+                        DiffLine::Padding
+                    }),
                     is_breakpoint: false,
                     is_conditional_bp: false,
                     is_dap_current: false,
@@ -6347,8 +7334,11 @@ fn build_rendered_window(
                     is_fold_header: false,
                     folded_line_count: 0,
                     is_ghost_continuation: false,
+                    is_virtual: removed_text.is_some(),
                     indent_guides: vec![],
                     colorcolumns: vec![],
+                    inlay_hints: Vec::new(),
+                    diagnostic_virtual_text: None,
                 });
                 aligned_idx += 1;
             }
@@ -6373,7 +7363,7 @@ fn build_rendered_window(
         let line_start_byte = buffer.content.line_to_byte(line_idx);
         let line_end_byte = line_start_byte + line.len_bytes();
 
-        let spans = if let Some(ref md) = buffer_state.md_rendered {
+        let mut spans = if let Some(ref md) = buffer_state.md_rendered {
             if line_idx < md.spans.len() {
                 let code_hl = md.code_highlights.get(line_idx);
                 md_spans_to_styled(&md.spans[line_idx], code_hl, theme, color_headings)
@@ -6400,8 +7390,20 @@ fn build_rendered_window(
                 is_markdown,
                 &buf_search_matches,
                 Some(window.buffer_id) == active_buf_id,
+                word_hl.map(|w| {
+                    (
+                        w,
+                        (is_active && line_idx == cursor_line).then_some(view.cursor.col),
+                    )
+                }),
             )
         };
+        // Trailing whitespace, except on the line being typed in Insert mode.
+        if engine.settings.highlight_trailing_whitespace
+            && !(is_active && engine.mode == crate::core::Mode::Insert && line_idx == cursor_line)
+        {
+            spans.extend(trailing_whitespace_span(&line_str, theme));
+        }
 
         // Git diff status for this line.
         let git_status = if has_git {
@@ -6429,7 +7431,21 @@ fn build_rendered_window(
             })
             .unwrap_or(false);
 
-        let fold_char = fold_indicator_char(buffer, view, line_idx);
+        // Unified diff marks added lines with `+` in the fold column.
+        let unified_added = engine
+            .diff_unified
+            .as_ref()
+            .is_some_and(|u| u.window == window_id)
+            && engine
+                .diff_results
+                .get(&window_id)
+                .and_then(|v| v.get(line_idx))
+                == Some(&DiffLine::Added);
+        let fold_char = if unified_added {
+            '+'
+        } else {
+            fold_indicator_char(buffer, view, line_idx)
+        };
         // Number of leading marker columns (bp + git) subtracted from the
         // numeric portion so line numbers fill their allotted width correctly.
         let marker_cols = if has_bp { 1 } else { 0 } + if has_git { 1 } else { 0 };
@@ -6495,6 +7511,25 @@ fn build_rendered_window(
             Vec::new()
         };
 
+        // End-of-line virtual text: the most severe diagnostic, then `(+N)`.
+        let line_virtual_text = if engine.settings.diagnostic_virtual_text
+            && !engine.line_annotations.contains_key(&line_idx)
+            && !(line_idx == cursor_line && ghost_for_cursor_line.is_some())
+        {
+            diag_by_line.get(&line_idx).and_then(|diags| {
+                let worst = diags
+                    .iter()
+                    .min_by_key(|d| (d.severity, d.range.start.character))?;
+                Some(DiagnosticVirtualText {
+                    message: worst.message.lines().next().unwrap_or("").to_string(),
+                    severity: worst.severity,
+                    more: diags.len() - 1,
+                })
+            })
+        } else {
+            None
+        };
+
         // Spell-check errors for this line — computed on visible lines only.
         let line_spell_errors: Vec<SpellMark> = if engine.settings.spell {
             if let Some(ref checker) = engine.spell_checker {
@@ -6524,6 +7559,13 @@ fn build_rendered_window(
             Vec::new()
         };
 
+        // LSP inlay hints for this line (positions are UTF-16 in the protocol).
+        let line_inlay_hints: Vec<InlayHintMark> = if engine.settings.inlay_hints {
+            inlay_hint_marks(&buffer_state.inlay_hints, line_idx, &line_str)
+        } else {
+            Vec::new()
+        };
+
         // Two-way diff status for this line.
         let diff_status = engine
             .diff_results
@@ -6535,12 +7577,20 @@ fn build_rendered_window(
         let wrap_on =
             (engine.settings.wrap || is_md_preview) && render_viewport_cols > 0 && !is_fold_header;
         let line_char_len = line_str.chars().count();
+        // Wrap on the line's content only (the engine's gj/gk/g0 do the same),
+        // so a trailing newline never spills onto its own row.
+        let content_str = line_str.trim_end_matches(['\n', '\r']);
+        let content_len = content_str.chars().count();
 
-        if wrap_on && line_char_len > render_viewport_cols {
+        if wrap_on && content_len > render_viewport_cols {
             // Split long line into viewport-width segments with word-boundary wrapping.
             let vp = render_viewport_cols;
-            // Build segment boundaries using word-aware splitting.
-            let segment_boundaries = compute_word_wrap_segments(&line_str, vp);
+            // Build segment boundaries using word-aware splitting; the last
+            // segment keeps the line ending.
+            let mut segment_boundaries = compute_word_wrap_segments(content_str, vp);
+            if let Some(last) = segment_boundaries.last_mut() {
+                last.1 = line_char_len;
+            }
             let num_segments = segment_boundaries.len();
             let cursor_seg = if line_idx == cursor_line {
                 // Find which segment contains the cursor column.
@@ -6592,6 +7642,7 @@ fn build_rendered_window(
                     is_wrap_continuation: is_cont,
                     segment_col_offset: seg_start_char,
                     annotation: if is_cont
+                        || !show_annotations
                         || (engine.mode == crate::core::Mode::Insert && !engine.is_vscode_mode())
                     {
                         None
@@ -6604,8 +7655,25 @@ fn build_rendered_window(
                         None
                     },
                     is_ghost_continuation: false,
+                    is_virtual: false,
                     indent_guides: Vec::new(), // filled below
                     colorcolumns: Vec::new(),  // filled below
+                    inlay_hints: line_inlay_hints
+                        .iter()
+                        .filter(|h| {
+                            h.col >= seg_start_char
+                                && (h.col < seg_end_char || seg + 1 == num_segments)
+                        })
+                        .map(|h| InlayHintMark {
+                            col: h.col - seg_start_char,
+                            label: h.label.clone(),
+                        })
+                        .collect(),
+                    diagnostic_virtual_text: if seg + 1 == num_segments {
+                        line_virtual_text.clone()
+                    } else {
+                        None
+                    },
                 });
 
                 // After the cursor segment, insert ghost continuation rows.
@@ -6634,8 +7702,11 @@ fn build_rendered_window(
                             annotation: None,
                             ghost_suffix: Some(cont.clone()),
                             is_ghost_continuation: true,
+                            is_virtual: false,
                             indent_guides: Vec::new(),
                             colorcolumns: Vec::new(),
+                            inlay_hints: Vec::new(),
+                            diagnostic_virtual_text: None,
                         });
                     }
                 }
@@ -6658,7 +7729,8 @@ fn build_rendered_window(
                 is_dap_current,
                 is_wrap_continuation: false,
                 segment_col_offset: 0,
-                annotation: if engine.mode == crate::core::Mode::Insert && !engine.is_vscode_mode()
+                annotation: if !show_annotations
+                    || (engine.mode == crate::core::Mode::Insert && !engine.is_vscode_mode())
                 {
                     None
                 } else {
@@ -6670,8 +7742,19 @@ fn build_rendered_window(
                     None
                 },
                 is_ghost_continuation: false,
+                is_virtual: false,
                 indent_guides: Vec::new(), // filled below
                 colorcolumns: Vec::new(),  // filled below
+                inlay_hints: if is_fold_header {
+                    Vec::new()
+                } else {
+                    line_inlay_hints
+                },
+                diagnostic_virtual_text: if is_fold_header {
+                    None
+                } else {
+                    line_virtual_text
+                },
             });
 
             // After the cursor line, insert ghost continuation rows.
@@ -6701,8 +7784,11 @@ fn build_rendered_window(
                         annotation: None,
                         ghost_suffix: Some(cont.clone()),
                         is_ghost_continuation: true,
+                        is_virtual: false,
                         indent_guides: Vec::new(),
                         colorcolumns: Vec::new(),
+                        inlay_hints: Vec::new(),
+                        diagnostic_virtual_text: None,
                     });
                 }
             }
@@ -6717,21 +7803,19 @@ fn build_rendered_window(
     }
 
     // Cursor (only if visible) — find its index in the rendered lines array.
+    let cursor_style = active_cursor_style(engine);
+    let cursor_color = cursor_style.color.as_deref().and_then(Color::try_from_hex);
+    let cursor_blink = is_active && cursor_style.blink;
     let cursor = if is_active {
         lines
             .iter()
             .enumerate()
             .find(|(_, l)| l.is_current_line)
             .map(|(view_line, l)| {
-                let shape = if engine.pending_key == Some('r') {
-                    CursorShape::Underline
-                } else if engine.is_vscode_mode() {
-                    CursorShape::Bar
-                } else {
-                    match engine.mode {
-                        Mode::Insert => CursorShape::Bar,
-                        _ => CursorShape::Block,
-                    }
+                let shape = match cursor_style.shape {
+                    CursorStyleShape::Block => CursorShape::Block,
+                    CursorStyleShape::Bar => CursorShape::Bar,
+                    CursorStyleShape::Underline => CursorShape::Underline,
                 };
                 // When wrapping, the cursor col is relative to the segment start.
                 let col = view.cursor.col.saturating_sub(l.segment_col_offset);
@@ -6749,7 +7833,7 @@ fn build_rendered_window(
             lines
                 .iter()
                 .enumerate()
-                .find(|(_, l)| l.line_idx == ec.line && !l.is_wrap_continuation)
+                .find(|(_, l)| l.line_idx == ec.line && !l.is_wrap_continuation && !l.is_virtual)
                 .map(|(view_line, l)| {
                     let col = ec.col.saturating_sub(l.segment_col_offset);
                     CursorPos { view_line, col }
@@ -6788,6 +7872,26 @@ fn build_rendered_window(
         None
     };
 
+    // Active snippet placeholder (only for active window)
+    let snippet_placeholder = engine
+        .snippet_session
+        .as_ref()
+        .filter(|session| is_active && session.buffer_id == window.buffer_id)
+        .and_then(|session| {
+            let (start, len) = session.stops[session.current];
+            (len > 0).then(|| {
+                let s = engine.char_idx_to_cursor(start);
+                let e = engine.char_idx_to_cursor(start + len - 1);
+                SelectionRange {
+                    kind: SelectionKind::Char,
+                    start_line: s.line,
+                    start_col: s.col,
+                    end_line: e.line,
+                    end_col: e.col,
+                }
+            })
+        });
+
     // Maximum line length across the whole buffer. When wrap is on, there is no
     // horizontal scrolling, so we report 0 to suppress the horizontal scrollbar.
     let is_md_preview = engine.md_preview_links.contains_key(&window.buffer_id);
@@ -6800,7 +7904,15 @@ fn build_rendered_window(
     // diagnostic_gutter is already built in the single-pass pre-indexing above.
 
     // ── Indent guides ──────────────────────────────────────────────────────
-    let tabstop = engine.settings.tabstop.max(1) as usize;
+    // Guides sit at each multiple of the buffer's shift width inside the
+    // leading whitespace, so they never overlap text.
+    let tabstop = buffer_state.tabstop(&engine.settings);
+    let shift_width = buffer_state.shift_width(&engine.settings);
+    let shift_width = if shift_width == 0 {
+        tabstop
+    } else {
+        shift_width
+    };
     let mut active_indent_col: Option<usize> = None;
     if engine.settings.indent_guides {
         // Compute the indent level for each visible line (in columns).
@@ -6830,40 +7942,38 @@ fn build_rendered_window(
             })
             .collect();
 
-        // Determine active guide column from cursor line indent
+        // Blank lines take the smaller indent of their surrounding lines.
+        let bridged = |i: usize| -> usize {
+            line_indents[i].unwrap_or_else(|| {
+                let above = line_indents[..i].iter().rev().find_map(|x| *x).unwrap_or(0);
+                let below = line_indents[i + 1..].iter().find_map(|x| *x).unwrap_or(0);
+                above.min(below)
+            })
+        };
+
+        // Active guide: the innermost level enclosing the cursor's block.
         if let Some(cursor_pos) = &cursor {
             let cursor_view_line = cursor_pos.0.view_line;
             if cursor_view_line < line_indents.len() {
-                if let Some(indent) = line_indents[cursor_view_line] {
-                    // Active guide is the highest tabstop ≤ cursor indent
-                    if indent >= tabstop {
-                        let guide_col = (indent / tabstop) * tabstop;
-                        // Use the guide one level below if cursor indent is exact multiple
-                        active_indent_col = Some(guide_col - tabstop);
-                    }
+                let indent = bridged(cursor_view_line);
+                if indent >= shift_width {
+                    let guide_col = (indent / shift_width) * shift_width;
+                    active_indent_col = Some(guide_col - shift_width);
                 }
             }
         }
 
         // Assign indent guides per line, bridging blank lines
         for (i, line) in lines.iter_mut().enumerate() {
-            if line.is_ghost_continuation {
+            if line.is_ghost_continuation || line.is_wrap_continuation {
                 continue;
             }
-            let indent = match line_indents[i] {
-                Some(ind) => ind,
-                None => {
-                    // Blank line: bridge using min indent of surrounding non-blank lines
-                    let above = line_indents[..i].iter().rev().find_map(|x| *x).unwrap_or(0);
-                    let below = line_indents[i + 1..].iter().find_map(|x| *x).unwrap_or(0);
-                    above.min(below)
-                }
-            };
+            let indent = bridged(i);
             let mut guides = Vec::new();
-            let mut col = tabstop;
+            let mut col = shift_width;
             while col <= indent {
-                guides.push(col - tabstop); // guide at the start of each tabstop level
-                col += tabstop;
+                guides.push(col - shift_width); // guide at the start of each level
+                col += shift_width;
             }
             line.indent_guides = guides;
         }
@@ -6885,6 +7995,9 @@ fn build_rendered_window(
             let cursor_line_idx = view.cursor.line;
             let cursor_col_idx = view.cursor.col;
             for (vi, l) in lines.iter().enumerate() {
+                if l.is_virtual {
+                    continue;
+                }
                 if l.line_idx == cursor_line_idx
                     && !l.is_ghost_continuation
                     && !l.is_wrap_continuation
@@ -6930,6 +8043,59 @@ fn build_rendered_window(
         vec![]
     };
 
+    // ── Sticky scroll ──────────────────────────────────────────────────────
+    let sticky_lines: Vec<StickyLine> = if buffer_state.md_rendered.is_some() {
+        Vec::new()
+    } else {
+        let marker_cols = usize::from(has_bp) + usize::from(has_git);
+        engine
+            .sticky_scroll_lines(window_id, visible_lines)
+            .into_iter()
+            .filter(|&line_idx| line_idx < total_lines)
+            .map(|line_idx| {
+                let line = buffer.content.line(line_idx);
+                let raw_text = line.to_string().replace('\0', "");
+                let start_byte = buffer.content.line_to_byte(line_idx);
+                let end_byte = start_byte + line.len_bytes();
+                let hl = &buffer_state.highlights;
+                let lo = hl.partition_point(|h| h.1 <= start_byte);
+                let hi = hl.partition_point(|h| h.0 < end_byte);
+                let spans = build_spans(
+                    engine,
+                    theme,
+                    &hl[lo..hi],
+                    &buffer_state.semantic_tokens,
+                    buffer,
+                    line_idx,
+                    &raw_text,
+                    start_byte,
+                    end_byte,
+                    false,
+                    &[],
+                    false,
+                    None,
+                );
+                let gutter_text = format!(
+                    "{}{}",
+                    " ".repeat(marker_cols),
+                    format_gutter_with_fold(
+                        line_number_mode,
+                        line_idx,
+                        cursor_line,
+                        gutter_char_width.saturating_sub(marker_cols),
+                        ' ',
+                    )
+                );
+                StickyLine {
+                    line_idx,
+                    gutter_text,
+                    raw_text,
+                    spans,
+                }
+            })
+            .collect()
+    };
+
     RenderedWindow {
         window_id,
         rect: *rect,
@@ -6939,6 +8105,7 @@ fn build_rendered_window(
         selection,
         extra_selections,
         yank_highlight,
+        snippet_placeholder,
         scroll_top,
         scroll_left: view.scroll_left,
         total_lines,
@@ -6951,7 +8118,7 @@ fn build_rendered_window(
         diagnostic_gutter,
         bracket_match_positions,
         active_indent_col,
-        tabstop: engine.settings.tabstop.max(1) as usize,
+        tabstop,
         code_action_lines: {
             // Only show lightbulb on the cursor line (like VSCode) — not on every
             // line that has cached actions, which would be noisy in Rust files where
@@ -6969,6 +8136,14 @@ fn build_rendered_window(
         },
         cursorline: engine.settings.cursorline,
         status_line: None,
+        blame_annotations: show_annotations && engine.blame_annotations_active,
+        list_chars: engine.settings.list.then(|| engine.settings.list_chars()),
+        winbar: None,
+        sticky_lines,
+        cursor_color,
+        cursor_blink,
+        scroll_frac,
+        minimap: None,
     }
 }
 
@@ -7324,6 +8499,7 @@ fn build_spans(
     is_markdown: bool,
     search_matches: &[(usize, usize)],
     is_active_buffer: bool,
+    word_highlight: Option<(&str, Option<usize>)>,
 ) -> Vec<StyledSpan> {
     let mut spans = Vec::new();
 
@@ -7396,8 +8572,38 @@ fn build_spans(
         }
     }
 
-    // Search match highlighting (skipped when hlsearch is disabled)
-    if engine.settings.hlsearch && !search_matches.is_empty() {
+    // Word-under-cursor occurrences: background only, keeping the colour of
+    // the span beneath.  Added before search matches so those take priority.
+    // `cursor_col` skips the occurrence the cursor itself is on.
+    if let Some((word, cursor_col)) = word_highlight {
+        for (start, end) in word_occurrences(line_str, word) {
+            if let Some(col) = cursor_col {
+                let start_col = line_str[..start].chars().count();
+                if (start_col..start_col + word.chars().count()).contains(&col) {
+                    continue;
+                }
+            }
+            let fg = spans
+                .iter()
+                .rev()
+                .find(|s| s.start_byte <= start && start < s.end_byte)
+                .map_or(theme.foreground, |s| s.style.fg);
+            spans.push(StyledSpan {
+                start_byte: start,
+                end_byte: end,
+                style: Style {
+                    fg,
+                    bg: Some(theme.word_highlight_bg),
+                    bold: false,
+                    italic: false,
+                    font_scale: 1.0,
+                },
+            });
+        }
+    }
+
+    // Search match highlighting (the caller passes no matches when hidden)
+    if !search_matches.is_empty() {
         let line_start_char = buffer.content.line_to_char(line_idx);
         let line_char_count = line_str.chars().count();
         let line_end_char = line_start_char + line_char_count;
@@ -7443,6 +8649,61 @@ fn build_spans(
     spans
 }
 
+/// Byte ranges of whole-word occurrences of `word` in `line`.
+fn word_occurrences(line: &str, word: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word)
+        .filter(|(start, _)| {
+            let end = start + word.len();
+            !line[..*start].chars().next_back().is_some_and(is_word)
+                && !line[end..].chars().next().is_some_and(is_word)
+        })
+        .map(|(start, _)| (start, start + word.len()))
+        .collect()
+}
+
+/// Span covering the trailing spaces/tabs of a line, painted with
+/// `theme.trailing_whitespace_bg`.
+fn trailing_whitespace_span(line_str: &str, theme: &Theme) -> Option<StyledSpan> {
+    let content = line_str.trim_end_matches(['\n', '\r']);
+    let trimmed = content.trim_end_matches([' ', '\t']);
+    if trimmed.len() == content.len() {
+        return None;
+    }
+    Some(StyledSpan {
+        start_byte: trimmed.len(),
+        end_byte: content.len(),
+        style: Style {
+            fg: theme.foreground,
+            bg: Some(theme.trailing_whitespace_bg),
+            bold: false,
+            italic: false,
+            font_scale: 1.0,
+        },
+    })
+}
+
+/// Glyphs for a whitespace character under `:set list`: the glyph for its
+/// first cell and, for tabs, the glyph filling the rest of the tab stop.
+/// `trailing` is true for whitespace after the last non-blank character.
+pub fn list_glyphs(lc: &ListChars, ch: char, trailing: bool) -> Option<(char, Option<char>)> {
+    match ch {
+        '\t' => lc.tab.map(|(first, fill)| (first, Some(fill))),
+        ' ' if trailing => lc.trail.or(lc.space).map(|g| (g, None)),
+        ' ' => lc.space.map(|g| (g, None)),
+        _ => None,
+    }
+}
+
+/// Char index where a line's trailing spaces/tabs begin (the char count when
+/// there are none).
+pub fn trailing_whitespace_col(line: &str) -> usize {
+    line.trim_end_matches(['\n', '\r'])
+        .trim_end_matches([' ', '\t'])
+        .chars()
+        .count()
+}
+
 /// Build a normalised [`SelectionRange`] from the engine's visual-mode state.
 fn build_selection(
     engine: &Engine,
@@ -7676,7 +8937,111 @@ pub fn calculate_gutter_cols(
     }
 }
 
+/// Expand a `statusline` format string for `window_id` into `(left, right)`.
+///
+/// Supported items: `%f` file name, `%F` full path, `%m` `[+]` when modified,
+/// `%y` `[filetype]`, `%l` line, `%c` column, `%L` line count, `%p` percent
+/// through the file, `%{lsp}` language-server status and `%%` a literal `%`.
+/// The first `%=` splits left from right.  Anything else is kept literally.
+pub fn expand_statusline(engine: &Engine, window_id: WindowId, fmt: &str) -> (String, String) {
+    use crate::core::lsp_manager::LspStatus;
+
+    let window = engine.windows.get(&window_id);
+    let buffer_state = window.and_then(|w| engine.buffer_manager.get(w.buffer_id));
+    let path = buffer_state.and_then(|s| s.file_path.as_ref());
+    let (line, col) = window.map_or((0, 0), |w| (w.view.cursor.line, w.view.cursor.col));
+    let total = buffer_state.map_or(1, |s| s.buffer.len_lines().max(1));
+
+    let mut halves = [String::new(), String::new()];
+    let mut side = 0;
+    let mut chars = fmt.chars();
+    while let Some(ch) = chars.next() {
+        let out = &mut halves[side];
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => out.push_str(
+                &path
+                    .and_then(|p| p.file_name())
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .or_else(|| buffer_state.and_then(|s| s.scratch_name.clone()))
+                    .unwrap_or_else(|| "[No Name]".to_string()),
+            ),
+            Some('F') => out.push_str(
+                &path.map_or_else(|| "[No Name]".to_string(), |p| p.display().to_string()),
+            ),
+            Some('m') => {
+                if buffer_state.is_some_and(|s| s.dirty) {
+                    out.push_str("[+]");
+                }
+            }
+            Some('y') => {
+                let ft = buffer_state
+                    .and_then(|s| s.lsp_language_id.clone())
+                    .or_else(|| path.and_then(|p| crate::core::lsp::language_id_from_path(p)));
+                if let Some(ft) = ft {
+                    out.push_str(&format!("[{ft}]"));
+                }
+            }
+            Some('l') => out.push_str(&(line + 1).to_string()),
+            Some('c') => out.push_str(&(col + 1).to_string()),
+            Some('L') => out.push_str(&total.to_string()),
+            Some('p') => out.push_str(&((line + 1) * 100 / total).to_string()),
+            Some('%') => out.push('%'),
+            Some('=') if side == 0 => side = 1,
+            Some('{') => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if closed && name == "lsp" {
+                    let status = window
+                        .map(|w| engine.lsp_status_for_buffer(w.buffer_id))
+                        .unwrap_or(LspStatus::None);
+                    match status {
+                        LspStatus::Running(name) => out.push_str(&name),
+                        LspStatus::Initializing(name) if name.is_empty() => out.push_str("LSP…"),
+                        LspStatus::Initializing(name) => out.push_str(&format!("{name}…")),
+                        LspStatus::Installing => out.push_str("LSP↓"),
+                        LspStatus::Crashed => out.push_str("LSP✗"),
+                        LspStatus::None => {}
+                    }
+                } else {
+                    out.push_str("%{");
+                    out.push_str(&name);
+                    if closed {
+                        out.push('}');
+                    }
+                }
+            }
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    let [left, right] = halves;
+    (left, right)
+}
+
 fn build_status_line(engine: &Engine) -> (String, String, Option<(usize, usize)>) {
+    if !engine.settings.statusline.is_empty() {
+        let (left, right) = expand_statusline(
+            engine,
+            engine.active_window_id(),
+            &engine.settings.statusline,
+        );
+        return (left, right, None);
+    }
+
     let mode_str = engine.mode_str();
 
     let filename = match engine.file_path() {
@@ -7687,7 +9052,19 @@ fn build_status_line(engine: &Engine) -> (String, String, Option<(usize, usize)>
         None => "[No Name]".to_string(),
     };
 
-    let dirty = if engine.dirty() { " [+]" } else { "" };
+    let flags = buffer_flags(
+        engine.active_buffer_state().read_only,
+        engine.active_buffer_state().modifiable,
+    );
+
+    // A just-auto-saved buffer briefly shows "[saved]" where "[+]" was.
+    let dirty = if engine.dirty() {
+        " [+]"
+    } else if engine.autosaved_at.is_some() {
+        " [saved]"
+    } else {
+        ""
+    };
 
     let recording = if let Some(reg) = engine.macro_recording {
         format!(" [recording @{}]", reg)
@@ -7713,7 +9090,10 @@ fn build_status_line(engine: &Engine) -> (String, String, Option<(usize, usize)>
         String::new()
     };
 
-    let prefix = format!(" -- {}{} -- {}{}", mode_str, recording, filename, dirty);
+    let prefix = format!(
+        " -- {}{} -- {}{}{}",
+        mode_str, recording, filename, dirty, flags
+    );
     let branch_range = if branch.is_empty() {
         None
     } else {
@@ -7742,6 +9122,18 @@ fn build_status_line(engine: &Engine) -> (String, String, Option<(usize, usize)>
     (left, right, branch_range)
 }
 
+/// Vim's status flags for a buffer that can't be edited: `[-]` when
+/// `nomodifiable`, otherwise `[RO]` when read-only.
+fn buffer_flags(read_only: bool, modifiable: bool) -> &'static str {
+    if !modifiable {
+        " [-]"
+    } else if read_only {
+        " [RO]"
+    } else {
+        ""
+    }
+}
+
 /// Build a per-window status line for a given window.
 /// Active windows get a rich, colorful bar; inactive windows get dimmed minimal info.
 pub fn build_window_status_line(
@@ -7763,11 +9155,19 @@ pub fn build_window_status_line(
         .unwrap_or_else(|| "[No Name]".to_string());
 
     let dirty = buffer_state.is_some_and(|s| s.dirty);
+    let large_file = buffer_state.is_some_and(|s| s.large_file);
+    let loading = buffer_state.is_some_and(|s| s.loading.is_some());
+    let flags = buffer_state.map_or("", |s| buffer_flags(s.read_only, s.modifiable));
     let cursor = view.map(|v| &v.cursor);
-    // Filetype from path
+    // Filetype: the buffer's language (path, shebang or modeline)
     let filetype = buffer_state
-        .and_then(|s| s.file_path.as_ref())
-        .and_then(|p| crate::core::lsp::language_id_from_path(p))
+        .and_then(|s| {
+            s.lsp_language_id.clone().or_else(|| {
+                s.file_path
+                    .as_ref()
+                    .and_then(|p| crate::core::lsp::language_id_from_path(p))
+            })
+        })
         .unwrap_or_default();
 
     // Derive per-window status bar colors from the editor background.
@@ -7792,14 +9192,42 @@ pub fn build_window_status_line(
     };
 
     // Indentation display text
-    let indent_text = if engine.settings.expand_tab {
-        format!("Spaces: {} ", engine.settings.tabstop)
+    let tabstop = buffer_state
+        .map(|s| s.tabstop(&engine.settings))
+        .unwrap_or(engine.settings.tabstop as usize);
+    let expand_tab = buffer_state
+        .map(|s| s.expand_tab(&engine.settings))
+        .unwrap_or(engine.settings.expand_tab);
+    let indent_text = if expand_tab {
+        format!("Spaces: {tabstop} ")
     } else {
-        format!("Tab Size: {} ", engine.settings.tabstop)
+        format!("Tab Size: {tabstop} ")
     };
 
     // Line ending display
     let line_ending_str = buffer_state.map(|s| s.line_ending.as_str()).unwrap_or("LF");
+    let encoding_str = buffer_state.map_or("utf-8", |s| s.encoding.name());
+
+    // A user `statusline` replaces the built-in segments in every window.
+    if !engine.settings.statusline.is_empty() {
+        let (left, right) = expand_statusline(engine, window_id, &engine.settings.statusline);
+        let (fg, bg) = if is_active {
+            (bar_fg, bar_bg)
+        } else {
+            (theme.status_inactive_fg, theme.status_inactive_bg)
+        };
+        let segment = |text: String| StatusSegment {
+            text,
+            fg,
+            bg,
+            bold: false,
+            action: None,
+        };
+        return WindowStatusLine {
+            left_segments: vec![segment(left)],
+            right_segments: vec![segment(right)],
+        };
+    }
 
     if is_active {
         // ── Active: MODE filename [+] branch | filetype indent encoding eol Ln:Col ──
@@ -7830,6 +9258,36 @@ pub fn build_window_status_line(
                 bold: false,
                 action: None,
             });
+        } else if engine.autosaved_at.is_some() {
+            left.push(StatusSegment {
+                text: " [saved]".to_string(),
+                fg: theme.line_number_fg,
+                bg: bar_bg,
+                bold: false,
+                action: None,
+            });
+        }
+        if !flags.is_empty() {
+            left.push(StatusSegment {
+                text: flags.to_string(),
+                fg: bar_fg,
+                bg: bar_bg,
+                bold: false,
+                action: None,
+            });
+        }
+        if large_file {
+            left.push(StatusSegment {
+                text: if loading {
+                    " [Large file, loading]".to_string()
+                } else {
+                    " [Large file]".to_string()
+                },
+                fg: bar_fg,
+                bg: bar_bg,
+                bold: false,
+                action: None,
+            });
         }
 
         // Recording indicator
@@ -7912,7 +9370,7 @@ pub fn build_window_status_line(
             action: Some(StatusAction::ChangeIndentation),
         });
         right.push(StatusSegment {
-            text: "utf-8 ".to_string(),
+            text: format!("{encoding_str} "),
             fg: bar_fg,
             bg: bar_bg,
             bold: false,
@@ -8051,6 +9509,24 @@ pub fn build_window_status_line(
                 action: None,
             });
         }
+        if !flags.is_empty() {
+            left.push(StatusSegment {
+                text: flags.to_string(),
+                fg: theme.status_inactive_fg,
+                bg: theme.status_inactive_bg,
+                bold: false,
+                action: None,
+            });
+        }
+        if large_file {
+            left.push(StatusSegment {
+                text: " [Large file]".to_string(),
+                fg: theme.status_inactive_fg,
+                bg: theme.status_inactive_bg,
+                bold: false,
+                action: None,
+            });
+        }
 
         let right = if let Some(c) = cursor {
             vec![StatusSegment {
@@ -8167,11 +9643,14 @@ fn build_command_line(engine: &Engine) -> CommandLineData {
         text
     };
 
+    let is_error = !show_cursor && !right_align && engine.message_is_error();
+
     CommandLineData {
         text,
         right_align,
         show_cursor,
         cursor_anchor_text,
+        is_error,
     }
 }
 
@@ -8190,6 +9669,8 @@ pub enum ClickTarget {
     Gutter,
     /// Click resolved to a buffer position in a specific window.
     BufferPos(WindowId, usize, usize),
+    /// Click was on a sticky scroll header: (window, header buffer line).
+    StickyLine(WindowId, usize),
     /// Click was on a tab-bar split button: (group_id, direction).
     SplitButton(GroupId, SplitDirection),
     /// Click was on a tab's close button: (group_id, tab_idx).
@@ -8447,6 +9928,54 @@ mod tests {
         assert_eq!(c.darken(1.0), Color::from_rgb(0, 0, 0));
     }
 
+    #[test]
+    fn test_high_contrast_theme() {
+        let hc = Theme::from_name("hc");
+        assert_eq!(hc.background, Color::from_hex("#000000"));
+        assert_eq!(hc.foreground, Color::from_hex("#ffffff"));
+        assert!(!hc.is_light());
+        assert!(Theme::available_names().contains(&"high-contrast".to_string()));
+    }
+
+    #[test]
+    fn test_from_color_map_json() {
+        let dir = std::env::temp_dir().join("vimcode_test_color_map");
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("mine.json");
+        std::fs::write(
+            &path,
+            r##"{
+            // Native theme: Theme field names → colours
+            "name": "Mine",
+            "background": "#101010",
+            "keyword": "#ff00ff",
+            "selection_alpha": 0.5
+        }"##,
+        )
+        .unwrap();
+        let theme = Theme::from_color_map_json(&path).unwrap();
+        assert_eq!(theme.background, Color::from_hex("#101010"));
+        assert_eq!(theme.keyword, Color::from_hex("#ff00ff"));
+        assert_eq!(theme.selection_alpha, 0.5);
+        // Unlisted fields keep OneDark's colours.
+        assert_eq!(theme.string_lit, Theme::onedark().string_lit);
+
+        // Every field round-trips, so a dumped theme covers the whole struct.
+        let dumped = serde_json::to_string(&Theme::gruvbox_dark()).unwrap();
+        std::fs::write(&path, dumped).unwrap();
+        let theme = Theme::from_color_map_json(&path).unwrap();
+        assert_eq!(theme.background, Theme::gruvbox_dark().background);
+        assert_eq!(theme.activity_bar_fg, Theme::gruvbox_dark().activity_bar_fg);
+
+        // Malformed values reject the file.
+        std::fs::write(&path, r#"{"background": "not-a-colour"}"#).unwrap();
+        assert!(Theme::from_color_map_json(&path).is_none());
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert!(Theme::from_color_map_json(&path).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_from_vscode_json() {
         let dir = std::env::temp_dir().join("vimcode_test_theme");
@@ -8585,6 +10114,518 @@ mod tests {
         assert_eq!(first_line.spell_errors[0].end_col, 8);
     }
 
+    #[test]
+    fn test_replace_mode_uses_underline_cursor() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "abc\n");
+        engine.feed_keys("R");
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 40.0, 10.0),
+        )];
+        let layout = build_screen_layout(&engine, &Theme::onedark(), &rects, 1.0, 1.0, false);
+        let (_, shape) = layout.windows[0].cursor.unwrap();
+        assert_eq!(shape, CursorShape::Underline);
+    }
+
+    #[test]
+    fn test_cursorline_marks_only_cursor_display_row() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine
+            .buffer_mut()
+            .insert(0, "one\ntwo two two two two\nthree\n");
+        engine.settings.wrap = true;
+        engine.settings.line_numbers = crate::core::settings::LineNumberMode::None;
+        engine.view_mut().cursor.line = 1;
+        engine.view_mut().cursor.col = 16;
+
+        // 10 text columns plus the fold column: line 1 wraps into three rows.
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 11.0, 10.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let window = &layout.windows[0];
+        assert!(window.cursorline);
+        let current: Vec<(usize, bool)> = window
+            .lines
+            .iter()
+            .filter(|l| l.is_current_line)
+            .map(|l| (l.line_idx, l.is_wrap_continuation))
+            .collect();
+        assert_eq!(current, vec![(1, true)]);
+
+        engine.settings.cursorline = false;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(!layout.windows[0].cursorline);
+    }
+
+    #[test]
+    fn test_diffunified_renders_deleted_lines_as_virtual_rows() {
+        use crate::core::Engine;
+
+        let dir = std::env::temp_dir().join("vimcode_render_diffunified");
+        let _ = std::fs::create_dir_all(&dir);
+        let base = dir.join("base.txt");
+        std::fs::write(&base, "keep\nold\ntail\n").unwrap();
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "keep\nnew\ntail\n");
+        engine.execute_command(&format!("diffunified {}", base.display()));
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 20.0, 10.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let rows: Vec<(&str, Option<DiffLine>, bool, usize)> = layout.windows[0]
+            .lines
+            .iter()
+            .take(4)
+            .map(|l| (l.raw_text.as_str(), l.diff_status, l.is_virtual, l.line_idx))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("keep\n", Some(DiffLine::Same), false, 0),
+                ("old\n", Some(DiffLine::Removed), true, 1),
+                ("new\n", Some(DiffLine::Added), false, 1),
+                ("tail\n", Some(DiffLine::Same), false, 2),
+            ]
+        );
+        assert!(layout.windows[0].lines[1].gutter_text.starts_with('-'));
+        assert!(layout.windows[0].lines[2].gutter_text.starts_with('+'));
+        // The cursor lands on the changed buffer line, not the virtual row.
+        assert_eq!(layout.windows[0].cursor.as_ref().unwrap().0.view_line, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_right_aligned_annotation_clips_to_width() {
+        // Fits: flush against the right edge.
+        assert_eq!(
+            right_aligned_annotation("me, 2d ago", 5, 40),
+            Some((30, "me, 2d ago".to_string()))
+        );
+        // Narrow window: truncated with an ellipsis, keeping a 2-column gap.
+        assert_eq!(
+            right_aligned_annotation("me, 2d ago", 10, 20),
+            Some((12, "me, 2d …".to_string()))
+        );
+        // No room at all.
+        assert_eq!(right_aligned_annotation("me, 2d ago", 16, 20), None);
+    }
+
+    #[test]
+    fn test_colorcolumns_on_every_text_line() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "a\nb\nc\n");
+        engine.execute_command("set cc=100,80");
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let rw = &layout.windows[0];
+        assert!(!rw.lines.is_empty());
+        // Buffer columns, sorted; backends subtract `scroll_left` themselves.
+        assert!(rw.lines.iter().all(|l| l.colorcolumns == vec![80, 100]));
+    }
+
+    #[test]
+    fn test_list_chars_glyphs() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "\tx  \n");
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.windows[0].list_chars.is_none());
+
+        engine.settings.list = true;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let rw = &layout.windows[0];
+        let lc = rw.list_chars.expect("list on");
+        // Buffer text is untouched; glyphs are substituted by the backends.
+        assert_eq!(rw.lines[0].raw_text, "\tx  \n");
+
+        let trail = trailing_whitespace_col(&rw.lines[0].raw_text);
+        assert_eq!(trail, 2);
+        assert_eq!(list_glyphs(&lc, '\t', false), Some(('→', Some(' '))));
+        assert_eq!(list_glyphs(&lc, ' ', false), None);
+        assert_eq!(list_glyphs(&lc, ' ', true), Some(('·', None)));
+        assert_eq!(list_glyphs(&lc, 'x', true), None);
+    }
+
+    #[test]
+    fn test_which_key_panel_layout() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.pending_key = Some('g');
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        // Hidden until the delay elapses, even with a prefix pending.
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.which_key.is_none());
+
+        engine.which_key_visible = true;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let wk = layout.which_key.expect("which-key panel");
+        assert_eq!(wk.prefix, "g");
+        assert!(wk
+            .entries
+            .iter()
+            .any(|(k, d)| k == "d" && d == "Go to definition"));
+
+        let (col_w, cols, rows) = which_key_grid(&wk, 80);
+        assert!(col_w * cols <= 80);
+        assert!(rows > 0 && rows <= WHICH_KEY_MAX_ROWS);
+
+        // Entries beyond the grid are counted in a last "+N more" cell.
+        let cells = which_key_cells(&wk, cols, rows);
+        if wk.entries.len() > cols * rows {
+            assert_eq!(cells.len(), cols * rows);
+            let hidden = wk.entries.len() - (cells.len() - 1);
+            assert_eq!(cells.last().unwrap().1, format!("+{hidden} more"));
+        } else {
+            assert_eq!(cells, wk.entries);
+        }
+        let narrow = which_key_cells(&wk, 1, 2);
+        assert_eq!(narrow.len(), 2);
+        assert_eq!(narrow[0], wk.entries[0]);
+        assert_eq!(narrow[1].1, format!("+{} more", wk.entries.len() - 1));
+    }
+
+    #[test]
+    fn test_statusline_format_expansion() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "a\nb\nc\nd\n");
+        engine.view_mut().cursor.line = 1;
+        let w = engine.active_window_id();
+        let (left, right) = expand_statusline(&engine, w, "%f%m %l:%c%=%p%% %q %{nope} %");
+        assert_eq!(left, "[No Name] 2:1");
+        // Percent of 4 lines; unknown items stay literal.
+        assert_eq!(right, "50% %q %{nope} %");
+
+        engine
+            .settings
+            .parse_set_option("stl=%f\\ %=%L\\ lines")
+            .unwrap();
+        assert_eq!(engine.settings.statusline, "%f %=%L lines");
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let status = layout.windows[0].status_line.as_ref().expect("status");
+        assert_eq!(status.left_segments[0].text, "[No Name] ");
+        assert_eq!(status.right_segments[0].text, "4 lines");
+    }
+
+    #[test]
+    fn test_winbar_takes_top_window_row() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "a\nb\nc\n");
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 1.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.windows[0].winbar.is_none());
+
+        // No LSP symbols: the bar is shown but empty.
+        engine.settings.winbar = true;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let rw = &layout.windows[0];
+        assert_eq!(rw.winbar.as_deref(), Some(""));
+        assert_eq!((rw.rect.y, rw.rect.height), (2.0, 9.0));
+
+        let bid = engine.active_buffer_id();
+        engine.buffer_manager.get_mut(bid).unwrap().document_symbols =
+            vec![crate::core::lsp::SymbolInfo {
+                name: "outer".to_string(),
+                kind: crate::core::lsp::SymbolKind::Module,
+                detail: None,
+                container: None,
+                path: None,
+                line: 0,
+                character: 0,
+                end_line: 2,
+                children: vec![crate::core::lsp::SymbolInfo {
+                    name: "inner".to_string(),
+                    kind: crate::core::lsp::SymbolKind::Function,
+                    detail: None,
+                    container: Some("outer".to_string()),
+                    path: None,
+                    line: 0,
+                    character: 0,
+                    end_line: 1,
+                    children: Vec::new(),
+                }],
+            }];
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert_eq!(layout.windows[0].winbar.as_deref(), Some("outer > inner"));
+
+        // Windows too short to spare a row keep their full height.
+        let short = vec![(w, WindowRect::new(0.0, 1.0, 40.0, 1.0))];
+        let layout = build_screen_layout(&engine, &theme, &short, 1.0, 1.0, false);
+        assert!(layout.windows[0].winbar.is_none());
+    }
+
+    #[test]
+    fn test_sticky_scroll_pins_enclosing_header() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        let text = format!("fn main() {{\n{}}}\n", "    x;\n".repeat(30));
+        engine.buffer_mut().insert(0, &text);
+        let bid = engine.active_buffer_id();
+        engine.buffer_manager.get_mut(bid).unwrap().document_symbols =
+            vec![crate::core::lsp::SymbolInfo {
+                name: "main".to_string(),
+                kind: crate::core::lsp::SymbolKind::Function,
+                detail: None,
+                container: None,
+                path: None,
+                line: 0,
+                character: 3,
+                end_line: 31,
+                children: Vec::new(),
+            }];
+        engine.view_mut().scroll_top = 5;
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.windows[0].sticky_lines.is_empty());
+
+        engine.settings.sticky_scroll = true;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let rw = &layout.windows[0];
+        assert_eq!(rw.sticky_lines.len(), 1);
+        let sticky = &rw.sticky_lines[0];
+        assert_eq!(sticky.line_idx, 0);
+        assert!(sticky.raw_text.starts_with("fn main()"));
+        assert_eq!(
+            sticky.gutter_text.chars().count(),
+            rw.lines[0].gutter_text.chars().count()
+        );
+        // The text underneath is unchanged; the header is an overlay.
+        assert_eq!(rw.lines[0].line_idx, 5);
+    }
+
+    #[test]
+    fn test_cursor_style_follows_mode() {
+        use crate::core::settings::CursorStyleShape;
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.settings.cursorstyle = "normal:underline-blink-#ff8800,visual:bar".to_string();
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let layout = build_screen_layout(&engine, &Theme::onedark(), &rects, 1.0, 1.0, false);
+        let rw = &layout.windows[0];
+        assert_eq!(
+            rw.cursor.map(|(_, shape)| shape),
+            Some(CursorShape::Underline)
+        );
+        assert_eq!(rw.cursor_color, Some(Color::from_hex("#ff8800")));
+        assert!(rw.cursor_blink);
+
+        engine.mode = Mode::Visual;
+        assert_eq!(active_cursor_style(&engine).shape, CursorStyleShape::Bar);
+        engine.mode = Mode::Insert;
+        let style = active_cursor_style(&engine);
+        assert_eq!(style.shape, CursorStyleShape::Bar);
+        assert!(style.blink && style.color.is_none());
+        engine.mode = Mode::Normal;
+        engine.pending_key = Some('r');
+        assert_eq!(
+            active_cursor_style(&engine).shape,
+            CursorStyleShape::Underline
+        );
+    }
+
+    #[test]
+    fn test_smooth_scroll_fraction_adds_partial_row() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, &"x\n".repeat(50));
+        engine.view_mut().scroll_top = 10;
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 400.0, 160.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        let rows = layout.windows[0].lines.len();
+        assert_eq!(layout.windows[0].scroll_frac, 0.0);
+
+        engine.view_mut().scroll_offset = 0.5;
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        let rw = &layout.windows[0];
+        assert_eq!(rw.scroll_frac, 0.5);
+        assert_eq!(rw.lines.len(), rows + 1);
+        assert_eq!(rw.lines[0].line_idx, 10);
+
+        // The offset counts display rows: a closed fold is a single row.
+        engine.view_mut().close_fold(7, 8);
+        engine.view_mut().scroll_offset = -2.5;
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        let rw = &layout.windows[0];
+        assert_eq!(rw.scroll_frac, 0.5);
+        let drawn: Vec<usize> = rw.lines.iter().take(3).map(|l| l.line_idx).collect();
+        assert_eq!(drawn, vec![6, 7, 9]);
+        assert_eq!(engine.view().scroll_top, 10);
+        assert_eq!(engine.view().visible_rows_between(10, 6), -3);
+        assert_eq!(engine.view().visible_rows_between(6, 10), 3);
+    }
+
+    #[test]
+    fn test_minimap_samples_lines_and_maps_clicks() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine
+            .buffer_mut()
+            .insert(0, &"    let x = 1;\n".repeat(400));
+        let w = engine.active_window_id();
+        let rects = vec![(w, WindowRect::new(0.0, 0.0, 800.0, 160.0))];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        assert!(layout.windows[0].minimap.is_none(), "off by default");
+
+        engine.settings.minimap = true;
+        let tui_rects = vec![(w, WindowRect::new(0.0, 0.0, 400.0, 40.0))];
+        let layout = build_screen_layout(&engine, &theme, &tui_rects, 1.0, 1.0, false);
+        assert!(
+            layout.windows[0].minimap.is_none(),
+            "cell backends have none"
+        );
+
+        engine.view_mut().scroll_top = 60;
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        let mm = layout.windows[0].minimap.clone().expect("minimap");
+        assert_eq!(mm.rect.x, 800.0 - 8.0 - MINIMAP_WIDTH_PX);
+        let step = minimap_step(401, mm.rect.height);
+        assert!(step > 1, "long buffers are sampled");
+        assert_eq!(mm.rows[0].line_idx, 0);
+        assert_eq!(mm.rows[1].line_idx, step);
+        let fg = theme.foreground;
+        assert_eq!(
+            mm.rows[0].blocks,
+            vec![(4, 3, fg), (8, 1, fg), (10, 1, fg), (12, 2, fg)]
+        );
+        assert_eq!(mm.viewport_y, 60.0 / step as f64 * MINIMAP_ROW_PX);
+
+        // A click on row 20 centres the line that row was sampled from.
+        let y = mm.rect.y + 20.0 * MINIMAP_ROW_PX + 1.0;
+        let visible = (mm.rect.height / 16.0).floor() as usize;
+        assert_eq!(
+            minimap_scroll_target(&engine, w, &mm.rect, 16.0, y),
+            Some(20 * step - visible / 2)
+        );
+
+        // Closed folds are skipped, both when sampling and when mapping back.
+        engine
+            .view_mut()
+            .folds
+            .push(crate::core::view::FoldRegion { start: 0, end: 99 });
+        let layout = build_screen_layout(&engine, &theme, &rects, 16.0, 8.0, false);
+        let mm = layout.windows[0].minimap.clone().expect("minimap");
+        let step = minimap_step(302, mm.rect.height);
+        assert_eq!(mm.rows[1].line_idx, 99 + step);
+        assert_eq!(
+            minimap_scroll_target(&engine, w, &mm.rect, 16.0, mm.rect.y),
+            Some(0)
+        );
+
+        let display = MinimapLines {
+            view: engine.view(),
+            total_lines: 401,
+        };
+        assert_eq!(display.len(), 302);
+        assert_eq!(display.line(0), 0);
+        assert_eq!(display.line(1), 100);
+        assert_eq!(display.index_of(100), 1);
+        assert_eq!(display.index_of(401), 302);
+    }
+
+    #[test]
+    fn test_blame_annotations_only_in_active_buffer_windows() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "one\ntwo\n");
+        engine
+            .line_annotations
+            .insert(0, "me, 2d ago — init".to_string());
+        engine.blame_annotations_active = true;
+        let w1 = engine.active_window_id();
+        engine.execute_command("vnew");
+        engine.buffer_mut().insert(0, "other\n");
+        engine
+            .line_annotations
+            .insert(0, "me, 1d ago — other".to_string());
+        let w2 = engine.active_window_id();
+
+        let rects = vec![
+            (w1, WindowRect::new(0.0, 0.0, 40.0, 10.0)),
+            (w2, WindowRect::new(40.0, 0.0, 40.0, 10.0)),
+        ];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let win = |id| layout.windows.iter().find(|w| w.window_id == id).unwrap();
+        assert!(win(w1).lines[0].annotation.is_none());
+        assert!(!win(w1).blame_annotations);
+        assert_eq!(
+            win(w2).lines[0].annotation.as_deref(),
+            Some("me, 1d ago — other")
+        );
+        assert!(win(w2).blame_annotations);
+    }
+
+    #[test]
+    fn test_trailing_whitespace_spans() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "one  \ntwo\t\nthree\n");
+        engine.settings.highlight_trailing_whitespace = true;
+        let wid = engine.active_window_id();
+        let rects = vec![(wid, WindowRect::new(0.0, 0.0, 40.0, 10.0))];
+        let theme = Theme::onedark();
+        let ws_spans = |engine: &Engine, line: usize| -> Vec<(usize, usize)> {
+            let layout = build_screen_layout(engine, &theme, &rects, 1.0, 1.0, false);
+            layout.windows[0].lines[line]
+                .spans
+                .iter()
+                .filter(|s| s.style.bg == Some(theme.trailing_whitespace_bg))
+                .map(|s| (s.start_byte, s.end_byte))
+                .collect()
+        };
+        assert_eq!(ws_spans(&engine, 0), vec![(3, 5)]);
+        assert_eq!(ws_spans(&engine, 1), vec![(3, 4)]);
+        assert!(ws_spans(&engine, 2).is_empty());
+
+        // The line being typed in Insert mode is left alone.
+        engine.mode = crate::core::Mode::Insert;
+        assert!(ws_spans(&engine, 0).is_empty());
+        assert_eq!(ws_spans(&engine, 1), vec![(3, 4)]);
+    }
+
     // ── Per-window status line tests ─────────────────────────────────────────
 
     #[test]
@@ -8816,6 +10857,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_status_large_file_segment() {
+        use crate::core::engine::Engine;
+        let mut engine = Engine::new();
+        engine.settings.window_status_line = true;
+        let theme = Theme::onedark();
+        let wid = engine.active_window_id();
+        let has_marker = |engine: &Engine| {
+            build_window_status_line(engine, &theme, wid, true)
+                .left_segments
+                .iter()
+                .any(|s| s.text.contains("[Large file]"))
+        };
+        assert!(!has_marker(&engine));
+        let id = engine.active_buffer_id();
+        engine.buffer_manager.get_mut(id).unwrap().large_file = true;
+        assert!(has_marker(&engine));
+    }
+
     #[test]
     fn test_status_indentation_segment() {
         use crate::core::engine::Engine;
@@ -8846,6 +10906,9 @@ mod tests {
         assert_eq!(LineEnding::detect("hello\r\nworld\r\n"), LineEnding::Crlf);
         assert_eq!(LineEnding::detect("no newline"), LineEnding::LF);
         assert_eq!(LineEnding::detect(""), LineEnding::LF);
+        // Mixed files take the dominant ending.
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\r\nb\nc\n"), LineEnding::LF);
     }
 
     #[test]
@@ -8946,6 +11009,39 @@ mod tests {
         assert_eq!(display_col_to_buffer_col("hello world", 0, 4, 6), 6);
     }
 
+    #[test]
+    fn test_display_col_to_buffer_col_tab_to_next_stop() {
+        // A mid-line tab only advances to the next multiple of tabstop.
+        assert_eq!(display_col_to_buffer_col("ab\tc", 4, 4, 0), 3);
+        assert_eq!(display_col_to_buffer_col("ab\tc", 8, 8, 0), 3);
+        // Two leading tabs with tabstop 2 put 'x' at display column 4.
+        assert_eq!(display_col_to_buffer_col("\t\tx", 4, 2, 0), 2);
+    }
+
+    #[test]
+    fn test_strip_inlay_hint_cols() {
+        // "let x: i32 = 1;" where ": i32" is a hint anchored before col 5.
+        let hints = vec![InlayHintMark {
+            col: 5,
+            label: ": i32".to_string(),
+        }];
+        assert_eq!(strip_inlay_hint_cols("let x = 1;", &hints, 3, 4), 3);
+        // Clicks inside the hint land on its anchor.
+        assert_eq!(strip_inlay_hint_cols("let x = 1;", &hints, 5, 4), 5);
+        assert_eq!(strip_inlay_hint_cols("let x = 1;", &hints, 9, 4), 5);
+        // Past the hint, its width is removed.
+        assert_eq!(strip_inlay_hint_cols("let x = 1;", &hints, 12, 4), 7);
+        assert_eq!(inlay_hint_width_before(&hints, 4), 0);
+        assert_eq!(inlay_hint_width_before(&hints, 5), 5);
+        // Anchors after a tab use the tab-expanded column.
+        let hints = vec![InlayHintMark {
+            col: 2,
+            label: "n: ".to_string(),
+        }];
+        assert_eq!(strip_inlay_hint_cols("\tab", &hints, 8, 4), 5);
+        assert_eq!(strip_inlay_hint_cols("\tab", &hints, 6, 4), 5);
+    }
+
     #[test]
     fn test_is_tab_close_click() {
         assert!(!is_tab_close_click(0, 10, 2));
@@ -10036,4 +12132,246 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&dir);
     }
+
+    #[test]
+    fn test_indent_guides_follow_shiftwidth() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine
+            .buffer_mut()
+            .insert(0, "fn f() {\n    if x {\n\n        y();\n    }\n}\n");
+        engine.settings.indent_guides = true;
+        engine.settings.shift_width = 4;
+        engine.view_mut().cursor.line = 3;
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let window = &layout.windows[0];
+        assert!(window.lines[0].indent_guides.is_empty());
+        assert_eq!(window.lines[1].indent_guides, vec![0]);
+        // Blank line bridges to the smaller surrounding indent.
+        assert_eq!(window.lines[2].indent_guides, vec![0]);
+        assert_eq!(window.lines[3].indent_guides, vec![0, 4]);
+        // Cursor on `y();` is inside the `if` block.
+        assert_eq!(window.active_indent_col, Some(4));
+
+        engine.settings.shift_width = 2;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert_eq!(layout.windows[0].lines[3].indent_guides, vec![0, 2, 4, 6]);
+    }
+
+    #[test]
+    fn test_word_highlight_spans_yield_to_search() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "foo food foo\n");
+        engine.word_highlight = Some((engine.active_buffer_id(), "foo".to_string()));
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let theme = Theme::onedark();
+        let word_spans = |engine: &Engine| {
+            let layout = build_screen_layout(engine, &theme, &rects, 1.0, 1.0, false);
+            layout.windows[0].lines[0]
+                .spans
+                .iter()
+                .filter(|s| s.style.bg == Some(theme.word_highlight_bg))
+                .map(|s| (s.start_byte, s.end_byte))
+                .collect::<Vec<_>>()
+        };
+        // Whole words only, and not the occurrence under the cursor.
+        assert_eq!(word_spans(&engine), vec![(9, 12)]);
+
+        // Search highlights are pushed later, so they win where both apply.
+        engine.search_query = "foo".to_string();
+        engine.search_matches = vec![(9, 12)];
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let spans = &layout.windows[0].lines[0].spans;
+        let last = spans
+            .iter()
+            .rev()
+            .find(|s| s.start_byte == 9)
+            .expect("span at second foo");
+        assert_eq!(last.style.bg, Some(theme.search_match_bg));
+    }
+
+    #[test]
+    fn test_diagnostic_virtual_text_fit() {
+        use crate::core::lsp::DiagnosticSeverity;
+        let vt = DiagnosticVirtualText {
+            message: "unused variable".to_string(),
+            severity: DiagnosticSeverity::Warning,
+            more: 0,
+        };
+        assert_eq!(vt.fit(10, 40), Some((12, "unused variable".to_string())));
+        assert_eq!(vt.fit(10, 20), Some((12, "unused …".to_string())));
+        // Too narrow: nothing drawn.
+        assert_eq!(vt.fit(10, 14), None);
+
+        let vt = DiagnosticVirtualText { more: 2, ..vt };
+        assert_eq!(vt.fit(0, 40), Some((2, "unused variable (+2)".to_string())));
+        // The `(+N)` suffix survives truncation.
+        assert_eq!(vt.fit(0, 14), Some((2, "unused… (+2)".to_string())));
+    }
+
+    #[test]
+    fn test_line_display_cols_expands_tabs() {
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "\tab\tc\nx\n");
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let layout = build_screen_layout(&engine, &Theme::onedark(), &rects, 1.0, 1.0, false);
+        let lines = &layout.windows[0].lines;
+        assert_eq!(line_display_cols(&lines[0], 4), 9);
+        assert_eq!(line_display_cols(&lines[0], 8), 17);
+        assert_eq!(line_display_cols(&lines[1], 4), 1);
+    }
+
+    #[test]
+    fn test_diagnostic_virtual_text_in_rendered_lines() {
+        use crate::core::engine::OpenMode;
+        use crate::core::lsp::{Diagnostic, DiagnosticSeverity, LspPosition, LspRange};
+        use crate::core::Engine;
+
+        let dir = std::env::temp_dir().join("vimcode_test_diag_virtual_text");
+        let _ = std::fs::create_dir_all(&dir);
+        let path = dir.join("main.rs");
+        std::fs::write(&path, "let a = 1;\nlet b = 2;\n").unwrap();
+
+        let diag = |character: u32, severity, message: &str| Diagnostic {
+            range: LspRange {
+                start: LspPosition { line: 0, character },
+                end: LspPosition {
+                    line: 0,
+                    character: character + 1,
+                },
+            },
+            severity,
+            message: message.to_string(),
+            source: None,
+            code: None,
+        };
+        let mut engine = Engine::new();
+        engine
+            .open_file_with_mode(&path, OpenMode::Permanent)
+            .unwrap();
+        engine.lsp_diagnostics.insert(
+            path.canonicalize().unwrap(),
+            vec![
+                diag(4, DiagnosticSeverity::Warning, "unused variable `a`"),
+                diag(
+                    8,
+                    DiagnosticSeverity::Error,
+                    "mismatched types\nexpected u8",
+                ),
+            ],
+        );
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let lines = &layout.windows[0].lines;
+        assert_eq!(
+            lines[0].diagnostic_virtual_text,
+            Some(DiagnosticVirtualText {
+                message: "mismatched types".to_string(),
+                severity: DiagnosticSeverity::Error,
+                more: 1,
+            })
+        );
+        assert!(lines[1].diagnostic_virtual_text.is_none());
+        // Virtual text never becomes part of the line's text.
+        assert_eq!(lines[0].raw_text, "let a = 1;\n");
+
+        engine.settings.diagnostic_virtual_text = false;
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.windows[0].lines[0].diagnostic_virtual_text.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_completion_documentation_panel() {
+        use crate::core::lsp::CompletionItem;
+        use crate::core::Engine;
+
+        let item = |label: &str, detail: Option<&str>, doc: Option<&str>| CompletionItem {
+            label: label.to_string(),
+            kind: None,
+            detail: detail.map(String::from),
+            insert_text: None,
+            is_snippet: false,
+            sort_text: None,
+            documentation: doc.map(String::from),
+            raw: serde_json::json!({ "label": label }),
+        };
+        let mut engine = Engine::new();
+        engine.completion_items = vec![
+            item(
+                "push",
+                Some("fn push(&mut self, value: T)"),
+                Some("Appends an element."),
+            ),
+            item("pop", None, None),
+        ];
+        engine.completion_candidates = vec!["push".to_string(), "pop".to_string()];
+        engine.completion_idx = Some(0);
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        let doc = &layout.completion.as_ref().unwrap().documentation;
+        assert!(doc
+            .iter()
+            .any(|l| l.contains("fn push(&mut self, value: T)")));
+        assert!(doc.iter().any(|l| l.contains("Appends an element.")));
+        assert!(doc
+            .iter()
+            .all(|l| l.chars().count() <= COMPLETION_DOC_WIDTH));
+
+        // Nothing to show for an item without detail or documentation.
+        engine.completion_idx = Some(1);
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert!(layout.completion.unwrap().documentation.is_empty());
+    }
+
+    #[test]
+    fn test_completion_menu_match_positions() {
+        use crate::core::Engine;
+
+        let mut engine = Engine::new();
+        engine.buffer_mut().insert(0, "tfm\n");
+        engine.view_mut().cursor.col = 3;
+        engine.completion_candidates = vec!["to_formatted_message".to_string()];
+        engine.completion_idx = Some(0);
+        engine.completion_start_col = 0;
+        engine.completion_display_only = true;
+
+        let rects = vec![(
+            engine.active_window_id(),
+            WindowRect::new(0.0, 0.0, 80.0, 24.0),
+        )];
+        let theme = Theme::onedark();
+        let layout = build_screen_layout(&engine, &theme, &rects, 1.0, 1.0, false);
+        assert_eq!(
+            layout.completion.unwrap().match_positions,
+            vec![vec![0, 3, 6]]
+        );
+    }
 }
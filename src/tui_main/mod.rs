@@ -13,10 +13,11 @@
     clippy::explicit_counter_loop
 )]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, Stdout, Write};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
@@ -69,10 +70,10 @@ pub(crate) use debug_log;
 use ratatui::backend::CrosstermBackend;
 use ratatui::crossterm::cursor::SetCursorStyle;
 use ratatui::crossterm::event::{
-    self as ct_event, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste,
-    EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
-    KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind, PopKeyboardEnhancementFlags,
-    PushKeyboardEnhancementFlags,
+    self as ct_event, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture,
+    EnableBracketedPaste, EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEvent,
+    KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
 };
 use ratatui::crossterm::execute;
 use ratatui::crossterm::terminal::{
@@ -84,8 +85,13 @@ use ratatui::style::{Color as RColor, Modifier};
 use ratatui::Terminal;
 
 use crate::core::engine::{DiffLine, EngineAction};
+use crate::core::explorer_filter::{
+    is_large_dir, read_dir_entries, ExplorerEntry, ExplorerFilter, ExplorerFilterConfig,
+};
+use crate::core::explorer_watch::ExplorerWatcher;
 use crate::core::lsp::DiagnosticSeverity;
-use crate::core::settings::ExplorerAction;
+use crate::core::paths::FileTarget;
+use crate::core::settings::{CursorStyleShape, ExplorerAction, ListChars};
 use crate::core::window::{GroupId, SplitDirection};
 use crate::core::{Engine, GitLineStatus, Mode, OpenMode, WindowRect};
 use crate::icons;
@@ -133,6 +139,50 @@ enum TuiPanel {
     Ai,
 }
 
+/// First row of the Search panel's results list, below the header, the four
+/// input boxes, the toggles and the status line.
+const SEARCH_RESULTS_ROW: u16 = 7;
+
+/// Which input box of the Search panel receives typed text.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum SearchField {
+    Query,
+    Replace,
+    Include,
+    Exclude,
+}
+
+impl SearchField {
+    /// Tab order: query → replace → include → exclude → query.
+    fn next(self) -> Self {
+        match self {
+            SearchField::Query => SearchField::Replace,
+            SearchField::Replace => SearchField::Include,
+            SearchField::Include => SearchField::Exclude,
+            SearchField::Exclude => SearchField::Query,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            SearchField::Query => SearchField::Exclude,
+            SearchField::Replace => SearchField::Query,
+            SearchField::Include => SearchField::Replace,
+            SearchField::Exclude => SearchField::Include,
+        }
+    }
+
+    /// The engine string this box edits.
+    fn text(self, engine: &mut Engine) -> &mut String {
+        match self {
+            SearchField::Query => &mut engine.project_search_query,
+            SearchField::Replace => &mut engine.project_replace_text,
+            SearchField::Include => &mut engine.project_search_options.include,
+            SearchField::Exclude => &mut engine.project_search_options.exclude,
+        }
+    }
+}
+
 // ─── Sidebar data structures ──────────────────────────────────────────────────
 
 struct ExplorerRow {
@@ -155,12 +205,12 @@ struct TuiSidebar {
     expanded: HashSet<PathBuf>,
     /// True while typing in the search input box (Search panel only).
     search_input_mode: bool,
-    /// When true and `search_input_mode` is true, the replace input is focused.
-    replace_input_focused: bool,
+    /// The input box typed text goes to while `search_input_mode` is true.
+    search_field: SearchField,
     /// Scroll offset for the search results area (written back by render_search_panel).
     search_scroll_top: usize,
-    /// Whether to show dotfiles in the explorer (mirrors Settings.show_hidden_files).
-    show_hidden_files: bool,
+    /// What the explorer hides (mirrors the dotfile / ignore settings).
+    filter: ExplorerFilterConfig,
     /// Sort explorer entries case-insensitively (mirrors Settings.explorer_sort_case_insensitive).
     sort_case_insensitive: bool,
     /// When true, the activity bar (toolbar) has keyboard focus.
@@ -171,6 +221,79 @@ struct TuiSidebar {
     pending_ctrl_w: bool,
     /// When set, sidebar renders an extension panel instead of the fixed panels.
     ext_panel_name: Option<String>,
+    /// Expanded folders too large to read while drawing.
+    large_dirs: LargeDirLoader,
+}
+
+/// Reads expanded explorer folders that pass [`is_large_dir`] on a
+/// background thread and keeps their entries until they are refreshed.
+#[derive(Default)]
+struct LargeDirLoader {
+    loaded: HashMap<PathBuf, Vec<ExplorerEntry>>,
+    pending: HashMap<PathBuf, Receiver<Vec<ExplorerEntry>>>,
+}
+
+impl LargeDirLoader {
+    /// The entries of `dir`: read in place for ordinary folders, otherwise
+    /// the last background read (`None` until the first one arrives).
+    fn entries(
+        &mut self,
+        dir: &Path,
+        config: &ExplorerFilterConfig,
+        filter: &mut ExplorerFilter,
+        case_insensitive: bool,
+    ) -> Option<Vec<ExplorerEntry>> {
+        if let Some(entries) = self.loaded.get(dir) {
+            return Some(entries.clone());
+        }
+        if self.pending.contains_key(dir) {
+            return None;
+        }
+        if !is_large_dir(dir) {
+            return Some(read_dir_entries(dir, filter, case_insensitive));
+        }
+        self.spawn(dir, config, case_insensitive);
+        None
+    }
+
+    fn spawn(&mut self, dir: &Path, config: &ExplorerFilterConfig, case_insensitive: bool) {
+        let (tx, rx) = mpsc::channel();
+        let (path, config) = (dir.to_path_buf(), config.clone());
+        std::thread::spawn(move || {
+            let mut filter = ExplorerFilter::new(&config);
+            let _ = tx.send(read_dir_entries(&path, &mut filter, case_insensitive));
+        });
+        self.pending.insert(dir.to_path_buf(), rx);
+    }
+
+    /// Re-read a loaded folder in the background; its old entries stay
+    /// listed until the new ones arrive.
+    fn refresh(&mut self, dir: &Path, config: &ExplorerFilterConfig, case_insensitive: bool) {
+        if self.loaded.contains_key(dir) && !self.pending.contains_key(dir) {
+            self.spawn(dir, config, case_insensitive);
+        }
+    }
+
+    fn forget(&mut self, dir: &Path) {
+        self.loaded.remove(dir);
+        self.pending.remove(dir);
+    }
+
+    /// Take in finished reads.  Returns true when any arrived.
+    fn poll(&mut self) -> bool {
+        let mut arrived = false;
+        let loaded = &mut self.loaded;
+        self.pending.retain(|dir, rx| match rx.try_recv() {
+            Ok(entries) => {
+                loaded.insert(dir.clone(), entries);
+                arrived = true;
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+            Err(TryRecvError::Disconnected) => false,
+        });
+        arrived
+    }
 }
 
 impl TuiSidebar {
@@ -188,20 +311,33 @@ impl TuiSidebar {
             root,
             expanded,
             search_input_mode: true,
-            replace_input_focused: false,
+            search_field: SearchField::Query,
             search_scroll_top: 0,
-            show_hidden_files: false,
+            filter: ExplorerFilterConfig::default(),
             sort_case_insensitive: true,
             toolbar_focused: false,
             toolbar_selected: 1, // Start on Explorer
             pending_ctrl_w: false,
             ext_panel_name: None,
+            large_dirs: LargeDirLoader::default(),
         };
         sb.build_rows();
         sb
     }
 
+    /// Re-root the tree at `root` (after `:cd`), keeping panel and focus state.
+    fn set_root(&mut self, root: PathBuf) {
+        self.expanded = HashSet::from([root.clone()]);
+        self.large_dirs = LargeDirLoader::default();
+        self.root = root;
+        self.selected = 0;
+        self.scroll_top = 0;
+        self.build_rows();
+    }
+
     fn build_rows(&mut self) {
+        // Keep the selection on the same path when rows come and go above it.
+        let selected_path = self.rows.get(self.selected).map(|r| r.path.clone());
         self.rows.clear();
         let root = self.root.clone();
         // Root folder entry at the top (like VSCode project name)
@@ -218,25 +354,55 @@ impl TuiSidebar {
             is_expanded: root_expanded,
         });
         if root_expanded {
-            collect_rows(
-                &root,
-                1,
-                &self.expanded,
-                self.show_hidden_files,
-                self.sort_case_insensitive,
-                &mut self.rows,
-            );
+            let mut lister = DirLister {
+                config: &self.filter,
+                filter: ExplorerFilter::new(&self.filter),
+                case_insensitive: self.sort_case_insensitive,
+                large_dirs: &mut self.large_dirs,
+            };
+            collect_rows(&root, 1, &self.expanded, &mut lister, &mut self.rows);
+        }
+        if let Some(idx) = selected_path.and_then(|p| self.rows.iter().position(|r| r.path == p)) {
+            self.selected = idx;
         }
         if !self.rows.is_empty() && self.selected >= self.rows.len() {
             self.selected = self.rows.len() - 1;
         }
     }
 
+    /// Pick up changed explorer filter settings (`:set showhiddenfiles`, the
+    /// `.` key, …) and rebuild the rows.  Returns true if they changed.
+    fn sync_filter(&mut self, settings: &crate::core::settings::Settings) -> bool {
+        let filter = ExplorerFilterConfig::from_settings(settings);
+        if filter == self.filter {
+            return false;
+        }
+        self.filter = filter;
+        self.large_dirs = LargeDirLoader::default();
+        self.build_rows();
+        true
+    }
+
+    /// Re-read large folders among `dirs` (or all of them when `None`) in
+    /// the background, then rebuild the rows from what is known now.
+    fn refresh_dirs(&mut self, dirs: Option<&[PathBuf]>) {
+        let loaded: Vec<PathBuf> = match dirs {
+            Some(dirs) => dirs.to_vec(),
+            None => self.large_dirs.loaded.keys().cloned().collect(),
+        };
+        for dir in loaded {
+            self.large_dirs
+                .refresh(&dir, &self.filter, self.sort_case_insensitive);
+        }
+        self.build_rows();
+    }
+
     fn toggle_dir(&mut self, idx: usize) {
         if idx < self.rows.len() && self.rows[idx].is_dir {
             let path = self.rows[idx].path.clone();
             if self.expanded.contains(&path) {
                 self.expanded.remove(&path);
+                self.large_dirs.forget(&path);
             } else {
                 self.expanded.insert(path);
             }
@@ -284,46 +450,40 @@ fn sync_sidebar_focus(sidebar: &TuiSidebar, engine: &mut Engine) {
     engine.search_has_focus = in_fixed_panel && sidebar.active_panel == TuiPanel::Search;
 }
 
+/// What [`collect_rows`] needs to list one directory level.
+struct DirLister<'a> {
+    config: &'a ExplorerFilterConfig,
+    filter: ExplorerFilter,
+    case_insensitive: bool,
+    large_dirs: &'a mut LargeDirLoader,
+}
+
 /// Recursively build the flat list of visible rows, respecting the `expanded` set.
+/// A large folder still being read shows "(loading…)" after its name.
 fn collect_rows(
     dir: &Path,
     depth: usize,
     expanded: &HashSet<PathBuf>,
-    show_hidden: bool,
-    case_insensitive: bool,
+    lister: &mut DirLister,
     out: &mut Vec<ExplorerRow>,
 ) {
-    let entries = match fs::read_dir(dir) {
-        Ok(e) => e,
-        Err(_) => return,
-    };
-    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
-    // Dirs first, then alphabetical (optionally case-insensitive)
-    entries.sort_by(|a, b| {
-        let ad = a.path().is_dir();
-        let bd = b.path().is_dir();
-        match (ad, bd) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => {
-                if case_insensitive {
-                    let an = a.file_name().to_string_lossy().to_lowercase();
-                    let bn = b.file_name().to_string_lossy().to_lowercase();
-                    an.cmp(&bn)
-                } else {
-                    a.file_name().cmp(&b.file_name())
-                }
-            }
+    // Dirs first, then alphabetical; hidden entries (and so hidden dirs'
+    // contents) are dropped before anything is read.
+    let entries = lister.large_dirs.entries(
+        dir,
+        lister.config,
+        &mut lister.filter,
+        lister.case_insensitive,
+    );
+    let Some(entries) = entries else {
+        // `dir`'s own row was pushed just before its children are collected.
+        if let Some(row) = out.last_mut().filter(|r| r.path == dir) {
+            row.name.push_str(" (loading…)");
         }
-    });
+        return;
+    };
     for entry in entries {
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
-        // Skip dotfiles unless show_hidden_files is enabled
-        if name.starts_with('.') && !show_hidden {
-            continue;
-        }
-        let is_dir = path.is_dir();
+        let ExplorerEntry { path, name, is_dir } = entry;
         let is_expanded = is_dir && expanded.contains(&path);
         out.push(ExplorerRow {
             depth,
@@ -333,14 +493,7 @@ fn collect_rows(
             is_expanded,
         });
         if is_expanded {
-            collect_rows(
-                &path,
-                depth + 1,
-                expanded,
-                show_hidden,
-                case_insensitive,
-                out,
-            );
+            collect_rows(&path, depth + 1, expanded, lister, out);
         }
     }
 }
@@ -836,7 +989,11 @@ fn intercept_paste_key(engine: &mut Engine, before: bool) -> bool {
 
 /// Initialise the engine, set up the terminal, run the event loop, and restore
 /// the terminal on exit.
-pub fn run(file_path: Option<PathBuf>, debug_log_path: Option<String>) {
+pub fn run(
+    file_target: Option<FileTarget>,
+    session: Option<String>,
+    debug_log_path: Option<String>,
+) {
     if let Some(ref path) = debug_log_path {
         init_debug_log(path);
         debug_log!("=== VimCode TUI debug log started ===");
@@ -854,16 +1011,14 @@ pub fn run(file_path: Option<PathBuf>, debug_log_path: Option<String>) {
     // Fetch fresh extension registry in background (updates ignore_error_sources, etc.)
     engine.ext_refresh();
     // Nerd font message is set right before event_loop to survive async overwrites.
-    if let Some(path) = file_path {
-        // CLI argument: open only the specified file/directory, skip session restore
-        if path.is_dir() {
-            debug_log!("Opening directory from CLI: {:?}", path);
-            engine.open_folder(&path);
-        } else {
-            // Load file into the initial window (reuses the scratch buffer's tab).
-            debug_log!("Opening file from CLI: {:?}", path);
-            let _ = engine.open_file_with_mode(&path, crate::core::engine::OpenMode::Permanent);
-        }
+    if let Some(ref name) = session {
+        // --session: restore a session saved with :mksession.
+        engine.source_session(name);
+    } else if let Some(target) = file_target {
+        // CLI argument: open only the specified file/directory, skip session restore.
+        // A file loads into the initial window (reusing the scratch buffer's tab).
+        debug_log!("Opening from CLI: {:?}", target);
+        engine.open_file_target(&target);
     } else {
         engine.restore_session_files();
     }
@@ -876,7 +1031,8 @@ pub fn run(file_path: Option<PathBuf>, debug_log_path: Option<String>) {
         stdout,
         EnterAlternateScreen,
         EnableMouseCapture,
-        EnableBracketedPaste
+        EnableBracketedPaste,
+        EnableFocusChange
     )
     .expect("enter alternate screen");
 
@@ -970,6 +1126,7 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>, keyboard_
         terminal.backend_mut(),
         DisableMouseCapture,
         DisableBracketedPaste,
+        DisableFocusChange,
         LeaveAlternateScreen
     );
     let _ = terminal.show_cursor();
@@ -995,10 +1152,16 @@ fn event_loop(
     } else {
         engine.session.explorer_visible || engine.settings.explorer_visible_on_startup
     };
-    let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-    let mut sidebar = TuiSidebar::new(root, initial_visible);
-    sidebar.show_hidden_files = engine.settings.show_hidden_files;
+    let mut sidebar = TuiSidebar::new(engine.cwd.clone(), initial_visible);
+    sidebar.sync_filter(&engine.settings);
     sidebar.sort_case_insensitive = engine.settings.explorer_sort_case_insensitive;
+    // Started on a directory: show the explorer focused, like netrw.
+    if std::mem::take(&mut engine.explorer_focus_on_start) {
+        sidebar.visible = true;
+        sidebar.has_focus = true;
+        sidebar.active_panel = TuiPanel::Explorer;
+        sync_sidebar_focus(&sidebar, engine);
+    }
 
     // Optional active prompt (for sidebar CRUD operations)
 
@@ -1088,6 +1251,7 @@ fn event_loop(
         .unwrap_or_else(Instant::now);
     // Auto-refresh sidebar to reflect external filesystem changes.
     let mut last_sidebar_refresh = Instant::now();
+    let mut explorer_watcher = ExplorerWatcher::new();
     // Auto-reload buffers whose files changed on disk.
     let mut last_file_check = Instant::now();
     // mtime of settings.json at last check — used to auto-reload when user edits the file.
@@ -1279,13 +1443,16 @@ fn event_loop(
             // that every group's active tab is still visible.
             engine.ensure_all_groups_tabs_visible();
 
-            // Set terminal cursor shape to match mode / pending key.
-            let cursor_style = if !sidebar.has_focus && engine.pending_key == Some('r') {
-                SetCursorStyle::SteadyUnderScore
-            } else if !sidebar.has_focus {
-                match engine.mode {
-                    Mode::Insert => SetCursorStyle::BlinkingBar,
-                    _ => SetCursorStyle::SteadyBlock,
+            // Set terminal cursor shape to the mode's `cursorstyle` look.
+            let cursor_style = if !sidebar.has_focus {
+                let style = render::active_cursor_style(engine);
+                match (style.shape, style.blink) {
+                    (CursorStyleShape::Block, false) => SetCursorStyle::SteadyBlock,
+                    (CursorStyleShape::Block, true) => SetCursorStyle::BlinkingBlock,
+                    (CursorStyleShape::Bar, false) => SetCursorStyle::SteadyBar,
+                    (CursorStyleShape::Bar, true) => SetCursorStyle::BlinkingBar,
+                    (CursorStyleShape::Underline, false) => SetCursorStyle::SteadyUnderScore,
+                    (CursorStyleShape::Underline, true) => SetCursorStyle::BlinkingUnderScore,
                 }
             } else {
                 SetCursorStyle::SteadyBlock
@@ -1322,6 +1489,9 @@ fn event_loop(
         let poll_timeout = if engine.tab_switcher_open {
             // Short poll when tab switcher is open so we can auto-confirm quickly
             Duration::from_millis(10)
+        } else if engine.picker_grep_active() || engine.picker_preview_loading() {
+            // Live grep / large-file preview: pick up background results promptly
+            Duration::from_millis(15)
         } else if engine.has_active_notifications() {
             // Animate spinner at ~10fps when background operations are running
             Duration::from_millis(100)
@@ -1383,14 +1553,46 @@ fn event_loop(
                 }
                 needs_redraw = true;
             }
+            if engine.tick_project_search_progress() {
+                needs_redraw = true;
+            }
             if engine.poll_project_replace() {
                 needs_redraw = true;
             }
-            // Auto-refresh explorer and SC panel to reflect external filesystem changes.
+            // Follow `:cd` with the explorer (and the project search root).
+            if std::mem::take(&mut engine.cwd_changed) {
+                sidebar.set_root(engine.cwd.clone());
+                needs_redraw = true;
+            }
+            // Rebuild the explorer as soon as its filter settings change.
+            if sidebar.sync_filter(&engine.settings) {
+                needs_redraw = true;
+            }
+            // Rebuild the explorer when a directory it shows changes on disk.
+            explorer_watcher.set_dirs(
+                sidebar
+                    .rows
+                    .iter()
+                    .filter(|r| r.is_expanded)
+                    .map(|r| r.path.as_path()),
+            );
+            let changed = explorer_watcher.poll();
+            if !changed.is_empty() {
+                sidebar.refresh_dirs(Some(&changed));
+                needs_redraw = true;
+            }
+            // Show large folders read in the background.
+            if sidebar.large_dirs.poll() {
+                sidebar.build_rows();
+                needs_redraw = true;
+            }
+            // Auto-refresh SC panel (and, without a watcher, the explorer) to
+            // reflect external filesystem changes.
             if sidebar.visible && last_sidebar_refresh.elapsed() >= Duration::from_secs(2) {
-                sidebar.show_hidden_files = engine.settings.show_hidden_files;
                 sidebar.sort_case_insensitive = engine.settings.explorer_sort_case_insensitive;
-                sidebar.build_rows();
+                if !explorer_watcher.is_active() {
+                    sidebar.refresh_dirs(None);
+                }
                 if sidebar.active_panel == TuiPanel::Git
                     || sidebar.active_panel == TuiPanel::Explorer
                 {
@@ -1405,6 +1607,10 @@ fn event_loop(
                 if engine.check_file_changes() {
                     needs_redraw = true;
                 }
+                // Reload the project .vimcode.json when it changes on disk.
+                if engine.check_project_settings_changed() {
+                    needs_redraw = true;
+                }
             }
             // Auto-reload settings.json when its mtime changes (e.g. after :w in the editor).
             {
@@ -1417,8 +1623,7 @@ fn event_loop(
                             if let Ok(new_settings) =
                                 crate::core::settings::Settings::load_with_validation()
                             {
-                                engine.settings = new_settings;
-                                engine.ensure_spell_checker();
+                                engine.set_global_settings(new_settings);
                                 engine.message = "Settings reloaded".to_string();
                                 needs_redraw = true;
                             }
@@ -1462,6 +1667,22 @@ fn event_loop(
             if engine.poll_async_shells() {
                 needs_redraw = true;
             }
+            // Apply output of finished :! / :r ! / filter commands.
+            if engine.poll_shell_commands() {
+                needs_redraw = true;
+            }
+            // Show large files read in the background.
+            if engine.poll_file_loads() {
+                needs_redraw = true;
+            }
+            // Start debounced live-grep searches and stream in their matches.
+            if engine.poll_picker_grep() {
+                needs_redraw = true;
+            }
+            // Show file picker previews loaded in the background.
+            if engine.poll_picker_preview() {
+                needs_redraw = true;
+            }
             // Show startup message after async init completes (overrides
             // "Extension registry updated" etc.)
             if let Some(msg) = pending_startup_msg.take() {
@@ -1497,8 +1718,36 @@ fn event_loop(
             if engine.tick_syntax_debounce() {
                 needs_redraw = true;
             }
+            // Resolve an ambiguous user-keymap prefix after `timeoutlen`.
+            if let Some(action) = engine.tick_keymap_timeout() {
+                needs_redraw = true;
+                if dispatch_key_action(
+                    engine,
+                    action,
+                    terminal,
+                    &mut sidebar,
+                    &mut folder_picker,
+                    &mut quit_confirm,
+                ) {
+                    break;
+                }
+            }
+            // Show or hide the which-key popup for a pending prefix.
+            if engine.tick_which_key() {
+                needs_redraw = true;
+            }
+            // Highlight the word under the cursor once it settles.
+            if engine.tick_word_highlight() {
+                needs_redraw = true;
+            }
+            // Fetch documentation for the selected completion item.
+            engine.tick_completion_resolve();
             // Tick swap file writes (only does work when updatetime elapsed).
             engine.tick_swap_files();
+            // Auto-save after a pause in typing (`autosave = "interval"`).
+            if engine.tick_autosave() {
+                needs_redraw = true;
+            }
             // Check for externally modified files.
             engine.tick_file_watcher();
             // Poll for external git branch changes (rate-limited to once per 2s).
@@ -1520,7 +1769,13 @@ fn event_loop(
                 if quit_confirm && key_event.kind != KeyEventKind::Release {
                     match key_event.code {
                         KeyCode::Char('s') | KeyCode::Char('S') => {
-                            engine.save_all_dirty();
+                            if let Err(e) = engine.save_all_dirty() {
+                                // Something could not be written: stay open.
+                                engine.message = e;
+                                quit_confirm = false;
+                                needs_redraw = true;
+                                continue;
+                            }
                             engine.cleanup_all_swaps();
                             engine.lsp_shutdown();
                             save_session(engine);
@@ -1740,7 +1995,7 @@ fn event_loop(
                                     folder_picker = None;
                                     engine.open_folder(&path);
                                     sidebar = TuiSidebar::new(engine.cwd.clone(), sidebar.visible);
-                                    sidebar.show_hidden_files = engine.settings.show_hidden_files;
+                                    sidebar.sync_filter(&engine.settings);
                                     sidebar.sort_case_insensitive =
                                         engine.settings.explorer_sort_case_insensitive;
                                     if let Some(fp) = engine.file_path().cloned() {
@@ -1769,7 +2024,7 @@ fn event_loop(
                                         FolderPickerMode::OpenRecent => {}
                                     }
                                     sidebar = TuiSidebar::new(engine.cwd.clone(), sidebar.visible);
-                                    sidebar.show_hidden_files = engine.settings.show_hidden_files;
+                                    sidebar.sync_filter(&engine.settings);
                                     sidebar.sort_case_insensitive =
                                         engine.settings.explorer_sort_case_insensitive;
                                     // Reveal the active file from the restored session
@@ -1914,7 +2169,7 @@ fn event_loop(
                             if panel == TuiPanel::Search {
                                 engine.search_has_focus = true;
                                 sidebar.search_input_mode = true;
-                                sidebar.replace_input_focused = false;
+                                sidebar.search_field = SearchField::Query;
                             }
                             if panel == TuiPanel::Git {
                                 engine.sc_has_focus = true;
@@ -1995,7 +2250,7 @@ fn event_loop(
                             } else {
                                 sidebar.active_panel = TuiPanel::Search;
                                 sidebar.search_input_mode = true;
-                                sidebar.replace_input_focused = false;
+                                sidebar.search_field = SearchField::Query;
                             }
                             needs_redraw = true;
                             continue;
@@ -2058,8 +2313,24 @@ fn event_loop(
                                     continue;
                                 }
                                 KeyCode::Char('h') => {
-                                    let root = sidebar.root.clone();
-                                    engine.start_project_replace(root);
+                                    if engine.project_replace_preview {
+                                        engine.replace_selected_matches();
+                                    } else {
+                                        let root = sidebar.root.clone();
+                                        engine.start_project_replace(root);
+                                    }
+                                    continue;
+                                }
+                                KeyCode::Char('p') => {
+                                    engine.toggle_project_replace_preview();
+                                    continue;
+                                }
+                                KeyCode::Char('q') => {
+                                    engine.send_project_search_to_quickfix();
+                                    if engine.quickfix_open {
+                                        sidebar.has_focus = false;
+                                    }
+                                    needs_redraw = true;
                                     continue;
                                 }
                                 _ => {}
@@ -2077,11 +2348,14 @@ fn event_loop(
                             }
                             // Input mode: typing into the search or replace box
                             _ if sidebar.search_input_mode => match key_event.code {
-                                KeyCode::Tab | KeyCode::BackTab => {
-                                    sidebar.replace_input_focused = !sidebar.replace_input_focused;
+                                KeyCode::Tab => {
+                                    sidebar.search_field = sidebar.search_field.next();
+                                }
+                                KeyCode::BackTab => {
+                                    sidebar.search_field = sidebar.search_field.prev();
                                 }
                                 KeyCode::Enter => {
-                                    if sidebar.replace_input_focused {
+                                    if sidebar.search_field == SearchField::Replace {
                                         let root = sidebar.root.clone();
                                         engine.start_project_replace(root);
                                     } else {
@@ -2091,11 +2365,7 @@ fn event_loop(
                                     }
                                 }
                                 KeyCode::Backspace => {
-                                    if sidebar.replace_input_focused {
-                                        engine.project_replace_text.pop();
-                                    } else {
-                                        engine.project_search_query.pop();
-                                    }
+                                    sidebar.search_field.text(engine).pop();
                                 }
                                 KeyCode::Char('v')
                                     if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
@@ -2104,11 +2374,7 @@ fn event_loop(
                                         let line = text.lines().next().unwrap_or("");
                                         for c in line.chars() {
                                             if !c.is_control() {
-                                                if sidebar.replace_input_focused {
-                                                    engine.project_replace_text.push(c);
-                                                } else {
-                                                    engine.project_search_query.push(c);
-                                                }
+                                                sidebar.search_field.text(engine).push(c);
                                             }
                                         }
                                     }
@@ -2116,11 +2382,7 @@ fn event_loop(
                                 KeyCode::Char(c)
                                     if !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
                                 {
-                                    if sidebar.replace_input_focused {
-                                        engine.project_replace_text.push(c);
-                                    } else {
-                                        engine.project_search_query.push(c);
-                                    }
+                                    sidebar.search_field.text(engine).push(c);
                                 }
                                 _ => {}
                             },
@@ -2130,7 +2392,9 @@ fn event_loop(
                                     KeyCode::Char('j') | KeyCode::Down => {
                                         engine.project_search_select_next();
                                         if let Ok(size) = terminal.size() {
-                                            let rh = size.height.saturating_sub(7) as usize;
+                                            let rh =
+                                                size.height.saturating_sub(2 + SEARCH_RESULTS_ROW)
+                                                    as usize;
                                             ensure_search_selection_visible(
                                                 &engine.project_search_results,
                                                 engine.project_search_selected,
@@ -2142,7 +2406,9 @@ fn event_loop(
                                     KeyCode::Char('k') | KeyCode::Up => {
                                         engine.project_search_select_prev();
                                         if let Ok(size) = terminal.size() {
-                                            let rh = size.height.saturating_sub(7) as usize;
+                                            let rh =
+                                                size.height.saturating_sub(2 + SEARCH_RESULTS_ROW)
+                                                    as usize;
                                             ensure_search_selection_visible(
                                                 &engine.project_search_results,
                                                 engine.project_search_selected,
@@ -2171,12 +2437,17 @@ fn event_loop(
                                         sidebar.toolbar_focused = true;
                                         sidebar.toolbar_selected = 2; // Search row
                                     }
+                                    // Space: check/uncheck the match for Replace Selected
+                                    KeyCode::Char(' ') if engine.project_replace_preview => {
+                                        let idx = engine.project_search_selected;
+                                        engine.toggle_project_match_included(idx);
+                                    }
                                     // Any printable char: switch back to input mode
                                     KeyCode::Char(c)
                                         if !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
                                     {
                                         sidebar.search_input_mode = true;
-                                        sidebar.replace_input_focused = false;
+                                        sidebar.search_field = SearchField::Query;
                                         engine.project_search_query.push(c);
                                     }
                                     _ => {}
@@ -2755,6 +3026,10 @@ fn event_loop(
                                             engine.start_move_file_dialog(&path, &root);
                                         }
                                     }
+                                    ExplorerAction::ToggleHidden => {
+                                        engine.toggle_show_hidden_files();
+                                        sidebar.sync_filter(&engine.settings);
+                                    }
                                 }
                             }
                         }
@@ -2952,7 +3227,7 @@ fn event_loop(
                                 sidebar.active_panel = TuiPanel::Search;
                                 sidebar.has_focus = true;
                                 sidebar.search_input_mode = true;
-                                sidebar.replace_input_focused = false;
+                                sidebar.search_field = SearchField::Query;
                             }
                             sync_sidebar_focus(&sidebar, engine);
                             needs_redraw = true;
@@ -3148,8 +3423,7 @@ fn event_loop(
                                                         engine.cwd.clone(),
                                                         sidebar.visible,
                                                     );
-                                                    sidebar.show_hidden_files =
-                                                        engine.settings.show_hidden_files;
+                                                    sidebar.sync_filter(&engine.settings);
                                                 } else if act == EngineAction::SaveWorkspaceAsDialog
                                                 {
                                                     let ws_path =
@@ -3470,46 +3744,14 @@ fn event_loop(
                         if let Some(perf) = engine.perf_log.take() {
                             debug_log!("  {}", perf);
                         }
-                        // Handle OpenTerminal specially (needs terminal size info)
-                        if action == EngineAction::OpenTerminal {
-                            let cols = terminal.size().ok().map(|s| s.width).unwrap_or(80);
-                            engine.terminal_new_tab(cols, engine.session.terminal_panel_rows);
-                            needs_redraw = true;
-                        } else if let EngineAction::RunInTerminal(cmd) = action {
-                            let cols = terminal.size().ok().map(|s| s.width).unwrap_or(80);
-                            engine.terminal_run_command(
-                                &cmd,
-                                cols,
-                                engine.session.terminal_panel_rows,
-                            );
-                            needs_redraw = true;
-                        } else if action == EngineAction::OpenFolderDialog {
-                            folder_picker = Some(FolderPickerState::new(
-                                &engine.cwd.clone(),
-                                FolderPickerMode::OpenFolder,
-                                engine.settings.show_hidden_files,
-                            ));
-                            needs_redraw = true;
-                        } else if action == EngineAction::OpenWorkspaceDialog {
-                            // open_workspace_from_file() already ran in the engine;
-                            // just refresh the sidebar to reflect the new cwd.
-                            sidebar = TuiSidebar::new(engine.cwd.clone(), sidebar.visible);
-                            sidebar.show_hidden_files = engine.settings.show_hidden_files;
-                            sidebar.sort_case_insensitive =
-                                engine.settings.explorer_sort_case_insensitive;
-                            needs_redraw = true;
-                        } else if action == EngineAction::SaveWorkspaceAsDialog {
-                            // For TUI, save workspace to current directory immediately
-                            let ws_path = engine.cwd.join(".vimcode-workspace");
-                            engine.save_workspace_as(&ws_path);
-                            needs_redraw = true;
-                        } else if action == EngineAction::QuitWithUnsaved {
-                            quit_confirm = true;
-                            needs_redraw = true;
-                        } else if action == EngineAction::ToggleSidebar {
-                            sidebar.visible = !sidebar.visible;
-                            needs_redraw = true;
-                        } else if handle_action(engine, action) {
+                        if dispatch_key_action(
+                            engine,
+                            action,
+                            terminal,
+                            &mut sidebar,
+                            &mut folder_picker,
+                            &mut quit_confirm,
+                        ) {
                             break;
                         }
                     }
@@ -3765,11 +4007,7 @@ fn event_loop(
                 {
                     for c in first_line.chars() {
                         if !c.is_control() {
-                            if sidebar.replace_input_focused {
-                                engine.project_replace_text.push(c);
-                            } else {
-                                engine.project_search_query.push(c);
-                            }
+                            sidebar.search_field.text(engine).push(c);
                         }
                     }
                     needs_redraw = true;
@@ -3840,6 +4078,10 @@ fn event_loop(
                 // resets both buffers so the next draw emits every cell.
                 terminal.clear().ok();
             }
+            // Terminals that report focus changes let `autosave = "focus"` work.
+            Event::FocusLost => {
+                engine.on_focus_lost();
+            }
             _ => {}
         }
         needs_redraw = true;
@@ -4154,6 +4396,51 @@ fn translate_key_to_pty(event: KeyEvent) -> Vec<u8> {
 
 // ─── Engine action handling ───────────────────────────────────────────────────
 
+/// Act on what `handle_key` (or a keymap resolved by `timeoutlen`)
+/// returned: actions that need TUI state are handled here, the rest by
+/// [`handle_action`].  Returns true when the editor should quit.
+fn dispatch_key_action(
+    engine: &mut Engine,
+    action: EngineAction,
+    terminal: &Terminal<CrosstermBackend<Stdout>>,
+    sidebar: &mut TuiSidebar,
+    folder_picker: &mut Option<FolderPickerState>,
+    quit_confirm: &mut bool,
+) -> bool {
+    let terminal_cols = || terminal.size().ok().map(|s| s.width).unwrap_or(80);
+    match action {
+        EngineAction::OpenTerminal => {
+            engine.terminal_new_tab(terminal_cols(), engine.session.terminal_panel_rows);
+        }
+        EngineAction::RunInTerminal(cmd) => {
+            engine.terminal_run_command(&cmd, terminal_cols(), engine.session.terminal_panel_rows);
+        }
+        EngineAction::OpenFolderDialog => {
+            *folder_picker = Some(FolderPickerState::new(
+                &engine.cwd.clone(),
+                FolderPickerMode::OpenFolder,
+                engine.settings.show_hidden_files,
+            ));
+        }
+        EngineAction::OpenWorkspaceDialog => {
+            // open_workspace_from_file() already ran in the engine;
+            // just refresh the sidebar to reflect the new cwd.
+            *sidebar = TuiSidebar::new(engine.cwd.clone(), sidebar.visible);
+            sidebar.sync_filter(&engine.settings);
+            sidebar.sort_case_insensitive = engine.settings.explorer_sort_case_insensitive;
+        }
+        EngineAction::SaveWorkspaceAsDialog => {
+            // For TUI, save workspace to current directory immediately
+            let ws_path = engine.cwd.join(".vimcode-workspace");
+            engine.save_workspace_as(&ws_path);
+        }
+        EngineAction::QuitWithUnsaved => *quit_confirm = true,
+        EngineAction::ToggleSidebar => sidebar.visible = !sidebar.visible,
+        action => return handle_action(engine, action),
+    }
+    false
+}
+
 fn handle_action(engine: &mut Engine, action: EngineAction) -> bool {
     match action {
         EngineAction::Quit | EngineAction::SaveQuit => {
@@ -4230,3 +4517,36 @@ fn byte_to_char_idx(text: &str, byte_offset: usize) -> usize {
     }
     text[..safe].chars().count()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::explorer_filter::LARGE_DIR_ENTRIES;
+
+    #[test]
+    fn test_large_explorer_folder_loads_in_background() {
+        let root = std::env::temp_dir().join("vimcode_test_tui_large_dir");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        for i in 0..=LARGE_DIR_ENTRIES {
+            fs::write(root.join(format!("f{i:05}.txt")), "").unwrap();
+        }
+
+        let mut sidebar = TuiSidebar::new(root.clone(), true);
+        assert_eq!(sidebar.rows.len(), 1);
+        assert!(sidebar.rows[0].name.ends_with("(loading…)"));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !sidebar.large_dirs.poll() {
+            assert!(Instant::now() < deadline, "folder never loaded");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        sidebar.build_rows();
+        assert_eq!(sidebar.rows.len(), LARGE_DIR_ENTRIES + 3);
+        assert!(!sidebar.rows[0].name.contains("loading"));
+        // Directories first.
+        assert_eq!(sidebar.rows[1].name, "sub");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
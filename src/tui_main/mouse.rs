@@ -720,10 +720,11 @@ pub(super) fn handle_mouse(
                             let buf_line = drag_rl
                                 .map(|l| l.line_idx)
                                 .unwrap_or_else(|| rw.scroll_top + view_row);
-                            let seg_offset = drag_rl.map(|l| l.segment_col_offset).unwrap_or(0);
-                            let col_in_text = (rel_col - wx).saturating_sub(gutter) as usize
-                                + rw.scroll_left
-                                + seg_offset;
+                            let col_in_text = text_click_col(
+                                rw,
+                                drag_rl,
+                                (rel_col - wx).saturating_sub(gutter) as usize,
+                            );
                             engine.mouse_drag(rw.window_id, buf_line, col_in_text);
                             *mouse_text_drag = true;
                             return sidebar_width;
@@ -1380,7 +1381,11 @@ pub(super) fn handle_mouse(
                         .get(view_row)
                         .map(|l| l.line_idx)
                         .unwrap_or_else(|| rw.scroll_top + view_row);
-                    let text_col = (rel_col - wx).saturating_sub(gutter) as usize + rw.scroll_left;
+                    let text_col = text_click_col(
+                        rw,
+                        rw.lines.get(view_row),
+                        (rel_col - wx).saturating_sub(gutter) as usize,
+                    );
                     engine.editor_hover_mouse_move(buf_line, text_col, mouse_on_editor_hover);
                     found = true;
                     break;
@@ -1600,7 +1605,7 @@ pub(super) fn handle_mouse(
                             // open_workspace_from_file() already ran in the engine;
                             // refresh the sidebar to reflect the new cwd.
                             *sidebar = TuiSidebar::new(engine.cwd.clone(), sidebar.visible);
-                            sidebar.show_hidden_files = engine.settings.show_hidden_files;
+                            sidebar.sync_filter(&engine.settings);
                         } else if act == EngineAction::SaveWorkspaceAsDialog {
                             let ws_path = engine.cwd.join(".vimcode-workspace");
                             engine.save_workspace_as(&ws_path);
@@ -2202,12 +2207,12 @@ pub(super) fn handle_mouse(
             return sidebar_width;
         } else if sidebar.active_panel == TuiPanel::Search {
             sidebar.has_focus = true;
-            // results_height = (total height - 2 status rows) - 5 panel header rows
-            let results_height = term_height.saturating_sub(7) as usize;
+            // results_height = (total height - 2 status rows) - panel header rows
+            let results_height = term_height.saturating_sub(2 + SEARCH_RESULTS_ROW) as usize;
             let results = &engine.project_search_results;
 
             // Click on the scrollbar column in the results area → jump-scroll
-            if col == sb_col && !results.is_empty() && sidebar_row >= 5 {
+            if col == sb_col && !results.is_empty() && sidebar_row >= SEARCH_RESULTS_ROW {
                 // Count total display rows (result rows + file header rows)
                 let total_display = {
                     let mut count = 0usize;
@@ -2222,7 +2227,7 @@ pub(super) fn handle_mouse(
                     count
                 };
                 if total_display > results_height {
-                    let rel_row = sidebar_row.saturating_sub(5) as usize;
+                    let rel_row = sidebar_row.saturating_sub(SEARCH_RESULTS_ROW) as usize;
                     let ratio = rel_row as f64 / results_height as f64;
                     let new_scroll = (ratio * total_display as f64) as usize;
                     sidebar.search_scroll_top =
@@ -2230,7 +2235,7 @@ pub(super) fn handle_mouse(
                     // Arm drag state so subsequent Drag events continue scrolling.
                     // track_abs_start is the absolute terminal row of the track top.
                     *dragging_sidebar_search = Some(SidebarScrollDrag {
-                        track_abs_start: 5 + menu_rows,
+                        track_abs_start: SEARCH_RESULTS_ROW + menu_rows,
                         track_len: results_height as u16,
                         total: total_display,
                     });
@@ -2238,17 +2243,24 @@ pub(super) fn handle_mouse(
                 return sidebar_width;
             }
 
-            // sidebar_rows 0-2: header + search + replace inputs — clicking enters input mode
-            if sidebar_row <= 2 {
+            // sidebar_rows 0-4: header + search/replace/include/exclude inputs —
+            // clicking enters input mode
+            if sidebar_row <= 4 {
                 sidebar.search_input_mode = true;
-                sidebar.replace_input_focused = sidebar_row == 2;
+                sidebar.search_field = match sidebar_row {
+                    2 => SearchField::Replace,
+                    3 => SearchField::Include,
+                    4 => SearchField::Exclude,
+                    _ => SearchField::Query,
+                };
             } else {
                 sidebar.search_input_mode = false;
-                sidebar.replace_input_focused = false;
-                // sidebar_row 3 = toggles, 4 = status line; 5+ = results area
+                sidebar.search_field = SearchField::Query;
+                // sidebar_row 5 = toggles, 6 = status line; 7+ = results area
                 // Add scroll offset so clicks map to the correct result.
-                let content_row =
-                    (sidebar_row as usize).saturating_sub(5) + sidebar.search_scroll_top;
+                let content_row = (sidebar_row as usize)
+                    .saturating_sub(SEARCH_RESULTS_ROW as usize)
+                    + sidebar.search_scroll_top;
                 if !results.is_empty() {
                     let selected = visual_row_to_result_idx(results, content_row);
                     if let Some(idx) = selected {
@@ -2745,6 +2757,12 @@ pub(super) fn handle_mouse(
 
                 // Check gutter area
                 let view_row = (editor_row - wy) as usize;
+                // Sticky scroll rows jump to their symbol instead of
+                // placing the cursor underneath.
+                if let Some(sticky) = rw.sticky_lines.get(view_row) {
+                    engine.sticky_scroll_jump(rw.window_id, sticky.line_idx);
+                    return sidebar_width;
+                }
                 if gutter > 0 && rel_col >= wx && rel_col < wx + gutter {
                     if let Some(rl) = rw.lines.get(view_row) {
                         let gutter_col = (rel_col - wx) as usize;
@@ -2796,10 +2814,9 @@ pub(super) fn handle_mouse(
                 let buf_line = clicked_rl
                     .map(|l| l.line_idx)
                     .unwrap_or_else(|| rw.scroll_top + view_row);
-                // For wrapped lines, add segment_col_offset so the click
-                // targets the correct column within the full buffer line.
-                let seg_offset = clicked_rl.map(|l| l.segment_col_offset).unwrap_or(0);
-                let col_in_text = (rel_col - wx - gutter) as usize + rw.scroll_left + seg_offset;
+                // Tab-aware display → char column; for wrapped lines this also
+                // adds segment_col_offset so the click targets the full line.
+                let col_in_text = text_click_col(rw, clicked_rl, (rel_col - wx - gutter) as usize);
 
                 // Double-click detection
                 let now = Instant::now();
@@ -2832,6 +2849,30 @@ pub(super) fn handle_mouse(
     sidebar_width
 }
 
+/// Convert a cell offset inside a window's text area into a buffer column.
+/// Tabs expand to the next `tabstop` multiple (matching `render_text_line`),
+/// and wrapped continuation rows add their `segment_col_offset`.
+fn text_click_col(
+    rw: &crate::render::RenderedWindow,
+    rl: Option<&crate::render::RenderedLine>,
+    text_x: usize,
+) -> usize {
+    match rl {
+        Some(rl) => {
+            let text = rl.raw_text.trim_end_matches(['\n', '\r']);
+            let display_col = crate::render::strip_inlay_hint_cols(
+                text,
+                &rl.inlay_hints,
+                text_x + rw.scroll_left,
+                rw.tabstop,
+            );
+            rl.segment_col_offset
+                + crate::render::display_col_to_buffer_col(text, display_col, rw.tabstop.max(1), 0)
+        }
+        None => text_x + rw.scroll_left,
+    }
+}
+
 /// Walk status line segments and find which action (if any) is at `click_col`.
 fn status_segment_hit_test(
     status: &crate::render::WindowStatusLine,
@@ -1103,63 +1103,53 @@ pub(super) fn render_search_panel(
         x += 1;
     }
     // Cursor blinking indicator: show │ at cursor position when in input mode
-    if sidebar.search_input_mode && !sidebar.replace_input_focused && x < end_bracket_x {
+    if sidebar.search_input_mode && sidebar.search_field == SearchField::Query && x < end_bracket_x
+    {
         set_cell(buf, x, input_y, '\u{258f}', rc(theme.cursor), input_bg); // ▏
     }
 
-    if area.height < 3 {
-        return;
-    }
-
-    // Row 2: replace input box  "[ replace_ ]"
-    let replace_y = area.y + 2;
-    let replace_text = &engine.project_replace_text;
-    let replace_bg = if sidebar.replace_input_focused && sidebar.search_input_mode {
-        input_bg
-    } else {
-        rc(theme.tab_bar_bg) // dimmer when unfocused
-    };
-    set_cell(buf, area.x, replace_y, '[', dim_fg, bg);
-    let rep_end_x = if area.width > 1 {
-        area.x + area.width - 1
-    } else {
-        area.x
-    };
-    set_cell(buf, rep_end_x, replace_y, ']', dim_fg, bg);
-    for x in (area.x + 1)..rep_end_x {
-        set_cell(buf, x, replace_y, ' ', input_fg, replace_bg);
-    }
-    // Placeholder or actual text
-    if replace_text.is_empty() && !(sidebar.replace_input_focused && sidebar.search_input_mode) {
-        let placeholder = "Replace…";
-        let mut x = area.x + 1;
-        for ch in placeholder.chars() {
-            if x >= rep_end_x {
-                break;
-            }
-            set_cell(buf, x, replace_y, ch, dim_fg, replace_bg);
-            x += 1;
-        }
-    } else {
-        let mut x = area.x + 1;
-        for ch in replace_text.chars() {
-            if x >= rep_end_x {
-                break;
-            }
-            set_cell(buf, x, replace_y, ch, input_fg, replace_bg);
-            x += 1;
-        }
-        if sidebar.replace_input_focused && sidebar.search_input_mode && x < rep_end_x {
-            set_cell(buf, x, replace_y, '\u{258f}', rc(theme.cursor), replace_bg);
+    // Rows 2-4: replace, files-to-include and files-to-exclude boxes
+    let secondary_inputs = [
+        (
+            SearchField::Replace,
+            &engine.project_replace_text,
+            "Replace…",
+        ),
+        (
+            SearchField::Include,
+            &engine.project_search_options.include,
+            "Files to include…",
+        ),
+        (
+            SearchField::Exclude,
+            &engine.project_search_options.exclude,
+            "Files to exclude…",
+        ),
+    ];
+    for (i, (field, text, placeholder)) in secondary_inputs.into_iter().enumerate() {
+        let y = area.y + 2 + i as u16;
+        if y >= area.y + area.height {
+            return;
         }
+        let focused = sidebar.search_input_mode && sidebar.search_field == field;
+        render_search_input(
+            buf,
+            area.x,
+            y,
+            area.width,
+            text,
+            placeholder,
+            focused,
+            theme,
+        );
     }
 
-    if area.height < 4 {
+    if area.height < 6 {
         return;
     }
 
-    // Row 3: toggle indicators (Aa / Ab| / .* ) + hint
-    let toggle_y = area.y + 3;
+    // Row 5: toggle indicators (Aa / Ab| / .* ) + hint
+    let toggle_y = area.y + 5;
     for x in area.x..area.x + area.width {
         set_cell(buf, x, toggle_y, ' ', dim_fg, bg);
     }
@@ -1189,9 +1179,14 @@ pub(super) fn render_search_panel(
         draw_toggle(buf, "Aa", opts.case_sensitive, &mut tx);
         draw_toggle(buf, "Ab|", opts.whole_word, &mut tx);
         draw_toggle(buf, ".*", opts.use_regex, &mut tx);
+        draw_toggle(buf, "\u{21c4}", engine.project_replace_preview, &mut tx);
 
         // Hint text
-        let hint = "Alt+C/W/R/H";
+        let hint = if engine.project_replace_preview {
+            "Space toggle, Alt+H replace"
+        } else {
+            "Alt+C/W/R/H/P/Q"
+        };
         if tx + 1 < area.x + area.width {
             // Small gap
             tx += 1;
@@ -1205,12 +1200,12 @@ pub(super) fn render_search_panel(
         }
     }
 
-    if area.height < 5 {
+    if area.height < 7 {
         return;
     }
 
-    // Row 4: status / hint line
-    let status_y = area.y + 4;
+    // Row 6: status / hint line
+    let status_y = area.y + 6;
     let status_text = if engine.project_search_results.is_empty() {
         if query.is_empty() {
             " Type to search, Enter to run"
@@ -1231,29 +1226,37 @@ pub(super) fn render_search_panel(
         x += 1;
     }
 
-    if area.height < 6 {
+    if area.height < 8 {
         return;
     }
 
-    // Rows 5+: results
+    // Rows 7+: results
     let results = &engine.project_search_results;
     if results.is_empty() {
         return;
     }
 
-    let results_start_y = area.y + 5;
-    let results_height = area.height.saturating_sub(5) as usize;
+    let results_start_y = area.y + SEARCH_RESULTS_ROW;
+    let results_height = area.height.saturating_sub(SEARCH_RESULTS_ROW) as usize;
 
     // Build the flat display list (file headers + result rows)
     struct DisplayRow {
         text: String,
         is_header: bool,
         result_idx: Option<usize>,
+        /// Unchecked in the replace preview.
+        excluded: bool,
     }
 
     let mut display_rows: Vec<DisplayRow> = Vec::new();
     let root = &sidebar.root;
     let mut last_file: Option<&std::path::Path> = None;
+    // Replace preview: each row shows "[x] line: before → after".
+    let previews = if engine.project_replace_preview {
+        engine.project_replace_preview_lines(0..results.len())
+    } else {
+        None
+    };
 
     for (idx, m) in results.iter().enumerate() {
         if last_file != Some(m.file.as_path()) {
@@ -1263,13 +1266,25 @@ pub(super) fn render_search_panel(
                 text: rel.display().to_string(),
                 is_header: true,
                 result_idx: None,
+                excluded: false,
             });
         }
-        let snippet = format!("  {}: {}", m.line + 1, m.line_text.trim());
+        let included = engine.project_match_included(idx);
+        let snippet = match previews.as_ref().and_then(|p| p.get(idx)) {
+            Some(after) => format!(
+                "  [{}] {}: {} \u{2192} {}",
+                if included { 'x' } else { ' ' },
+                m.line + 1,
+                m.line_text.trim(),
+                after.trim()
+            ),
+            None => format!("  {}: {}", m.line + 1, m.line_text.trim()),
+        };
         display_rows.push(DisplayRow {
             text: snippet,
             is_header: false,
             result_idx: Some(idx),
+            excluded: previews.is_some() && !included,
         });
     }
 
@@ -1305,6 +1320,8 @@ pub(super) fn render_search_panel(
             (sel_fg, sel_bg)
         } else if dr.is_header {
             (file_header_fg, bg)
+        } else if dr.excluded {
+            (dim_fg, bg)
         } else {
             (fg, bg)
         };
@@ -1351,6 +1368,51 @@ pub(super) fn render_search_panel(
     }
 }
 
+/// One secondary search-panel input box: `[text      ]`, dimmed and showing
+/// `placeholder` while empty and unfocused.
+#[allow(clippy::too_many_arguments)]
+fn render_search_input(
+    buf: &mut ratatui::buffer::Buffer,
+    x0: u16,
+    y: u16,
+    width: u16,
+    text: &str,
+    placeholder: &str,
+    focused: bool,
+    theme: &Theme,
+) {
+    let fg = rc(theme.foreground);
+    let bg = rc(theme.tab_bar_bg);
+    let dim_fg = rc(theme.line_number_fg);
+    let box_bg = if focused {
+        rc(theme.active_background)
+    } else {
+        bg // dimmer when unfocused
+    };
+    set_cell(buf, x0, y, '[', dim_fg, bg);
+    let end_x = if width > 1 { x0 + width - 1 } else { x0 };
+    set_cell(buf, end_x, y, ']', dim_fg, bg);
+    for x in (x0 + 1)..end_x {
+        set_cell(buf, x, y, ' ', fg, box_bg);
+    }
+    let (shown, shown_fg) = if text.is_empty() && !focused {
+        (placeholder, dim_fg)
+    } else {
+        (text, fg)
+    };
+    let mut x = x0 + 1;
+    for ch in shown.chars() {
+        if x >= end_x {
+            break;
+        }
+        set_cell(buf, x, y, ch, shown_fg, box_bg);
+        x += 1;
+    }
+    if focused && x < end_x {
+        set_cell(buf, x, y, '\u{258f}', rc(theme.cursor), box_bg);
+    }
+}
+
 // ─── Wildmenu (command Tab completion bar) ───────────────────────────────────
 
 pub(super) fn render_wildmenu(
@@ -1472,7 +1534,11 @@ pub(super) fn render_command_line(
     command: &render::CommandLineData,
     theme: &Theme,
 ) {
-    let fg = rc(theme.command_fg);
+    let fg = rc(if command.is_error {
+        theme.diagnostic_error
+    } else {
+        theme.command_fg
+    });
     let bg = rc(theme.command_bg);
 
     for x in area.x..area.x + area.width {
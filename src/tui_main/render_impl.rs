@@ -423,12 +423,11 @@ pub(super) fn draw_frame(
                 let gutter_w = active_win.gutter_char_width as u16;
                 let win_x = editor_area.x + active_win.rect.x as u16;
                 let win_y = editor_area.y + active_win.rect.y as u16;
-                let raw = active_win
+                let vis_col = active_win
                     .lines
                     .get(cursor_pos.view_line)
-                    .map(|l| l.raw_text.as_str())
-                    .unwrap_or("");
-                let vis_col = char_col_to_visual(raw, cursor_pos.col, active_win.tabstop)
+                    .map(|l| line_col_to_visual(l, cursor_pos.col, active_win.tabstop))
+                    .unwrap_or(0)
                     .saturating_sub(active_win.scroll_left) as u16;
                 let popup_x = win_x + gutter_w + vis_col;
                 let popup_y = win_y + cursor_pos.view_line as u16 + 1;
@@ -656,6 +655,11 @@ pub(super) fn draw_frame(
         render_tab_switcher_popup(frame.buffer_mut(), area, ts, theme);
     }
 
+    // ── Which-key popup (bottom of the editor area) ─────────────────────────
+    if let Some(ref wk) = screen.which_key {
+        render_which_key(frame.buffer_mut(), editor_area, wk, theme);
+    }
+
     // ── Context menu popup (above status/command line) ─────────────────────
     if let Some(ref ctx_menu) = screen.context_menu {
         render_context_menu(frame.buffer_mut(), area, ctx_menu, theme);
@@ -1315,6 +1319,7 @@ pub(super) fn render_completion_popup(
     let sel_bg_color = rc(theme.completion_selected_bg);
     let fg_color = rc(theme.completion_fg);
     let border_color = rc(theme.completion_border);
+    let match_color = rc(theme.fuzzy_match_fg);
 
     let buf = frame.buffer_mut();
     for (i, candidate) in menu.candidates.iter().enumerate().take(visible as usize) {
@@ -1339,13 +1344,76 @@ pub(super) fn render_completion_popup(
                 cell.set_char(ch).set_fg(border_color);
             }
         }
-        // Render candidate text starting at col 1
+        // Render candidate text starting at col 1, fuzzy-matched chars highlighted
+        let matched = menu.match_positions.get(i);
         let display = format!(" {}", candidate);
         for (j, ch) in display.chars().enumerate() {
             let cell_x = x + 1 + j as u16;
             if cell_x + 1 < x + width && cell_x < term_area.width && row_y < term_area.height {
+                let is_match = j > 0 && matched.is_some_and(|m| m.contains(&(j - 1)));
                 let cell = &mut buf[(cell_x, row_y)];
-                cell.set_char(ch).set_fg(fg_color).set_bg(row_bg);
+                if is_match {
+                    cell.set_char(ch).set_fg(match_color).set_bg(row_bg);
+                    cell.modifier |= Modifier::BOLD;
+                } else {
+                    cell.set_char(ch).set_fg(fg_color).set_bg(row_bg);
+                }
+            }
+        }
+    }
+
+    render_completion_doc_panel(frame, menu, x, y, width, term_area, theme);
+}
+
+/// Draw the selected completion item's documentation beside the popup at
+/// (`x`, `y`, `width`): to the right when it fits, otherwise to the left.
+fn render_completion_doc_panel(
+    frame: &mut ratatui::Frame,
+    menu: &CompletionMenu,
+    x: u16,
+    y: u16,
+    width: u16,
+    term_area: Rect,
+    theme: &Theme,
+) {
+    let doc = &menu.documentation;
+    if doc.is_empty() {
+        return;
+    }
+    let max_len = doc.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let doc_width = (max_len as u16 + 4).max(12);
+    let doc_x = if x + width + doc_width <= term_area.width {
+        x + width
+    } else if x >= doc_width {
+        x - doc_width
+    } else {
+        return;
+    };
+    let rows = (doc.len() as u16).min(term_area.height.saturating_sub(y));
+
+    let bg_color = rc(theme.hover_bg);
+    let fg_color = rc(theme.hover_fg);
+    let border_color = rc(theme.hover_border);
+
+    let buf = frame.buffer_mut();
+    for (i, text_line) in doc.iter().enumerate().take(rows as usize) {
+        let row_y = y + i as u16;
+        for col in 0..doc_width {
+            let cell = &mut buf[(doc_x + col, row_y)];
+            let ch = if col == 0 || col == doc_width - 1 {
+                '│'
+            } else {
+                ' '
+            };
+            cell.set_char(ch).set_fg(border_color).set_bg(bg_color);
+        }
+        for (j, ch) in text_line.chars().enumerate() {
+            let cell_x = doc_x + 2 + j as u16;
+            if cell_x + 1 < doc_x + doc_width {
+                buf[(cell_x, row_y)]
+                    .set_char(ch)
+                    .set_fg(fg_color)
+                    .set_bg(bg_color);
             }
         }
     }
@@ -1773,7 +1841,7 @@ pub(super) fn render_picker_popup(
 ) {
     let term_cols = term_area.width;
     let term_rows = term_area.height;
-    let has_preview = picker.preview.is_some();
+    let has_preview = picker.preview_lines.is_some();
 
     // Size adapts based on whether we have a preview pane
     let width = if has_preview {
@@ -2015,17 +2083,43 @@ pub(super) fn render_picker_popup(
                     set_cell(buf, cx, ry, ' ', fg_color, bg_color);
                 }
             }
-            if let Some(ref preview) = picker.preview {
+            if let Some(ref note) = picker.preview_note {
+                if row_idx == 0 {
+                    for (j, ch) in format!(" {note}").chars().enumerate().take(right_inner) {
+                        let cx = right_start + j as u16;
+                        if cx + 1 < x + width && cx < term_area.width {
+                            set_cell(buf, cx, ry, ch, border_fg, bg_color);
+                        }
+                    }
+                }
+            } else if let Some(ref preview) = picker.preview_lines {
                 let preview_idx = row_idx + picker.preview_scroll;
-                if let Some((lineno, text, is_match)) = preview.get(preview_idx) {
-                    // Replace tabs with spaces so each character occupies exactly one cell.
-                    let sanitized = text.replace('\t', "    ");
-                    let preview_text = format!("{:4}: {}", lineno, sanitized);
-                    let preview_fg = if *is_match { title_fg } else { fg_color };
-                    for (j, ch) in preview_text.chars().enumerate().take(right_inner) {
+                if let Some(line) = preview.get(preview_idx) {
+                    let base_fg = if line.is_match { title_fg } else { fg_color };
+                    let mut cells: Vec<(char, RColor)> = format!("{:4}: ", line.lineno)
+                        .chars()
+                        .map(|ch| (ch, base_fg))
+                        .collect();
+                    for (byte, ch) in line.text.char_indices() {
+                        let fg = line
+                            .spans
+                            .iter()
+                            .rev()
+                            .find(|(start, end, _)| (*start..*end).contains(&byte))
+                            .map_or(base_fg, |(_, _, color)| rc(*color));
+                        // Expand tabs so each character occupies exactly one cell.
+                        if ch == '\t' {
+                            for _ in 0..4 {
+                                cells.push((' ', fg));
+                            }
+                        } else {
+                            cells.push((ch, fg));
+                        }
+                    }
+                    for (j, (ch, fg)) in cells.into_iter().enumerate().take(right_inner) {
                         let cx = right_start + j as u16;
                         if cx + 1 < x + width && cx < term_area.width {
-                            set_cell(buf, cx, ry, ch, preview_fg, bg_color);
+                            set_cell(buf, cx, ry, ch, fg, bg_color);
                         }
                     }
                 }
@@ -2227,6 +2321,55 @@ pub(super) fn render_tab_switcher_popup(
     }
 }
 
+/// Which-key panel: a title row plus a grid of `key → description` cells,
+/// anchored to the bottom of the editor area.
+pub(super) fn render_which_key(
+    buf: &mut ratatui::buffer::Buffer,
+    editor_area: Rect,
+    wk: &render::WhichKeyPanel,
+    theme: &Theme,
+) {
+    let width = editor_area.width as usize;
+    if wk.entries.is_empty() || width == 0 {
+        return;
+    }
+    let (col_w, cols, rows) = render::which_key_grid(wk, width);
+    let height = (rows as u16 + 1).min(editor_area.height);
+    let y0 = editor_area.y + editor_area.height - height;
+    let bg = rc(theme.hover_bg);
+    let fg = rc(theme.hover_fg);
+    let key_fg = rc(theme.keyword);
+    let title_fg = rc(theme.fuzzy_title_fg);
+
+    for row in 0..height {
+        for col in 0..editor_area.width {
+            set_cell(buf, editor_area.x + col, y0 + row, ' ', fg, bg);
+        }
+    }
+    let title = format!(" {} ", wk.prefix);
+    for (i, ch) in title.chars().take(width).enumerate() {
+        set_cell(buf, editor_area.x + i as u16, y0, ch, title_fg, bg);
+    }
+
+    for (idx, (key, desc)) in render::which_key_cells(wk, cols, rows).iter().enumerate() {
+        let (row, col) = (idx % rows, idx / rows);
+        let ry = y0 + 1 + row as u16;
+        if ry >= y0 + height {
+            continue;
+        }
+        let cell = format!("{key} → {desc}");
+        let key_len = key.chars().count();
+        let x = col * col_w;
+        for (i, ch) in cell.chars().take(col_w.saturating_sub(1)).enumerate() {
+            if x + i >= width {
+                break;
+            }
+            let c = if i < key_len { key_fg } else { fg };
+            set_cell(buf, editor_area.x + (x + i) as u16, ry, ch, c, bg);
+        }
+    }
+}
+
 pub(super) fn render_quit_confirm_overlay(
     buf: &mut ratatui::buffer::Buffer,
     term_area: Rect,
@@ -2659,6 +2802,21 @@ pub(super) fn render_window(
     window: &RenderedWindow,
     theme: &Theme,
 ) {
+    // Winbar: the row directly above the text area
+    if let Some(ref winbar) = window.winbar {
+        if area.y > 0 {
+            let y = area.y - 1;
+            let bg = rc(theme.winbar_bg);
+            let fg = rc(theme.breadcrumb_active_fg);
+            let text = format!(" {winbar}");
+            let mut chars = text.chars();
+            for col in 0..area.width {
+                let ch = chars.next().unwrap_or(' ');
+                set_cell(frame.buffer_mut(), area.x + col, y, ch, fg, bg);
+            }
+        }
+    }
+
     // Reserve bottom row for per-window status line when present
     let status_bar_row = if window.status_line.is_some() && area.height > 1 {
         Some(area.y + area.height - 1)
@@ -2776,7 +2934,9 @@ pub(super) fn render_window(
                 set_cell(frame.buffer_mut(), gx, screen_y, ch, fg, line_bg);
             }
             // Diagnostic gutter icon (overwrite leftmost gutter char)
-            if let Some(severity) = window.diagnostic_gutter.get(&line.line_idx) {
+            if line.is_virtual {
+                // Deleted lines in a unified diff keep their `-` marker.
+            } else if let Some(severity) = window.diagnostic_gutter.get(&line.line_idx) {
                 let (diag_ch, diag_color) = match severity {
                     DiagnosticSeverity::Error => ('●', rc(theme.diagnostic_error)),
                     DiagnosticSeverity::Warning => ('●', rc(theme.diagnostic_warning)),
@@ -2822,6 +2982,8 @@ pub(super) fn render_window(
             theme,
             line_bg,
             window.tabstop,
+            window.blame_annotations,
+            window.list_chars.as_ref(),
         );
 
         // Indent guides: draw │ at guide columns where the cell is a space
@@ -2891,8 +3053,8 @@ pub(super) fn render_window(
                 DiagnosticSeverity::Information => theme.diagnostic_info,
                 DiagnosticSeverity::Hint => theme.diagnostic_hint,
             });
-            let vis_start = char_col_to_visual(&line.raw_text, dm.start_col, window.tabstop);
-            let vis_end = char_col_to_visual(&line.raw_text, dm.end_col, window.tabstop);
+            let vis_start = line_col_to_visual(line, dm.start_col, window.tabstop);
+            let vis_end = line_col_to_visual(line, dm.end_col, window.tabstop);
             for vcol in vis_start..vis_end {
                 if vcol < window.scroll_left {
                     continue;
@@ -2914,8 +3076,8 @@ pub(super) fn render_window(
         // Spell error underlines
         let spell_fg = rc(theme.spell_error);
         for sm in &line.spell_errors {
-            let vis_start = char_col_to_visual(&line.raw_text, sm.start_col, window.tabstop);
-            let vis_end = char_col_to_visual(&line.raw_text, sm.end_col, window.tabstop);
+            let vis_start = line_col_to_visual(line, sm.start_col, window.tabstop);
+            let vis_end = line_col_to_visual(line, sm.end_col, window.tabstop);
             for vcol in vis_start..vis_end {
                 if vcol < window.scroll_left {
                     continue;
@@ -2938,7 +3100,7 @@ pub(super) fn render_window(
         let bracket_bg = rc(theme.bracket_match_bg);
         for &(view_line, col) in &window.bracket_match_positions {
             if view_line == row_idx {
-                let vis = char_col_to_visual(&line.raw_text, col, window.tabstop);
+                let vis = line_col_to_visual(line, col, window.tabstop);
                 if vis < window.scroll_left {
                     continue;
                 }
@@ -2981,6 +3143,19 @@ pub(super) fn render_window(
         );
     }
 
+    // Active snippet placeholder
+    if let Some(ph) = &window.snippet_placeholder {
+        render_selection(
+            frame.buffer_mut(),
+            area,
+            window,
+            ph,
+            window_bg,
+            theme.selection,
+            rc(theme.foreground),
+        );
+    }
+
     // Yank highlight overlay (brief flash after yank)
     if let Some(yh) = &window.yank_highlight {
         render_selection(
@@ -3024,12 +3199,11 @@ pub(super) fn render_window(
     // Cursor
     if let Some((cursor_pos, cursor_shape)) = &window.cursor {
         let cursor_screen_y = area.y + cursor_pos.view_line as u16;
-        let raw = window
+        let vis_col = window
             .lines
             .get(cursor_pos.view_line)
-            .map(|l| l.raw_text.as_str())
-            .unwrap_or("");
-        let vis_col = char_col_to_visual(raw, cursor_pos.col, window.tabstop)
+            .map(|l| line_col_to_visual(l, cursor_pos.col, window.tabstop))
+            .unwrap_or(0)
             .saturating_sub(window.scroll_left) as u16;
         let cursor_screen_x = area.x + gutter_w + vis_col;
 
@@ -3044,7 +3218,8 @@ pub(super) fn render_window(
                     let cell = &mut buf[(cursor_screen_x, cursor_screen_y)];
                     let old_fg = cell.fg;
                     let old_bg = cell.bg;
-                    cell.set_fg(old_bg).set_bg(old_fg);
+                    let cursor_bg = window.cursor_color.map_or(old_fg, rc);
+                    cell.set_fg(old_bg).set_bg(cursor_bg);
                 }
             }
             CursorShape::Bar | CursorShape::Underline => {
@@ -3058,7 +3233,7 @@ pub(super) fn render_window(
         if let Some(rl) = window.lines.get(cursor_pos.view_line) {
             if let Some(ghost) = &rl.ghost_suffix {
                 let ghost_screen_y = area.y + cursor_pos.view_line as u16;
-                let vis_col = char_col_to_visual(&rl.raw_text, cursor_pos.col, window.tabstop)
+                let vis_col = line_col_to_visual(rl, cursor_pos.col, window.tabstop)
                     .saturating_sub(window.scroll_left) as u16;
                 let ghost_start_x = area.x + gutter_w + vis_col;
                 let ghost_fg = rc(theme.ghost_text_fg);
@@ -3085,13 +3260,12 @@ pub(super) fn render_window(
         } else {
             extra_pos.col
         };
-        let raw = window
+        let vis_col = window
             .lines
             .get(extra_pos.view_line)
-            .map(|l| l.raw_text.as_str())
-            .unwrap_or("");
-        let vis_col =
-            char_col_to_visual(raw, col, window.tabstop).saturating_sub(window.scroll_left) as u16;
+            .map(|l| line_col_to_visual(l, col, window.tabstop))
+            .unwrap_or(0)
+            .saturating_sub(window.scroll_left) as u16;
         let sx = area.x + gutter_w + vis_col;
         let buf = frame.buffer_mut();
         if sx < buf.area.x + buf.area.width && sy < buf.area.y + buf.area.height {
@@ -3104,12 +3278,93 @@ pub(super) fn render_window(
         }
     }
 
+    // Sticky scroll: pinned scope headers over the first rows.
+    if !window.sticky_lines.is_empty() {
+        let text_width = area
+            .width
+            .saturating_sub(gutter_w)
+            .saturating_sub(if has_scrollbar { 1 } else { 0 });
+        render_sticky_lines(
+            frame.buffer_mut(),
+            area,
+            gutter_w,
+            text_width,
+            window,
+            theme,
+        );
+    }
+
     // ── Per-window status bar ────────────────────────────────────────────────
     if let (Some(status), Some(sy)) = (&window.status_line, status_bar_row) {
         render_window_status_line(frame.buffer_mut(), area.x, sy, area.width, status, theme);
     }
 }
 
+/// Draw the sticky scroll headers over the top rows of a window, on
+/// `theme.sticky_bg` and unaffected by horizontal scrolling.
+fn render_sticky_lines(
+    buf: &mut ratatui::buffer::Buffer,
+    area: Rect,
+    gutter_w: u16,
+    text_width: u16,
+    window: &RenderedWindow,
+    theme: &Theme,
+) {
+    let bg = rc(theme.sticky_bg);
+    let num_fg = rc(theme.line_number_fg);
+    let tabstop = window.tabstop.max(1);
+    for (row, sticky) in window.sticky_lines.iter().enumerate() {
+        let y = area.y + row as u16;
+        if y >= area.y + area.height {
+            break;
+        }
+        for col in 0..area.width {
+            set_cell(buf, area.x + col, y, ' ', num_fg, bg);
+        }
+        for (i, ch) in sticky
+            .gutter_text
+            .chars()
+            .take(gutter_w as usize)
+            .enumerate()
+        {
+            set_cell(buf, area.x + i as u16, y, ch, num_fg, bg);
+        }
+        let raw = &sticky.raw_text;
+        let mut vis_col = 0usize;
+        for (byte, ch) in raw.char_indices() {
+            if ch == '\n' || ch == '\r' {
+                break;
+            }
+            let fg = sticky
+                .spans
+                .iter()
+                .rev()
+                .find(|sp| sp.start_byte <= byte && byte < sp.end_byte)
+                .map_or(theme.foreground, |sp| sp.style.fg);
+            let width = if ch == '\t' {
+                tabstop - vis_col % tabstop
+            } else {
+                1
+            };
+            let glyph = if ch == '\t' { ' ' } else { ch };
+            for _ in 0..width {
+                if vis_col >= text_width as usize {
+                    break;
+                }
+                set_cell(
+                    buf,
+                    area.x + gutter_w + vis_col as u16,
+                    y,
+                    glyph,
+                    rc(fg),
+                    bg,
+                );
+                vis_col += 1;
+            }
+        }
+    }
+}
+
 /// Draw a per-window status line into the given row.
 fn render_window_status_line(
     buf: &mut ratatui::buffer::Buffer,
@@ -3279,6 +3534,13 @@ pub(super) fn char_col_to_visual(raw_text: &str, char_col: usize, tabstop: usize
     vis
 }
 
+/// Like [`char_col_to_visual`], but also counts inlay hint labels drawn
+/// before `char_col` on the row.
+pub(super) fn line_col_to_visual(line: &RenderedLine, char_col: usize, tabstop: usize) -> usize {
+    char_col_to_visual(&line.raw_text, char_col, tabstop)
+        + render::inlay_hint_width_before(&line.inlay_hints, char_col)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub(super) fn render_text_line(
     buf: &mut ratatui::buffer::Buffer,
@@ -3290,6 +3552,8 @@ pub(super) fn render_text_line(
     theme: &Theme,
     window_bg: RColor,
     tabstop: usize,
+    blame: bool,
+    list: Option<&ListChars>,
 ) {
     let raw = &line.raw_text;
     let chars: Vec<char> = raw.chars().filter(|&c| c != '\n' && c != '\r').collect();
@@ -3320,23 +3584,62 @@ pub(super) fn render_text_line(
     // space entries for a single tab.
     let tabstop = tabstop.max(1);
     let mut vis_col: usize = 0;
-    // Build a flat list of (visual_column, char_to_draw, char_index_for_style)
-    let mut cells: Vec<(usize, char, usize)> = Vec::with_capacity(chars.len());
+    // Build a flat list of (visual_column, char_to_draw, char_index_for_style,
+    // is_list_glyph); inlay hint cells carry `usize::MAX` as their style index.
+    // Tab stops are computed from the text alone (`text_col`) so hints don't
+    // shift them.  `:set list` glyphs replace the blanks a tab or space would
+    // draw, so the column layout is identical with the option on or off.
+    let trail_start = render::trailing_whitespace_col(raw);
+    let mut cells: Vec<(usize, char, usize, bool)> = Vec::with_capacity(chars.len());
+    let mut hints = line.inlay_hints.iter().peekable();
+    let mut text_col: usize = 0;
     for (i, &ch) in chars.iter().enumerate() {
+        while let Some(h) = hints.next_if(|h| h.col <= i) {
+            for hc in h.label.chars() {
+                cells.push((vis_col, hc, usize::MAX, false));
+                vis_col += 1;
+            }
+        }
+        let glyphs = list.and_then(|lc| render::list_glyphs(lc, ch, i >= trail_start));
         if ch == '\t' {
-            let next_stop = ((vis_col / tabstop) + 1) * tabstop;
-            while vis_col < next_stop {
-                cells.push((vis_col, ' ', i));
+            let next_stop = ((text_col / tabstop) + 1) * tabstop;
+            let mut first = true;
+            while text_col < next_stop {
+                let cell = match glyphs {
+                    Some((g, _)) if first => (vis_col, g, i, true),
+                    Some((_, Some(fill))) => (vis_col, fill, i, true),
+                    _ => (vis_col, ' ', i, false),
+                };
+                cells.push(cell);
+                first = false;
                 vis_col += 1;
+                text_col += 1;
             }
         } else {
-            cells.push((vis_col, ch, i));
+            text_col += 1;
+            match glyphs {
+                Some((g, _)) => cells.push((vis_col, g, i, true)),
+                None => cells.push((vis_col, ch, i, false)),
+            }
             vis_col += 1;
         }
     }
+    for h in hints {
+        for hc in h.label.chars() {
+            cells.push((vis_col, hc, usize::MAX, false));
+            vis_col += 1;
+        }
+    }
+    // End-of-line marker, only on the segment that actually ends the line.
+    if let Some(eol) = list.and_then(|lc| lc.eol).filter(|_| raw.ends_with('\n')) {
+        cells.push((vis_col, eol, usize::MAX, true));
+        vis_col += 1;
+    }
     let total_vis_cols = vis_col;
 
-    for &(vcol, ch, ci) in &cells {
+    let hint_fg = rc(theme.inlay_hint_fg);
+    let whitespace_fg = rc(theme.whitespace_fg);
+    for &(vcol, ch, ci, glyph) in &cells {
         if vcol < scroll_left {
             continue;
         }
@@ -3344,6 +3647,29 @@ pub(super) fn render_text_line(
         if col >= max_width {
             break;
         }
+        if glyph {
+            let bg = char_bgs
+                .get(ci)
+                .copied()
+                .flatten()
+                .map(rc)
+                .unwrap_or(window_bg);
+            set_cell(buf, x_start + col, y, ch, whitespace_fg, bg);
+            continue;
+        }
+        if ci == usize::MAX {
+            set_cell_styled(
+                buf,
+                x_start + col,
+                y,
+                ch,
+                hint_fg,
+                window_bg,
+                Modifier::ITALIC,
+                None,
+            );
+            continue;
+        }
         let fg = rc(char_fgs[ci]);
         let bg = char_bgs[ci].map(rc).unwrap_or(window_bg);
         if char_mods[ci].is_empty() {
@@ -3353,6 +3679,20 @@ pub(super) fn render_text_line(
         }
     }
 
+    // Git blame: right-aligned, clipped when the window is narrow.
+    if let Some(ann) = line.annotation.as_ref().filter(|_| blame) {
+        let visible_cols = total_vis_cols.saturating_sub(scroll_left);
+        if let Some((start, text)) =
+            render::right_aligned_annotation(ann, visible_cols, max_width as usize)
+        {
+            let ann_fg = rc(theme.blame_fg);
+            for (i, ch) in text.chars().enumerate() {
+                set_cell(buf, x_start + (start + i) as u16, y, ch, ann_fg, window_bg);
+            }
+        }
+        return;
+    }
+
     // Inline annotation / virtual text (e.g. git blame)
     if let Some(ann) = &line.annotation {
         let visible_cols = total_vis_cols.saturating_sub(scroll_left);
@@ -3365,6 +3705,27 @@ pub(super) fn render_text_line(
             }
             set_cell(buf, col, y, ch, ann_fg, window_bg);
         }
+        return;
+    }
+
+    // Diagnostic virtual text after the line's content, clipped to the window.
+    if let Some(vt) = &line.diagnostic_virtual_text {
+        let visible_cols = total_vis_cols.saturating_sub(scroll_left);
+        if let Some((start, text)) = vt.fit(visible_cols, max_width as usize) {
+            let vt_fg = rc(theme.diagnostic_virtual_text(vt.severity));
+            for (i, ch) in text.chars().enumerate() {
+                set_cell_styled(
+                    buf,
+                    x_start + (start + i) as u16,
+                    y,
+                    ch,
+                    vt_fg,
+                    window_bg,
+                    Modifier::ITALIC,
+                    None,
+                );
+            }
+        }
     }
 }
 
@@ -3384,7 +3745,7 @@ pub(super) fn render_selection(
 
     for (row_idx, line) in window.lines.iter().enumerate() {
         let buffer_line = line.line_idx;
-        if buffer_line < sel.start_line || buffer_line > sel.end_line {
+        if line.is_virtual || buffer_line < sel.start_line || buffer_line > sel.end_line {
             continue;
         }
         let screen_y = area.y + row_idx as u16;
@@ -3431,8 +3792,8 @@ pub(super) fn render_selection(
         let effective_end = col_end.min(char_count);
 
         // Convert char-index column range to visual columns accounting for tabs.
-        let vis_start = char_col_to_visual(&line.raw_text, col_start, window.tabstop);
-        let vis_end = char_col_to_visual(&line.raw_text, effective_end, window.tabstop);
+        let vis_start = line_col_to_visual(line, col_start, window.tabstop);
+        let vis_end = line_col_to_visual(line, effective_end, window.tabstop);
 
         for vis in vis_start..vis_end {
             if vis < window.scroll_left {
@@ -4268,14 +4629,15 @@ mod tests {
             root: std::path::PathBuf::from("/tmp"),
             expanded: std::collections::HashSet::new(),
             search_input_mode: false,
-            replace_input_focused: false,
+            search_field: SearchField::Query,
             search_scroll_top: 0,
-            show_hidden_files: false,
+            filter: Default::default(),
             sort_case_insensitive: false,
             toolbar_focused: false,
             toolbar_selected: 0,
             pending_ctrl_w: false,
             ext_panel_name: None,
+            large_dirs: Default::default(),
         };
         let sidebar_width = 0u16;
         let area = Rect {
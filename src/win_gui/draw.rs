@@ -10,7 +10,7 @@ use windows::Win32::Graphics::DirectWrite::*;
 use crate::core::engine::Notification;
 use crate::render::{
     BreadcrumbBar, Color, CursorShape, MenuBarData, RenderedLine, RenderedWindow, ScreenLayout,
-    SelectionKind, Theme, MENU_STRUCTURE,
+    SelectionKind, StyledSpan, Theme, MENU_STRUCTURE,
 };
 
 use super::{SidebarPanel, WinSidebar};
@@ -239,6 +239,11 @@ impl<'a> DrawContext<'a> {
                 self.draw_tab_switcher(ts);
             }
 
+            // Draw which-key panel
+            if let Some(ref wk) = layout.which_key {
+                self.draw_which_key(wk);
+            }
+
             // NOTE: context menu, menu dropdown, tab drag overlay, and dialog
             // are drawn separately after sidebar in on_paint for correct z-order.
         }
@@ -473,6 +478,24 @@ impl<'a> DrawContext<'a> {
         let rw_w = rw.rect.width as f32;
         let rw_h = rw.rect.height as f32;
 
+        // Winbar: the row directly above the text area
+        if let Some(ref winbar) = rw.winbar {
+            let wb_y = ry - self.line_height;
+            let wb_bg = self.solid_brush(self.theme.winbar_bg);
+            unsafe {
+                self.rt
+                    .FillRectangle(&rect_f(rx, wb_y, rw_w, self.line_height), &wb_bg);
+            }
+            let max_chars = (rw_w / self.char_width) as usize;
+            let text: String = winbar.chars().take(max_chars.saturating_sub(1)).collect();
+            self.draw_text(
+                &text,
+                rx + self.char_width,
+                wb_y,
+                self.theme.breadcrumb_active_fg,
+            );
+        }
+
         // Clip to window bounds so text doesn't bleed into adjacent areas
         unsafe {
             self.rt.PushAxisAlignedClip(
@@ -495,19 +518,18 @@ impl<'a> DrawContext<'a> {
         for (row_idx, line) in rw.lines.iter().enumerate() {
             let line_y = ry + (row_idx as f32) * self.line_height;
 
-            // Current line highlight (full width, not affected by scroll)
-            if line.is_current_line && rw.cursorline && rw.is_active {
+            // Current line highlight (full width including the gutter, not
+            // affected by scroll). Diff / DAP stopped-line backgrounds win.
+            if line.is_current_line
+                && rw.cursorline
+                && rw.is_active
+                && !line.is_dap_current
+                && line.diff_status.is_none()
+            {
                 let cl_bg = self.solid_brush(self.theme.cursorline_bg);
                 unsafe {
-                    self.rt.FillRectangle(
-                        &rect_f(
-                            rx + gutter_px,
-                            line_y,
-                            rw.rect.width as f32 - gutter_px,
-                            self.line_height,
-                        ),
-                        &cl_bg,
-                    );
+                    self.rt
+                        .FillRectangle(&rect_f(rx, line_y, rw_w, self.line_height), &cl_bg);
                 }
             }
 
@@ -563,6 +585,10 @@ impl<'a> DrawContext<'a> {
             if let Some(ref sel) = rw.selection {
                 self.draw_selection_for_line(rw, line, row_idx, sel, text_x, line_y);
             }
+            // Active snippet placeholder
+            if let Some(ref ph) = rw.snippet_placeholder {
+                self.draw_selection_for_line(rw, line, row_idx, ph, text_x, line_y);
+            }
 
             // Syntax-highlighted text spans (scrolled with text)
             self.draw_styled_line(line, text_x, line_y);
@@ -575,7 +601,11 @@ impl<'a> DrawContext<'a> {
             // Indent guides (scrolled with text)
             for &guide_col in &line.indent_guides {
                 let gx = text_x + guide_col as f32 * self.char_width;
-                let guide_brush = self.solid_brush_alpha(self.theme.line_number_fg, 0.3);
+                let guide_brush = if rw.active_indent_col == Some(guide_col) {
+                    self.solid_brush(self.theme.indent_guide_active_fg)
+                } else {
+                    self.solid_brush(self.theme.indent_guide_fg)
+                };
                 unsafe {
                     self.rt
                         .FillRectangle(&rect_f(gx, line_y, 1.0, self.line_height), &guide_brush);
@@ -589,7 +619,7 @@ impl<'a> DrawContext<'a> {
                     let cx = text_x + cc_col as f32 * self.char_width;
                     unsafe {
                         self.rt.FillRectangle(
-                            &rect_f(cx, line_y, self.char_width, self.line_height),
+                            &rect_f(cx.round(), line_y, 1.0, self.line_height),
                             &cc_brush,
                         );
                     }
@@ -598,16 +628,37 @@ impl<'a> DrawContext<'a> {
 
             // Ghost text (scrolled with text)
             if let Some(ref ghost) = line.ghost_suffix {
-                let text_len = line.raw_text.trim_end_matches('\n').chars().count();
+                let text_len = crate::render::line_display_cols(line, rw.tabstop);
                 let gx = text_x + text_len as f32 * self.char_width;
                 self.draw_text(ghost, gx, line_y, self.theme.line_number_fg);
             }
 
-            // Inline annotation (scrolled with text)
-            if let Some(ref ann) = line.annotation {
-                let text_len = line.raw_text.trim_end_matches('\n').chars().count();
+            // Git blame: right-aligned, clipped when the window is narrow.
+            if let Some(ann) = line.annotation.as_ref().filter(|_| rw.blame_annotations) {
+                let text_len = crate::render::line_display_cols(line, rw.tabstop);
+                let text_cols = text_len.saturating_sub(rw.scroll_left);
+                let width_cols = ((rw_w - gutter_px) / self.char_width).floor() as usize;
+                if let Some((start, text)) =
+                    crate::render::right_aligned_annotation(ann, text_cols, width_cols)
+                {
+                    let ax = rx + gutter_px + start as f32 * self.char_width;
+                    self.draw_text(&text, ax, line_y, self.theme.blame_fg);
+                }
+            } else if let Some(ref ann) = line.annotation {
+                // Inline annotation (scrolled with text)
+                let text_len = crate::render::line_display_cols(line, rw.tabstop);
                 let ax = text_x + (text_len as f32 + 2.0) * self.char_width;
                 self.draw_text(ann, ax, line_y, self.theme.line_number_fg);
+            } else if let Some(ref vt) = line.diagnostic_virtual_text {
+                // Diagnostic virtual text after the content, clipped to the window.
+                let text_len = crate::render::line_display_cols(line, rw.tabstop);
+                let text_cols = text_len.saturating_sub(rw.scroll_left);
+                let width_cols = ((rw_w - gutter_px) / self.char_width).floor() as usize;
+                if let Some((start, text)) = vt.fit(text_cols, width_cols) {
+                    let vx = rx + gutter_px + start as f32 * self.char_width;
+                    let fg = self.theme.diagnostic_virtual_text(vt.severity);
+                    self.draw_text(&text, vx, line_y, fg);
+                }
             }
         }
 
@@ -735,6 +786,21 @@ impl<'a> DrawContext<'a> {
             }
         }
 
+        // Sticky scroll headers pinned over the first rows
+        if !rw.sticky_lines.is_empty() {
+            let sticky_bg = self.solid_brush(self.theme.sticky_bg);
+            for (row, sticky) in rw.sticky_lines.iter().enumerate() {
+                let sy = ry + row as f32 * self.line_height;
+                unsafe {
+                    self.rt
+                        .FillRectangle(&rect_f(rx, sy, rw_w, self.line_height), &sticky_bg);
+                }
+                self.draw_text(&sticky.gutter_text, rx, sy, self.theme.line_number_fg);
+                let text = sticky.raw_text.trim_end_matches(['\n', '\r']);
+                self.draw_styled_text(text, &sticky.spans, rx + gutter_px, sy);
+            }
+        }
+
         // Pop editor window clip
         unsafe {
             self.rt.PopAxisAlignedClip();
@@ -742,17 +808,21 @@ impl<'a> DrawContext<'a> {
     }
 
     fn draw_styled_line(&self, line: &RenderedLine, x: f32, y: f32) {
-        if line.spans.is_empty() {
+        self.draw_styled_text(&line.raw_text, &line.spans, x, y);
+    }
+
+    /// Draw `raw` with syntax `spans` (byte offsets) starting at (`x`, `y`).
+    fn draw_styled_text(&self, raw: &str, spans: &[StyledSpan], x: f32, y: f32) {
+        if spans.is_empty() {
             // No syntax: draw raw text in default color
-            self.draw_text(&line.raw_text, x, y, self.theme.foreground);
+            self.draw_text(raw, x, y, self.theme.foreground);
             return;
         }
 
-        let raw = &line.raw_text;
         let raw_len = raw.len();
         let mut cursor_byte = 0usize;
 
-        for span in &line.spans {
+        for span in spans {
             let span_start = span.start_byte.min(raw_len);
             let span_end = span.end_byte.min(raw_len);
 
@@ -801,7 +871,7 @@ impl<'a> DrawContext<'a> {
     ) {
         let buf_line = line.line_idx;
 
-        if buf_line < sel.start_line || buf_line > sel.end_line {
+        if line.is_virtual || buf_line < sel.start_line || buf_line > sel.end_line {
             return;
         }
 
@@ -859,12 +929,13 @@ impl<'a> DrawContext<'a> {
         let h_scroll_px = rw.scroll_left as f32 * self.char_width;
         let cx = rx + gutter_px + pos.col as f32 * self.char_width - h_scroll_px;
         let cy = ry + pos.view_line as f32 * self.line_height;
-        let cursor_brush = self.solid_brush(self.theme.cursor);
+        let cursor_color = rw.cursor_color.unwrap_or(self.theme.cursor);
+        let cursor_brush = self.solid_brush(cursor_color);
 
         unsafe {
             match shape {
                 CursorShape::Block => {
-                    let block_brush = self.solid_brush_alpha(self.theme.cursor, 0.7);
+                    let block_brush = self.solid_brush_alpha(cursor_color, 0.7);
                     self.rt.FillRectangle(
                         &rect_f(cx, cy, self.char_width, self.line_height),
                         &block_brush,
@@ -989,12 +1060,12 @@ impl<'a> DrawContext<'a> {
             );
         }
         let cmd = &layout.command;
-        self.draw_text(
-            &cmd.text,
-            self.editor_left + self.char_width * 0.5,
-            y,
-            self.theme.foreground,
-        );
+        let fg = if cmd.is_error {
+            self.theme.diagnostic_error
+        } else {
+            self.theme.foreground
+        };
+        self.draw_text(&cmd.text, self.editor_left + self.char_width * 0.5, y, fg);
     }
 
     // ─── Completion popup ────────────────────────────────────────────────────
@@ -1050,7 +1121,53 @@ impl<'a> DrawContext<'a> {
                         .FillRectangle(&rect_f(x, iy, popup_w, self.line_height), &sel_bg);
                 }
             }
-            self.draw_text(candidate, x + self.char_width, iy, self.theme.foreground);
+            let matched = comp.match_positions.get(i).filter(|m| !m.is_empty());
+            if let Some(matched) = matched {
+                // Render char by char, highlighting fuzzy matches
+                let mut cx = x + self.char_width;
+                for (ci, ch) in candidate.chars().enumerate() {
+                    let color = if matched.contains(&ci) {
+                        self.theme.fuzzy_match_fg
+                    } else {
+                        self.theme.foreground
+                    };
+                    self.draw_text(&String::from(ch), cx, iy, color);
+                    cx += self.char_width;
+                }
+            } else {
+                self.draw_text(candidate, x + self.char_width, iy, self.theme.foreground);
+            }
+        }
+
+        // Documentation panel for the selected item: right of the popup when
+        // it fits, otherwise to its left.
+        if !comp.documentation.is_empty() {
+            let max_len = comp
+                .documentation
+                .iter()
+                .map(|l| l.chars().count())
+                .max()
+                .unwrap_or(0);
+            let doc_w = (max_len as f32 + 2.0) * self.char_width;
+            let doc_h = comp.documentation.len() as f32 * self.line_height;
+            let (rt_w, _) = self.rt_size();
+            let doc_x = if x + popup_w + doc_w <= rt_w {
+                x + popup_w
+            } else {
+                (x - doc_w).max(0.0)
+            };
+            let doc_bg = self.solid_brush(self.theme.hover_bg);
+            let doc_border = self.solid_brush(self.theme.hover_border);
+            unsafe {
+                self.rt
+                    .FillRectangle(&rect_f(doc_x, y, doc_w, doc_h), &doc_bg);
+                self.rt
+                    .DrawRectangle(&rect_f(doc_x, y, doc_w, doc_h), &doc_border, 1.0, None);
+            }
+            for (i, line) in comp.documentation.iter().enumerate() {
+                let ly = y + i as f32 * self.line_height;
+                self.draw_text(line, doc_x + self.char_width, ly, self.theme.hover_fg);
+            }
         }
     }
 
@@ -2183,7 +2300,7 @@ impl<'a> DrawContext<'a> {
         }
 
         let max_visible = 12usize;
-        let has_preview = picker.preview.is_some();
+        let has_preview = picker.preview_lines.is_some();
         let list_w = if has_preview { rt_w * 0.4 } else { rt_w * 0.6 };
         let total_w = if has_preview { rt_w * 0.8 } else { list_w };
         let header_h = self.line_height * 2.0; // title + input
@@ -2307,7 +2424,7 @@ impl<'a> DrawContext<'a> {
         }
 
         // Preview pane
-        if let Some(ref preview_lines) = picker.preview {
+        if let Some(ref preview_lines) = picker.preview_lines {
             let preview_x = dx + list_w;
             let preview_w = total_w - list_w;
             let sep = self.solid_brush(self.theme.separator);
@@ -2316,9 +2433,22 @@ impl<'a> DrawContext<'a> {
                     .FillRectangle(&rect_f(preview_x, dy + header_h, 1.0, body_h), &sep);
             }
 
-            for (i, (line_no, text, is_hl)) in preview_lines.iter().take(max_visible).enumerate() {
+            if let Some(ref note) = picker.preview_note {
+                self.draw_text(
+                    note,
+                    preview_x + self.char_width,
+                    list_y,
+                    self.theme.line_number_fg,
+                );
+            }
+            let lines = if picker.preview_note.is_some() {
+                &[][..]
+            } else {
+                &preview_lines[..]
+            };
+            for (i, line) in lines.iter().take(max_visible).enumerate() {
                 let py = list_y + i as f32 * self.line_height;
-                if *is_hl {
+                if line.is_match {
                     let hl = self.solid_brush_alpha(self.theme.selection, 0.4);
                     unsafe {
                         self.rt.FillRectangle(
@@ -2328,26 +2458,82 @@ impl<'a> DrawContext<'a> {
                     }
                 }
                 // Line number
-                let ln_text = format!("{:>4} ", line_no);
+                let ln_text = format!("{:>4} ", line.lineno);
                 self.draw_text(
                     &ln_text,
                     preview_x + self.char_width,
                     py,
                     self.theme.line_number_fg,
                 );
-                // Content
-                self.draw_text(
-                    text,
-                    preview_x + 6.0 * self.char_width,
-                    py,
-                    self.theme.foreground,
-                );
+                // Content, one run per syntax colour
+                let mut col = 0usize;
+                let mut run_start = 0usize;
+                let mut run_color = None;
+                let color_at = |byte: usize| {
+                    line.spans
+                        .iter()
+                        .rev()
+                        .find(|(start, end, _)| (*start..*end).contains(&byte))
+                        .map_or(self.theme.foreground, |(_, _, color)| *color)
+                };
+                let mut runs: Vec<(usize, &str, Color)> = Vec::new();
+                for (byte, _) in line.text.char_indices() {
+                    let color = color_at(byte);
+                    if run_color != Some(color) {
+                        if let Some(prev) = run_color {
+                            let run = &line.text[run_start..byte];
+                            runs.push((col, run, prev));
+                            col += run.chars().count();
+                        }
+                        run_start = byte;
+                        run_color = Some(color);
+                    }
+                }
+                if let Some(prev) = run_color {
+                    runs.push((col, &line.text[run_start..], prev));
+                }
+                for (col, run, color) in runs {
+                    self.draw_text(
+                        run,
+                        preview_x + (6 + col) as f32 * self.char_width,
+                        py,
+                        color,
+                    );
+                }
             }
         }
     }
 
     // ─── Tab switcher (Ctrl+Tab) ─────────────────────────────────────────────
 
+    fn draw_which_key(&self, wk: &crate::render::WhichKeyPanel) {
+        let (width, height) = self.rt_size();
+        let width_cells = (width / self.char_width) as usize;
+        let (col_w, cols, rows) = crate::render::which_key_grid(wk, width_cells);
+        let panel_h = (rows as f32 + 1.0) * self.line_height; // +1 for title
+        let y = height - 2.0 * self.line_height - panel_h;
+
+        let bg = self.solid_brush(self.theme.hover_bg);
+        let border = self.solid_brush(self.theme.hover_border);
+        unsafe {
+            self.rt.FillRectangle(&rect_f(0.0, y, width, panel_h), &bg);
+            self.rt
+                .DrawRectangle(&rect_f(0.0, y, width, panel_h), &border, 1.0, None);
+        }
+
+        self.draw_text(&wk.prefix, self.char_width, y, self.theme.fuzzy_title_fg);
+
+        let cells = crate::render::which_key_cells(wk, cols, rows);
+        for (idx, (key, desc)) in cells.iter().enumerate() {
+            let (row, col) = (idx % rows, idx / rows);
+            let x = (col * col_w) as f32 * self.char_width;
+            let iy = y + (row as f32 + 1.0) * self.line_height;
+            self.draw_text(key, x, iy, self.theme.keyword);
+            let desc_x = x + key.chars().count() as f32 * self.char_width;
+            self.draw_text(&format!(" → {desc}"), desc_x, iy, self.theme.hover_fg);
+        }
+    }
+
     fn draw_tab_switcher(&self, ts: &crate::render::TabSwitcherPanel) {
         let (rt_w, rt_h) = self.rt_size();
 
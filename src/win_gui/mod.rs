@@ -32,7 +32,12 @@ use windows::Win32::UI::Shell::Common::COMDLG_FILTERSPEC;
 use windows::Win32::UI::Shell::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
-use crate::core::engine::{open_url_in_browser, Engine, EngineAction, OpenMode};
+use crate::core::engine::{open_url_in_browser, Engine, EngineAction};
+use crate::core::explorer_filter::{
+    read_dir_entries, ExplorerEntry, ExplorerFilter, ExplorerFilterConfig,
+};
+use crate::core::explorer_watch::ExplorerWatcher;
+use crate::core::paths::FileTarget;
 use crate::core::window::{DropZone, GroupId, SplitDirection, WindowId, WindowRect};
 use crate::icons;
 use crate::render::{
@@ -126,7 +131,8 @@ struct WinSidebar {
     expanded: HashSet<PathBuf>,
     selected: usize,
     scroll_top: usize,
-    show_hidden: bool,
+    /// What the explorer hides (mirrors the dotfile / ignore settings).
+    filter: ExplorerFilterConfig,
     sort_case_insensitive: bool,
     /// Set when tree needs to be rebuilt (file opened, folder toggled, etc.)
     dirty: bool,
@@ -151,7 +157,7 @@ impl WinSidebar {
             expanded: HashSet::new(),
             selected: 0,
             scroll_top: 0,
-            show_hidden: false,
+            filter: ExplorerFilterConfig::default(),
             sort_case_insensitive: true,
             dirty: true,
             has_focus: false,
@@ -172,6 +178,8 @@ impl WinSidebar {
     }
 
     fn build_rows(&mut self, root: &Path) {
+        // Keep the selection on the same path when rows come and go above it.
+        let selected_path = self.rows.get(self.selected).map(|r| r.path.clone());
         self.rows.clear();
         let root_name = root
             .file_name()
@@ -190,11 +198,14 @@ impl WinSidebar {
                 root,
                 1,
                 &self.expanded,
-                self.show_hidden,
+                &mut ExplorerFilter::new(&self.filter),
                 self.sort_case_insensitive,
                 &mut self.rows,
             );
         }
+        if let Some(idx) = selected_path.and_then(|p| self.rows.iter().position(|r| r.path == p)) {
+            self.selected = idx;
+        }
         if !self.rows.is_empty() && self.selected >= self.rows.len() {
             self.selected = self.rows.len() - 1;
         }
@@ -217,39 +228,12 @@ fn collect_explorer_rows(
     dir: &Path,
     depth: usize,
     expanded: &HashSet<PathBuf>,
-    show_hidden: bool,
+    filter: &mut ExplorerFilter,
     case_insensitive: bool,
     out: &mut Vec<ExplorerRow>,
 ) {
-    let entries = match std::fs::read_dir(dir) {
-        Ok(e) => e,
-        Err(_) => return,
-    };
-    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
-    entries.sort_by(|a, b| {
-        let ad = a.path().is_dir();
-        let bd = b.path().is_dir();
-        match (ad, bd) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => {
-                if case_insensitive {
-                    let an = a.file_name().to_string_lossy().to_lowercase();
-                    let bn = b.file_name().to_string_lossy().to_lowercase();
-                    an.cmp(&bn)
-                } else {
-                    a.file_name().cmp(&b.file_name())
-                }
-            }
-        }
-    });
-    for entry in entries {
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
-        if name.starts_with('.') && !show_hidden {
-            continue;
-        }
-        let is_dir = path.is_dir();
+    for entry in read_dir_entries(dir, filter, case_insensitive) {
+        let ExplorerEntry { path, name, is_dir } = entry;
         let is_expanded = is_dir && expanded.contains(&path);
         out.push(ExplorerRow {
             depth,
@@ -259,14 +243,7 @@ fn collect_explorer_rows(
             is_expanded,
         });
         if is_expanded {
-            collect_explorer_rows(
-                &path,
-                depth + 1,
-                expanded,
-                show_hidden,
-                case_insensitive,
-                out,
-            );
+            collect_explorer_rows(&path, depth + 1, expanded, filter, case_insensitive, out);
         }
     }
 }
@@ -359,6 +336,8 @@ struct AppState {
 
     // ── Periodic refresh ─────────────────────────────────────────────────
     last_sidebar_refresh: Instant,
+    /// Reports explorer directories that changed on disk.
+    explorer_watcher: ExplorerWatcher,
 
     // ── Layout cache ─────────────────────────────────────────────────────
     /// Bottom chrome height in DIPs (status bar + cmd line + terminal + above-terminal status)
@@ -460,7 +439,7 @@ fn register_user_font(font_path: &std::path::Path) {
 
 // ─── Entry point ────────────────────────────────────────────────────────────
 
-pub fn run(file_path: Option<PathBuf>) {
+pub fn run(file_target: Option<FileTarget>, session: Option<String>) {
     // Initialize COM (needed for native file dialogs, etc.)
     unsafe {
         let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE);
@@ -492,15 +471,16 @@ pub fn run(file_path: Option<PathBuf>) {
                 .to_string();
     }
 
-    if let Some(path) = file_path {
-        if path.is_dir() {
-            engine.open_folder(&path);
-        } else {
-            let _ = engine.open_file_with_mode(&path, OpenMode::Permanent);
-        }
+    if let Some(ref name) = session {
+        engine.source_session(name);
+    } else if let Some(target) = file_target {
+        engine.open_file_target(&target);
     } else {
         engine.restore_session_files();
     }
+    // Started on a directory: show the explorer focused, like netrw.
+    let explorer_on_start = std::mem::take(&mut engine.explorer_focus_on_start);
+    engine.explorer_has_focus = explorer_on_start;
 
     // Windows clipboard: use the same powershell-based clipboard as TUI
     setup_win_clipboard(&mut engine);
@@ -678,7 +658,11 @@ pub fn run(file_path: Option<PathBuf>) {
             cached_dividers: Vec::new(),
             cached_find_replace_rect: None,
             fr_input_dragging: false,
-            sidebar: WinSidebar::new(),
+            sidebar: WinSidebar {
+                visible: explorer_on_start,
+                has_focus: explorer_on_start,
+                ..WinSidebar::new()
+            },
             current_colorscheme: initial_colorscheme,
             current_font_size: initial_font_size,
             mouse_text_drag: false,
@@ -696,6 +680,7 @@ pub fn run(file_path: Option<PathBuf>) {
             last_clipboard_register: None,
             tab_tooltip_x: 0.0,
             last_sidebar_refresh: Instant::now(),
+            explorer_watcher: ExplorerWatcher::new(),
             bottom_chrome_px: 0.0,
             popup_rects: CachedPopupRects::default(),
             caption_hover: None,
@@ -941,6 +926,13 @@ unsafe fn wnd_proc_inner(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -
             on_dpi_changed(hwnd, wparam, lparam);
             LRESULT(0)
         }
+        WM_ACTIVATE => {
+            // Switching to another application — run `autosave=focus`.
+            if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE {
+                on_focus_lost(hwnd);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
         WM_CLOSE => {
             // Check for unsaved changes before closing
             let has_unsaved = APP.with(|app| {
@@ -1196,6 +1188,7 @@ fn on_paint(hwnd: HWND) {
 
         // Update viewports for all windows based on their rects
         for (wid, wrect) in &window_rects {
+            let wrect = render::winbar_content_rect(&state.engine, *wrect, lh);
             let vp_lines = (wrect.height / lh).floor() as usize;
             let vp_cols = (wrect.width / cw).floor() as usize;
             state
@@ -1203,15 +1196,22 @@ fn on_paint(hwnd: HWND) {
                 .set_viewport_for_window(*wid, vp_lines.saturating_sub(1), vp_cols);
         }
 
+        // Re-root the explorer at the new cwd after `:cd`.
+        if std::mem::take(&mut state.engine.cwd_changed) {
+            state.sidebar.expanded = HashSet::from([state.engine.cwd.clone()]);
+            state.sidebar.selected = 0;
+            state.sidebar.scroll_top = 0;
+            state.sidebar.dirty = true;
+        }
+
         // Rebuild explorer rows only when dirty
         if state.sidebar.visible
             && state.sidebar.active_panel == SidebarPanel::Explorer
             && state.sidebar.dirty
         {
-            if let Some(ref root) = state.engine.workspace_root.clone() {
-                state.sidebar.build_rows(root);
-                state.sidebar.dirty = false;
-            }
+            let root = state.engine.cwd.clone();
+            state.sidebar.build_rows(&root);
+            state.sidebar.dirty = false;
         }
 
         let screen = build_screen_layout(&state.engine, &state.theme, &window_rects, lh, cw, true);
@@ -1626,6 +1626,18 @@ fn cache_layout(
     }
 }
 
+fn on_focus_lost(hwnd: HWND) {
+    APP.with(|app| {
+        let mut app = app.borrow_mut();
+        let state = app.as_mut().expect("AppState");
+        if state.engine.on_focus_lost() {
+            unsafe {
+                let _ = InvalidateRect(Some(hwnd), None, false);
+            }
+        }
+    });
+}
+
 fn on_resize(hwnd: HWND) {
     APP.with(|app| {
         let mut app = app.borrow_mut();
@@ -1866,9 +1878,7 @@ fn on_key_down(wparam: WPARAM, _lparam: LPARAM) -> bool {
                 state.sidebar.visible = true;
                 state.sidebar.dirty = true;
                 if state.sidebar.expanded.is_empty() {
-                    if let Some(ref root) = state.engine.workspace_root.clone() {
-                        state.sidebar.expanded.insert(root.clone());
-                    }
+                    state.sidebar.expanded.insert(state.engine.cwd.clone());
                 }
             }
             state.sidebar.has_focus = !state.sidebar.has_focus;
@@ -2145,10 +2155,10 @@ fn on_key_down(wparam: WPARAM, _lparam: LPARAM) -> bool {
                     }
                     "Return" => {
                         if state.sidebar.replace_input_focused {
-                            let root = state.engine.workspace_root.clone().unwrap_or_default();
+                            let root = state.engine.cwd.clone();
                             state.engine.start_project_replace(root);
                         } else {
-                            let root = state.engine.workspace_root.clone().unwrap_or_default();
+                            let root = state.engine.cwd.clone();
                             state.engine.start_project_search(root);
                             state.sidebar.search_scroll_top = 0;
                         }
@@ -2393,9 +2403,8 @@ fn on_key_down(wparam: WPARAM, _lparam: LPARAM) -> bool {
                             let path = state.sidebar.rows[idx].path.clone();
                             if is_dir {
                                 state.sidebar.toggle_expand(idx);
-                                if let Some(ref root) = state.engine.workspace_root.clone() {
-                                    state.sidebar.build_rows(root);
-                                }
+                                let root = state.engine.cwd.clone();
+                                state.sidebar.build_rows(&root);
                             } else {
                                 state.engine.open_file_in_tab(&path);
                                 state.sidebar.has_focus = false;
@@ -2413,9 +2422,8 @@ fn on_key_down(wparam: WPARAM, _lparam: LPARAM) -> bool {
                             && state.sidebar.rows[idx].is_expanded
                         {
                             state.sidebar.toggle_expand(idx);
-                            if let Some(ref root) = state.engine.workspace_root.clone() {
-                                state.sidebar.build_rows(root);
-                            }
+                            let root = state.engine.cwd.clone();
+                            state.sidebar.build_rows(&root);
                         }
                     }
                     true
@@ -2927,9 +2935,8 @@ fn on_char(wparam: WPARAM) {
                             if is_dir {
                                 if !state.sidebar.rows[idx].is_expanded {
                                     state.sidebar.toggle_expand(idx);
-                                    if let Some(ref root) = state.engine.workspace_root.clone() {
-                                        state.sidebar.build_rows(root);
-                                    }
+                                    let root = state.engine.cwd.clone();
+                                    state.sidebar.build_rows(&root);
                                 }
                             } else {
                                 state.engine.open_file_in_tab(&path);
@@ -2947,9 +2954,8 @@ fn on_char(wparam: WPARAM) {
                             && state.sidebar.rows[idx].is_expanded
                         {
                             state.sidebar.toggle_expand(idx);
-                            if let Some(ref root) = state.engine.workspace_root.clone() {
-                                state.sidebar.build_rows(root);
-                            }
+                            let root = state.engine.cwd.clone();
+                            state.sidebar.build_rows(&root);
                         }
                     }
                     true
@@ -3101,7 +3107,23 @@ fn pixel_to_editor_pos(state: &AppState, px: f32, py: f32) -> Option<(WindowId,
 
             let text_x = px - rx - gutter_px;
             let scroll_left = w.map_or(0, |w| w.view.scroll_left);
-            let col = (text_x / cw).max(0.0).floor() as usize + scroll_left;
+            let display_col = (text_x / cw).max(0.0).floor() as usize;
+            // Map the display column back to a char column, expanding tabs
+            // to the next `tabstop` multiple exactly like the renderer does.
+            let buffer_state = w.and_then(|w| state.engine.buffer_manager.get(w.buffer_id));
+            let line_text = buffer_state
+                .filter(|bs| buf_line < bs.buffer.content.len_lines())
+                .map(|bs| bs.buffer.content.line(buf_line).to_string())
+                .unwrap_or_default();
+            let tabstop = buffer_state
+                .map(|bs| bs.tabstop(&state.engine.settings))
+                .unwrap_or(state.engine.settings.tabstop.max(1) as usize);
+            let col = render::display_col_to_buffer_col(
+                line_text.trim_end_matches(['\n', '\r']),
+                display_col,
+                tabstop,
+                scroll_left,
+            );
 
             return Some((cwr.window_id, buf_line, col));
         }
@@ -3109,6 +3131,30 @@ fn pixel_to_editor_pos(state: &AppState, px: f32, py: f32) -> Option<(WindowId,
     None
 }
 
+/// The sticky scroll header under (px, py), if any: (window, header line).
+fn sticky_line_hit(state: &AppState, px: f32, py: f32) -> Option<(WindowId, usize)> {
+    let lh = state.line_height;
+    let cwr = state.cached_window_rects.iter().find(|cwr| {
+        let r = &cwr.rect;
+        px >= r.x as f32
+            && px < (r.x + r.width) as f32
+            && py >= r.y as f32
+            && py < (r.y + r.height) as f32
+    })?;
+    let status_h = if state.engine.settings.window_status_line {
+        lh
+    } else {
+        0.0
+    };
+    let rows = ((cwr.rect.height as f32 - status_h) / lh).floor().max(0.0) as usize;
+    let view_row = ((py - cwr.rect.y as f32) / lh).floor().max(0.0) as usize;
+    let line = *state
+        .engine
+        .sticky_scroll_lines(cwr.window_id, rows)
+        .get(view_row)?;
+    Some((cwr.window_id, line))
+}
+
 /// Check if (px, py) is inside a scrollbar track; if so, return the window ID
 /// and the scroll_top that corresponds to clicking at that Y position.
 fn scrollbar_hit(state: &AppState, px: f32, py: f32) -> Option<(WindowId, usize)> {
@@ -3552,9 +3598,7 @@ fn on_mouse_down(hwnd: HWND, lparam: LPARAM) {
                     }
                     if clicked_panel == SidebarPanel::Explorer && state.sidebar.expanded.is_empty()
                     {
-                        if let Some(ref root) = state.engine.workspace_root.clone() {
-                            state.sidebar.expanded.insert(root.clone());
-                        }
+                        state.sidebar.expanded.insert(state.engine.cwd.clone());
                     }
                 }
             } else {
@@ -3607,9 +3651,8 @@ fn on_mouse_down(hwnd: HWND, lparam: LPARAM) {
                         let path = state.sidebar.rows[vis_idx].path.clone();
                         if is_dir {
                             state.sidebar.toggle_expand(vis_idx);
-                            if let Some(ref root) = state.engine.workspace_root.clone() {
-                                state.sidebar.build_rows(root);
-                            }
+                            let root = state.engine.cwd.clone();
+                            state.sidebar.build_rows(&root);
                         } else {
                             state.engine.open_file_preview(&path);
                         }
@@ -4514,7 +4557,9 @@ fn on_mouse_down(hwnd: HWND, lparam: LPARAM) {
         state.sidebar.has_focus = false;
         state.engine.terminal_has_focus = false;
         state.engine.clear_sidebar_focus();
-        if let Some((wid, line, col)) = pixel_to_editor_pos(state, px, py) {
+        if let Some((wid, line)) = sticky_line_hit(state, px, py) {
+            state.engine.sticky_scroll_jump(wid, line);
+        } else if let Some((wid, line, col)) = pixel_to_editor_pos(state, px, py) {
             // Clear VSCode selection on click (matching GTK behavior)
             if state.engine.is_vscode_mode() {
                 state.engine.vscode_clear_selection();
@@ -5549,13 +5594,11 @@ fn on_right_click(hwnd: HWND, lparam: LPARAM) {
                     state
                         .engine
                         .open_explorer_context_menu(path, is_dir, screen_col, screen_row);
-                } else if let Some(ref root) = state.engine.workspace_root.clone() {
-                    state.engine.open_explorer_context_menu(
-                        root.clone(),
-                        true,
-                        screen_col,
-                        screen_row,
-                    );
+                } else {
+                    let root = state.engine.cwd.clone();
+                    state
+                        .engine
+                        .open_explorer_context_menu(root, true, screen_col, screen_row);
                 }
             }
             unsafe {
@@ -5740,11 +5783,12 @@ fn on_mouse_wheel(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) {
 }
 
 fn on_tick(hwnd: HWND) {
-    APP.with(|app| {
+    let should_quit = APP.with(|app| {
         let mut app = app.borrow_mut();
         let state = app.as_mut().expect("AppState");
 
         let mut needs_redraw = false;
+        let mut quit = false;
 
         // Poll LSP
         if state.engine.poll_lsp() {
@@ -5779,14 +5823,68 @@ fn on_tick(hwnd: HWND) {
             needs_redraw = true;
         }
 
+        // Resolve an ambiguous user-keymap prefix after `timeoutlen`
+        if let Some(action) = state.engine.tick_keymap_timeout() {
+            needs_redraw = true;
+            quit = handle_action_with_sidebar(state, action);
+        }
+
+        // Show or hide the which-key popup for a pending prefix
+        if state.engine.tick_which_key() {
+            needs_redraw = true;
+        }
+
+        // Highlight the word under the cursor once it settles
+        if state.engine.tick_word_highlight() {
+            needs_redraw = true;
+        }
+
+        // Fetch documentation for the selected completion item
+        state.engine.tick_completion_resolve();
+
         // Swap file periodic writes
         state.engine.tick_swap_files();
 
+        // Auto-save after a pause in typing
+        if state.engine.tick_autosave() {
+            needs_redraw = true;
+        }
+
         // Poll for completed async shell tasks (plugin background commands)
         if state.engine.poll_async_shells() {
             needs_redraw = true;
         }
 
+        // Collect background project search results and live progress
+        if state.engine.poll_project_search() || state.engine.tick_project_search_progress() {
+            needs_redraw = true;
+        }
+
+        // Apply output of finished :! / :r ! / filter commands
+        if state.engine.poll_shell_commands() {
+            needs_redraw = true;
+        }
+
+        // Show large files read in the background
+        if state.engine.poll_file_loads() {
+            needs_redraw = true;
+        }
+
+        // Poll async blame results
+        if state.engine.poll_blame() {
+            needs_redraw = true;
+        }
+
+        // Start debounced live-grep searches and stream in their matches
+        if state.engine.poll_picker_grep() {
+            needs_redraw = true;
+        }
+
+        // Show file picker previews loaded in the background
+        if state.engine.poll_picker_preview() {
+            needs_redraw = true;
+        }
+
         // Check for panel reveal request from plugins
         if let Some(panel_name) = state.engine.ext_panel_focus_pending.take() {
             state.sidebar.ext_panel_name = Some(panel_name);
@@ -5805,12 +5903,36 @@ fn on_tick(hwnd: HWND) {
             state.engine.sc_refresh();
             state.sidebar.dirty = true;
             state.last_sidebar_refresh = Instant::now();
+            // Reload the project .vimcode.json when it changes on disk.
+            state.engine.check_project_settings_changed();
             needs_redraw = true;
         }
 
         // File watcher (external modification detection)
         state.engine.tick_file_watcher();
 
+        // Rebuild the explorer as soon as its filter settings change.
+        let filter = ExplorerFilterConfig::from_settings(&state.engine.settings);
+        if filter != state.sidebar.filter {
+            state.sidebar.filter = filter;
+            state.sidebar.dirty = true;
+            needs_redraw = true;
+        }
+
+        // Rebuild the explorer when a directory it shows changes on disk.
+        state.explorer_watcher.set_dirs(
+            state
+                .sidebar
+                .rows
+                .iter()
+                .filter(|r| r.is_expanded)
+                .map(|r| r.path.as_path()),
+        );
+        if !state.explorer_watcher.poll().is_empty() {
+            state.sidebar.dirty = true;
+            needs_redraw = true;
+        }
+
         // Poll for external git branch changes (rate-limited to once per 2s inside).
         if state.engine.tick_git_branch() {
             needs_redraw = true;
@@ -5859,7 +5981,14 @@ fn on_tick(hwnd: HWND) {
                 let _ = InvalidateRect(Some(hwnd), None, false);
             }
         }
+        quit
     });
+
+    if should_quit {
+        unsafe {
+            let _ = DestroyWindow(hwnd);
+        }
+    }
 }
 
 /// Update the Win32 window title to reflect the current file and dirty state.
@@ -6008,9 +6137,7 @@ fn handle_action_with_sidebar(state: &mut AppState, action: EngineAction) -> boo
             state.sidebar.visible = !state.sidebar.visible;
             state.sidebar.dirty = true;
             if state.sidebar.visible && state.sidebar.expanded.is_empty() {
-                if let Some(ref root) = state.engine.workspace_root.clone() {
-                    state.sidebar.expanded.insert(root.clone());
-                }
+                state.sidebar.expanded.insert(state.engine.cwd.clone());
             }
         }
         EngineAction::OpenFolderDialog => {
@@ -9,7 +9,7 @@
 // Build as a Windows GUI application (no console window).
 #![windows_subsystem = "windows"]
 
-use std::path::PathBuf;
+use std::collections::HashSet;
 
 mod core;
 mod icons;
@@ -25,12 +25,19 @@ fn main() {
         return;
     }
 
-    // First positional argument (not starting with '-')
-    let file_path = args
+    // --session <name>: restore a session saved with :mksession
+    let session = args
         .iter()
-        .skip(1)
-        .find(|a| !a.starts_with('-'))
-        .map(PathBuf::from);
+        .position(|a| a == "--session")
+        .and_then(|i| args.get(i + 1))
+        .cloned();
 
-    win_gui::run(file_path);
+    // First positional argument (not starting with '-' or '+', not a --session value)
+    let skip_args: HashSet<usize> = match args.iter().position(|a| a == "--session") {
+        Some(i) => HashSet::from([i, i + 1]),
+        None => HashSet::new(),
+    };
+    let file_target = core::paths::file_target_from_args(&args, &skip_args);
+
+    win_gui::run(file_target, session);
 }
@@ -153,6 +153,53 @@ fn test_percent_empty_parens() {
     assert_cursor(&e, 0, 0);
 }
 
+#[test]
+fn test_percent_skips_brackets_in_string_span() {
+    // fn f(")") — the ')' inside the string must not end the call parens.
+    let mut e = engine_with("f(\")\")\n");
+    e.active_buffer_state_mut().highlights = vec![(2, 5, "string".to_string())];
+    press(&mut e, 'l');
+    press(&mut e, '%');
+    assert_cursor(&e, 0, 5);
+    press(&mut e, '%');
+    assert_cursor(&e, 0, 1);
+}
+
+#[test]
+fn test_percent_skips_brackets_in_comment_span() {
+    let mut e = engine_with("{ // }\n}\n");
+    e.active_buffer_state_mut().highlights = vec![(2, 6, "comment".to_string())];
+    press(&mut e, '%');
+    assert_cursor(&e, 1, 0);
+}
+
+#[test]
+fn test_percent_inside_string_matches_naively() {
+    // Starting on a bracket inside a string: no skipping, plain nesting.
+    let mut e = engine_with("\"(a)\"\n");
+    e.active_buffer_state_mut().highlights = vec![(0, 5, "string".to_string())];
+    press(&mut e, 'l');
+    press(&mut e, '%');
+    assert_cursor(&e, 0, 3);
+}
+
+#[test]
+fn test_percent_scan_is_bounded() {
+    // The match lies beyond the scan limit, so % leaves the cursor alone.
+    let text = format!("({})\n", "x".repeat(20_000));
+    let mut e = engine_with(&text);
+    press(&mut e, '%');
+    assert_cursor(&e, 0, 0);
+}
+
+#[test]
+fn test_bracket_match_highlight_skips_string_span() {
+    let mut e = engine_with("(\")\")\n");
+    e.active_buffer_state_mut().highlights = vec![(1, 4, "string".to_string())];
+    e.update_bracket_match();
+    assert_eq!(e.bracket_match, Some((0, 4)));
+}
+
 // ── Operator motions with % ─────────────────────────────────────────────────
 
 #[test]
@@ -178,6 +225,25 @@ fn test_d_percent_from_close() {
     assert_cursor(&e, 0, 0);
 }
 
+#[test]
+fn test_d_percent_skips_brackets_in_string_span() {
+    let mut e = engine_with("f(\")\") x\n");
+    e.active_buffer_state_mut().highlights = vec![(2, 5, "string".to_string())];
+    press(&mut e, 'l');
+    press(&mut e, 'd');
+    press(&mut e, '%');
+    assert_buf(&e, "f x\n");
+}
+
+#[test]
+fn test_d_percent_scan_is_bounded() {
+    let text = format!("({})\n", "x".repeat(20_000));
+    let mut e = engine_with(&text);
+    press(&mut e, 'd');
+    press(&mut e, '%');
+    assert_buf(&e, &text);
+}
+
 #[test]
 fn test_d_percent_curly() {
     let mut e = engine_with("before{inside}after\n");
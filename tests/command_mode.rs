@@ -188,6 +188,13 @@ fn test_colorscheme_alias_gruvbox() {
     );
 }
 
+#[test]
+fn test_colorscheme_alias_light() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "colorscheme light");
+    assert_eq!(e.settings.colorscheme, "vscode-light");
+}
+
 #[test]
 fn test_colorscheme_set_tokyo_night() {
     let mut e = engine_with("hello\n");
@@ -20,6 +20,8 @@ pub fn engine_with(text: &str) -> Engine {
     e.settings = vimcode_core::Settings::default();
     e.extension_state = vimcode_core::core::session::ExtensionState::default();
     e.ext_registry = None;
+    // Registers restored from the last session would vary between machines.
+    e.registers.clear();
     // Reset mode + keymaps in case Engine::new() loaded VSCode mode from disk.
     e.mode = Mode::Normal;
     e.rebuild_user_keymaps();
@@ -80,6 +82,20 @@ pub fn drain_macro_queue(e: &mut Engine) {
     }
 }
 
+/// Poll until every background `:!` command has been applied (the UI
+/// normally polls on each tick).
+pub fn wait_for_shell_commands(e: &mut Engine) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while e.shell_commands_pending() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "shell command timed out"
+        );
+        e.poll_shell_commands();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+}
+
 // ── Buffer state ───────────────────────────────────────────────────────────────
 
 pub fn buf(e: &Engine) -> String {
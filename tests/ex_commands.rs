@@ -31,7 +31,8 @@ fn normalizer_di_to_display() {
 fn normalizer_te_to_terminal() {
     let mut e = engine_with("hello\n");
     let act = exec(&mut e, "te");
-    assert_eq!(act, EngineAction::OpenTerminal);
+    assert_eq!(act, EngineAction::None);
+    assert!(e.terminal_buffers.contains_key(&e.active_buffer_id()));
 }
 
 #[test]
@@ -178,6 +179,29 @@ fn normalizer_e_to_edit() {
     let _ = std::fs::remove_file(&path);
 }
 
+#[test]
+fn edit_with_line_and_column_suffix() {
+    let mut e = engine_with("hello\n");
+    let path = std::env::temp_dir().join("vimcode_test_e_position.txt");
+    let text: String = (1..=50).map(|n| format!("    line {n}\n")).collect();
+    std::fs::write(&path, text).unwrap();
+
+    let act = exec(&mut e, &format!("e {}:30:7", path.display()));
+    assert_eq!(act, EngineAction::None);
+    assert!(e
+        .file_path()
+        .is_some_and(|p| p.ends_with("vimcode_test_e_position.txt")));
+    assert_cursor(&e, 29, 6);
+
+    // Without a column the cursor lands on the first non-blank; past the
+    // end it clamps to the last line.
+    exec(&mut e, &format!("e {}:12", path.display()));
+    assert_cursor(&e, 11, 4);
+    exec(&mut e, &format!("e {}:999", path.display()));
+    assert_cursor(&e, 49, 4);
+    let _ = std::fs::remove_file(&path);
+}
+
 #[test]
 fn normalizer_sp_to_split() {
     let mut e = engine_with("hello\n");
@@ -510,6 +534,50 @@ fn cmd_windo() {
     assert_eq!(e.settings.tabstop, 3);
 }
 
+#[test]
+fn cmd_windo_restores_active_window() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "vsplit");
+    let original = e.active_window_id();
+    exec(&mut e, "windo normal! x");
+    assert_eq!(e.active_window_id(), original);
+    // Both windows share the buffer, so x ran twice.
+    assert_eq!(get_lines(&e)[0], "llo");
+}
+
+#[test]
+fn cmd_windo_collects_errors() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "vsplit");
+    let act = exec(&mut e, "windo notacommand");
+    assert_eq!(act, EngineAction::Error);
+    assert_msg_contains(&e, "windo: 2 errors");
+}
+
+#[test]
+fn cmd_bufdo_runs_in_every_buffer_and_restores() {
+    let mut e = engine_with("foo one\n");
+    let first = e.active_buffer_id();
+    exec(&mut e, "enew");
+    e.buffer_mut().insert(0, "foo two\n");
+    exec(&mut e, "bufdo s/foo/bar/");
+    // Back on the buffer we were on when :bufdo started.
+    assert_ne!(e.active_buffer_id(), first);
+    assert_eq!(get_lines(&e)[0], "bar two");
+    exec(&mut e, "b 1");
+    assert_eq!(e.active_buffer_id(), first);
+    assert_eq!(get_lines(&e)[0], "bar one");
+}
+
+#[test]
+fn cmd_bufdo_reports_error_summary() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "enew");
+    let act = exec(&mut e, "bufdo notacommand");
+    assert_eq!(act, EngineAction::Error);
+    assert_msg_contains(&e, "bufdo: 2 errors");
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 //  Group 3: Existing command abbreviation tests
 // ═══════════════════════════════════════════════════════════════════════════════
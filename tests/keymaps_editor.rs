@@ -96,8 +96,8 @@ fn save_keymaps_buffer_rejects_invalid_lines() {
     let mut e = engine_with("hello\n");
     exec(&mut e, "Keymaps");
 
-    // Invalid keymap (no colon prefix on command)
-    e.active_buffer_state_mut().buffer.content = ropey::Rope::from_str("n K join\n");
+    // Invalid keymap (unknown mode)
+    e.active_buffer_state_mut().buffer.content = ropey::Rope::from_str("x K :join\n");
     e.active_buffer_state_mut().dirty = true;
 
     let result = e.save();
@@ -131,7 +131,7 @@ fn save_keymaps_buffer_rebuilds_user_keymaps() {
 
     // User keymaps should be rebuilt
     assert_eq!(e.user_keymaps.len(), 1);
-    assert_eq!(e.user_keymaps[0].action, "join");
+    assert_eq!(e.user_keymaps[0].action.to_string(), ":join");
 }
 
 #[test]
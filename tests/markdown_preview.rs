@@ -58,7 +58,7 @@ fn read_only_blocks_insert_mode() {
             "key '{ch}' should not enter insert mode in read-only"
         );
         assert!(
-            e.message.contains("read-only"),
+            e.message.contains("E45"),
             "key '{ch}' should show read-only message, got: {:?}",
             e.message
         );
@@ -252,7 +252,7 @@ fn netrw_is_read_only() {
 
     // Try to enter insert mode
     press(&mut e, 'i');
-    assert_msg_contains(&e, "read-only");
+    assert_msg_contains(&e, "E45");
 
     cleanup(&dir);
 }
@@ -431,3 +431,38 @@ fn test_swap_no_recovery_when_content_matches_disk() {
     let _ = fs::remove_file(&swap_path);
     let _ = fs::remove_file(&path);
 }
+
+// ── 15. Warning when the file is newer than the swap ────────────────────────
+
+#[test]
+fn test_swap_recovery_warns_when_file_is_newer() {
+    let path = temp_file("newer.rs", "saved elsewhere\n");
+    let canonical_path = canonical(&path);
+    let swap_path = swap::swap_path_for(&canonical_path);
+
+    // The swap records an mtime long before the file's current one.
+    fs::create_dir_all(swap_path.parent().unwrap()).unwrap();
+    {
+        let mut f = fs::File::create(&swap_path).unwrap();
+        writeln!(f, "VIMCODE_SWAP_V1").unwrap();
+        writeln!(f, "path: {}", canonical_path.display()).unwrap();
+        writeln!(f, "pid: 999999999").unwrap(); // dead PID
+        writeln!(f, "modified: 2026-01-01T00:00:00Z").unwrap();
+        writeln!(f, "mtime: 1000").unwrap();
+        writeln!(f, "---").unwrap();
+        writeln!(f, "unsaved edits").unwrap();
+    }
+
+    let mut e = engine_with("");
+    e.open_file_in_tab(&path);
+
+    let dialog = e.dialog.as_ref().expect("recovery dialog");
+    assert!(
+        dialog.body.iter().any(|l| l.contains("changed on disk")),
+        "dialog should warn that the file is newer: {:?}",
+        dialog.body
+    );
+
+    let _ = fs::remove_file(&swap_path);
+    let _ = fs::remove_file(&path);
+}
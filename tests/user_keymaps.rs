@@ -133,7 +133,7 @@ fn invalid_keymap_definitions_ignored() {
         &[
             "x K :join", // invalid mode
             "n K",       // missing action
-            "n K join",  // action missing ':'
+            "n K :",     // empty command
             "",          // empty
             "n",         // incomplete
         ],
@@ -172,6 +172,7 @@ fn test_filter_command_execution() {
     let mut e = engine_with("cherry\napple\nbanana\n");
     // Sort lines 1-3 using shell command
     run_cmd(&mut e, "1,3!sort");
+    wait_for_shell_commands(&mut e);
     // Lines should be sorted alphabetically
     assert_buf(&e, "apple\nbanana\ncherry\n");
 }
@@ -344,6 +345,7 @@ fn test_filter_command_reverse() {
         "1,3!tac"
     };
     run_cmd(&mut e, cmd);
+    wait_for_shell_commands(&mut e);
     assert_buf(&e, "cherry\nbanana\napple\n");
 }
 
@@ -389,6 +391,55 @@ fn test_visual_block_a_on_empty_buffer() {
     assert_buf(&e, "aZ\nbZ\ncZ\n");
 }
 
+#[test]
+fn test_visual_block_i_skips_short_lines() {
+    let mut e = engine_with("abcdef\nab\nabcdef\n");
+    type_chars(&mut e, "3l");
+    e.handle_key("v", Some('v'), true); // Ctrl-V, anchor=(0,3)
+    type_chars(&mut e, "2j03l"); // cursor at (2,3): block column 3
+    type_chars(&mut e, "I|");
+    press_key(&mut e, "Escape");
+    // "ab" ends before the block column, so it is left untouched
+    assert_buf(&e, "abc|def\nab\nabc|def\n");
+}
+
+#[test]
+fn test_visual_block_a_pads_short_lines() {
+    let mut e = engine_with("abcdef\nab\nabcdef\n");
+    type_chars(&mut e, "3l");
+    e.handle_key("v", Some('v'), true); // Ctrl-V, anchor=(0,3)
+    type_chars(&mut e, "2j03l");
+    type_chars(&mut e, "A|");
+    press_key(&mut e, "Escape");
+    assert_buf(&e, "abcd|ef\nab  |\nabcd|ef\n");
+}
+
+#[test]
+fn test_visual_block_a_pads_short_first_line() {
+    let mut e = engine_with("ab\nabcdef\n");
+    type_chars(&mut e, "j3l");
+    e.handle_key("v", Some('v'), true); // Ctrl-V, anchor=(1,3)
+    type_chars(&mut e, "k"); // cursor clamps to (0,1); block right edge stays 3
+    type_chars(&mut e, "A|");
+    press_key(&mut e, "Escape");
+    assert_buf(&e, "ab  |\nabcd|ef\n");
+}
+
+#[test]
+fn test_visual_block_insert_single_undo() {
+    let mut e = engine_with("abcdef\nab\nabcdef\n");
+    type_chars(&mut e, "3l");
+    e.handle_key("v", Some('v'), true); // Ctrl-V
+    type_chars(&mut e, "2j03l");
+    type_chars(&mut e, "A--");
+    press_key(&mut e, "Escape");
+    assert_buf(&e, "abcd--ef\nab  --\nabcd--ef\n");
+    type_chars(&mut e, "u");
+    assert_buf(&e, "abcdef\nab\nabcdef\n");
+    e.handle_key("r", Some('r'), true); // Ctrl-R
+    assert_buf(&e, "abcd--ef\nab  --\nabcd--ef\n");
+}
+
 // =============================================================================
 // Bug fix: :q on dirty buffer in split should NOT block when another window
 // still shows the same buffer.
@@ -492,6 +543,70 @@ fn test_check_file_changes_warns_dirty_buffer() {
     let _ = std::fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn test_checktime_reloads_and_keeps_cursor() {
+    let dir = std::env::temp_dir().join("vimcode_test_checktime");
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join("checktime.txt");
+    std::fs::write(&path, "one\ntwo\nthree\nfour\n").unwrap();
+
+    let mut e = engine_with("");
+    e.settings.autoread = false;
+    e.open_file_in_tab(&path);
+    e.view_mut().cursor.line = 2;
+    e.view_mut().cursor.col = 3;
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(&path, "one\ntwo\n3\nfour\nfive\n").unwrap();
+
+    // autoread is off, so only :checktime picks the change up.
+    assert!(!e.check_file_changes());
+    exec(&mut e, "checktime");
+    assert_eq!(e.buffer().to_string(), "one\ntwo\n3\nfour\nfive\n");
+    assert!(e.message.contains("reloaded"));
+    assert_eq!(e.view().cursor.line, 2);
+    assert_eq!(e.view().cursor.col, 0, "clamped to the shorter line");
+
+    exec(&mut e, "checktime");
+    assert!(e.message.contains("No files changed"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_write_refuses_to_clobber_external_change() {
+    let dir = std::env::temp_dir().join("vimcode_test_clobber");
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join("clobber.txt");
+    std::fs::write(&path, "original\n").unwrap();
+
+    let mut e = engine_with("");
+    e.open_file_in_tab(&path);
+    type_chars(&mut e, "iX");
+    press_key(&mut e, "Escape");
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(&path, "external\n").unwrap();
+
+    exec(&mut e, "write");
+    assert!(e.message.contains("changed on disk"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "external\n");
+    assert!(e.dirty());
+
+    // :wa and :wqa take the same guard, and :wqa doesn't quit.
+    assert_eq!(exec(&mut e, "wall"), vimcode_core::EngineAction::Error);
+    assert!(e.message.contains("changed on disk"));
+    assert_eq!(exec(&mut e, "wqall"), vimcode_core::EngineAction::Error);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "external\n");
+    assert!(e.dirty());
+
+    exec(&mut e, "write!");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Xoriginal\n");
+    assert!(!e.dirty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 // =============================================================================
 // Bug fix: :new / :split respect splitbelow / splitright
 // =============================================================================
@@ -606,3 +721,54 @@ fn test_edit_bang_reloads_file() {
 
     let _ = std::fs::remove_dir_all(&dir);
 }
+
+#[test]
+fn test_edit_bang_keeps_undo_history_when_file_unchanged() {
+    let dir = std::env::temp_dir().join("vimcode_test_edit_bang_undo");
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join("keep.txt");
+    std::fs::write(&path, "original\n").unwrap();
+
+    let mut e = engine_with("");
+    e.open_file_in_tab(&path);
+    type_chars(&mut e, "iA");
+    press_key(&mut e, "Escape");
+    type_chars(&mut e, "AB");
+    press_key(&mut e, "Escape");
+    assert_buf(&e, "AoriginalB\n");
+
+    // The file is still the undo base: the reload becomes an undo step.
+    exec(&mut e, "edit!");
+    assert_buf(&e, "original\n");
+    assert!(!e.dirty());
+    press(&mut e, 'u');
+    assert_buf(&e, "AoriginalB\n");
+    assert!(e.dirty());
+    press(&mut e, 'u');
+    assert_buf(&e, "Aoriginal\n");
+    ctrl(&mut e, 'r');
+    ctrl(&mut e, 'r');
+    assert_buf(&e, "original\n");
+    assert!(!e.dirty());
+
+    // Saving moves the base; an external change resets the history.
+    type_chars(&mut e, "iC");
+    press_key(&mut e, "Escape");
+    exec(&mut e, "w");
+    type_chars(&mut e, "iD");
+    press_key(&mut e, "Escape");
+    exec(&mut e, "edit!");
+    assert_buf(&e, "Coriginal\n");
+    press(&mut e, 'u');
+    assert_buf(&e, "DCoriginal\n");
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    std::fs::write(&path, "from disk\n").unwrap();
+    exec(&mut e, "edit!");
+    assert_buf(&e, "from disk\n");
+    press(&mut e, 'u');
+    assert_buf(&e, "from disk\n");
+    assert_msg_contains(&e, "oldest change");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -272,17 +272,17 @@ fn set_tab_shows_settings() {
     );
 }
 
-// ── :set w<tab> filters to settings starting with w ─────────────────────
+// ── :set wraps<tab> filters to settings starting with wraps ─────────────
 
 #[test]
 fn set_partial_arg_filters() {
     let mut e = engine_with("hello\n");
     press(&mut e, ':');
-    type_chars(&mut e, "set w");
+    type_chars(&mut e, "set wraps");
     press_key(&mut e, "Tab");
 
-    // "set w" should match "set wrap" (single match → auto-complete)
-    assert_eq!(e.command_buffer, "set wrap");
+    // "set wraps" should match "set wrapscan" (single match → auto-complete)
+    assert_eq!(e.command_buffer, "set wrapscan");
 }
 
 // ── :set no<tab> shows noXxx variants ───────────────────────────────────
@@ -412,3 +412,77 @@ fn trailing_space_item_clears_wildmenu_for_next_tab() {
         "should show setting completions"
     );
 }
+
+// ── File path arguments ─────────────────────────────────────────────────
+
+fn path_completion_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("alps")).unwrap();
+    std::fs::write(dir.join("alpha.rs"), "").unwrap();
+    std::fs::write(dir.join("alps").join("inner.txt"), "").unwrap();
+    std::fs::write(dir.join(".alhidden"), "").unwrap();
+    dir
+}
+
+#[test]
+fn tab_completes_file_paths_for_edit() {
+    let dir = path_completion_dir("vimcode_wildmenu_paths_edit");
+    let base = dir.to_string_lossy().to_string();
+    let mut e = engine_with("hello\n");
+    press(&mut e, ':');
+    type_chars(&mut e, &format!("e {base}/al"));
+    press_key(&mut e, "Tab");
+
+    // Hidden files are skipped; directories get a trailing slash.
+    assert_eq!(
+        e.wildmenu_items,
+        vec![format!("e {base}/alpha.rs"), format!("e {base}/alps/")]
+    );
+    press_key(&mut e, "Tab");
+    assert_eq!(e.command_buffer, format!("e {base}/alpha.rs"));
+    press_key(&mut e, "ISO_Left_Tab");
+    press_key(&mut e, "ISO_Left_Tab");
+    assert_eq!(e.command_buffer, format!("e {base}/alpha.rs"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tab_after_trailing_slash_lists_directory_contents() {
+    let dir = path_completion_dir("vimcode_wildmenu_paths_slash");
+    let base = dir.to_string_lossy().to_string();
+    let mut e = engine_with("hello\n");
+    press(&mut e, ':');
+    type_chars(&mut e, &format!("vsplit {base}/alps/"));
+    press_key(&mut e, "Tab");
+    assert_eq!(e.command_buffer, format!("vsplit {base}/alps/inner.txt"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tab_completes_only_directories_for_cd() {
+    let dir = path_completion_dir("vimcode_wildmenu_paths_cd");
+    let base = dir.to_string_lossy().to_string();
+    let mut e = engine_with("hello\n");
+    press(&mut e, ':');
+    type_chars(&mut e, &format!("cd {base}/al"));
+    press_key(&mut e, "Tab");
+    assert_eq!(e.command_buffer, format!("cd {base}/alps/"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tab_completes_relative_paths_from_window_directory() {
+    let dir = path_completion_dir("vimcode_wildmenu_paths_lcd");
+    let base = dir.to_string_lossy().to_string();
+    let mut e = engine_with("hello\n");
+    exec(&mut e, &format!("lcd {base}"));
+    press(&mut e, ':');
+    type_chars(&mut e, "e al");
+    press_key(&mut e, "Tab");
+    assert_eq!(
+        e.wildmenu_items,
+        vec!["e alpha.rs".to_string(), "e alps/".to_string()]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -8,6 +8,18 @@ fn win_count(e: &vimcode_core::Engine) -> usize {
     e.active_tab().layout.window_ids().len()
 }
 
+/// Top-left corner and size of the active window on a 100×100 screen.
+fn active_rect(e: &vimcode_core::Engine) -> (f64, f64, f64, f64) {
+    use vimcode_core::core::window::WindowRect;
+    let id = e.active_window_id();
+    let rects = e
+        .active_tab()
+        .layout
+        .calculate_rects(WindowRect::new(0.0, 0.0, 100.0, 100.0));
+    let (_, r) = rects.into_iter().find(|(w, _)| *w == id).unwrap();
+    (r.x, r.y, r.width, r.height)
+}
+
 // ── :wincmd ex command ───────────────────────────────────────────────────────
 
 #[test]
@@ -71,6 +83,21 @@ fn wincmd_w_cycles_windows() {
     );
 }
 
+#[test]
+fn wincmd_shift_w_cycles_backwards() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "split");
+    exec(&mut e, "split");
+    let order = e.active_tab().layout.window_ids();
+    let start = e.active_window_id();
+    exec(&mut e, "wincmd W");
+    let i = order.iter().position(|&id| id == start).unwrap();
+    let prev = order[(i + order.len() - 1) % order.len()];
+    assert_eq!(e.active_window_id(), prev);
+    exec(&mut e, "wincmd w");
+    assert_eq!(e.active_window_id(), start);
+}
+
 #[test]
 fn wincmd_n_creates_new_window() {
     let mut e = engine_with("hello\n");
@@ -99,6 +126,168 @@ fn wincmd_resize_with_count() {
     assert_eq!(win_count(&e), 2);
 }
 
+#[test]
+fn wincmd_hjkl_follow_window_geometry() {
+    let mut e = engine_with("hello\n");
+    // Top row split in two, one full-width window below.
+    exec(&mut e, "split");
+    exec(&mut e, "wincmd k");
+    exec(&mut e, "vsplit");
+    exec(&mut e, "wincmd h");
+    assert_eq!((active_rect(&e).0, active_rect(&e).1), (0.0, 0.0));
+    exec(&mut e, "wincmd l");
+    assert_eq!((active_rect(&e).0, active_rect(&e).1), (50.0, 0.0));
+    exec(&mut e, "wincmd j");
+    assert_eq!((active_rect(&e).1, active_rect(&e).2), (50.0, 100.0));
+    // From the bottom, `k` goes up to a window above (not the next in order).
+    exec(&mut e, "wincmd k");
+    assert_eq!(active_rect(&e).1, 0.0);
+    // Nothing above and no group: focus stays and no side-panel overflow.
+    // (The leftmost `wincmd h` above overflowed; the backend takes that.)
+    e.window_nav_overflow.take();
+    let before = e.active_window_id();
+    exec(&mut e, "wincmd k");
+    assert_eq!(e.active_window_id(), before);
+    assert_eq!(e.window_nav_overflow, None);
+}
+
+#[test]
+fn wincmd_resize_and_equalize_windows() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "vsplit");
+    exec(&mut e, "vsplit");
+    let start = active_rect(&e).2;
+    exec(&mut e, "wincmd > 2");
+    let grown = active_rect(&e).2 - start;
+    assert!(grown > 0.0);
+    exec(&mut e, "wincmd <");
+    assert!((active_rect(&e).2 - start - grown / 2.0).abs() < 1e-9);
+    // No horizontal split in the tab: height can't change.
+    exec(&mut e, "wincmd +");
+    assert_eq!(active_rect(&e).3, 100.0);
+    // `=` gives all three columns the same width.
+    exec(&mut e, "wincmd =");
+    let rects = e
+        .active_tab()
+        .layout
+        .calculate_rects(vimcode_core::core::window::WindowRect::new(
+            0.0, 0.0, 90.0, 10.0,
+        ));
+    assert_eq!(rects.len(), 3);
+    for (_, r) in rects {
+        assert!((r.width - 30.0).abs() < 1e-9);
+    }
+}
+
+/// Three side-by-side windows showing three different buffers.
+fn three_buffers_side_by_side() -> vimcode_core::Engine {
+    let mut e = engine_with("one\ntwo\nthree\n");
+    exec(&mut e, "vsplit");
+    exec(&mut e, "enew");
+    exec(&mut e, "vsplit");
+    exec(&mut e, "enew");
+    e
+}
+
+fn window_bufs(e: &vimcode_core::Engine) -> Vec<usize> {
+    e.active_tab()
+        .layout
+        .window_ids()
+        .iter()
+        .map(|id| e.windows[id].buffer_id.0)
+        .collect()
+}
+
+#[test]
+fn wincmd_x_swaps_buffer_and_view_with_next_window() {
+    let mut e = three_buffers_side_by_side();
+    let ids = e.active_tab().layout.window_ids();
+    let bufs = window_bufs(&e);
+    // Window 2's cursor travels with its buffer.
+    e.active_tab_mut().active_window = ids[1];
+    e.view_mut().cursor.line = 2;
+    exec(&mut e, "wincmd x");
+    assert_eq!(window_bufs(&e), vec![bufs[0], bufs[2], bufs[1]]);
+    assert_eq!(e.active_window_id(), ids[1], "focus stays in place");
+    assert_eq!(e.windows[&ids[2]].view.cursor.line, 2);
+
+    // The last window swaps with the one before it, not the first.
+    e.active_tab_mut().active_window = ids[2];
+    exec(&mut e, "wincmd x");
+    assert_eq!(window_bufs(&e), bufs);
+}
+
+#[test]
+fn wincmd_r_rotates_and_focus_follows_buffer() {
+    let mut e = three_buffers_side_by_side();
+    let ids = e.active_tab().layout.window_ids();
+    let bufs = window_bufs(&e);
+    e.active_tab_mut().active_window = ids[0];
+    exec(&mut e, "wincmd r");
+    assert_eq!(window_bufs(&e), vec![bufs[2], bufs[0], bufs[1]]);
+    assert_eq!(e.active_window_id(), ids[1]);
+    exec(&mut e, "wincmd R 2");
+    assert_eq!(window_bufs(&e), vec![bufs[1], bufs[2], bufs[0]]);
+    assert_eq!(e.active_window_id(), ids[2]);
+}
+
+#[test]
+fn wincmd_hjkl_upper_moves_window_to_edge() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "split");
+    exec(&mut e, "vsplit");
+    let moved = e.active_window_id();
+    exec(&mut e, "wincmd J");
+    assert_eq!(e.active_window_id(), moved);
+    assert_eq!(e.editor_groups.len(), 1, "stays in the same group");
+    assert_eq!(e.active_tab().layout.window_ids().last(), Some(&moved));
+    let (x, y, w, _) = active_rect(&e);
+    assert_eq!((x, y, w), (0.0, 50.0, 100.0));
+    exec(&mut e, "wincmd H");
+    let (x, y, _, h) = active_rect(&e);
+    assert_eq!((x, y, h), (0.0, 0.0, 100.0));
+}
+
+#[test]
+fn wincmd_o_keeps_windows_with_unsaved_changes() {
+    let mut e = three_buffers_side_by_side();
+    let ids = e.active_tab().layout.window_ids();
+    let dirty_buf = e.windows[&ids[0]].buffer_id;
+    e.buffer_manager.get_mut(dirty_buf).unwrap().dirty = true;
+    e.active_tab_mut().active_window = ids[2];
+    exec(&mut e, "wincmd o");
+    assert_eq!(e.active_tab().layout.window_ids(), vec![ids[0], ids[2]]);
+    assert_msg_contains(&e, "E445");
+    assert_eq!(e.active_window_id(), ids[2]);
+    exec(&mut e, "only!");
+    assert_eq!(win_count(&e), 1);
+    assert!(e.buffer_manager.get(dirty_buf).is_some(), "buffer kept");
+}
+
+#[test]
+fn wincmd_z_zooms_and_restores_layout() {
+    let mut e = engine_with("hello\n");
+    exec(&mut e, "split");
+    exec(&mut e, "vsplit");
+    let ids = e.active_tab().layout.window_ids();
+    let before = active_rect(&e);
+    ctrl(&mut e, 'w');
+    press(&mut e, 'z');
+    assert_eq!(win_count(&e), 1);
+    assert_eq!(active_rect(&e), (0.0, 0.0, 100.0, 100.0));
+    assert_eq!(e.windows.len(), 3, "hidden windows stay open");
+    exec(&mut e, "wincmd z");
+    assert_eq!(e.active_tab().layout.window_ids(), ids);
+    assert_eq!(active_rect(&e), before);
+
+    // CTRL-W o while zoomed closes the hidden windows too.
+    exec(&mut e, "wincmd z");
+    exec(&mut e, "wincmd o");
+    exec(&mut e, "wincmd z");
+    assert_eq!(win_count(&e), 1);
+    assert_eq!(e.windows.len(), 1);
+}
+
 #[test]
 fn wincmd_maximize() {
     let mut e = engine_with("hello\n");
@@ -144,7 +333,7 @@ fn wincmd_no_arg_shows_error() {
 #[test]
 fn wincmd_unknown_char_shows_error() {
     let mut e = engine_with("hello\n");
-    exec(&mut e, "wincmd z");
+    exec(&mut e, "wincmd y");
     assert_msg_contains(&e, "Unknown wincmd");
 }
 
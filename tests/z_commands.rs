@@ -315,6 +315,76 @@ fn test_zr_big_open_all() {
     assert!(e.view().folds.is_empty());
 }
 
+// ── zm/zr: fold level ───────────────────────────────────────────────────────
+
+const NESTED: &str =
+    "fn main() {\n    if a {\n        x();\n    }\n    if b {\n        y();\n    }\n}\n";
+
+#[test]
+fn test_zm_from_open_closes_innermost_level() {
+    let mut e = engine_with(NESTED);
+    type_chars(&mut e, "zm");
+    // Max depth is 2, so one zm leaves level 1: only the inner ifs are closed.
+    assert_eq!(e.view().fold_level, Some(1));
+    assert!(e.view().fold_at(0).is_none());
+    assert!(e.view().fold_at(1).is_some());
+    assert!(e.view().fold_at(4).is_some());
+}
+
+#[test]
+fn test_zm_twice_closes_outer_and_clamps_at_zero() {
+    let mut e = engine_with(NESTED);
+    type_chars(&mut e, "zmzmzm");
+    assert_eq!(e.view().fold_level, Some(0));
+    assert_eq!(e.view().folds.len(), 1);
+    assert!(e.view().fold_at(0).is_some());
+}
+
+#[test]
+fn test_zr_after_zm_reopens_one_level() {
+    let mut e = engine_with(NESTED);
+    type_chars(&mut e, "zM");
+    assert_eq!(e.view().fold_level, Some(0));
+    type_chars(&mut e, "zr");
+    assert_eq!(e.view().fold_level, Some(1));
+    assert!(e.view().fold_at(0).is_none());
+    assert!(e.view().fold_at(1).is_some());
+    type_chars(&mut e, "zr");
+    assert!(e.view().folds.is_empty());
+    // Clamped at the deepest level.
+    type_chars(&mut e, "zr");
+    assert_eq!(e.view().fold_level, Some(2));
+}
+
+#[test]
+fn test_zm_with_count() {
+    let mut e = engine_with(NESTED);
+    type_chars(&mut e, "2zm");
+    assert_eq!(e.view().fold_level, Some(0));
+    assert!(e.view().fold_at(0).is_some());
+}
+
+#[test]
+fn test_zm_moves_hidden_cursor_to_fold_header() {
+    let mut e = engine_with(NESTED);
+    e.view_mut().cursor.line = 5; // inside `if b`
+    type_chars(&mut e, "zm");
+    assert_eq!(e.cursor().line, 4);
+    assert!(!e.view().is_line_hidden(e.cursor().line));
+}
+
+#[test]
+fn test_zr_big_sets_max_level() {
+    let mut e = engine_with(NESTED);
+    type_chars(&mut e, "zM");
+    type_chars(&mut e, "zR");
+    assert!(e.view().folds.is_empty());
+    assert_eq!(e.view().fold_level, Some(2));
+    // zm from here closes the innermost level again.
+    type_chars(&mut e, "zm");
+    assert!(e.view().fold_at(1).is_some());
+}
+
 // ── Combined: fold + cursor movement interaction ────────────────────────────
 
 #[test]